@@ -0,0 +1,78 @@
+//! Benchmarks of insertion, transposition table lookup, traversal, and garbage
+//! collection on generated graphs. Run with `cargo bench`.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use search_graph::bench_support::{self, Workload};
+
+const SIZES: [usize; 2] = [1_000, 100_000];
+
+fn workloads(vertex_count: usize) -> Vec<(&'static str, Workload)> {
+  vec![
+    ("dag", Workload::dag(vertex_count, 4, 0.25, 1)),
+    ("scale_free", Workload::scale_free(vertex_count, 2, 1)),
+  ]
+}
+
+fn insertion(c: &mut Criterion) {
+  let mut group = c.benchmark_group("insertion");
+  for size in SIZES.iter() {
+    for (name, workload) in workloads(*size) {
+      group.throughput(Throughput::Elements(workload.edges().len() as u64));
+      group.bench_with_input(BenchmarkId::new(name, size), &workload, |b, w| {
+        b.iter(|| w.insert())
+      });
+    }
+  }
+  group.finish();
+}
+
+fn lookup(c: &mut Criterion) {
+  let mut group = c.benchmark_group("lookup");
+  for size in SIZES.iter() {
+    let workload = Workload::dag(*size, 4, 0.25, 1);
+    let graph = workload.insert();
+    for hit_rate in [0.5, 1.0].iter() {
+      let states = workload.lookups(10_000, *hit_rate, 2);
+      group.throughput(Throughput::Elements(states.len() as u64));
+      let id = BenchmarkId::new(format!("hit_rate_{}", hit_rate), size);
+      group.bench_with_input(id, &states, |b, states| {
+        b.iter(|| bench_support::lookup(&graph, states))
+      });
+    }
+  }
+  group.finish();
+}
+
+fn traversal(c: &mut Criterion) {
+  let mut group = c.benchmark_group("traversal");
+  for size in SIZES.iter() {
+    for (name, workload) in workloads(*size) {
+      let graph = workload.insert();
+      group.throughput(Throughput::Elements(graph.vertex_count() as u64));
+      group.bench_with_input(BenchmarkId::new(name, size), &graph, |b, g| {
+        b.iter(|| bench_support::traverse(g))
+      });
+    }
+  }
+  group.finish();
+}
+
+fn gc(c: &mut Criterion) {
+  let mut group = c.benchmark_group("gc");
+  for size in SIZES.iter() {
+    for (name, workload) in workloads(*size) {
+      group.throughput(Throughput::Elements(workload.vertex_count() as u64));
+      group.bench_with_input(BenchmarkId::new(name, size), &workload, |b, w| {
+        b.iter_batched(
+          || w.insert(),
+          |mut g| bench_support::collect(&mut g, &[1]),
+          BatchSize::LargeInput,
+        )
+      });
+    }
+  }
+  group.finish();
+}
+
+criterion_group!(benches, insertion, lookup, traversal, gc);
+criterion_main!(benches);
@@ -0,0 +1,1680 @@
+//! Algorithms that summarize or traverse whole graphs.
+//!
+//! The functions in this module are generic over the navigation traits in
+//! [nav_types](../nav_types/index.html), so they accept a shared borrow of a
+//! [Graph](../struct.Graph.html), a [Snapshot](../snapshot/struct.Snapshot.html)
+//! of one, or any other type that implements
+//! [INavTypes](../nav_types/trait.INavTypes.html). Most explore the part of
+//! the graph that is reachable from a set of root game states by following
+//! child edges, and ignore root game states that are not in the graph. Those
+//! that check invariants examine the whole graph.
+//!
+//! [propagate](fn.propagate.html) and
+//! [value_iteration](fn.value_iteration.html), which rewrite vertex data, take
+//! a mutable borrow of a `Graph` instead, and
+//! [merge_ranked](fn.merge_ranked.html), which combines several graphs into
+//! one, consumes them.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap};
+use std::hash::Hash;
+use std::mem;
+use std::ops::Add;
+use std::slice;
+
+use crate::base::{EdgeId, RawEdge, RawVertex, VertexId};
+use crate::nav::Expansion;
+use crate::nav_types::{IChildren, IEdge, INavTypes, IVertex};
+use crate::Graph;
+use symbol_map::indexing::Indexing;
+use symbol_map::SymbolId;
+
+/// The type of game states in graphs of type `N`.
+type Label<N> = <<N as INavTypes>::Vertex as IVertex>::Label;
+
+/// The type of edge handles in graphs of type `N`.
+type Edge<N> = <<N as INavTypes>::Vertex as IVertex>::Edge;
+
+/// The type of edge data in graphs of type `N`.
+type EdgeData<N> = <Edge<N> as IEdge>::Data;
+
+/// Data from several graphs, each paired with the weight of its graph, as
+/// combined by [merge_ranked](fn.merge_ranked.html).
+type Weighted<D> = Vec<(D, f64)>;
+
+/// Statistics about the vertices at one ply (i.e., distance from the nearest
+/// root) of a graph, as computed by
+/// [branching_profile](fn.branching_profile.html).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PlyBranching {
+  /// The number of vertices at this ply.
+  pub vertices: usize,
+  /// The number of vertices at this ply that have no child edges.
+  pub leaves: usize,
+  /// The total number of child edges of vertices at this ply.
+  pub edges: usize,
+  /// The greatest number of child edges of any vertex at this ply.
+  pub max: usize,
+}
+
+impl PlyBranching {
+  /// Returns the mean number of child edges per vertex at this ply, or `None`
+  /// if there are no vertices.
+  pub fn mean(&self) -> Option<f64> {
+    match self.vertices {
+      0 => None,
+      n => Some(self.edges as f64 / n as f64),
+    }
+  }
+
+  /// Returns the mean number of child edges per vertex at this ply, counting
+  /// only vertices that have children, or `None` if there are no such
+  /// vertices.
+  pub fn mean_expanded(&self) -> Option<f64> {
+    match self.vertices - self.leaves {
+      0 => None,
+      n => Some(self.edges as f64 / n as f64),
+    }
+  }
+}
+
+/// Returns branching statistics for each ply of the part of `graph` that is
+/// reachable from `roots`. Element `i` describes the vertices whose shortest
+/// path from a root has `i` edges, so element 0 describes the roots. Each
+/// vertex is counted once, at its shallowest ply, but all of its child edges
+/// are counted (including those that lead to vertices at shallower plies).
+///
+/// This takes time linear in the size of the reachable part of the graph.
+///
+/// ```rust
+/// # use search_graph::Graph;
+/// # use search_graph::algo::branching_profile;
+/// # fn main() {
+/// let mut g: Graph<u32, (), ()> = Graph::new();
+/// g.add_edge(0, |_| (), 1, |_| (), ());
+/// g.add_edge(0, |_| (), 2, |_| (), ());
+/// g.add_edge(1, |_| (), 3, |_| (), ());
+/// g.add_edge(1, |_| (), 4, |_| (), ());
+/// g.add_edge(1, |_| (), 5, |_| (), ());
+/// let profile = branching_profile(&g, &[0]);
+/// assert_eq!(profile.len(), 3);
+/// assert_eq!(profile[0].mean(), Some(2.0));
+/// assert_eq!(profile[1].mean(), Some(1.5));
+/// assert_eq!(profile[1].mean_expanded(), Some(3.0));
+/// assert_eq!(profile[1].max, 3);
+/// assert_eq!(profile[2].edges, 0);
+/// # }
+/// ```
+pub fn branching_profile<N: INavTypes>(graph: N, roots: &[Label<N>]) -> Vec<PlyBranching> {
+  let mut seen = vec![false; graph.vertex_count()];
+  let mut layer = Vec::new();
+  for v in roots.iter().filter_map(|state| graph.find_vertex(state)) {
+    if !seen[v.get_id()] {
+      seen[v.get_id()] = true;
+      layer.push(v);
+    }
+  }
+  let mut profile = Vec::new();
+  while !layer.is_empty() {
+    let mut ply = PlyBranching {
+      vertices: layer.len(),
+      ..Default::default()
+    };
+    let mut next = Vec::new();
+    for v in layer {
+      let children = v.get_child_list();
+      if children.is_empty() {
+        ply.leaves += 1;
+      }
+      ply.edges += children.len();
+      ply.max = ply.max.max(children.len());
+      for e in children.iter() {
+        let target = e.get_target();
+        if !seen[target.get_id()] {
+          seen[target.get_id()] = true;
+          next.push(target);
+        }
+      }
+    }
+    profile.push(ply);
+    layer = next;
+  }
+  profile
+}
+
+/// Returns `n` vertices sampled at random from the part of `graph` that is
+/// reachable from `root`, or no vertices if `root` is not in the graph. This
+/// is useful for estimating properties of graphs that are too large to scan.
+/// The same vertex may be sampled more than once.
+///
+/// Each sample is the endpoint of an independent random walk that starts at
+/// `root`. At a vertex with `c` children, the walk stops with probability
+/// `1 / (c + 1)` and otherwise moves to a child chosen uniformly at random.
+/// Sampling is therefore biased toward shallow vertices: in a tree in which
+/// every vertex has `b` children, a given vertex at depth `d` is sampled with
+/// probability `(b + 1)^-(d + 1)`.
+///
+/// `rng` must return uniformly distributed random numbers (e.g., `|| rng.next_u64()`
+/// with the `rand` crate).
+///
+/// ```rust
+/// # use search_graph::Graph;
+/// # use search_graph::algo::sample_reachable;
+/// # fn main() {
+/// let mut g: Graph<u32, (), ()> = Graph::new();
+/// g.add_edge(0, |_| (), 1, |_| (), ());
+/// g.add_edge(1, |_| (), 2, |_| (), ());
+/// g.add_edge(5, |_| (), 0, |_| (), ());
+/// let mut state = 1u64;
+/// let mut rng = || {
+///   state ^= state << 13;
+///   state ^= state >> 7;
+///   state ^= state << 17;
+///   state
+/// };
+/// let samples = sample_reachable(&g, &mut rng, &0, 100);
+/// assert_eq!(samples.len(), 100);
+/// assert!(samples.iter().all(|n| *n.get_label() < 3));
+/// # }
+/// ```
+pub fn sample_reachable<N, R>(graph: N, rng: &mut R, root: &Label<N>, n: usize) -> Vec<N::Vertex>
+where
+  N: INavTypes,
+  R: FnMut() -> u64,
+{
+  let root = match graph.find_vertex(root) {
+    Some(v) => v,
+    None => return Vec::new(),
+  };
+  let mut samples = Vec::with_capacity(n);
+  for _ in 0..n {
+    let mut v = root;
+    loop {
+      let children = v.get_child_list();
+      // Choose uniformly among stopping and each of the children.
+      let choice = ((rng() as u128 * (children.len() as u128 + 1)) >> 64) as usize;
+      if choice < children.len() {
+        v = children.get_edge(choice).get_target();
+      } else {
+        break;
+      }
+    }
+    samples.push(v);
+  }
+  samples
+}
+
+/// Returns the vertices that are reachable from `roots` in breadth-first
+/// order. Each vertex appears once. Roots come first, in the order given, and
+/// the children of each vertex are visited in order.
+///
+/// ```rust
+/// # use search_graph::Graph;
+/// # use search_graph::algo::breadth_first;
+/// # fn main() {
+/// let mut g: Graph<u32, (), ()> = Graph::new();
+/// g.add_edge(0, |_| (), 1, |_| (), ());
+/// g.add_edge(1, |_| (), 2, |_| (), ());
+/// g.add_edge(0, |_| (), 3, |_| (), ());
+/// let order: Vec<u32> = breadth_first(&g, &[0])
+///   .iter()
+///   .map(|n| *n.get_label())
+///   .collect();
+/// assert_eq!(order, vec![0, 1, 3, 2]);
+/// # }
+/// ```
+pub fn breadth_first<N: INavTypes>(graph: N, roots: &[Label<N>]) -> Vec<N::Vertex> {
+  let mut seen = vec![false; graph.vertex_count()];
+  let mut order = Vec::new();
+  for v in roots.iter().filter_map(|state| graph.find_vertex(state)) {
+    if !seen[v.get_id()] {
+      seen[v.get_id()] = true;
+      order.push(v);
+    }
+  }
+  let mut next = 0;
+  while next < order.len() {
+    for e in order[next].get_child_list().iter() {
+      let target = e.get_target();
+      if !seen[target.get_id()] {
+        seen[target.get_id()] = true;
+        order.push(target);
+      }
+    }
+    next += 1;
+  }
+  order
+}
+
+/// Returns the vertices that are reachable from `roots` in depth-first
+/// preorder. Each vertex appears once. Roots are searched in the order given,
+/// and the children of each vertex are searched in order.
+///
+/// ```rust
+/// # use search_graph::Graph;
+/// # use search_graph::algo::depth_first;
+/// # fn main() {
+/// let mut g: Graph<u32, (), ()> = Graph::new();
+/// g.add_edge(0, |_| (), 1, |_| (), ());
+/// g.add_edge(1, |_| (), 2, |_| (), ());
+/// g.add_edge(0, |_| (), 3, |_| (), ());
+/// let order: Vec<u32> = depth_first(&g, &[0])
+///   .iter()
+///   .map(|n| *n.get_label())
+///   .collect();
+/// assert_eq!(order, vec![0, 1, 2, 3]);
+/// # }
+/// ```
+pub fn depth_first<N: INavTypes>(graph: N, roots: &[Label<N>]) -> Vec<N::Vertex> {
+  let mut seen = vec![false; graph.vertex_count()];
+  let mut order = Vec::new();
+  let mut stack: Vec<N::Vertex> = roots
+    .iter()
+    .rev()
+    .filter_map(|state| graph.find_vertex(state))
+    .collect();
+  while let Some(v) = stack.pop() {
+    if seen[v.get_id()] {
+      continue;
+    }
+    seen[v.get_id()] = true;
+    order.push(v);
+    stack.extend(
+      v.get_child_list()
+        .iter()
+        .rev()
+        .map(|e| e.get_target())
+        .filter(|t| !seen[t.get_id()]),
+    );
+  }
+  order
+}
+
+/// Returns the length of the shortest path from any of `roots` to each vertex,
+/// as found by Dijkstra's algorithm. Element `i` of the result is the distance
+/// to the vertex with ID `i`, or `None` if it is not reachable. Roots are at
+/// distance `C::default()`.
+///
+/// The length of a path is the sum of `cost` over the data of its edges.
+/// Costs must not be less than `C::default()`.
+///
+/// ```rust
+/// # use search_graph::Graph;
+/// # use search_graph::algo::shortest_paths;
+/// # fn main() {
+/// let mut g: Graph<u32, (), u32> = Graph::new();
+/// g.add_edge(0, |_| (), 1, |_| (), 5);
+/// g.add_edge(0, |_| (), 2, |_| (), 1);
+/// g.add_edge(2, |_| (), 1, |_| (), 1);
+/// g.add_node(3, ());
+/// let distances = shortest_paths(&g, &[0], |cost| *cost);
+/// let distance = |state| distances[g.find_node(&state).unwrap().get_id()];
+/// assert_eq!(distance(1), Some(2));
+/// assert_eq!(distance(3), None);
+/// # }
+/// ```
+pub fn shortest_paths<N, C, F>(graph: N, roots: &[Label<N>], cost: F) -> Vec<Option<C>>
+where
+  N: INavTypes,
+  C: Copy + Ord + Default + Add<Output = C>,
+  F: FnMut(&EdgeData<N>) -> C,
+{
+  uniform_cost(&graph, roots, None, cost, |_, _| {})
+}
+
+/// Returns the vertices that can be reached from `root` by paths whose length
+/// does not exceed `budget`, each with the length of the shortest such path,
+/// in order of increasing length. `root` is first, at length `C::default()`,
+/// unless it is not in the graph, in which case nothing is returned.
+///
+/// The length of a path is the sum of `cost` over the data of its edges, as
+/// for [shortest_paths](fn.shortest_paths.html), but the search stops at the
+/// budget, so its cost depends only on the part of the graph within reach
+/// (e.g., the positions reachable within a number of plies or an amount of
+/// clock time).
+///
+/// ```rust
+/// # use search_graph::Graph;
+/// # use search_graph::algo::reachable_within;
+/// # fn main() {
+/// // Edge data is the time a move takes.
+/// let mut g: Graph<&str, (), u32> = Graph::new();
+/// g.add_edge("root", |_| (), "a", |_| (), 3);
+/// g.add_edge("root", |_| (), "b", |_| (), 1);
+/// g.add_edge("b", |_| (), "c", |_| (), 1);
+/// g.add_edge("c", |_| (), "d", |_| (), 5);
+/// let reached: Vec<(&str, u32)> = reachable_within(&g, &"root", 3, |t| *t)
+///   .into_iter()
+///   .map(|(v, t)| (*v.get_label(), t))
+///   .collect();
+/// assert_eq!(reached, vec![("root", 0), ("b", 1), ("c", 2), ("a", 3)]);
+/// # }
+/// ```
+pub fn reachable_within<N, C, F>(
+  graph: N,
+  root: &Label<N>,
+  budget: C,
+  cost: F,
+) -> Vec<(N::Vertex, C)>
+where
+  N: INavTypes,
+  C: Copy + Ord + Default + Add<Output = C>,
+  F: FnMut(&EdgeData<N>) -> C,
+{
+  let mut reached = Vec::new();
+  uniform_cost(
+    &graph,
+    std::slice::from_ref(root),
+    Some(budget),
+    cost,
+    |v, distance| reached.push((v, distance)),
+  );
+  reached
+}
+
+/// Runs Dijkstra's algorithm from `roots`, ignoring paths longer than
+/// `budget` if one is given, and calls `settle` on each vertex that is reached
+/// with its distance, in order of increasing distance. Returns the distances
+/// as for [shortest_paths](fn.shortest_paths.html).
+fn uniform_cost<N, C, F, V>(
+  graph: &N,
+  roots: &[Label<N>],
+  budget: Option<C>,
+  mut cost: F,
+  mut settle: V,
+) -> Vec<Option<C>>
+where
+  N: INavTypes,
+  C: Copy + Ord + Default + Add<Output = C>,
+  F: FnMut(&EdgeData<N>) -> C,
+  V: FnMut(N::Vertex, C),
+{
+  let mut distances = vec![None; graph.vertex_count()];
+  let mut done = vec![false; graph.vertex_count()];
+  let mut queue = BinaryHeap::new();
+  for v in roots.iter().filter_map(|state| graph.find_vertex(state)) {
+    distances[v.get_id()] = Some(C::default());
+    queue.push(Reverse((C::default(), v.get_id())));
+  }
+  while let Some(Reverse((distance, id))) = queue.pop() {
+    if done[id] {
+      continue;
+    }
+    done[id] = true;
+    let v = graph.get_vertex(id).unwrap();
+    for e in v.get_child_list().iter() {
+      let target = e.get_target().get_id();
+      let d = distance + cost(e.get_data());
+      if budget.is_some_and(|budget| d > budget) {
+        continue;
+      }
+      if !done[target] && distances[target].is_none_or(|old| d < old) {
+        distances[target] = Some(d);
+        queue.push(Reverse((d, target)));
+      }
+    }
+    settle(v, distance);
+  }
+  distances
+}
+
+/// Returns the edges of `graph` whose source and target belong to the same
+/// player, as determined by `player_of` on their game states. In a game in
+/// which players alternate, every edge should join vertices of different
+/// players, so such edges indicate a bug (e.g., in move generation). Edges are
+/// returned in order of source vertex ID, and then in child order.
+///
+/// Every vertex of the graph is examined, whether or not it is reachable from
+/// any particular root.
+///
+/// ```rust
+/// # use search_graph::Graph;
+/// # use search_graph::algo::check_alternation;
+/// # fn main() {
+/// // Game states are (player to move, move count).
+/// let mut g: Graph<(bool, u32), (), ()> = Graph::new();
+/// g.add_edge((true, 0), |_| (), (false, 1), |_| (), ());
+/// g.add_edge((false, 1), |_| (), (true, 2), |_| (), ());
+/// g.add_edge((false, 1), |_| (), (false, 2), |_| (), ());
+/// let violations = check_alternation(&g, |state| state.0);
+/// assert_eq!(violations.len(), 1);
+/// assert_eq!(*violations[0].get_target().get_label(), (false, 2));
+/// # }
+/// ```
+pub fn check_alternation<N, P, F>(graph: N, player_of: F) -> Vec<Edge<N>>
+where
+  N: INavTypes,
+  P: PartialEq,
+  F: Fn(&Label<N>) -> P,
+{
+  let mut violations = Vec::new();
+  for id in 0..graph.vertex_count() {
+    let v = graph.get_vertex(id).unwrap();
+    let children = v.get_child_list();
+    if children.is_empty() {
+      continue;
+    }
+    let player = player_of(v.get_label());
+    violations.extend(
+      children
+        .iter()
+        .filter(|e| player_of(e.get_target().get_label()) == player),
+    );
+  }
+  violations
+}
+
+/// The parts of one graph that are missing from another, as computed by
+/// [is_subgraph](fn.is_subgraph.html).
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SubgraphReport<T> {
+  /// The game states of vertices that are missing, in order of vertex ID in
+  /// the smaller graph.
+  pub missing_vertices: Vec<T>,
+  /// The source and target game states of edges that are missing, in order of
+  /// source vertex ID in the smaller graph, and then in child order.
+  pub missing_edges: Vec<(T, T)>,
+}
+
+impl<T> SubgraphReport<T> {
+  /// Returns true iff nothing is missing.
+  pub fn is_subgraph(&self) -> bool {
+    self.missing_vertices.is_empty() && self.missing_edges.is_empty()
+  }
+}
+
+/// Determines whether `small` is a subgraph of `big`, by game state: each
+/// vertex of `small` must have a vertex with the same game state in `big`, and
+/// each edge of `small` must have an edge in `big` between the vertices with
+/// the same game states. Vertex and edge data are not compared, and parallel
+/// edges in `small` are all matched by a single edge in `big`.
+///
+/// This is useful for checking that a search reproduces at least a reference
+/// tree.
+///
+/// ```rust
+/// # use search_graph::Graph;
+/// # use search_graph::algo::is_subgraph;
+/// # fn main() {
+/// let mut reference: Graph<u32, (), ()> = Graph::new();
+/// reference.add_edge(0, |_| (), 1, |_| (), ());
+/// reference.add_edge(1, |_| (), 2, |_| (), ());
+/// let mut searched: Graph<u32, (), ()> = Graph::new();
+/// searched.add_edge(0, |_| (), 1, |_| (), ());
+/// searched.add_edge(0, |_| (), 3, |_| (), ());
+/// let report = is_subgraph(&reference, &searched);
+/// assert!(!report.is_subgraph());
+/// assert_eq!(report.missing_vertices, vec![2]);
+/// assert_eq!(report.missing_edges, vec![(1, 2)]);
+/// # }
+/// ```
+pub fn is_subgraph<M, N>(small: M, big: N) -> SubgraphReport<Label<M>>
+where
+  M: INavTypes,
+  N: INavTypes,
+  N::Vertex: IVertex<Label = Label<M>>,
+  Label<M>: Clone,
+{
+  let mut report = SubgraphReport {
+    missing_vertices: Vec::new(),
+    missing_edges: Vec::new(),
+  };
+  // The ID in `big` of the vertex for each vertex of `small`, if any.
+  let mut matches: Vec<Option<usize>> = Vec::with_capacity(small.vertex_count());
+  for id in 0..small.vertex_count() {
+    let v = small.get_vertex(id).unwrap();
+    let found = big.find_vertex(v.get_label()).map(|w| w.get_id());
+    if found.is_none() {
+      report.missing_vertices.push(v.get_label().clone());
+    }
+    matches.push(found);
+  }
+  for id in 0..small.vertex_count() {
+    let v = small.get_vertex(id).unwrap();
+    let targets: Vec<usize> = match matches[id] {
+      Some(big_id) => big
+        .get_vertex(big_id)
+        .unwrap()
+        .get_child_list()
+        .iter()
+        .map(|e| e.get_target().get_id())
+        .collect(),
+      None => Vec::new(),
+    };
+    for e in v.get_child_list().iter() {
+      let target = e.get_target();
+      let found = matches[target.get_id()].is_some_and(|t| targets.contains(&t));
+      if !found {
+        report
+          .missing_edges
+          .push((v.get_label().clone(), target.get_label().clone()));
+      }
+    }
+  }
+  report
+}
+
+/// The data at a vertex of a graph produced by [coarsen](fn.coarsen.html).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Coarse<D> {
+  /// The number of vertices of the original graph that this vertex stands
+  /// for.
+  pub count: usize,
+  /// True iff this vertex stands for a collapsed subtree, rather than for the
+  /// single vertex with the same game state.
+  pub summary: bool,
+  /// The aggregate data of the vertices that this vertex stands for.
+  pub data: D,
+}
+
+/// Returns a graph of at most `max_nodes` vertices that summarizes the part of
+/// `graph` that is reachable from `roots`, for rendering searches that are too
+/// large to draw in full.
+///
+/// The reachable part of `graph` is divided into subtrees of its breadth-first
+/// spanning tree. Subtrees are expanded greedily from the roots, most
+/// important first by `score` (higher scores are more important, and ties go
+/// to the shallower vertex), for as long as the children of the vertex being
+/// expanded fit in the budget. Each subtree that is not expanded is collapsed
+/// into a single summary vertex that has the game state of the subtree's root.
+/// Every vertex of the result has [Coarse](struct.Coarse.html) data, which
+/// records the number of original vertices it stands for and the result of
+/// `aggregate` over them, in breadth-first order.
+///
+/// There is an edge in the result wherever there is an edge between the
+/// vertices that two result vertices stand for, except within a summary. Its
+/// data is the number of original edges that it stands for. If there are more
+/// roots than `max_nodes`, each root is collapsed into a summary.
+///
+/// ```rust
+/// # use search_graph::Graph;
+/// # use search_graph::algo::coarsen;
+/// # fn main() {
+/// let mut g: Graph<u32, u32, ()> = Graph::new();
+/// g.add_edge(0, |_| 1, 1, |_| 10, ());
+/// g.add_edge(0, |_| 1, 2, |_| 20, ());
+/// g.add_edge(2, |_| 20, 3, |_| 30, ());
+/// g.add_edge(2, |_| 20, 4, |_| 40, ());
+/// let coarse = coarsen(&g, &[0], 3, |v| v.get_id(), |vs| {
+///   vs.iter().map(|v| *v.get_data()).sum::<u32>()
+/// });
+/// assert_eq!(coarse.vertex_count(), 3);
+/// let summary = coarse.find_node(&2).unwrap();
+/// assert!(summary.get_data().summary);
+/// assert_eq!(summary.get_data().count, 3);
+/// assert_eq!(summary.get_data().data, 90);
+/// # }
+/// ```
+pub fn coarsen<N, K, D, F, G>(
+  graph: N,
+  roots: &[Label<N>],
+  max_nodes: usize,
+  mut score: F,
+  mut aggregate: G,
+) -> Graph<Label<N>, Coarse<D>, usize>
+where
+  N: INavTypes,
+  Label<N>: Hash + Eq + Clone,
+  K: Ord,
+  F: FnMut(&N::Vertex) -> K,
+  G: FnMut(&[N::Vertex]) -> D,
+{
+  // Breadth-first spanning tree of the reachable vertices.
+  let mut order: Vec<N::Vertex> = Vec::new();
+  let mut position: Vec<Option<usize>> = vec![None; graph.vertex_count()];
+  let mut tree_parent: Vec<Option<usize>> = Vec::new();
+  let mut tree_children: Vec<Vec<usize>> = Vec::new();
+  for v in roots.iter().filter_map(|state| graph.find_vertex(state)) {
+    if position[v.get_id()].is_none() {
+      position[v.get_id()] = Some(order.len());
+      order.push(v);
+      tree_parent.push(None);
+      tree_children.push(Vec::new());
+    }
+  }
+  let root_count = order.len();
+  let mut next = 0;
+  while next < order.len() {
+    let v = order[next];
+    for e in v.get_child_list().iter() {
+      let target = e.get_target();
+      if position[target.get_id()].is_none() {
+        position[target.get_id()] = Some(order.len());
+        tree_children[next].push(order.len());
+        order.push(target);
+        tree_parent.push(Some(next));
+        tree_children.push(Vec::new());
+      }
+    }
+    next += 1;
+  }
+
+  // Expand subtrees, most important first, while they fit.
+  let mut expanded = vec![false; order.len()];
+  let mut count = root_count;
+  let mut frontier: BinaryHeap<(K, Reverse<usize>)> = (0..root_count)
+    .map(|i| (score(&order[i]), Reverse(i)))
+    .collect();
+  while let Some((_, Reverse(i))) = frontier.pop() {
+    if count + tree_children[i].len() <= max_nodes {
+      expanded[i] = true;
+      count += tree_children[i].len();
+      for &c in tree_children[i].iter() {
+        frontier.push((score(&order[c]), Reverse(c)));
+      }
+    }
+  }
+
+  // Each reachable vertex is represented by itself if it was expanded, and
+  // otherwise by the root of the collapsed subtree that contains it.
+  let mut representative: Vec<usize> = Vec::with_capacity(order.len());
+  for (i, parent) in tree_parent.iter().enumerate() {
+    let r = match *parent {
+      Some(p) if !expanded[p] => representative[p],
+      _ => i,
+    };
+    representative.push(r);
+  }
+  let mut members: Vec<Vec<N::Vertex>> = vec![Vec::new(); order.len()];
+  for (i, v) in order.iter().enumerate() {
+    members[representative[i]].push(*v);
+  }
+
+  let mut coarse = Graph::new();
+  for (i, v) in order.iter().enumerate() {
+    if representative[i] == i {
+      let data = Coarse {
+        count: members[i].len(),
+        summary: !expanded[i],
+        data: aggregate(&members[i]),
+      };
+      coarse.add_node(v.get_label().clone(), data);
+    }
+  }
+  let mut edges: Vec<((usize, usize), usize)> = Vec::new();
+  let mut edge_positions: HashMap<(usize, usize), usize> = HashMap::new();
+  for (i, v) in order.iter().enumerate() {
+    for e in v.get_child_list().iter() {
+      let source = representative[i];
+      let target = representative[position[e.get_target().get_id()].unwrap()];
+      if source == target && !expanded[source] {
+        continue;
+      }
+      let edge_position = *edge_positions.entry((source, target)).or_insert_with(|| {
+        edges.push(((source, target), 0));
+        edges.len() - 1
+      });
+      edges[edge_position].1 += 1;
+    }
+  }
+  for ((source, target), count) in edges {
+    coarse.add_edge(
+      order[source].get_label().clone(),
+      |_| unreachable!(),
+      order[target].get_label().clone(),
+      |_| unreachable!(),
+      count,
+    );
+  }
+  coarse
+}
+
+/// The child edges of a vertex, as pairs of the data of each edge and the
+/// data of its target, in order. Passed to the function given to
+/// [propagate](fn.propagate.html) or
+/// [value_iteration](fn.value_iteration.html).
+pub struct Incoming<'a, S, A> {
+  vertices: &'a [RawVertex<S>],
+  arcs: &'a [RawEdge<A>],
+  children: slice::Iter<'a, EdgeId>,
+}
+
+impl<'a, S, A> Iterator for Incoming<'a, S, A> {
+  type Item = (&'a A, &'a S);
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let arc = &self.arcs[self.children.next()?.as_usize()];
+    Some((&arc.data, &self.vertices[arc.target.as_usize()].data))
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    self.children.size_hint()
+  }
+}
+
+impl<'a, S, A> ExactSizeIterator for Incoming<'a, S, A> {}
+
+/// Recomputes the data of the vertices for the game states in `order`, one at
+/// a time and in one pass. The new data of each vertex is the result of `f`
+/// on its current data and on the data of its child edges and their targets.
+/// Game states that are not in the graph are ignored.
+///
+/// Each vertex sees the data of its children as of when it is visited, so
+/// values are propagated from the leaves to the roots of an acyclic graph when
+/// `order` is a reverse topological order (e.g., the reverse of a
+/// [breadth_first](fn.breadth_first.html) order of a tree). This suits
+/// recomputing minimax or other backed-up values after a batch of expansions.
+///
+/// ```rust
+/// # use search_graph::Graph;
+/// # use search_graph::algo::propagate;
+/// # fn main() {
+/// let mut g: Graph<&str, u32, u32> = Graph::new();
+/// g.add_edge("root", |_| 0, "a", |_| 0, 1);
+/// g.add_edge("root", |_| 0, "b", |_| 3, 2);
+/// g.add_edge("a", |_| 0, "c", |_| 10, 4);
+/// // Each vertex's value is the greatest of its children's values plus the
+/// // weights of the edges to them.
+/// propagate(&mut g, &["a", "root"], |data, incoming| {
+///   incoming.map(|(weight, child)| weight + child).max().unwrap_or(*data)
+/// });
+/// assert_eq!(*g.find_node(&"a").unwrap().get_data(), 14);
+/// assert_eq!(*g.find_node(&"root").unwrap().get_data(), 15);
+/// # }
+/// ```
+pub fn propagate<T, S, A, F>(graph: &mut Graph<T, S, A>, order: &[T], mut f: F)
+where
+  T: Hash + Eq + Clone,
+  F: FnMut(&S, Incoming<S, A>) -> S,
+{
+  for state in order {
+    let id = match graph.lookup(state) {
+      Some(id) => id,
+      None => continue,
+    };
+    let vertex = graph.get_vertex(id);
+    let incoming = Incoming {
+      vertices: &graph.vertices,
+      arcs: &graph.arcs,
+      children: vertex.children.iter(),
+    };
+    let data = f(&vertex.data, incoming);
+    *graph.get_data_mut(id) = data;
+  }
+}
+
+/// The outcome of [value_iteration](fn.value_iteration.html).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Convergence {
+  /// The number of passes over the graph that were made.
+  pub iterations: usize,
+  /// The greatest change reported for any vertex in the last pass, or
+  /// infinity if no pass was made.
+  pub residual: f64,
+  /// True iff the last pass changed no vertex by more than the tolerance.
+  pub converged: bool,
+}
+
+/// Recomputes the data of every vertex of `graph` repeatedly until it stops
+/// changing, which suits backing up values in graphs with cycles (e.g., games
+/// in which positions repeat), where one pass of
+/// [propagate](fn.propagate.html) in reverse topological order is not enough.
+///
+/// Each pass visits every vertex once, in depth-first postorder, so that the
+/// children of a vertex are visited before it except along edges that close a
+/// cycle. `update` is called with the current data of a vertex and the data
+/// of its child edges and their targets, and returns its new data along with
+/// the magnitude of the change (e.g., the absolute difference between old and
+/// new values). Updates take effect immediately, so later vertices in a pass
+/// see them. Passes stop once the greatest change in a pass is at most
+/// `epsilon`, or after `max_iters` passes.
+///
+/// An acyclic graph converges in at most two passes: one to compute values
+/// and one to confirm them.
+///
+/// ```rust
+/// # use search_graph::Graph;
+/// # use search_graph::algo::value_iteration;
+/// # fn main() {
+/// // Positions "a" and "b" can repeat forever; only "b" can reach the win.
+/// let mut g: Graph<&str, f64, ()> = Graph::new();
+/// g.add_edge("a", |_| 0.0, "b", |_| 0.0, ());
+/// g.add_edge("b", |_| 0.0, "a", |_| 0.0, ());
+/// g.add_edge("b", |_| 0.0, "win", |_| 1.0, ());
+/// let result = value_iteration(
+///   &mut g,
+///   |value, children| {
+///     let best = children.map(|(_, child)| 0.9 * child).fold(*value, f64::max);
+///     (best, (best - value).abs())
+///   },
+///   1e-9,
+///   100,
+/// );
+/// assert!(result.converged);
+/// assert!((g.find_node(&"a").unwrap().get_data() - 0.81).abs() < 1e-9);
+/// # }
+/// ```
+pub fn value_iteration<T, S, A, F>(
+  graph: &mut Graph<T, S, A>,
+  mut update: F,
+  epsilon: f64,
+  max_iters: usize,
+) -> Convergence
+where
+  T: Hash + Eq + Clone,
+  F: FnMut(&S, Incoming<S, A>) -> (S, f64),
+{
+  let order = postorder(graph);
+  let mut result = Convergence {
+    iterations: 0,
+    residual: f64::INFINITY,
+    converged: false,
+  };
+  while result.iterations < max_iters {
+    let mut residual: f64 = 0.0;
+    for &id in order.iter() {
+      let vertex = graph.get_vertex(id);
+      let incoming = Incoming {
+        vertices: &graph.vertices,
+        arcs: &graph.arcs,
+        children: vertex.children.iter(),
+      };
+      let (data, change) = update(&vertex.data, incoming);
+      *graph.get_data_mut(id) = data;
+      residual = residual.max(change);
+    }
+    result.iterations += 1;
+    result.residual = residual;
+    if residual <= epsilon {
+      result.converged = true;
+      break;
+    }
+  }
+  result
+}
+
+/// Returns every vertex of `graph` in depth-first postorder, starting
+/// traversals from vertices in order of ID.
+fn postorder<T: Hash + Eq + Clone, S, A>(graph: &Graph<T, S, A>) -> Vec<VertexId> {
+  let mut order = Vec::with_capacity(graph.vertices.len());
+  let mut visited = vec![false; graph.vertices.len()];
+  // Vertices whose children are being traversed, with the index of the next
+  // child to visit.
+  let mut stack: Vec<(VertexId, usize)> = Vec::new();
+  for start in 0..graph.vertices.len() {
+    if visited[start] {
+      continue;
+    }
+    visited[start] = true;
+    stack.push((VertexId(start), 0));
+    while let Some((id, next)) = stack.last_mut() {
+      let children = &graph.get_vertex(*id).children;
+      if *next == children.len() {
+        order.push(*id);
+        stack.pop();
+        continue;
+      }
+      let target = graph.get_arc(children[*next]).target;
+      *next += 1;
+      if !visited[target.as_usize()] {
+        visited[target.as_usize()] = true;
+        stack.push((target, 0));
+      }
+    }
+  }
+  order
+}
+
+/// Merges several graphs (e.g., built by different engines, or in different
+/// epochs of a search) into one consensus graph, consuming them. Each graph
+/// is given with a weight that ranks it among the others.
+///
+/// The merged graph has a vertex for each game state that is in any of the
+/// graphs. Its data is returned by `combine_vertex`, which is called with the
+/// game state and the data of the vertex in each graph that has one, paired
+/// with that graph's weight. An edge is likewise combined by `combine_edge`
+/// from the edges with the same source and target game states, where the
+/// `k`th of several parallel edges in each graph is combined with the `k`th
+/// in the others. Data is passed in order of decreasing weight, and in the
+/// order of `graphs` among equal weights, so the functions may, e.g.,
+/// average it or keep the first value. They are called even if only one
+/// graph has the vertex or edge.
+///
+/// Vertices and child edges are ordered by first appearance, scanning
+/// `graphs` in order. Each vertex is as expanded as it is in the most
+/// expanded of the graphs, and aliases that do not conflict with a game state
+/// are kept. Data for child edges whose targets have not been determined is
+/// dropped. The merged graph is configured like the first graph.
+///
+/// ```rust
+/// # use search_graph::Graph;
+/// # use search_graph::algo::merge_ranked;
+/// # fn main() {
+/// let mut a: Graph<&str, f64, u32> = Graph::new();
+/// a.add_edge("root", |_| 1.0, "x", |_| 0.0, 1);
+/// let mut b: Graph<&str, f64, u32> = Graph::new();
+/// b.add_edge("root", |_| 0.0, "y", |_| 1.0, 2);
+/// let merged = merge_ranked(
+///   vec![(a, 3.0), (b, 1.0)],
+///   |_, data| {
+///     let total: f64 = data.iter().map(|(_, w)| w).sum();
+///     data.iter().map(|(d, w)| d * w).sum::<f64>() / total
+///   },
+///   |_, _, data| data[0].0,
+/// );
+/// assert_eq!(merged.vertex_count(), 3);
+/// assert_eq!(*merged.find_node(&"root").unwrap().get_data(), 0.75);
+/// assert_eq!(merged.find_node(&"root").unwrap().get_child_list().len(), 2);
+/// # }
+/// ```
+pub fn merge_ranked<T, S, A, F, G>(
+  graphs: Vec<(Graph<T, S, A>, f64)>,
+  mut combine_vertex: F,
+  mut combine_edge: G,
+) -> Graph<T, S, A>
+where
+  T: Hash + Eq + Clone,
+  F: FnMut(&T, Weighted<S>) -> S,
+  G: FnMut(&T, &T, Weighted<A>) -> A,
+{
+  let mut merged = match graphs.first() {
+    Some((graph, _)) => graph.new_like(),
+    None => Graph::new(),
+  };
+  // Game states in order of first appearance, and their indices.
+  let mut states: Vec<T> = Vec::new();
+  let mut state_index: HashMap<T, usize> = HashMap::new();
+  // By merged vertex index.
+  let mut vertex_data: Vec<Weighted<S>> = Vec::new();
+  let mut expansions: Vec<Expansion> = Vec::new();
+  let mut children: Vec<Vec<usize>> = Vec::new();
+  // Source index, target index, and data of each merged edge.
+  let mut edges: Vec<(usize, usize, Weighted<A>)> = Vec::new();
+  // Indexed by source, target, and rank among parallel edges.
+  let mut edge_index: HashMap<(usize, usize, usize), usize> = HashMap::new();
+  let mut aliases: Vec<(T, usize)> = Vec::new();
+
+  for (mut graph, weight) in graphs {
+    let mut labels: Vec<Option<&T>> = vec![None; graph.vertices.len()];
+    for symbol in graph.state_ids.table() {
+      labels[symbol.id().as_usize()] = Some(symbol.data());
+    }
+    let ids: Vec<usize> = labels
+      .into_iter()
+      .map(|label| {
+        let label = label.unwrap();
+        match state_index.get(label) {
+          Some(i) => *i,
+          None => {
+            state_index.insert(label.clone(), states.len());
+            states.push(label.clone());
+            vertex_data.push(Vec::new());
+            expansions.push(Expansion::Unexpanded);
+            children.push(Vec::new());
+            states.len() - 1
+          }
+        }
+      })
+      .collect();
+    let (targets, mut arc_data): (Vec<VertexId>, Vec<Option<A>>) = mem::take(&mut graph.arcs)
+      .into_iter()
+      .map(|arc| (arc.target, Some(arc.data)))
+      .unzip();
+    for (i, vertex) in mem::take(&mut graph.vertices).into_iter().enumerate() {
+      let source = ids[i];
+      vertex_data[source].push((vertex.data, weight));
+      expansions[source] = expansions[source].max(vertex.expansion);
+      let mut parallel: HashMap<usize, usize> = HashMap::new();
+      for child in vertex.children {
+        let target = ids[targets[child.as_usize()].as_usize()];
+        let rank = parallel.entry(target).or_insert(0);
+        let edge = *edge_index
+          .entry((source, target, *rank))
+          .or_insert_with(|| {
+            edges.push((source, target, Vec::new()));
+            children[source].push(edges.len() - 1);
+            edges.len() - 1
+          });
+        *rank += 1;
+        let data = arc_data[child.as_usize()].take().unwrap();
+        edges[edge].2.push((data, weight));
+      }
+    }
+    for (alias, id) in graph.aliases.drain() {
+      aliases.push((alias, ids[id.as_usize()]));
+    }
+  }
+
+  fn by_weight<D>(data: &mut [(D, f64)]) {
+    data.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+  }
+  for ((state, mut data), expansion) in states.iter().zip(vertex_data).zip(expansions) {
+    by_weight(&mut data);
+    let data = combine_vertex(state, data);
+    merged.state_ids.get_or_insert(state.clone());
+    merged.add_raw_vertex(data).expansion = expansion;
+  }
+  let mut edges: Vec<Option<_>> = edges.into_iter().map(Some).collect();
+  for edge_ids in children.iter() {
+    for edge in edge_ids.iter() {
+      let (source, target, mut data) = edges[*edge].take().unwrap();
+      by_weight(&mut data);
+      let data = combine_edge(&states[source], &states[target], data);
+      merged.add_raw_edge(data, VertexId(source), VertexId(target));
+    }
+  }
+  for (alias, id) in aliases {
+    if merged.lookup(&alias).is_none() {
+      merged.aliases.insert(alias, VertexId(id));
+    }
+  }
+  merged
+}
+
+#[cfg(test)]
+mod test {
+  use super::{
+    branching_profile, breadth_first, check_alternation, coarsen, depth_first, is_subgraph,
+    merge_ranked, propagate, reachable_within, sample_reachable, shortest_paths, value_iteration,
+    Coarse, PlyBranching,
+  };
+  use crate::nav_types::{IChildren, IEdge, INavTypes, IVertex};
+
+  type Graph = crate::Graph<&'static str, &'static str, &'static str>;
+
+  /// A graph backend that is defined outside of the crate's own types, stored
+  /// as adjacency lists of (target, cost) pairs.
+  struct Adjacency {
+    labels: Vec<&'static str>,
+    children: Vec<Vec<(usize, u32)>>,
+  }
+
+  impl Adjacency {
+    fn new(edges: &[(&'static str, &'static str, u32)]) -> Self {
+      let mut adjacency = Adjacency {
+        labels: Vec::new(),
+        children: Vec::new(),
+      };
+      for (source, target, cost) in edges {
+        let source = adjacency.intern(source);
+        let target = adjacency.intern(target);
+        adjacency.children[source].push((target, *cost));
+      }
+      adjacency
+    }
+
+    fn intern(&mut self, label: &'static str) -> usize {
+      match self.labels.iter().position(|l| *l == label) {
+        Some(id) => id,
+        None => {
+          self.labels.push(label);
+          self.children.push(Vec::new());
+          self.labels.len() - 1
+        }
+      }
+    }
+  }
+
+  #[derive(Clone, Copy)]
+  struct AdjacencyVertex<'g> {
+    graph: &'g Adjacency,
+    id: usize,
+  }
+
+  #[derive(Clone, Copy)]
+  struct AdjacencyEdge<'g> {
+    graph: &'g Adjacency,
+    source: usize,
+    index: usize,
+  }
+
+  impl<'g> INavTypes for &'g Adjacency {
+    type Vertex = AdjacencyVertex<'g>;
+
+    fn vertex_count(&self) -> usize {
+      self.labels.len()
+    }
+
+    fn find_vertex(&self, label: &&'static str) -> Option<AdjacencyVertex<'g>> {
+      let graph = *self;
+      graph
+        .labels
+        .iter()
+        .position(|l| l == label)
+        .map(|id| AdjacencyVertex { graph, id })
+    }
+
+    fn get_vertex(&self, id: usize) -> Option<AdjacencyVertex<'g>> {
+      if id < self.labels.len() {
+        Some(AdjacencyVertex { graph: self, id })
+      } else {
+        None
+      }
+    }
+  }
+
+  impl<'g> IVertex for AdjacencyVertex<'g> {
+    type Label = &'static str;
+    type Data = ();
+    type Edge = AdjacencyEdge<'g>;
+    type Children = AdjacencyVertex<'g>;
+
+    fn get_id(&self) -> usize {
+      self.id
+    }
+
+    fn get_label(&self) -> &&'static str {
+      &self.graph.labels[self.id]
+    }
+
+    fn get_data(&self) -> &() {
+      &()
+    }
+
+    fn get_child_list(&self) -> AdjacencyVertex<'g> {
+      *self
+    }
+  }
+
+  impl<'g> IChildren for AdjacencyVertex<'g> {
+    type Edge = AdjacencyEdge<'g>;
+
+    fn len(&self) -> usize {
+      self.graph.children[self.id].len()
+    }
+
+    fn get_edge(&self, i: usize) -> AdjacencyEdge<'g> {
+      AdjacencyEdge {
+        graph: self.graph,
+        source: self.id,
+        index: i,
+      }
+    }
+  }
+
+  impl<'g> IEdge for AdjacencyEdge<'g> {
+    type Data = u32;
+    type Vertex = AdjacencyVertex<'g>;
+
+    fn get_id(&self) -> usize {
+      self.graph.children[..self.source]
+        .iter()
+        .map(Vec::len)
+        .sum::<usize>()
+        + self.index
+    }
+
+    fn get_data(&self) -> &u32 {
+      &self.graph.children[self.source][self.index].1
+    }
+
+    fn get_source(&self) -> AdjacencyVertex<'g> {
+      AdjacencyVertex {
+        graph: self.graph,
+        id: self.source,
+      }
+    }
+
+    fn get_target(&self) -> AdjacencyVertex<'g> {
+      AdjacencyVertex {
+        graph: self.graph,
+        id: self.graph.children[self.source][self.index].0,
+      }
+    }
+  }
+
+  const EDGES: [(&str, &str, u32); 7] = [
+    ("root", "a", 4),
+    ("root", "b", 1),
+    ("b", "a", 1),
+    ("a", "c", 1),
+    ("b", "d", 7),
+    ("c", "d", 1),
+    ("other", "root", 1),
+  ];
+
+  fn labels<V: IVertex<Label = &'static str>>(vertices: Vec<V>) -> Vec<&'static str> {
+    vertices.iter().map(|v| *v.get_label()).collect()
+  }
+
+  /// Returns the distances computed by `shortest_paths`, keyed by label.
+  fn distances<N: INavTypes + Copy>(graph: N) -> Vec<(&'static str, Option<u32>)>
+  where
+    N::Vertex: IVertex<Label = &'static str>,
+    <N::Vertex as IVertex>::Edge: IEdge<Data = u32>,
+  {
+    let distances = shortest_paths(graph, &["root"], |cost| *cost);
+    let mut distances: Vec<_> = (0..graph.vertex_count())
+      .map(|id| (*graph.get_vertex(id).unwrap().get_label(), distances[id]))
+      .collect();
+    distances.sort();
+    distances
+  }
+
+  fn edge_graph() -> crate::Graph<&'static str, (), u32> {
+    let mut g = crate::Graph::new();
+    for (source, target, cost) in EDGES.iter() {
+      g.add_edge(*source, |_| (), *target, |_| (), *cost);
+    }
+    g
+  }
+
+  #[test]
+  fn branching_profile_ok() {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| "", "a", |_| "", "");
+    g.add_edge("root", |_| "", "b", |_| "", "");
+    g.add_edge("a", |_| "", "b", |_| "", "");
+    g.add_edge("a", |_| "", "root", |_| "", "");
+    g.add_edge("b", |_| "", "c", |_| "", "");
+    g.add_edge("other", |_| "", "root", |_| "", "");
+
+    let profile = branching_profile(&g, &["root", "missing"]);
+    assert_eq!(
+      vec![
+        PlyBranching {
+          vertices: 1,
+          leaves: 0,
+          edges: 2,
+          max: 2,
+        },
+        PlyBranching {
+          vertices: 2,
+          leaves: 0,
+          edges: 3,
+          max: 2,
+        },
+        PlyBranching {
+          vertices: 1,
+          leaves: 1,
+          edges: 0,
+          max: 0,
+        },
+      ],
+      profile
+    );
+    assert_eq!(None, profile[2].mean_expanded());
+
+    let profile = branching_profile(&g, &["b", "root", "b"]);
+    assert_eq!(2, profile[0].vertices);
+    assert_eq!(2, profile[1].vertices);
+    assert!(branching_profile(&g, &["missing"]).is_empty());
+  }
+
+  #[test]
+  fn sample_reachable_ok() {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| "", "a", |_| "", "");
+    g.add_edge("root", |_| "", "b", |_| "", "");
+    g.add_edge("a", |_| "", "root", |_| "", "");
+    g.add_edge("other", |_| "", "root", |_| "", "");
+    let mut state = 0x2545f4914f6cdd1du64;
+    let mut rng = || {
+      state ^= state << 13;
+      state ^= state >> 7;
+      state ^= state << 17;
+      state
+    };
+    let samples = sample_reachable(&g, &mut rng, &"root", 3000);
+    let count = |label| samples.iter().filter(|n| *n.get_label() == label).count();
+    assert_eq!(0, count("other"));
+    // Walks end at "root" and "b" with probability 2/5 each, and at "a" with
+    // probability 1/5.
+    assert!(count("root") > 1000);
+    assert!(count("b") > 1000);
+    assert!(count("a") > 450);
+    assert_eq!(3000, count("root") + count("a") + count("b"));
+    assert!(sample_reachable(&g, &mut rng, &"missing", 10).is_empty());
+    assert!(sample_reachable(&g, &mut rng, &"root", 0).is_empty());
+  }
+
+  #[test]
+  fn check_alternation_ok() {
+    let mut g = edge_graph();
+    let adjacency = Adjacency::new(&EDGES);
+    // "root", "c", and "other" belong to one player.
+    let player_of = |state: &&str| ["root", "c", "other"].contains(state);
+    let expected = vec![("b", "a"), ("b", "d"), ("other", "root")];
+    let from_graph: Vec<_> = check_alternation(&g, player_of)
+      .iter()
+      .map(|e| (*e.get_source().get_label(), *e.get_target().get_label()))
+      .collect();
+    assert_eq!(expected, from_graph);
+    let from_adjacency: Vec<_> = check_alternation(&adjacency, player_of)
+      .iter()
+      .map(|e| (*e.get_source().get_label(), *e.get_target().get_label()))
+      .collect();
+    assert_eq!(expected, from_adjacency);
+    assert_eq!(EDGES.len(), check_alternation(&g.snapshot(), |_| 0).len());
+    assert_eq!(4, check_alternation(&g, |state| state.len()).len());
+  }
+
+  #[test]
+  fn is_subgraph_ok() {
+    let mut g = edge_graph();
+    let adjacency = Adjacency::new(&EDGES);
+    assert!(is_subgraph(&g, &adjacency).is_subgraph());
+    assert!(is_subgraph(&adjacency, &g.snapshot()).is_subgraph());
+
+    let small = Adjacency::new(&[
+      ("root", "a", 0),
+      ("root", "a", 0),
+      ("a", "root", 0),
+      ("a", "e", 0),
+      ("e", "f", 0),
+    ]);
+    let report = is_subgraph(&small, &g);
+    assert_eq!(vec!["e", "f"], report.missing_vertices);
+    assert_eq!(
+      vec![("a", "root"), ("a", "e"), ("e", "f")],
+      report.missing_edges
+    );
+    assert!(!report.is_subgraph());
+    let report = is_subgraph(&g, &small);
+    assert_eq!(vec!["b", "c", "d", "other"], report.missing_vertices);
+    assert_eq!(6, report.missing_edges.len());
+  }
+
+  #[test]
+  fn breadth_first_ok() {
+    let mut g = edge_graph();
+    let adjacency = Adjacency::new(&EDGES);
+    let expected = vec!["root", "a", "b", "c", "d"];
+    assert_eq!(expected, labels(breadth_first(&g, &["root", "missing"])));
+    assert_eq!(expected, labels(breadth_first(&g.snapshot(), &["root"])));
+    assert_eq!(expected, labels(breadth_first(&adjacency, &["root"])));
+    assert_eq!(
+      vec!["c", "b", "d", "a"],
+      labels(breadth_first(&adjacency, &["c", "b", "c"]))
+    );
+    assert!(breadth_first(&adjacency, &["missing"]).is_empty());
+  }
+
+  #[test]
+  fn depth_first_ok() {
+    let mut g = edge_graph();
+    let adjacency = Adjacency::new(&EDGES);
+    let expected = vec!["root", "a", "c", "d", "b"];
+    assert_eq!(expected, labels(depth_first(&g, &["root"])));
+    assert_eq!(expected, labels(depth_first(&g.snapshot(), &["root"])));
+    assert_eq!(expected, labels(depth_first(&adjacency, &["root"])));
+    assert_eq!(
+      vec!["b", "a", "c", "d", "other", "root"],
+      labels(depth_first(&adjacency, &["b", "other"]))
+    );
+  }
+
+  #[test]
+  fn shortest_paths_ok() {
+    let mut g = edge_graph();
+    let adjacency = Adjacency::new(&EDGES);
+    let expected = vec![
+      ("a", Some(2)),
+      ("b", Some(1)),
+      ("c", Some(3)),
+      ("d", Some(4)),
+      ("other", None),
+      ("root", Some(0)),
+    ];
+    assert_eq!(expected, distances(&g));
+    assert_eq!(expected, distances(&g.snapshot()));
+    assert_eq!(expected, distances(&adjacency));
+  }
+
+  #[test]
+  fn reachable_within_ok() {
+    let mut g = edge_graph();
+    let reached = |budget| {
+      reachable_within(&g, &"root", budget, |cost| *cost)
+        .into_iter()
+        .map(|(v, d)| (*v.get_label(), d))
+        .collect::<Vec<_>>()
+    };
+    assert_eq!(vec![("root", 0)], reached(0));
+    assert_eq!(vec![("root", 0), ("b", 1), ("a", 2)], reached(2));
+    assert_eq!(
+      vec![("root", 0), ("b", 1), ("a", 2), ("c", 3), ("d", 4)],
+      reached(100)
+    );
+    assert!(reachable_within(&g, &"missing", 100, |cost| *cost).is_empty());
+    let snapshot = g.snapshot();
+    assert_eq!(
+      4,
+      reachable_within(&snapshot, &"root", 3, |cost| *cost).len()
+    );
+  }
+
+  #[test]
+  fn custom_backend_ok() {
+    let g = edge_graph();
+    let adjacency = Adjacency::new(&EDGES);
+    assert_eq!(
+      branching_profile(&g, &["root"]),
+      branching_profile(&adjacency, &["root"])
+    );
+    let mut state = 0x2545f4914f6cdd1du64;
+    let mut rng = || {
+      state ^= state << 13;
+      state ^= state >> 7;
+      state ^= state << 17;
+      state
+    };
+    let samples = sample_reachable(&adjacency, &mut rng, &"b", 100);
+    assert_eq!(100, samples.len());
+    assert!(samples.iter().all(|v| *v.get_label() != "root"));
+    let b = (&adjacency).find_vertex(&"b").unwrap();
+    let edges: Vec<usize> = b.get_child_list().iter().map(|e| e.get_id()).collect();
+    assert_eq!(vec![3, 4], edges);
+  }
+
+  #[test]
+  fn coarsen_ok() {
+    let mut g = Graph::new();
+    for (source, target) in [
+      ("root", "A"),
+      ("root", "B"),
+      ("B", "C"),
+      ("B", "D"),
+      ("C", "A"),
+      ("D", "E"),
+    ]
+    .iter()
+    {
+      g.add_edge(*source, |_| "", *target, |_| "", "");
+    }
+    let coarse = |max_nodes| {
+      coarsen(
+        &g,
+        &["root"],
+        max_nodes,
+        |v| *v.get_label(),
+        |vs| vs.iter().map(|v| *v.get_label()).collect::<Vec<_>>(),
+      )
+    };
+    let edges = |c: &crate::Graph<&'static str, Coarse<Vec<&'static str>>, usize>| {
+      let mut edges = Vec::new();
+      for state in c.states() {
+        for e in c.find_node(state).unwrap().get_child_list().iter() {
+          edges.push((*state, *e.get_target().get_label(), *e.get_data()));
+        }
+      }
+      edges
+    };
+
+    let c = coarse(4);
+    assert_eq!(
+      vec!["root", "A", "B"],
+      c.states().cloned().collect::<Vec<_>>()
+    );
+    assert_eq!(
+      Coarse {
+        count: 4,
+        summary: true,
+        data: vec!["B", "C", "D", "E"],
+      },
+      *c.find_node(&"B").unwrap().get_data()
+    );
+    assert!(!c.find_node(&"A").unwrap().get_data().summary);
+    assert_eq!(
+      vec![("root", "A", 1), ("root", "B", 1), ("B", "A", 1)],
+      edges(&c)
+    );
+
+    let c = coarse(5);
+    assert_eq!(5, c.vertex_count());
+    assert_eq!(2, c.find_node(&"D").unwrap().get_data().count);
+    assert!(!c.find_node(&"C").unwrap().get_data().summary);
+    assert_eq!(5, c.edge_count());
+
+    let c = coarse(0);
+    assert_eq!(1, c.vertex_count());
+    assert_eq!(6, c.find_node(&"root").unwrap().get_data().count);
+    assert_eq!(0, c.edge_count());
+
+    let c = coarsen(&g, &["missing"], 10, |v| v.get_id(), |_| ());
+    assert_eq!(0, c.vertex_count());
+  }
+
+  #[test]
+  fn propagate_ok() {
+    let mut g = crate::Graph::<&str, u32, u32>::new();
+    g.add_edge("root", |_| 0, "a", |_| 0, 1);
+    g.add_edge("root", |_| 0, "b", |_| 0, 5);
+    g.add_edge("a", |_| 0, "c", |_| 10, 2);
+    g.add_edge("b", |_| 0, "c", |_| 10, 1);
+    let mut order: Vec<&str> = breadth_first(&g, &["root"])
+      .iter()
+      .map(|n| *n.get_label())
+      .collect();
+    order.reverse();
+    order.push("missing");
+    let mut visited = Vec::new();
+    propagate(&mut g, &order, |data, incoming| {
+      visited.push(incoming.len());
+      incoming
+        .map(|(weight, child)| weight + child)
+        .max()
+        .unwrap_or(*data)
+    });
+    assert_eq!(vec![0, 1, 1, 2], visited);
+    let data = |g: &crate::Graph<&str, u32, u32>, state| *g.find_node(&state).unwrap().get_data();
+    assert_eq!(12, data(&g, "a"));
+    assert_eq!(11, data(&g, "b"));
+    assert_eq!(16, data(&g, "root"));
+    assert_eq!(10, data(&g, "c"));
+  }
+
+  #[test]
+  fn value_iteration_ok() {
+    // A cycle between "a" and "b", with exits to "draw" and "loss".
+    let mut g = crate::Graph::<&str, f64, f64>::new();
+    g.add_edge("root", |_| 0.0, "a", |_| 0.0, 1.0);
+    g.add_edge("a", |_| 0.0, "b", |_| 0.0, 0.5);
+    g.add_edge("b", |_| 0.0, "a", |_| 0.0, 0.5);
+    g.add_edge("a", |_| 0.0, "draw", |_| 0.2, 1.0);
+    g.add_edge("b", |_| 0.0, "loss", |_| -1.0, 1.0);
+    let mut updates = 0;
+    let result = value_iteration(
+      &mut g,
+      |value, children| {
+        updates += 1;
+        if children.len() == 0 {
+          return (*value, 0.0);
+        }
+        let best = children
+          .map(|(weight, child)| weight * child)
+          .fold(f64::NEG_INFINITY, f64::max);
+        (best, (best - value).abs())
+      },
+      1e-12,
+      100,
+    );
+    assert!(result.converged);
+    assert!(result.iterations > 2);
+    assert!(result.residual <= 1e-12);
+    assert_eq!(5 * result.iterations, updates);
+    let data = |state| *g.find_node(&state).unwrap().get_data();
+    assert!((data("a") - 0.2).abs() < 1e-9);
+    assert!((data("b") - 0.1).abs() < 1e-9);
+    assert!((data("root") - 0.2).abs() < 1e-9);
+  }
+
+  #[test]
+  fn value_iteration_acyclic_ok() {
+    let mut g = crate::Graph::<&str, u32, u32>::new();
+    g.add_edge("root", |_| 0, "a", |_| 0, 1);
+    g.add_edge("a", |_| 0, "b", |_| 0, 2);
+    g.add_edge("b", |_| 0, "c", |_| 4, 3);
+    let result = value_iteration(
+      &mut g,
+      |value, children| {
+        let sum = children.map(|(w, child)| w + child).max().unwrap_or(*value);
+        (sum, f64::from(sum) - f64::from(*value))
+      },
+      0.0,
+      10,
+    );
+    assert_eq!(2, result.iterations);
+    assert!(result.converged);
+    assert_eq!(10, *g.find_node(&"root").unwrap().get_data());
+  }
+
+  #[test]
+  fn value_iteration_err() {
+    let mut g = crate::Graph::<&str, u32, ()>::new();
+    g.add_edge("a", |_| 0, "b", |_| 0, ());
+    g.add_edge("b", |_| 0, "a", |_| 0, ());
+    let result = value_iteration(&mut g, |value, _| (value + 1, 1.0), 0.5, 3);
+    assert_eq!(3, result.iterations);
+    assert_eq!(1.0, result.residual);
+    assert!(!result.converged);
+    assert_eq!(3, *g.find_node(&"a").unwrap().get_data());
+    let result = value_iteration(&mut g, |value, _| (*value, 0.0), 0.5, 0);
+    assert_eq!(f64::INFINITY, result.residual);
+    assert!(!result.converged);
+  }
+
+  #[test]
+  fn merge_ranked_ok() {
+    type Graph = crate::Graph<&'static str, u32, u32>;
+    let mut a = Graph::new();
+    a.add_edge("root", |_| 1, "x", |_| 1, 10);
+    a.add_edge("root", |_| 1, "x", |_| 1, 11);
+    let mut b = Graph::new();
+    b.add_edge("root", |_| 2, "y", |_| 2, 20);
+    b.add_edge("root", |_| 2, "x", |_| 2, 21);
+    b.alias_state("alias", &"y").unwrap();
+    b.alias_state("x2", &"x").unwrap();
+    let mut c = Graph::new();
+    c.add_node("x2", 3);
+    c.add_edge("y", |_| 3, "root", |_| 3, 30);
+
+    let mut calls = Vec::new();
+    let merged = merge_ranked(
+      vec![(a, 1.0), (b, 2.0), (c, 1.0)],
+      |state, data| {
+        calls.push((
+          *state,
+          data.iter().map(|(d, w)| (*d, *w)).collect::<Vec<_>>(),
+        ));
+        data[0].0
+      },
+      |_, _, data| data.iter().map(|(d, _)| d).sum(),
+    );
+    assert_eq!(
+      vec![
+        ("root", vec![(2, 2.0), (1, 1.0), (3, 1.0)]),
+        ("x", vec![(2, 2.0), (1, 1.0)]),
+        ("y", vec![(2, 2.0), (3, 1.0)]),
+        ("x2", vec![(3, 1.0)]),
+      ],
+      calls
+    );
+    crate::assert_graph!(merged,
+    vertices: ["root" => 2, "x" => 2, "y" => 2, "x2" => 3],
+    edges: [
+      "root" => "x" => 31,
+      "root" => "x" => 11,
+      "root" => "y" => 20,
+      "y" => "root" => 30,
+    ]);
+    assert_eq!("y", *merged.find_node(&"alias").unwrap().get_label());
+    assert_eq!("x2", *merged.find_node(&"x2").unwrap().get_label());
+    assert!(crate::testing::invariant_violations(&merged).is_empty());
+
+    assert_eq!(
+      0,
+      merge_ranked(Vec::<(Graph, f64)>::new(), |_, _| 0, |_, _, _| 0).vertex_count()
+    );
+  }
+}
@@ -0,0 +1,173 @@
+//! Additional game states that address existing vertices.
+//!
+//! Each vertex of a graph is labeled with a single canonical game state, which
+//! is what [Node::get_label](../nav/struct.Node.html#method.get_label)
+//! returns. [Graph::alias_state](../struct.Graph.html#method.alias_state)
+//! registers another game state as an alias of a vertex, after which looking
+//! up or inserting the alias (e.g., with `find_node` or `add_edge`) reaches
+//! that vertex instead of creating a new one. This is useful when distinct
+//! game states are known to be equivalent (e.g., under a symmetry) but
+//! canonicalizing them eagerly is expensive.
+//!
+//! Aliases survive garbage collection as long as the vertex they address
+//! does, and are dropped along with it.
+//!
+//! ```rust
+//! # use search_graph::Graph;
+//! # fn main() {
+//! let mut g: Graph<&str, u32, ()> = Graph::new();
+//! g.add_edge("root", |_| 0, "a", |_| 1, ());
+//! g.alias_state("a'", &"a").unwrap();
+//! g.add_edge("root", |_| 0, "a'", |_| 2, ());
+//! assert_eq!(g.vertex_count(), 2);
+//! let a = g.find_node(&"a'").unwrap();
+//! assert_eq!(*a.get_label(), "a");
+//! assert_eq!(*a.get_data(), 1);
+//! assert_eq!(a.get_aliases().collect::<Vec<_>>(), vec![&"a'"]);
+//! # }
+//! ```
+
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+
+use crate::base::VertexId;
+use crate::Graph;
+
+/// Errors that may arise when registering an alias.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum AliasError {
+  /// The game state to be aliased does not address any vertex.
+  UnknownState,
+  /// The alias already addresses a different vertex.
+  AlreadyInUse,
+}
+
+impl fmt::Display for AliasError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      AliasError::UnknownState => write!(f, "Aliased game state is not in the graph"),
+      AliasError::AlreadyInUse => write!(f, "Alias already addresses a different vertex"),
+    }
+  }
+}
+
+impl Error for AliasError {}
+
+impl<T: Hash + Eq + Clone, S, A> Graph<T, S, A> {
+  /// Makes `alias` address the vertex that `state` addresses, so that
+  /// subsequent lookups and insertions of `alias` reach that vertex. `state`
+  /// may itself be an alias.
+  ///
+  /// Returns an error if `state` does not address a vertex, or if `alias`
+  /// already addresses a different vertex (either as its canonical game state
+  /// or as an alias). Registering an alias that already addresses the same
+  /// vertex has no effect.
+  pub fn alias_state(&mut self, alias: T, state: &T) -> Result<(), AliasError> {
+    let id = self.lookup(state).ok_or(AliasError::UnknownState)?;
+    match self.lookup(&alias) {
+      Some(existing) if existing == id => Ok(()),
+      Some(_) => Err(AliasError::AlreadyInUse),
+      None => {
+        self.aliases.insert(alias, id);
+        Ok(())
+      }
+    }
+  }
+
+  /// Removes `alias`, returning true iff it was an alias. Canonical game
+  /// states cannot be removed this way.
+  pub fn remove_alias(&mut self, alias: &T) -> bool {
+    self.aliases.remove(alias).is_some()
+  }
+
+  /// Returns the number of aliases in the graph.
+  pub fn alias_count(&self) -> usize {
+    self.aliases.len()
+  }
+
+  /// Returns an iterator over the aliases of the vertex with the given ID, in
+  /// no particular order. This takes time proportional to the total number of
+  /// aliases in the graph.
+  pub(crate) fn aliases_of(&self, id: VertexId) -> impl Iterator<Item = &T> {
+    self
+      .aliases
+      .iter()
+      .filter(move |(_, v)| **v == id)
+      .map(|(alias, _)| alias)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::AliasError;
+
+  type Graph = crate::Graph<&'static str, &'static str, &'static str>;
+
+  fn make_graph() -> Graph {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| "root_data", "a", |_| "a_data", "root_a");
+    g.add_edge("root", |_| "root_data", "b", |_| "b_data", "root_b");
+    g
+  }
+
+  #[test]
+  fn alias_state_ok() {
+    let mut g = make_graph();
+    assert_eq!(Ok(()), g.alias_state("a1", &"a"));
+    assert_eq!(Ok(()), g.alias_state("a2", &"a1"));
+    assert_eq!(Ok(()), g.alias_state("a2", &"a"));
+    assert_eq!(Ok(()), g.alias_state("a", &"a"));
+    assert_eq!(2, g.alias_count());
+    let node = g.find_node(&"a2").unwrap();
+    assert_eq!("a", *node.get_label());
+    let mut aliases: Vec<_> = node.get_aliases().cloned().collect();
+    aliases.sort();
+    assert_eq!(vec!["a1", "a2"], aliases);
+    assert_eq!(0, g.find_node(&"b").unwrap().get_aliases().count());
+
+    g.add_edge("b", |_| "", "a1", |_| "", "b_a");
+    assert_eq!(3, g.vertex_count());
+    assert_eq!(
+      "a",
+      *g.find_node(&"b")
+        .unwrap()
+        .get_child_list()
+        .get_edge(0)
+        .get_target()
+        .get_label()
+    );
+    *g.find_node_mut(&"a1").unwrap().get_data_mut() = "new_data";
+    assert_eq!("new_data", *g.find_node(&"a").unwrap().get_data());
+
+    assert!(g.remove_alias(&"a1"));
+    assert!(!g.remove_alias(&"a1"));
+    assert!(!g.remove_alias(&"a"));
+    assert!(g.find_node(&"a1").is_none());
+  }
+
+  #[test]
+  fn alias_state_err() {
+    let mut g = make_graph();
+    assert_eq!(
+      Err(AliasError::UnknownState),
+      g.alias_state("c", &"missing")
+    );
+    assert_eq!(Err(AliasError::AlreadyInUse), g.alias_state("b", &"a"));
+    g.alias_state("a1", &"a").unwrap();
+    assert_eq!(Err(AliasError::AlreadyInUse), g.alias_state("a1", &"b"));
+  }
+
+  #[test]
+  fn aliases_survive_collection_ok() {
+    let mut g = make_graph();
+    g.add_edge("b", |_| "", "c", |_| "", "b_c");
+    g.alias_state("root1", &"root").unwrap();
+    g.alias_state("c1", &"c").unwrap();
+    g.compact_with(&["c1"], &mut []);
+    assert_eq!(1, g.vertex_count());
+    assert_eq!(1, g.alias_count());
+    assert!(g.find_node(&"root1").is_none());
+    assert_eq!("c", *g.find_node(&"c1").unwrap().get_label());
+  }
+}
@@ -0,0 +1,849 @@
+//! An immutable, read-only archive format for graphs.
+//!
+//! [Graph::to_archive](../struct.Graph.html#method.to_archive) serializes a
+//! graph into a flat byte buffer, and
+//! [ArchivedGraph](struct.ArchivedGraph.html) answers queries directly against
+//! such a buffer without deserializing it. This makes it possible to build a
+//! large graph once (for example, an endgame tablebase), write it to disk, and
+//! later memory-map it and query it in place.
+//!
+//! Queries do not allocate, except that
+//! [find_node](struct.ArchivedGraph.html#method.find_node) encodes the game
+//! state that it looks up into a new buffer. Lookups in a loop may reuse one
+//! buffer with
+//! [find_node_with](struct.ArchivedGraph.html#method.find_node_with), or pass
+//! encoded game states to
+//! [find_node_by_bytes](struct.ArchivedGraph.html#method.find_node_by_bytes).
+//!
+//! Game states, vertex data, and edge data are stored as opaque byte strings,
+//! using the encoding provided by the [ArchiveBytes](trait.ArchiveBytes.html)
+//! trait. Game states are looked up with a minimal perfect hash function that
+//! is built when the archive is written, so a lookup reads a constant number of
+//! words from the buffer and performs a single byte string comparison.
+//!
+//! ```rust
+//! # use search_graph::Graph;
+//! # use search_graph::archive::ArchivedGraph;
+//! # fn main() {
+//! let mut g: Graph<String, u32, u8> = Graph::new();
+//! g.add_edge("root".to_string(), |_| 10, "a".to_string(), |_| 20, 1);
+//! g.add_edge("root".to_string(), |_| 10, "b".to_string(), |_| 30, 2);
+//! let bytes = g.to_archive();
+//!
+//! let archive = ArchivedGraph::new(&bytes).unwrap();
+//! assert_eq!(archive.vertex_count(), 3);
+//! let root = archive.find_node("root").unwrap();
+//! assert_eq!(root.get_data(), &10u32.to_le_bytes());
+//! let targets: Vec<&[u8]> = root.children().map(|e| e.get_target().get_label()).collect();
+//! assert_eq!(targets, vec![&b"a"[..], &b"b"[..]]);
+//! assert!(archive.find_node("c").is_none());
+//! # }
+//! ```
+//!
+//! # Format
+//!
+//! An archive is an 8-byte magic string followed by a sequence of
+//! little-endian `u64` words and a trailing blob of byte strings:
+//!
+//! * A header holding the format version, vertex count `n`, edge count `m`,
+//!   number of perfect hash buckets `r`, and blob length.
+//! * `r` bucket displacements and `n` hash slots, which map each game state to
+//!   its vertex.
+//! * `n` vertex records of 8 words each: label offset and length, data offset
+//!   and length, and the start and length of the vertex's ranges in the child
+//!   and parent lists.
+//! * `m` edge records of 4 words each: source vertex, target vertex, and data
+//!   offset and length.
+//! * The child list and the parent list, of `m` edge ids each.
+//! * The blob, which holds all labels and data.
+//!
+//! Vertex and edge ids in an archive written by `to_archive` are the same as
+//! those in the graph that it was written from. They depend on the order in
+//! which vertices and edges were added, so graphs with the same contents that
+//! were built in different orders produce different archives.
+//! [Graph::to_archive_sorted_by](../struct.Graph.html#method.to_archive_sorted_by)
+//! instead numbers vertices in order of their game states and edges in order
+//! of their sources and positions among their siblings, and lists the parents
+//! of each vertex in order of edge id, so that such graphs produce
+//! byte-identical archives (e.g., for content-addressed caching).
+
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+
+use crate::base::{EdgeId, VertexId};
+use crate::Graph;
+use symbol_map::SymbolId;
+
+const MAGIC: &[u8; 8] = b"SGARCHV\0";
+const FORMAT_VERSION: u64 = 1;
+const HEADER_WORDS: usize = 5;
+const VERTEX_WORDS: usize = 8;
+const EDGE_WORDS: usize = 4;
+/// Average number of game states per perfect hash bucket.
+const BUCKET_SIZE: usize = 4;
+
+/// Types that can be written into an archive as a byte string.
+///
+/// Two values that are not equal must not have the same encoding if they are
+/// used as game states.
+pub trait ArchiveBytes {
+  /// Appends the encoding of `self` to `out`.
+  fn write_bytes(&self, out: &mut Vec<u8>);
+}
+
+impl ArchiveBytes for [u8] {
+  fn write_bytes(&self, out: &mut Vec<u8>) {
+    out.extend_from_slice(self)
+  }
+}
+
+impl ArchiveBytes for Vec<u8> {
+  fn write_bytes(&self, out: &mut Vec<u8>) {
+    out.extend_from_slice(self)
+  }
+}
+
+impl ArchiveBytes for str {
+  fn write_bytes(&self, out: &mut Vec<u8>) {
+    out.extend_from_slice(self.as_bytes())
+  }
+}
+
+impl ArchiveBytes for String {
+  fn write_bytes(&self, out: &mut Vec<u8>) {
+    out.extend_from_slice(self.as_bytes())
+  }
+}
+
+impl ArchiveBytes for () {
+  fn write_bytes(&self, _: &mut Vec<u8>) {}
+}
+
+impl<T: ArchiveBytes + ?Sized> ArchiveBytes for &T {
+  fn write_bytes(&self, out: &mut Vec<u8>) {
+    (**self).write_bytes(out)
+  }
+}
+
+macro_rules! archive_le_bytes {
+  ($($t:ty),*) => {
+    $(
+      impl ArchiveBytes for $t {
+        fn write_bytes(&self, out: &mut Vec<u8>) {
+          out.extend_from_slice(&self.to_le_bytes())
+        }
+      }
+    )*
+  };
+}
+
+archive_le_bytes!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+/// Errors that may arise when opening an archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArchiveError {
+  /// The buffer does not start with the archive magic string.
+  BadMagic,
+  /// The archive was written with an unsupported format version.
+  UnsupportedVersion(u64),
+  /// The buffer length does not match the length recorded in its header.
+  Truncated {
+    /// The length that the header implies.
+    expected: u64,
+    /// The actual buffer length.
+    actual: usize,
+  },
+  /// A record in the archive refers to data outside of the archive.
+  Corrupt,
+}
+
+impl fmt::Display for ArchiveError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      ArchiveError::BadMagic => write!(f, "Buffer is not a graph archive"),
+      ArchiveError::UnsupportedVersion(v) => {
+        write!(f, "Unsupported graph archive version {}", v)
+      }
+      ArchiveError::Truncated { expected, actual } => write!(
+        f,
+        "Graph archive has length {} but should have length {}",
+        actual, expected
+      ),
+      ArchiveError::Corrupt => write!(f, "Graph archive is corrupt"),
+    }
+  }
+}
+
+impl Error for ArchiveError {}
+
+/// Seeded 64-bit hash of a byte string. This must be stable across platforms
+/// and releases, because its values are baked into archives.
+fn hash_bytes(bytes: &[u8], seed: u64) -> u64 {
+  let mut h = 0xcbf2_9ce4_8422_2325u64 ^ seed.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+  for b in bytes {
+    h ^= u64::from(*b);
+    h = h.wrapping_mul(0x0100_0000_01b3);
+  }
+  // splitmix64 finalizer.
+  h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+  h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+  h ^ (h >> 31)
+}
+
+/// Builds a minimal perfect hash over `keys` by hash-and-displace. Returns a
+/// displacement for each bucket and the key index that is stored in each slot.
+fn build_perfect_hash(keys: &[&[u8]]) -> (Vec<u64>, Vec<u64>) {
+  let n = keys.len();
+  let r = n.div_ceil(BUCKET_SIZE) + 1;
+  let mut buckets: Vec<Vec<usize>> = vec![Vec::new(); r];
+  for (i, key) in keys.iter().enumerate() {
+    buckets[(hash_bytes(key, 0) % r as u64) as usize].push(i);
+  }
+  let mut order: Vec<usize> = (0..r).collect();
+  order.sort_by_key(|b| std::cmp::Reverse(buckets[*b].len()));
+
+  let mut displacements = vec![0u64; r];
+  let mut slots = vec![0u64; n];
+  let mut taken = vec![false; n];
+  let mut positions = Vec::new();
+  for b in order {
+    if buckets[b].is_empty() {
+      break;
+    }
+    let mut d = 1u64;
+    loop {
+      positions.clear();
+      let fits = buckets[b].iter().all(|i| {
+        let p = (hash_bytes(keys[*i], d) % n as u64) as usize;
+        if taken[p] || positions.contains(&p) {
+          false
+        } else {
+          positions.push(p);
+          true
+        }
+      });
+      if fits {
+        break;
+      }
+      d += 1;
+    }
+    for (i, p) in buckets[b].iter().zip(positions.iter()) {
+      taken[*p] = true;
+      slots[*p] = *i as u64;
+    }
+    displacements[b] = d;
+  }
+  (displacements, slots)
+}
+
+impl<T: Hash + Eq + Clone, S, A> Graph<T, S, A> {
+  /// Writes this graph into a byte buffer that can be queried with
+  /// [ArchivedGraph](archive/struct.ArchivedGraph.html).
+  ///
+  /// Panics if two distinct game states have the same
+  /// [ArchiveBytes](archive/trait.ArchiveBytes.html) encoding.
+  pub fn to_archive(&self) -> Vec<u8>
+  where
+    T: ArchiveBytes,
+    S: ArchiveBytes,
+    A: ArchiveBytes,
+  {
+    let vertices: Vec<VertexId> = (0..self.vertices.len()).map(VertexId).collect();
+    let arcs: Vec<EdgeId> = (0..self.arcs.len()).map(EdgeId).collect();
+    self.write_archive(&vertices, &arcs, false)
+  }
+
+  /// As [to_archive](#method.to_archive), but numbers the vertices of the
+  /// archive in the order of their game states under `compare` and their
+  /// edges in order of source vertex and then of child position, and lists
+  /// the parent edges of each vertex in order of edge id. Graphs with the
+  /// same game states, data, and child edges (in the same order) therefore
+  /// produce identical archives, however they were built, as long as
+  /// `compare` is a total order on their game states.
+  ///
+  /// Panics if two distinct game states have the same
+  /// [ArchiveBytes](archive/trait.ArchiveBytes.html) encoding.
+  pub fn to_archive_sorted_by<F>(&self, compare: F) -> Vec<u8>
+  where
+    T: ArchiveBytes,
+    S: ArchiveBytes,
+    A: ArchiveBytes,
+    F: FnMut(&T, &T) -> Ordering,
+  {
+    let vertices = self.vertex_ids_sorted_by(compare);
+    let arcs: Vec<EdgeId> = vertices
+      .iter()
+      .flat_map(|id| self.get_vertex(*id).children.iter().cloned())
+      .collect();
+    self.write_archive(&vertices, &arcs, true)
+  }
+
+  /// Writes an archive in which vertex `i` is the vertex with ID
+  /// `vertices[i]` and edge `i` is the edge with ID `arcs[i]`. Parent edges
+  /// are listed in order of archived edge id if `sort_parents` is true, and in
+  /// graph order otherwise.
+  fn write_archive(&self, vertices: &[VertexId], arcs: &[EdgeId], sort_parents: bool) -> Vec<u8>
+  where
+    T: ArchiveBytes,
+    S: ArchiveBytes,
+    A: ArchiveBytes,
+  {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::debug_span!(
+      "to_archive",
+      vertices = self.vertices.len(),
+      edges = self.arcs.len()
+    )
+    .entered();
+
+    let mut blob = Vec::new();
+    let mut write = |x: &dyn Fn(&mut Vec<u8>)| {
+      let start = blob.len();
+      x(&mut blob);
+      (start as u64, (blob.len() - start) as u64)
+    };
+
+    let mut vertex_ids = vec![0; self.vertices.len()];
+    for (i, id) in vertices.iter().enumerate() {
+      vertex_ids[id.as_usize()] = i as u64;
+    }
+    let mut edge_ids = vec![0; self.arcs.len()];
+    for (i, id) in arcs.iter().enumerate() {
+      edge_ids[id.as_usize()] = i as u64;
+    }
+
+    let mut labels = Vec::with_capacity(self.vertices.len());
+    let mut vertex_records = Vec::with_capacity(self.vertices.len() * VERTEX_WORDS);
+    let mut children = Vec::with_capacity(self.arcs.len());
+    let mut parents = Vec::with_capacity(self.arcs.len());
+    for id in vertices.iter() {
+      let vertex = self.get_vertex(*id);
+      let label = write(&|out| self.get_state(*id).unwrap().write_bytes(out));
+      let data = write(&|out| vertex.data.write_bytes(out));
+      labels.push(label);
+      vertex_records.extend_from_slice(&[
+        label.0,
+        label.1,
+        data.0,
+        data.1,
+        children.len() as u64,
+        vertex.children.len() as u64,
+        parents.len() as u64,
+        vertex.parents.len() as u64,
+      ]);
+      children.extend(vertex.children.iter().map(|e| edge_ids[e.as_usize()]));
+      let start = parents.len();
+      parents.extend(vertex.parents.iter().map(|e| edge_ids[e.as_usize()]));
+      if sort_parents {
+        parents[start..].sort_unstable();
+      }
+    }
+    let mut edge_records = Vec::with_capacity(self.arcs.len() * EDGE_WORDS);
+    for id in arcs.iter() {
+      let arc = self.get_arc(*id);
+      let data = write(&|out| arc.data.write_bytes(out));
+      edge_records.extend_from_slice(&[
+        vertex_ids[arc.source.as_usize()],
+        vertex_ids[arc.target.as_usize()],
+        data.0,
+        data.1,
+      ]);
+    }
+
+    let keys: Vec<&[u8]> = labels
+      .iter()
+      .map(|(start, len)| &blob[*start as usize..(*start + *len) as usize])
+      .collect();
+    assert_eq!(
+      keys.iter().collect::<HashSet<_>>().len(),
+      keys.len(),
+      "distinct game states have the same archived encoding"
+    );
+    let (displacements, slots) = build_perfect_hash(&keys);
+
+    let header = [
+      FORMAT_VERSION,
+      self.vertices.len() as u64,
+      self.arcs.len() as u64,
+      displacements.len() as u64,
+      blob.len() as u64,
+    ];
+    let word_count = header.len()
+      + displacements.len()
+      + slots.len()
+      + vertex_records.len()
+      + edge_records.len()
+      + children.len()
+      + parents.len();
+    let mut out = Vec::with_capacity(MAGIC.len() + 8 * word_count + blob.len());
+    out.extend_from_slice(MAGIC);
+    for section in [
+      &header[..],
+      &displacements,
+      &slots,
+      &vertex_records,
+      &edge_records,
+      &children,
+      &parents,
+    ]
+    .iter()
+    {
+      for word in section.iter() {
+        out.extend_from_slice(&word.to_le_bytes());
+      }
+    }
+    out.extend_from_slice(&blob);
+    out
+  }
+}
+
+/// A read-only view of a graph archive, which was written by
+/// [Graph::to_archive](../struct.Graph.html#method.to_archive).
+///
+/// All queries read directly from the underlying buffer, which may be
+/// memory-mapped.
+#[derive(Clone, Copy)]
+pub struct ArchivedGraph<'b> {
+  bytes: &'b [u8],
+  vertex_count: usize,
+  edge_count: usize,
+  bucket_count: usize,
+  /// Word offsets of each section.
+  slots: usize,
+  vertices: usize,
+  edges: usize,
+  children: usize,
+  parents: usize,
+  /// Byte offset of the blob.
+  blob: usize,
+}
+
+impl<'b> ArchivedGraph<'b> {
+  /// Opens the archive in `bytes`.
+  ///
+  /// This validates every record in the archive, so that later queries cannot
+  /// fail. Validation takes time linear in the size of the archive but does not
+  /// allocate.
+  pub fn new(bytes: &'b [u8]) -> Result<Self, ArchiveError> {
+    if bytes.len() < MAGIC.len() || &bytes[..MAGIC.len()] != MAGIC {
+      return Err(ArchiveError::BadMagic);
+    }
+    let header_len = (MAGIC.len() + 8 * HEADER_WORDS) as u64;
+    if (bytes.len() as u64) < header_len {
+      return Err(ArchiveError::Truncated {
+        expected: header_len,
+        actual: bytes.len(),
+      });
+    }
+    let header = |i: usize| read_word(bytes, i);
+    if header(0) != FORMAT_VERSION {
+      return Err(ArchiveError::UnsupportedVersion(header(0)));
+    }
+    let (n, m, r, blob_len) = (header(1), header(2), header(3), header(4));
+    let expected = n
+      .checked_mul(1 + VERTEX_WORDS as u64)
+      .and_then(|w| w.checked_add(m.checked_mul(2 + EDGE_WORDS as u64)?))
+      .and_then(|w| w.checked_add(r))
+      .and_then(|w| w.checked_mul(8))
+      .and_then(|w| w.checked_add(header_len))
+      .and_then(|w| w.checked_add(blob_len))
+      .ok_or(ArchiveError::Corrupt)?;
+    if expected != bytes.len() as u64 {
+      return Err(ArchiveError::Truncated {
+        expected,
+        actual: bytes.len(),
+      });
+    }
+    if r == 0 {
+      return Err(ArchiveError::Corrupt);
+    }
+    let (n, m, r) = (n as usize, m as usize, r as usize);
+    let slots = HEADER_WORDS + r;
+    let vertices = slots + n;
+    let edges = vertices + n * VERTEX_WORDS;
+    let children = edges + m * EDGE_WORDS;
+    let parents = children + m;
+    let archive = ArchivedGraph {
+      bytes,
+      vertex_count: n,
+      edge_count: m,
+      bucket_count: r,
+      slots,
+      vertices,
+      edges,
+      children,
+      parents,
+      blob: MAGIC.len() + 8 * (parents + m),
+    };
+    archive.validate()?;
+    Ok(archive)
+  }
+
+  fn validate(&self) -> Result<(), ArchiveError> {
+    let (n, m) = (self.vertex_count as u64, self.edge_count as u64);
+    let blob_len = (self.bytes.len() - self.blob) as u64;
+    let in_blob = |start: u64, len: u64| start.checked_add(len).is_some_and(|end| end <= blob_len);
+    let in_list = |start: u64, len: u64| start.checked_add(len).is_some_and(|end| end <= m);
+    let ok = (0..self.vertex_count).all(|i| self.word(self.slots + i) < n)
+      && (0..self.vertex_count).all(|i| {
+        let w = |j| self.word(self.vertices + i * VERTEX_WORDS + j);
+        in_blob(w(0), w(1)) && in_blob(w(2), w(3)) && in_list(w(4), w(5)) && in_list(w(6), w(7))
+      })
+      && (0..self.edge_count).all(|i| {
+        let w = |j| self.word(self.edges + i * EDGE_WORDS + j);
+        w(0) < n && w(1) < n && in_blob(w(2), w(3))
+      })
+      && (0..2 * self.edge_count).all(|i| self.word(self.children + i) < m);
+    if ok {
+      Ok(())
+    } else {
+      Err(ArchiveError::Corrupt)
+    }
+  }
+
+  fn word(&self, index: usize) -> u64 {
+    read_word(self.bytes, index)
+  }
+
+  fn blob_slice(&self, start: u64, len: u64) -> &'b [u8] {
+    let start = self.blob + start as usize;
+    &self.bytes[start..start + len as usize]
+  }
+
+  /// Returns the number of vertices in the archive.
+  pub fn vertex_count(&self) -> usize {
+    self.vertex_count
+  }
+
+  /// Returns the number of edges in the archive.
+  pub fn edge_count(&self) -> usize {
+    self.edge_count
+  }
+
+  /// Returns a node handle for the vertex with the given id, or `None` if the
+  /// id is out of bounds.
+  pub fn node(&self, id: usize) -> Option<ArchivedNode<'b>> {
+    if id < self.vertex_count {
+      Some(ArchivedNode {
+        archive: *self,
+        id: VertexId(id),
+      })
+    } else {
+      None
+    }
+  }
+
+  /// Returns an edge handle for the edge with the given id, or `None` if the id
+  /// is out of bounds.
+  pub fn edge(&self, id: usize) -> Option<ArchivedEdge<'b>> {
+    if id < self.edge_count {
+      Some(ArchivedEdge {
+        archive: *self,
+        id: EdgeId(id),
+      })
+    } else {
+      None
+    }
+  }
+
+  /// Returns a node handle for the given game state, or `None` if there is no
+  /// such game state in the archive. This allocates a buffer for the encoding
+  /// of `state`; see [find_node_with](#method.find_node_with).
+  pub fn find_node<K: ArchiveBytes + ?Sized>(&self, state: &K) -> Option<ArchivedNode<'b>> {
+    self.find_node_with(state, &mut Vec::new())
+  }
+
+  /// As [find_node](#method.find_node), but encodes `state` into `buf`, which
+  /// is cleared first. Reusing a buffer across lookups avoids allocating once
+  /// it is large enough for the longest encoding.
+  pub fn find_node_with<K: ArchiveBytes + ?Sized>(
+    &self,
+    state: &K,
+    buf: &mut Vec<u8>,
+  ) -> Option<ArchivedNode<'b>> {
+    buf.clear();
+    state.write_bytes(buf);
+    self.find_node_by_bytes(buf)
+  }
+
+  /// Returns a node handle for the game state whose encoding is `key`, or
+  /// `None` if there is no such game state in the archive.
+  pub fn find_node_by_bytes(&self, key: &[u8]) -> Option<ArchivedNode<'b>> {
+    if self.vertex_count == 0 {
+      return None;
+    }
+    let bucket = hash_bytes(key, 0) % self.bucket_count as u64;
+    let d = self.word(HEADER_WORDS + bucket as usize);
+    let slot = hash_bytes(key, d) % self.vertex_count as u64;
+    let node = self.node(self.word(self.slots + slot as usize) as usize)?;
+    if node.get_label() == key {
+      Some(node)
+    } else {
+      None
+    }
+  }
+}
+
+fn read_word(bytes: &[u8], index: usize) -> u64 {
+  let start = MAGIC.len() + 8 * index;
+  let mut word = [0u8; 8];
+  word.copy_from_slice(&bytes[start..start + 8]);
+  u64::from_le_bytes(word)
+}
+
+/// Immutable handle to a vertex in an [ArchivedGraph](struct.ArchivedGraph.html).
+#[derive(Clone, Copy)]
+pub struct ArchivedNode<'b> {
+  archive: ArchivedGraph<'b>,
+  id: VertexId,
+}
+
+impl<'b> ArchivedNode<'b> {
+  fn field(&self, j: usize) -> u64 {
+    self
+      .archive
+      .word(self.archive.vertices + self.id.as_usize() * VERTEX_WORDS + j)
+  }
+
+  /// Returns the id of this vertex, which is the same as its id in the graph
+  /// that the archive was written from.
+  pub fn get_id(&self) -> usize {
+    self.id.as_usize()
+  }
+
+  /// Returns the encoding of the game state at this vertex.
+  pub fn get_label(&self) -> &'b [u8] {
+    self.archive.blob_slice(self.field(0), self.field(1))
+  }
+
+  /// Returns the encoding of the data at this vertex.
+  pub fn get_data(&self) -> &'b [u8] {
+    self.archive.blob_slice(self.field(2), self.field(3))
+  }
+
+  /// Returns true iff this vertex has no outgoing edges.
+  pub fn is_leaf(&self) -> bool {
+    self.field(5) == 0
+  }
+
+  /// Returns true iff this vertex has no incoming edges.
+  pub fn is_root(&self) -> bool {
+    self.field(7) == 0
+  }
+
+  /// Returns an iterator over outgoing edges.
+  pub fn children(&self) -> ArchivedEdgeIter<'b> {
+    let start = self.archive.children + self.field(4) as usize;
+    ArchivedEdgeIter {
+      archive: self.archive,
+      range: start..start + self.field(5) as usize,
+    }
+  }
+
+  /// Returns an iterator over incoming edges.
+  pub fn parents(&self) -> ArchivedEdgeIter<'b> {
+    let start = self.archive.parents + self.field(6) as usize;
+    ArchivedEdgeIter {
+      archive: self.archive,
+      range: start..start + self.field(7) as usize,
+    }
+  }
+}
+
+/// Immutable handle to an edge in an [ArchivedGraph](struct.ArchivedGraph.html).
+#[derive(Clone, Copy)]
+pub struct ArchivedEdge<'b> {
+  archive: ArchivedGraph<'b>,
+  id: EdgeId,
+}
+
+impl<'b> ArchivedEdge<'b> {
+  fn field(&self, j: usize) -> u64 {
+    self
+      .archive
+      .word(self.archive.edges + self.id.as_usize() * EDGE_WORDS + j)
+  }
+
+  /// Returns the id of this edge, which is the same as its id in the graph that
+  /// the archive was written from.
+  pub fn get_id(&self) -> usize {
+    self.id.as_usize()
+  }
+
+  /// Returns the encoding of the data at this edge.
+  pub fn get_data(&self) -> &'b [u8] {
+    self.archive.blob_slice(self.field(2), self.field(3))
+  }
+
+  /// Returns a node handle for this edge's source vertex.
+  pub fn get_source(&self) -> ArchivedNode<'b> {
+    ArchivedNode {
+      archive: self.archive,
+      id: VertexId(self.field(0) as usize),
+    }
+  }
+
+  /// Returns a node handle for this edge's target vertex.
+  pub fn get_target(&self) -> ArchivedNode<'b> {
+    ArchivedNode {
+      archive: self.archive,
+      id: VertexId(self.field(1) as usize),
+    }
+  }
+}
+
+/// Iterator over the child or parent edges of an
+/// [ArchivedNode](struct.ArchivedNode.html).
+#[derive(Clone)]
+pub struct ArchivedEdgeIter<'b> {
+  archive: ArchivedGraph<'b>,
+  /// Word offsets of the edge ids that remain.
+  range: std::ops::Range<usize>,
+}
+
+impl<'b> Iterator for ArchivedEdgeIter<'b> {
+  type Item = ArchivedEdge<'b>;
+
+  fn next(&mut self) -> Option<ArchivedEdge<'b>> {
+    let archive = self.archive;
+    self.range.next().map(|i| ArchivedEdge {
+      archive,
+      id: EdgeId(archive.word(i) as usize),
+    })
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    self.range.size_hint()
+  }
+}
+
+impl<'b> ExactSizeIterator for ArchivedEdgeIter<'b> {}
+
+#[cfg(test)]
+mod test {
+  use super::{ArchiveError, ArchivedGraph};
+
+  type Graph = crate::Graph<&'static str, &'static str, &'static str>;
+
+  fn make_graph() -> Graph {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| "root_data", "a", |_| "a_data", "root_a");
+    g.add_edge("root", |_| "root_data", "b", |_| "b_data", "root_b");
+    g.add_edge("a", |_| "a_data", "c", |_| "c_data", "a_c");
+    g.add_edge("b", |_| "b_data", "c", |_| "c_data", "b_c");
+    g
+  }
+
+  #[test]
+  fn archive_round_trip_ok() {
+    let g = make_graph();
+    let bytes = g.to_archive();
+    let archive = ArchivedGraph::new(&bytes).unwrap();
+    assert_eq!(g.vertex_count(), archive.vertex_count());
+    assert_eq!(g.edge_count(), archive.edge_count());
+    for label in &["root", "a", "b", "c"] {
+      let node = g.find_node(label).unwrap();
+      let archived = archive.find_node(*label).unwrap();
+      assert_eq!(node.get_id(), archived.get_id());
+      assert_eq!(label.as_bytes(), archived.get_label());
+      assert_eq!(node.get_data().as_bytes(), archived.get_data());
+      let children: Vec<&[u8]> = node
+        .get_child_list()
+        .iter()
+        .map(|e| e.get_data().as_bytes())
+        .collect();
+      let archived_children: Vec<&[u8]> = archived.children().map(|e| e.get_data()).collect();
+      assert_eq!(children, archived_children);
+      assert_eq!(node.get_parent_list().len(), archived.parents().len());
+    }
+    let c = archive.find_node("c").unwrap();
+    assert!(c.is_leaf());
+    let sources: Vec<&[u8]> = c.parents().map(|e| e.get_source().get_label()).collect();
+    assert_eq!(vec![&b"a"[..], &b"b"[..]], sources);
+    assert!(archive.find_node("d").is_none());
+
+    let mut buf = b"stale".to_vec();
+    assert_eq!(
+      c.get_id(),
+      archive.find_node_with("c", &mut buf).unwrap().get_id()
+    );
+    assert_eq!(b"c", &buf[..]);
+    assert!(archive.find_node_with("d", &mut buf).is_none());
+  }
+
+  #[test]
+  fn archive_sorted_by_ok() {
+    let g = make_graph();
+    let mut h = Graph::new();
+    h.add_edge("b", |_| "b_data", "c", |_| "c_data", "b_c");
+    h.add_edge("a", |_| "a_data", "c", |_| "c_data", "a_c");
+    h.add_edge("root", |_| "root_data", "a", |_| "a_data", "root_a");
+    h.add_edge("root", |_| "root_data", "b", |_| "b_data", "root_b");
+    assert!(g.to_archive() != h.to_archive());
+    let bytes = g.to_archive_sorted_by(|a, b| a.cmp(b));
+    assert_eq!(bytes, h.to_archive_sorted_by(|a, b| a.cmp(b)));
+
+    let archive = ArchivedGraph::new(&bytes).unwrap();
+    for (i, label) in ["a", "b", "c", "root"].iter().enumerate() {
+      let archived = archive.find_node(*label).unwrap();
+      assert_eq!(i, archived.get_id());
+      assert_eq!(label.as_bytes(), archived.get_label());
+    }
+    let root = archive.find_node("root").unwrap();
+    let targets: Vec<&[u8]> = root
+      .children()
+      .map(|e| e.get_target().get_label())
+      .collect();
+    assert_eq!(vec![&b"a"[..], &b"b"[..]], targets);
+    let c = archive.find_node("c").unwrap();
+    let sources: Vec<&[u8]> = c.parents().map(|e| e.get_source().get_label()).collect();
+    assert_eq!(vec![&b"a"[..], &b"b"[..]], sources);
+  }
+
+  #[test]
+  fn archive_many_states_ok() {
+    let mut g: crate::Graph<u32, u32, ()> = crate::Graph::new();
+    for i in 0..1000u32 {
+      g.add_edge(i, |_| i * 2, i + 1, |_| (i + 1) * 2, ());
+    }
+    let bytes = g.to_archive();
+    let archive = ArchivedGraph::new(&bytes).unwrap();
+    for i in 0..1001u32 {
+      let node = archive.find_node(&i).unwrap();
+      assert_eq!(&(i * 2).to_le_bytes(), node.get_data());
+    }
+    assert!(archive.find_node(&1001u32).is_none());
+  }
+
+  #[test]
+  fn archive_empty_ok() {
+    let bytes = Graph::new().to_archive();
+    let archive = ArchivedGraph::new(&bytes).unwrap();
+    assert_eq!(0, archive.vertex_count());
+    assert!(archive.find_node("root").is_none());
+  }
+
+  #[test]
+  fn archive_corrupt_err() {
+    let bytes = make_graph().to_archive();
+    assert_eq!(
+      Err(ArchiveError::BadMagic),
+      ArchivedGraph::new(&bytes[1..]).map(|_| ())
+    );
+    match ArchivedGraph::new(&bytes[..bytes.len() - 1]) {
+      Err(ArchiveError::Truncated { .. }) => (),
+      _ => panic!(),
+    }
+    let mut corrupt = bytes.clone();
+    // Point the first hash slot past the end of the vertex table.
+    let slot = 8 + 8 * (5 + 2);
+    corrupt[slot..slot + 8].copy_from_slice(&100u64.to_le_bytes());
+    assert_eq!(
+      Err(ArchiveError::Corrupt),
+      ArchivedGraph::new(&corrupt).map(|_| ())
+    );
+  }
+}
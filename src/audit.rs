@@ -0,0 +1,414 @@
+//! An audit trail of graph mutations, tagged with their causes.
+//!
+//! When debugging an expansion policy, it helps to know which step of a search
+//! created a vertex or last modified its data. A graph on which auditing has
+//! been enabled with
+//! [Graph::set_audit_capacity](../struct.Graph.html#method.set_audit_capacity)
+//! attaches the tag most recently set with
+//! [Graph::set_audit_tag](../struct.Graph.html#method.set_audit_tag) (e.g., a
+//! search iteration number) to each mutation. It records:
+//!
+//! * the [Provenance](struct.Provenance.html) of each vertex and edge, which
+//!   is returned by
+//!   [Node::get_provenance](../nav/struct.Node.html#method.get_provenance) and
+//!   [Edge::get_provenance](../nav/struct.Edge.html#method.get_provenance);
+//!   and
+//! * the most recent mutations as [AuditEvent](struct.AuditEvent.html)s, in a
+//!   ring buffer of fixed capacity that is read with
+//!   [Graph::audit_events](../struct.Graph.html#method.audit_events).
+//!
+//! Vertex and edge IDs in recorded events are updated when garbage collection
+//! or edge removal reassigns them, and are cleared when the vertex or edge is
+//! dropped. Graphs produced by
+//! [partitioning](../struct.Graph.html#method.partition) keep the provenance
+//! of their vertices and edges but start with no events. Spilled vertices (see
+//! [tier](../tier/index.html)) are recorded as created again when they are
+//! reloaded.
+//!
+//! ```rust
+//! # use search_graph::Graph;
+//! # use search_graph::audit::AuditOp;
+//! # fn main() {
+//! let mut g: Graph<&str, u32, ()> = Graph::new();
+//! g.set_audit_capacity(Some(100));
+//! g.set_audit_tag(Some(1));
+//! g.add_edge("root", |_| 0, "a", |_| 0, ());
+//! g.set_audit_tag(Some(2));
+//! g.add_edge("a", |_| 0, "b", |_| 0, ());
+//! *g.find_node_mut(&"a").unwrap().get_data_mut() += 1;
+//! let a = g.find_node(&"a").unwrap().get_provenance().unwrap();
+//! assert_eq!((a.created, a.modified), (Some(1), Some(2)));
+//! let last = g.audit_events().last().unwrap();
+//! assert_eq!(last.op, AuditOp::ModifyVertex);
+//! assert_eq!(last.id, Some(g.find_node(&"a").unwrap().get_id()));
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+use std::hash::Hash;
+
+use crate::Graph;
+
+/// The tags of the mutations that created a vertex or edge and last modified
+/// its data.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Provenance {
+  /// The tag in effect when the vertex or edge was created.
+  pub created: Option<u64>,
+  /// The tag in effect when the data of the vertex or edge was last modified,
+  /// or when it was created if its data has not been modified.
+  pub modified: Option<u64>,
+}
+
+/// A kind of mutation recorded in an [AuditEvent](struct.AuditEvent.html).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum AuditOp {
+  /// A vertex was added.
+  AddVertex,
+  /// The data of a vertex was borrowed mutably.
+  ModifyVertex,
+  /// An edge was added.
+  AddEdge,
+  /// The data of an edge was borrowed mutably.
+  ModifyEdge,
+  /// An edge was removed. The event refers to the edge's source vertex.
+  RemoveEdge,
+  /// Vertices were dropped by garbage collection or a similar compaction
+  /// (e.g., [Graph::retain_nodes](../struct.Graph.html#method.retain_nodes)).
+  /// The event refers to no vertex or edge.
+  Collect,
+}
+
+impl AuditOp {
+  /// Returns true iff events of this kind refer to an edge, rather than to a
+  /// vertex.
+  pub(crate) fn is_edge(self) -> bool {
+    matches!(self, AuditOp::AddEdge | AuditOp::ModifyEdge)
+  }
+}
+
+/// A mutation of a graph, with the tag that was in effect when it was made.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct AuditEvent {
+  /// The audit tag.
+  pub tag: Option<u64>,
+  /// What was done.
+  pub op: AuditOp,
+  /// The current ID of the vertex or edge that was mutated, or `None` if it
+  /// has since been dropped or the event refers to neither.
+  pub id: Option<usize>,
+}
+
+/// The audit tag, provenance, and recent events of a graph.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct AuditTrail {
+  /// The tag attached to mutations.
+  pub tag: Option<u64>,
+  /// The maximum number of events that are kept.
+  capacity: usize,
+  /// Recent events, oldest first.
+  pub events: VecDeque<AuditEvent>,
+  /// Indexed by `VertexId`.
+  pub vertices: Vec<Provenance>,
+  /// Indexed by `EdgeId`.
+  pub edges: Vec<Provenance>,
+}
+
+impl AuditTrail {
+  /// Returns a trail for a graph with the given numbers of vertices and edges,
+  /// whose provenance is unknown.
+  pub fn new(capacity: usize, vertex_count: usize, edge_count: usize) -> Self {
+    AuditTrail {
+      tag: None,
+      capacity,
+      events: VecDeque::with_capacity(capacity),
+      vertices: vec![Default::default(); vertex_count],
+      edges: vec![Default::default(); edge_count],
+    }
+  }
+
+  /// Returns an empty trail with the same tag and capacity as this one.
+  pub fn empty_like(&self) -> Self {
+    AuditTrail {
+      tag: self.tag,
+      ..AuditTrail::new(self.capacity, 0, 0)
+    }
+  }
+
+  /// Changes the maximum number of events that are kept, dropping the oldest
+  /// events if there are too many.
+  pub fn set_capacity(&mut self, capacity: usize) {
+    self.capacity = capacity;
+    let excess = self.events.len().saturating_sub(capacity);
+    self.events.drain(..excess);
+  }
+
+  /// Records an event with the current tag.
+  pub fn record(&mut self, op: AuditOp, id: Option<usize>) {
+    if self.capacity == 0 {
+      return;
+    }
+    if self.events.len() == self.capacity {
+      self.events.pop_front();
+    }
+    self.events.push_back(AuditEvent {
+      tag: self.tag,
+      op,
+      id,
+    });
+  }
+
+  /// Records the creation of a vertex, which must be the next in ID order.
+  pub fn create_vertex(&mut self) {
+    self.vertices.push(self.created());
+    self.record(AuditOp::AddVertex, Some(self.vertices.len() - 1));
+  }
+
+  /// Records a modification of the data of the vertex with the given index.
+  pub fn touch_vertex(&mut self, index: usize) {
+    self.vertices[index].modified = self.tag;
+    self.record(AuditOp::ModifyVertex, Some(index));
+  }
+
+  /// Records the creation of an edge, which must be the next in ID order.
+  pub fn create_edge(&mut self) {
+    self.edges.push(self.created());
+    self.record(AuditOp::AddEdge, Some(self.edges.len() - 1));
+  }
+
+  /// Records a modification of the data of the edge with the given index.
+  pub fn touch_edge(&mut self, index: usize) {
+    self.edges[index].modified = self.tag;
+    self.record(AuditOp::ModifyEdge, Some(index));
+  }
+
+  /// Returns the provenance of a vertex or edge that is created now.
+  fn created(&self) -> Provenance {
+    Provenance {
+      created: self.tag,
+      modified: self.tag,
+    }
+  }
+
+  /// Updates the IDs in recorded events after vertex and edge IDs have been
+  /// reassigned. `vertex` and `edge` map old IDs to new ones, or to `None` for
+  /// vertices and edges that were dropped.
+  pub fn remap<F, G>(&mut self, vertex: F, edge: G)
+  where
+    F: Fn(usize) -> Option<usize>,
+    G: Fn(usize) -> Option<usize>,
+  {
+    for event in self.events.iter_mut() {
+      event.id = match event.id {
+        Some(id) if event.op.is_edge() => edge(id),
+        Some(id) => vertex(id),
+        None => None,
+      };
+    }
+  }
+}
+
+impl<T: Hash + Eq + Clone, S, A> Graph<T, S, A> {
+  /// Enables auditing, keeping up to `capacity` of the most recent events, or
+  /// disables it if `capacity` is `None` (see [audit](audit/index.html)).
+  ///
+  /// Auditing is disabled by default. When it is enabled, the provenance of
+  /// existing vertices and edges is unknown. Disabling auditing discards the
+  /// audit tag, all provenance, and all events. A capacity of 0 keeps
+  /// provenance but no events.
+  pub fn set_audit_capacity(&mut self, capacity: Option<usize>) {
+    match (capacity, self.audit.as_mut()) {
+      (Some(capacity), Some(audit)) => audit.set_capacity(capacity),
+      (Some(capacity), None) => {
+        self.audit = Some(AuditTrail::new(
+          capacity,
+          self.vertices.len(),
+          self.arcs.len(),
+        ))
+      }
+      (None, _) => self.audit = None,
+    }
+  }
+
+  /// Sets the tag that is attached to subsequent mutations. Has no effect if
+  /// auditing is disabled.
+  pub fn set_audit_tag(&mut self, tag: Option<u64>) {
+    if let Some(ref mut audit) = self.audit {
+      audit.tag = tag;
+    }
+  }
+
+  /// Returns the tag that is attached to mutations, or `None` if there is no
+  /// tag or auditing is disabled.
+  pub fn audit_tag(&self) -> Option<u64> {
+    self.audit.as_ref().and_then(|a| a.tag)
+  }
+
+  /// Returns the most recent mutations, oldest first. Returns nothing if
+  /// auditing is disabled.
+  pub fn audit_events(&self) -> impl Iterator<Item = &AuditEvent> {
+    self.audit.iter().flat_map(|a| a.events.iter())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::{AuditEvent, AuditOp, Provenance};
+
+  type Graph = crate::Graph<&'static str, u32, u32>;
+
+  fn provenance(created: u64, modified: u64) -> Option<Provenance> {
+    Some(Provenance {
+      created: Some(created),
+      modified: Some(modified),
+    })
+  }
+
+  #[test]
+  fn provenance_ok() {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| 0, "a", |_| 0, 0);
+    g.set_audit_capacity(Some(0));
+    assert_eq!(
+      Some(Provenance::default()),
+      g.find_node(&"root").unwrap().get_provenance()
+    );
+    g.set_audit_tag(Some(1));
+    g.add_edge("a", |_| 0, "b", |_| 0, 0);
+    g.set_audit_tag(Some(2));
+    *g.find_node_mut(&"b").unwrap().get_data_mut() += 1;
+    *g.find_node_mut(&"a")
+      .unwrap()
+      .get_child_list_mut()
+      .get_edge_mut(0)
+      .get_data_mut() += 1;
+    g.set_audit_tag(Some(3));
+    g.add_edge("b", |_| 0, "c", |_| 0, 0);
+    assert_eq!(Some(3), g.audit_tag());
+    assert_eq!(0, g.audit_events().count());
+    let node = |g: &Graph, state| g.find_node(&state).unwrap().get_provenance();
+    assert_eq!(Some(Provenance::default()), node(&g, "a"));
+    assert_eq!(provenance(1, 2), node(&g, "b"));
+    assert_eq!(provenance(3, 3), node(&g, "c"));
+    let a = g.find_node(&"a").unwrap();
+    let edge = a.get_child_list().get_edge(0);
+    assert_eq!(provenance(1, 2), edge.get_provenance());
+
+    g.compact_with(&["b"], &mut []);
+    assert_eq!(provenance(1, 2), node(&g, "b"));
+    assert_eq!(provenance(3, 3), node(&g, "c"));
+    let (left, right) = g.partition(|state, _| *state == "c");
+    assert_eq!(provenance(3, 3), node(&left, "c"));
+    assert_eq!(provenance(1, 2), node(&right, "b"));
+    assert_eq!(Some(3), right.audit_tag());
+
+    let mut g = Graph::new();
+    g.set_audit_tag(Some(1));
+    g.add_node("root", 0);
+    assert_eq!(None, g.audit_tag());
+    assert_eq!(None, node(&g, "root"));
+    g.set_audit_capacity(Some(1));
+    g.set_audit_tag(Some(1));
+    g.set_audit_capacity(None);
+    assert_eq!(None, g.audit_tag());
+  }
+
+  #[test]
+  fn audit_events_ok() {
+    let mut g = Graph::new();
+    g.set_audit_capacity(Some(100));
+    g.set_audit_tag(Some(7));
+    g.add_edge("root", |_| 0, "a", |_| 0, 0);
+    g.add_edge("root", |_| 0, "b", |_| 0, 0);
+    g.add_edge("b", |_| 0, "c", |_| 0, 0);
+    g.set_audit_tag(None);
+    *g.find_node_mut(&"b")
+      .unwrap()
+      .get_child_list_mut()
+      .get_edge_mut(0)
+      .get_data_mut() = 1;
+    g.retain_edges(|e| *e.get_target().get_label() != "a");
+    let event = |op, id| AuditEvent {
+      tag: Some(7),
+      op,
+      id,
+    };
+    assert_eq!(
+      vec![
+        event(AuditOp::AddVertex, Some(0)),
+        event(AuditOp::AddVertex, Some(1)),
+        event(AuditOp::AddEdge, None),
+        event(AuditOp::AddVertex, Some(2)),
+        // The last edge has moved into the removed edge's slot.
+        event(AuditOp::AddEdge, Some(1)),
+        event(AuditOp::AddVertex, Some(3)),
+        event(AuditOp::AddEdge, Some(0)),
+        AuditEvent {
+          tag: None,
+          op: AuditOp::ModifyEdge,
+          id: Some(0),
+        },
+        AuditEvent {
+          tag: None,
+          op: AuditOp::RemoveEdge,
+          id: Some(0),
+        },
+      ],
+      g.audit_events().cloned().collect::<Vec<_>>()
+    );
+
+    g.set_audit_capacity(Some(3));
+    g.compact_with(&["b"], &mut []);
+    assert_eq!(
+      vec![
+        (AuditOp::ModifyEdge, Some(0)),
+        (AuditOp::RemoveEdge, None),
+        (AuditOp::Collect, None),
+      ],
+      g.audit_events().map(|e| (e.op, e.id)).collect::<Vec<_>>()
+    );
+    assert_eq!(0, Graph::new().audit_events().count());
+  }
+
+  #[test]
+  fn provenance_remove_several_ok() {
+    let targets = ["a", "b", "c", "d", "e", "f"];
+    let mut g = Graph::new();
+    g.set_audit_capacity(Some(100));
+    for (tag, target) in targets.iter().enumerate() {
+      g.set_audit_tag(Some(tag as u64));
+      g.add_edge("root", |_| 0, *target, |_| 0, tag as u32);
+    }
+    g.set_audit_tag(None);
+    assert_eq!(
+      2,
+      g.retain_edges(|e| !["b", "e"].contains(e.get_target().get_label()))
+    );
+
+    let root = g.find_node(&"root").unwrap();
+    let children = root.get_child_list();
+    assert_eq!(4, children.len());
+    for i in 0..children.len() {
+      let edge = children.get_edge(i);
+      let tag = u64::from(*edge.get_data());
+      assert_eq!(targets[tag as usize], *edge.get_target().get_label());
+      assert_eq!(provenance(tag, tag), edge.get_provenance());
+    }
+    let added: Vec<_> = g
+      .audit_events()
+      .filter(|e| e.op == AuditOp::AddEdge)
+      .map(|e| (e.tag.unwrap(), e.id))
+      .collect();
+    assert_eq!(6, added.len());
+    for (tag, id) in added {
+      match id {
+        Some(id) => {
+          let edge = &g.arcs[id];
+          assert_eq!(u64::from(edge.data), tag);
+          assert_eq!(targets[tag as usize], *g.get_state(edge.target).unwrap());
+        }
+        None => assert!(tag == 1 || tag == 4),
+      }
+    }
+  }
+}
@@ -1,4 +1,4 @@
-use symbol_map;
+use crate::nav::Expansion;
 
 /// Internal edge identifier.
 ///
@@ -6,6 +6,7 @@ use symbol_map;
 /// graph that it belongs to, which makes it only slightly less dangerous than a
 /// pointer with no lifetime.
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[repr(transparent)]
 pub(crate) struct EdgeId(pub usize);
 
 impl EdgeId {
@@ -15,6 +16,13 @@ impl EdgeId {
     let EdgeId(x) = self;
     x
   }
+
+  /// Views a slice of `EdgeId`s as the slice of their `usize` values, without
+  /// copying.
+  pub fn as_usize_slice(ids: &[EdgeId]) -> &[usize] {
+    // Safe because EdgeId is a transparent wrapper around usize.
+    unsafe { std::slice::from_raw_parts(ids.as_ptr() as *const usize, ids.len()) }
+  }
 }
 
 /// Internal vertex identifier.
@@ -23,15 +31,9 @@ impl EdgeId {
 /// states. This type is not exported by the crate because it does not identify
 /// the graph that it belongs to, which makes it only slightly less dangerous
 /// than a pointer with no lifetime.
-#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub(crate) struct VertexId(pub usize);
 
-impl Default for VertexId {
-  fn default() -> Self {
-    VertexId(0)
-  }
-}
-
 impl symbol_map::SymbolId for VertexId {
   fn next(&self) -> Self {
     VertexId(self.0 + 1)
@@ -45,7 +47,7 @@ impl symbol_map::SymbolId for VertexId {
 ///
 /// The Hash, Ord, and Eq implementations will conflate parallel edges with
 /// identical statistics.
-#[derive(Debug, Eq, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
 pub(crate) struct RawEdge<A> {
   /// Edge data.
   pub data: A,
@@ -56,7 +58,7 @@ pub(crate) struct RawEdge<A> {
 }
 
 /// Internal type for graph vertices.
-#[derive(Debug, Eq, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
 pub(crate) struct RawVertex<S> {
   /// Vertex data.
   pub data: S,
@@ -64,4 +66,6 @@ pub(crate) struct RawVertex<S> {
   pub parents: Vec<EdgeId>,
   /// Child edges pointing out of this vertex.
   pub children: Vec<EdgeId>,
+  /// How much of this vertex's set of children has been generated.
+  pub expansion: Expansion,
 }
@@ -0,0 +1,197 @@
+//! Workloads for measuring the performance of common graph operations.
+//!
+//! The benchmarks in this crate's `benches/` directory are built from these
+//! workloads, which are public so that users may time the same operations on
+//! their own hardware, or on graphs shaped like their own searches. A
+//! [Workload](struct.Workload.html) is a list of edges taken from one of the
+//! graphs in [testing::generate](../testing/generate/index.html), recorded up
+//! front so that timing insertion does not also time the generator. The
+//! operations measured are:
+//!
+//! * insertion, with [Workload::insert](struct.Workload.html#method.insert),
+//!   which adds every edge to an empty graph;
+//! * transposition table lookup, with [lookup](fn.lookup.html), which finds
+//!   the vertices for a list of game states, usually drawn with
+//!   [Workload::lookups](struct.Workload.html#method.lookups);
+//! * traversal, with [traverse](fn.traverse.html), which visits every vertex
+//!   that is reachable from the root; and
+//! * garbage collection, with [collect](fn.collect.html), which drops every
+//!   vertex that is not reachable from a set of roots.
+//!
+//! Random choices are drawn from [rng](fn.rng.html), so a workload is the
+//! same on every run for the same seed.
+//!
+//! ```rust
+//! # use search_graph::bench_support::{self, Workload};
+//! # fn main() {
+//! let workload = Workload::dag(1000, 4, 0.25, 7);
+//! let mut g = workload.insert();
+//! assert_eq!(g.vertex_count(), 1000);
+//! let states = workload.lookups(100, 0.9, 7);
+//! assert!(bench_support::lookup(&g, &states) >= 80);
+//! assert_eq!(bench_support::traverse(&g), 1000);
+//! bench_support::collect(&mut g, &[1]);
+//! assert!(g.vertex_count() < 1000);
+//! # }
+//! ```
+
+use crate::algo;
+use crate::testing::generate;
+use crate::Graph;
+
+/// Returns a source of pseudo-random numbers (SplitMix64) that produces the
+/// same sequence for the same seed, in the form that the generators in
+/// [testing::generate](../testing/generate/index.html) expect.
+pub fn rng(seed: u64) -> impl FnMut() -> u64 {
+  let mut state = seed;
+  move || {
+    state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+  }
+}
+
+/// The edges of a synthetic graph, as pairs of the game states of their
+/// source and target vertices. Game states are the serial numbers of
+/// vertices, starting with the root at 0.
+#[derive(Clone, Debug)]
+pub struct Workload {
+  vertex_count: usize,
+  edges: Vec<(u64, u64)>,
+}
+
+impl Workload {
+  /// Returns the edges of a graph generated with
+  /// [generate::tree](../testing/generate/fn.tree.html).
+  pub fn tree(branching: usize, depth: usize) -> Self {
+    Self::from_graph(&generate::tree(branching, depth))
+  }
+
+  /// Returns the edges of a graph generated with
+  /// [generate::dag_with_transpositions](../testing/generate/fn.dag_with_transpositions.html),
+  /// drawing random numbers from `rng(seed)`. Adding these edges to a graph
+  /// looks up an existing vertex about `transposition_rate` of the time.
+  pub fn dag(vertex_count: usize, branching: usize, transposition_rate: f64, seed: u64) -> Self {
+    Self::from_graph(&generate::dag_with_transpositions(
+      vertex_count,
+      branching,
+      transposition_rate,
+      &mut rng(seed),
+    ))
+  }
+
+  /// Returns the edges of a graph generated with
+  /// [generate::scale_free](../testing/generate/fn.scale_free.html), drawing
+  /// random numbers from `rng(seed)`.
+  pub fn scale_free(vertex_count: usize, parents: usize, seed: u64) -> Self {
+    Self::from_graph(&generate::scale_free(vertex_count, parents, &mut rng(seed)))
+  }
+
+  /// Records the child edges of every vertex of `graph`, in order of vertex
+  /// ID and then of child position.
+  fn from_graph(graph: &Graph<u64, (), ()>) -> Self {
+    let mut edges = Vec::with_capacity(graph.edge_count());
+    for state in 0..graph.vertex_count() as u64 {
+      let node = graph.find_node(&state).unwrap();
+      for edge in node.get_child_list().iter() {
+        edges.push((state, *edge.get_target().get_label()));
+      }
+    }
+    Workload {
+      vertex_count: graph.vertex_count(),
+      edges,
+    }
+  }
+
+  /// Returns the number of vertices in the graph.
+  pub fn vertex_count(&self) -> usize {
+    self.vertex_count
+  }
+
+  /// Returns the edges of the graph, in the order in which they are inserted.
+  pub fn edges(&self) -> &[(u64, u64)] {
+    &self.edges
+  }
+
+  /// Builds the graph by adding the root and then each edge in turn to an
+  /// empty graph.
+  pub fn insert(&self) -> Graph<u64, (), ()> {
+    let mut graph = Graph::new();
+    graph.add_node(0, ());
+    for (source, target) in self.edges.iter() {
+      graph.add_edge(*source, |_| (), *target, |_| (), ());
+    }
+    graph
+  }
+
+  /// Returns `count` game states to look up in the graph, drawing random
+  /// numbers from `rng(seed)`. Each game state is that of a vertex chosen
+  /// uniformly at random with probability `hit_rate`, and otherwise is not in
+  /// the graph.
+  pub fn lookups(&self, count: usize, hit_rate: f64, seed: u64) -> Vec<u64> {
+    let mut rng = rng(seed);
+    let vertex_count = self.vertex_count as u64;
+    (0..count)
+      .map(|_| {
+        let hit = ((rng() >> 11) as f64 / (1u64 << 53) as f64) < hit_rate;
+        let state = rng() % vertex_count;
+        if hit {
+          state
+        } else {
+          vertex_count + state
+        }
+      })
+      .collect()
+  }
+}
+
+/// Looks up the vertex for each of `states` in `graph`, and returns the number
+/// that were found.
+pub fn lookup(graph: &Graph<u64, (), ()>, states: &[u64]) -> usize {
+  states
+    .iter()
+    .filter(|state| graph.find_node(state).is_some())
+    .count()
+}
+
+/// Visits the vertices of `graph` that are reachable from the root in
+/// breadth-first order, and returns their number.
+pub fn traverse(graph: &Graph<u64, (), ()>) -> usize {
+  algo::breadth_first(graph, &[0]).len()
+}
+
+/// Garbage-collects `graph`, keeping only the vertices that are reachable from
+/// `roots`.
+pub fn collect(graph: &mut Graph<u64, (), ()>, roots: &[u64]) {
+  graph.compact_with(roots, &mut []);
+}
+
+#[cfg(test)]
+mod test {
+  use super::Workload;
+
+  #[test]
+  fn workload_ok() {
+    let workload = Workload::dag(500, 3, 0.3, 1);
+    let g = workload.insert();
+    assert_eq!(500, g.vertex_count());
+    assert_eq!(workload.edges().len(), g.edge_count());
+    assert_eq!(workload.edges(), Workload::dag(500, 3, 0.3, 1).edges());
+    assert_eq!(500, super::traverse(&g));
+    assert_eq!(0, super::lookup(&g, &workload.lookups(50, 0.0, 2)));
+    assert_eq!(50, super::lookup(&g, &workload.lookups(50, 1.0, 2)));
+
+    let workload = Workload::tree(2, 3);
+    assert_eq!(15, workload.vertex_count());
+    let mut g = workload.insert();
+    super::collect(&mut g, &[1]);
+    assert_eq!(7, g.vertex_count());
+    assert_eq!(0, g.find_node(&1).unwrap().get_id());
+
+    let g = Workload::scale_free(1, 2, 3).insert();
+    assert_eq!(1, g.vertex_count());
+    assert_eq!(1, super::traverse(&g));
+  }
+}
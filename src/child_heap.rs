@@ -0,0 +1,175 @@
+//! Optional ordering of the child edges of each vertex by a key.
+//!
+//! When enabled on a [Graph](../struct.Graph.html), the child edges of each
+//! vertex are kept in a binary heap ordered by a key extracted from edge data,
+//! so that the child edge with the greatest key can be found without scanning
+//! the vertex's child list. Keys are recomputed only when an edge is added or
+//! touched.
+
+use crate::base::{EdgeId, RawEdge, RawVertex, VertexId};
+use symbol_map::SymbolId;
+
+/// Binary max-heaps of the child edges of each vertex, ordered by a key
+/// extracted from edge data. Ties are broken in favor of lower edge IDs.
+pub(crate) struct ChildHeaps<A> {
+  /// Extracts keys from edge data.
+  pub key: fn(&A) -> u64,
+  /// Heap of child edges, by source vertex.
+  heaps: Vec<Vec<EdgeId>>,
+  /// The key of each edge as of when it was added or last touched, by edge.
+  keys: Vec<u64>,
+  /// The position of each edge in the heap of its source, by edge.
+  positions: Vec<usize>,
+}
+
+impl<A> ChildHeaps<A> {
+  /// Creates heaps over all edges in the given graph components.
+  pub fn new<S>(key: fn(&A) -> u64, vertices: &[RawVertex<S>], arcs: &[RawEdge<A>]) -> Self {
+    let mut heaps = ChildHeaps {
+      key,
+      heaps: Vec::new(),
+      keys: Vec::new(),
+      positions: Vec::new(),
+    };
+    heaps.rebuild(vertices, arcs);
+    heaps
+  }
+
+  /// Creates empty heaps that extract keys in the same way as these.
+  pub fn empty_like(&self) -> Self {
+    ChildHeaps {
+      key: self.key,
+      heaps: Vec::new(),
+      keys: Vec::new(),
+      positions: Vec::new(),
+    }
+  }
+
+  /// Discards all heaps and rebuilds them from the given graph components,
+  /// recomputing every key.
+  pub fn rebuild<S>(&mut self, vertices: &[RawVertex<S>], arcs: &[RawEdge<A>]) {
+    self.keys = arcs.iter().map(|arc| (self.key)(&arc.data)).collect();
+    self.positions = vec![0; arcs.len()];
+    self.heaps = vertices.iter().map(|v| v.children.clone()).collect();
+    for source in 0..self.heaps.len() {
+      let len = self.heaps[source].len();
+      for i in 0..len {
+        self.positions[self.heaps[source][i].as_usize()] = i;
+      }
+      for i in (0..len / 2).rev() {
+        self.sift_down(source, i);
+      }
+    }
+  }
+
+  /// Adds a newly added edge to the heap of its source.
+  pub fn insert(&mut self, source: VertexId, edge_id: EdgeId, data: &A) {
+    if self.heaps.len() <= source.as_usize() {
+      self.heaps.resize_with(source.as_usize() + 1, Vec::new);
+    }
+    if self.keys.len() <= edge_id.as_usize() {
+      self.keys.resize(edge_id.as_usize() + 1, 0);
+      self.positions.resize(edge_id.as_usize() + 1, 0);
+    }
+    self.keys[edge_id.as_usize()] = (self.key)(data);
+    let heap = &mut self.heaps[source.as_usize()];
+    let i = heap.len();
+    heap.push(edge_id);
+    self.positions[edge_id.as_usize()] = i;
+    self.sift_up(source.as_usize(), i);
+  }
+
+  /// Recomputes the key of an edge from its current data and restores the
+  /// heap order of its source.
+  pub fn update(&mut self, source: VertexId, edge_id: EdgeId, data: &A) {
+    self.keys[edge_id.as_usize()] = (self.key)(data);
+    let i = self.positions[edge_id.as_usize()];
+    let i = self.sift_up(source.as_usize(), i);
+    self.sift_down(source.as_usize(), i);
+  }
+
+  /// Returns the child edge of `source` with the greatest key, if any.
+  pub fn best(&self, source: VertexId) -> Option<EdgeId> {
+    self
+      .heaps
+      .get(source.as_usize())
+      .and_then(|heap| heap.first())
+      .cloned()
+  }
+
+  /// Returns true iff each heap holds exactly the child edges of its source,
+  /// in heap order by their current keys, and the recorded positions of edges
+  /// match the heaps. Heaps need not be laid out as a rebuild would lay them
+  /// out, since that depends on the order in which edges were added.
+  pub fn is_consistent<S>(&self, vertices: &[RawVertex<S>], arcs: &[RawEdge<A>]) -> bool {
+    if self.keys.len() != arcs.len() || self.positions.len() != arcs.len() {
+      return false;
+    }
+    let fresh = arcs.iter().map(|arc| (self.key)(&arc.data));
+    if !fresh.eq(self.keys.iter().cloned()) {
+      return false;
+    }
+    vertices.iter().enumerate().all(|(source, vertex)| {
+      let heap = self.heaps.get(source).map_or(&[][..], Vec::as_slice);
+      let mut sorted = heap.to_vec();
+      let mut children = vertex.children.clone();
+      sorted.sort_unstable();
+      children.sort_unstable();
+      sorted == children
+        && heap
+          .iter()
+          .enumerate()
+          .all(|(i, id)| self.positions[id.as_usize()] == i)
+        && (1..heap.len()).all(|i| !self.precedes(heap[i], heap[(i - 1) / 2]))
+    })
+  }
+
+  /// Returns true iff `a` belongs above `b` in a heap.
+  fn precedes(&self, a: EdgeId, b: EdgeId) -> bool {
+    let (ka, kb) = (self.keys[a.as_usize()], self.keys[b.as_usize()]);
+    ka > kb || (ka == kb && a < b)
+  }
+
+  /// Swaps two entries of the heap of `source`.
+  fn swap(&mut self, source: usize, i: usize, j: usize) {
+    let heap = &mut self.heaps[source];
+    heap.swap(i, j);
+    self.positions[heap[i].as_usize()] = i;
+    self.positions[heap[j].as_usize()] = j;
+  }
+
+  /// Moves the entry at position `i` of the heap of `source` up until it is in
+  /// heap order, and returns its new position.
+  fn sift_up(&mut self, source: usize, mut i: usize) -> usize {
+    while i > 0 {
+      let parent = (i - 1) / 2;
+      let heap = &self.heaps[source];
+      if !self.precedes(heap[i], heap[parent]) {
+        break;
+      }
+      self.swap(source, i, parent);
+      i = parent;
+    }
+    i
+  }
+
+  /// Moves the entry at position `i` of the heap of `source` down until it is
+  /// in heap order.
+  fn sift_down(&mut self, source: usize, mut i: usize) {
+    let len = self.heaps[source].len();
+    loop {
+      let heap = &self.heaps[source];
+      let mut first = i;
+      for child in [2 * i + 1, 2 * i + 2].iter() {
+        if *child < len && self.precedes(heap[*child], heap[first]) {
+          first = *child;
+        }
+      }
+      if first == i {
+        break;
+      }
+      self.swap(source, i, first);
+      i = first;
+    }
+  }
+}
@@ -0,0 +1,74 @@
+//! Optional index from (vertex, move key) pairs to outgoing edges.
+//!
+//! When enabled on a [Graph](../struct.Graph.html), this index allows the
+//! child edge of a vertex that corresponds to a particular move to be found
+//! without scanning the vertex's child list.
+
+use std::collections::HashMap;
+
+use crate::base::{EdgeId, RawEdge, RawVertex, VertexId};
+
+/// Maps from a vertex and a key extracted from edge data to the first child
+/// edge of that vertex with that key.
+pub(crate) struct ChildIndex<A> {
+  /// Extracts keys from edge data.
+  pub key: fn(&A) -> u64,
+  /// True iff keys are hashes of `MoveKey` keys.
+  pub by_move: bool,
+  edges: HashMap<(VertexId, u64), EdgeId>,
+}
+
+impl<A> ChildIndex<A> {
+  /// Creates an index over all edges in the given graph components.
+  pub fn new<S>(
+    key: fn(&A) -> u64,
+    by_move: bool,
+    vertices: &[RawVertex<S>],
+    arcs: &[RawEdge<A>],
+  ) -> Self {
+    let mut index = ChildIndex {
+      key,
+      by_move,
+      edges: HashMap::new(),
+    };
+    index.rebuild(vertices, arcs);
+    index
+  }
+
+  /// Creates an empty index that extracts keys in the same way as this one.
+  pub fn empty_like(&self) -> Self {
+    ChildIndex {
+      key: self.key,
+      by_move: self.by_move,
+      edges: HashMap::new(),
+    }
+  }
+
+  /// Discards all index entries and re-indexes the given graph components.
+  pub fn rebuild<S>(&mut self, vertices: &[RawVertex<S>], arcs: &[RawEdge<A>]) {
+    self.edges.clear();
+    for (source, vertex) in vertices.iter().enumerate() {
+      for &edge_id in vertex.children.iter() {
+        self.insert(VertexId(source), edge_id, &arcs[edge_id.as_usize()].data);
+      }
+    }
+  }
+
+  /// Indexes a newly added edge. If `source` already has a child edge with the
+  /// same key, the existing entry is retained.
+  pub fn insert(&mut self, source: VertexId, edge_id: EdgeId, data: &A) {
+    let key = (self.key)(data);
+    self.edges.entry((source, key)).or_insert(edge_id);
+  }
+
+  /// Returns true iff this index has the same entries as an index rebuilt
+  /// from the given graph components.
+  pub fn is_consistent<S>(&self, vertices: &[RawVertex<S>], arcs: &[RawEdge<A>]) -> bool {
+    self.edges == ChildIndex::new(self.key, self.by_move, vertices, arcs).edges
+  }
+
+  /// Returns the first child edge of `source` with the given key, if any.
+  pub fn get(&self, source: VertexId, key: u64) -> Option<EdgeId> {
+    self.edges.get(&(source, key)).cloned()
+  }
+}
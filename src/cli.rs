@@ -0,0 +1,341 @@
+//! Helpers for command-line tools that inspect saved graphs.
+//!
+//! The functions in this module do the work of a small inspection tool, so
+//! that a binary only has to choose the types of game states and data and
+//! dispatch on its arguments. Graphs are read and written as JSON in the
+//! format described in [serialize](../serialize/index.html), and game states
+//! are given on the command line as JSON values (a bare word is taken to be a
+//! string).
+//!
+//! * [load](fn.load.html) and [save](fn.save.html) read and write graphs.
+//! * [Summary](struct.Summary.html) describes the size and shape of a graph,
+//!   and displays itself as one `name: value` line per statistic.
+//! * [extract](fn.extract.html) copies the part of a graph that is reachable
+//!   from a game state, as by
+//!   [Graph::extract_reachable_from](../struct.Graph.html#method.extract_reachable_from).
+//! * [write_dot](fn.write_dot.html) renders all or part of a graph in the DOT
+//!   language with [io::dot](../io/dot/index.html), labeling vertices and
+//!   edges with their `Debug` representations.
+//!
+//! This module is only available when the `cli` feature is enabled, which
+//! also enables the `serde` and `dot` features.
+//!
+//! ```rust
+//! # use search_graph::Graph;
+//! # use search_graph::cli;
+//! # fn main() {
+//! let mut g: Graph<String, u32, char> = Graph::new();
+//! g.add_edge("root".to_string(), |_| 0, "a".to_string(), |_| 1, 'x');
+//! g.add_edge("a".to_string(), |_| 1, "b".to_string(), |_| 2, 'y');
+//! let mut saved = Vec::new();
+//! cli::save(&g, &mut saved).unwrap();
+//!
+//! let g: Graph<String, u32, char> = cli::load(&saved[..]).unwrap();
+//! assert_eq!(cli::Summary::of(&g).vertices, 3);
+//! let state: String = cli::parse_state("a").unwrap();
+//! let sub = cli::extract(&g, &state).unwrap();
+//! let mut dot = Vec::new();
+//! cli::write_dot(&sub, None, None, &mut dot).unwrap();
+//! assert!(String::from_utf8(dot).unwrap().contains("\\\"b\\\": 2"));
+//! # }
+//! ```
+
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+use std::io::{self, Read, Write};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::io::dot::Dot;
+use crate::nav::Expansion;
+use crate::Graph;
+use symbol_map::SymbolId;
+
+/// Errors that may arise in command-line tools.
+#[derive(Debug)]
+pub enum CliError {
+  /// Reading or writing failed.
+  Io(io::Error),
+  /// A graph or game state could not be parsed.
+  Parse(serde_json::Error),
+  /// The graph has no vertex for the game state with the given text.
+  UnknownState(String),
+}
+
+impl fmt::Display for CliError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      CliError::Io(ref e) => write!(f, "I/O error: {}", e),
+      CliError::Parse(ref e) => write!(f, "Parse error: {}", e),
+      CliError::UnknownState(ref state) => write!(f, "No vertex for game state {}", state),
+    }
+  }
+}
+
+impl Error for CliError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match *self {
+      CliError::Io(ref e) => Some(e),
+      CliError::Parse(ref e) => Some(e),
+      CliError::UnknownState(_) => None,
+    }
+  }
+}
+
+impl From<io::Error> for CliError {
+  fn from(e: io::Error) -> Self {
+    CliError::Io(e)
+  }
+}
+
+impl From<serde_json::Error> for CliError {
+  fn from(e: serde_json::Error) -> Self {
+    if e.is_io() {
+      CliError::Io(e.into())
+    } else {
+      CliError::Parse(e)
+    }
+  }
+}
+
+/// Reads a graph that was written by [save](fn.save.html).
+pub fn load<T, S, A, R>(reader: R) -> Result<Graph<T, S, A>, CliError>
+where
+  T: Hash + Eq + Clone + DeserializeOwned,
+  S: DeserializeOwned,
+  A: DeserializeOwned,
+  R: Read,
+{
+  Ok(serde_json::from_reader(io::BufReader::new(reader))?)
+}
+
+/// Writes `graph` as JSON.
+pub fn save<T, S, A, W>(graph: &Graph<T, S, A>, writer: W) -> Result<(), CliError>
+where
+  T: Hash + Eq + Clone + Serialize,
+  S: Serialize,
+  A: Serialize,
+  W: Write,
+{
+  let mut writer = io::BufWriter::new(writer);
+  serde_json::to_writer(&mut writer, graph)?;
+  writer.flush()?;
+  Ok(())
+}
+
+/// Parses a game state from command-line text, which is a JSON value. If
+/// `text` is not valid JSON, it is parsed as a JSON string with its contents,
+/// so string game states need not be quoted.
+pub fn parse_state<T: DeserializeOwned>(text: &str) -> Result<T, CliError> {
+  match serde_json::from_str(text) {
+    Ok(state) => Ok(state),
+    Err(e) if e.is_syntax() || e.is_eof() => Ok(serde_json::from_value(
+      serde_json::Value::String(text.to_string()),
+    )?),
+    Err(e) => Err(e.into()),
+  }
+}
+
+/// Statistics about the size and shape of a graph.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Summary {
+  /// The number of vertices.
+  pub vertices: usize,
+  /// The number of edges.
+  pub edges: usize,
+  /// The number of vertices with no incoming edges.
+  pub roots: usize,
+  /// The number of vertices with no child edges.
+  pub leaves: usize,
+  /// The number of vertices that are unexpanded, partially expanded, and fully
+  /// expanded.
+  pub expansion: [usize; 3],
+  /// The greatest number of child edges of any vertex.
+  pub max_children: usize,
+  /// The number of aliases.
+  pub aliases: usize,
+  /// The number of child edges whose targets have not been determined.
+  pub unexpanded_edges: usize,
+  /// Whether the graph has a cycle.
+  pub cyclic: bool,
+}
+
+impl Summary {
+  /// Computes statistics about `graph`. This takes time linear in its size.
+  pub fn of<T: Hash + Eq + Clone, S, A>(graph: &Graph<T, S, A>) -> Self {
+    let mut summary = Summary {
+      vertices: graph.vertices.len(),
+      edges: graph.arcs.len(),
+      aliases: graph.aliases.len(),
+      unexpanded_edges: graph.unexpanded.values().map(Vec::len).sum(),
+      cyclic: graph.has_cycle(),
+      ..Default::default()
+    };
+    let mut has_parent = vec![false; graph.vertices.len()];
+    for arc in graph.arcs.iter() {
+      has_parent[arc.target.as_usize()] = true;
+    }
+    summary.roots = has_parent.iter().filter(|p| !**p).count();
+    for vertex in graph.vertices.iter() {
+      if vertex.children.is_empty() {
+        summary.leaves += 1;
+      }
+      summary.max_children = summary.max_children.max(vertex.children.len());
+      let index = match vertex.expansion {
+        Expansion::Unexpanded => 0,
+        Expansion::PartiallyExpanded => 1,
+        Expansion::FullyExpanded => 2,
+      };
+      summary.expansion[index] += 1;
+    }
+    summary
+  }
+}
+
+impl fmt::Display for Summary {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    writeln!(f, "vertices: {}", self.vertices)?;
+    writeln!(f, "edges: {}", self.edges)?;
+    writeln!(f, "roots: {}", self.roots)?;
+    writeln!(f, "leaves: {}", self.leaves)?;
+    writeln!(f, "unexpanded: {}", self.expansion[0])?;
+    writeln!(f, "partially expanded: {}", self.expansion[1])?;
+    writeln!(f, "fully expanded: {}", self.expansion[2])?;
+    writeln!(f, "max children: {}", self.max_children)?;
+    writeln!(f, "aliases: {}", self.aliases)?;
+    writeln!(f, "unexpanded edges: {}", self.unexpanded_edges)?;
+    writeln!(f, "cyclic: {}", self.cyclic)
+  }
+}
+
+/// Returns a copy of the part of `graph` that is reachable from `state`, or
+/// an error if there is no vertex for `state`.
+pub fn extract<T, S, A>(graph: &Graph<T, S, A>, state: &T) -> Result<Graph<T, S, A>, CliError>
+where
+  T: Hash + Eq + Clone + fmt::Debug,
+  S: Clone,
+  A: Clone,
+{
+  if graph.find_node(state).is_none() {
+    return Err(CliError::UnknownState(format!("{:?}", state)));
+  }
+  Ok(graph.extract_reachable_from(state))
+}
+
+/// Writes `graph` to `out` in the DOT language, or only the part that is
+/// reachable from `root` within `max_depth` edges, if they are given. Vertices
+/// are labeled with their game states and data, and edges with their data.
+pub fn write_dot<T, S, A, W>(
+  graph: &Graph<T, S, A>,
+  root: Option<&T>,
+  max_depth: Option<usize>,
+  out: &mut W,
+) -> Result<(), CliError>
+where
+  T: Hash + Eq + Clone + fmt::Debug,
+  S: fmt::Debug,
+  A: fmt::Debug,
+  W: Write,
+{
+  let mut dot = match root {
+    Some(root) => {
+      if graph.find_node(root).is_none() {
+        return Err(CliError::UnknownState(format!("{:?}", root)));
+      }
+      Dot::rooted(root.clone())
+    }
+    None => Dot::new(),
+  };
+  if let Some(max_depth) = max_depth {
+    dot.set_max_depth(max_depth);
+  }
+  dot.write(
+    graph,
+    |state, data| format!("{:?}: {:?}", state, data),
+    |data| format!("{:?}", data),
+    out,
+  )?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod test {
+  use super::{extract, load, parse_state, save, write_dot, CliError, Summary};
+
+  type Graph = crate::Graph<String, u32, char>;
+
+  fn make_graph() -> Graph {
+    let mut g = Graph::new();
+    let s = |s: &str| s.to_string();
+    g.add_edge(s("root"), |_| 0, s("a"), |_| 1, 'x');
+    g.add_edge(s("root"), |_| 0, s("b"), |_| 2, 'y');
+    g.add_edge(s("a"), |_| 1, s("b"), |_| 2, 'z');
+    g.add_node(s("c"), 3);
+    g
+  }
+
+  #[test]
+  fn load_save_ok() {
+    let g = make_graph();
+    let mut saved = Vec::new();
+    save(&g, &mut saved).unwrap();
+    let h: Graph = load(&saved[..]).unwrap();
+    assert_eq!(Summary::of(&g), Summary::of(&h));
+    assert_eq!(
+      "vertices: 4\nedges: 3\nroots: 2\nleaves: 2\nunexpanded: 2\n\
+       partially expanded: 2\nfully expanded: 0\nmax children: 2\naliases: 0\n\
+       unexpanded edges: 0\ncyclic: false\n",
+      Summary::of(&h).to_string()
+    );
+
+    match load::<String, u32, char, _>(&b"{\"config\": "[..]) {
+      Err(CliError::Parse(_)) => (),
+      _ => panic!(),
+    }
+  }
+
+  #[test]
+  fn parse_state_ok() {
+    assert_eq!("a", parse_state::<String>("a").unwrap());
+    assert_eq!("a b", parse_state::<String>("\"a b\"").unwrap());
+    assert_eq!(17, parse_state::<u32>("17").unwrap());
+    assert_eq!(vec![1, 2], parse_state::<Vec<u8>>("[1, 2]").unwrap());
+    assert!(parse_state::<u32>("x").is_err());
+  }
+
+  #[test]
+  fn extract_ok() {
+    let g = make_graph();
+    let sub = extract(&g, &"a".to_string()).unwrap();
+    assert_eq!(2, sub.vertex_count());
+    assert_eq!(1, sub.edge_count());
+    match extract(&g, &"d".to_string()) {
+      Err(CliError::UnknownState(state)) => assert_eq!("\"d\"", state),
+      _ => panic!(),
+    }
+  }
+
+  #[test]
+  fn write_dot_ok() {
+    let g = make_graph();
+    let mut out = Vec::new();
+    write_dot(&g, Some(&"a".to_string()), Some(1), &mut out).unwrap();
+    assert_eq!(
+      "digraph {\n  \
+       n1 [label=\"\\\"a\\\": 1\"];\n  \
+       n2 [label=\"\\\"b\\\": 2\"];\n  \
+       n1 -> n2 [label=\"'z'\"];\n\
+       }\n",
+      String::from_utf8(out).unwrap()
+    );
+    let mut out = Vec::new();
+    assert_eq!(
+      "No vertex for game state \"d\"",
+      write_dot(&g, Some(&"d".to_string()), None, &mut out)
+        .unwrap_err()
+        .to_string()
+    );
+  }
+}
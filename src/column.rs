@@ -0,0 +1,384 @@
+//! Per-vertex and per-edge values that are stored apart from vertex and edge
+//! data.
+//!
+//! Experiments often need one more field on each edge (e.g., a counter or a
+//! flag) that the edge data type `A` does not have.
+//! [Graph::attach_edge_column](../struct.Graph.html#method.attach_edge_column)
+//! adds a dense column of values of any type to a graph, one for each edge,
+//! and returns an [EdgeColumnId](struct.EdgeColumnId.html) through which the
+//! value of an edge is read with
+//! [Edge::column](../nav/struct.Edge.html#method.column) and written with
+//! [MutEdge::column_mut](../mutators/struct.MutEdge.html#method.column_mut).
+//! Columns of values for each vertex are attached in the same way with
+//! [Graph::attach_vertex_column](../struct.Graph.html#method.attach_vertex_column),
+//! and accessed with [Node::column](../nav/struct.Node.html#method.column) and
+//! [MutNode::column_mut](../mutators/struct.MutNode.html#method.column_mut).
+//!
+//! Each value starts as `V::default()`, both for the vertices and edges that
+//! exist when the column is attached and for those added later. Values follow
+//! their vertices and edges through garbage collection, edge removal, and
+//! [partitioning](../partition/index.html), and are dropped with them. They
+//! are not part of vertex or edge data, so they are not compared, exported,
+//! archived, or included in snapshots.
+//!
+//! ```rust
+//! # use search_graph::Graph;
+//! # fn main() {
+//! let mut g: Graph<&str, (), &str> = Graph::new();
+//! g.add_edge("root", |_| (), "a", |_| (), "root_a");
+//! let visits = g.attach_edge_column::<u32>();
+//! g.add_edge("root", |_| (), "b", |_| (), "root_b");
+//! {
+//!   let mut root = g.find_node_mut(&"root").unwrap();
+//!   *root.get_child_list_mut().get_edge_mut(1).column_mut(visits) += 1;
+//! }
+//! let root = g.find_node(&"root").unwrap();
+//! let counts: Vec<u32> = root.get_child_list().iter().map(|e| *e.column(visits)).collect();
+//! assert_eq!(counts, vec![0, 1]);
+//! # }
+//! ```
+
+use std::any::Any;
+use std::fmt;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::mem;
+
+use crate::Graph;
+
+/// Defines a type of column ID, for columns of values of type `V`.
+macro_rules! column_id {
+  ($(#[$attr:meta])* $name:ident) => {
+    $(#[$attr])*
+    pub struct $name<V> {
+      index: usize,
+      _value: PhantomData<fn() -> V>,
+    }
+
+    impl<V> $name<V> {
+      fn new(index: usize) -> Self {
+        $name {
+          index,
+          _value: PhantomData,
+        }
+      }
+    }
+
+    impl<V> Clone for $name<V> {
+      fn clone(&self) -> Self {
+        *self
+      }
+    }
+
+    impl<V> Copy for $name<V> {}
+
+    impl<V> PartialEq for $name<V> {
+      fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+      }
+    }
+
+    impl<V> Eq for $name<V> {}
+
+    impl<V> fmt::Debug for $name<V> {
+      fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}({})", stringify!($name), self.index)
+      }
+    }
+  };
+}
+
+column_id! {
+  /// Identifies a column of per-edge values of type `V` that has been
+  /// attached to a graph.
+  ///
+  /// Column IDs are only meaningful for the graph that returned them. Using
+  /// one with a graph that has no edge column with its ID and type panics.
+  EdgeColumnId
+}
+
+column_id! {
+  /// Identifies a column of per-vertex values of type `V` that has been
+  /// attached to a graph.
+  ///
+  /// Column IDs are only meaningful for the graph that returned them. Using
+  /// one with a graph that has no vertex column with its ID and type panics.
+  VertexColumnId
+}
+
+/// A column of per-vertex or per-edge values whose type has been erased.
+trait Column: Send + Sync {
+  /// Adds a default value for a new item, which must be the next in ID order.
+  fn create(&mut self);
+  /// Moves the value of the last item into the slot of item `index`.
+  fn swap_remove(&mut self, index: usize);
+  /// Moves the value of each item with index `i` to index `f(i)`, dropping
+  /// those of items for which `f` returns `None`.
+  fn compact(&mut self, f: &dyn Fn(usize) -> Option<usize>);
+  /// Moves the value of item `from` of this column to item `to` of `other`,
+  /// which must be a column of the same type.
+  fn move_to(&mut self, from: usize, other: &mut dyn Column, to: usize);
+  /// Returns an empty column of the same type.
+  fn empty_like(&self) -> Box<dyn Column>;
+  /// Returns the number of items.
+  fn len(&self) -> usize;
+  fn as_any(&self) -> &dyn Any;
+  fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<V: Default + Send + Sync + 'static> Column for Vec<V> {
+  fn create(&mut self) {
+    self.push(V::default());
+  }
+
+  fn swap_remove(&mut self, index: usize) {
+    Vec::swap_remove(self, index);
+  }
+
+  fn compact(&mut self, f: &dyn Fn(usize) -> Option<usize>) {
+    crate::mark_compact::permute_compact(self, f);
+  }
+
+  fn move_to(&mut self, from: usize, other: &mut dyn Column, to: usize) {
+    let other = other.as_any_mut().downcast_mut::<Vec<V>>().unwrap();
+    other[to] = mem::take(&mut self[from]);
+  }
+
+  fn empty_like(&self) -> Box<dyn Column> {
+    Box::new(Vec::<V>::new())
+  }
+
+  fn len(&self) -> usize {
+    Vec::len(self)
+  }
+
+  fn as_any(&self) -> &dyn Any {
+    self
+  }
+
+  fn as_any_mut(&mut self) -> &mut dyn Any {
+    self
+  }
+}
+
+/// The vertex or edge columns attached to a graph.
+#[derive(Default)]
+pub(crate) struct Columns {
+  columns: Vec<Box<dyn Column>>,
+}
+
+impl Columns {
+  /// Returns columns of the same types as these, with no values.
+  pub fn empty_like(&self) -> Self {
+    Columns {
+      columns: self.columns.iter().map(|c| c.empty_like()).collect(),
+    }
+  }
+
+  /// Adds a default value to each column for a new vertex or edge, which must
+  /// be the next in ID order.
+  pub fn create(&mut self) {
+    for column in self.columns.iter_mut() {
+      column.create();
+    }
+  }
+
+  /// Moves the value of the last item of each column into the slot of item
+  /// `index`.
+  pub fn swap_remove(&mut self, index: usize) {
+    for column in self.columns.iter_mut() {
+      column.swap_remove(index);
+    }
+  }
+
+  /// Moves the value of each item with index `i` to index `f(i)`, dropping
+  /// those of items for which `f` returns `None`.
+  pub fn compact<F: Fn(usize) -> Option<usize>>(&mut self, f: F) {
+    for column in self.columns.iter_mut() {
+      column.compact(&f);
+    }
+  }
+
+  /// Moves the values of item `from` to item `to` of `other`, which must have
+  /// been created by `empty_like` on these columns.
+  pub fn move_to(&mut self, from: usize, other: &mut Columns, to: usize) {
+    for (column, other) in self.columns.iter_mut().zip(other.columns.iter_mut()) {
+      column.move_to(from, other.as_mut(), to);
+    }
+  }
+
+  /// Returns the number of items in each column.
+  pub fn lens(&self) -> impl Iterator<Item = usize> + '_ {
+    self.columns.iter().map(|c| c.len())
+  }
+
+  /// Adds a column with a default value for each of `len` items, and returns
+  /// its index.
+  fn attach<V: Default + Send + Sync + 'static>(&mut self, len: usize) -> usize {
+    let mut values = Vec::with_capacity(len);
+    values.resize_with(len, V::default);
+    self.columns.push(Box::new(values));
+    self.columns.len() - 1
+  }
+
+  fn get<V: 'static>(&self, index: usize) -> Option<&[V]> {
+    self
+      .columns
+      .get(index)
+      .and_then(|c| c.as_any().downcast_ref::<Vec<V>>())
+      .map(Vec::as_slice)
+  }
+
+  fn get_mut<V: 'static>(&mut self, index: usize) -> Option<&mut [V]> {
+    self
+      .columns
+      .get_mut(index)
+      .and_then(|c| c.as_any_mut().downcast_mut::<Vec<V>>())
+      .map(Vec::as_mut_slice)
+  }
+}
+
+impl<T: Hash + Eq + Clone, S, A> Graph<T, S, A> {
+  /// Attaches a column of values of type `V` to the edges of this graph, as
+  /// described in the [column](column/index.html) module. Each edge starts
+  /// with the value `V::default()`.
+  ///
+  /// Columns are carried over to graphs derived from this one by
+  /// [partition](struct.Graph.html#method.partition), under the same IDs.
+  pub fn attach_edge_column<V: Default + Send + Sync + 'static>(&mut self) -> EdgeColumnId<V> {
+    EdgeColumnId::new(self.edge_columns.attach::<V>(self.arcs.len()))
+  }
+
+  /// Returns the values of the column `id`, indexed by edge ID (as returned
+  /// by `get_id` on edge handles). Panics if this graph has no such column.
+  pub fn edge_column<V: 'static>(&self, id: EdgeColumnId<V>) -> &[V] {
+    self
+      .edge_columns
+      .get(id.index)
+      .expect("no such edge column in this graph")
+  }
+
+  /// Returns the values of the column `id` mutably, indexed by edge ID.
+  /// Panics if this graph has no such column.
+  pub fn edge_column_mut<V: 'static>(&mut self, id: EdgeColumnId<V>) -> &mut [V] {
+    self
+      .edge_columns
+      .get_mut(id.index)
+      .expect("no such edge column in this graph")
+  }
+
+  /// Attaches a column of values of type `V` to the vertices of this graph,
+  /// as described in the [column](column/index.html) module. Each vertex
+  /// starts with the value `V::default()`.
+  ///
+  /// Columns are carried over to graphs derived from this one by
+  /// [partition](struct.Graph.html#method.partition), under the same IDs.
+  pub fn attach_vertex_column<V: Default + Send + Sync + 'static>(&mut self) -> VertexColumnId<V> {
+    VertexColumnId::new(self.vertex_columns.attach::<V>(self.vertices.len()))
+  }
+
+  /// Returns the values of the column `id`, indexed by vertex ID (as returned
+  /// by `get_id` on node handles). Panics if this graph has no such column.
+  pub fn vertex_column<V: 'static>(&self, id: VertexColumnId<V>) -> &[V] {
+    self
+      .vertex_columns
+      .get(id.index)
+      .expect("no such vertex column in this graph")
+  }
+
+  /// Returns the values of the column `id` mutably, indexed by vertex ID.
+  /// Panics if this graph has no such column.
+  pub fn vertex_column_mut<V: 'static>(&mut self, id: VertexColumnId<V>) -> &mut [V] {
+    self
+      .vertex_columns
+      .get_mut(id.index)
+      .expect("no such vertex column in this graph")
+  }
+}
+
+#[cfg(test)]
+mod test {
+  type Graph = crate::Graph<&'static str, &'static str, &'static str>;
+
+  #[test]
+  fn edge_column_ok() {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| "", "a", |_| "", "root_a");
+    g.add_edge("root", |_| "", "b", |_| "", "root_b");
+    let labels = g.attach_edge_column::<String>();
+    let counts = g.attach_edge_column::<u32>();
+    g.add_edge("a", |_| "", "c", |_| "", "a_c");
+    g.add_edge("b", |_| "", "d", |_| "", "b_d");
+    assert_eq!([0, 0, 0, 0], g.edge_column(counts));
+    for (i, count) in g.edge_column_mut(counts).iter_mut().enumerate() {
+      *count = i as u32;
+    }
+    g.find_node_mut(&"b")
+      .unwrap()
+      .get_child_list_mut()
+      .get_edge_mut(0)
+      .column_mut(labels)
+      .push_str("b_d");
+    let a_c = g.find_node(&"a").unwrap().get_child_list().get_edge(0);
+    assert_eq!(2, *a_c.column(counts));
+
+    // Removing an edge moves the last edge into its slot.
+    assert_eq!(1, g.retain_edges(|e| *e.get_data() != "root_a"));
+    let b_d = g.find_node(&"b").unwrap().get_child_list().get_edge(0);
+    assert_eq!("b_d", b_d.column(labels));
+    assert_eq!(3, *b_d.column(counts));
+    assert_eq!(3, g.edge_column(counts).len());
+
+    // Garbage collection keeps values with their edges.
+    g.compact_with(&["b"], &mut []);
+    assert_eq!(["b_d".to_string()], g.edge_column(labels));
+
+    let (with_b, _) = g.partition(|state, _| *state != "root");
+    assert_eq!(["b_d".to_string()], with_b.edge_column(labels));
+    assert_eq!([3], with_b.edge_column(counts));
+  }
+
+  #[test]
+  fn vertex_column_ok() {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| "", "a", |_| "", "root_a");
+    let depths = g.attach_vertex_column::<u32>();
+    g.add_edge("a", |_| "", "b", |_| "", "a_b");
+    g.add_edge("root", |_| "", "c", |_| "", "root_c");
+    assert_eq!([0, 0, 0, 0], g.vertex_column(depths));
+    for (state, depth) in [("a", 1), ("b", 2), ("c", 1)].iter() {
+      *g.find_node_mut(state).unwrap().column_mut(depths) = *depth;
+    }
+    assert_eq!(2, *g.find_node(&"b").unwrap().column(depths));
+
+    // Garbage collection keeps values with their vertices.
+    g.compact_with(&["a"], &mut []);
+    assert_eq!([1, 2], g.vertex_column(depths));
+    g.add_node("d", "");
+    assert_eq!([1, 2, 0], g.vertex_column(depths));
+
+    let (deep, shallow) = g.partition(|state, _| *state == "b");
+    assert_eq!([2], deep.vertex_column(depths));
+    assert_eq!([1, 0], shallow.vertex_column(depths));
+  }
+
+  #[test]
+  #[should_panic(expected = "no such edge column in this graph")]
+  fn edge_column_err() {
+    let mut g = Graph::new();
+    let mut other = Graph::new();
+    let id = other.attach_edge_column::<u32>();
+    g.add_edge("root", |_| "", "a", |_| "", "root_a");
+    g.edge_column(id);
+  }
+  #[test]
+  #[should_panic(expected = "no such vertex column in this graph")]
+  fn vertex_column_err() {
+    let mut g = Graph::new();
+    let id = g.attach_vertex_column::<String>();
+    let mut other = Graph::new();
+    other.attach_vertex_column::<u32>();
+    other.add_node("root", "");
+    other.find_node(&"root").unwrap().column(id);
+  }
+}
@@ -0,0 +1,330 @@
+//! Read-only views of the part of a graph that is reachable from a vertex.
+//!
+//! A [ComponentView](struct.ComponentView.html) is created with
+//! [Graph::component_view](../struct.Graph.html#method.component_view). It
+//! determines which vertices are reachable from a root vertex once, when it is
+//! created, and then provides navigation that is restricted to those vertices
+//! and the edges between them. This gives a cheap logical subgraph without
+//! copying any graph data, and because it only holds a shared borrow of its
+//! graph, it may be shared across threads.
+
+use std::hash::Hash;
+
+use crate::base::{EdgeId, VertexId};
+use crate::nav::{Edge, Node};
+use crate::Graph;
+use symbol_map::SymbolId;
+
+/// A read-only view of the vertices that are reachable from a root vertex, and
+/// the edges between them.
+pub struct ComponentView<'a, T, S, A>
+where
+  T: Hash + Eq + Clone + 'a,
+  S: 'a,
+  A: 'a,
+{
+  graph: &'a Graph<T, S, A>,
+  root: VertexId,
+  /// Indexed by `VertexId`. True iff a vertex is in the component.
+  members: Vec<bool>,
+  vertex_count: usize,
+  edge_count: usize,
+}
+
+impl<T: Hash + Eq + Clone, S, A> Graph<T, S, A> {
+  /// Returns a view of the vertices that are reachable from the vertex for
+  /// `root` by following child edges, or `None` if `root` is not a known game
+  /// state.
+  ///
+  /// ```rust
+  /// # use search_graph::Graph;
+  /// # fn main() {
+  /// let mut g: Graph<u32, (), ()> = Graph::new();
+  /// g.add_edge(0, |_| (), 1, |_| (), ());
+  /// g.add_edge(1, |_| (), 2, |_| (), ());
+  /// let component = g.component_view(&1).unwrap();
+  /// assert_eq!(component.vertex_count(), 2);
+  /// assert!(component.contains(&2));
+  /// assert!(!component.contains(&0));
+  /// // Vertex 1 has a parent in the graph, but not in the component.
+  /// assert!(component.root().is_root());
+  /// # }
+  /// ```
+  pub fn component_view<'s>(&'s self, root: &T) -> Option<ComponentView<'s, T, S, A>> {
+    let root = self.lookup(root)?;
+    let mut members = vec![false; self.vertices.len()];
+    let mut vertex_count = 1;
+    let mut edge_count = 0;
+    members[root.as_usize()] = true;
+    let mut frontier = vec![root];
+    while let Some(id) = frontier.pop() {
+      for edge_id in self.get_vertex(id).children.iter() {
+        edge_count += 1;
+        let target = self.get_arc(*edge_id).target;
+        if !members[target.as_usize()] {
+          members[target.as_usize()] = true;
+          vertex_count += 1;
+          frontier.push(target);
+        }
+      }
+    }
+    Some(ComponentView {
+      graph: self,
+      root,
+      members,
+      vertex_count,
+      edge_count,
+    })
+  }
+}
+
+impl<'a, T, S, A> ComponentView<'a, T, S, A>
+where
+  T: Hash + Eq + Clone + 'a,
+  S: 'a,
+  A: 'a,
+{
+  /// Returns a node handle for the vertex that this component was created
+  /// from.
+  pub fn root<'s>(&'s self) -> ComponentNode<'s, T, S, A> {
+    self.node(self.root)
+  }
+
+  /// Returns a node handle for the given game state, or `None` if it is not
+  /// in this component.
+  pub fn find_node<'s>(&'s self, state: &T) -> Option<ComponentNode<'s, T, S, A>> {
+    let id = self.graph.lookup(state)?;
+    if self.members[id.as_usize()] {
+      Some(self.node(id))
+    } else {
+      None
+    }
+  }
+
+  /// Returns true iff `state` is in this component.
+  pub fn contains(&self, state: &T) -> bool {
+    self.find_node(state).is_some()
+  }
+
+  /// Returns the number of vertices in this component.
+  pub fn vertex_count(&self) -> usize {
+    self.vertex_count
+  }
+
+  /// Returns the number of edges in this component.
+  pub fn edge_count(&self) -> usize {
+    self.edge_count
+  }
+
+  /// Returns the graph that this component is a part of.
+  pub fn graph(&self) -> &'a Graph<T, S, A> {
+    self.graph
+  }
+
+  fn node<'s>(&'s self, id: VertexId) -> ComponentNode<'s, T, S, A> {
+    ComponentNode {
+      graph: self.graph,
+      members: &self.members,
+      id,
+    }
+  }
+}
+
+/// Immutable handle to a vertex in a [ComponentView](struct.ComponentView.html).
+#[derive(Clone, Copy)]
+pub struct ComponentNode<'c, T, S, A>
+where
+  T: Hash + Eq + Clone + 'c,
+  S: 'c,
+  A: 'c,
+{
+  graph: &'c Graph<T, S, A>,
+  members: &'c [bool],
+  id: VertexId,
+}
+
+impl<'c, T, S, A> ComponentNode<'c, T, S, A>
+where
+  T: Hash + Eq + Clone + 'c,
+  S: 'c,
+  A: 'c,
+{
+  /// Returns the canonical label that is used to address this vertex.
+  pub fn get_label(&self) -> &'c T {
+    self.graph.get_state(self.id).unwrap()
+  }
+
+  /// Returns an immutable ID that is guaranteed to identify this vertex
+  /// uniquely within its graph.
+  pub fn get_id(&self) -> usize {
+    self.id.as_usize()
+  }
+
+  /// Returns the data at this vertex.
+  pub fn get_data(&self) -> &'c S {
+    &self.graph.get_vertex(self.id).data
+  }
+
+  /// Returns true iff this vertex has no outgoing edges.
+  pub fn is_leaf(&self) -> bool {
+    self.graph.get_vertex(self.id).children.is_empty()
+  }
+
+  /// Returns true iff this vertex has no incoming edges from within the
+  /// component.
+  pub fn is_root(&self) -> bool {
+    self.parents().next().is_none()
+  }
+
+  /// Returns an iterator over outgoing edges. All outgoing edges of a vertex
+  /// in the component are also in the component.
+  pub fn children(&self) -> impl Iterator<Item = ComponentEdge<'c, T, S, A>> + 'c {
+    let (graph, members) = (self.graph, self.members);
+    self
+      .graph
+      .get_vertex(self.id)
+      .children
+      .iter()
+      .map(move |id| ComponentEdge {
+        graph,
+        members,
+        id: *id,
+      })
+  }
+
+  /// Returns an iterator over incoming edges whose source is in the
+  /// component.
+  ///
+  /// Panics if the graph does not track incoming edges (see
+  /// [GraphConfig](../config/struct.GraphConfig.html)). The same is true of
+  /// `is_root`.
+  pub fn parents(&self) -> impl Iterator<Item = ComponentEdge<'c, T, S, A>> + 'c {
+    let (graph, members) = (self.graph, self.members);
+    self
+      .graph
+      .parents(self.id)
+      .iter()
+      .filter(move |id| members[graph.get_arc(**id).source.as_usize()])
+      .map(move |id| ComponentEdge {
+        graph,
+        members,
+        id: *id,
+      })
+  }
+
+  /// Returns an unrestricted node handle for this vertex, which may be used
+  /// to navigate outside of the component.
+  pub fn to_node(&self) -> Node<'c, T, S, A> {
+    Node::new(self.graph, self.id)
+  }
+}
+
+/// Immutable handle to an edge in a [ComponentView](struct.ComponentView.html).
+#[derive(Clone, Copy)]
+pub struct ComponentEdge<'c, T, S, A>
+where
+  T: Hash + Eq + Clone + 'c,
+  S: 'c,
+  A: 'c,
+{
+  graph: &'c Graph<T, S, A>,
+  members: &'c [bool],
+  id: EdgeId,
+}
+
+impl<'c, T, S, A> ComponentEdge<'c, T, S, A>
+where
+  T: Hash + Eq + Clone + 'c,
+  S: 'c,
+  A: 'c,
+{
+  /// Returns an immutable ID that is guaranteed to identify this edge
+  /// uniquely within its graph.
+  pub fn get_id(&self) -> usize {
+    self.id.as_usize()
+  }
+
+  /// Returns the data at this edge.
+  pub fn get_data(&self) -> &'c A {
+    &self.graph.get_arc(self.id).data
+  }
+
+  /// Returns a node handle for this edge's source vertex.
+  pub fn get_source(&self) -> ComponentNode<'c, T, S, A> {
+    ComponentNode {
+      graph: self.graph,
+      members: self.members,
+      id: self.graph.get_arc(self.id).source,
+    }
+  }
+
+  /// Returns a node handle for this edge's target vertex.
+  pub fn get_target(&self) -> ComponentNode<'c, T, S, A> {
+    ComponentNode {
+      graph: self.graph,
+      members: self.members,
+      id: self.graph.get_arc(self.id).target,
+    }
+  }
+
+  /// Returns an unrestricted edge handle for this edge.
+  pub fn to_edge(&self) -> Edge<'c, T, S, A> {
+    Edge::new(self.graph, self.id)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use crossbeam_utils::thread;
+
+  type Graph = crate::Graph<&'static str, &'static str, &'static str>;
+
+  fn make_graph() -> Graph {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| "root_data", "a", |_| "a_data", "root_a");
+    g.add_edge("root", |_| "root_data", "b", |_| "b_data", "root_b");
+    g.add_edge("a", |_| "a_data", "c", |_| "c_data", "a_c");
+    g.add_edge("b", |_| "b_data", "c", |_| "c_data", "b_c");
+    g.add_edge("c", |_| "c_data", "a", |_| "a_data", "c_a");
+    g
+  }
+
+  #[test]
+  fn component_view_ok() {
+    let g = make_graph();
+    assert!(g.component_view(&"missing").is_none());
+    let component = g.component_view(&"a").unwrap();
+    assert_eq!(2, component.vertex_count());
+    assert_eq!(2, component.edge_count());
+    assert!(component.find_node(&"b").is_none());
+    assert!(component.find_node(&"root").is_none());
+
+    let a = component.root();
+    assert_eq!("a_data", *a.get_data());
+    // "a" has parents "root" and "c", but only "c" is in the component.
+    let parents: Vec<&str> = a.parents().map(|e| *e.get_data()).collect();
+    assert_eq!(vec!["c_a"], parents);
+    assert!(!a.is_root());
+    assert_eq!(2, a.to_node().get_parent_list().len());
+
+    let c = component.find_node(&"c").unwrap();
+    let parents: Vec<&str> = c.parents().map(|e| *e.get_source().get_label()).collect();
+    assert_eq!(vec!["a"], parents);
+    let children: Vec<&str> = c.children().map(|e| *e.get_target().get_label()).collect();
+    assert_eq!(vec!["a"], children);
+  }
+
+  #[test]
+  fn component_view_shared_ok() {
+    let g = make_graph();
+    let component = g.component_view(&"root").unwrap();
+    let component = &component;
+    thread::scope(|s| {
+      let t1 = s.spawn(move |_| component.vertex_count());
+      let t2 = s.spawn(move |_| component.root().children().count());
+      assert_eq!(4, t1.join().unwrap());
+      assert_eq!(2, t2.join().unwrap());
+    })
+    .unwrap();
+  }
+}
@@ -0,0 +1,348 @@
+//! A graph under construction that many threads may extend at once.
+//!
+//! Every mutation of a [Graph](../struct.Graph.html) requires a mutable
+//! borrow, so threads that share one must serialize all of their accesses.
+//! [Stages](../stage/index.html) lift this restriction for insertions, but
+//! threads cannot see each other's staged insertions until they are committed.
+//! A [ConcurrentGraph](struct.ConcurrentGraph.html) instead keeps its vertices
+//! in shards by the hash of their game states, each behind its own lock, so
+//! that threads (e.g., parallel rollouts of a search) may add vertices and
+//! edges, look up transpositions, and update vertex data through a shared
+//! borrow, and only contend when they touch the same shard.
+//!
+//! Each operation locks one shard at a time, so operations cannot deadlock,
+//! and no lock is held while user code other than the closures passed to
+//! [get](struct.ConcurrentGraph.html#method.get) and
+//! [update](struct.ConcurrentGraph.html#method.update) runs. A
+//! `ConcurrentGraph` is converted into a `Graph` with
+//! [into_graph](struct.ConcurrentGraph.html#method.into_graph), or merged into
+//! an existing one with
+//! [merge_into](struct.ConcurrentGraph.html#method.merge_into). Vertices and
+//! edges are added to the `Graph` in the order in which they were added to
+//! the `ConcurrentGraph`, so vertex and edge IDs depend on how threads were
+//! scheduled, but not on how game states were sharded.
+//!
+//! ```rust
+//! # use search_graph::concurrent::ConcurrentGraph;
+//! # fn main() {
+//! let g: ConcurrentGraph<u32, u32, ()> = ConcurrentGraph::new();
+//! g.add_node(0, 0);
+//! std::thread::scope(|s| {
+//!   for t in 0..4u32 {
+//!     let g = &g;
+//!     s.spawn(move || {
+//!       for m in 1..=3 {
+//!         g.add_edge(0, || 0, m, || 0, ());
+//!         g.update(&m, |visits| *visits += t);
+//!       }
+//!     });
+//!   }
+//! });
+//! assert_eq!(g.vertex_count(), 4);
+//! assert_eq!(g.edge_count(), 12);
+//! assert_eq!(g.get(&1, |visits| *visits), Some(6));
+//! let g = g.into_graph();
+//! assert_eq!(g.find_node(&0).unwrap().get_child_list().len(), 12);
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+use crate::moves::hash_key;
+use crate::Graph;
+use symbol_map::indexing::Insertion;
+
+/// The default number of shards.
+const DEFAULT_SHARDS: usize = 64;
+
+/// A vertex of a `ConcurrentGraph`.
+struct Vertex<T, S, A> {
+  /// When the vertex was added, relative to other vertices and edges.
+  seq: u64,
+  data: S,
+  /// The child edges of the vertex, with when they were added and their
+  /// targets.
+  children: Vec<(u64, T, A)>,
+}
+
+/// The vertices whose game states hash to one shard.
+type Shard<T, S, A> = HashMap<T, Vertex<T, S, A>>;
+
+/// A graph that may be extended through a shared borrow, as described in the
+/// [concurrent](index.html) module.
+pub struct ConcurrentGraph<T, S, A> {
+  shards: Vec<Mutex<Shard<T, S, A>>>,
+  /// The sequence number of the next vertex or edge to be added.
+  next_seq: AtomicU64,
+  vertex_count: AtomicU64,
+  edge_count: AtomicU64,
+}
+
+impl<T: Hash + Eq + Clone, S, A> Default for ConcurrentGraph<T, S, A> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<T: Hash + Eq + Clone, S, A> ConcurrentGraph<T, S, A> {
+  /// Creates an empty `ConcurrentGraph` with a default number of shards.
+  pub fn new() -> Self {
+    Self::with_shards(DEFAULT_SHARDS)
+  }
+
+  /// Creates an empty `ConcurrentGraph` with `shards` shards. More shards
+  /// reduce contention between threads at the cost of memory. Panics if
+  /// `shards` is 0.
+  pub fn with_shards(shards: usize) -> Self {
+    assert!(shards > 0, "a concurrent graph needs at least one shard");
+    ConcurrentGraph {
+      shards: (0..shards).map(|_| Mutex::new(HashMap::new())).collect(),
+      next_seq: AtomicU64::new(0),
+      vertex_count: AtomicU64::new(0),
+      edge_count: AtomicU64::new(0),
+    }
+  }
+
+  /// Locks the shard for `state`. A shard whose lock was poisoned by a panic
+  /// on another thread is still used, since each operation leaves its shard
+  /// consistent before running user code.
+  fn shard(&self, state: &T) -> MutexGuard<'_, Shard<T, S, A>> {
+    let index = (hash_key(state) % self.shards.len() as u64) as usize;
+    self.shards[index].lock().unwrap_or_else(|e| e.into_inner())
+  }
+
+  fn next_seq(&self) -> u64 {
+    self.next_seq.fetch_add(1, Ordering::Relaxed)
+  }
+
+  /// Adds a vertex to a locked shard for `state`, if there is none, with the
+  /// data returned by `data`. Returns true iff the vertex was added.
+  fn insert<F: FnOnce() -> S>(&self, shard: &mut Shard<T, S, A>, state: T, data: F) -> bool {
+    if shard.contains_key(&state) {
+      return false;
+    }
+    let vertex = Vertex {
+      seq: self.next_seq(),
+      data: data(),
+      children: Vec::new(),
+    };
+    shard.insert(state, vertex);
+    self.vertex_count.fetch_add(1, Ordering::Relaxed);
+    true
+  }
+
+  /// Adds a vertex for `state` with data `data`, if there is none. Returns
+  /// true iff a vertex was added. If there is already a vertex for `state`,
+  /// `data` is dropped.
+  pub fn add_node(&self, state: T, data: S) -> bool {
+    let mut shard = self.shard(&state);
+    self.insert(&mut shard, state, || data)
+  }
+
+  /// Adds an edge from the vertex for `source` to the vertex for `dest`, with
+  /// data `data`. If there is no vertex for `source` or `dest`, one is added
+  /// with the data returned by `source_data` or `dest_data`, which are called
+  /// with the vertex's shard locked. Parallel edges are kept; they are merged
+  /// as configured by the graph they are eventually added to.
+  pub fn add_edge<F, G>(&self, source: T, source_data: F, dest: T, dest_data: G, data: A)
+  where
+    F: FnOnce() -> S,
+    G: FnOnce() -> S,
+  {
+    {
+      let mut shard = self.shard(&dest);
+      self.insert(&mut shard, dest.clone(), dest_data);
+    }
+    let mut shard = self.shard(&source);
+    self.insert(&mut shard, source.clone(), source_data);
+    let seq = self.next_seq();
+    shard
+      .get_mut(&source)
+      .unwrap()
+      .children
+      .push((seq, dest, data));
+    self.edge_count.fetch_add(1, Ordering::Relaxed);
+  }
+
+  /// Returns true iff there is a vertex for `state`.
+  pub fn contains(&self, state: &T) -> bool {
+    self.shard(state).contains_key(state)
+  }
+
+  /// Calls `f` on the data of the vertex for `state` and returns its result,
+  /// or returns `None` if there is no such vertex. The vertex's shard is
+  /// locked while `f` runs.
+  pub fn get<R, F: FnOnce(&S) -> R>(&self, state: &T, f: F) -> Option<R> {
+    self.shard(state).get(state).map(|v| f(&v.data))
+  }
+
+  /// Calls `f` on the data of the vertex for `state`, mutably, and returns its
+  /// result, or returns `None` if there is no such vertex. The vertex's shard
+  /// is locked while `f` runs.
+  pub fn update<R, F: FnOnce(&mut S) -> R>(&self, state: &T, f: F) -> Option<R> {
+    self.shard(state).get_mut(state).map(|v| f(&mut v.data))
+  }
+
+  /// Returns the game states at the targets of the child edges of the vertex
+  /// for `state`, in the order the edges were added, or `None` if there is no
+  /// such vertex.
+  pub fn children(&self, state: &T) -> Option<Vec<T>> {
+    self
+      .shard(state)
+      .get(state)
+      .map(|v| v.children.iter().map(|(_, t, _)| t.clone()).collect())
+  }
+
+  /// Returns the number of vertices.
+  pub fn vertex_count(&self) -> usize {
+    self.vertex_count.load(Ordering::Relaxed) as usize
+  }
+
+  /// Returns the number of edges.
+  pub fn edge_count(&self) -> usize {
+    self.edge_count.load(Ordering::Relaxed) as usize
+  }
+
+  /// Adds the vertices and edges of this graph to `graph`, in the order in
+  /// which they were added to this graph. Game states that are already in
+  /// `graph` resolve to the existing vertex, and the data of their vertices in
+  /// this graph is dropped, whatever the duplicate policy of `graph` (see
+  /// [Graph::set_duplicate_policy](../struct.Graph.html#method.set_duplicate_policy)).
+  /// Automatic pruning of `graph` does not run during the merge.
+  pub fn merge_into(self, graph: &mut Graph<T, S, A>) {
+    let mut vertices = Vec::with_capacity(self.vertex_count());
+    let mut edges = Vec::with_capacity(self.edge_count());
+    for shard in self.shards {
+      let shard = shard.into_inner().unwrap_or_else(|e| e.into_inner());
+      for (state, vertex) in shard {
+        for (seq, dest, data) in vertex.children {
+          edges.push((seq, state.clone(), dest, data));
+        }
+        vertices.push((vertex.seq, state, vertex.data));
+      }
+    }
+    vertices.sort_by_key(|v| v.0);
+    edges.sort_by_key(|e| e.0);
+    let mut ids = HashMap::with_capacity(vertices.len());
+    for (_, state, data) in vertices {
+      let id = match graph.intern(state.clone()) {
+        Insertion::Present(id) => id,
+        Insertion::New(id) => {
+          graph.add_raw_vertex(data);
+          id
+        }
+      };
+      ids.insert(state, id);
+    }
+    for (_, source, dest, data) in edges {
+      graph.add_raw_edge(data, ids[&source], ids[&dest]);
+    }
+  }
+
+  /// Returns a `Graph` with the vertices and edges of this graph, added in the
+  /// order in which they were added to this graph.
+  pub fn into_graph(self) -> Graph<T, S, A> {
+    let mut graph = Graph::new();
+    self.merge_into(&mut graph);
+    graph
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::ConcurrentGraph;
+
+  type Graph = crate::Graph<&'static str, u32, &'static str>;
+
+  #[test]
+  fn concurrent_graph_ok() {
+    let g = ConcurrentGraph::with_shards(2);
+    assert!(g.add_node("root", 1));
+    assert!(!g.add_node("root", 2));
+    g.add_edge("root", || 0, "a", || 3, "root_a");
+    g.add_edge("b", || 4, "a", || 0, "b_a");
+    g.add_edge("root", || 0, "b", || 0, "root_b");
+    assert!(g.contains(&"b"));
+    assert!(!g.contains(&"c"));
+    assert_eq!(Some(1), g.get(&"root", |data| *data));
+    assert_eq!(
+      Some(5),
+      g.update(&"b", |data| {
+        *data += 1;
+        *data
+      })
+    );
+    assert_eq!(None, g.update(&"c", |data| *data));
+    assert_eq!(Some(vec!["a", "b"]), g.children(&"root"));
+    assert_eq!(Some(vec![]), g.children(&"a"));
+    assert_eq!(3, g.vertex_count());
+    assert_eq!(3, g.edge_count());
+
+    let g = g.into_graph();
+    let states: Vec<_> = g.states().cloned().collect();
+    assert_eq!(vec!["root", "a", "b"], states);
+    let data: Vec<_> = g.iter().map(|(_, data)| *data).collect();
+    assert_eq!(vec![1, 3, 5], data);
+    let root = g.find_node(&"root").unwrap();
+    let edges: Vec<_> = root
+      .get_child_list()
+      .iter()
+      .map(|e| *e.get_data())
+      .collect();
+    assert_eq!(vec!["root_a", "root_b"], edges);
+    assert_eq!(1, g.find_node(&"b").unwrap().get_child_list().len());
+  }
+
+  #[test]
+  fn concurrent_graph_merge_ok() {
+    let mut base = Graph::new();
+    base.add_edge("root", |_| 1, "a", |_| 2, "root_a");
+    let g = ConcurrentGraph::new();
+    std::thread::scope(|s| {
+      for t in 0..8 {
+        let g = &g;
+        s.spawn(move || {
+          for i in 0..100 {
+            let state = ["root", "a", "b", "c"][(t + i) % 4];
+            g.add_edge("root", || 0, state, || 0, "edge");
+            g.update(&state, |visits| *visits += 1);
+          }
+        });
+      }
+    });
+    assert_eq!(4, g.vertex_count());
+    assert_eq!(800, g.edge_count());
+    let visits: u32 = ["root", "a", "b", "c"]
+      .iter()
+      .map(|s| g.get(s, |v| *v).unwrap())
+      .sum();
+    assert_eq!(800, visits);
+    g.merge_into(&mut base);
+    assert_eq!(4, base.vertex_count());
+    assert_eq!(801, base.edge_count());
+    assert_eq!(1, *base.find_node(&"root").unwrap().get_data());
+    assert_eq!(200, *base.find_node(&"b").unwrap().get_data());
+  }
+
+  #[test]
+  fn merge_auto_prune_ok() {
+    use crate::duplicate::DuplicatePolicy;
+    use crate::prune::{AutoPrune, KeepDeepest};
+
+    let mut base = Graph::new();
+    base.add_edge("root", |_| 1, "a", |_| 2, "root_a");
+    base.set_auto_prune(Some(AutoPrune::new(2, vec!["root"], KeepDeepest(1))));
+    base.set_duplicate_policy(DuplicatePolicy::Error);
+    let g = ConcurrentGraph::new();
+    g.add_edge("a", || 0, "b", || 3, "a_b");
+    g.add_edge("b", || 0, "c", || 4, "b_c");
+    g.merge_into(&mut base);
+    crate::assert_graph!(base,
+      vertices: ["root" => 1, "a" => 2, "b" => 3, "c" => 4],
+      edges: ["root" => "a" => "root_a", "a" => "b" => "a_b", "b" => "c" => "b_c"]);
+    assert!(crate::testing::invariant_violations(&base).is_empty());
+  }
+}
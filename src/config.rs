@@ -0,0 +1,451 @@
+//! Options that are fixed when a graph is created.
+//!
+//! A [Graph](../struct.Graph.html) created with
+//! [Graph::with_config](../struct.Graph.html#method.with_config) behaves
+//! according to a [GraphConfig](struct.GraphConfig.html). The default
+//! configuration, which `Graph::new` uses, supports every operation and
+//! behaves as graphs always have.
+//!
+//! Options that depend on user-supplied code are set on the graph instead:
+//! automatic pruning requires a
+//! [PrunePolicy](../prune/trait.PrunePolicy.html) and is configured with
+//! [Graph::set_auto_prune](../struct.Graph.html#method.set_auto_prune), and
+//! child indices require a key function. The hashing of game states is fixed.
+//!
+//! Searches that only ever move from vertices to their children may disable
+//! parent tracking, so that inserting an edge does not also record it at its
+//! target vertex. Operations that traverse edges in reverse are unavailable in
+//! that mode: fallible ones (e.g.,
+//! [Node::try_get_parent_list](../nav/struct.Node.html#method.try_get_parent_list))
+//! return a [ParentsUntracked](struct.ParentsUntracked.html) error, and the
+//! rest panic.
+//!
+//! Graphs that track parents may also keep each parent list sorted by source
+//! vertex, so that checking whether one vertex is a parent of another does
+//! not scan the parent list. Since each source then occupies a contiguous run
+//! of its target's parent list, graphs that also merge parallel edges list
+//! each parent exactly once.
+//!
+//! ```rust
+//! # use search_graph::Graph;
+//! # use search_graph::config::{GraphConfig, ParentsUntracked};
+//! # fn main() {
+//! let mut g: Graph<&str, (), ()> = Graph::with_config(GraphConfig {
+//!   track_parents: false,
+//!   ..Default::default()
+//! });
+//! g.add_edge("root", |_| (), "a", |_| (), ());
+//! let a = g.find_node(&"a").unwrap();
+//! assert_eq!(a.get_child_list().len(), 0);
+//! assert_eq!(a.try_get_parent_list().err(), Some(ParentsUntracked));
+//! # }
+//! ```
+
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+
+use crate::base::{EdgeId, VertexId};
+use crate::Graph;
+
+/// What happens when an edge is added between two vertices that are already
+/// joined by an edge in the same direction.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ParallelEdges {
+  /// A new edge is added alongside the existing one. This is the default.
+  #[default]
+  Allow,
+  /// No edge is added. The data for the new edge is dropped, and the first
+  /// existing edge between the two vertices is returned in its place. Finding
+  /// the existing edge scans the child list of the source vertex unless an
+  /// index has been built with
+  /// [Graph::index_edges](../struct.Graph.html#method.index_edges).
+  Merge,
+}
+
+/// Options for a [Graph](../struct.Graph.html).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(
+  feature = "serde",
+  derive(serde::Serialize, serde::Deserialize),
+  serde(default)
+)]
+pub struct GraphConfig {
+  /// Whether each vertex records its incoming edges. Defaults to true.
+  pub track_parents: bool,
+  /// Whether the parent list of each vertex is kept sorted by source vertex
+  /// ID, and then by edge ID. This makes
+  /// [Node::has_parent](../nav/struct.Node.html#method.has_parent) a binary
+  /// search, at the cost of inserting each edge into the middle of its target's
+  /// parent list and of re-sorting parent lists when IDs are reassigned.
+  /// Parallel edges are not removed, so a source vertex may occur several
+  /// times in a row; with `parallel_edges` set to `ParallelEdges::Merge`,
+  /// each occurs once. Has no effect unless `track_parents` is true. Defaults
+  /// to false.
+  pub sorted_parents: bool,
+  /// How edges that parallel existing edges are added. Defaults to
+  /// `ParallelEdges::Allow`.
+  pub parallel_edges: ParallelEdges,
+  /// Whether transposition table statistics are collected from the start, as
+  /// with [Graph::set_track_interning](../struct.Graph.html#method.set_track_interning).
+  /// Defaults to false.
+  pub track_interning: bool,
+  /// Whether the tick at which each vertex was created and at which its data
+  /// was last modified are recorded, as described by
+  /// [Recency](../stats/struct.Recency.html). Defaults to false.
+  pub track_recency: bool,
+  /// The number of atomic words of hot data that are stored for each vertex,
+  /// as described in [hot](../hot/index.html). Defaults to 0.
+  pub hot_words: usize,
+  /// Whether each vertex is assigned a stable ID derived from its game state,
+  /// as described in [stable](../stable/index.html). Defaults to false.
+  pub stable_ids: bool,
+  /// The number of low bits of hash keys that keyed game states are
+  /// restricted to, as with
+  /// [Graph::set_key_bits](../struct.Graph.html#method.set_key_bits). Must be
+  /// in `1..=64`. Defaults to 64.
+  #[cfg(feature = "hashkeys")]
+  pub key_bits: u32,
+}
+
+impl Default for GraphConfig {
+  fn default() -> Self {
+    GraphConfig {
+      track_parents: true,
+      sorted_parents: false,
+      parallel_edges: ParallelEdges::Allow,
+      track_interning: false,
+      track_recency: false,
+      hot_words: 0,
+      stable_ids: false,
+      #[cfg(feature = "hashkeys")]
+      key_bits: 64,
+    }
+  }
+}
+
+/// The error returned when incoming edges are requested from a graph that
+/// does not track them.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ParentsUntracked;
+
+impl fmt::Display for ParentsUntracked {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "Graph does not track parent edges")
+  }
+}
+
+impl Error for ParentsUntracked {}
+
+impl<T: Hash + Eq + Clone, S, A> Graph<T, S, A> {
+  /// Creates an empty `Graph` with no vertices or edges that behaves according
+  /// to `config`.
+  ///
+  /// Panics if `config` is invalid.
+  pub fn with_config(config: GraphConfig) -> Self {
+    let mut graph = Graph::new();
+    graph.config = config;
+    graph.set_track_interning(config.track_interning);
+    if config.track_recency {
+      graph.recency = Some(Default::default());
+    }
+    graph.hot = crate::hot::HotTable::new(config.hot_words);
+    if config.stable_ids {
+      graph.stable = Some(Default::default());
+    }
+    #[cfg(feature = "hashkeys")]
+    graph.keyed.set_key_bits(config.key_bits);
+    graph
+  }
+
+  /// Returns the options this graph was created with.
+  pub fn config(&self) -> &GraphConfig {
+    &self.config
+  }
+
+  /// Returns the incoming edges of the vertex with the given ID, or an error
+  /// if they are not tracked.
+  pub(crate) fn try_parents(&self, id: VertexId) -> Result<&[EdgeId], ParentsUntracked> {
+    if self.config.track_parents {
+      Ok(&self.get_vertex(id).parents)
+    } else {
+      Err(ParentsUntracked)
+    }
+  }
+
+  /// Returns the first edge from `source` to `target`, if any.
+  pub(crate) fn find_parallel(&self, source: VertexId, target: VertexId) -> Option<EdgeId> {
+    if let Some(ref index) = self.edge_index {
+      return index.get(source, target);
+    }
+    self
+      .get_vertex(source)
+      .children
+      .iter()
+      .find(|id| self.get_arc(**id).target == target)
+      .cloned()
+  }
+
+  /// Returns true iff there is an edge from `source` to `target`, using the
+  /// fastest means available.
+  pub(crate) fn has_edge(&self, source: VertexId, target: VertexId) -> bool {
+    if let Some(ref index) = self.edge_index {
+      return index.get(source, target).is_some();
+    }
+    let children = &self.get_vertex(source).children;
+    if !self.config.track_parents {
+      return children.iter().any(|c| self.get_arc(*c).target == target);
+    }
+    let parents = &self.get_vertex(target).parents;
+    if self.config.sorted_parents {
+      parents
+        .binary_search_by_key(&source, |p| self.get_arc(*p).source)
+        .is_ok()
+    } else if parents.len() < children.len() {
+      parents.iter().any(|p| self.get_arc(*p).source == source)
+    } else {
+      children.iter().any(|c| self.get_arc(*c).target == target)
+    }
+  }
+
+  /// Returns the position in the parent list of `target` at which to insert
+  /// a new edge from `source`, which follows all existing edges.
+  pub(crate) fn parent_insertion_point(&self, source: VertexId, target: VertexId) -> usize {
+    let parents = &self.get_vertex(target).parents;
+    if self.config.sorted_parents {
+      parents.partition_point(|p| self.get_arc(*p).source <= source)
+    } else {
+      parents.len()
+    }
+  }
+
+  /// Restores the order of the parent list of the vertex with the given ID,
+  /// if the graph keeps parent lists sorted.
+  pub(crate) fn sort_parents_of(&mut self, id: VertexId) {
+    if !(self.config.track_parents && self.config.sorted_parents) {
+      return;
+    }
+    let mut parents = std::mem::take(&mut self.get_vertex_mut(id).parents);
+    parents.sort_unstable_by_key(|p| (self.get_arc(*p).source, *p));
+    self.get_vertex_mut(id).parents = parents;
+  }
+
+  /// Restores the order of all parent lists, if the graph keeps them sorted.
+  pub(crate) fn sort_parents(&mut self) {
+    if self.config.track_parents && self.config.sorted_parents {
+      for i in 0..self.vertices.len() {
+        self.sort_parents_of(VertexId(i));
+      }
+    }
+  }
+
+  /// As `try_parents`, but panics if incoming edges are not tracked.
+  pub(crate) fn parents(&self, id: VertexId) -> &[EdgeId] {
+    match self.try_parents(id) {
+      Ok(parents) => parents,
+      Err(e) => panic!("{}", e),
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::{GraphConfig, ParallelEdges, ParentsUntracked};
+  use crate::search::{SearchError, Stack, Traversal};
+  use std::fmt;
+
+  type Graph = crate::Graph<&'static str, &'static str, &'static str>;
+
+  fn forward_only() -> Graph {
+    let mut g = Graph::with_config(GraphConfig {
+      track_parents: false,
+      ..Default::default()
+    });
+    g.add_edge("root", |_| "root_data", "a", |_| "a_data", "root_a");
+    g.add_edge("a", |_| "a_data", "b", |_| "b_data", "a_b");
+    g
+  }
+
+  #[derive(Debug)]
+  struct MockError;
+
+  impl fmt::Display for MockError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      write!(f, "mock")
+    }
+  }
+
+  impl std::error::Error for MockError {}
+
+  #[test]
+  fn default_config_ok() {
+    let g = Graph::new();
+    assert!(g.config().track_parents);
+    assert_eq!(GraphConfig::default(), *g.config());
+  }
+
+  #[test]
+  fn with_config_ok() {
+    let mut g = Graph::with_config(GraphConfig {
+      parallel_edges: ParallelEdges::Merge,
+      track_interning: true,
+      ..Default::default()
+    });
+    g.add_edge("root", |_| "root_data", "a", |_| "a_data", "root_a");
+    let id = g.add_edge("root", |_| "", "a", |_| "", "root_a2").get_id();
+    assert_eq!(0, id);
+    g.add_edge("a", |_| "", "root", |_| "", "a_root");
+    assert_eq!(2, g.edge_count());
+    assert_eq!(
+      "root_a",
+      *g.find_node(&"a")
+        .unwrap()
+        .get_parent_list()
+        .get_edge(0)
+        .get_data()
+    );
+    {
+      let mut a = g.find_node_mut(&"a").unwrap();
+      let mut children = a.get_child_list_mut();
+      children.add_unexpanded_child("a_root2");
+      children.to_unexpanded(0).unwrap().expand("root", || "");
+    }
+    assert_eq!(2, g.edge_count());
+    assert_eq!(5, g.interning_stats().unwrap().hits);
+    let (_, rest) = g.partition(|_, _| false);
+    assert_eq!(ParallelEdges::Merge, rest.config().parallel_edges);
+    assert!(rest.interning_stats().is_some());
+  }
+
+  #[test]
+  fn forward_only_ok() {
+    let mut g = forward_only();
+    assert!(!g.config().track_parents);
+    let a = g.find_node(&"a").unwrap();
+    assert_eq!(1, a.get_child_list().len());
+    assert_eq!(
+      "b",
+      *a.get_child_list().get_edge(0).get_target().get_label()
+    );
+    let mut s = Stack::new(g.find_node_mut(&"root").unwrap());
+    assert!(s
+      .push(|_| Ok::<_, MockError>(Some(Traversal::Child(0))))
+      .is_ok());
+    assert_eq!("a", *s.head().get_label());
+    assert!(s.peek_parent(0).is_none());
+    g.compact_with(&["a"], &mut []);
+    assert_eq!(2, g.vertex_count());
+    let config = *g.config();
+    let (solved, _) = g.partition(|state, _| *state == "b");
+    assert_eq!(config, *solved.config());
+  }
+
+  #[test]
+  fn forward_only_err() {
+    let mut g = forward_only();
+    assert_eq!(
+      Some(ParentsUntracked),
+      g.find_node(&"a").unwrap().try_get_parent_list().err()
+    );
+    assert_eq!(
+      Some(ParentsUntracked),
+      g.find_node(&"a").unwrap().try_is_root().err()
+    );
+    assert!(g
+      .find_node_mut(&"a")
+      .unwrap()
+      .try_get_parent_list_mut()
+      .is_err());
+    let mut s = Stack::new(g.find_node_mut(&"a").unwrap());
+    match s.push(|_| Ok::<_, MockError>(Some(Traversal::Parent(0)))) {
+      Err(SearchError::ParentsUntracked) => (),
+      _ => panic!(),
+    }
+  }
+
+  #[test]
+  #[should_panic(expected = "Graph does not track parent edges")]
+  fn forward_only_parent_list_err() {
+    let g = forward_only();
+    g.find_node(&"a").unwrap().get_parent_list();
+  }
+
+  fn parent_labels(g: &Graph, state: &'static str) -> Vec<&'static str> {
+    g.find_node(&state)
+      .unwrap()
+      .get_parent_list()
+      .iter()
+      .map(|e| *e.get_source().get_label())
+      .collect()
+  }
+
+  #[test]
+  fn sorted_parents_ok() {
+    let mut g = Graph::with_config(GraphConfig {
+      sorted_parents: true,
+      ..Default::default()
+    });
+    g.add_edge("y", |_| "", "t", |_| "", "y_t");
+    g.add_edge("z", |_| "", "t", |_| "", "z_t");
+    g.add_edge("x", |_| "", "t", |_| "", "x_t");
+    g.add_edge("y", |_| "", "t", |_| "", "y_t2");
+    g.add_edge("t", |_| "", "t", |_| "", "t_t");
+    assert_eq!(vec!["y", "y", "t", "z", "x"], parent_labels(&g, "t"));
+    let t = g.find_node(&"t").unwrap();
+    for parent in ["x", "y", "z", "t"] {
+      assert!(t.has_parent(&g.find_node(&parent).unwrap()));
+    }
+    assert!(!g.find_node(&"x").unwrap().has_parent(&t));
+
+    // Removing edges renumbers them.
+    assert_eq!(1, g.retain_edges(|e| *e.get_data() != "y_t"));
+    let edges: Vec<_> = g
+      .find_node(&"t")
+      .unwrap()
+      .get_parent_list()
+      .iter()
+      .map(|e| *e.get_data())
+      .collect();
+    assert_eq!(vec!["y_t2", "t_t", "z_t", "x_t"], edges);
+
+    // Collecting garbage renumbers vertices in the order they are marked.
+    g.compact_with(&["x", "z", "y"], &mut []);
+    assert_eq!(vec!["x", "z", "y", "t"], parent_labels(&g, "t"));
+    let t = g.find_node(&"t").unwrap();
+    let sources: Vec<_> = t
+      .get_parent_list()
+      .iter()
+      .map(|e| e.get_source().get_id())
+      .collect();
+    assert!(sources.windows(2).all(|w| w[0] <= w[1]));
+    assert!(!g.find_node(&"y").unwrap().has_parent(&t));
+  }
+
+  #[test]
+  fn sorted_merged_parents_ok() {
+    let mut g = Graph::with_config(GraphConfig {
+      sorted_parents: true,
+      parallel_edges: ParallelEdges::Merge,
+      ..Default::default()
+    });
+    g.add_edge("y", |_| "", "t", |_| "", "y_t");
+    g.add_edge("x", |_| "", "t", |_| "", "x_t");
+    g.add_edge("y", |_| "", "t", |_| "", "y_t2");
+    g.add_edge("x", |_| "", "t", |_| "", "x_t2");
+    assert_eq!(vec!["y", "x"], parent_labels(&g, "t"));
+  }
+
+  #[test]
+  fn unsorted_parents_ok() {
+    let mut g = Graph::new();
+    g.add_edge("y", |_| "", "t", |_| "", "y_t");
+    g.add_edge("x", |_| "", "t", |_| "", "x_t");
+    g.add_edge("t", |_| "", "t", |_| "", "t_t");
+    assert_eq!(vec!["y", "x", "t"], parent_labels(&g, "t"));
+    let t = g.find_node(&"t").unwrap();
+    assert!(t.has_parent(&g.find_node(&"x").unwrap()));
+    assert!(t.has_parent(&t));
+    assert!(!g.find_node(&"x").unwrap().has_parent(&t));
+  }
+}
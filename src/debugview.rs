@@ -0,0 +1,285 @@
+//! Text rendering of graphs for debugging live searches.
+//!
+//! A [DebugView](struct.DebugView.html) renders a depth-limited tree view of
+//! the part of a graph that is reachable from a chosen game state. Vertices
+//! that are reached more than once (transpositions) are shown once in full and
+//! marked at their other occurrences, so that the view stays finite even when
+//! the graph has cycles.
+//!
+//! A `DebugView` remembers the shape of the graph at its last render, along
+//! with a count of the graph's modifications, so that a search loop can
+//! cheaply call [refresh](struct.DebugView.html#method.refresh) to redraw a
+//! terminal only when the graph has changed. Any mutable borrow of vertex or
+//! edge data counts as a modification, whether or not the data changed.
+//!
+//! This module is only available when the `debugview` feature is enabled.
+//!
+//! ```rust
+//! # use search_graph::Graph;
+//! # use search_graph::debugview::DebugView;
+//! # fn main() {
+//! let mut g: Graph<&str, u32, char> = Graph::new();
+//! g.add_edge("root", |_| 2, "a", |_| 1, 'x');
+//! g.add_edge("root", |_| 2, "b", |_| 1, 'y');
+//! g.add_edge("a", |_| 1, "b", |_| 1, 'z');
+//! let mut view = DebugView::new("root");
+//! let text = view.render(&g, |s, d| format!("{} ({})", s, d), |m| m.to_string());
+//! assert_eq!(
+//!   text,
+//!   "root (2)\n\
+//!    ├─ x → a (1)\n\
+//!    │  └─ z → b (1)\n\
+//!    └─ y → b (1) [seen]\n"
+//! );
+//! # }
+//! ```
+
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::io::{self, Write};
+
+use crate::nav::Node;
+use crate::Graph;
+
+/// The state of a graph that determines whether a view of it is stale.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Shape {
+  version: u64,
+  modifications: u64,
+  vertices: usize,
+  edges: usize,
+}
+
+impl Shape {
+  fn of<T: Hash + Eq + Clone, S, A>(graph: &Graph<T, S, A>) -> Self {
+    Shape {
+      version: graph.version,
+      modifications: graph.modifications,
+      vertices: graph.vertices.len(),
+      edges: graph.arcs.len(),
+    }
+  }
+}
+
+/// Renders a depth-limited view of a graph, rooted at a chosen game state.
+pub struct DebugView<T> {
+  root: T,
+  max_depth: usize,
+  max_children: usize,
+  last_shape: Option<Shape>,
+}
+
+impl<T: Hash + Eq + Clone> DebugView<T> {
+  /// Creates a view rooted at `root` that shows vertices up to 3 edges away
+  /// from it and up to 16 children per vertex.
+  pub fn new(root: T) -> Self {
+    DebugView {
+      root,
+      max_depth: 3,
+      max_children: 16,
+      last_shape: None,
+    }
+  }
+
+  /// Sets the maximum number of edges between the root and a vertex that is
+  /// shown.
+  pub fn set_max_depth(&mut self, max_depth: usize) {
+    self.max_depth = max_depth;
+    self.last_shape = None;
+  }
+
+  /// Sets the maximum number of children that are shown for each vertex.
+  pub fn set_max_children(&mut self, max_children: usize) {
+    self.max_children = max_children;
+    self.last_shape = None;
+  }
+
+  /// Changes the game state that the view is rooted at.
+  pub fn set_root(&mut self, root: T) {
+    self.root = root;
+    self.last_shape = None;
+  }
+
+  /// Returns true iff `graph` may have changed since the last call to
+  /// `render` or `refresh`.
+  pub fn is_stale<S, A>(&self, graph: &Graph<T, S, A>) -> bool {
+    self.last_shape != Some(Shape::of(graph))
+  }
+
+  /// Renders a view of `graph`, with one line per edge. Vertices are described
+  /// by `vertex` and edges by `edge`.
+  pub fn render<S, A, F, G>(&mut self, graph: &Graph<T, S, A>, vertex: F, edge: G) -> String
+  where
+    F: Fn(&T, &S) -> String,
+    G: Fn(&A) -> String,
+  {
+    self.last_shape = Some(Shape::of(graph));
+    let root = match graph.find_node(&self.root) {
+      Some(root) => root,
+      None => return "(root not found)\n".to_string(),
+    };
+    let mut out = vertex(root.get_label(), root.get_data());
+    out.push('\n');
+    let mut seen = HashSet::new();
+    seen.insert(root.get_id());
+    let mut prefix = String::new();
+    self.render_children(root, 1, &mut prefix, &mut seen, &vertex, &edge, &mut out);
+    out
+  }
+
+  #[allow(clippy::too_many_arguments)]
+  fn render_children<S, A, F, G>(
+    &self,
+    node: Node<T, S, A>,
+    depth: usize,
+    prefix: &mut String,
+    seen: &mut HashSet<usize>,
+    vertex: &F,
+    edge: &G,
+    out: &mut String,
+  ) where
+    F: Fn(&T, &S) -> String,
+    G: Fn(&A) -> String,
+  {
+    let children = node.get_child_list();
+    if children.is_empty() {
+      return;
+    }
+    if depth > self.max_depth {
+      out.push_str(&format!("{}└─ … ({} children)\n", prefix, children.len()));
+      return;
+    }
+    let shown = children.len().min(self.max_children);
+    let elided = children.len() - shown;
+    for (i, e) in children.iter().take(shown).enumerate() {
+      let last = i + 1 == shown && elided == 0;
+      let target = e.get_target();
+      let first_visit = seen.insert(target.get_id());
+      out.push_str(&format!(
+        "{}{}{} → {}{}\n",
+        prefix,
+        if last { "└─ " } else { "├─ " },
+        edge(e.get_data()),
+        vertex(target.get_label(), target.get_data()),
+        if first_visit { "" } else { " [seen]" }
+      ));
+      if first_visit {
+        let len = prefix.len();
+        prefix.push_str(if last { "   " } else { "│  " });
+        self.render_children(target, depth + 1, prefix, seen, vertex, edge, out);
+        prefix.truncate(len);
+      }
+    }
+    if elided > 0 {
+      out.push_str(&format!("{}└─ … ({} more)\n", prefix, elided));
+    }
+  }
+
+  /// If `graph` has changed since it was last rendered, clears the terminal
+  /// that `out` writes to and draws a new view of `graph` on it. Returns true
+  /// iff the view was redrawn.
+  ///
+  /// This is intended to be called periodically from a search loop.
+  pub fn refresh<S, A, F, G, W>(
+    &mut self,
+    graph: &Graph<T, S, A>,
+    vertex: F,
+    edge: G,
+    out: &mut W,
+  ) -> io::Result<bool>
+  where
+    F: Fn(&T, &S) -> String,
+    G: Fn(&A) -> String,
+    W: Write,
+  {
+    if !self.is_stale(graph) {
+      return Ok(false);
+    }
+    let text = self.render(graph, vertex, edge);
+    // Clear the screen and move the cursor to the top left corner.
+    out.write_all(b"\x1b[2J\x1b[H")?;
+    out.write_all(text.as_bytes())?;
+    out.flush()?;
+    Ok(true)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::DebugView;
+
+  type Graph = crate::Graph<&'static str, &'static str, &'static str>;
+
+  fn render(view: &mut DebugView<&'static str>, g: &Graph) -> String {
+    view.render(g, |s, _| s.to_string(), |a| a.to_string())
+  }
+
+  #[test]
+  fn render_depth_limit_ok() {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| "", "a", |_| "", "root_a");
+    g.add_edge("a", |_| "", "b", |_| "", "a_b");
+    g.add_edge("b", |_| "", "root", |_| "", "b_root");
+    let mut view = DebugView::new("root");
+    assert_eq!(
+      "root\n└─ root_a → a\n   └─ a_b → b\n      └─ b_root → root [seen]\n",
+      render(&mut view, &g)
+    );
+    view.set_max_depth(1);
+    assert_eq!(
+      "root\n└─ root_a → a\n   └─ … (1 children)\n",
+      render(&mut view, &g)
+    );
+  }
+
+  #[test]
+  fn render_max_children_ok() {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| "", "a", |_| "", "root_a");
+    g.add_edge("root", |_| "", "b", |_| "", "root_b");
+    g.add_edge("root", |_| "", "c", |_| "", "root_c");
+    let mut view = DebugView::new("root");
+    view.set_max_children(2);
+    assert_eq!(
+      "root\n├─ root_a → a\n├─ root_b → b\n└─ … (1 more)\n",
+      render(&mut view, &g)
+    );
+    view.set_root("missing");
+    assert_eq!("(root not found)\n", render(&mut view, &g));
+  }
+
+  #[test]
+  fn refresh_ok() {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| "", "a", |_| "", "root_a");
+    let mut view = DebugView::new("root");
+    let mut out = Vec::new();
+    assert!(view
+      .refresh(&g, |s, _| s.to_string(), |a| a.to_string(), &mut out)
+      .unwrap());
+    assert!(String::from_utf8(out.clone())
+      .unwrap()
+      .ends_with("root\n└─ root_a → a\n"));
+    assert!(!view.is_stale(&g));
+    assert!(!view
+      .refresh(&g, |s, _| s.to_string(), |a| a.to_string(), &mut out)
+      .unwrap());
+    g.add_edge("a", |_| "", "b", |_| "", "a_b");
+    assert!(view.is_stale(&g));
+    assert!(view
+      .refresh(&g, |s, _| s.to_string(), |a| a.to_string(), &mut out)
+      .unwrap());
+
+    // Changes to data alone make the view stale.
+    *g.find_node_mut(&"b").unwrap().get_data_mut() = "b_data";
+    assert!(view.is_stale(&g));
+    render(&mut view, &g);
+    assert!(!view.is_stale(&g));
+    *g.find_node_mut(&"a")
+      .unwrap()
+      .get_child_list_mut()
+      .get_edge_mut(0)
+      .get_data_mut() = "a_b2";
+    assert!(view.is_stale(&g));
+  }
+}
@@ -0,0 +1,231 @@
+//! Policies for insertions of known game states with different data.
+//!
+//! [Graph::add_node](../struct.Graph.html#method.add_node) returns the
+//! existing vertex when its game state is already in the graph. By default,
+//! the data it was given is dropped, even if it differs from the data of the
+//! existing vertex. A [DuplicatePolicy](enum.DuplicatePolicy.html) set with
+//! [Graph::set_duplicate_policy](../struct.Graph.html#method.set_duplicate_policy)
+//! instead replaces the existing data, merges the new data into it, or reports
+//! an error, which makes mistakes in initialization code visible.
+//!
+//! The policy applies only when the data differ, as determined by
+//! `PartialEq`. It applies to `add_node`,
+//! [Graph::try_add_node](../struct.Graph.html#method.try_add_node), vertices
+//! added through [view](../view/index.html), and vertices recorded with
+//! [Stage::add_node](../stage/struct.Stage.html#method.add_node). The vertex
+//! data passed to `add_edge` and the insertion methods of
+//! [mutators](../mutators/index.html) is computed by callbacks that are only
+//! called for novel game states, so it is never in conflict.
+//!
+//! ```rust
+//! # use search_graph::Graph;
+//! # use search_graph::duplicate::{DuplicateData, DuplicatePolicy};
+//! # fn main() {
+//! let mut g: Graph<&str, u32, ()> = Graph::new();
+//! g.set_duplicate_policy(DuplicatePolicy::Merge(Box::new(|old: &mut u32, new| *old += new)));
+//! g.add_node("root", 1);
+//! g.add_node("root", 2);
+//! assert_eq!(*g.find_node(&"root").unwrap().get_data(), 3);
+//! g.set_duplicate_policy(DuplicatePolicy::Error);
+//! assert!(g.try_add_node("root", 3).is_ok());
+//! assert_eq!(g.try_add_node("root", 4).err(), Some(DuplicateData));
+//! # }
+//! ```
+
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+
+use crate::base::VertexId;
+use crate::{mutators, Graph};
+use symbol_map::SymbolId;
+
+type MergeFn<S> = Box<dyn FnMut(&mut S, S) + Send + Sync>;
+
+/// What happens when a game state that is already in a graph is added again
+/// with data that differs from that of its vertex.
+#[derive(Default)]
+pub enum DuplicatePolicy<S> {
+  /// The new data is dropped. This is the default.
+  #[default]
+  Ignore,
+  /// The new data replaces the data of the existing vertex.
+  Replace,
+  /// The new data is merged into the data of the existing vertex by calling
+  /// the given function on them.
+  Merge(MergeFn<S>),
+  /// The new data is dropped and an error is reported.
+  /// [Graph::try_add_node](../struct.Graph.html#method.try_add_node) returns
+  /// a [DuplicateData](struct.DuplicateData.html) error, and other insertions
+  /// panic.
+  Error,
+}
+
+impl<S> fmt::Debug for DuplicatePolicy<S> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      DuplicatePolicy::Ignore => write!(f, "Ignore"),
+      DuplicatePolicy::Replace => write!(f, "Replace"),
+      DuplicatePolicy::Merge(_) => write!(f, "Merge(..)"),
+      DuplicatePolicy::Error => write!(f, "Error"),
+    }
+  }
+}
+
+/// The error reported when a game state is added again with different data
+/// under [DuplicatePolicy::Error](enum.DuplicatePolicy.html#variant.Error).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct DuplicateData;
+
+impl fmt::Display for DuplicateData {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "Game state is already in the graph with different data")
+  }
+}
+
+impl Error for DuplicateData {}
+
+/// A duplicate policy, along with the means to compare vertex data that it
+/// requires.
+pub(crate) struct Duplicates<S> {
+  policy: DuplicatePolicy<S>,
+  eq: Option<fn(&S, &S) -> bool>,
+}
+
+impl<S> Default for Duplicates<S> {
+  fn default() -> Self {
+    Duplicates {
+      policy: Default::default(),
+      eq: None,
+    }
+  }
+}
+
+impl<T: Hash + Eq + Clone, S, A> Graph<T, S, A> {
+  /// Sets what happens when a game state that is already in this graph is
+  /// added again with different data. The default policy is
+  /// `DuplicatePolicy::Ignore`.
+  ///
+  /// The policy is not carried over to graphs derived from this one (e.g., by
+  /// [partition](struct.Graph.html#method.partition)).
+  pub fn set_duplicate_policy(&mut self, policy: DuplicatePolicy<S>)
+  where
+    S: PartialEq,
+  {
+    self.duplicates = Duplicates {
+      policy,
+      eq: Some(<S as PartialEq>::eq),
+    };
+  }
+
+  /// Returns the duplicate policy of this graph.
+  pub fn duplicate_policy(&self) -> &DuplicatePolicy<S> {
+    &self.duplicates.policy
+  }
+
+  /// As [add_node](struct.Graph.html#method.add_node), but returns an error
+  /// instead of panicking if `state` is already known, `data` differs from
+  /// the data of its vertex, and the duplicate policy is
+  /// `DuplicatePolicy::Error`. The data of the vertex is unchanged in that
+  /// case.
+  pub fn try_add_node<'s>(
+    &'s mut self,
+    state: T,
+    data: S,
+  ) -> Result<mutators::MutNode<'s, T, S, A>, DuplicateData> {
+    let id = self.add_vertex(state, data)?;
+    Ok(mutators::MutNode::new(self, id))
+  }
+
+  /// Applies the duplicate policy to `data`, which was given for the game
+  /// state of the existing vertex `id`.
+  pub(crate) fn add_duplicate(&mut self, id: VertexId, data: S) -> Result<(), DuplicateData> {
+    let eq = match self.duplicates.eq {
+      Some(eq) => eq,
+      None => return Ok(()),
+    };
+    if eq(&self.get_vertex(id).data, &data) {
+      return Ok(());
+    }
+    let existing = &mut self.vertices[id.as_usize()].data;
+    match self.duplicates.policy {
+      DuplicatePolicy::Ignore => return Ok(()),
+      DuplicatePolicy::Error => return Err(DuplicateData),
+      DuplicatePolicy::Replace => *existing = data,
+      DuplicatePolicy::Merge(ref mut merge) => merge(existing, data),
+    }
+    self.touch_data(id);
+    self.touch_vertex(id);
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::{DuplicateData, DuplicatePolicy};
+
+  type Graph = crate::Graph<&'static str, &'static str, &'static str>;
+
+  #[test]
+  fn duplicate_policy_ok() {
+    let mut g = Graph::new();
+    g.add_node("root", "a");
+    g.add_node("root", "b");
+    assert_eq!("a", *g.find_node(&"root").unwrap().get_data());
+    g.set_duplicate_policy(DuplicatePolicy::Replace);
+    assert_eq!("b", *g.add_node("root", "b").get_data());
+    g.set_duplicate_policy(DuplicatePolicy::Merge(Box::new(|old, new| {
+      if new < *old {
+        *old = new;
+      }
+    })));
+    g.add_node("root", "c");
+    crate::view::of_graph(&mut g, |mut v| {
+      v.append_node("root", "a");
+    });
+    let mut stage = g.stage();
+    stage.add_node("root", "z");
+    stage.add_edge("root", "0", "child", "child_data", "edge");
+    g.commit(vec![stage]);
+    assert_eq!("a", *g.find_node(&"root").unwrap().get_data());
+    assert_eq!(2, g.vertex_count());
+    assert!(matches!(g.duplicate_policy(), DuplicatePolicy::Merge(_)));
+    g.set_duplicate_policy(DuplicatePolicy::Ignore);
+    assert!(matches!(g.duplicate_policy(), DuplicatePolicy::Ignore));
+  }
+
+  #[test]
+  fn duplicate_policy_snapshot_ok() {
+    let mut g = crate::Graph::<u32, u32, ()>::new();
+    g.set_duplicate_policy(DuplicatePolicy::Replace);
+    g.add_node(0, 1);
+    let before = g.snapshot();
+    g.add_node(0, 2);
+    let after = g.snapshot();
+    assert_eq!(1, *before.find_node(&0).unwrap().get_data());
+    assert_eq!(2, *after.find_node(&0).unwrap().get_data());
+    g.set_duplicate_policy(DuplicatePolicy::Merge(Box::new(|old, new| *old += new)));
+    g.add_node(0, 3);
+    assert_eq!(5, *g.snapshot().find_node(&0).unwrap().get_data());
+    assert_eq!(2, *after.find_node(&0).unwrap().get_data());
+  }
+
+  #[test]
+  fn duplicate_policy_err() {
+    let mut g = Graph::new();
+    g.set_duplicate_policy(DuplicatePolicy::Error);
+    assert!(g.try_add_node("root", "a").is_ok());
+    assert!(g.try_add_node("root", "a").is_ok());
+    assert_eq!(Some(DuplicateData), g.try_add_node("root", "b").err());
+    assert_eq!("a", *g.find_node(&"root").unwrap().get_data());
+  }
+
+  #[test]
+  #[should_panic(expected = "Game state is already in the graph with different data")]
+  fn duplicate_policy_panic_err() {
+    let mut g = Graph::new();
+    g.set_duplicate_policy(DuplicatePolicy::Error);
+    g.add_node("root", "a");
+    g.add_node("root", "b");
+  }
+}
@@ -0,0 +1,52 @@
+//! Optional index from (source, target) vertex pairs to edges.
+//!
+//! When enabled on a [Graph](../struct.Graph.html), this index allows an edge
+//! between two vertices to be found without scanning the child list of the
+//! source vertex.
+
+use std::collections::HashMap;
+
+use crate::base::{EdgeId, RawEdge, RawVertex, VertexId};
+
+/// Maps from a pair of source and target vertices to the first edge between
+/// them.
+#[derive(Default)]
+pub(crate) struct EdgeIndex {
+  edges: HashMap<(VertexId, VertexId), EdgeId>,
+}
+
+impl EdgeIndex {
+  /// Creates an index over all edges in the given graph components.
+  pub fn new<S, A>(vertices: &[RawVertex<S>], arcs: &[RawEdge<A>]) -> Self {
+    let mut index = EdgeIndex::default();
+    index.rebuild(vertices, arcs);
+    index
+  }
+
+  /// Discards all index entries and re-indexes the given graph components.
+  pub fn rebuild<S, A>(&mut self, vertices: &[RawVertex<S>], arcs: &[RawEdge<A>]) {
+    self.edges.clear();
+    for (source, vertex) in vertices.iter().enumerate() {
+      for &edge_id in vertex.children.iter() {
+        self.insert(VertexId(source), arcs[edge_id.as_usize()].target, edge_id);
+      }
+    }
+  }
+
+  /// Indexes a newly added edge. If there is already an edge from `source` to
+  /// `target`, the existing entry is retained.
+  pub fn insert(&mut self, source: VertexId, target: VertexId, edge_id: EdgeId) {
+    self.edges.entry((source, target)).or_insert(edge_id);
+  }
+
+  /// Returns true iff this index has the same entries as an index rebuilt
+  /// from the given graph components.
+  pub fn is_consistent<S, A>(&self, vertices: &[RawVertex<S>], arcs: &[RawEdge<A>]) -> bool {
+    self.edges == EdgeIndex::new(vertices, arcs).edges
+  }
+
+  /// Returns the first edge from `source` to `target`, if any.
+  pub fn get(&self, source: VertexId, target: VertexId) -> Option<EdgeId> {
+    self.edges.get(&(source, target)).cloned()
+  }
+}
@@ -0,0 +1,254 @@
+//! Support for batched evaluation of game states.
+//!
+//! Search algorithms commonly assign a heuristic evaluation (e.g., from a
+//! neural network) to each vertex when it is first expanded. Such evaluators
+//! are much more efficient when given many states at once, so this module
+//! provides the [Evaluator](trait.Evaluator.html) and
+//! [AsyncEvaluator](trait.AsyncEvaluator.html) traits, which
+//! [Graph::ensure_evaluated](../struct.Graph.html#method.ensure_evaluated) and
+//! [Graph::ensure_evaluated_async](../struct.Graph.html#method.ensure_evaluated_async)
+//! use to evaluate all vertices in a batch that still need evaluation.
+
+use std::future::Future;
+use std::hash::Hash;
+
+use crate::base::VertexId;
+use crate::Graph;
+
+/// Computes evaluations of game states in batches.
+pub trait Evaluator<T, S> {
+  /// The result of evaluating a single game state.
+  type Evaluation;
+  /// The type of errors that may arise during evaluation.
+  type Error;
+
+  /// Evaluates `states`. The returned vector must contain exactly one
+  /// evaluation for each element of `states`, in the same order.
+  fn evaluate(&mut self, states: &[T]) -> Result<Vec<Self::Evaluation>, Self::Error>;
+
+  /// Writes `evaluation` into the data for the vertex it was computed for.
+  fn store(&mut self, data: &mut S, evaluation: Self::Evaluation);
+}
+
+/// Computes evaluations of game states in batches, asynchronously.
+///
+/// This is the asynchronous analogue of [Evaluator](trait.Evaluator.html), for
+/// evaluators that hand batches off to another thread, process, or device.
+pub trait AsyncEvaluator<T, S> {
+  /// The result of evaluating a single game state.
+  type Evaluation;
+  /// The type of errors that may arise during evaluation.
+  type Error;
+  /// The future that resolves to a batch of evaluations.
+  type Future: Future<Output = Result<Vec<Self::Evaluation>, Self::Error>>;
+
+  /// Begins evaluating `states`. The returned future must resolve to exactly
+  /// one evaluation for each element of `states`, in the same order.
+  fn evaluate(&mut self, states: Vec<T>) -> Self::Future;
+
+  /// Writes `evaluation` into the data for the vertex it was computed for.
+  fn store(&mut self, data: &mut S, evaluation: Self::Evaluation);
+}
+
+impl<T: Hash + Eq + Clone, S, A> Graph<T, S, A> {
+  /// Resolves `states` to vertices and returns the distinct IDs of those for
+  /// which `is_evaluated` is false, in order, along with their game states.
+  fn unevaluated<'t, I, P>(&self, states: I, is_evaluated: P) -> (Vec<VertexId>, Vec<T>)
+  where
+    T: 't,
+    I: IntoIterator<Item = &'t T>,
+    P: Fn(&S) -> bool,
+  {
+    let mut ids: Vec<VertexId> = states
+      .into_iter()
+      .filter_map(|state| self.find_node(state).map(|n| n.id))
+      .filter(|id| !is_evaluated(&self.get_vertex(*id).data))
+      .collect();
+    ids.sort();
+    ids.dedup();
+    let states = ids
+      .iter()
+      .map(|id| self.get_state(*id).unwrap().clone())
+      .collect();
+    (ids, states)
+  }
+
+  /// Stores a batch of evaluations for the vertices with the given IDs.
+  fn store_evaluations<E, F>(&mut self, ids: Vec<VertexId>, evaluations: Vec<E>, mut store: F)
+  where
+    F: FnMut(&mut S, E),
+  {
+    assert_eq!(
+      ids.len(),
+      evaluations.len(),
+      "evaluator returned the wrong number of evaluations"
+    );
+    for (id, evaluation) in ids.into_iter().zip(evaluations) {
+      store(self.get_data_mut(id), evaluation);
+    }
+  }
+
+  /// Evaluates, in a single batch, each vertex for one of `states` whose data
+  /// does not satisfy `is_evaluated`, and stores the results in the vertex
+  /// data. Game states that are not in the graph are ignored.
+  ///
+  /// Returns the number of vertices that were evaluated, or the error
+  /// reported by `evaluator`, in which case no vertex data is modified.
+  ///
+  /// Panics if `evaluator` returns the wrong number of evaluations.
+  pub fn ensure_evaluated<'t, I, P, E>(
+    &mut self,
+    states: I,
+    is_evaluated: P,
+    evaluator: &mut E,
+  ) -> Result<usize, E::Error>
+  where
+    T: 't,
+    I: IntoIterator<Item = &'t T>,
+    P: Fn(&S) -> bool,
+    E: Evaluator<T, S>,
+  {
+    let (ids, states) = self.unevaluated(states, is_evaluated);
+    if ids.is_empty() {
+      return Ok(0);
+    }
+    let count = ids.len();
+    let evaluations = evaluator.evaluate(&states)?;
+    self.store_evaluations(ids, evaluations, |data, e| evaluator.store(data, e));
+    Ok(count)
+  }
+
+  /// As [ensure_evaluated](#method.ensure_evaluated), but awaiting the results
+  /// of an asynchronous evaluator.
+  pub async fn ensure_evaluated_async<'t, I, P, E>(
+    &mut self,
+    states: I,
+    is_evaluated: P,
+    evaluator: &mut E,
+  ) -> Result<usize, E::Error>
+  where
+    T: 't,
+    I: IntoIterator<Item = &'t T>,
+    P: Fn(&S) -> bool,
+    E: AsyncEvaluator<T, S>,
+  {
+    let (ids, states) = self.unevaluated(states, is_evaluated);
+    if ids.is_empty() {
+      return Ok(0);
+    }
+    let count = ids.len();
+    let evaluations = evaluator.evaluate(states).await?;
+    self.store_evaluations(ids, evaluations, |data, e| evaluator.store(data, e));
+    Ok(count)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::{AsyncEvaluator, Evaluator};
+  use std::future::{self, Future, Ready};
+  use std::pin::Pin;
+  use std::task::{Context, Poll, Waker};
+
+  type Graph = crate::Graph<u32, Option<u32>, ()>;
+
+  /// Evaluates a state as its square, recording the size of each batch.
+  struct Square(Vec<usize>);
+
+  impl Evaluator<u32, Option<u32>> for Square {
+    type Evaluation = u32;
+    type Error = ();
+
+    fn evaluate(&mut self, states: &[u32]) -> Result<Vec<u32>, ()> {
+      self.0.push(states.len());
+      Ok(states.iter().map(|s| s * s).collect())
+    }
+
+    fn store(&mut self, data: &mut Option<u32>, evaluation: u32) {
+      *data = Some(evaluation);
+    }
+  }
+
+  impl AsyncEvaluator<u32, Option<u32>> for Square {
+    type Evaluation = u32;
+    type Error = ();
+    type Future = Ready<Result<Vec<u32>, ()>>;
+
+    fn evaluate(&mut self, states: Vec<u32>) -> Self::Future {
+      future::ready(Evaluator::evaluate(self, &states))
+    }
+
+    fn store(&mut self, data: &mut Option<u32>, evaluation: u32) {
+      *data = Some(evaluation);
+    }
+  }
+
+  struct Failing;
+
+  impl Evaluator<u32, Option<u32>> for Failing {
+    type Evaluation = u32;
+    type Error = &'static str;
+
+    fn evaluate(&mut self, _: &[u32]) -> Result<Vec<u32>, &'static str> {
+      Err("failed")
+    }
+
+    fn store(&mut self, _: &mut Option<u32>, _: u32) {
+      panic!()
+    }
+  }
+
+  fn make_graph() -> Graph {
+    let mut g = Graph::new();
+    g.add_edge(1, |_| Some(1), 2, |_| None, ());
+    g.add_edge(1, |_| Some(1), 3, |_| None, ());
+    g.add_edge(3, |_| None, 4, |_| None, ());
+    g
+  }
+
+  fn data(g: &Graph, state: u32) -> Option<u32> {
+    *g.find_node(&state).unwrap().get_data()
+  }
+
+  #[test]
+  fn ensure_evaluated_ok() {
+    let mut g = make_graph();
+    let mut evaluator = Square(Vec::new());
+    let states = [1, 3, 2, 3, 5];
+    let count = g.ensure_evaluated(states.iter(), Option::is_some, &mut evaluator);
+    assert_eq!(Ok(2), count);
+    assert_eq!(vec![2], evaluator.0);
+    assert_eq!(Some(1), data(&g, 1));
+    assert_eq!(Some(4), data(&g, 2));
+    assert_eq!(Some(9), data(&g, 3));
+    assert_eq!(None, data(&g, 4));
+
+    let count = g.ensure_evaluated(states.iter(), Option::is_some, &mut evaluator);
+    assert_eq!(Ok(0), count);
+    assert_eq!(vec![2], evaluator.0);
+  }
+
+  #[test]
+  fn ensure_evaluated_err() {
+    let mut g = make_graph();
+    let count = g.ensure_evaluated([2, 4].iter(), Option::is_some, &mut Failing);
+    assert_eq!(Err("failed"), count);
+    assert_eq!(None, data(&g, 2));
+  }
+
+  #[test]
+  fn ensure_evaluated_async_ok() {
+    let mut g = make_graph();
+    let mut evaluator = Square(Vec::new());
+    let states = [4, 2];
+    let mut f = Box::pin(g.ensure_evaluated_async(states.iter(), Option::is_some, &mut evaluator));
+    let mut cx = Context::from_waker(Waker::noop());
+    match Pin::as_mut(&mut f).poll(&mut cx) {
+      Poll::Ready(count) => assert_eq!(Ok(2), count),
+      Poll::Pending => panic!(),
+    }
+    drop(f);
+    assert_eq!(Some(4), data(&g, 2));
+    assert_eq!(Some(16), data(&g, 4));
+  }
+}
@@ -0,0 +1,206 @@
+//! Digests of graphs that do not depend on how vertices and edges are
+//! numbered.
+//!
+//! Two processes that build the same graph generally assign different vertex
+//! and edge IDs, so comparing their graphs element by element requires
+//! exchanging game states. [Graph::fingerprint](../struct.Graph.html#method.fingerprint)
+//! instead computes a 64-bit digest of the game states of the vertices of a
+//! graph and of the game states at the ends of each edge, so that workers may
+//! cheaply check that they hold identical graphs (e.g., before exchanging
+//! deltas) by comparing digests.
+//!
+//! Game states are hashed by a caller-supplied function, which must agree
+//! between the processes that compare fingerprints. Vertex and edge data
+//! contribute to the digest as well if a [HashData](trait.HashData.html) is
+//! given: `()` ignores data, and a pair of functions `(hash_vertex,
+//! hash_edge)` hashes vertex data and edge data respectively.
+//!
+//! A fingerprint depends only on the multisets of vertices and edges, so it is
+//! the same regardless of the order in which they were added or of garbage
+//! collection. Aliases, expansion states, and unexpanded child edges do not
+//! contribute to it. Distinct graphs have equal fingerprints only if hashes
+//! collide, which is unlikely but possible.
+//!
+//! ```rust
+//! # use search_graph::Graph;
+//! # use std::collections::hash_map::DefaultHasher;
+//! # use std::hash::{Hash, Hasher};
+//! # fn main() {
+//! fn hash<K: Hash>(key: &K) -> u64 {
+//!   let mut hasher = DefaultHasher::new();
+//!   key.hash(&mut hasher);
+//!   hasher.finish()
+//! }
+//!
+//! let mut a: Graph<&str, u32, u32> = Graph::new();
+//! a.add_edge("root", |_| 0, "x", |_| 1, 10);
+//! a.add_edge("x", |_| 1, "y", |_| 2, 20);
+//! let mut b: Graph<&str, u32, u32> = Graph::new();
+//! b.add_edge("x", |_| 1, "y", |_| 2, 20);
+//! b.add_edge("root", |_| 0, "x", |_| 1, 10);
+//! let data = (|s: &u32| hash(s), |e: &u32| hash(e));
+//! assert_eq!(a.fingerprint(hash, data), b.fingerprint(hash, data));
+//! *b.find_node_mut(&"y").unwrap().get_data_mut() = 3;
+//! assert_ne!(a.fingerprint(hash, data), b.fingerprint(hash, data));
+//! assert_eq!(a.fingerprint(hash, ()), b.fingerprint(hash, ()));
+//! # }
+//! ```
+
+use std::hash::Hash;
+
+use crate::Graph;
+use symbol_map::indexing::Indexing;
+use symbol_map::SymbolId;
+
+/// How the data of vertices and edges contribute to a fingerprint. See the
+/// [fingerprint](index.html) module.
+pub trait HashData<S, A> {
+  /// Returns a hash of vertex data, or `None` if vertex data is ignored.
+  fn hash_vertex(&mut self, data: &S) -> Option<u64>;
+
+  /// Returns a hash of edge data, or `None` if edge data is ignored.
+  fn hash_edge(&mut self, data: &A) -> Option<u64>;
+}
+
+impl<S, A> HashData<S, A> for () {
+  fn hash_vertex(&mut self, _: &S) -> Option<u64> {
+    None
+  }
+
+  fn hash_edge(&mut self, _: &A) -> Option<u64> {
+    None
+  }
+}
+
+impl<S, A, FS, FA> HashData<S, A> for (FS, FA)
+where
+  FS: FnMut(&S) -> u64,
+  FA: FnMut(&A) -> u64,
+{
+  fn hash_vertex(&mut self, data: &S) -> Option<u64> {
+    Some((self.0)(data))
+  }
+
+  fn hash_edge(&mut self, data: &A) -> Option<u64> {
+    Some((self.1)(data))
+  }
+}
+
+/// The splitmix64 finalizer, which spreads each bit of `h` over the result.
+fn mix(mut h: u64) -> u64 {
+  h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+  h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+  h ^ (h >> 31)
+}
+
+// Seeds that distinguish the terms for vertices and edges, and data from its
+// absence.
+const VERTEX: u64 = 0x9e37_79b9_7f4a_7c15;
+const EDGE: u64 = 0xc2b2_ae3d_27d4_eb4f;
+const DATA: u64 = 0x1656_67b1_9e37_79f9;
+
+impl<T: Hash + Eq + Clone, S, A> Graph<T, S, A> {
+  /// Returns a digest of the vertices and edges of this graph that does not
+  /// depend on their IDs, as described in the [fingerprint](fingerprint/index.html)
+  /// module. Game states are hashed with `hash_state`, and vertex and edge
+  /// data are hashed as specified by `hash_data`.
+  pub fn fingerprint<F, D>(&self, mut hash_state: F, mut hash_data: D) -> u64
+  where
+    F: FnMut(&T) -> u64,
+    D: HashData<S, A>,
+  {
+    let mut states = vec![0; self.vertices.len()];
+    for symbol in self.state_ids.table() {
+      states[symbol.id().as_usize()] = mix(hash_state(symbol.data()) ^ VERTEX);
+    }
+    let data = |hash: Option<u64>| hash.map_or(0, |h| mix(h ^ DATA));
+    let mut digest = 0u64;
+    for (state, vertex) in states.iter().zip(self.vertices.iter()) {
+      let term = mix(state ^ data(hash_data.hash_vertex(&vertex.data)));
+      digest = digest.wrapping_add(term);
+    }
+    for arc in self.arcs.iter() {
+      let source = states[arc.source.as_usize()];
+      let target = states[arc.target.as_usize()].rotate_left(32);
+      let term = mix(mix(source ^ EDGE) ^ target ^ data(hash_data.hash_edge(&arc.data)));
+      digest = digest.wrapping_add(term);
+    }
+    mix(digest ^ mix(self.vertices.len() as u64) ^ mix(self.arcs.len() as u64 ^ EDGE))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use crate::moves::hash_key;
+
+  type Graph = crate::Graph<&'static str, &'static str, &'static str>;
+
+  type HashFn = fn(&&'static str) -> u64;
+
+  fn data() -> (HashFn, HashFn) {
+    (hash_key, hash_key)
+  }
+
+  #[test]
+  fn fingerprint_ok() {
+    let mut a = Graph::new();
+    a.add_edge("root", |_| "r", "a", |_| "a", "root_a");
+    a.add_edge("root", |_| "r", "b", |_| "b", "root_b");
+    a.add_edge("a", |_| "a", "b", |_| "b", "a_b");
+    a.add_edge("z", |_| "z", "root", |_| "r", "z_root");
+    let mut b = Graph::new();
+    b.add_edge("a", |_| "a", "b", |_| "b", "a_b");
+    b.add_edge("root", |_| "r", "b", |_| "b", "root_b");
+    b.add_edge("root", |_| "r", "a", |_| "a", "root_a");
+    assert_ne!(a.fingerprint(hash_key, ()), b.fingerprint(hash_key, ()));
+    a.compact_with(&["root"], &mut []);
+    assert_eq!(a.fingerprint(hash_key, ()), b.fingerprint(hash_key, ()));
+    assert_eq!(
+      a.fingerprint(hash_key, data()),
+      b.fingerprint(hash_key, data())
+    );
+    assert_eq!(
+      Graph::new().fingerprint(hash_key, ()),
+      Graph::new().fingerprint(hash_key, ())
+    );
+  }
+
+  #[test]
+  fn fingerprint_differs_ok() {
+    let mut a = Graph::new();
+    a.add_edge("root", |_| "r", "a", |_| "a", "root_a");
+    a.add_edge("a", |_| "a", "b", |_| "b", "a_b");
+    let base = a.fingerprint(hash_key, data());
+    let topology = a.fingerprint(hash_key, ());
+
+    // Reversing an edge.
+    let mut b = Graph::new();
+    b.add_edge("root", |_| "r", "a", |_| "a", "root_a");
+    b.add_edge("b", |_| "b", "a", |_| "a", "a_b");
+    assert_ne!(topology, b.fingerprint(hash_key, ()));
+
+    // Adding a parallel edge.
+    let mut b = Graph::new();
+    b.add_edge("root", |_| "r", "a", |_| "a", "root_a");
+    b.add_edge("a", |_| "a", "b", |_| "b", "a_b");
+    b.add_edge("a", |_| "a", "b", |_| "b", "a_b");
+    assert_ne!(topology, b.fingerprint(hash_key, ()));
+
+    // Changing vertex or edge data.
+    let mut b = Graph::new();
+    b.add_edge("root", |_| "r", "a", |_| "x", "root_a");
+    b.add_edge("a", |_| "x", "b", |_| "b", "a_b");
+    assert_eq!(topology, b.fingerprint(hash_key, ()));
+    assert_ne!(base, b.fingerprint(hash_key, data()));
+    let mut b = Graph::new();
+    b.add_edge("root", |_| "r", "a", |_| "a", "root_a");
+    b.add_edge("a", |_| "a", "b", |_| "b", "x");
+    assert_ne!(base, b.fingerprint(hash_key, data()));
+
+    // Swapping the data of two vertices.
+    let mut b = Graph::new();
+    b.add_edge("root", |_| "a", "a", |_| "r", "root_a");
+    b.add_edge("a", |_| "r", "b", |_| "b", "a_b");
+    assert_ne!(base, b.fingerprint(hash_key, data()));
+  }
+}
@@ -0,0 +1,241 @@
+//! A writer handle and reader handles that share a graph across threads.
+//!
+//! [Graph::into_handles](../struct.Graph.html#method.into_handles) consumes a
+//! graph and returns a [WriteHandle](struct.WriteHandle.html), which owns the
+//! graph and mutates it as usual, and a [ReadHandle](struct.ReadHandle.html),
+//! which may be cloned and sent to any number of reader threads. Readers see
+//! the [Snapshot](../snapshot/struct.Snapshot.html) that the writer most
+//! recently published with
+//! [WriteHandle::publish](struct.WriteHandle.html#method.publish), so each
+//! reader traverses a consistent graph while the writer keeps appending to its
+//! own copy. This suits engines that split work between a search thread,
+//! which writes, and analysis threads, which read.
+//!
+//! Neither readers nor the writer take locks. The published snapshot is
+//! swapped atomically, and a superseded snapshot is reclaimed with
+//! [crossbeam-epoch](https://docs.rs/crossbeam-epoch) once no reader can still
+//! be loading it. Publishing costs as much as
+//! [Graph::snapshot](../struct.Graph.html#method.snapshot), which copies only
+//! the parts of the graph that changed since the previous publication.
+//!
+//! This module is only available when the `epoch` feature is enabled.
+//!
+//! ```rust
+//! # use search_graph::Graph;
+//! # fn main() {
+//! let mut g: Graph<&str, u32, ()> = Graph::new();
+//! g.add_edge("root", |_| 0, "a", |_| 0, ());
+//! let (mut writer, reader) = g.into_handles();
+//! let analysis = std::thread::spawn(move || {
+//!   reader.read(|snapshot| snapshot.find_node(&"root").unwrap().get_child_list().len())
+//! });
+//! writer.graph_mut().add_edge("root", |_| 0, "b", |_| 0, ());
+//! // Readers do not see changes until they are published.
+//! assert_eq!(analysis.join().unwrap(), 1);
+//! let reader = writer.reader();
+//! writer.publish();
+//! assert_eq!(reader.load().vertex_count(), 3);
+//! # }
+//! ```
+
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crossbeam_epoch::{self as epoch, Atomic, Owned};
+
+use crate::snapshot::Snapshot;
+use crate::Graph;
+
+/// The state that a writer shares with its readers.
+struct Shared<T, S, A> {
+  /// The most recently published snapshot, which is never null.
+  current: Atomic<Snapshot<T, S, A>>,
+  /// The number of snapshots that have been published.
+  publications: AtomicU64,
+}
+
+impl<T, S, A> Drop for Shared<T, S, A> {
+  fn drop(&mut self) {
+    // No handles remain, so no other thread may be reading the pointer.
+    unsafe {
+      let guard = epoch::unprotected();
+      drop(self.current.load(Ordering::Relaxed, guard).into_owned());
+    }
+  }
+}
+
+impl<T: Hash + Eq + Clone, S: Clone, A: Clone> Graph<T, S, A> {
+  /// Consumes this graph and returns a handle for mutating it and a handle
+  /// for reading it from other threads, as described in the
+  /// [handle](handle/index.html) module. A snapshot of the graph is published
+  /// immediately.
+  pub fn into_handles(mut self) -> (WriteHandle<T, S, A>, ReadHandle<T, S, A>) {
+    let shared = Arc::new(Shared {
+      current: Atomic::new(self.snapshot()),
+      publications: AtomicU64::new(1),
+    });
+    let reader = ReadHandle {
+      shared: shared.clone(),
+    };
+    let writer = WriteHandle {
+      graph: self,
+      shared,
+    };
+    (writer, reader)
+  }
+}
+
+/// The handle through which a single thread mutates a shared graph and
+/// publishes snapshots of it to readers.
+pub struct WriteHandle<T: Hash + Eq + Clone, S, A> {
+  graph: Graph<T, S, A>,
+  shared: Arc<Shared<T, S, A>>,
+}
+
+impl<T: Hash + Eq + Clone, S: Clone, A: Clone> WriteHandle<T, S, A> {
+  /// Returns the writer's copy of the graph, which includes changes that have
+  /// not been published.
+  pub fn graph(&self) -> &Graph<T, S, A> {
+    &self.graph
+  }
+
+  /// Returns the writer's copy of the graph for modification. Changes are not
+  /// visible to readers until they are published.
+  pub fn graph_mut(&mut self) -> &mut Graph<T, S, A> {
+    &mut self.graph
+  }
+
+  /// Makes the current state of the graph visible to readers, and returns the
+  /// number of snapshots that have been published so far.
+  pub fn publish(&mut self) -> u64 {
+    let snapshot = Owned::new(self.graph.snapshot());
+    // The count is incremented first, so that a reader that loads the new
+    // snapshot sees a count that includes it.
+    let count = self.shared.publications.fetch_add(1, Ordering::Release) + 1;
+    let guard = epoch::pin();
+    let old = self.shared.current.swap(snapshot, Ordering::AcqRel, &guard);
+    // Readers that loaded the old snapshot are pinned until they have cloned
+    // it, so it may be freed once they unpin.
+    unsafe {
+      guard.defer_destroy(old);
+    }
+    count
+  }
+
+  /// Returns a new handle for reading the published graph.
+  pub fn reader(&self) -> ReadHandle<T, S, A> {
+    ReadHandle {
+      shared: self.shared.clone(),
+    }
+  }
+
+  /// Consumes this handle and returns the graph, including changes that have
+  /// not been published. Existing readers keep the last published snapshot.
+  pub fn into_graph(self) -> Graph<T, S, A> {
+    self.graph
+  }
+}
+
+/// A handle for reading the snapshots that a
+/// [WriteHandle](struct.WriteHandle.html) publishes. Read handles are cheap to
+/// clone, and may be sent to other threads when game states and data may be.
+pub struct ReadHandle<T, S, A> {
+  shared: Arc<Shared<T, S, A>>,
+}
+
+impl<T, S, A> Clone for ReadHandle<T, S, A> {
+  fn clone(&self) -> Self {
+    ReadHandle {
+      shared: self.shared.clone(),
+    }
+  }
+}
+
+impl<T, S, A> ReadHandle<T, S, A> {
+  /// Returns the most recently published snapshot. The snapshot remains
+  /// valid, and unchanged, however long it is kept.
+  pub fn load(&self) -> Snapshot<T, S, A> {
+    self.read(Snapshot::clone)
+  }
+
+  /// Calls `f` on the most recently published snapshot without cloning it,
+  /// and returns its result. The snapshot is not reclaimed while `f` runs, so
+  /// `f` should not block for long.
+  pub fn read<R, F: FnOnce(&Snapshot<T, S, A>) -> R>(&self, f: F) -> R {
+    let guard = epoch::pin();
+    let current = self.shared.current.load(Ordering::Acquire, &guard);
+    // The pointer is never null, and is not freed while `guard` is held.
+    f(unsafe { current.deref() })
+  }
+
+  /// Returns the number of snapshots that have been published, including
+  /// the one published when the handles were created. The count includes any
+  /// snapshot that was loaded before this was called, and it may include a
+  /// snapshot that is being published but cannot be loaded yet.
+  pub fn publications(&self) -> u64 {
+    self.shared.publications.load(Ordering::Acquire)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use crossbeam_utils::thread;
+
+  type Graph = crate::Graph<u32, u32, ()>;
+
+  #[test]
+  fn publish_ok() {
+    let mut g = Graph::new();
+    g.add_edge(0, |_| 0, 1, |_| 1, ());
+    let (mut writer, reader) = g.into_handles();
+    assert_eq!(1, reader.publications());
+    let before = reader.load();
+    writer.graph_mut().add_edge(1, |_| 1, 2, |_| 2, ());
+    *writer.graph_mut().find_node_mut(&0).unwrap().get_data_mut() = 10;
+    assert_eq!(2, reader.load().vertex_count());
+    assert_eq!(3, writer.graph().vertex_count());
+    assert_eq!(2, writer.publish());
+    assert_eq!(2, reader.clone().publications());
+    assert_eq!(3, reader.load().vertex_count());
+    assert_eq!(10, reader.read(|s| *s.find_node(&0).unwrap().get_data()));
+    assert_eq!(0, *before.find_node(&0).unwrap().get_data());
+    let g = writer.into_graph();
+    assert_eq!(3, g.vertex_count());
+    assert_eq!(3, reader.load().vertex_count());
+  }
+
+  #[test]
+  fn concurrent_readers_ok() {
+    let mut g = Graph::new();
+    g.add_node(0, 0);
+    let (mut writer, reader) = g.into_handles();
+    thread::scope(|scope| {
+      for _ in 0..4 {
+        let reader = reader.clone();
+        scope.spawn(move |_| {
+          let mut last = 0;
+          while last < 100 {
+            // Each snapshot is a chain whose length matches its data.
+            let (count, data) = reader.read(|s| {
+              let root = s.find_node(&0).unwrap();
+              (s.vertex_count(), *root.get_data())
+            });
+            assert_eq!(count as u32, data + 1);
+            assert!(reader.publications() > data as u64);
+            assert!(data >= last);
+            last = data;
+          }
+        });
+      }
+      for i in 1..=100 {
+        let graph = writer.graph_mut();
+        graph.add_edge(i - 1, |_| 0, i, |_| 0, ());
+        *graph.find_node_mut(&0).unwrap().get_data_mut() = i;
+        writer.publish();
+      }
+    })
+    .unwrap();
+    assert_eq!(101, reader.publications());
+  }
+}
@@ -0,0 +1,605 @@
+//! Support for indexing game states by 64-bit hash keys.
+//!
+//! Game engines commonly identify positions by a 64-bit
+//! [Zobrist hash](https://en.wikipedia.org/wiki/Zobrist_hashing), which can be
+//! updated incrementally as moves are made and unmade.
+//! [ZobristTable](struct.ZobristTable.html) provides the random keys and
+//! update operations for such a scheme.
+//!
+//! A `Graph<u64, S, A>` whose game states are hash keys uses much less memory
+//! than one that stores full game states, at the cost of conflating distinct
+//! game states whose keys collide. The `add_keyed_*` and `find_keyed_*` methods
+//! of `Graph` take full game states that implement
+//! [HashKey](trait.HashKey.html) and address vertices by their keys. If
+//! auditing has been enabled with
+//! [set_audit_keys](../struct.Graph.html#method.set_audit_keys), a second,
+//! independent hash of each game state is stored with its vertex, so that
+//! collisions can be counted and keyed lookups that hit a colliding vertex
+//! fail. A [CollisionPolicy](enum.CollisionPolicy.html) determines which game
+//! state keeps a vertex when a collision is detected, and
+//! [set_key_bits](../struct.Graph.html#method.set_key_bits) bounds the number of
+//! vertices by truncating keys.
+//!
+//! This module is only available when the `hashkeys` feature is enabled.
+//!
+//! ```rust
+//! # use search_graph::Graph;
+//! # use search_graph::hashkeys::{HashKey, ZobristTable};
+//! #[derive(Hash)]
+//! struct Board {
+//!   cells: [bool; 9],
+//!   key: u64,
+//! }
+//!
+//! impl HashKey for Board {
+//!   fn hash_key(&self) -> u64 {
+//!     self.key
+//!   }
+//! }
+//!
+//! # fn main() {
+//! let zobrist = ZobristTable::new(9, 0);
+//! let empty = Board { cells: [false; 9], key: 0 };
+//! let mut cells = [false; 9];
+//! cells[4] = true;
+//! let center = Board { cells, key: zobrist.toggle(empty.key, 4) };
+//! assert_eq!(center.key, zobrist.hash(vec![4]));
+//!
+//! let mut graph: Graph<u64, u32, ()> = Graph::new();
+//! graph.set_audit_keys(true);
+//! graph.add_keyed_edge(&empty, |_| 0, &center, |_| 0, ());
+//! let root = graph.find_keyed_node(&empty).unwrap();
+//! assert_eq!(root.get_child_list().len(), 1);
+//! assert_eq!(graph.key_collisions(), Some(0));
+//! # }
+//! ```
+
+use std::hash::Hash;
+use std::sync::Arc;
+
+use crate::base::VertexId;
+use crate::moves::hash_key;
+use crate::{mutators, nav, Graph};
+use symbol_map::indexing::{Indexing, Insertion};
+use symbol_map::SymbolId;
+
+/// Game states that can be identified by a 64-bit key.
+pub trait HashKey {
+  /// Returns the key for this game state. Equal game states must have equal
+  /// keys.
+  fn hash_key(&self) -> u64;
+}
+
+impl HashKey for u64 {
+  fn hash_key(&self) -> u64 {
+    *self
+  }
+}
+
+/// Random keys for Zobrist hashing over a fixed number of features.
+///
+/// A feature is any fact about a game state that may be present or absent
+/// (e.g., "there is a white knight on e4"). The key for a game state is the
+/// exclusive or of the keys of the features that are present in it, so the key
+/// can be updated in constant time when a feature is added or removed.
+#[derive(Clone, Debug)]
+pub struct ZobristTable {
+  keys: Vec<u64>,
+}
+
+impl ZobristTable {
+  /// Creates a table with keys for `features` features. Keys are generated
+  /// deterministically from `seed`, so tables that are created with the same
+  /// arguments are identical.
+  pub fn new(features: usize, seed: u64) -> Self {
+    let mut state = seed;
+    let keys = (0..features)
+      .map(|_| {
+        // splitmix64.
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+      })
+      .collect();
+    ZobristTable { keys }
+  }
+
+  /// Returns the number of features that this table has keys for.
+  pub fn len(&self) -> usize {
+    self.keys.len()
+  }
+
+  /// Returns true iff this table has no keys.
+  pub fn is_empty(&self) -> bool {
+    self.keys.is_empty()
+  }
+
+  /// Returns the key for `feature`.
+  ///
+  /// Panics if `feature` is out of bounds.
+  pub fn key(&self, feature: usize) -> u64 {
+    self.keys[feature]
+  }
+
+  /// Returns `key` with `feature` added if it was absent or removed if it was
+  /// present.
+  pub fn toggle(&self, key: u64, feature: usize) -> u64 {
+    key ^ self.keys[feature]
+  }
+
+  /// Returns `key` with the feature `from` replaced by the feature `to`, as
+  /// when a piece moves from one square to another.
+  pub fn replace(&self, key: u64, from: usize, to: usize) -> u64 {
+    key ^ self.keys[from] ^ self.keys[to]
+  }
+
+  /// Returns the key for a game state that has exactly the given features.
+  pub fn hash<I: IntoIterator<Item = usize>>(&self, features: I) -> u64 {
+    features.into_iter().fold(0, |key, f| self.toggle(key, f))
+  }
+}
+
+/// The outcome of a hash key collision, as decided by a
+/// [CollisionPolicy](enum.CollisionPolicy.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CollisionResolution {
+  /// Keep the existing vertex data, and ignore the incoming game state.
+  Keep,
+  /// Rebind the vertex to the incoming game state and replace its data. The
+  /// vertex's edges are retained.
+  Replace,
+}
+
+/// A function that resolves a hash key collision, given the existing vertex
+/// data and the incoming data.
+pub type CollisionResolver<S> = Arc<dyn Fn(&S, &S) -> CollisionResolution + Send + Sync>;
+
+/// Determines what happens when a keyed insertion collides with a vertex that
+/// was added for a different game state with the same key.
+///
+/// These mirror the replacement schemes of classic fixed-size transposition
+/// tables. Collisions can only be detected when key auditing is enabled.
+#[derive(Default)]
+pub enum CollisionPolicy<S> {
+  /// Always keep the existing vertex data. This is the default.
+  #[default]
+  Keep,
+  /// Always replace the existing vertex data.
+  Replace,
+  /// Calls a resolver with the existing vertex data and the incoming data, and
+  /// follows its decision.
+  Resolve(CollisionResolver<S>),
+}
+
+impl<S> Clone for CollisionPolicy<S> {
+  fn clone(&self) -> Self {
+    match *self {
+      CollisionPolicy::Keep => CollisionPolicy::Keep,
+      CollisionPolicy::Replace => CollisionPolicy::Replace,
+      CollisionPolicy::Resolve(ref f) => CollisionPolicy::Resolve(f.clone()),
+    }
+  }
+}
+
+/// Secondary hashes of the game states at each vertex of a keyed graph.
+pub(crate) struct KeyAudit {
+  /// Indexed by `VertexId`. Zero for vertices that have not been audited.
+  pub(crate) checks: Vec<u64>,
+  collisions: u64,
+}
+
+impl KeyAudit {
+  fn new() -> Self {
+    KeyAudit {
+      checks: Vec::new(),
+      collisions: 0,
+    }
+  }
+
+  fn check<K: Hash>(state: &K) -> u64 {
+    hash_key(state).max(1)
+  }
+
+  fn get(&self, id: VertexId) -> u64 {
+    self.checks.get(id.as_usize()).cloned().unwrap_or(0)
+  }
+
+  /// Records that `id` was reached with a game state whose secondary hash is
+  /// `check`. Returns true iff this is a collision.
+  fn record(&mut self, id: VertexId, check: u64) -> bool {
+    if self.checks.len() <= id.as_usize() {
+      self.checks.resize(id.as_usize() + 1, 0);
+    }
+    let stored = &mut self.checks[id.as_usize()];
+    if *stored == 0 {
+      *stored = check;
+      false
+    } else if *stored != check {
+      self.collisions += 1;
+      true
+    } else {
+      false
+    }
+  }
+}
+
+/// Configuration of how a `Graph<u64, S, A>` treats keyed game states.
+pub(crate) struct KeyedConfig<S> {
+  pub(crate) audit: Option<KeyAudit>,
+  mask: u64,
+  policy: CollisionPolicy<S>,
+}
+
+impl<S> KeyedConfig<S> {
+  pub(crate) fn new() -> Self {
+    KeyedConfig {
+      audit: None,
+      mask: !0,
+      policy: CollisionPolicy::Keep,
+    }
+  }
+
+  /// Returns a configuration with the same settings and no audit data.
+  pub(crate) fn empty_like(&self) -> Self {
+    KeyedConfig {
+      audit: self.audit.as_ref().map(|_| KeyAudit::new()),
+      mask: self.mask,
+      policy: self.policy.clone(),
+    }
+  }
+
+  /// Returns a configuration with the same key width and audit data for a
+  /// graph with different vertex data. The collision policy depends on vertex
+  /// data, so it is reset to the default.
+  pub(crate) fn topology_clone<R>(&self) -> KeyedConfig<R> {
+    KeyedConfig {
+      audit: self.audit.as_ref().map(|audit| KeyAudit {
+        checks: audit.checks.clone(),
+        collisions: audit.collisions,
+      }),
+      mask: self.mask,
+      policy: CollisionPolicy::Keep,
+    }
+  }
+
+  /// Restricts keys to their low `bits` bits. Panics if `bits` is 0 or
+  /// greater than 64.
+  pub(crate) fn set_key_bits(&mut self, bits: u32) {
+    assert!(bits > 0 && bits <= 64, "key width must be in 1..=64");
+    self.mask = !0 >> (64 - bits);
+  }
+
+  fn key<K: HashKey>(&self, state: &K) -> u64 {
+    state.hash_key() & self.mask
+  }
+}
+
+impl<S, A> Graph<u64, S, A> {
+  /// Enables or disables auditing of hash key collisions by the `add_keyed_*`
+  /// and `find_keyed_*` methods.
+  ///
+  /// Auditing is disabled by default. It costs an additional 8 bytes per
+  /// vertex. Disabling auditing discards all audit data.
+  pub fn set_audit_keys(&mut self, enabled: bool) {
+    if !enabled {
+      self.keyed.audit = None;
+    } else if self.keyed.audit.is_none() {
+      self.keyed.audit = Some(KeyAudit::new());
+    }
+  }
+
+  /// Returns the number of hash key collisions that have been detected since
+  /// auditing was enabled, or `None` if auditing is disabled.
+  ///
+  /// A collision is detected when `add_keyed_node` or `add_keyed_edge` is
+  /// given a game state whose key matches that of an existing vertex, but
+  /// whose secondary hash does not.
+  pub fn key_collisions(&self) -> Option<u64> {
+    self.keyed.audit.as_ref().map(|a| a.collisions)
+  }
+
+  /// Sets the policy that is followed when `add_keyed_node` or
+  /// `add_keyed_edge` detects a hash key collision. Enables key auditing if it
+  /// is not already enabled, since collisions cannot be detected without it.
+  pub fn set_collision_policy(&mut self, policy: CollisionPolicy<S>) {
+    self.set_audit_keys(true);
+    self.keyed.policy = policy;
+  }
+
+  /// Restricts keyed game states to the low `bits` bits of their keys, so that
+  /// the graph has at most `2^bits` vertices. Together with a collision policy,
+  /// this bounds memory use in the way that a fixed-size transposition table
+  /// does, while retaining explicit edges for the paths that are added.
+  ///
+  /// Panics if the graph is not empty, since existing keys would not be
+  /// consistent with the new key width, or if `bits` is 0 or greater than 64.
+  pub fn set_key_bits(&mut self, bits: u32) {
+    assert!(
+      self.vertices.is_empty(),
+      "key width must be set before adding vertices"
+    );
+    self.keyed.set_key_bits(bits);
+  }
+
+  fn keyed_id<K: HashKey + Hash>(&self, state: &K) -> Option<VertexId> {
+    let id = *self.state_ids.get(&self.keyed.key(state))?.id();
+    match self.keyed.audit {
+      Some(ref audit) => match audit.get(id) {
+        0 => Some(id),
+        check if check == KeyAudit::check(state) => Some(id),
+        _ => None,
+      },
+      None => Some(id),
+    }
+  }
+
+  /// Looks up or adds the vertex for the key of `state`, auditing it and
+  /// applying the collision policy if auditing is enabled.
+  fn keyed_vertex<K, F>(&mut self, state: &K, data: F) -> VertexId
+  where
+    K: HashKey + Hash,
+    F: for<'b> FnOnce(nav::Node<'b, u64, S, A>) -> S,
+  {
+    let check = KeyAudit::check(state);
+    match self.intern(self.keyed.key(state)) {
+      Insertion::New(id) => {
+        self.add_interned_vertex(id, |g| data(nav::Node::new(g, id)));
+        if let Some(ref mut audit) = self.keyed.audit {
+          audit.record(id, check);
+        }
+        id
+      }
+      Insertion::Present(id) => {
+        let collided = match self.keyed.audit {
+          Some(ref mut audit) => audit.record(id, check),
+          None => false,
+        };
+        if collided {
+          let incoming = match self.keyed.policy.clone() {
+            CollisionPolicy::Keep => None,
+            CollisionPolicy::Replace => Some(data(nav::Node::new(self, id))),
+            CollisionPolicy::Resolve(resolve) => {
+              let incoming = data(nav::Node::new(self, id));
+              match resolve(&self.get_vertex(id).data, &incoming) {
+                CollisionResolution::Keep => None,
+                CollisionResolution::Replace => Some(incoming),
+              }
+            }
+          };
+          if let Some(incoming) = incoming {
+            *self.get_data_mut(id) = incoming;
+            self.keyed.audit.as_mut().unwrap().checks[id.as_usize()] = check;
+          }
+        }
+        id
+      }
+    }
+  }
+
+  /// Gets a node handle for the vertex whose key is that of `state`.
+  ///
+  /// If no vertex has the key of `state`, or if key auditing is enabled and
+  /// the vertex with that key was added for a different game state, returns
+  /// `None`.
+  pub fn find_keyed_node<'s, K: HashKey + Hash>(
+    &'s self,
+    state: &K,
+  ) -> Option<nav::Node<'s, u64, S, A>> {
+    let id = self.keyed_id(state)?;
+    Some(nav::Node::new(self, id))
+  }
+
+  /// Gets a mutable node handle for the vertex whose key is that of `state`.
+  ///
+  /// Returns `None` under the same conditions as
+  /// [find_keyed_node](struct.Graph.html#method.find_keyed_node).
+  pub fn find_keyed_node_mut<'s, K: HashKey + Hash>(
+    &'s mut self,
+    state: &K,
+  ) -> Option<mutators::MutNode<'s, u64, S, A>> {
+    let id = self.keyed_id(state)?;
+    Some(mutators::MutNode::new(self, id))
+  }
+
+  /// Adds a vertex for the key of `state`, as
+  /// [add_node](struct.Graph.html#method.add_node) does. If auditing is
+  /// enabled, the vertex is audited for a collision, and on a collision, the
+  /// collision policy determines whether `data` replaces the vertex data.
+  pub fn add_keyed_node<'s, K: HashKey + Hash>(
+    &'s mut self,
+    state: &K,
+    data: S,
+  ) -> mutators::MutNode<'s, u64, S, A> {
+    let id = self.keyed_vertex(state, |_| data);
+    mutators::MutNode::new(self, id)
+  }
+
+  /// Adds an edge between the vertices for the keys of `source` and `dest`, as
+  /// [add_edge](struct.Graph.html#method.add_edge) does. Both vertices are
+  /// treated as by [add_keyed_node](struct.Graph.html#method.add_keyed_node),
+  /// and the data callbacks are also called to produce incoming data for the
+  /// collision policy.
+  pub fn add_keyed_edge<'s, K, F, G>(
+    &'s mut self,
+    source: &K,
+    source_data: F,
+    dest: &K,
+    dest_data: G,
+    edge_data: A,
+  ) -> mutators::MutEdge<'s, u64, S, A>
+  where
+    K: HashKey + Hash,
+    F: for<'b> FnOnce(nav::Node<'b, u64, S, A>) -> S,
+    G: for<'b> FnOnce(nav::Node<'b, u64, S, A>) -> S,
+  {
+    let source_id = self.keyed_vertex(source, source_data);
+    let dest_id = self.keyed_vertex(dest, dest_data);
+    let edge_id = self.add_raw_edge(edge_data, source_id, dest_id);
+    mutators::MutEdge::new(self, edge_id)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::{CollisionPolicy, CollisionResolution, HashKey, ZobristTable};
+  use std::sync::Arc;
+
+  type Graph = crate::Graph<u64, &'static str, &'static str>;
+
+  /// A game state whose key is deliberately weak, so that collisions are easy
+  /// to construct.
+  #[derive(Hash)]
+  struct State(&'static str);
+
+  impl HashKey for State {
+    fn hash_key(&self) -> u64 {
+      self.0.len() as u64
+    }
+  }
+
+  #[test]
+  fn zobrist_ok() {
+    let table = ZobristTable::new(64, 17);
+    assert_eq!(64, table.len());
+    assert_eq!(table.key(3), ZobristTable::new(64, 17).key(3));
+    assert_ne!(table.key(3), ZobristTable::new(64, 18).key(3));
+    let key = table.hash(vec![1, 2, 3]);
+    assert_eq!(table.hash(vec![1, 2]), table.toggle(key, 3));
+    assert_eq!(table.hash(vec![1, 2, 4]), table.replace(key, 3, 4));
+    assert_eq!(0, table.hash(vec![5, 5]));
+  }
+
+  #[test]
+  fn keyed_graph_ok() {
+    let mut g = Graph::new();
+    assert_eq!(None, g.key_collisions());
+    g.add_keyed_edge(
+      &State("a"),
+      |_| "a_data",
+      &State("bb"),
+      |_| "bb_data",
+      "a_bb",
+    );
+    assert_eq!(2, g.vertex_count());
+    let bb = g.find_keyed_node(&State("bb")).unwrap();
+    assert_eq!("bb_data", *bb.get_data());
+    // Without auditing, a colliding state resolves to the existing vertex.
+    assert_eq!(
+      bb.get_id(),
+      g.find_keyed_node(&State("cc")).unwrap().get_id()
+    );
+    assert!(g.find_keyed_node(&State("ccc")).is_none());
+  }
+
+  #[test]
+  fn keyed_graph_audit_ok() {
+    let mut g = Graph::new();
+    g.set_audit_keys(true);
+    g.add_keyed_edge(
+      &State("a"),
+      |_| "a_data",
+      &State("bb"),
+      |_| "bb_data",
+      "a_bb",
+    );
+    g.add_keyed_node(&State("bb"), "ignored");
+    assert_eq!(Some(0), g.key_collisions());
+    g.add_keyed_node(&State("cc"), "ignored");
+    assert_eq!(Some(1), g.key_collisions());
+    assert_eq!(2, g.vertex_count());
+    assert!(g.find_keyed_node(&State("bb")).is_some());
+    assert!(g.find_keyed_node(&State("cc")).is_none());
+    assert!(g.find_keyed_node_mut(&State("cc")).is_none());
+    g.set_audit_keys(false);
+    assert_eq!(None, g.key_collisions());
+  }
+
+  #[test]
+  fn keyed_graph_audit_retain_reachable_ok() {
+    let mut g = Graph::new();
+    g.set_audit_keys(true);
+    g.add_keyed_edge(
+      &State("a"),
+      |_| "a_data",
+      &State("bb"),
+      |_| "bb_data",
+      "a_bb",
+    );
+    g.add_keyed_edge(
+      &State("ddd"),
+      |_| "ddd_data",
+      &State("bb"),
+      |_| "bb_data",
+      "ddd_bb",
+    );
+    crate::view::of_graph(&mut g, |v| {
+      let root = v.find_node(&3).unwrap();
+      v.retain_reachable_from(vec![root]);
+    });
+    assert_eq!(2, g.vertex_count());
+    assert!(g.find_keyed_node(&State("a")).is_none());
+    assert!(g.find_keyed_node(&State("ddd")).is_some());
+    assert!(g.find_keyed_node(&State("bb")).is_some());
+    assert!(g.find_keyed_node(&State("cc")).is_none());
+  }
+
+  #[test]
+  fn collision_policy_replace_ok() {
+    let mut g = Graph::new();
+    g.set_collision_policy(CollisionPolicy::Replace);
+    g.add_keyed_edge(
+      &State("a"),
+      |_| "a_data",
+      &State("bb"),
+      |_| "bb_data",
+      "a_bb",
+    );
+    g.add_keyed_node(&State("cc"), "cc_data");
+    assert_eq!(Some(1), g.key_collisions());
+    assert!(g.find_keyed_node(&State("bb")).is_none());
+    let cc = g.find_keyed_node(&State("cc")).unwrap();
+    assert_eq!("cc_data", *cc.get_data());
+    assert_eq!(1, cc.get_parent_list().len());
+  }
+
+  #[test]
+  fn collision_policy_resolve_ok() {
+    let mut g = crate::Graph::<u64, u32, ()>::new();
+    g.set_collision_policy(CollisionPolicy::Resolve(Arc::new(|existing, incoming| {
+      if incoming > existing {
+        CollisionResolution::Replace
+      } else {
+        CollisionResolution::Keep
+      }
+    })));
+    g.add_keyed_node(&State("aa"), 5);
+    g.add_keyed_node(&State("bb"), 3);
+    assert!(g.find_keyed_node(&State("aa")).is_some());
+    g.add_keyed_edge(&State("c"), |_| 0, &State("dd"), |_| 7, ());
+    assert_eq!(Some(2), g.key_collisions());
+    assert!(g.find_keyed_node(&State("aa")).is_none());
+    assert_eq!(7, *g.find_keyed_node(&State("dd")).unwrap().get_data());
+  }
+
+  #[test]
+  fn key_bits_ok() {
+    let mut g = crate::Graph::<u64, (), ()>::new();
+    g.set_key_bits(2);
+    for i in 0..16u64 {
+      g.add_keyed_node(&i, ());
+    }
+    assert_eq!(4, g.vertex_count());
+    assert!(g.find_keyed_node(&13u64).is_some());
+
+    let mut g = crate::Graph::<u64, (), ()>::with_config(crate::config::GraphConfig {
+      key_bits: 3,
+      ..Default::default()
+    });
+    for i in 0..16u64 {
+      g.add_keyed_node(&i, ());
+    }
+    assert_eq!(8, g.vertex_count());
+  }
+}
@@ -0,0 +1,171 @@
+//! Vertex data that may be read and written without locks.
+//!
+//! Statistics that a parallel search reads on every selection step (such as
+//! visit counts and value sums) can be stored in a fixed number of atomic
+//! words per vertex, apart from the vertex's own data. The number of words is
+//! chosen when a graph is created, with
+//! [GraphConfig::hot_words](../config/struct.GraphConfig.html#structfield.hot_words),
+//! and the words of a vertex are returned by
+//! [Graph::hot_data](../struct.Graph.html#method.hot_data), which needs only
+//! a shared borrow of the graph. Threads that share a `&Graph` may then read
+//! and update hot data with atomic operations while no thread holds a
+//! handle, and without locking anything.
+//!
+//! Hot data starts at zero for each new vertex and follows its vertex through
+//! garbage collection and [partitioning](../partition/index.html). It is
+//! not part of vertex data, so it is not compared, exported, or archived with
+//! it.
+//!
+//! ```rust
+//! # use search_graph::Graph;
+//! # use search_graph::config::GraphConfig;
+//! # use std::sync::atomic::Ordering;
+//! # fn main() {
+//! let mut g: Graph<u32, (), ()> = Graph::with_config(GraphConfig {
+//!   hot_words: 2,
+//!   ..Default::default()
+//! });
+//! g.add_edge(0, |_| (), 1, |_| (), ());
+//! let id = g.find_node(&1).unwrap().get_id();
+//! let g = &g;
+//! std::thread::scope(|scope| {
+//!   for _ in 0..4 {
+//!     scope.spawn(move || g.hot_data(id)[0].fetch_add(1, Ordering::Relaxed));
+//!   }
+//! });
+//! assert_eq!(g.hot_data(id)[0].load(Ordering::Relaxed), 4);
+//! # }
+//! ```
+
+use std::hash::Hash;
+use std::sync::atomic::AtomicU64;
+
+use crate::Graph;
+
+/// The hot data of each vertex in a graph, stored contiguously in order of
+/// `VertexId`.
+#[derive(Debug, Default)]
+pub(crate) struct HotTable {
+  /// The number of words per vertex.
+  pub words: usize,
+  pub values: Vec<AtomicU64>,
+}
+
+impl HotTable {
+  /// Returns an empty table with `words` words per vertex.
+  pub fn new(words: usize) -> Self {
+    HotTable {
+      words,
+      values: Vec::new(),
+    }
+  }
+
+  /// Returns the words of the vertex with the given index.
+  pub fn get(&self, index: usize) -> &[AtomicU64] {
+    &self.values[index * self.words..(index + 1) * self.words]
+  }
+
+  /// Adds zeroed words for a vertex, which must be the next in ID order.
+  pub fn create(&mut self) {
+    let len = self.values.len() + self.words;
+    self.values.resize_with(len, Default::default);
+  }
+
+  /// Moves the words of each vertex with index `i` to index `f(i)`, dropping
+  /// those of vertices for which `f` returns `None`. The vertices that are kept
+  /// must be mapped onto `0..count`.
+  pub fn compact<F: Fn(usize) -> Option<usize>>(&mut self, count: usize, f: F) {
+    let mut values: Vec<AtomicU64> = Vec::with_capacity(count * self.words);
+    values.resize_with(count * self.words, Default::default);
+    for (i, old) in self.values.chunks_mut(self.words.max(1)).enumerate() {
+      if let Some(j) = f(i) {
+        for (new, old) in values[j * self.words..].iter_mut().zip(old.iter_mut()) {
+          *new.get_mut() = *old.get_mut();
+        }
+      }
+    }
+    self.values = values;
+  }
+}
+
+impl<T: Hash + Eq + Clone, S, A> Graph<T, S, A> {
+  /// Returns the hot data of the vertex with the given ID (as returned by
+  /// `get_id` on node handles), which has as many words as configured by
+  /// [GraphConfig::hot_words](config/struct.GraphConfig.html#structfield.hot_words).
+  /// Panics if there is no vertex with that ID.
+  pub fn hot_data(&self, id: usize) -> &[AtomicU64] {
+    assert!(id < self.vertices.len(), "no vertex with ID {}", id);
+    self.hot.get(id)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use crate::config::GraphConfig;
+  use std::sync::atomic::Ordering;
+
+  type Graph = crate::Graph<&'static str, &'static str, ()>;
+
+  fn make_graph() -> Graph {
+    let mut g = Graph::with_config(GraphConfig {
+      hot_words: 2,
+      ..Default::default()
+    });
+    g.add_edge("root", |_| "root", "a", |_| "a", ());
+    g.add_edge("root", |_| "root", "b", |_| "b", ());
+    g.add_edge("b", |_| "b", "c", |_| "c", ());
+    for (i, state) in ["root", "a", "b", "c"].iter().enumerate() {
+      let id = g.find_node(state).unwrap().get_id();
+      g.hot_data(id)[0].store(i as u64, Ordering::Relaxed);
+      g.hot_data(id)[1].store(10 * i as u64, Ordering::Relaxed);
+    }
+    g
+  }
+
+  fn hot(g: &Graph, state: &'static str) -> (u64, u64) {
+    let data = g.hot_data(g.find_node(&state).unwrap().get_id());
+    (
+      data[0].load(Ordering::Relaxed),
+      data[1].load(Ordering::Relaxed),
+    )
+  }
+
+  #[test]
+  fn hot_data_ok() {
+    let mut g = make_graph();
+    assert_eq!((1, 10), hot(&g, "a"));
+    assert_eq!((3, 30), hot(&g, "c"));
+    g.add_node("d", "d");
+    assert_eq!((0, 0), hot(&g, "d"));
+    let mut g = Graph::new();
+    g.add_node("root", "root");
+    assert_eq!(0, g.hot_data(0).len());
+  }
+
+  #[test]
+  #[should_panic(expected = "no vertex with ID 4")]
+  fn hot_data_err() {
+    make_graph().hot_data(4);
+  }
+
+  #[test]
+  fn hot_data_gc_ok() {
+    let mut g = make_graph();
+    crate::view::of_graph(&mut g, |v| {
+      let b = v.find_node(&"b").unwrap();
+      v.retain_reachable_from(vec![b]);
+    });
+    assert_eq!(2, g.vertex_count());
+    assert_eq!((2, 20), hot(&g, "b"));
+    assert_eq!((3, 30), hot(&g, "c"));
+  }
+
+  #[test]
+  fn hot_data_partition_ok() {
+    let (left, right) = make_graph().partition(|state, _| *state == "b" || *state == "c");
+    assert_eq!((2, 20), hot(&left, "b"));
+    assert_eq!((3, 30), hot(&left, "c"));
+    assert_eq!((1, 10), hot(&right, "a"));
+    assert_eq!(2, right.hot_data(0).len());
+  }
+}
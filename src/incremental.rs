@@ -0,0 +1,290 @@
+//! Garbage collection that is spread across many short calls.
+//!
+//! Collecting a large graph with
+//! [View::retain_reachable_from](../view/struct.View.html#method.retain_reachable_from)
+//! traverses everything that is reachable from the roots in one pause. An
+//! [IncrementalCollector](struct.IncrementalCollector.html) instead marks
+//! reachable vertices a bounded amount at a time: each call to
+//! [collect_step](struct.IncrementalCollector.html#method.collect_step)
+//! examines at most a given number of vertices and edges and returns, so that
+//! a search may interleave collection with its own iterations and keep
+//! modifying the graph in between.
+//!
+//! Marking is conservative with respect to changes made between steps.
+//! Vertices added after marking began are retained, as are the targets of
+//! edges added after it began, so nothing that became reachable in the
+//! meantime is dropped. Garbage that is created during marking is left for the
+//! next collection. If vertex or edge IDs are reassigned between steps (e.g.,
+//! by another collection or by removing edges), marking starts over.
+//!
+//! Once marking is finished, the step that finishes it compacts the graph,
+//! dropping unmarked vertices and their incident edges as
+//! [Graph::retain_nodes](../struct.Graph.html#method.retain_nodes) would. This
+//! does not traverse the graph again, but it does take time linear in the size
+//! of the graph, and it reassigns IDs as described by
+//! [last_remap](../struct.Graph.html#method.last_remap). It is skipped if
+//! nothing is unreachable.
+//!
+//! ```rust
+//! # use search_graph::Graph;
+//! # use search_graph::incremental::{CollectStep, IncrementalCollector};
+//! # fn main() {
+//! let mut g: Graph<u32, (), ()> = Graph::new();
+//! for i in 0..100 {
+//!   g.add_edge(i, |_| (), i + 1, |_| (), ());
+//! }
+//! let mut collector = IncrementalCollector::new(&[50]);
+//! let mut steps = 1;
+//! while collector.collect_step(&mut g, 10) == CollectStep::Marking {
+//!   steps += 1;
+//! }
+//! assert!(steps > 1);
+//! assert_eq!(g.vertex_count(), 51);
+//! assert!(g.find_node(&49).is_none());
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+use std::hash::Hash;
+
+use crate::base::VertexId;
+use crate::mark_compact::Collector;
+use crate::Graph;
+use symbol_map::SymbolId;
+
+/// The outcome of a call to
+/// [IncrementalCollector::collect_step](struct.IncrementalCollector.html#method.collect_step).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum CollectStep {
+  /// Marking is not finished, and the graph has not been modified.
+  Marking,
+  /// Marking finished and unreachable vertices were dropped, along with their
+  /// incident edges. The next step starts a new collection.
+  Collected {
+    /// The number of vertices that were dropped.
+    vertices: usize,
+    /// The number of edges that were dropped.
+    edges: usize,
+  },
+}
+
+/// Garbage collection state that persists between calls, as described in the
+/// [incremental](index.html) module.
+#[derive(Clone, Debug)]
+pub struct IncrementalCollector<T> {
+  /// The game states from which vertices are retained.
+  roots: Vec<T>,
+  /// The graph version that marking began at, if it has begun.
+  version: Option<u64>,
+  /// Whether each vertex that existed when marking began has been marked,
+  /// indexed by `VertexId`.
+  marked: Vec<bool>,
+  /// Marked vertices whose children have not all been examined.
+  frontier: VecDeque<VertexId>,
+  /// The number of children of the vertex at the front of `frontier` that
+  /// have been examined.
+  next_child: usize,
+  /// The number of edges that have been checked for new targets.
+  edges_seen: usize,
+}
+
+impl<T: Hash + Eq + Clone> IncrementalCollector<T> {
+  /// Creates a collector that retains the vertices that are reachable from
+  /// the vertices for `roots`. Game states in `roots` that are not in the
+  /// graph are ignored.
+  pub fn new(roots: &[T]) -> Self {
+    IncrementalCollector {
+      roots: roots.to_vec(),
+      version: None,
+      marked: Vec::new(),
+      frontier: VecDeque::new(),
+      next_child: 0,
+      edges_seen: 0,
+    }
+  }
+
+  /// Replaces the roots of this collector. Any marking in progress starts
+  /// over.
+  pub fn set_roots(&mut self, roots: &[T]) {
+    self.roots = roots.to_vec();
+    self.version = None;
+  }
+
+  /// Returns true iff marking has begun and has not yet finished.
+  pub fn is_marking(&self) -> bool {
+    self.version.is_some()
+  }
+
+  /// Examines at most `budget` vertices and edges of `graph`, beginning
+  /// marking if it has not begun, and compacts the graph if marking is
+  /// finished. The same graph must be passed to each step of a collection.
+  pub fn collect_step<S, A>(&mut self, graph: &mut Graph<T, S, A>, budget: usize) -> CollectStep {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::debug_span!("collect_step", budget).entered();
+    if self.version != Some(graph.version) {
+      self.start(graph);
+    }
+    let mut work = 0;
+    // Edges that were added since the last step may lead to vertices that
+    // have not been marked.
+    while self.edges_seen < graph.arcs.len() {
+      if work >= budget {
+        return CollectStep::Marking;
+      }
+      let target = graph.arcs[self.edges_seen].target;
+      self.mark(target);
+      self.edges_seen += 1;
+      work += 1;
+    }
+    while work < budget {
+      let id = match self.frontier.front() {
+        Some(id) => *id,
+        None => return self.finish(graph),
+      };
+      let children = &graph.vertices[id.as_usize()].children;
+      while self.next_child < children.len() && work < budget {
+        let target = graph.arcs[children[self.next_child].as_usize()].target;
+        self.mark(target);
+        self.next_child += 1;
+        work += 1;
+      }
+      if self.next_child == children.len() {
+        self.frontier.pop_front();
+        self.next_child = 0;
+        work += 1;
+      }
+    }
+    CollectStep::Marking
+  }
+
+  /// Begins marking `graph` from the roots.
+  fn start<S, A>(&mut self, graph: &Graph<T, S, A>) {
+    self.version = Some(graph.version);
+    self.marked = vec![false; graph.vertices.len()];
+    self.frontier.clear();
+    self.next_child = 0;
+    self.edges_seen = graph.arcs.len();
+    let roots: Vec<VertexId> = self
+      .roots
+      .iter()
+      .filter_map(|state| graph.lookup(state))
+      .collect();
+    for id in roots {
+      self.mark(id);
+    }
+  }
+
+  /// Marks `id` and adds it to the frontier, if it existed when marking began
+  /// and has not been marked.
+  fn mark(&mut self, id: VertexId) {
+    if let Some(marked) = self.marked.get_mut(id.as_usize()) {
+      if !*marked {
+        *marked = true;
+        self.frontier.push_back(id);
+      }
+    }
+  }
+
+  /// Drops the vertices that were not marked, which must be the only ones
+  /// that are unreachable.
+  fn finish<S, A>(&mut self, graph: &mut Graph<T, S, A>) -> CollectStep {
+    self.version = None;
+    let mut keep = std::mem::take(&mut self.marked);
+    keep.resize(graph.vertices.len(), true);
+    let roots: Vec<VertexId> = (0..keep.len()).filter(|i| keep[*i]).map(VertexId).collect();
+    let vertices = keep.len() - roots.len();
+    if vertices == 0 {
+      return CollectStep::Collected {
+        vertices: 0,
+        edges: 0,
+      };
+    }
+    let edge_count = graph.arcs.len();
+    Collector::retain_selected(graph, &roots, keep);
+    CollectStep::Collected {
+      vertices,
+      edges: edge_count - graph.arcs.len(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::{CollectStep, IncrementalCollector};
+
+  type Graph = crate::Graph<&'static str, &'static str, &'static str>;
+
+  fn make_graph() -> Graph {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| "", "a", |_| "", "root_a");
+    g.add_edge("root", |_| "", "b", |_| "", "root_b");
+    g.add_edge("a", |_| "", "c", |_| "", "a_c");
+    g.add_edge("b", |_| "", "c", |_| "", "b_c");
+    g.add_edge("c", |_| "", "d", |_| "", "c_d");
+    g.add_edge("e", |_| "", "a", |_| "", "e_a");
+    g
+  }
+
+  #[test]
+  fn collect_step_ok() {
+    let mut g = make_graph();
+    let mut collector = IncrementalCollector::new(&["a", "missing"]);
+    assert!(!collector.is_marking());
+    assert_eq!(CollectStep::Marking, collector.collect_step(&mut g, 1));
+    assert!(collector.is_marking());
+    assert_eq!(6, g.vertex_count());
+    // A new vertex, and an old vertex that becomes reachable during marking,
+    // are retained.
+    g.add_edge("d", |_| "", "b", |_| "", "d_b");
+    g.add_edge("d", |_| "", "f", |_| "", "d_f");
+    let mut steps = 0;
+    let step = loop {
+      steps += 1;
+      match collector.collect_step(&mut g, 2) {
+        CollectStep::Marking => (),
+        step => break step,
+      }
+    };
+    assert!(steps > 2);
+    assert_eq!(
+      CollectStep::Collected {
+        vertices: 2,
+        edges: 3
+      },
+      step
+    );
+    assert!(!collector.is_marking());
+    let mut states: Vec<_> = g.states().cloned().collect();
+    states.sort();
+    assert_eq!(vec!["a", "b", "c", "d", "f"], states);
+    assert_eq!(5, g.edge_count());
+
+    // Nothing is unreachable, so a second collection leaves the graph as is.
+    let version = g.version;
+    while collector.collect_step(&mut g, 100) == CollectStep::Marking {}
+    assert_eq!(version, g.version);
+  }
+
+  #[test]
+  fn collect_step_restart_ok() {
+    let mut g = make_graph();
+    let mut collector = IncrementalCollector::new(&["root"]);
+    assert_eq!(CollectStep::Marking, collector.collect_step(&mut g, 2));
+    // Reassigning IDs between steps restarts marking.
+    g.retain_edges(|e| *e.get_data() != "root_b");
+    assert_eq!(CollectStep::Marking, collector.collect_step(&mut g, 2));
+    while collector.collect_step(&mut g, 2) == CollectStep::Marking {}
+    assert!(g.find_node(&"b").is_none());
+    assert!(g.find_node(&"e").is_none());
+    assert_eq!(4, g.vertex_count());
+
+    collector.set_roots(&["c"]);
+    assert_eq!(
+      CollectStep::Collected {
+        vertices: 2,
+        edges: 2
+      },
+      collector.collect_step(&mut g, 100)
+    );
+  }
+}
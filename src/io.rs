@@ -0,0 +1,9 @@
+//! Export of graphs to formats that are consumed by other tools.
+//!
+//! Each format is provided by a submodule that is enabled by a feature of the
+//! same name.
+
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(feature = "dot")]
+pub mod dot;
@@ -0,0 +1,262 @@
+//! Export of graphs as [Apache Arrow](https://arrow.apache.org) record batches.
+//!
+//! Vertices and edges are exported as separate tables, which may be written
+//! to Parquet or Arrow IPC files with the `parquet` and `arrow-ipc` crates, or
+//! handed directly to query engines that consume Arrow data.
+//!
+//! The vertex table has a `UInt64` column `id` with the vertex ID, a `Utf8`
+//! column `state` with each game state serialized by a caller-provided
+//! closure, and columns for vertex data as determined by a
+//! [SchemaAdapter](trait.SchemaAdapter.html). The edge table has `UInt64`
+//! columns `id`, `source`, and `target`, followed by columns for edge data.
+//! Vertex and edge IDs are those returned by `get_id` on node and edge
+//! handles, so the tables may be joined on them.
+//!
+//! Tables are produced as a sequence of record batches of bounded size, so
+//! that large graphs can be exported without building a copy of the whole
+//! graph in memory.
+//!
+//! ```rust
+//! # use std::sync::Arc;
+//! # use arrow_array::{ArrayRef, UInt32Array};
+//! # use arrow_schema::{DataType, Field};
+//! # use search_graph::Graph;
+//! # use search_graph::io::arrow::{self, NoColumns, SchemaAdapter};
+//! struct Visits;
+//!
+//! impl SchemaAdapter<u32> for Visits {
+//!   fn fields(&self) -> Vec<Field> {
+//!     vec![Field::new("visits", DataType::UInt32, false)]
+//!   }
+//!
+//!   fn columns(&self, data: &[&u32]) -> Vec<ArrayRef> {
+//!     vec![Arc::new(data.iter().map(|d| **d).collect::<UInt32Array>())]
+//!   }
+//! }
+//!
+//! # fn main() {
+//! let mut g: Graph<String, u32, ()> = Graph::new();
+//! g.add_edge("root".to_string(), |_| 10, "a".to_string(), |_| 3, ());
+//! let batches: Vec<_> = arrow::vertex_batches(&g, |s| s.clone(), &Visits, 1024)
+//!   .collect::<Result<_, _>>()
+//!   .unwrap();
+//! assert_eq!(batches.len(), 1);
+//! assert_eq!(batches[0].num_rows(), 2);
+//! assert_eq!(batches[0].schema().field(2).name(), "visits");
+//! let edges = arrow::edge_batches(&g, &NoColumns, 1024).next().unwrap().unwrap();
+//! assert_eq!(edges.num_columns(), 3);
+//! # }
+//! ```
+
+use std::hash::Hash;
+use std::sync::Arc;
+
+use arrow_array::{ArrayRef, RecordBatch, StringArray, UInt64Array};
+use arrow_schema::{ArrowError, DataType, Field, Schema, SchemaRef};
+
+use crate::base::VertexId;
+use crate::Graph;
+use symbol_map::SymbolId;
+
+/// Converts vertex or edge data into Arrow columns.
+pub trait SchemaAdapter<D> {
+  /// Returns the fields of the columns that this adapter produces.
+  fn fields(&self) -> Vec<Field>;
+
+  /// Returns one column for each field returned by `fields`, each having one
+  /// row for each element of `data`.
+  fn columns(&self, data: &[&D]) -> Vec<ArrayRef>;
+}
+
+/// A [SchemaAdapter](trait.SchemaAdapter.html) that produces no columns, for
+/// data that should not be exported.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoColumns;
+
+impl<D> SchemaAdapter<D> for NoColumns {
+  fn fields(&self) -> Vec<Field> {
+    Vec::new()
+  }
+
+  fn columns(&self, _: &[&D]) -> Vec<ArrayRef> {
+    Vec::new()
+  }
+}
+
+fn id_field(name: &str) -> Field {
+  Field::new(name, DataType::UInt64, false)
+}
+
+/// Returns the schema of the vertex table for vertex data exported by
+/// `adapter`.
+pub fn vertex_schema<S, V: SchemaAdapter<S>>(adapter: &V) -> SchemaRef {
+  let mut fields = vec![id_field("id"), Field::new("state", DataType::Utf8, false)];
+  fields.extend(adapter.fields());
+  Arc::new(Schema::new(fields))
+}
+
+/// Returns the schema of the edge table for edge data exported by `adapter`.
+pub fn edge_schema<A, E: SchemaAdapter<A>>(adapter: &E) -> SchemaRef {
+  let mut fields = vec![id_field("id"), id_field("source"), id_field("target")];
+  fields.extend(adapter.fields());
+  Arc::new(Schema::new(fields))
+}
+
+/// Returns the start of each batch of at most `batch_size` rows out of `len`.
+fn batch_starts(len: usize, batch_size: usize) -> std::iter::StepBy<std::ops::Range<usize>> {
+  assert!(batch_size > 0, "batch size must be positive");
+  (0..len).step_by(batch_size)
+}
+
+/// Returns an iterator over record batches of at most `batch_size` rows that
+/// make up the vertex table of `graph`. Game states are serialized with
+/// `state`, and vertex data is exported with `adapter`.
+///
+/// Panics if `batch_size` is 0.
+pub fn vertex_batches<'a, T, S, A, F, V>(
+  graph: &'a Graph<T, S, A>,
+  state: F,
+  adapter: &'a V,
+  batch_size: usize,
+) -> impl Iterator<Item = Result<RecordBatch, ArrowError>> + 'a
+where
+  T: Hash + Eq + Clone,
+  F: Fn(&T) -> String + 'a,
+  V: SchemaAdapter<S>,
+{
+  let schema = vertex_schema(adapter);
+  let len = graph.vertices.len();
+  batch_starts(len, batch_size).map(move |start| {
+    let ids = start..(start + batch_size).min(len);
+    let mut columns: Vec<ArrayRef> = vec![
+      Arc::new(ids.clone().map(|i| i as u64).collect::<UInt64Array>()),
+      Arc::new(
+        ids
+          .clone()
+          .map(|i| Some(state(graph.get_state(VertexId(i)).unwrap())))
+          .collect::<StringArray>(),
+      ),
+    ];
+    let data: Vec<&S> = graph.vertices[ids].iter().map(|v| &v.data).collect();
+    columns.extend(adapter.columns(&data));
+    RecordBatch::try_new(schema.clone(), columns)
+  })
+}
+
+/// Returns an iterator over record batches of at most `batch_size` rows that
+/// make up the edge table of `graph`. Edge data is exported with `adapter`.
+///
+/// Panics if `batch_size` is 0.
+pub fn edge_batches<'a, T, S, A, E>(
+  graph: &'a Graph<T, S, A>,
+  adapter: &'a E,
+  batch_size: usize,
+) -> impl Iterator<Item = Result<RecordBatch, ArrowError>> + 'a
+where
+  T: Hash + Eq + Clone,
+  E: SchemaAdapter<A>,
+{
+  let schema = edge_schema(adapter);
+  let len = graph.arcs.len();
+  batch_starts(len, batch_size).map(move |start| {
+    let ids = start..(start + batch_size).min(len);
+    let arcs = &graph.arcs[ids.clone()];
+    let mut columns: Vec<ArrayRef> = vec![
+      Arc::new(ids.map(|i| i as u64).collect::<UInt64Array>()),
+      Arc::new(
+        arcs
+          .iter()
+          .map(|a| a.source.as_usize() as u64)
+          .collect::<UInt64Array>(),
+      ),
+      Arc::new(
+        arcs
+          .iter()
+          .map(|a| a.target.as_usize() as u64)
+          .collect::<UInt64Array>(),
+      ),
+    ];
+    let data: Vec<&A> = arcs.iter().map(|a| &a.data).collect();
+    columns.extend(adapter.columns(&data));
+    RecordBatch::try_new(schema.clone(), columns)
+  })
+}
+
+#[cfg(test)]
+mod test {
+  use super::{edge_batches, vertex_batches, NoColumns, SchemaAdapter};
+  use arrow_array::{Array, ArrayRef, StringArray, UInt64Array};
+  use arrow_schema::{DataType, Field};
+  use std::sync::Arc;
+
+  type Graph = crate::Graph<&'static str, &'static str, &'static str>;
+
+  struct Label;
+
+  impl SchemaAdapter<&'static str> for Label {
+    fn fields(&self) -> Vec<Field> {
+      vec![Field::new("label", DataType::Utf8, false)]
+    }
+
+    fn columns(&self, data: &[&&'static str]) -> Vec<ArrayRef> {
+      vec![Arc::new(
+        data.iter().map(|d| Some(**d)).collect::<StringArray>(),
+      )]
+    }
+  }
+
+  fn make_graph() -> Graph {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| "root_data", "a", |_| "a_data", "root_a");
+    g.add_edge("root", |_| "root_data", "b", |_| "b_data", "root_b");
+    g.add_edge("a", |_| "a_data", "b", |_| "b_data", "a_b");
+    g
+  }
+
+  fn column<'a, C: 'static>(batch: &'a arrow_array::RecordBatch, name: &str) -> &'a C {
+    batch
+      .column_by_name(name)
+      .unwrap()
+      .as_any()
+      .downcast_ref::<C>()
+      .unwrap()
+  }
+
+  #[test]
+  fn vertex_batches_ok() {
+    let g = make_graph();
+    let batches: Vec<_> = vertex_batches(&g, |s| s.to_string(), &Label, 2)
+      .collect::<Result<_, _>>()
+      .unwrap();
+    assert_eq!(2, batches.len());
+    assert_eq!(2, batches[0].num_rows());
+    assert_eq!(1, batches[1].num_rows());
+    let states = column::<StringArray>(&batches[0], "state");
+    assert_eq!("root", states.value(0));
+    assert_eq!("a", states.value(1));
+    let labels = column::<StringArray>(&batches[1], "label");
+    assert_eq!("b_data", labels.value(0));
+    assert_eq!(2, column::<UInt64Array>(&batches[1], "id").value(0));
+  }
+
+  #[test]
+  fn edge_batches_ok() {
+    let g = make_graph();
+    let batches: Vec<_> = edge_batches(&g, &NoColumns, 10)
+      .collect::<Result<_, _>>()
+      .unwrap();
+    assert_eq!(1, batches.len());
+    assert_eq!(3, batches[0].num_columns());
+    let sources = column::<UInt64Array>(&batches[0], "source");
+    let targets = column::<UInt64Array>(&batches[0], "target");
+    assert_eq!(vec![0, 0, 1], sources.values().to_vec());
+    assert_eq!(vec![1, 2, 2], targets.values().to_vec());
+    assert_eq!(0, sources.null_count());
+  }
+
+  #[test]
+  fn empty_graph_ok() {
+    let g = Graph::new();
+    assert_eq!(0, edge_batches(&g, &Label, 10).count());
+  }
+}
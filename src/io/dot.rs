@@ -0,0 +1,335 @@
+//! Export of graphs in the [Graphviz](https://graphviz.org) DOT language.
+//!
+//! A [Dot](struct.Dot.html) renders either a whole graph or the part of it
+//! that is reachable from a chosen game state, optionally limited to a number
+//! of edges from that state. Vertices and edges are labeled by caller-provided
+//! closures. Vertices that are not shown with all of their children because of
+//! the depth limit are drawn dashed.
+//!
+//! The edges of a search path (e.g., a [Stack](../../search/struct.Stack.html)
+//! or a [Path](../../nav/struct.Path.html)) and the vertices along it may be
+//! highlighted, which is useful for seeing which way a search went. Highlights
+//! refer to vertex and edge IDs, so they should be set after the graph was
+//! last modified.
+//!
+//! ```rust
+//! # use search_graph::Graph;
+//! # use search_graph::io::dot::Dot;
+//! # use search_graph::nav::Path;
+//! # fn main() {
+//! let mut g: Graph<&str, u32, char> = Graph::new();
+//! g.add_edge("root", |_| 2, "a", |_| 1, 'x');
+//! g.add_edge("a", |_| 1, "b", |_| 0, 'y');
+//! let mut dot = Dot::rooted("root");
+//! dot.set_max_depth(1);
+//! let path = Path::from_edge_ids(&g, &[0]).unwrap();
+//! dot.highlight_path(&path);
+//! let text = dot.render(&g, |s, d| format!("{} ({})", s, d), |m| m.to_string());
+//! assert_eq!(
+//!   text,
+//!   "digraph {\n  \
+//!    n0 [label=\"root (2)\", color=red, penwidth=2];\n  \
+//!    n1 [label=\"a (1)\", color=red, penwidth=2, style=dashed];\n  \
+//!    n0 -> n1 [label=\"x\", color=red, penwidth=2];\n\
+//!    }\n"
+//! );
+//! # }
+//! ```
+
+use std::collections::{HashSet, VecDeque};
+use std::fmt::Write as _;
+use std::hash::Hash;
+use std::io::{self, Write};
+
+use crate::base::VertexId;
+use crate::nav::{Node, Path};
+use crate::search::{Stack, StackItem};
+use crate::Graph;
+
+/// The attributes that are added to highlighted vertices and edges.
+const HIGHLIGHT: &str = ", color=red, penwidth=2";
+
+/// Renders a graph, or part of one, in the DOT language.
+#[derive(Clone, Debug)]
+pub struct Dot<T> {
+  root: Option<T>,
+  max_depth: Option<usize>,
+  vertices: HashSet<usize>,
+  edges: HashSet<usize>,
+}
+
+impl<T: Hash + Eq + Clone> Default for Dot<T> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<T: Hash + Eq + Clone> Dot<T> {
+  /// Creates a renderer for whole graphs.
+  pub fn new() -> Self {
+    Dot {
+      root: None,
+      max_depth: None,
+      vertices: HashSet::new(),
+      edges: HashSet::new(),
+    }
+  }
+
+  /// Creates a renderer for the part of a graph that is reachable from
+  /// `root`.
+  pub fn rooted(root: T) -> Self {
+    Dot {
+      root: Some(root),
+      ..Self::new()
+    }
+  }
+
+  /// Sets the maximum number of edges between the root and a vertex that is
+  /// shown. This has no effect on renderers for whole graphs.
+  pub fn set_max_depth(&mut self, max_depth: usize) {
+    self.max_depth = Some(max_depth);
+  }
+
+  /// Highlights the edges of `path` and the vertices along it.
+  pub fn highlight_path<S, A>(&mut self, path: &Path<T, S, A>) {
+    self.vertices.insert(path.first_node().get_id());
+    for e in path.iter() {
+      self.edges.insert(e.get_id());
+      self.vertices.insert(e.get_target().get_id());
+    }
+  }
+
+  /// Highlights the edges that `stack` has traversed and the vertices along
+  /// them, including its head.
+  pub fn highlight_stack<S, A>(&mut self, stack: &Stack<T, S, A>) {
+    for item in stack.iter() {
+      match item {
+        StackItem::Item(e) => {
+          self.edges.insert(e.get_id());
+          self.vertices.insert(e.get_source().get_id());
+          self.vertices.insert(e.get_target().get_id());
+        }
+        StackItem::Head(n) => {
+          self.vertices.insert(n.get_id());
+        }
+      }
+    }
+  }
+
+  /// Removes all highlights.
+  pub fn clear_highlights(&mut self) {
+    self.vertices.clear();
+    self.edges.clear();
+  }
+
+  /// Returns the vertices to render, each with a flag that is true iff its
+  /// children are to be rendered.
+  fn select<'a, S, A>(&self, graph: &'a Graph<T, S, A>) -> Vec<(Node<'a, T, S, A>, bool)> {
+    let root = match self.root {
+      Some(ref root) => root,
+      None => {
+        return (0..graph.vertices.len())
+          .map(|i| (Node::new(graph, VertexId(i)), true))
+          .collect()
+      }
+    };
+    let mut selected = Vec::new();
+    let root = match graph.find_node(root) {
+      Some(root) => root,
+      None => return selected,
+    };
+    let mut seen = HashSet::new();
+    seen.insert(root.get_id());
+    let mut queue = VecDeque::new();
+    queue.push_back((root, 0));
+    while let Some((node, depth)) = queue.pop_front() {
+      let expand = self.max_depth.is_none_or(|max| depth < max);
+      if expand {
+        for e in node.get_child_list().iter() {
+          let target = e.get_target();
+          if seen.insert(target.get_id()) {
+            queue.push_back((target, depth + 1));
+          }
+        }
+      }
+      selected.push((node, expand));
+    }
+    selected
+  }
+
+  /// Renders `graph` as a DOT digraph. Vertices are labeled by `vertex` and
+  /// edges by `edge`. Vertices are named by their IDs. If the root of a
+  /// rooted renderer is not in the graph, the digraph is empty.
+  pub fn render<S, A, F, G>(&self, graph: &Graph<T, S, A>, vertex: F, edge: G) -> String
+  where
+    F: Fn(&T, &S) -> String,
+    G: Fn(&A) -> String,
+  {
+    let selected = self.select(graph);
+    let mut out = String::from("digraph {\n");
+    for (node, expand) in selected.iter() {
+      let _ = write!(
+        out,
+        "  n{} [label=\"{}\"",
+        node.get_id(),
+        escape(&vertex(node.get_label(), node.get_data()))
+      );
+      if self.vertices.contains(&node.get_id()) {
+        out.push_str(HIGHLIGHT);
+      }
+      if !expand && !node.is_leaf() {
+        out.push_str(", style=dashed");
+      }
+      out.push_str("];\n");
+    }
+    for (node, expand) in selected.iter() {
+      if !expand {
+        continue;
+      }
+      for e in node.get_child_list().iter() {
+        let _ = write!(
+          out,
+          "  n{} -> n{} [label=\"{}\"",
+          node.get_id(),
+          e.get_target().get_id(),
+          escape(&edge(e.get_data()))
+        );
+        if self.edges.contains(&e.get_id()) {
+          out.push_str(HIGHLIGHT);
+        }
+        out.push_str("];\n");
+      }
+    }
+    out.push_str("}\n");
+    out
+  }
+
+  /// As `render`, but writes the digraph to `out`.
+  pub fn write<S, A, F, G, W>(
+    &self,
+    graph: &Graph<T, S, A>,
+    vertex: F,
+    edge: G,
+    out: &mut W,
+  ) -> io::Result<()>
+  where
+    F: Fn(&T, &S) -> String,
+    G: Fn(&A) -> String,
+    W: Write,
+  {
+    out.write_all(self.render(graph, vertex, edge).as_bytes())
+  }
+}
+
+/// Escapes `label` for use in a quoted DOT string.
+fn escape(label: &str) -> String {
+  let mut escaped = String::with_capacity(label.len());
+  for c in label.chars() {
+    match c {
+      '"' => escaped.push_str("\\\""),
+      '\\' => escaped.push_str("\\\\"),
+      '\n' => escaped.push_str("\\n"),
+      c => escaped.push(c),
+    }
+  }
+  escaped
+}
+
+#[cfg(test)]
+mod test {
+  use super::Dot;
+  use crate::search::{Stack, Traversal};
+
+  type Graph = crate::Graph<&'static str, &'static str, &'static str>;
+
+  fn render(dot: &Dot<&'static str>, g: &Graph) -> String {
+    dot.render(g, |s, _| s.to_string(), |a| a.to_string())
+  }
+
+  fn make_graph() -> Graph {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| "", "a", |_| "", "root_a");
+    g.add_edge("root", |_| "", "b", |_| "", "root_b");
+    g.add_edge("a", |_| "", "c", |_| "", "a_c");
+    g.add_edge("c", |_| "", "root", |_| "", "c_root");
+    g.add_edge("z", |_| "", "root", |_| "", "z_root");
+    g
+  }
+
+  #[test]
+  fn render_ok() {
+    let g = make_graph();
+    let expected = "digraph {\n  \
+                    n0 [label=\"root\"];\n  \
+                    n1 [label=\"a\"];\n  \
+                    n2 [label=\"b\"];\n  \
+                    n3 [label=\"c\"];\n  \
+                    n4 [label=\"z\"];\n  \
+                    n0 -> n1 [label=\"root_a\"];\n  \
+                    n0 -> n2 [label=\"root_b\"];\n  \
+                    n1 -> n3 [label=\"a_c\"];\n  \
+                    n3 -> n0 [label=\"c_root\"];\n  \
+                    n4 -> n0 [label=\"z_root\"];\n\
+                    }\n";
+    assert_eq!(expected, render(&Dot::new(), &g));
+    let mut out = Vec::new();
+    Dot::new()
+      .write(&g, |s, _| s.to_string(), |a| a.to_string(), &mut out)
+      .unwrap();
+    assert_eq!(expected.as_bytes(), &out[..]);
+
+    let text = Dot::rooted("a").render(&g, |_, _| "say \"hi\"\\\n".to_string(), |_| String::new());
+    assert!(text.contains("n1 [label=\"say \\\"hi\\\"\\\\\\n\"];\n"));
+    assert_eq!("digraph {\n}\n", render(&Dot::rooted("missing"), &g));
+  }
+
+  #[test]
+  fn render_rooted_ok() {
+    let g = make_graph();
+    let mut dot = Dot::rooted("a");
+    assert_eq!(
+      "digraph {\n  \
+       n1 [label=\"a\"];\n  \
+       n3 [label=\"c\"];\n  \
+       n0 [label=\"root\"];\n  \
+       n2 [label=\"b\"];\n  \
+       n1 -> n3 [label=\"a_c\"];\n  \
+       n3 -> n0 [label=\"c_root\"];\n  \
+       n0 -> n1 [label=\"root_a\"];\n  \
+       n0 -> n2 [label=\"root_b\"];\n\
+       }\n",
+      render(&dot, &g)
+    );
+    dot.set_max_depth(1);
+    assert_eq!(
+      "digraph {\n  \
+       n1 [label=\"a\"];\n  \
+       n3 [label=\"c\", style=dashed];\n  \
+       n1 -> n3 [label=\"a_c\"];\n\
+       }\n",
+      render(&dot, &g)
+    );
+  }
+
+  #[test]
+  fn highlight_stack_ok() {
+    let mut g = make_graph();
+    let mut dot = Dot::rooted("root");
+    dot.set_max_depth(1);
+    {
+      let mut stack = Stack::new(g.find_node_mut(&"root").unwrap());
+      stack
+        .push(|_| Ok::<_, std::fmt::Error>(Some(Traversal::Child(0))))
+        .unwrap();
+      dot.highlight_stack(&stack);
+    }
+    let text = render(&dot, &g);
+    assert!(text.contains("n0 [label=\"root\", color=red, penwidth=2];\n"));
+    assert!(text.contains("n1 [label=\"a\", color=red, penwidth=2, style=dashed];\n"));
+    assert!(text.contains("n2 [label=\"b\"];\n"));
+    assert!(text.contains("n0 -> n1 [label=\"root_a\", color=red, penwidth=2];\n"));
+    assert!(text.contains("n0 -> n2 [label=\"root_b\"];\n"));
+    dot.clear_highlights();
+    assert!(!render(&dot, &g).contains("color"));
+  }
+}
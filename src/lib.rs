@@ -11,14 +11,193 @@
 //!   [zippers](https://en.wikipedia.org/wiki/Zipper_(data_structure)) in other
 //!   contexts, this pattern should be familiar).
 //! * [mutators](mutators/index.html) is a read-write analogue of `nav`.
+//! * [component](component/index.html) provides a read-only view of the part
+//!   of a `Graph` that is reachable from a vertex.
+//!
+//! Algorithms that only navigate may be written against the traits in
+//! [nav_types](nav_types/index.html) and [mut_types](mut_types/index.html),
+//! and so run over a `Graph`, a [snapshot](snapshot/index.html) of one, or
+//! other graph storage.
+//!
+//! Options such as whether incoming edges are tracked are chosen when a graph
+//! is created, as described in [config](config/index.html).
+//!
+//! What happens when a known game state is added again with different data is
+//! set with a policy from [duplicate](duplicate/index.html).
+//!
+//! Distinct game states may be made to address the same vertex with the
+//! aliases described in [alias](alias/index.html).
+//!
+//! The [moves](moves/index.html) module supports the common convention that
+//! edges correspond one-to-one with game moves.
+//!
+//! Whole-graph summaries and traversals, such as the branching factor at each
+//! ply, are provided as functions in [algo](algo/index.html).
+//!
+//! Vertices may be evaluated in batches with the traits in
+//! [eval](eval/index.html).
+//!
+//! Graphs may be written to a flat, read-only format that can be queried in
+//! place with the types in [archive](archive/index.html).
+//!
+//! Graph topology may be exported to sparse or dense matrices with
+//! [matrix](matrix/index.html).
+//!
+//! Graphs may be exported for analysis in other tools with the modules in
+//! [io](io/index.html).
+//!
+//! Graphs may be shrunk beyond what is reachable from a set of roots with the
+//! policies in [prune](prune/index.html).
+//!
+//! Repeated ancestry queries on a graph that is not changing may be answered
+//! from the precomputed index in [reach](reach/index.html).
+//!
+//! The [remap](remap/index.html) module describes how garbage collection
+//! reassigns vertex and edge IDs.
+//!
+//! Searches with tight time budgets may spread garbage collection across
+//! many short steps with the collector in [incremental](incremental/index.html).
+//!
+//! Readers on other threads may traverse [snapshot](snapshot/index.html)s of
+//! a graph while it continues to change.
+//!
+//! Insertions may be recorded on many threads at once in the buffers provided
+//! by [stage](stage/index.html) and later applied to a graph in a
+//! deterministic order. Threads that need to see each other's insertions
+//! (e.g., to look up transpositions) may instead share a sharded graph from
+//! [concurrent](concurrent/index.html).
+//!
+//! Statistics about how a graph is being used may be collected with the types
+//! in [stats](stats/index.html).
+//!
+//! Mutations may be tagged with their causes (e.g., search iteration numbers),
+//! which are then recorded for each vertex and edge and in a log of recent
+//! mutations, as described in [audit](audit/index.html).
+//!
+//! Small, fixed-size vertex statistics may be kept in atomic words that
+//! threads read and update through a shared borrow of a graph, as described in
+//! [hot](hot/index.html).
+//!
+//! Values of any type may be attached to each vertex or edge after the fact,
+//! without changing the vertex or edge data type, as described in [column](column/index.html).
+//!
+//! Vertices may be given IDs derived from their game states, which agree
+//! between processes that build the same graph, as described in
+//! [stable](stable/index.html).
+//!
+//! Bookkeeping outside a graph may refer to vertices by pinned IDs, which are
+//! kept up to date through garbage collection, as described in
+//! [pin](pin/index.html).
+//!
+//! Processes may check that they hold identical graphs by comparing digests
+//! that do not depend on vertex or edge IDs, as described in
+//! [fingerprint](fingerprint/index.html).
+//!
+//! Graphs that are expected to be acyclic may be sorted topologically, and
+//! cycles that creep into them may be found, with the methods described in
+//! [topo](topo/index.html).
+//!
+//! Graphs whose vertices and edges are all known up front (e.g., by an
+//! importer) may be built in one step with the constructor in
+//! [parts](parts/index.html).
+//!
+//! Passes over the data of every vertex and edge may use the bulk access in
+//! [raw](raw/index.html), which leaves the structure of the graph read-only.
+//!
+//! Tests may check the contents of a graph against a compact description with
+//! the [assert_graph](macro.assert_graph.html) macro and the types in
+//! [testing](testing/index.html).
+//!
+//! Cold subgraphs may be moved out of memory and reloaded on demand with the
+//! tiered storage in [tier](tier/index.html).
+//!
+//! The workloads timed by this crate's benchmarks are provided in
+//! [bench_support](bench_support/index.html).
+//!
+//! # Optional features
+//!
+//! * `tracing`: Emits [tracing](https://docs.rs/tracing) spans for garbage
+//!   collection phases (marking, sweeping, and rebuilding the game state
+//!   table) and bulk operations, and a `TRACE`-level event with a boolean
+//!   `hit` field for every transposition table lookup made while inserting
+//!   game states.
+//! * `arrow`: Enables the [io::arrow](io/arrow/index.html) module, which
+//!   exports graphs as [Apache Arrow](https://arrow.apache.org) record batches.
+//! * `cli`: Enables the [cli](cli/index.html) module, which loads, summarizes,
+//!   and exports saved graphs for command-line inspection tools. Implies
+//!   `serde` and `dot`.
+//! * `debugview`: Enables the [debugview](debugview/index.html) module, which
+//!   renders depth-limited text views of graphs for debugging.
+//! * `dot`: Enables the [io::dot](io/dot/index.html) module, which renders
+//!   graphs in the [Graphviz](https://graphviz.org) DOT language.
+//! * `epoch`: Enables the [handle](handle/index.html) module, which shares a
+//!   graph between one writer and many lock-free readers using
+//!   [crossbeam-epoch](https://docs.rs/crossbeam-epoch).
+//! * `hashkeys`: Enables the [hashkeys](hashkeys/index.html) module, which
+//!   supports graphs whose game states are 64-bit hash keys.
+//! * `ndarray`: Enables export of dense adjacency matrices as
+//!   [ndarray](https://docs.rs/ndarray) arrays.
+//! * `score`: Enables the [score](score/index.html) module, which provides
+//!   common edge selection formulas such as UCB1 and PUCT.
+//! * `serde`: Enables the [serialize](serialize/index.html) module, which
+//!   implements [serde](https://serde.rs) serialization for graphs.
 
+pub mod algo;
+pub mod alias;
+pub mod archive;
+pub mod audit;
 pub(crate) mod base;
+pub mod bench_support;
+pub(crate) mod child_heap;
+pub(crate) mod child_index;
+#[cfg(feature = "cli")]
+pub mod cli;
+pub mod column;
+pub mod component;
+pub mod concurrent;
+pub mod config;
+#[cfg(feature = "debugview")]
+pub mod debugview;
+pub mod duplicate;
+pub(crate) mod edge_index;
+pub mod eval;
+pub mod fingerprint;
+#[cfg(feature = "epoch")]
+pub mod handle;
+#[cfg(feature = "hashkeys")]
+pub mod hashkeys;
+pub mod hot;
+pub mod incremental;
+pub mod io;
 pub(crate) mod mark_compact;
+pub mod matrix;
+pub mod moves;
+pub mod mut_types;
 pub mod mutators;
 pub mod nav;
+pub mod nav_types;
+pub mod partition;
+pub mod parts;
+pub mod pin;
+pub mod prune;
+pub mod raw;
+pub mod reach;
+pub mod remap;
+#[cfg(feature = "score")]
+pub mod score;
 pub mod search;
+#[cfg(feature = "serde")]
+pub mod serialize;
+pub mod snapshot;
+pub mod stable;
+pub mod stage;
+pub mod stats;
+pub mod testing;
+pub mod tier;
+pub mod topo;
 pub mod view;
 
+use std::collections::HashMap;
 use std::hash::Hash;
 
 use base::{EdgeId, RawEdge, RawVertex, VertexId};
@@ -56,8 +235,62 @@ use symbol_map::SymbolId;
 pub struct Graph<T: Hash + Eq + Clone, S, A> {
   /// Lookup table that maps from game states to `VertexId`.
   state_ids: symbol_map::indexing::HashIndexing<T, VertexId>,
+  /// Additional game states that address existing vertices.
+  aliases: HashMap<T, VertexId>,
   vertices: Vec<RawVertex<S>>, // Indexed by VertexId.
   arcs: Vec<RawEdge<A>>,       // Indexed by EdgeId.
+  /// Data for child edges whose targets have not been determined yet, by
+  /// source vertex.
+  unexpanded: HashMap<VertexId, Vec<A>>,
+  /// Transposition table statistics, if they are being tracked.
+  interning_stats: Option<stats::InterningStats>,
+  /// When each vertex was created and last modified, if this is tracked.
+  recency: Option<stats::RecencyTable>,
+  /// Atomic words of data for each vertex, if any are configured.
+  hot: hot::HotTable,
+  /// Columns of values attached to each vertex.
+  vertex_columns: column::Columns,
+  /// Columns of values attached to each edge.
+  edge_columns: column::Columns,
+  /// Stable IDs derived from game states, if they are being assigned.
+  stable: Option<stable::StableIds>,
+  /// The vertices that pinned IDs refer to.
+  pins: pin::Pins,
+  /// The audit tag, provenance, and recent mutations, if auditing is enabled.
+  audit: Option<audit::AuditTrail>,
+  /// Incremented whenever vertex and edge IDs are reassigned.
+  version: u64,
+  /// Incremented whenever a vertex or edge is borrowed for modification.
+  modifications: u64,
+  /// Index of child edges by move key, if one has been requested.
+  child_index: Option<child_index::ChildIndex<A>>,
+  /// Heaps of child edges ordered by key, if they have been requested.
+  child_heaps: Option<child_heap::ChildHeaps<A>>,
+  /// Index of edges by source and target, if one has been requested.
+  edge_index: Option<edge_index::EdgeIndex>,
+  /// How IDs were reassigned by the most recent garbage collection.
+  last_remap: Option<remap::Remap>,
+  /// Automatic pruning configuration, if any.
+  auto_prune: Option<Box<prune::AutoPrune<T, S, A>>>,
+  /// What happens when a known game state is added with different data.
+  duplicates: duplicate::Duplicates<S>,
+  /// The most recent snapshot and what has changed since, if a snapshot has
+  /// been taken.
+  snapshot_cache: Option<Box<snapshot::SnapshotCache<T, S, A>>>,
+  /// The spill store and the regions spilled to it, if a store has been set.
+  tiering: Option<Box<tier::Tiering<T, S, A>>>,
+  /// Options fixed when the graph was created.
+  config: config::GraphConfig,
+  /// Configuration of keyed game states, for graphs whose game states are
+  /// hash keys.
+  #[cfg(feature = "hashkeys")]
+  keyed: hashkeys::KeyedConfig<S>,
+}
+
+impl<T: Hash + Eq + Clone, S, A> Default for Graph<T, S, A> {
+  fn default() -> Self {
+    Self::new()
+  }
 }
 
 impl<T: Hash + Eq + Clone, S, A> Graph<T, S, A> {
@@ -65,9 +298,120 @@ impl<T: Hash + Eq + Clone, S, A> Graph<T, S, A> {
   pub fn new() -> Self {
     Graph {
       state_ids: Default::default(),
+      aliases: HashMap::new(),
       vertices: Vec::new(),
       arcs: Vec::new(),
+      unexpanded: HashMap::new(),
+      interning_stats: None,
+      recency: None,
+      hot: Default::default(),
+      vertex_columns: Default::default(),
+      edge_columns: Default::default(),
+      stable: None,
+      pins: Default::default(),
+      audit: None,
+      version: 0,
+      modifications: 0,
+      child_index: None,
+      child_heaps: None,
+      edge_index: None,
+      last_remap: None,
+      auto_prune: None,
+      duplicates: Default::default(),
+      snapshot_cache: None,
+      tiering: None,
+      config: Default::default(),
+      #[cfg(feature = "hashkeys")]
+      keyed: hashkeys::KeyedConfig::new(),
+    }
+  }
+
+  /// Creates an empty `Graph` that has the same configuration as this one and
+  /// tracks statistics and maintains indices in the same way.
+  fn new_like(&self) -> Self {
+    let mut graph = Graph::new();
+    graph.interning_stats = self.interning_stats.map(|_| Default::default());
+    graph.recency = self.recency.as_ref().map(|r| r.empty_like());
+    graph.hot = hot::HotTable::new(self.hot.words);
+    graph.vertex_columns = self.vertex_columns.empty_like();
+    graph.edge_columns = self.edge_columns.empty_like();
+    graph.stable = self.stable.as_ref().map(|_| Default::default());
+    graph.pins = self.pins.empty_like();
+    graph.audit = self.audit.as_ref().map(|a| a.empty_like());
+    graph.child_index = self.child_index.as_ref().map(|i| i.empty_like());
+    graph.child_heaps = self.child_heaps.as_ref().map(|h| h.empty_like());
+    graph.edge_index = self.edge_index.as_ref().map(|_| Default::default());
+    graph.config = self.config;
+    #[cfg(feature = "hashkeys")]
+    {
+      graph.keyed = self.keyed.empty_like();
     }
+    graph
+  }
+
+  /// Returns a copy of the vertices, edges, and game states of this graph,
+  /// without vertex or edge data, for analyses that need a scratch copy of
+  /// the graph's structure. This does not require `S` or `A` to implement
+  /// `Clone`.
+  ///
+  /// Vertex and edge IDs, aliases, expansion states, and unexpanded child
+  /// edges are preserved, as are the options this graph was created with.
+  /// [Hot data](hot/index.html) is zeroed, and stable IDs and pins are
+  /// preserved.
+  /// Settings that depend on vertex or edge data are not: the copy has no
+  /// child index, columns, or automatic pruning, and keyed graphs use the default
+  /// collision policy.
+  pub fn topology_clone(&self) -> Graph<T, (), ()> {
+    let mut graph = Graph::with_config(self.config);
+    let mut labels: Vec<Option<&T>> = vec![None; self.vertices.len()];
+    for symbol in self.state_ids.table() {
+      labels[symbol.id().as_usize()] = Some(symbol.data());
+    }
+    for label in labels.into_iter().map(Option::unwrap) {
+      graph.state_ids.get_or_insert(label.clone());
+    }
+    graph.aliases = self.aliases.clone();
+    graph.vertices = self
+      .vertices
+      .iter()
+      .map(|v| RawVertex {
+        data: (),
+        parents: v.parents.clone(),
+        children: v.children.clone(),
+        expansion: v.expansion,
+      })
+      .collect();
+    graph.arcs = self
+      .arcs
+      .iter()
+      .map(|a| RawEdge {
+        data: (),
+        source: a.source,
+        target: a.target,
+      })
+      .collect();
+    graph.unexpanded = self
+      .unexpanded
+      .iter()
+      .map(|(id, edges)| (*id, vec![(); edges.len()]))
+      .collect();
+    graph.interning_stats = self.interning_stats.map(|_| Default::default());
+    graph.recency = self.recency.clone();
+    for _ in 0..self.vertices.len() {
+      graph.hot.create();
+    }
+    graph.stable = self.stable.clone();
+    graph.pins = self.pins.clone();
+    graph.audit = self.audit.clone();
+    graph.edge_index = self
+      .edge_index
+      .as_ref()
+      .map(|_| edge_index::EdgeIndex::new(&graph.vertices, &graph.arcs));
+    #[cfg(feature = "hashkeys")]
+    {
+      graph.keyed = self.keyed.topology_clone();
+    }
+    graph
   }
 
   /// Returns the vertex for the given `VertexId`.
@@ -75,11 +419,45 @@ impl<T: Hash + Eq + Clone, S, A> Graph<T, S, A> {
     &self.vertices[state.as_usize()]
   }
 
+  /// Records that the data of the vertex for the given `VertexId` is being
+  /// modified.
+  fn touch_data(&mut self, state: VertexId) {
+    if let Some(ref mut recency) = self.recency {
+      recency.touch(state.as_usize());
+    }
+    if let Some(ref mut audit) = self.audit {
+      audit.touch_vertex(state.as_usize());
+    }
+  }
+
+  /// Records that the data of the edge for the given `EdgeId` is being
+  /// modified.
+  fn touch_edge_data(&mut self, arc: EdgeId) {
+    if let Some(ref mut audit) = self.audit {
+      audit.touch_edge(arc.as_usize());
+    }
+  }
+
+  /// Returns the data of the vertex for the given `VertexId`, mutably.
+  fn get_data_mut(&mut self, state: VertexId) -> &mut S {
+    self.touch_data(state);
+    &mut self.get_vertex_mut(state).data
+  }
+
   /// Returns the vertex for the given `VertexId`.
   fn get_vertex_mut(&mut self, state: VertexId) -> &mut RawVertex<S> {
+    self.touch_vertex(state);
     &mut self.vertices[state.as_usize()]
   }
 
+  /// Returns the data for the unexpanded child edges of the given vertex.
+  fn get_unexpanded(&self, source: VertexId) -> &[A] {
+    match self.unexpanded.get(&source) {
+      Some(edges) => edges,
+      None => &[],
+    }
+  }
+
   /// Returns the edge for the given `EdgeId`.
   fn get_arc(&self, arc: EdgeId) -> &RawEdge<A> {
     &self.arcs[arc.as_usize()]
@@ -87,6 +465,7 @@ impl<T: Hash + Eq + Clone, S, A> Graph<T, S, A> {
 
   /// Returns the edge for the given `EdgeId`.
   fn get_arc_mut(&mut self, arc: EdgeId) -> &mut RawEdge<A> {
+    self.touch_arc(arc);
     &mut self.arcs[arc.as_usize()]
   }
 
@@ -95,39 +474,227 @@ impl<T: Hash + Eq + Clone, S, A> Graph<T, S, A> {
     self.state_ids.get_symbol(&id).as_ref().map(|x| x.data())
   }
 
+  /// Looks up the `VertexId` for `state`, which may be a canonical game state
+  /// or an alias.
+  fn lookup(&self, state: &T) -> Option<VertexId> {
+    match self.state_ids.get(state) {
+      Some(symbol) => Some(*symbol.id()),
+      None => self.aliases.get(state).cloned(),
+    }
+  }
+
+  /// Looks up the `VertexId` for `state`, assigning it a new ID if it is not
+  /// already known.
+  ///
+  /// Callers are responsible for adding a vertex with `add_raw_vertex` when
+  /// `Insertion::New` is returned.
+  fn intern(&mut self, state: T) -> Insertion<VertexId> {
+    let insertion = match self.aliases.get(&state) {
+      Some(id) => Insertion::Present(*id),
+      None => self.state_ids.get_or_insert(state).map(|s| *s.id()),
+    };
+    let hit = matches!(insertion, Insertion::Present(_));
+    #[cfg(feature = "tracing")]
+    tracing::trace!(hit, "transposition table lookup");
+    if let Some(ref mut stats) = self.interning_stats {
+      stats.record(hit);
+    }
+    insertion
+  }
+
+  /// Adds a vertex for the game state that was just interned with ID `id`,
+  /// with the data returned by `data`. If `data` panics, the game state is
+  /// removed from the transposition table before the panic resumes, so that
+  /// every game state in the table keeps a vertex.
+  fn add_interned_vertex<F>(&mut self, id: VertexId, data: F) -> &mut RawVertex<S>
+  where
+    F: FnOnce(&Self) -> S,
+  {
+    let this = &*self;
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| data(this))) {
+      Ok(data) => self.add_raw_vertex(data),
+      Err(payload) => {
+        self.unintern(id);
+        std::panic::resume_unwind(payload)
+      }
+    }
+  }
+
+  /// Removes the game state with ID `id` from the transposition table. It
+  /// must be the most recently interned game state, and have no vertex.
+  fn unintern(&mut self, id: VertexId) {
+    let mut table = std::mem::take(&mut self.state_ids).to_table();
+    table.remap(|symbol| Some(*symbol.id()).filter(|other| *other != id));
+    self.state_ids = symbol_map::indexing::HashIndexing::from_table(table);
+  }
+
   /// Adds a new vertex with the given data, returning a mutable reference to it.
   ///
   /// This method does not add incoming or outgoing edges. That must be done by
   /// calling `add_arc` with the new vertex `VertexId`.
   fn add_raw_vertex(&mut self, data: S) -> &mut RawVertex<S> {
+    if let Some(ref mut recency) = self.recency {
+      recency.create();
+    }
+    self.hot.create();
+    self.vertex_columns.create();
+    if let Some(ref mut audit) = self.audit {
+      audit.create_vertex();
+    }
+    if let Some(ref mut stable) = self.stable {
+      let id = VertexId(self.vertices.len());
+      stable.create(self.state_ids.get_symbol(&id).as_ref().unwrap().data());
+    }
     self.vertices.push(RawVertex {
-      data: data,
+      data,
       parents: Vec::new(),
       children: Vec::new(),
+      expansion: nav::Expansion::Unexpanded,
     });
     self.vertices.last_mut().unwrap()
   }
 
   /// Adds a new edge with the given data, source, and target. Returns the
   /// internal ID for the new edge.
+  ///
+  /// If the graph merges parallel edges and there is already an edge from
+  /// `source` to `target`, `data` is dropped and the ID of the existing edge
+  /// is returned instead.
   fn add_raw_edge(&mut self, data: A, source: VertexId, target: VertexId) -> EdgeId {
+    if self.config.parallel_edges == config::ParallelEdges::Merge {
+      if let Some(id) = self.find_parallel(source, target) {
+        return id;
+      }
+    }
     let arc_id = EdgeId(self.arcs.len());
-    self.get_vertex_mut(source).children.push(arc_id);
-    self.get_vertex_mut(target).parents.push(arc_id);
+    let vertex = self.get_vertex_mut(source);
+    vertex.children.push(arc_id);
+    if vertex.expansion == nav::Expansion::Unexpanded {
+      vertex.expansion = nav::Expansion::PartiallyExpanded;
+    }
+    if self.config.track_parents {
+      let position = self.parent_insertion_point(source, target);
+      self.get_vertex_mut(target).parents.insert(position, arc_id);
+    }
+    if let Some(ref mut index) = self.child_index {
+      index.insert(source, arc_id, &data);
+    }
+    if let Some(ref mut heaps) = self.child_heaps {
+      heaps.insert(source, arc_id, &data);
+    }
+    if let Some(ref mut index) = self.edge_index {
+      index.insert(source, target, arc_id);
+    }
+    if let Some(ref mut audit) = self.audit {
+      audit.create_edge();
+    }
+    self.edge_columns.create();
     self.arcs.push(RawEdge {
-      data: data,
-      source: source,
-      target: target,
+      data,
+      source,
+      target,
     });
     arc_id
   }
 
+  /// Removes the edges with the given IDs, detaching them from the child lists
+  /// of their sources and the parent lists of their targets. The edges that
+  /// remain are renumbered so that edge IDs stay contiguous, so this counts as
+  /// a reassignment of IDs: the graph version is incremented and the mapping
+  /// is recorded as the most recent remap. Vertex IDs do not change.
+  fn remove_raw_edges(&mut self, mut ids: Vec<EdgeId>) {
+    ids.sort();
+    ids.dedup();
+    if ids.is_empty() {
+      return;
+    }
+    let from_version = self.version;
+    self.version += 1;
+    let mut child_ranges = Vec::with_capacity(self.vertices.len());
+    let mut child_positions = Vec::with_capacity(self.arcs.len());
+    for vertex in self.vertices.iter() {
+      let start = child_positions.len();
+      let mut next = 0;
+      for c in vertex.children.iter() {
+        if ids.binary_search(c).is_ok() {
+          child_positions.push(None);
+        } else {
+          child_positions.push(Some(next));
+          next += 1;
+        }
+      }
+      child_ranges.push((start, vertex.children.len()));
+    }
+    for &id in ids.iter() {
+      let RawEdge { source, target, .. } = *self.get_arc(id);
+      let vertex = self.get_vertex_mut(source);
+      vertex.children.retain(|c| *c != id);
+      if vertex.expansion == nav::Expansion::FullyExpanded {
+        vertex.expansion = nav::Expansion::PartiallyExpanded;
+      }
+      self.get_vertex_mut(target).parents.retain(|p| *p != id);
+    }
+    // The original ID of the edge in each slot, as edges are moved.
+    let mut original_ids: Vec<usize> = (0..self.arcs.len()).collect();
+    for &id in ids.iter().rev() {
+      let last = EdgeId(self.arcs.len() - 1);
+      let RawEdge { source, .. } = *self.get_arc(id);
+      self.arcs.swap_remove(id.as_usize());
+      self.edge_columns.swap_remove(id.as_usize());
+      if let Some(ref mut audit) = self.audit {
+        audit.edges.swap_remove(id.as_usize());
+        audit.record(audit::AuditOp::RemoveEdge, Some(source.as_usize()));
+      }
+      original_ids.swap_remove(id.as_usize());
+      if id != last {
+        // The last edge has moved into the removed edge's slot.
+        let RawEdge { source, target, .. } = *self.get_arc(id);
+        for c in self.get_vertex_mut(source).children.iter_mut() {
+          if *c == last {
+            *c = id;
+          }
+        }
+        for p in self.get_vertex_mut(target).parents.iter_mut() {
+          if *p == last {
+            *p = id;
+          }
+        }
+        self.sort_parents_of(target);
+      }
+    }
+    let mut edges: Vec<Option<EdgeId>> = vec![None; original_ids.len() + ids.len()];
+    for (new_id, original_id) in original_ids.into_iter().enumerate() {
+      edges[original_id] = Some(EdgeId(new_id));
+    }
+    if let Some(ref mut index) = self.child_index {
+      index.rebuild(&self.vertices, &self.arcs);
+    }
+    if let Some(ref mut heaps) = self.child_heaps {
+      heaps.rebuild(&self.vertices, &self.arcs);
+    }
+    if let Some(ref mut index) = self.edge_index {
+      index.rebuild(&self.vertices, &self.arcs);
+    }
+    if let Some(ref mut audit) = self.audit {
+      audit.remap(Some, |i| edges[i].map(|id| id.as_usize()));
+    }
+    self.last_remap = Some(remap::Remap {
+      from_version,
+      vertices: (0..self.vertices.len())
+        .map(|i| Some(VertexId(i)))
+        .collect(),
+      edges,
+      child_ranges,
+      child_positions,
+    });
+  }
+
   /// Gets a node handle for the given game state.
   ///
   /// If `state` does not correspond to a known game state, returns `None`.
   pub fn find_node<'s>(&'s self, state: &T) -> Option<nav::Node<'s, T, S, A>> {
-    match self.state_ids.get(state) {
-      Some(symbol) => Some(nav::Node::new(self, *symbol.id())),
+    match self.lookup(state) {
+      Some(id) => Some(nav::Node::new(self, id)),
       None => None,
     }
   }
@@ -136,28 +703,77 @@ impl<T: Hash + Eq + Clone, S, A> Graph<T, S, A> {
   ///
   /// If `state` does not correspond to a known game state, returns `None`.
   pub fn find_node_mut<'s>(&'s mut self, state: &T) -> Option<mutators::MutNode<'s, T, S, A>> {
-    match self.state_ids.get(state).map(|s| s.id().clone()) {
+    match self.lookup(state) {
       Some(id) => Some(mutators::MutNode::new(self, id)),
       None => None,
     }
   }
 
+  /// Converts a token obtained from [nav::Node::token](nav/struct.Node.html#method.token)
+  /// into a mutable node handle, without looking up its game state.
+  ///
+  /// Returns `None` if `token` was obtained from a different graph, or if this
+  /// graph has been garbage-collected since `token` was obtained. Graph
+  /// identity is established by address, so a token is also rejected if the
+  /// graph has been moved since the token was obtained.
+  pub fn upgrade<'s>(
+    &'s mut self,
+    token: nav::NodeToken,
+  ) -> Option<mutators::MutNode<'s, T, S, A>> {
+    self
+      .resolve_token(token)
+      .map(move |id| mutators::MutNode::new(self, id))
+  }
+
+  /// Returns the ID of the vertex that `token` identifies, or `None` if it is
+  /// not valid for this graph (see [upgrade](#method.upgrade)).
+  fn resolve_token(&self, token: nav::NodeToken) -> Option<VertexId> {
+    if token.graph == self.address()
+      && token.version == self.version
+      && token.id.as_usize() < self.vertices.len()
+    {
+      Some(token.id)
+    } else {
+      None
+    }
+  }
+
+  /// Returns the address of this graph, for use in identity checks.
+  fn address(&self) -> usize {
+    self as *const Self as usize
+  }
+
   /// Adds a vertex (with no parents or children) for the given game state and
   /// data and returns a mutable handle for it.
   ///
   /// If `state` is already known, returns a mutable handle to that state,
-  /// ignoring the `data` parameter. As a result, this method is guaranteed to
-  /// return a handle for a root vertex only when `state` is a novel game
-  /// state.
+  /// handling the `data` parameter according to the
+  /// [duplicate policy](struct.Graph.html#method.set_duplicate_policy), which
+  /// by default ignores it. As a result, this method is guaranteed to return a
+  /// handle for a root vertex only when `state` is a novel game state.
+  ///
+  /// If automatic pruning is configured, the graph may be pruned before the
+  /// vertex is added.
+  ///
+  /// Panics if `state` is already known with data other than `data` and the
+  /// duplicate policy is `DuplicatePolicy::Error`.
   pub fn add_node<'s>(&'s mut self, state: T, data: S) -> mutators::MutNode<'s, T, S, A> {
-    let node_id = match self.state_ids.get_or_insert(state).map(|s| s.id().clone()) {
-      Insertion::Present(id) => id,
+    match self.add_vertex(state, data) {
+      Ok(id) => mutators::MutNode::new(self, id),
+      Err(e) => panic!("{}", e),
+    }
+  }
+
+  /// Finds or adds the vertex for `state` on behalf of `add_node`.
+  fn add_vertex(&mut self, state: T, data: S) -> Result<VertexId, duplicate::DuplicateData> {
+    self.maybe_prune();
+    match self.intern(state) {
+      Insertion::Present(id) => self.add_duplicate(id, data).map(|()| id),
       Insertion::New(id) => {
         self.add_raw_vertex(data);
-        id
+        Ok(id)
       }
-    };
-    mutators::MutNode::new(self, node_id)
+    }
   }
 
   /// Adds an edge from the vertex with state data `source` to the vertex with
@@ -167,6 +783,13 @@ impl<T: Hash + Eq + Clone, S, A> Graph<T, S, A> {
   ///
   /// The edge that is created will have the data `edge_data`. Returns a
   /// mutable edge handle for that edge.
+  ///
+  /// If automatic pruning is configured, the graph may be pruned before the
+  /// edge is added.
+  ///
+  /// If `source_data` or `dest_data` panics, no vertex is added for the game
+  /// state that it was called for and no edge is added, so the graph remains
+  /// consistent (although a vertex that was added for `source` is kept).
   pub fn add_edge<'s, F, G>(
     &'s mut self,
     source: T,
@@ -179,19 +802,18 @@ impl<T: Hash + Eq + Clone, S, A> Graph<T, S, A> {
     F: for<'b> FnOnce(nav::Node<'b, T, S, A>) -> S,
     G: for<'b> FnOnce(nav::Node<'b, T, S, A>) -> S,
   {
-    let source_id = match self.state_ids.get_or_insert(source).map(|s| s.id().clone()) {
+    self.maybe_prune();
+    let source_id = match self.intern(source) {
       Insertion::Present(id) => id,
       Insertion::New(id) => {
-        let data = source_data(nav::Node::new(self, id));
-        self.add_raw_vertex(data);
+        self.add_interned_vertex(id, |g| source_data(nav::Node::new(g, id)));
         id
       }
     };
-    let dest_id = match self.state_ids.get_or_insert(dest).map(|s| s.id().clone()) {
+    let dest_id = match self.intern(dest) {
       Insertion::Present(id) => id,
       Insertion::New(id) => {
-        let data = dest_data(nav::Node::new(self, id));
-        self.add_raw_vertex(data);
+        self.add_interned_vertex(id, |g| dest_data(nav::Node::new(g, id)));
         id
       }
     };
@@ -199,6 +821,283 @@ impl<T: Hash + Eq + Clone, S, A> Graph<T, S, A> {
     mutators::MutEdge::new(self, edge_id)
   }
 
+  /// Replaces the incoming edges of the vertex for `subtree_root` with a
+  /// single edge from the vertex for `new_parent`, which has the data
+  /// `edge_data`. This is intended for manual repairs to the shape of a graph
+  /// (e.g., after importing data). Returns a mutable edge handle for the new
+  /// edge, or `None` if either game state is not in the graph, in which case
+  /// the graph is unchanged.
+  ///
+  /// The edges that are removed are removed as by
+  /// [retain_edges](struct.Graph.html#method.retain_edges), so edge IDs may be
+  /// reassigned. Nothing prevents the new edge from creating a cycle.
+  pub fn reattach<'s>(
+    &'s mut self,
+    subtree_root: &T,
+    new_parent: &T,
+    edge_data: A,
+  ) -> Option<mutators::MutEdge<'s, T, S, A>> {
+    let root_id = self.lookup(subtree_root)?;
+    let parent_id = self.lookup(new_parent)?;
+    let incoming: Vec<EdgeId> = match self.try_parents(root_id) {
+      Ok(parents) => parents.to_vec(),
+      Err(_) => (0..self.arcs.len())
+        .map(EdgeId)
+        .filter(|id| self.get_arc(*id).target == root_id)
+        .collect(),
+    };
+    // Removing edges does not change vertex IDs.
+    self.remove_raw_edges(incoming);
+    let edge_id = self.add_raw_edge(edge_data, parent_id, root_id);
+    Some(mutators::MutEdge::new(self, edge_id))
+  }
+
+  /// Enables or disables tracking of transposition table hits and misses when
+  /// game states are inserted (by `add_node`, `add_edge`, and the insertion
+  /// methods in the `mutators` module).
+  ///
+  /// Tracking is disabled by default. Disabling tracking discards any counts
+  /// that have been collected so far.
+  pub fn set_track_interning(&mut self, enabled: bool) {
+    self.interning_stats = if enabled {
+      Some(self.interning_stats.unwrap_or_default())
+    } else {
+      None
+    };
+  }
+
+  /// Returns the transposition table statistics collected since tracking was
+  /// enabled or last reset, or `None` if tracking is disabled.
+  pub fn interning_stats(&self) -> Option<stats::InterningStats> {
+    self.interning_stats
+  }
+
+  /// Resets the transposition table statistics to zero. Has no effect if
+  /// tracking is disabled.
+  pub fn reset_interning_stats(&mut self) {
+    if let Some(ref mut stats) = self.interning_stats {
+      *stats = Default::default();
+    }
+  }
+
+  /// Returns handles for the `n` vertices whose data was modified least
+  /// recently (or, if it has not been modified, that were created least
+  /// recently), in order from least to most recent. Returns every vertex if
+  /// there are fewer than `n`, and no vertices if the graph does not track
+  /// recency (see [Recency](stats/struct.Recency.html)).
+  ///
+  /// This takes time linear in the number of vertices, plus `O(n log n)`.
+  pub fn least_recently_used<'s>(&'s self, n: usize) -> Vec<nav::Node<'s, T, S, A>> {
+    let recency = match self.recency {
+      Some(ref recency) => recency,
+      None => return Vec::new(),
+    };
+    let mut touched: Vec<(u64, usize)> = recency
+      .vertices
+      .iter()
+      .enumerate()
+      .map(|(i, r)| (r.touched, i))
+      .collect();
+    if n < touched.len() {
+      touched.select_nth_unstable(n);
+      touched.truncate(n);
+    }
+    touched.sort_unstable();
+    touched
+      .into_iter()
+      .map(|(_, i)| nav::Node::new(self, VertexId(i)))
+      .collect()
+  }
+
+  /// Applies updates to the data of many vertices in one pass.
+  ///
+  /// Each element of `updates` pairs a game state with a function that
+  /// modifies its vertex data. Game states are resolved to vertices first, and
+  /// updates are then applied in vertex order, which is friendlier to the
+  /// cache than applying them in the order given. Updates to the same vertex
+  /// are applied in the order given. Updates for game states that are not in
+  /// the graph are dropped.
+  ///
+  /// Returns the number of updates that were applied.
+  pub fn update_data_bulk<'t, I, F>(&mut self, updates: I) -> usize
+  where
+    T: 't,
+    I: IntoIterator<Item = (&'t T, F)>,
+    F: FnOnce(&mut S),
+  {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::debug_span!("update_data_bulk").entered();
+    let mut resolved: Vec<(VertexId, F)> = updates
+      .into_iter()
+      .filter_map(|(state, f)| self.lookup(state).map(|id| (id, f)))
+      .collect();
+    resolved.sort_by_key(|&(id, _)| id);
+    let count = resolved.len();
+    for (id, f) in resolved {
+      f(self.get_data_mut(id));
+    }
+    count
+  }
+
+  /// Builds and begins maintaining an index of child edges, keyed by the
+  /// value that `key` extracts from edge data. Once the index is built,
+  /// [ChildList::find_by_key](nav/struct.ChildList.html#method.find_by_key)
+  /// finds the child edge for a key in constant time, instead of scanning a
+  /// vertex's child list.
+  ///
+  /// Keys are typically an encoding of the game move that an edge
+  /// represents. The key extracted from an edge's data should not change once
+  /// the edge has been added to the graph. If it does, the index still maps
+  /// the old key to the edge: lookups for the old key fall back to a scan of
+  /// the child list, but lookups for the new key may miss the edge. When a
+  /// vertex has several child edges with the same key, lookups will find the
+  /// first of them.
+  ///
+  /// Any existing index is replaced.
+  pub fn index_children_by(&mut self, key: fn(&A) -> u64) {
+    self.child_index = Some(child_index::ChildIndex::new(
+      key,
+      false,
+      &self.vertices,
+      &self.arcs,
+    ));
+  }
+
+  /// Builds and begins maintaining an index of child edges by the move that
+  /// they represent, so that `child_by_move` lookups take constant time. See
+  /// the [moves](moves/index.html) module.
+  ///
+  /// Any existing index is replaced.
+  pub fn index_moves(&mut self)
+  where
+    A: moves::MoveKey,
+  {
+    self.child_index = Some(child_index::ChildIndex::new(
+      moves::index_key::<A>,
+      true,
+      &self.vertices,
+      &self.arcs,
+    ));
+  }
+
+  /// Discards any index built by `index_children_by`.
+  pub fn clear_child_index(&mut self) {
+    self.child_index = None;
+  }
+
+  /// Builds and begins maintaining a heap of the child edges of each vertex,
+  /// ordered by the value that `key` extracts from edge data. Once the heaps
+  /// are built,
+  /// [ChildList::best_by_key](nav/struct.ChildList.html#method.best_by_key)
+  /// finds the child edge with the greatest key in constant time, instead of
+  /// scanning a vertex's child list (e.g., to select the most promising move
+  /// in a very wide position).
+  ///
+  /// Keys are computed when an edge is added and are kept up to date lazily:
+  /// after modifying the data of an edge in a way that changes its key, call
+  /// [MutEdge::touch_key](mutators/struct.MutEdge.html#method.touch_key),
+  /// which restores the order of its siblings in logarithmic time. Ties are
+  /// broken in favor of the edge with the lowest ID. Garbage collection and
+  /// the removal of edges recompute every key.
+  ///
+  /// Any existing heaps are replaced.
+  pub fn order_children_by(&mut self, key: fn(&A) -> u64) {
+    self.child_heaps = Some(child_heap::ChildHeaps::new(key, &self.vertices, &self.arcs));
+  }
+
+  /// Discards any heaps built by `order_children_by`.
+  pub fn clear_child_order(&mut self) {
+    self.child_heaps = None;
+  }
+
+  /// Builds and begins maintaining an index of edges by their source and
+  /// target vertices. Once the index is built,
+  /// [find_edge](struct.Graph.html#method.find_edge),
+  /// [add_unique_edge](struct.Graph.html#method.add_unique_edge),
+  /// [View::find_edge](view/struct.View.html#method.find_edge), and the
+  /// merging of parallel edges under
+  /// [ParallelEdges::Merge](config/enum.ParallelEdges.html#variant.Merge)
+  /// find the edge between two vertices in constant time, instead of scanning
+  /// the child list of the source vertex. When there are several edges
+  /// between the same vertices, lookups find the first of them.
+  pub fn index_edges(&mut self) {
+    self.edge_index = Some(edge_index::EdgeIndex::new(&self.vertices, &self.arcs));
+  }
+
+  /// Discards any index built by `index_edges`.
+  pub fn clear_edge_index(&mut self) {
+    self.edge_index = None;
+  }
+
+  /// Returns the first edge from the vertex for `source` to the vertex for
+  /// `target`, or `None` if either game state is not in the graph or there is
+  /// no such edge. This scans the child list of the source vertex unless an
+  /// index has been built with
+  /// [index_edges](struct.Graph.html#method.index_edges).
+  pub fn find_edge<'s>(&'s self, source: &T, target: &T) -> Option<nav::Edge<'s, T, S, A>> {
+    let source = self.lookup(source)?;
+    let target = self.lookup(target)?;
+    self
+      .find_parallel(source, target)
+      .map(|id| nav::Edge::new(self, id))
+  }
+
+  /// Returns true iff there is an edge from the vertex for `source` to the
+  /// vertex for `target`. This is a lookup if an index has been built with
+  /// [index_edges](struct.Graph.html#method.index_edges) or a binary search if
+  /// parent lists are sorted (see
+  /// [GraphConfig](config/struct.GraphConfig.html)), and otherwise scans the
+  /// shorter of the child list of the source and the parent list of the
+  /// target.
+  pub fn contains_edge(&self, source: &T, target: &T) -> bool {
+    match (self.lookup(source), self.lookup(target)) {
+      (Some(source), Some(target)) => self.has_edge(source, target),
+      _ => false,
+    }
+  }
+
+  /// As [add_edge](struct.Graph.html#method.add_edge), but if there is already
+  /// an edge from the vertex for `source` to the vertex for `dest`, no edge is
+  /// added. Instead, `merge` is called on the data of the first such edge and
+  /// `edge_data`, and a handle for that edge is returned.
+  ///
+  /// Finding the existing edge scans the child list of the source vertex
+  /// unless an index has been built with
+  /// [index_edges](struct.Graph.html#method.index_edges).
+  pub fn add_unique_edge<'s, F, G, M>(
+    &'s mut self,
+    source: T,
+    source_data: F,
+    dest: T,
+    dest_data: G,
+    edge_data: A,
+    merge: M,
+  ) -> mutators::MutEdge<'s, T, S, A>
+  where
+    F: for<'b> FnOnce(nav::Node<'b, T, S, A>) -> S,
+    G: for<'b> FnOnce(nav::Node<'b, T, S, A>) -> S,
+    M: FnOnce(&mut A, A),
+  {
+    if let (Some(source_id), Some(dest_id)) = (self.lookup(&source), self.lookup(&dest)) {
+      if let Some(id) = self.find_parallel(source_id, dest_id) {
+        let mut edge = mutators::MutEdge::new(self, id);
+        merge(edge.get_data_mut(), edge_data);
+        edge.touch_key();
+        return edge;
+      }
+    }
+    self.add_edge(source, source_data, dest, dest_data, edge_data)
+  }
+
+  /// Returns the table of how vertex and edge IDs were reassigned by the most
+  /// recent garbage collection (or removal of edges, as by
+  /// [retain_edges](struct.Graph.html#method.retain_edges) or
+  /// [MutChildList::prune_dominated](mutators/struct.MutChildList.html#method.prune_dominated)),
+  /// or `None` if IDs have never been reassigned.
+  pub fn last_remap(&self) -> Option<&remap::Remap> {
+    self.last_remap.as_ref()
+  }
+
   /// Returns the number of vertices in the graph.
   pub fn vertex_count(&self) -> usize {
     // TODO: This is actually the number of vertices we have allocated.
@@ -210,6 +1109,201 @@ impl<T: Hash + Eq + Clone, S, A> Graph<T, S, A> {
     // TODO: This is actually the number of edges we have allocated.
     self.arcs.len()
   }
+
+  /// Returns the IDs of the source and target vertices of the edge with ID
+  /// `id`, as returned by [Edge::get_id](nav/struct.Edge.html#method.get_id)
+  /// or [Node::child_edge_ids](nav/struct.Node.html#method.child_edge_ids).
+  /// Panics if there is no such edge.
+  pub fn edge_endpoints(&self, id: usize) -> (usize, usize) {
+    let arc = &self.arcs[id];
+    (arc.source.as_usize(), arc.target.as_usize())
+  }
+
+  /// Returns an iterator over the game states in the graph, in order of
+  /// vertex ID.
+  pub fn states<'s>(&'s self) -> impl ExactSizeIterator<Item = &'s T> + 's {
+    (0..self.vertices.len()).map(move |i| self.get_state(VertexId(i)).unwrap())
+  }
+
+  /// Returns the game states in the graph, sorted by `compare`. Unlike the
+  /// order of [states](#method.states), this order does not depend on the
+  /// order in which vertices were added, so it may be used to produce output
+  /// that is identical across runs and platforms. Aliases are not included.
+  pub fn states_sorted_by<F>(&self, mut compare: F) -> Vec<&T>
+  where
+    F: FnMut(&T, &T) -> std::cmp::Ordering,
+  {
+    let mut states: Vec<&T> = self.states().collect();
+    states.sort_by(|a, b| compare(a, b));
+    states
+  }
+
+  /// Returns the IDs of the vertices in the graph, sorted by `compare` on
+  /// their game states.
+  fn vertex_ids_sorted_by<F>(&self, mut compare: F) -> Vec<VertexId>
+  where
+    F: FnMut(&T, &T) -> std::cmp::Ordering,
+  {
+    let mut ids: Vec<VertexId> = (0..self.vertices.len()).map(VertexId).collect();
+    ids.sort_by(|a, b| compare(self.get_state(*a).unwrap(), self.get_state(*b).unwrap()));
+    ids
+  }
+
+  /// Returns an iterator over the game states in the graph, paired with their
+  /// vertex data, in order of vertex ID. This treats the graph as a map from
+  /// game states to vertex data, in the manner of `HashMap::iter`.
+  pub fn iter<'s>(&'s self) -> impl ExactSizeIterator<Item = (&'s T, &'s S)> + 's {
+    self
+      .vertices
+      .iter()
+      .enumerate()
+      .map(move |(i, v)| (self.get_state(VertexId(i)).unwrap(), &v.data))
+  }
+
+  /// Returns node handles for all vertices whose data satisfies `pred`, in
+  /// order of vertex ID.
+  pub fn find_nodes_where<'s, F: Fn(&S) -> bool>(&'s self, pred: F) -> Vec<nav::Node<'s, T, S, A>> {
+    self
+      .vertices
+      .iter()
+      .enumerate()
+      .filter(|(_, v)| pred(&v.data))
+      .map(|(i, _)| nav::Node::new(self, VertexId(i)))
+      .collect()
+  }
+
+  /// As [find_nodes_where](struct.Graph.html#method.find_nodes_where), but
+  /// evaluates `pred` on up to `threads` threads at once. Matching nodes are
+  /// returned in order of vertex ID, regardless of how many threads are used.
+  ///
+  /// Panics if `threads` is 0.
+  pub fn par_find_nodes_where<'s, F>(
+    &'s self,
+    pred: F,
+    threads: usize,
+  ) -> Vec<nav::Node<'s, T, S, A>>
+  where
+    S: Sync,
+    F: Fn(&S) -> bool + Sync,
+  {
+    assert!(threads > 0, "thread count must be positive");
+    let chunk_size = self.vertices.len().div_ceil(threads).max(1);
+    let pred = &pred;
+    let ids: Vec<usize> = std::thread::scope(|s| {
+      let workers: Vec<_> = self
+        .vertices
+        .chunks(chunk_size)
+        .enumerate()
+        .map(|(chunk, vertices)| {
+          s.spawn(move || {
+            vertices
+              .iter()
+              .enumerate()
+              .filter(|(_, v)| pred(&v.data))
+              .map(|(i, _)| chunk * chunk_size + i)
+              .collect::<Vec<_>>()
+          })
+        })
+        .collect();
+      workers
+        .into_iter()
+        .flat_map(|w| w.join().unwrap())
+        .collect()
+    });
+    ids
+      .into_iter()
+      .map(|i| nav::Node::new(self, VertexId(i)))
+      .collect()
+  }
+
+  /// Visits every vertex once, in order of vertex ID, and returns the result
+  /// of `projection` for each vertex that satisfies `filter`. Because the rows
+  /// returned are owned values rather than handles, they may be kept (e.g.,
+  /// logged or sent to another thread) after the graph is modified, which
+  /// makes this suitable for periodic summaries of large graphs.
+  pub fn report<R, F, P>(&self, mut filter: F, mut projection: P) -> Vec<R>
+  where
+    F: FnMut(&nav::Node<T, S, A>) -> bool,
+    P: FnMut(&nav::Node<T, S, A>) -> R,
+  {
+    (0..self.vertices.len())
+      .map(|i| nav::Node::new(self, VertexId(i)))
+      .filter(|n| filter(n))
+      .map(|n| projection(&n))
+      .collect()
+  }
+
+  /// Removes every edge for which `pred` returns false, and returns the number
+  /// of edges removed. Vertices are kept, even if they become unreachable, so
+  /// this is cheaper than garbage collection when only edges need to be
+  /// dropped (e.g., moves that a rules change has made illegal). Sources of
+  /// removed edges that were fully expanded become partially expanded.
+  ///
+  /// Edges are visited in order of edge ID. Vertex IDs do not change, but edge
+  /// IDs are reassigned as described by
+  /// [last_remap](struct.Graph.html#method.last_remap) if any edge is removed.
+  pub fn retain_edges<F>(&mut self, mut pred: F) -> usize
+  where
+    F: FnMut(nav::Edge<T, S, A>) -> bool,
+  {
+    let removed: Vec<EdgeId> = (0..self.arcs.len())
+      .map(EdgeId)
+      .filter(|id| !pred(nav::Edge::new(self, *id)))
+      .collect();
+    let count = removed.len();
+    self.remove_raw_edges(removed);
+    count
+  }
+
+  /// Removes every vertex for which `pred` returns false, along with its
+  /// incident edges, and returns the number of vertices removed. Vertices that
+  /// are kept are retained even if they become unreachable (e.g., when
+  /// dropping positions older than a move horizon), and sources of removed
+  /// edges become partially expanded.
+  ///
+  /// Vertices are visited in order of vertex ID. If any vertex is removed, the
+  /// graph is compacted as by garbage collection: vertices that are kept retain
+  /// their relative order, and vertex and edge IDs are reassigned as described
+  /// by [last_remap](struct.Graph.html#method.last_remap).
+  pub fn retain_nodes<F>(&mut self, mut pred: F) -> usize
+  where
+    F: FnMut(nav::Node<T, S, A>) -> bool,
+  {
+    let keep: Vec<bool> = (0..self.vertices.len())
+      .map(|i| pred(nav::Node::new(self, VertexId(i))))
+      .collect();
+    let roots: Vec<VertexId> = (0..keep.len()).filter(|i| keep[*i]).map(VertexId).collect();
+    let count = keep.len() - roots.len();
+    if count > 0 {
+      mark_compact::Collector::retain_selected(self, &roots, keep);
+    }
+    count
+  }
+
+  /// Reports which vertices and edges garbage collection from the vertices
+  /// for `roots` (e.g., with
+  /// [View::retain_reachable_from](view/struct.View.html#method.retain_reachable_from))
+  /// would drop, without modifying the graph. Game states in `roots` that are
+  /// not in the graph are ignored.
+  ///
+  /// This runs only the mark phase of collection, which takes time linear in
+  /// the size of the reachable part of the graph, so a search may use it to
+  /// decide whether a collection would free enough to be worth its pause.
+  pub fn reachability_report(&self, roots: &[T]) -> stats::ReachabilityReport {
+    let root_ids: Vec<VertexId> = roots
+      .iter()
+      .filter_map(|state| self.lookup(state))
+      .collect();
+    let marked = mark_compact::mark_reachable(self, &root_ids);
+    stats::ReachabilityReport {
+      vertices_before: self.vertices.len(),
+      edges_before: self.arcs.len(),
+      dropped_vertices: (0..marked.len()).filter(|i| !marked[*i]).collect(),
+      dropped_edges: (0..self.arcs.len())
+        .filter(|i| !marked[self.arcs[*i].source.as_usize()])
+        .collect(),
+    }
+  }
 }
 
 #[cfg(test)]
@@ -219,6 +1313,797 @@ mod test {
 
   type Graph = crate::Graph<&'static str, &'static str, &'static str>;
 
+  #[test]
+  fn interning_stats_ok() {
+    let mut g = Graph::new();
+    g.add_node("root", "root_data");
+    assert_eq!(None, g.interning_stats());
+
+    g.set_track_interning(true);
+    g.add_edge("root", |_| "root_data", "0", |_| "0_data", "root_0_data");
+    g.add_node("0", "0_data");
+    let stats = g.interning_stats().unwrap();
+    assert_eq!(2, stats.hits);
+    assert_eq!(1, stats.misses);
+    assert_eq!(3, stats.lookups());
+
+    g.find_node_mut(&"0")
+      .unwrap()
+      .to_child_list()
+      .add_child("1", || "1_data", "0_1_data");
+    assert_eq!(2, g.interning_stats().unwrap().misses);
+
+    g.reset_interning_stats();
+    assert_eq!(Some(Default::default()), g.interning_stats());
+    assert_eq!(None, g.interning_stats().unwrap().hit_rate());
+
+    g.set_track_interning(false);
+    g.add_node("root", "root_data");
+    assert_eq!(None, g.interning_stats());
+  }
+
+  #[test]
+  fn upgrade_ok() {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| "root_data", "0", |_| "0_data", "root_0_data");
+    let token = g
+      .find_node(&"root")
+      .unwrap()
+      .get_child_list()
+      .get_edge(0)
+      .get_target()
+      .token();
+    let mut node = g.upgrade(token).unwrap();
+    assert_eq!("0", *node.get_label());
+    *node.get_data_mut() = "0_data_modified";
+    assert_eq!("0_data_modified", *g.find_node(&"0").unwrap().get_data());
+  }
+
+  #[test]
+  fn upgrade_other_graph_is_none() {
+    let mut g1 = Graph::new();
+    let mut g2 = Graph::new();
+    g1.add_node("root", "root_data");
+    g2.add_node("root", "root_data");
+    let token = g1.find_node(&"root").unwrap().token();
+    assert!(g2.upgrade(token).is_none());
+    assert!(g1.upgrade(token).is_some());
+  }
+
+  #[test]
+  fn upgrade_after_gc_is_none() {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| "root_data", "0", |_| "0_data", "root_0_data");
+    let token = g.find_node(&"0").unwrap().token();
+    crate::view::of_graph(&mut g, |v| {
+      let root = v.find_node(&"root").unwrap();
+      v.retain_reachable_from(vec![root]);
+    });
+    assert!(g.upgrade(token).is_none());
+    let token = g.find_node(&"0").unwrap().token();
+    assert!(g.upgrade(token).is_some());
+  }
+
+  #[test]
+  fn iter_ok() {
+    let mut g = Graph::new();
+    assert_eq!(0, g.iter().len());
+    g.add_edge("root", |_| "root_data", "0", |_| "0_data", "root_0_data");
+    g.add_edge("root", |_| "root_data", "1", |_| "1_data", "root_1_data");
+    assert_eq!(vec![&"root", &"0", &"1"], g.states().collect::<Vec<_>>());
+    assert_eq!(
+      vec![
+        (&"root", &"root_data"),
+        (&"0", &"0_data"),
+        (&"1", &"1_data")
+      ],
+      g.iter().collect::<Vec<_>>()
+    );
+  }
+
+  #[test]
+  fn states_sorted_by_ok() {
+    let mut g = Graph::new();
+    assert!(g.states_sorted_by(|a, b| a.cmp(b)).is_empty());
+    g.add_edge("root", |_| "root_data", "1", |_| "1_data", "root_1_data");
+    g.add_edge("root", |_| "root_data", "0", |_| "0_data", "root_0_data");
+    assert_eq!(
+      vec![&"0", &"1", &"root"],
+      g.states_sorted_by(|a, b| a.cmp(b))
+    );
+    assert_eq!(
+      vec![&"root", &"1", &"0"],
+      g.states_sorted_by(|a, b| b.cmp(a))
+    );
+  }
+
+  #[test]
+  fn find_nodes_where_ok() {
+    let mut g = crate::Graph::<u32, f64, ()>::new();
+    for i in 1..10 {
+      g.add_edge(
+        0,
+        |_| 0.0,
+        i,
+        |_| if i % 3 == 0 { f64::NAN } else { 1.0 },
+        (),
+      );
+    }
+    let nan = |d: &f64| d.is_nan();
+    let labels = |nodes: Vec<crate::nav::Node<u32, f64, ()>>| -> Vec<u32> {
+      nodes.iter().map(|n| *n.get_label()).collect()
+    };
+    assert_eq!(vec![3, 6, 9], labels(g.find_nodes_where(nan)));
+    for threads in 1..12 {
+      assert_eq!(vec![3, 6, 9], labels(g.par_find_nodes_where(nan, threads)));
+    }
+    assert!(g.find_nodes_where(|d| *d > 1.0).is_empty());
+    assert!(crate::Graph::<u32, f64, ()>::new()
+      .par_find_nodes_where(nan, 4)
+      .is_empty());
+  }
+
+  #[test]
+  fn report_ok() {
+    let mut g = crate::Graph::<u32, u32, ()>::new();
+    for i in 1..6 {
+      g.add_edge(0, |_| 0, i, |_| i * 10, ());
+    }
+    g.add_edge(2, |_| 20, 6, |_| 60, ());
+    let rows = g.report(
+      |n| !n.is_leaf(),
+      |n| (*n.get_label(), *n.get_data(), n.get_child_list().len()),
+    );
+    assert_eq!(vec![(0, 0, 5), (2, 20, 1)], rows);
+    // Rows do not borrow the graph.
+    g.add_edge(6, |_| 60, 7, |_| 70, ());
+    assert_eq!(2, rows.len());
+    assert!(g.report(|n| *n.get_data() > 100, |n| n.get_id()).is_empty());
+  }
+
+  #[test]
+  fn recency_ok() {
+    use crate::config::GraphConfig;
+    use crate::stats::Recency;
+    let mut g = Graph::with_config(GraphConfig {
+      track_recency: true,
+      ..Default::default()
+    });
+    g.add_edge("root", |_| "", "a", |_| "", "");
+    g.add_edge("a", |_| "", "b", |_| "", "");
+    g.add_edge("root", |_| "", "c", |_| "", "");
+    assert_eq!(
+      Some(Recency {
+        created: 1,
+        touched: 1,
+      }),
+      g.find_node(&"a").unwrap().get_recency()
+    );
+    let labels = |nodes: Vec<crate::nav::Node<_, _, _>>| -> Vec<&str> {
+      nodes.iter().map(|n| *n.get_label()).collect()
+    };
+    assert_eq!(vec!["root", "a"], labels(g.least_recently_used(2)));
+
+    *g.find_node_mut(&"root").unwrap().get_data_mut() = "modified";
+    g.update_data_bulk(vec![(&"b", |d: &mut &str| *d = "modified")]);
+    assert_eq!(
+      Some(Recency {
+        created: 0,
+        touched: 4,
+      }),
+      g.find_node(&"root").unwrap().get_recency()
+    );
+    assert_eq!(
+      vec!["a", "c", "root", "b"],
+      labels(g.least_recently_used(10))
+    );
+    assert!(g.least_recently_used(0).is_empty());
+
+    g.retain_nodes(|n| *n.get_label() != "a");
+    assert_eq!(vec!["c", "root"], labels(g.least_recently_used(2)));
+    let (_, rest) = g.partition(|state, _| *state == "c");
+    assert_eq!(vec!["root", "b"], labels(rest.least_recently_used(2)));
+    assert_eq!(
+      Some(Recency {
+        created: 0,
+        touched: 4,
+      }),
+      rest.find_node(&"root").unwrap().get_recency()
+    );
+  }
+
+  #[test]
+  fn recency_untracked_ok() {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| "", "a", |_| "", "");
+    *g.find_node_mut(&"a").unwrap().get_data_mut() = "modified";
+    assert_eq!(None, g.find_node(&"a").unwrap().get_recency());
+    assert!(g.least_recently_used(1).is_empty());
+  }
+
+  #[test]
+  fn reserve_children_ok() {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| "", "a", |_| "", "root_a");
+    let mut root = g.find_node_mut(&"root").unwrap();
+    root.reserve_children(10);
+    for child in ["b", "c", "d"].iter() {
+      root.get_child_list_mut().add_child(child, || "", "");
+    }
+    assert_eq!(4, root.get_child_list().len());
+    assert!(g.vertices[0].children.capacity() >= 11);
+    assert!(g.arcs.capacity() >= 11);
+    assert!(g.vertices.capacity() >= 12);
+
+    g.retain_edges(|e| *e.get_data() == "root_a");
+    let mut root = g.find_node_mut(&"root").unwrap();
+    root.shrink_children();
+    assert_eq!(1, root.get_child_list().len());
+    assert_eq!(1, g.vertices[0].children.capacity());
+  }
+
+  #[test]
+  fn topology_clone_ok() {
+    struct Opaque;
+    let mut g = crate::Graph::<&str, Opaque, Opaque>::new();
+    g.add_edge("root", |_| Opaque, "a", |_| Opaque, Opaque);
+    g.add_edge("a", |_| Opaque, "b", |_| Opaque, Opaque);
+    g.add_edge("root", |_| Opaque, "b", |_| Opaque, Opaque);
+    g.alias_state("b2", &"b").unwrap();
+    {
+      let mut root = g.find_node_mut(&"root").unwrap();
+      root.get_child_list_mut().add_unexpanded_child(Opaque);
+    }
+    let t = g.topology_clone();
+    assert_eq!(3, t.vertex_count());
+    assert_eq!(3, t.edge_count());
+    for state in ["root", "a", "b"].iter() {
+      let n = g.find_node(state).unwrap();
+      let m = t.find_node(state).unwrap();
+      assert_eq!(n.get_id(), m.get_id());
+      assert_eq!(n.get_expansion(), m.get_expansion());
+      let n_targets: Vec<usize> = n
+        .get_child_list()
+        .iter()
+        .map(|e| e.get_target().get_id())
+        .collect();
+      let m_targets: Vec<usize> = m
+        .get_child_list()
+        .iter()
+        .map(|e| e.get_target().get_id())
+        .collect();
+      assert_eq!(n_targets, m_targets);
+      assert_eq!(n.get_parent_list().len(), m.get_parent_list().len());
+    }
+    assert_eq!(
+      t.find_node(&"b").unwrap().get_id(),
+      t.find_node(&"b2").unwrap().get_id()
+    );
+    let root = t.find_node(&"root").unwrap();
+    assert_eq!(1, root.get_child_list().unexpanded_len());
+
+    // The copy is independent of the original.
+    let mut t = t;
+    t.add_edge("b", |_| (), "c", |_| (), ());
+    assert_eq!(4, t.vertex_count());
+    assert_eq!(3, g.vertex_count());
+  }
+
+  #[test]
+  fn retain_edges_ok() {
+    use crate::nav::Expansion;
+    let mut g = Graph::new();
+    g.add_edge("root", |_| "", "a", |_| "", "legal");
+    g.add_edge("root", |_| "", "b", |_| "", "illegal");
+    g.add_edge("a", |_| "", "b", |_| "", "legal");
+    g.add_edge("b", |_| "", "root", |_| "", "illegal");
+    g.find_node_mut(&"root").unwrap().mark_expanded();
+    assert_eq!(0, g.retain_edges(|_| true));
+    assert!(g.last_remap().is_none());
+
+    assert_eq!(2, g.retain_edges(|e| *e.get_data() == "legal"));
+    assert_eq!(3, g.vertex_count());
+    assert_eq!(2, g.edge_count());
+    let root = g.find_node(&"root").unwrap();
+    assert_eq!(Expansion::PartiallyExpanded, root.get_expansion());
+    assert!(root.is_root());
+    let children: Vec<_> = root
+      .get_child_list()
+      .iter()
+      .map(|e| *e.get_target().get_label())
+      .collect();
+    assert_eq!(vec!["a"], children);
+    let b = g.find_node(&"b").unwrap();
+    assert!(b.is_leaf());
+    assert_eq!(1, b.get_parent_list().len());
+    assert_eq!(
+      "a",
+      *b.get_parent_list().get_edge(0).get_source().get_label()
+    );
+    let remap = g.last_remap().unwrap();
+    assert_eq!(Some(0), remap.edge(0));
+    assert_eq!(None, remap.edge(1));
+    assert_eq!(Some(1), remap.edge(2));
+    assert!(crate::testing::invariant_violations(&g).is_empty());
+  }
+
+  /// Checks that the most recent remap of `g` maps each edge whose data was
+  /// `before[i]` to the edge that now has that data, and removed edges to
+  /// `None`. Edge data must be distinct.
+  fn check_edge_remap(g: &Graph, before: &[&'static str]) {
+    let remap = g.last_remap().unwrap();
+    for (old, data) in before.iter().enumerate() {
+      let new = g.arcs.iter().position(|arc| arc.data == *data);
+      assert_eq!(new, remap.edge(old), "edge {} ({})", old, data);
+    }
+    assert!(crate::testing::invariant_violations(g).is_empty());
+  }
+
+  #[test]
+  fn retain_edges_several_ok() {
+    let mut g = Graph::new();
+    for target in ["a", "b", "c", "d", "e"] {
+      g.add_edge("root", |_| "", target, |_| "", target);
+    }
+    g.add_edge("a", |_| "", "b", |_| "", "a_b");
+    let before: Vec<&str> = g.arcs.iter().map(|arc| arc.data).collect();
+    assert_eq!(2, g.retain_edges(|e| !["b", "d"].contains(e.get_data())));
+    check_edge_remap(&g, &before);
+    let remap = g.last_remap().unwrap();
+    assert_eq!(None, remap.edge(3));
+    assert!(remap.edge(4).unwrap() < g.edge_count());
+    let children: Vec<_> = g
+      .find_node(&"root")
+      .unwrap()
+      .get_child_list()
+      .iter()
+      .map(|e| *e.get_data())
+      .collect();
+    assert_eq!(vec!["a", "c", "e"], children);
+
+    // Removing every edge but one, including the last.
+    let before: Vec<&str> = g.arcs.iter().map(|arc| arc.data).collect();
+    assert_eq!(3, g.retain_edges(|e| *e.get_data() == "c"));
+    check_edge_remap(&g, &before);
+  }
+
+  #[test]
+  fn view_remove_edge_several_ok() {
+    let mut g = Graph::new();
+    for target in ["a", "b", "c", "d", "e"] {
+      g.add_edge("root", |_| "", target, |_| "", target);
+    }
+    for removed in ["b", "d", "a"] {
+      let before: Vec<&str> = g.arcs.iter().map(|arc| arc.data).collect();
+      crate::view::of_graph(&mut g, |v| {
+        let root = v.find_node(&"root").unwrap();
+        let edge = v.children(root).find(|e| v[*e] == removed).unwrap();
+        v.remove_edge(edge);
+      });
+      check_edge_remap(&g, &before);
+    }
+    let children: Vec<_> = g
+      .find_node(&"root")
+      .unwrap()
+      .get_child_list()
+      .iter()
+      .map(|e| *e.get_data())
+      .collect();
+    assert_eq!(vec!["c", "e"], children);
+  }
+
+  #[test]
+  fn retain_nodes_ok() {
+    use crate::nav::Expansion;
+    let mut g = crate::Graph::<&str, u32, &str>::new();
+    g.add_edge("root", |_| 0, "a", |_| 1, "root_a");
+    g.add_edge("root", |_| 0, "b", |_| 1, "root_b");
+    g.add_edge("a", |_| 1, "c", |_| 2, "a_c");
+    g.add_edge("b", |_| 1, "c", |_| 2, "b_c");
+    g.add_edge("c", |_| 2, "d", |_| 3, "c_d");
+    g.find_node_mut(&"root").unwrap().mark_expanded();
+    assert_eq!(0, g.retain_nodes(|_| true));
+    assert!(g.last_remap().is_none());
+
+    assert_eq!(
+      2,
+      g.retain_nodes(|n| *n.get_data() > 0 && *n.get_label() != "b")
+    );
+    assert_eq!(3, g.vertex_count());
+    assert_eq!(2, g.edge_count());
+    assert!(g.find_node(&"root").is_none());
+    assert!(g.find_node(&"b").is_none());
+    let labels: Vec<_> = g.states().cloned().collect();
+    assert_eq!(vec!["a", "c", "d"], labels);
+    let a = g.find_node(&"a").unwrap();
+    assert!(a.is_root());
+    assert_eq!(Expansion::PartiallyExpanded, a.get_expansion());
+    let c = g.find_node(&"c").unwrap();
+    assert_eq!(1, c.get_parent_list().len());
+    assert_eq!("a_c", *c.get_parent_list().get_edge(0).get_data());
+    assert_eq!("c_d", *c.get_child_list().get_edge(0).get_data());
+    let remap = g.last_remap().unwrap();
+    assert_eq!(None, remap.vertex(0));
+    assert_eq!(Some(0), remap.vertex(1));
+  }
+
+  #[test]
+  fn reachability_report_ok() {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| "", "a", |_| "", "root_a");
+    g.add_edge("root", |_| "", "b", |_| "", "root_b");
+    g.add_edge("b", |_| "", "c", |_| "", "b_c");
+    g.add_edge("x", |_| "", "a", |_| "", "x_a");
+    g.add_edge("c", |_| "", "b", |_| "", "c_b");
+    let report = g.reachability_report(&["b", "missing"]);
+    assert_eq!(5, report.vertices_before);
+    assert_eq!(5, report.edges_before);
+    assert_eq!(vec![0, 1, 4], report.dropped_vertices);
+    assert_eq!(vec![0, 1, 3], report.dropped_edges);
+    assert_eq!(2, report.vertices_after());
+    assert_eq!(2, report.edges_after());
+    assert_eq!(5, g.vertex_count());
+    assert!(g.last_remap().is_none());
+
+    crate::view::of_graph(&mut g, |v| {
+      let b = v.find_node(&"b").unwrap();
+      v.retain_reachable_from(vec![b]);
+    });
+    assert_eq!(report.vertices_after(), g.vertex_count());
+    assert_eq!(report.edges_after(), g.edge_count());
+    let report = g.reachability_report(&["b"]);
+    assert!(report.dropped_vertices.is_empty() && report.dropped_edges.is_empty());
+    assert_eq!(2, g.reachability_report(&[]).dropped_vertices.len());
+  }
+
+  #[test]
+  fn reattach_ok() {
+    use crate::config::GraphConfig;
+    for track_parents in [true, false] {
+      let mut g = Graph::with_config(GraphConfig {
+        track_parents,
+        ..GraphConfig::default()
+      });
+      g.add_edge("root", |_| "", "a", |_| "", "root_a");
+      g.add_edge("root", |_| "", "b", |_| "", "root_b");
+      g.add_edge("b", |_| "", "a", |_| "", "b_a");
+      g.add_edge("a", |_| "", "c", |_| "", "a_c");
+      assert!(g.reattach(&"a", &"missing", "").is_none());
+      assert!(g.reattach(&"missing", &"c", "").is_none());
+      assert_eq!(4, g.edge_count());
+
+      let edge = g.reattach(&"a", &"c", "c_a").unwrap();
+      assert_eq!("c", *edge.get_source().get_label());
+      assert_eq!("a", *edge.get_target().get_label());
+      assert_eq!(3, g.edge_count());
+      assert!(g.find_node(&"b").unwrap().is_leaf());
+      let root = g.find_node(&"root").unwrap();
+      assert_eq!(1, root.get_child_list().len());
+      let c = g.find_node(&"c").unwrap();
+      assert_eq!("c_a", *c.get_child_list().get_edge(0).get_data());
+      if track_parents {
+        let a = g.find_node(&"a").unwrap();
+        assert_eq!(1, a.get_parent_list().len());
+        assert_eq!("c_a", *a.get_parent_list().get_edge(0).get_data());
+      }
+      assert!(crate::testing::invariant_violations(&g).is_empty());
+    }
+  }
+
+  #[test]
+  fn reattach_several_ok() {
+    use crate::config::GraphConfig;
+    for track_parents in [true, false] {
+      let mut g = Graph::with_config(GraphConfig {
+        track_parents,
+        ..GraphConfig::default()
+      });
+      g.add_edge("root", |_| "", "x", |_| "", "root_x");
+      g.add_edge("root", |_| "", "a", |_| "", "root_a");
+      g.add_edge("b", |_| "", "a", |_| "", "b_a");
+      g.add_edge("b", |_| "", "y", |_| "", "b_y");
+      g.add_edge("c", |_| "", "a", |_| "", "c_a");
+      g.add_edge("a", |_| "", "z", |_| "", "a_z");
+      let before: Vec<&str> = g.arcs.iter().map(|arc| arc.data).collect();
+      g.reattach(&"a", &"x", "x_a").unwrap();
+      check_edge_remap(&g, &before);
+      let remap = g.last_remap().unwrap();
+      assert_eq!(
+        3,
+        (0..before.len())
+          .filter(|i| remap.edge(*i).is_none())
+          .count()
+      );
+      assert_eq!(4, g.edge_count());
+      assert_eq!("x_a", g.arcs[3].data);
+    }
+  }
+
+  #[test]
+  fn expansion_ok() {
+    use crate::nav::Expansion;
+    let mut g = Graph::new();
+    g.add_node("root", "root_data");
+    assert_eq!(
+      Expansion::Unexpanded,
+      g.find_node(&"root").unwrap().get_expansion()
+    );
+    g.add_edge("root", |_| "root_data", "0", |_| "0_data", "root_0_data");
+    assert_eq!(
+      Expansion::PartiallyExpanded,
+      g.find_node(&"root").unwrap().get_expansion()
+    );
+    assert_eq!(
+      Expansion::Unexpanded,
+      g.find_node(&"0").unwrap().get_expansion()
+    );
+    g.find_node_mut(&"root").unwrap().mark_expanded();
+    g.find_node_mut(&"0").unwrap().mark_expanded();
+    assert_eq!(
+      Expansion::FullyExpanded,
+      g.find_node(&"root").unwrap().get_expansion()
+    );
+    // A terminal vertex may be fully expanded without having children.
+    assert!(g.find_node(&"0").unwrap().is_leaf());
+    assert_eq!(
+      Expansion::FullyExpanded,
+      g.find_node(&"0").unwrap().get_expansion()
+    );
+    // Adding more children does not undo marking.
+    g.add_edge("root", |_| "root_data", "1", |_| "1_data", "root_1_data");
+    assert_eq!(
+      Expansion::FullyExpanded,
+      g.find_node(&"root").unwrap().get_expansion()
+    );
+    let (root, rest) = g.partition(|state, _| *state != "1");
+    assert_eq!(
+      Expansion::PartiallyExpanded,
+      root.find_node(&"root").unwrap().get_expansion()
+    );
+    assert_eq!(
+      Expansion::FullyExpanded,
+      root.find_node(&"0").unwrap().get_expansion()
+    );
+    assert_eq!(
+      Expansion::Unexpanded,
+      rest.find_node(&"1").unwrap().get_expansion()
+    );
+  }
+
+  #[test]
+  fn unexpanded_child_ok() {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| "root_data", "a", |_| "a_data", "root_a");
+    {
+      let mut children = g.find_node_mut(&"root").unwrap().to_child_list();
+      assert_eq!(0, children.add_unexpanded_child("root_b"));
+      assert_eq!(1, children.add_unexpanded_child("root_a2"));
+      assert_eq!(1, children.len());
+      assert_eq!(2, children.unexpanded_len());
+      assert!(children.get_unexpanded_mut(2).is_none());
+    }
+    g.find_node_mut(&"a")
+      .unwrap()
+      .to_child_list()
+      .add_unexpanded_child("a_c");
+    assert_eq!(2, g.vertex_count());
+    assert_eq!(1, g.edge_count());
+    assert_eq!(
+      vec!["root_b", "root_a2"],
+      g.find_node(&"root")
+        .unwrap()
+        .get_child_list()
+        .iter_unexpanded()
+        .cloned()
+        .collect::<Vec<_>>()
+    );
+
+    // Resolving to an existing vertex adds no vertex.
+    let edge = g
+      .find_node_mut(&"root")
+      .unwrap()
+      .to_child_list()
+      .to_unexpanded(1)
+      .unwrap()
+      .expand("a", || panic!());
+    assert_eq!("root_a2", *edge.get_data());
+    assert_eq!(2, g.vertex_count());
+    // Resolving to a new vertex creates it.
+    let edge = g
+      .find_node_mut(&"root")
+      .unwrap()
+      .to_child_list()
+      .to_unexpanded(0)
+      .unwrap()
+      .expand("b", || "b_data");
+    assert_eq!("b_data", *edge.get_target().get_data());
+    let root = g.find_node(&"root").unwrap().get_child_list();
+    assert_eq!(0, root.unexpanded_len());
+    assert_eq!(3, root.len());
+
+    // Unexpanded edges follow their source through garbage collection.
+    g.compact_with(&["a"], &mut []);
+    assert_eq!(1, g.vertex_count());
+    assert_eq!(
+      "a_c",
+      *g.find_node(&"a")
+        .unwrap()
+        .get_child_list()
+        .get_unexpanded_data(0)
+    );
+    let mut children = g.find_node_mut(&"a").unwrap().to_child_list();
+    assert_eq!("a_c", children.get_unexpanded_mut(0).unwrap().discard());
+    assert_eq!(0, children.unexpanded_len());
+  }
+
+  #[test]
+  fn expand_all_ok() {
+    let mut g = crate::Graph::<u32, u32, u32>::new();
+    g.add_edge(0, |_| 0, 2, |_| 20, 2);
+    let mut root = g.find_node_mut(&0).unwrap();
+    assert_eq!(0, root.expand_all(|_| panic!()));
+    {
+      let mut children = root.get_child_list_mut();
+      for m in 1..5 {
+        children.add_unexpanded_child(m);
+      }
+    }
+    // 0 and 2 are already in the graph.
+    assert_eq!(2, root.expand_all(|m| (m % 4, m * 100)));
+    assert_eq!(0, root.get_child_list().unexpanded_len());
+    let targets: Vec<u32> = root
+      .get_child_list()
+      .iter()
+      .map(|e| *e.get_target().get_label())
+      .collect();
+    assert_eq!(vec![2, 1, 2, 3, 0], targets);
+    assert_eq!(4, g.vertex_count());
+    assert_eq!(100, *g.find_node(&1).unwrap().get_data());
+    assert_eq!(20, *g.find_node(&2).unwrap().get_data());
+  }
+
+  #[test]
+  fn expand_all_panic_ok() {
+    let mut g = crate::Graph::<u32, u32, u32>::new();
+    g.add_node(0, 0);
+    {
+      let mut children = g.find_node_mut(&0).unwrap().to_child_list();
+      for m in 1..5 {
+        children.add_unexpanded_child(m);
+      }
+    }
+    crate::testing::assert_consistent_after_panic(&mut g, |g| {
+      g.find_node_mut(&0).unwrap().expand_all(|m| {
+        assert!(*m < 3);
+        (*m, *m * 100)
+      });
+    });
+    let root = g.find_node(&0).unwrap();
+    assert_eq!(2, root.get_child_list().len());
+    assert_eq!(2, root.get_child_list().unexpanded_len());
+    assert_eq!(3, *root.get_child_list().get_unexpanded_data(0));
+    assert_eq!(3, g.vertex_count());
+    let mut root = g.find_node_mut(&0).unwrap();
+    assert_eq!(0, root.expand_all(|m| (*m, *m * 100)));
+    let targets: Vec<u32> = root
+      .get_child_list()
+      .iter()
+      .map(|e| *e.get_target().get_label())
+      .collect();
+    assert_eq!(vec![1, 2, 3, 4], targets);
+  }
+
+  #[test]
+  fn prune_dominated_ok() {
+    use crate::mutators::Dominance;
+    let mut g = crate::Graph::<u32, (), (u32, u32)>::new();
+    g.add_edge(9, |_| (), 0, |_| (), (0, 0));
+    for (m, stats) in [
+      (1, (3, 1)),
+      (2, (1, 3)),
+      (3, (2, 2)),
+      (4, (1, 1)),
+      (2, (1, 3)),
+    ] {
+      g.add_edge(0, |_| (), m, |_| (), stats);
+    }
+    g.add_edge(4, |_| (), 5, |_| (), (7, 7));
+    g.find_node_mut(&0).unwrap().mark_expanded();
+    let pareto = |a: &(u32, u32), b: &(u32, u32)| {
+      if a == b {
+        Dominance::Incomparable
+      } else if a.0 >= b.0 && a.1 >= b.1 {
+        Dominance::Dominates
+      } else if a.0 <= b.0 && a.1 <= b.1 {
+        Dominance::Dominated
+      } else {
+        Dominance::Incomparable
+      }
+    };
+    let mut root = g.find_node_mut(&0).unwrap();
+    assert_eq!(1, root.get_child_list_mut().prune_dominated(pareto));
+    assert_eq!(
+      crate::nav::Expansion::PartiallyExpanded,
+      root.get_expansion()
+    );
+    let remaining: Vec<_> = root
+      .get_child_list()
+      .iter()
+      .map(|e| *e.get_target().get_label())
+      .collect();
+    assert_eq!(vec![1, 2, 3, 2], remaining);
+    assert_eq!(0, root.get_child_list_mut().prune_dominated(pareto));
+    assert_eq!(6, g.edge_count());
+    assert_eq!(7, g.vertex_count());
+    let four = g.find_node(&4).unwrap();
+    assert!(four.is_root());
+    assert_eq!(
+      5,
+      *four.get_child_list().get_edge(0).get_target().get_label()
+    );
+    assert_eq!(2, g.find_node(&2).unwrap().get_parent_list().len());
+    let remap = g.last_remap().unwrap();
+    assert_eq!(1, remap.to_version());
+    assert_eq!(None, remap.edge(4));
+    assert_eq!(Some(4), remap.edge(6));
+    assert_eq!(Some(3), remap.vertex(3));
+  }
+
+  #[test]
+  fn prune_dominated_remap_ok() {
+    use crate::mutators::Dominance;
+    let mut g = crate::Graph::<u32, (), (u32, u32)>::new();
+    for (m, stats) in [
+      (1, (3, 1)),
+      (2, (1, 1)),
+      (3, (1, 3)),
+      (4, (0, 0)),
+      (5, (2, 2)),
+    ] {
+      g.add_edge(0, |_| (), m, |_| (), stats);
+    }
+    g.add_edge(7, |_| (), 8, |_| (), (9, 9));
+    let before: Vec<(u32, u32)> = g.arcs.iter().map(|arc| arc.data).collect();
+    let pareto = |a: &(u32, u32), b: &(u32, u32)| {
+      if a.0 >= b.0 && a.1 >= b.1 {
+        Dominance::Dominates
+      } else if a.0 <= b.0 && a.1 <= b.1 {
+        Dominance::Dominated
+      } else {
+        Dominance::Incomparable
+      }
+    };
+    let mut root = g.find_node_mut(&0).unwrap();
+    assert_eq!(2, root.get_child_list_mut().prune_dominated(pareto));
+    let remap = g.last_remap().unwrap();
+    let edges: Vec<Option<usize>> = (0..before.len()).map(|i| remap.edge(i)).collect();
+    assert_eq!(vec![Some(0), None, Some(2), None, Some(1), Some(3)], edges);
+    for (old, new) in edges.iter().enumerate() {
+      if let Some(new) = new {
+        assert_eq!(before[old], g.arcs[*new].data);
+      }
+    }
+    assert!(crate::testing::invariant_violations(&g).is_empty());
+  }
+
+  #[test]
+  fn update_data_bulk_ok() {
+    let mut g = crate::Graph::<u32, Vec<u32>, ()>::new();
+    g.add_edge(0, |_| vec![], 1, |_| vec![], ());
+    g.add_edge(0, |_| vec![], 2, |_| vec![], ());
+    let updates: Vec<(u32, u32)> = vec![(2, 20), (5, 50), (0, 0), (2, 21)];
+    let count = g.update_data_bulk(
+      updates
+        .iter()
+        .map(|(state, value)| (state, move |data: &mut Vec<u32>| data.push(*value))),
+    );
+    assert_eq!(3, count);
+    assert_eq!(&vec![0], g.find_node(&0).unwrap().get_data());
+    assert!(g.find_node(&1).unwrap().get_data().is_empty());
+    assert_eq!(&vec![20, 21], g.find_node(&2).unwrap().get_data());
+    assert!(g.find_node(&5).is_none());
+  }
+
   #[test]
   fn send_to_thread_safe_ok() {
     let mut g = Graph::new();
@@ -262,4 +2147,95 @@ mod test {
     })
     .unwrap();
   }
+
+  #[test]
+  fn find_edge_ok() {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| "root_data", "a", |_| "a_data", "root_a");
+    g.add_edge("root", |_| "root_data", "b", |_| "b_data", "root_b");
+    g.add_edge("root", |_| "root_data", "b", |_| "b_data", "root_b2");
+    for indexed in [false, true].iter() {
+      if *indexed {
+        g.index_edges();
+      }
+      assert_eq!("root_b", *g.find_edge(&"root", &"b").unwrap().get_data());
+      assert!(g.find_edge(&"b", &"root").is_none());
+      assert!(g.find_edge(&"root", &"c").is_none());
+    }
+    g.add_edge("b", |_| "", "a", |_| "", "b_a");
+    assert_eq!("b_a", *g.find_edge(&"b", &"a").unwrap().get_data());
+    g.retain_edges(|e| *e.get_data() != "root_b");
+    assert_eq!("root_b2", *g.find_edge(&"root", &"b").unwrap().get_data());
+    g.compact_with(&["b"], &mut []);
+    assert_eq!("b_a", *g.find_edge(&"b", &"a").unwrap().get_data());
+    crate::view::of_graph(&mut g, |v| {
+      let (a, b) = (v.find_node(&"a").unwrap(), v.find_node(&"b").unwrap());
+      assert!(v.find_edge(b, a).is_some());
+      assert!(v.find_edge(a, b).is_none());
+    });
+    g.clear_edge_index();
+    assert_eq!("b_a", *g.find_edge(&"b", &"a").unwrap().get_data());
+  }
+
+  #[test]
+  fn contains_edge_ok() {
+    use crate::config::GraphConfig;
+    let configs = [
+      GraphConfig::default(),
+      GraphConfig {
+        sorted_parents: true,
+        ..Default::default()
+      },
+      GraphConfig {
+        track_parents: false,
+        ..Default::default()
+      },
+    ];
+    for config in configs.iter() {
+      for indexed in [false, true].iter() {
+        let mut g = Graph::with_config(*config);
+        if *indexed {
+          g.index_edges();
+        }
+        g.add_edge("root", |_| "", "a", |_| "", "root_a");
+        g.add_edge("root", |_| "", "b", |_| "", "root_b");
+        g.add_edge("c", |_| "", "a", |_| "", "c_a");
+        g.add_edge("a", |_| "", "a", |_| "", "a_a");
+        assert!(g.contains_edge(&"root", &"a"));
+        assert!(g.contains_edge(&"c", &"a"));
+        assert!(g.contains_edge(&"a", &"a"));
+        assert!(!g.contains_edge(&"a", &"root"));
+        assert!(!g.contains_edge(&"b", &"a"));
+        assert!(!g.contains_edge(&"root", &"missing"));
+        let (root, a, b) = (
+          g.find_node(&"root").unwrap(),
+          g.find_node(&"a").unwrap(),
+          g.find_node(&"b").unwrap(),
+        );
+        assert!(root.has_child(&a));
+        assert!(root.has_child(&b));
+        assert!(a.has_child(&a));
+        assert!(!a.has_child(&root));
+        assert!(!b.has_child(&a));
+        if config.track_parents {
+          assert!(a.has_parent(&root));
+          assert!(!root.has_parent(&a));
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn add_unique_edge_ok() {
+    let mut g = crate::Graph::<&str, (), u32>::new();
+    g.index_edges();
+    g.add_unique_edge("root", |_| (), "a", |_| (), 1, |old, new| *old += new);
+    let edge = g.add_unique_edge("root", |_| (), "a", |_| (), 2, |old, new| *old += new);
+    assert_eq!(3, *edge.get_data());
+    g.add_unique_edge("a", |_| (), "root", |_| (), 4, |old, new| *old += new);
+    assert_eq!(2, g.edge_count());
+    assert_eq!(2, g.vertex_count());
+    let (a, _) = g.partition(|state, _| *state == "root");
+    assert_eq!(0, a.edge_count());
+  }
 }
@@ -5,14 +5,22 @@
 //! retained. Running time and memory required are linear in graph size,
 //! although there is a potential for a high cost when rebuilding the hashtable
 //! that maps from game states to their IDs.
+//!
+//! Collection preserves the relative order of the child edges of each vertex
+//! that is retained, unless the children are explicitly reordered with
+//! `retain_reachable_ordered`. Vertices and edges are renumbered in the order
+//! in which they are reached by a breadth-first traversal from the roots.
 
-use std::cmp::Eq;
+use std::cmp::{Eq, Ordering};
 use std::collections::VecDeque;
 use std::hash::Hash;
 use std::mem;
 use std::ptr;
 
-use crate::base::{EdgeId, VertexId};
+use crate::audit::AuditOp;
+use crate::base::{EdgeId, RawEdge, RawVertex, VertexId};
+use crate::nav::Expansion;
+use crate::remap::{Remap, Remappable};
 use crate::Graph;
 use symbol_map::indexing::{HashIndexing, Indexing};
 use symbol_map::SymbolId;
@@ -21,9 +29,19 @@ use symbol_map::SymbolId;
 /// `f(i)`.
 ///
 /// Elements `j` of `data` for which `f(j)` is `None` are discarded.
-fn permute_compact<T, F>(data: &mut Vec<T>, f: F)
+pub(crate) fn permute_compact<T, F>(data: &mut Vec<T>, f: F)
 where
   F: Fn(usize) -> Option<usize>,
+{
+  permute_compact_with(data, f, |_, _| {})
+}
+
+/// As `permute_compact`, but passes each discarded element to `dropped`, with
+/// its index, instead of dropping it.
+fn permute_compact_with<T, F, D>(data: &mut Vec<T>, f: F, mut dropped: D)
+where
+  F: Fn(usize) -> Option<usize>,
+  D: FnMut(usize, T),
 {
   if data.is_empty() {
     return;
@@ -31,27 +49,66 @@ where
 
   // TODO: We should benchmark doing this in-place vs. via moving.
   let mut new_data: Vec<T> = Vec::with_capacity(data.len());
-  // TODO: This relies on an implementation detail of Vec (namely, that
-  // Vec::with_capacity gives us a block that we can read into with
-  // get_unchecked_mut, even if the index we're accessing is beyond the length
-  // of the Vec). This seems unlikely to change, but it may ultimately be more
-  // future-proof to allocate a block of memory, do writes into it manually,
-  // and pass it to Vec::from_raw_parts.
+  // Writes go through the raw pointer to the Vec's spare capacity, since
+  // indices beyond the length of the Vec may not be addressed through the
+  // slice API. Every index in 0..retained_count is written exactly once
+  // before the length is set.
   let mut retained_count = 0;
   {
     let compacted = data
       .drain(..)
       .enumerate()
-      .filter_map(|(old_index, t)| f(old_index).map(|new_index| (new_index, t)));
+      .filter_map(|(old_index, t)| match f(old_index) {
+        Some(new_index) => Some((new_index, t)),
+        None => {
+          dropped(old_index, t);
+          None
+        }
+      });
     for (new_index, t) in compacted {
-      unsafe { ptr::write(new_data.get_unchecked_mut(new_index), t) };
+      unsafe { ptr::write(new_data.as_mut_ptr().add(new_index), t) };
       retained_count += 1;
     }
   }
   unsafe { new_data.set_len(retained_count) }; // TODO: Maybe do this after each swap?
-  mem::replace(data, new_data);
+  *data = new_data;
+}
+
+/// Returns which vertices of `graph` are reachable from `roots`, indexed by
+/// `VertexId`, without modifying the graph. This is the mark phase of
+/// collection on its own: an edge is retained by collection iff its source is
+/// marked.
+pub(crate) fn mark_reachable<T: Hash + Eq + Clone, S, A>(
+  graph: &Graph<T, S, A>,
+  roots: &[VertexId],
+) -> Vec<bool> {
+  let mut marked = vec![false; graph.vertices.len()];
+  let mut frontier = VecDeque::new();
+  for id in roots.iter() {
+    if !marked[id.as_usize()] {
+      marked[id.as_usize()] = true;
+      frontier.push_back(*id);
+    }
+  }
+  while let Some(id) = frontier.pop_front() {
+    for edge_id in graph.get_vertex(id).children.iter() {
+      let target = graph.get_arc(*edge_id).target;
+      if !marked[target.as_usize()] {
+        marked[target.as_usize()] = true;
+        frontier.push_back(target);
+      }
+    }
+  }
+  marked
 }
 
+/// Compares the data of two child edges, to sort the children of a vertex.
+type ChildOrder<'o, A> = &'o mut dyn FnMut(&A, &A) -> Ordering;
+
+/// The vertices and edges dropped by a collection, with their IDs from before
+/// it, in order of those IDs.
+pub(crate) type Dropped<S, A> = (Vec<(VertexId, RawVertex<S>)>, Vec<(EdgeId, RawEdge<A>)>);
+
 /// Garbage collector state.
 pub struct Collector<'a, T, S, A>
 where
@@ -65,6 +122,19 @@ where
   state_id_map: Vec<Option<VertexId>>,
   arc_id_map: Vec<Option<EdgeId>>,
   frontier: VecDeque<VertexId>,
+  /// Indexed by `VertexId`. If present, vertices that are false are not
+  /// marked, even if they are reachable.
+  keep: Option<Vec<bool>>,
+  /// Indexed by `EdgeId`. If present, edges that are false are not marked,
+  /// even if they are reachable.
+  keep_arcs: Option<Vec<bool>>,
+  /// If present, the vertices and edges that are dropped are moved here
+  /// instead of being dropped.
+  dropped: Option<Dropped<S, A>>,
+  /// Indexed by old `VertexId`. Where the old child edges of each marked
+  /// vertex are described in `child_positions`, as for a `Remap`.
+  child_ranges: Vec<(usize, usize)>,
+  child_positions: Vec<Option<usize>>,
 }
 
 impl<'a, T, S, A> Collector<'a, T, S, A>
@@ -81,22 +151,144 @@ where
   /// `retain_reachable()` method of `MutNode` or the `retain_reachable_from`
   /// method of `Graph`.
   pub(crate) fn retain_reachable(graph: &'a mut Graph<T, S, A>, roots: &[VertexId]) {
+    Self::retain_reachable_with(graph, roots, &mut [])
+  }
+
+  /// As `retain_reachable`, but also rewrites the vertex and edge IDs held by
+  /// `maps` once the graph has been compacted.
+  pub(crate) fn retain_reachable_with(
+    graph: &'a mut Graph<T, S, A>,
+    roots: &[VertexId],
+    maps: &mut [&mut dyn Remappable],
+  ) {
+    Self::collect(graph, roots, None, None, maps, None, false);
+  }
+
+  /// As `retain_reachable`, but also sorts the child edges of each retained
+  /// vertex by `compare` on their data. The sort is stable.
+  pub(crate) fn retain_reachable_ordered(
+    graph: &'a mut Graph<T, S, A>,
+    roots: &[VertexId],
+    compare: ChildOrder<'_, A>,
+  ) {
+    Self::collect(graph, roots, None, None, &mut [], Some(compare), false);
+  }
+
+  /// As `retain_reachable`, but also drops every vertex `v` for which
+  /// `keep[v]` is false, along with its incident edges. Vertices that are only
+  /// reachable through dropped vertices are dropped as well.
+  pub(crate) fn retain_selected(
+    graph: &'a mut Graph<T, S, A>,
+    roots: &[VertexId],
+    keep: Vec<bool>,
+  ) {
+    Self::collect(graph, roots, Some(keep), None, &mut [], None, false);
+  }
+
+  /// As `retain_reachable`, but only follows the edges `e` for which
+  /// `keep_arcs[e]` is true. Other edges are dropped, as are vertices that
+  /// are only reachable through them.
+  pub(crate) fn retain_selected_edges(
+    graph: &'a mut Graph<T, S, A>,
+    roots: &[VertexId],
+    keep_arcs: Vec<bool>,
+  ) {
+    Self::collect(graph, roots, None, Some(keep_arcs), &mut [], None, false);
+  }
+
+  /// As `retain_selected`, but also drops the edges `e` for which
+  /// `keep_arcs[e]` is false, and returns the vertices and edges that are
+  /// dropped instead of dropping them.
+  pub(crate) fn retain_selected_capturing(
+    graph: &'a mut Graph<T, S, A>,
+    roots: &[VertexId],
+    keep: Vec<bool>,
+    keep_arcs: Vec<bool>,
+  ) -> Dropped<S, A> {
+    Self::collect(
+      graph,
+      roots,
+      Some(keep),
+      Some(keep_arcs),
+      &mut [],
+      None,
+      true,
+    )
+  }
+
+  fn collect(
+    graph: &'a mut Graph<T, S, A>,
+    roots: &[VertexId],
+    keep: Option<Vec<bool>>,
+    keep_arcs: Option<Vec<bool>>,
+    maps: &mut [&mut dyn Remappable],
+    order: Option<ChildOrder<'_, A>>,
+    capture: bool,
+  ) -> Dropped<S, A> {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::debug_span!(
+      "retain_reachable",
+      roots = roots.len(),
+      vertices = graph.vertices.len(),
+      edges = graph.arcs.len()
+    )
+    .entered();
+    let from_version = graph.version;
+    graph.version += 1;
     let mut c = Collector::new(graph);
-    c.mark(roots);
+    c.keep = keep;
+    c.keep_arcs = keep_arcs;
+    if capture {
+      c.dropped = Some((Vec::new(), Vec::new()));
+    }
+    c.mark(roots, order);
     c.sweep();
+    let remap = Remap {
+      from_version,
+      vertices: mem::take(&mut c.state_id_map),
+      edges: mem::take(&mut c.arc_id_map),
+      child_ranges: mem::take(&mut c.child_ranges),
+      child_positions: mem::take(&mut c.child_positions),
+    };
+    for map in maps.iter_mut() {
+      map.remap(&remap);
+    }
+    c.graph.last_remap = Some(remap);
+    if let Some(ref mut index) = c.graph.child_index {
+      index.rebuild(&c.graph.vertices, &c.graph.arcs);
+    }
+    if let Some(ref mut heaps) = c.graph.child_heaps {
+      heaps.rebuild(&c.graph.vertices, &c.graph.arcs);
+    }
+    if let Some(ref mut index) = c.graph.edge_index {
+      index.rebuild(&c.graph.vertices, &c.graph.arcs);
+    }
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+      retained_vertices = c.marked_state_count,
+      retained_edges = c.marked_arc_count,
+      "garbage collection finished"
+    );
+    c.dropped.take().unwrap_or_default()
   }
 
   /// Creates a new mark-and-sweep garbage collector with empty initial state.
   fn new(graph: &'a mut Graph<T, S, A>) -> Self {
     let empty_states = vec![None; graph.vertices.len()];
     let empty_arcs = vec![None; graph.arcs.len()];
+    let empty_child_ranges = vec![(0, 0); graph.vertices.len()];
     Collector {
-      graph: graph,
+      graph,
       marked_state_count: 0,
       marked_arc_count: 0,
       state_id_map: empty_states,
       arc_id_map: empty_arcs,
       frontier: VecDeque::new(),
+      keep: None,
+      keep_arcs: None,
+      dropped: None,
+      child_ranges: empty_child_ranges,
+      child_positions: Vec::new(),
     }
   }
 
@@ -105,13 +297,19 @@ where
   /// reassigns `VertexId` and `EdgeId` values.
   ///
   /// As side effects, arc sources and vertex children are updated to use the
-  /// new addressing scheme.
-  fn mark(&mut self, roots: &[VertexId]) {
+  /// new addressing scheme. If `order` is given, the child edges of each
+  /// marked vertex are also sorted by it.
+  fn mark(&mut self, roots: &[VertexId], mut order: Option<ChildOrder<'_, A>>) {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::debug_span!("mark").entered();
     for id in roots.iter() {
+      if !self.is_kept(*id) {
+        continue;
+      }
       Self::remap_state_id(&mut self.state_id_map, &mut self.marked_state_count, *id);
       self.frontier.push_back(*id);
     }
-    while self.mark_next() {}
+    while self.mark_next(&mut order) {}
   }
 
   /// Looks up the mapping between old and new VertexIds. May update
@@ -150,7 +348,19 @@ where
     new_arc_id
   }
 
-  fn mark_next(&mut self) -> bool {
+  fn is_kept(&self, id: VertexId) -> bool {
+    self.keep.as_ref().is_none_or(|keep| keep[id.as_usize()])
+  }
+
+  fn is_arc_kept(&self, id: EdgeId) -> bool {
+    self
+      .keep_arcs
+      .as_ref()
+      .is_none_or(|keep| keep[id.as_usize()])
+      && self.is_kept(self.graph.get_arc(id).target)
+  }
+
+  fn mark_next(&mut self, order: &mut Option<ChildOrder<'_, A>>) -> bool {
     match self.frontier.pop_front() {
       None => false,
       Some(state_id) => {
@@ -161,6 +371,27 @@ where
             vertex.children.drain(0..).collect(),
           )
         };
+        // Children are only dropped or reordered if some are not kept or an
+        // order is given.
+        let old_child_arc_ids =
+          if self.keep.is_some() || self.keep_arcs.is_some() || order.is_some() {
+            Some(child_arc_ids.clone())
+          } else {
+            None
+          };
+        let first_new_arc_id = self.marked_arc_count;
+        if self.keep.is_some() || self.keep_arcs.is_some() {
+          let child_count = child_arc_ids.len();
+          child_arc_ids.retain(|id| self.is_arc_kept(*id));
+          if child_arc_ids.len() < child_count {
+            // Children that were generated have been discarded.
+            self.graph.get_vertex_mut(state_id).expansion = Expansion::PartiallyExpanded;
+          }
+        }
+        if let Some(compare) = order.as_mut() {
+          let arcs = &self.graph.arcs;
+          child_arc_ids.sort_by(|a, b| compare(&arcs[a.as_usize()].data, &arcs[b.as_usize()].data));
+        }
 
         for arc_id in child_arc_ids.iter_mut() {
           let arc = self.graph.get_arc_mut(*arc_id);
@@ -180,6 +411,22 @@ where
           *arc_id = new_arc_id;
         }
 
+        // Record where each old child edge is now, given that new EdgeIds
+        // are assigned to children in order.
+        let start = self.child_positions.len();
+        match old_child_arc_ids {
+          Some(old) => {
+            let arc_id_map = &self.arc_id_map;
+            self.child_positions.extend(old.iter().map(|id| {
+              arc_id_map[id.as_usize()].map(|new_id| new_id.as_usize() - first_new_arc_id)
+            }));
+          }
+          None => self
+            .child_positions
+            .extend((0..child_arc_ids.len()).map(Some)),
+        }
+        self.child_ranges[state_id.as_usize()] = (start, self.child_positions.len() - start);
+
         // Update vertex children to use new EdgeIds.
         self.graph.get_vertex_mut(state_id).children = child_arc_ids;
         true
@@ -191,8 +438,11 @@ where
   /// run after `mark()`.
   ///
   /// Also, updates vertex pointers to parent edges to use the new `EdgeId`
-  /// addressing scheme built in the previous call to `mark()`.
+  /// addressing scheme built in the previous call to `mark()`. The ID maps of
+  /// that scheme are left in place for the caller.
   fn sweep(&mut self) {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::debug_span!("sweep").entered();
     let state_id_map = {
       let mut state_id_map = Vec::new();
       mem::swap(&mut state_id_map, &mut self.state_id_map);
@@ -203,10 +453,58 @@ where
       mem::swap(&mut arc_id_map, &mut self.arc_id_map);
       arc_id_map
     };
-    // Compact marked vertices.
-    permute_compact(&mut self.graph.vertices, |i| {
+    // Compact marked vertices, keeping those that are dropped if requested.
+    let mut dropped = self.dropped.take();
+    permute_compact_with(
+      &mut self.graph.vertices,
+      |i| state_id_map[i].map(|id| id.as_usize()),
+      |i, vertex| {
+        if let Some((ref mut vertices, _)) = dropped {
+          vertices.push((VertexId(i), vertex));
+        }
+      },
+    );
+    if let Some(ref mut recency) = self.graph.recency {
+      permute_compact(&mut recency.vertices, |i| {
+        state_id_map[i].map(|id| id.as_usize())
+      });
+    }
+    self.graph.hot.compact(self.marked_state_count, |i| {
       state_id_map[i].map(|id| id.as_usize())
     });
+    self
+      .graph
+      .vertex_columns
+      .compact(|i| state_id_map[i].map(|id| id.as_usize()));
+    self
+      .graph
+      .edge_columns
+      .compact(|i| arc_id_map[i].map(|id| id.as_usize()));
+    if let Some(ref mut stable) = self.graph.stable {
+      permute_compact(&mut stable.ids, |i| state_id_map[i].map(|id| id.as_usize()));
+      stable.reindex();
+    }
+    self
+      .graph
+      .pins
+      .remap(|i| state_id_map[i].map(|id| id.as_usize()));
+    if let Some(ref mut audit) = self.graph.audit {
+      let vertex = |i: usize| state_id_map[i].map(|id| id.as_usize());
+      let edge = |i: usize| arc_id_map[i].map(|id| id.as_usize());
+      permute_compact(&mut audit.vertices, vertex);
+      permute_compact(&mut audit.edges, edge);
+      audit.remap(vertex, edge);
+      audit.record(AuditOp::Collect, None);
+    }
+    #[cfg(feature = "hashkeys")]
+    {
+      if let Some(ref mut audit) = self.graph.keyed.audit {
+        audit.checks.resize(state_id_map.len(), 0);
+        permute_compact(&mut audit.checks, |i| {
+          state_id_map[i].map(|id| id.as_usize())
+        });
+      }
+    }
     // Drop unmarked vertices.
     self.graph.vertices.truncate(self.marked_state_count);
     // Reassign and compact vertex parents.
@@ -224,20 +522,47 @@ where
     }
 
     // Compact marked arcs.
-    permute_compact(&mut self.graph.arcs, |i| {
-      arc_id_map[i].map(|id| id.as_usize())
-    });
+    permute_compact_with(
+      &mut self.graph.arcs,
+      |i| arc_id_map[i].map(|id| id.as_usize()),
+      |i, arc| {
+        if let Some((_, ref mut arcs)) = dropped {
+          arcs.push((EdgeId(i), arc));
+        }
+      },
+    );
+    self.dropped = dropped;
     // Reassign arc targets.
-    for mut arc in self.graph.arcs.iter_mut() {
+    for arc in self.graph.arcs.iter_mut() {
       arc.target = state_id_map[arc.target.as_usize()].unwrap();
     }
 
     // Update state namespace to use new mapping.
+    #[cfg(feature = "tracing")]
+    let _span = tracing::debug_span!("rehash", states = self.marked_state_count).entered();
     let mut new_state_ids = HashIndexing::default();
     mem::swap(&mut new_state_ids, &mut self.graph.state_ids);
     let mut table = new_state_ids.to_table();
     table.remap(|symbol| state_id_map[symbol.id().as_usize()]);
     self.graph.state_ids = HashIndexing::from_table(table);
+    self.graph.unexpanded = mem::take(&mut self.graph.unexpanded)
+      .into_iter()
+      .filter_map(|(id, edges)| state_id_map[id.as_usize()].map(|new_id| (new_id, edges)))
+      .collect();
+    self
+      .graph
+      .aliases
+      .retain(|_, id| match state_id_map[id.as_usize()] {
+        Some(new_id) => {
+          *id = new_id;
+          true
+        }
+        None => false,
+      });
+    self.state_id_map = state_id_map;
+    self.arc_id_map = arc_id_map;
+    // Sources were renumbered, so sorted parent lists may be out of order.
+    self.graph.sort_parents();
   }
 }
 
@@ -245,6 +570,7 @@ where
 mod test {
   use super::Collector;
   use crate::base::{EdgeId, RawEdge, RawVertex, VertexId};
+  use crate::nav::Expansion;
   use symbol_map::indexing::{HashIndexing, Indexing};
 
   use std::collections::HashMap;
@@ -264,18 +590,24 @@ mod test {
     parents: Vec<EdgeId>,
     children: Vec<EdgeId>,
   ) -> RawVertex<&'static str> {
+    let expansion = if children.is_empty() {
+      Expansion::Unexpanded
+    } else {
+      Expansion::PartiallyExpanded
+    };
     RawVertex {
-      data: data,
-      parents: parents,
-      children: children,
+      data,
+      parents,
+      children,
+      expansion,
     }
   }
 
   fn make_arc(data: &'static str, source: VertexId, target: VertexId) -> RawEdge<&'static str> {
     RawEdge {
-      data: data,
-      source: source,
-      target: target,
+      data,
+      source,
+      target,
     }
   }
 
@@ -297,7 +629,7 @@ mod test {
     assert_eq!(0, g.edge_count());
     let root_ids = [VertexId(0), VertexId(1), VertexId(2)];
     let mut c = Collector::new(&mut g);
-    c.mark(&root_ids);
+    c.mark(&root_ids, None);
     for (i, new_id) in c.state_id_map.iter().enumerate() {
       if new_id.is_some() {
         assert!(root_ids.contains(&VertexId(i)));
@@ -371,7 +703,7 @@ mod test {
 
     // Mark.
     let mut c = Collector::new(&mut g);
-    c.mark(&root_ids);
+    c.mark(&root_ids, None);
 
     for (i, new_id) in c.state_id_map.iter().enumerate() {
       if new_id.is_some() {
@@ -617,9 +949,65 @@ mod test {
     state_associations.insert("00", VertexId(0));
     state_associations.insert("0", VertexId(1));
     state_associations.insert("01", VertexId(2));
+    assert!(crate::testing::invariant_violations(&g).is_empty());
     state_associations.insert("010", VertexId(3));
     let mut state_ids = HashIndexing::<&'static str, VertexId>::default();
     mem::swap(&mut state_ids, &mut g.state_ids);
     assert_eq!(state_ids.to_table().to_hash_map(), state_associations);
   }
+
+  #[test]
+  fn child_index_rebuilt_ok() {
+    fn key(data: &&'static str) -> u64 {
+      data.len() as u64
+    }
+
+    let mut g = empty_graph();
+    g.add_edge("0", |_| "0_data", "00", |_| "00_data", "a");
+    g.add_edge("1", |_| "1_data", "10", |_| "10_data", "b");
+    g.add_edge("1", |_| "1_data", "11", |_| "11_data", "cc");
+    g.index_children_by(key);
+    Collector::retain_reachable(&mut g, &[VertexId(2)]);
+    assert!(crate::testing::invariant_violations(&g).is_empty());
+    let children = g.find_node(&"1").unwrap().get_child_list();
+    assert_eq!("b", *children.find_by_key(1).unwrap().get_data());
+    assert_eq!("cc", *children.find_by_key(2).unwrap().get_data());
+    assert!(g.find_node(&"0").is_none());
+  }
+
+  #[test]
+  fn child_order_ok() {
+    let mut g = empty_graph();
+    for (child, data) in [("c", "3"), ("a", "1"), ("b", "2"), ("a", "0")] {
+      g.add_edge("root", |_| "", child, |_| "", data);
+    }
+    g.add_edge("a", |_| "", "z", |_| "", "9");
+    g.add_edge("a", |_| "", "y", |_| "", "8");
+    let child_data = |g: &Graph, state| -> Vec<&'static str> {
+      g.find_node(&state)
+        .unwrap()
+        .get_child_list()
+        .iter()
+        .map(|e| *e.get_data())
+        .collect()
+    };
+
+    Collector::retain_reachable(&mut g, &[VertexId(0)]);
+    assert_eq!(vec!["3", "1", "2", "0"], child_data(&g, "root"));
+    assert_eq!(vec!["9", "8"], child_data(&g, "a"));
+
+    let root = g.find_node(&"root").unwrap().get_id();
+    Collector::retain_reachable_ordered(&mut g, &[VertexId(root)], &mut |a, b| a.cmp(b));
+    assert_eq!(vec!["0", "1", "2", "3"], child_data(&g, "root"));
+    assert_eq!(vec!["8", "9"], child_data(&g, "a"));
+    assert!(crate::testing::invariant_violations(&g).is_empty());
+    let ids: Vec<usize> = g
+      .find_node(&"root")
+      .unwrap()
+      .get_child_list()
+      .iter()
+      .map(|e| e.get_id())
+      .collect();
+    assert_eq!(vec![0, 1, 2, 3], ids);
+  }
 }
@@ -0,0 +1,132 @@
+//! Export of graph topology to matrix formats for numerical tooling.
+//!
+//! Rows and columns of exported matrices are indexed by vertex ID (as returned
+//! by `get_id` on node handles), and the entry at row `i` and column `j` is the
+//! sum of the weights of all edges from vertex `i` to vertex `j`.
+//!
+//! [Graph::to_csr](../struct.Graph.html#method.to_csr) produces a sparse matrix
+//! in compressed sparse row format. When the `ndarray` feature is enabled,
+//! [Graph::to_adjacency_matrix](../struct.Graph.html#method.to_adjacency_matrix)
+//! produces a dense `ndarray::Array2`.
+
+use std::hash::Hash;
+
+use crate::Graph;
+use symbol_map::SymbolId;
+
+/// A sparse matrix in compressed sparse row (CSR) format.
+///
+/// The column indices and values for row `i` are
+/// `indices[indptr[i]..indptr[i + 1]]` and `values[indptr[i]..indptr[i + 1]]`.
+/// Column indices are sorted and unique within each row. This is the layout
+/// expected by, e.g., `scipy.sparse.csr_matrix((values, indices, indptr))`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CsrMatrix {
+  /// Offsets into `indices` and `values` at which each row starts. Has one
+  /// more element than there are rows.
+  pub indptr: Vec<usize>,
+  /// Column index of each stored entry.
+  pub indices: Vec<usize>,
+  /// Value of each stored entry.
+  pub values: Vec<f64>,
+}
+
+impl CsrMatrix {
+  /// Returns the number of rows (and columns) in the matrix.
+  pub fn dimension(&self) -> usize {
+    self.indptr.len().saturating_sub(1)
+  }
+
+  /// Returns the number of stored entries.
+  pub fn nnz(&self) -> usize {
+    self.values.len()
+  }
+}
+
+impl<T: Hash + Eq + Clone, S, A> Graph<T, S, A> {
+  /// Returns the weighted adjacency matrix of this graph in compressed sparse
+  /// row format, where `weight` gives the weight of each edge.
+  pub fn to_csr<F: Fn(&A) -> f64>(&self, weight: F) -> CsrMatrix {
+    let mut csr = CsrMatrix {
+      indptr: Vec::with_capacity(self.vertices.len() + 1),
+      indices: Vec::with_capacity(self.arcs.len()),
+      values: Vec::with_capacity(self.arcs.len()),
+    };
+    csr.indptr.push(0);
+    let mut row = Vec::new();
+    for vertex in self.vertices.iter() {
+      row.clear();
+      row.extend(vertex.children.iter().map(|e| {
+        let arc = self.get_arc(*e);
+        (arc.target.as_usize(), weight(&arc.data))
+      }));
+      row.sort_by_key(|(column, _)| *column);
+      for (column, value) in row.iter() {
+        if csr.indices.len() > *csr.indptr.last().unwrap() && csr.indices.last() == Some(column) {
+          *csr.values.last_mut().unwrap() += value;
+        } else {
+          csr.indices.push(*column);
+          csr.values.push(*value);
+        }
+      }
+      csr.indptr.push(csr.indices.len());
+    }
+    csr
+  }
+
+  /// Returns the weighted adjacency matrix of this graph as a dense array,
+  /// where `weight` gives the weight of each edge.
+  ///
+  /// This requires the `ndarray` feature.
+  #[cfg(feature = "ndarray")]
+  pub fn to_adjacency_matrix<F: Fn(&A) -> f64>(&self, weight: F) -> ndarray::Array2<f64> {
+    let n = self.vertices.len();
+    let mut matrix = ndarray::Array2::zeros((n, n));
+    for arc in self.arcs.iter() {
+      matrix[[arc.source.as_usize(), arc.target.as_usize()]] += weight(&arc.data);
+    }
+    matrix
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::CsrMatrix;
+
+  type Graph = crate::Graph<&'static str, (), f64>;
+
+  fn make_graph() -> Graph {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| (), "a", |_| (), 1.0);
+    g.add_edge("root", |_| (), "b", |_| (), 2.0);
+    g.add_edge("b", |_| (), "a", |_| (), 3.0);
+    g.add_edge("root", |_| (), "a", |_| (), 0.5);
+    g
+  }
+
+  #[test]
+  fn to_csr_ok() {
+    let csr = make_graph().to_csr(|w| *w);
+    assert_eq!(
+      CsrMatrix {
+        indptr: vec![0, 2, 2, 3],
+        indices: vec![1, 2, 1],
+        values: vec![1.5, 2.0, 3.0],
+      },
+      csr
+    );
+    assert_eq!(3, csr.dimension());
+    assert_eq!(3, csr.nnz());
+    assert_eq!(0, Graph::new().to_csr(|w| *w).dimension());
+  }
+
+  #[cfg(feature = "ndarray")]
+  #[test]
+  fn to_adjacency_matrix_ok() {
+    let matrix = make_graph().to_adjacency_matrix(|w| *w);
+    assert_eq!(
+      ndarray::arr2(&[[0.0, 1.5, 2.0], [0.0, 0.0, 0.0], [0.0, 3.0, 0.0]]),
+      matrix
+    );
+  }
+}
@@ -0,0 +1,128 @@
+//! Support for graphs whose edges correspond one-to-one with game moves.
+//!
+//! Most users of this crate store a representation of a game move in edge
+//! data, and a vertex has at most one child edge for each move that may be
+//! played from it. The [MoveKey](trait.MoveKey.html) trait formalizes this
+//! convention, so that the child edge for a move can be found with
+//! `child_by_move` methods on [nav::Node](../nav/struct.Node.html),
+//! [mutators::MutNode](../mutators/struct.MutNode.html), and
+//! [view::View](../view/struct.View.html).
+//!
+//! ```rust
+//! # use search_graph::Graph;
+//! # use search_graph::moves::MoveKey;
+//! struct Move {
+//!   column: u8,
+//!   visits: u32,
+//! }
+//!
+//! impl MoveKey for Move {
+//!   type Key = u8;
+//!
+//!   fn move_key(&self) -> u8 {
+//!     self.column
+//!   }
+//! }
+//!
+//! # fn main() {
+//! let mut graph: Graph<String, (), Move> = Graph::new();
+//! graph.index_moves();
+//! let mut root = graph.add_node("".into(), ()).to_child_list();
+//! root.add_child("3".into(), || (), Move { column: 3, visits: 0 });
+//! root.add_child("5".into(), || (), Move { column: 5, visits: 0 });
+//! let root = graph.find_node(&"".into()).unwrap();
+//! assert_eq!(root.child_by_move(&5).unwrap().get_target().get_label(), "5");
+//! assert!(root.child_by_move(&4).is_none());
+//! # }
+//! ```
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Associates edge data with a key that identifies the game move the edge
+/// represents.
+pub trait MoveKey {
+  /// The type of keys that identify moves.
+  type Key: Hash + Eq;
+
+  /// Returns the key for the move that this edge represents. This should not
+  /// change after an edge has been added to a graph, or indexed lookups may
+  /// not find the edge by its new key.
+  fn move_key(&self) -> Self::Key;
+}
+
+/// Hashes a move key into the key space of the graph's child index.
+pub(crate) fn hash_key<K: Hash>(key: &K) -> u64 {
+  let mut hasher = DefaultHasher::new();
+  key.hash(&mut hasher);
+  hasher.finish()
+}
+
+/// Extracts an index key from edge data.
+pub(crate) fn index_key<A: MoveKey>(data: &A) -> u64 {
+  hash_key(&data.move_key())
+}
+
+#[cfg(test)]
+mod test {
+  use super::MoveKey;
+  use crate::view;
+
+  #[derive(Debug, PartialEq)]
+  struct Move(u8, u32);
+
+  impl MoveKey for Move {
+    type Key = u8;
+
+    fn move_key(&self) -> u8 {
+      self.0
+    }
+  }
+
+  type Graph = crate::Graph<&'static str, (), Move>;
+
+  fn make_graph() -> Graph {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| (), "0", |_| (), Move(0, 0));
+    g.add_edge("root", |_| (), "1", |_| (), Move(1, 0));
+    g.add_edge("1", |_| (), "0", |_| (), Move(0, 0));
+    g
+  }
+
+  #[test]
+  fn child_by_move_unindexed_ok() {
+    let g = make_graph();
+    let root = g.find_node(&"root").unwrap();
+    assert_eq!(
+      "1",
+      *root.child_by_move(&1).unwrap().get_target().get_label()
+    );
+    assert!(root.child_by_move(&2).is_none());
+  }
+
+  #[test]
+  fn child_by_move_mut_ok() {
+    let mut g = make_graph();
+    g.index_moves();
+    let mut root = g.find_node_mut(&"root").unwrap();
+    root.child_by_move_mut(&1).unwrap().get_data_mut().1 += 1;
+    assert!(root.child_by_move_mut(&2).is_none());
+    assert_eq!(&Move(1, 1), root.child_by_move(&1).unwrap().get_data());
+  }
+
+  #[test]
+  fn view_child_by_move_ok() {
+    let mut g = make_graph();
+    g.index_moves();
+    view::of_graph(&mut g, |mut v| {
+      let one = v.find_node(&"1").unwrap();
+      let two = v.append_node("2", ());
+      v.append_edge(one, two, Move(2, 0));
+      let e = v.child_by_move(one, &2).unwrap();
+      assert_eq!(two, v.edge_target(e));
+      let e = v.child_by_move(one, &0).unwrap();
+      assert_eq!("0", *v.node_state(v.edge_target(e)));
+      assert!(v.child_by_move(one, &1).is_none());
+    });
+  }
+}
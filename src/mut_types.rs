@@ -0,0 +1,198 @@
+//! Traits for read-write navigation that abstract over graph storage.
+//!
+//! These are the mutable counterparts of the traits in
+//! [nav_types](../nav_types/index.html). Mutable handles own an exclusive
+//! borrow of their graph, so they are not `Copy`: an
+//! [IMutVertex](trait.IMutVertex.html) exposes the data on its outgoing edges
+//! directly, and moves along an edge by consuming itself.
+//!
+//! [IMutTypes](trait.IMutTypes.html) is implemented by `&mut Graph`, and
+//! [IMutVertex](trait.IMutVertex.html) by
+//! [MutNode](../mutators/struct.MutNode.html).
+
+use std::hash::Hash;
+
+use crate::mutators::MutNode;
+use crate::Graph;
+
+/// A graph-like structure whose vertices may be looked up for modification.
+pub trait IMutTypes: Sized {
+  /// The type of mutable vertex handles.
+  type Vertex: IMutVertex;
+
+  /// Returns a mutable handle for the vertex with the given label, if there is
+  /// one.
+  fn find_vertex_mut(self, label: &<Self::Vertex as IMutVertex>::Label) -> Option<Self::Vertex>;
+}
+
+/// A mutable handle to a vertex.
+pub trait IMutVertex: Sized {
+  /// The type of vertex labels (game states).
+  type Label;
+  /// The type of vertex data.
+  type Data;
+  /// The type of edge data.
+  type EdgeData;
+
+  /// Returns an ID that identifies this vertex uniquely within its graph.
+  fn get_id(&self) -> usize;
+
+  /// Returns the canonical label of this vertex.
+  fn get_label(&self) -> &Self::Label;
+
+  /// Returns the data at this vertex.
+  fn get_data(&self) -> &Self::Data;
+
+  /// Returns the data at this vertex, mutably.
+  fn get_data_mut(&mut self) -> &mut Self::Data;
+
+  /// Returns the number of outgoing edges.
+  fn child_count(&self) -> usize;
+
+  /// Returns the data at the `i`th outgoing edge. Panics if there are `i` or
+  /// fewer outgoing edges.
+  fn get_child_data(&self, i: usize) -> &Self::EdgeData;
+
+  /// Returns the data at the `i`th outgoing edge, mutably. Panics if there are
+  /// `i` or fewer outgoing edges.
+  fn get_child_data_mut(&mut self, i: usize) -> &mut Self::EdgeData;
+
+  /// Consumes `self` and returns a handle for the target of the `i`th outgoing
+  /// edge. Panics if there are `i` or fewer outgoing edges.
+  fn to_child(self, i: usize) -> Self;
+
+  /// Adds an outgoing edge to the vertex labeled by `label`, which is created
+  /// with the data returned by `f` if it does not exist. Returns the index of
+  /// the edge among the outgoing edges of this vertex, which may be that of an
+  /// existing edge if the graph merges parallel edges.
+  fn add_child<F>(&mut self, label: Self::Label, f: F, edge_data: Self::EdgeData) -> usize
+  where
+    F: FnOnce() -> Self::Data;
+}
+
+impl<'a, T: Hash + Eq + Clone, S, A> IMutTypes for &'a mut Graph<T, S, A> {
+  type Vertex = MutNode<'a, T, S, A>;
+
+  fn find_vertex_mut(self, label: &T) -> Option<MutNode<'a, T, S, A>> {
+    self.find_node_mut(label)
+  }
+}
+
+impl<'a, T: Hash + Eq + Clone, S, A> IMutVertex for MutNode<'a, T, S, A> {
+  type Label = T;
+  type Data = S;
+  type EdgeData = A;
+
+  fn get_id(&self) -> usize {
+    MutNode::get_id(self)
+  }
+
+  fn get_label(&self) -> &T {
+    MutNode::get_label(self)
+  }
+
+  fn get_data(&self) -> &S {
+    MutNode::get_data(self)
+  }
+
+  fn get_data_mut(&mut self) -> &mut S {
+    MutNode::get_data_mut(self)
+  }
+
+  fn child_count(&self) -> usize {
+    self.graph.get_vertex(self.id).children.len()
+  }
+
+  fn get_child_data(&self, i: usize) -> &A {
+    let arc_id = self.graph.get_vertex(self.id).children[i];
+    &self.graph.get_arc(arc_id).data
+  }
+
+  fn get_child_data_mut(&mut self, i: usize) -> &mut A {
+    let arc_id = self.graph.get_vertex(self.id).children[i];
+    self.graph.touch_edge_data(arc_id);
+    &mut self.graph.get_arc_mut(arc_id).data
+  }
+
+  fn to_child(self, i: usize) -> Self {
+    self.to_child_list().to_edge(i).to_target()
+  }
+
+  fn add_child<F>(&mut self, label: T, f: F, edge_data: A) -> usize
+  where
+    F: FnOnce() -> S,
+  {
+    let edge_id = self.get_child_list_mut().add_child(label, f, edge_data).id;
+    self
+      .graph
+      .get_vertex(self.id)
+      .children
+      .iter()
+      .position(|id| *id == edge_id)
+      .unwrap()
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::{IMutTypes, IMutVertex};
+  use crate::config::{GraphConfig, ParallelEdges};
+
+  type Graph = crate::Graph<&'static str, u32, u32>;
+
+  /// Follows the most visited edge from `root` until reaching a leaf,
+  /// incrementing the visit counts of the vertices and edges along the way,
+  /// and returns the leaf.
+  fn descend<G: IMutTypes>(g: G, root: &<G::Vertex as IMutVertex>::Label) -> G::Vertex
+  where
+    G::Vertex: IMutVertex<Data = u32, EdgeData = u32>,
+  {
+    let mut v = g.find_vertex_mut(root).unwrap();
+    loop {
+      *v.get_data_mut() += 1;
+      let best = (0..v.child_count()).max_by_key(|i| *v.get_child_data(*i));
+      match best {
+        Some(i) => {
+          *v.get_child_data_mut(i) += 1;
+          v = v.to_child(i);
+        }
+        None => return v,
+      }
+    }
+  }
+
+  #[test]
+  fn descend_ok() {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| 0, "a", |_| 0, 0);
+    g.add_edge("root", |_| 0, "b", |_| 0, 5);
+    g.add_edge("b", |_| 0, "c", |_| 0, 0);
+    assert_eq!("c", *descend(&mut g, &"root").get_label());
+    assert_eq!(1, *g.find_node(&"b").unwrap().get_data());
+    assert_eq!(1, *g.find_node(&"c").unwrap().get_data());
+    assert_eq!(0, *g.find_node(&"a").unwrap().get_data());
+    let root = g.find_node(&"root").unwrap();
+    assert_eq!(6, *root.get_child_list().get_edge(1).get_data());
+
+    let mut c = (&mut g).find_vertex_mut(&"c").unwrap();
+    assert_eq!(0, c.add_child("d", || 7, 1));
+    assert_eq!(1, c.add_child("root", || 7, 1));
+    assert_eq!(2, c.child_count());
+    assert_eq!(7, *c.to_child(0).get_data());
+    assert!((&mut g).find_vertex_mut(&"missing").is_none());
+  }
+
+  #[test]
+  fn add_child_merge_ok() {
+    let mut g = Graph::with_config(GraphConfig {
+      parallel_edges: ParallelEdges::Merge,
+      ..Default::default()
+    });
+    let mut root = g.add_node("root", 0);
+    assert_eq!(0, IMutVertex::add_child(&mut root, "a", || 1, 1));
+    assert_eq!(1, IMutVertex::add_child(&mut root, "b", || 1, 1));
+    assert_eq!(0, IMutVertex::add_child(&mut root, "a", || 1, 2));
+    assert_eq!(2, root.child_count());
+    assert_eq!(1, *root.get_child_data(0));
+  }
+}
@@ -3,15 +3,24 @@
 //!
 //! The data structures in this module own a read-write borrow of an underlying
 //! graph. As a result, only one handle may be active at any given time.
+//!
+//! Child edges may be added before their targets are known with
+//! [MutChildList::add_unexpanded_child](struct.MutChildList.html#method.add_unexpanded_child),
+//! and resolved later through an [UnexpandedEdge](struct.UnexpandedEdge.html).
 
 use std::clone::Clone;
 use std::cmp::Eq;
 use std::hash::Hash;
+use std::iter::Enumerate;
 
 use crate::base::{EdgeId, RawEdge, RawVertex, VertexId};
-use crate::nav::{ChildList, ChildListIter, Edge, Node, ParentList, ParentListIter};
+use crate::column::{EdgeColumnId, VertexColumnId};
+use crate::config::ParentsUntracked;
+use crate::moves::MoveKey;
+use crate::nav::{ChildList, ChildListIter, Edge, Expansion, Node, ParentList, ParentListIter};
+use crate::pin::PinnedNodeId;
 use crate::Graph;
-use symbol_map::indexing::{Indexing, Insertion};
+use symbol_map::indexing::Insertion;
 use symbol_map::SymbolId;
 
 /// Mutable handle to a graph vertex ("node handle").
@@ -34,37 +43,57 @@ impl<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> MutNode<'a, T, S, A> {
     MutNode { graph, id }
   }
 
-  fn vertex<'s>(&'s self) -> &'s RawVertex<S> {
+  fn vertex(&self) -> &RawVertex<S> {
     self.graph.get_vertex(self.id)
   }
 
-  fn vertex_mut<'s>(&'s mut self) -> &'s mut RawVertex<S> {
+  fn vertex_mut(&mut self) -> &mut RawVertex<S> {
     self.graph.get_vertex_mut(self.id)
   }
 
   /// Returns an immutable ID that is guaranteed to identify this vertex
-  /// uniquely within its graph. This ID may change when the graph is mutated.
+  /// uniquely within its graph. This ID may change when the graph is mutated;
+  /// use [pin](#method.pin) for an ID that does not.
   pub fn get_id(&self) -> usize {
     self.id.as_usize()
   }
 
+  /// Pins this vertex, returning an ID that may be resolved with
+  /// [Graph::resolve](../struct.Graph.html#method.resolve) after the graph is
+  /// garbage-collected (see [pin](../pin/index.html)).
+  pub fn pin(&mut self) -> PinnedNodeId {
+    self.graph.pins.create(self.id)
+  }
+
   /// Returns the canonical label that is used to address this `MutNode`.
   ///
   /// Graph instances which project multiple labels to the same vertex will
   /// consistently return a single value, regardless of which value was used
   /// to obtain this node handle.
   pub fn get_label(&self) -> &T {
-    &self.graph.get_state(self.id).unwrap()
+    self.graph.get_state(self.id).unwrap()
   }
 
   /// Returns the data at this vertex.
-  pub fn get_data<'s>(&'s self) -> &'s S {
+  pub fn get_data(&self) -> &S {
     &self.vertex().data
   }
 
   /// Returns the data at this vertex, mutably.
-  pub fn get_data_mut<'s>(&'s mut self) -> &'s mut S {
-    &mut self.vertex_mut().data
+  pub fn get_data_mut(&mut self) -> &mut S {
+    self.graph.get_data_mut(self.id)
+  }
+
+  /// Returns the value of this vertex in the column `id` (see
+  /// [column](../column/index.html)). Panics if the graph has no such column.
+  pub fn column<V: 'static>(&self, id: VertexColumnId<V>) -> &V {
+    &self.graph.vertex_column(id)[self.id.as_usize()]
+  }
+
+  /// Returns the value of this vertex in the column `id`, mutably. Panics if
+  /// the graph has no such column.
+  pub fn column_mut<V: 'static>(&mut self, id: VertexColumnId<V>) -> &mut V {
+    &mut self.graph.vertex_column_mut(id)[self.id.as_usize()]
   }
 
   /// Returns true iff this vertex has no outgoing edges.
@@ -72,9 +101,107 @@ impl<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> MutNode<'a, T, S, A> {
     self.vertex().children.is_empty()
   }
 
+  /// Returns how much of this vertex's set of children has been generated.
+  pub fn get_expansion(&self) -> Expansion {
+    self.vertex().expansion
+  }
+
+  /// Records that all of this vertex's children have been generated.
+  pub fn mark_expanded(&mut self) {
+    self.set_expansion(Expansion::FullyExpanded);
+  }
+
+  /// Overrides how much of this vertex's set of children is recorded as
+  /// having been generated (e.g., to revisit a vertex after its move
+  /// generator changes).
+  pub fn set_expansion(&mut self, expansion: Expansion) {
+    self.vertex_mut().expansion = expansion;
+  }
+
+  /// Reserves storage for at least `n` more child edges of this vertex, and
+  /// for as many new vertices and edges in the graph, in the manner of
+  /// `Vec::reserve`. This avoids repeated reallocation when the number of
+  /// children to be added (e.g., the number of legal moves) is known up front.
+  pub fn reserve_children(&mut self, n: usize) {
+    self.graph.vertices.reserve(n);
+    self.graph.arcs.reserve(n);
+    self.vertex_mut().children.reserve(n);
+  }
+
+  /// Releases storage that is reserved for child edges of this vertex but not
+  /// in use (e.g., after children have been pruned), in the manner of
+  /// `Vec::shrink_to_fit`.
+  pub fn shrink_children(&mut self) {
+    self.vertex_mut().children.shrink_to_fit();
+  }
+
   /// Returns true iff this vertex has no incoming edges.
+  ///
+  /// Panics if the graph does not track incoming edges (see
+  /// [GraphConfig](../config/struct.GraphConfig.html)).
   pub fn is_root(&self) -> bool {
-    self.vertex().parents.is_empty()
+    self.graph.parents(self.id).is_empty()
+  }
+
+  /// As `is_root`, but returns an error if the graph does not track incoming
+  /// edges.
+  pub fn try_is_root(&self) -> Result<bool, ParentsUntracked> {
+    Ok(self.graph.try_parents(self.id)?.is_empty())
+  }
+
+  /// Resolves all of this vertex's unexpanded child edges (see
+  /// [MutChildList::add_unexpanded_child](struct.MutChildList.html#method.add_unexpanded_child))
+  /// at once. For each edge, `f` is called on the edge data and returns the
+  /// child's game state and the data for its vertex, which is used only if
+  /// the game state is not already in the graph. Resolved edges are appended
+  /// to the child edges of this vertex in the order in which they were added.
+  ///
+  /// Storage for the new vertices and edges is reserved up front. (The
+  /// transposition table is not, because `symbol_map` provides no means of
+  /// reserving it, so it may still grow as game states are added.) Returns
+  /// the number of edges that resolved to vertices that were already in the
+  /// graph.
+  ///
+  /// If `f` panics, the edges that were resolved before it was called remain
+  /// child edges, and the edge that it was called on and those after it
+  /// remain unexpanded.
+  pub fn expand_all<F>(&mut self, mut f: F) -> usize
+  where
+    F: FnMut(&A) -> (T, S),
+  {
+    let pending = match self.graph.unexpanded.remove(&self.id) {
+      Some(pending) => pending,
+      None => return 0,
+    };
+    self.graph.vertices.reserve(pending.len());
+    self.graph.arcs.reserve(pending.len());
+    self.vertex_mut().children.reserve(pending.len());
+    let mut present = 0;
+    let mut pending = pending.into_iter();
+    while let Some(edge_data) = pending.next() {
+      let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f(&edge_data)));
+      let (child_label, child_data) = match result {
+        Ok(child) => child,
+        Err(payload) => {
+          let mut rest = vec![edge_data];
+          rest.extend(pending);
+          self.graph.unexpanded.insert(self.id, rest);
+          std::panic::resume_unwind(payload)
+        }
+      };
+      let target_id = match self.graph.intern(child_label) {
+        Insertion::Present(id) => {
+          present += 1;
+          id
+        }
+        Insertion::New(id) => {
+          self.graph.add_raw_vertex(child_data);
+          id
+        }
+      };
+      self.graph.add_raw_edge(edge_data, self.id, target_id);
+    }
+    present
   }
 
   /// Returns a traversible list of outgoing edges. Its lifetime will be
@@ -103,22 +230,47 @@ impl<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> MutNode<'a, T, S, A> {
 
   /// Returns a traversible list of incoming edges. Its lifetime will be
   /// limited to a local borrow of `self`.
+  ///
+  /// Panics if the graph does not track incoming edges (see
+  /// [GraphConfig](../config/struct.GraphConfig.html)).
   pub fn get_parent_list<'s>(&'s self) -> ParentList<'s, T, S, A> {
-    ParentList::new(self.graph, self.id)
+    self.get_node().get_parent_list()
+  }
+
+  /// As `get_parent_list`, but returns an error if the graph does not track
+  /// incoming edges.
+  pub fn try_get_parent_list<'s>(&'s self) -> Result<ParentList<'s, T, S, A>, ParentsUntracked> {
+    self.get_node().try_get_parent_list()
   }
 
   /// Returns a traversible list of incoming edges. Its lifetime will be
   /// limited to a local borrow of `self`.
+  ///
+  /// Panics if the graph does not track incoming edges.
   pub fn get_parent_list_mut<'s>(&'s mut self) -> MutParentList<'s, T, S, A> {
-    MutParentList {
+    self
+      .try_get_parent_list_mut()
+      .unwrap_or_else(|e| panic!("{}", e))
+  }
+
+  /// As `get_parent_list_mut`, but returns an error if the graph does not
+  /// track incoming edges.
+  pub fn try_get_parent_list_mut<'s>(
+    &'s mut self,
+  ) -> Result<MutParentList<'s, T, S, A>, ParentsUntracked> {
+    self.graph.try_parents(self.id)?;
+    Ok(MutParentList {
       graph: self.graph,
       id: self.id,
-    }
+    })
   }
 
   /// Returns a traversible list of outgoing edges. `self` is consumed, and
   /// the return value's lifetime will be the same as that of `self`.
+  ///
+  /// Panics if the graph does not track incoming edges.
   pub fn to_parent_list(self) -> MutParentList<'a, T, S, A> {
+    self.graph.parents(self.id);
     MutParentList {
       graph: self.graph,
       id: self.id,
@@ -138,6 +290,40 @@ impl<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> MutNode<'a, T, S, A> {
   pub fn get_node<'s>(&'s self) -> Node<'s, T, S, A> {
     Node::new(self.graph, self.id)
   }
+
+  /// Returns an edge handle for the child edge that represents the move
+  /// `key`, or `None` if there is no such edge. Its lifetime will be limited
+  /// to a local borrow of `self`. See the [moves](../moves/index.html) module.
+  pub fn child_by_move<'s>(&'s self, key: &A::Key) -> Option<Edge<'s, T, S, A>>
+  where
+    A: MoveKey,
+  {
+    self.get_node().child_by_move(key)
+  }
+
+  /// Returns a mutable edge handle for the child edge that represents the
+  /// move `key`, or `None` if there is no such edge. Its lifetime will be
+  /// limited to a local borrow of `self`.
+  pub fn child_by_move_mut<'s>(&'s mut self, key: &A::Key) -> Option<MutEdge<'s, T, S, A>>
+  where
+    A: MoveKey,
+  {
+    let id = self.child_by_move(key).map(|e| e.id)?;
+    Some(MutEdge::new(self.graph, id))
+  }
+}
+
+/// How one child edge compares to another under a partial order on edge data,
+/// as judged by the callback passed to
+/// [MutChildList::prune_dominated](struct.MutChildList.html#method.prune_dominated).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Dominance {
+  /// The first edge dominates the second, which may be pruned.
+  Dominates,
+  /// The first edge is dominated by the second, and may be pruned.
+  Dominated,
+  /// Neither edge dominates the other.
+  Incomparable,
 }
 
 /// A traversible list of a vertex's outgoing edges.
@@ -147,7 +333,7 @@ pub struct MutChildList<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> {
 }
 
 impl<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> MutChildList<'a, T, S, A> {
-  fn vertex<'s>(&'s self) -> &'s RawVertex<S> {
+  fn vertex(&self) -> &RawVertex<S> {
     self.graph.get_vertex(self.id)
   }
 
@@ -172,7 +358,7 @@ impl<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> MutChildList<'a, T, S, A> {
     let id = self.vertex().children[i];
     MutEdge {
       graph: self.graph,
-      id: id,
+      id,
     }
   }
 
@@ -182,7 +368,7 @@ impl<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> MutChildList<'a, T, S, A> {
     let id = self.vertex().children[i];
     MutEdge {
       graph: self.graph,
-      id: id,
+      id,
     }
   }
 
@@ -216,6 +402,142 @@ impl<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> MutChildList<'a, T, S, A> {
     self.get_source_node().get_child_list().iter()
   }
 
+  /// Returns an iterator over child edges paired with their indices.
+  pub fn iter_with_index<'s>(&'s self) -> Enumerate<ChildListIter<'s, T, S, A>> {
+    self.iter().enumerate()
+  }
+
+  /// Returns an edge handle for the first edge whose data satisfies `f`, or
+  /// `None` if there is no such edge.
+  pub fn find_by<'s, F>(&'s self, f: F) -> Option<Edge<'s, T, S, A>>
+  where
+    F: FnMut(&A) -> bool,
+  {
+    self.get_source_node().get_child_list().find_by(f)
+  }
+
+  /// Returns an edge handle for the first edge whose key is `key`, as
+  /// [ChildList::find_by_key](../nav/struct.ChildList.html#method.find_by_key).
+  pub fn find_by_key<'s>(&'s self, key: u64) -> Option<Edge<'s, T, S, A>> {
+    self.get_source_node().get_child_list().find_by_key(key)
+  }
+
+  /// Returns an edge handle for the edge with the greatest key, as
+  /// [ChildList::best_by_key](../nav/struct.ChildList.html#method.best_by_key).
+  pub fn best_by_key<'s>(&'s self) -> Option<Edge<'s, T, S, A>> {
+    self.get_source_node().get_child_list().best_by_key()
+  }
+
+  /// Returns a mutable edge handle for the edge with the greatest key, as
+  /// [ChildList::best_by_key](../nav/struct.ChildList.html#method.best_by_key).
+  /// Its lifetime will be limited to a local borrow of `self`.
+  pub fn best_by_key_mut<'s>(&'s mut self) -> Option<MutEdge<'s, T, S, A>> {
+    let id = self.graph.child_heaps.as_ref()?.best(self.id)?;
+    Some(MutEdge {
+      graph: self.graph,
+      id,
+    })
+  }
+
+  /// Returns the number of unexpanded child edges, whose targets have not
+  /// been determined yet. These are not included in `len`.
+  pub fn unexpanded_len(&self) -> usize {
+    self.graph.get_unexpanded(self.id).len()
+  }
+
+  /// Adds a child edge with data `edge_data` whose target has not been
+  /// determined yet, so that computing the child game state can be put off
+  /// until the edge is traversed. The edge may later be resolved to a vertex
+  /// with [UnexpandedEdge::expand](struct.UnexpandedEdge.html#method.expand).
+  /// Returns the index of the new edge among unexpanded edges.
+  ///
+  /// Unexpanded edges are not visible through edge handles, are not counted
+  /// by `Graph::edge_count`, and do not affect reachability during garbage
+  /// collection. They are dropped along with their source vertex.
+  pub fn add_unexpanded_child(&mut self, edge_data: A) -> usize {
+    let vertex = self.graph.get_vertex_mut(self.id);
+    if vertex.expansion == Expansion::Unexpanded {
+      vertex.expansion = Expansion::PartiallyExpanded;
+    }
+    let edges = self.graph.unexpanded.entry(self.id).or_default();
+    edges.push(edge_data);
+    edges.len() - 1
+  }
+
+  /// Returns a handle for the `i`th unexpanded child edge, with a lifetime
+  /// limited to a borrow of `self`, or `None` if there are `i` or fewer such
+  /// edges.
+  pub fn get_unexpanded_mut<'s>(&'s mut self, i: usize) -> Option<UnexpandedEdge<'s, T, S, A>> {
+    if i < self.unexpanded_len() {
+      Some(UnexpandedEdge {
+        graph: self.graph,
+        source: self.id,
+        index: i,
+      })
+    } else {
+      None
+    }
+  }
+
+  /// Returns a handle for the `i`th unexpanded child edge, or `None` if there
+  /// are `i` or fewer such edges. `self` is consumed, and the return value's
+  /// lifetime will be the same as that of `self`.
+  pub fn to_unexpanded(self, i: usize) -> Option<UnexpandedEdge<'a, T, S, A>> {
+    if i < self.unexpanded_len() {
+      Some(UnexpandedEdge {
+        graph: self.graph,
+        source: self.id,
+        index: i,
+      })
+    } else {
+      None
+    }
+  }
+
+  /// Removes every child edge that is dominated by another child edge, as
+  /// determined by calling `f` on the data of each pair of child edges, and
+  /// returns the number of edges removed. `f` should describe a strict partial
+  /// order; edges with equivalent data should be `Incomparable`. The removed
+  /// edges are also dropped from the parent lists of their targets. The
+  /// relative order of the remaining child edges is unchanged.
+  ///
+  /// Target vertices are retained even if they become unreachable. If any
+  /// edges are removed and the source vertex was fully expanded, it is marked
+  /// as partially expanded.
+  ///
+  /// Removing edges reassigns the IDs of other edges, so if any edges are
+  /// removed, the graph's version is incremented and the reassignment is
+  /// available from
+  /// [Graph::last_remap](../struct.Graph.html#method.last_remap), as after
+  /// garbage collection. Vertex IDs are not changed.
+  pub fn prune_dominated<F>(&mut self, mut f: F) -> usize
+  where
+    F: FnMut(&A, &A) -> Dominance,
+  {
+    let children = self.vertex().children.clone();
+    let mut dominated = vec![false; children.len()];
+    for i in 0..children.len() {
+      for j in (i + 1)..children.len() {
+        let a = &self.graph.get_arc(children[i]).data;
+        let b = &self.graph.get_arc(children[j]).data;
+        match f(a, b) {
+          Dominance::Dominates => dominated[j] = true,
+          Dominance::Dominated => dominated[i] = true,
+          Dominance::Incomparable => (),
+        }
+      }
+    }
+    let removed: Vec<EdgeId> = children
+      .into_iter()
+      .zip(dominated)
+      .filter(|(_, d)| *d)
+      .map(|(id, _)| id)
+      .collect();
+    let count = removed.len();
+    self.graph.remove_raw_edges(removed);
+    count
+  }
+
   /// Adds a child edge to the vertex labeled by `child_label`. If no such
   /// vertex exists, it is created and associated with the data returned by
   /// `f`. Returns a mutable edge handle for the new edge, with a lifetime
@@ -224,15 +546,10 @@ impl<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> MutChildList<'a, T, S, A> {
   where
     F: FnOnce() -> S,
   {
-    let target_id = match self
-      .graph
-      .state_ids
-      .get_or_insert(child_label)
-      .map(|s| *s.id())
-    {
+    let target_id = match self.graph.intern(child_label) {
       Insertion::Present(id) => id,
       Insertion::New(id) => {
-        self.graph.add_raw_vertex(f());
+        self.graph.add_interned_vertex(id, |_| f());
         id
       }
     };
@@ -250,15 +567,10 @@ impl<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> MutChildList<'a, T, S, A> {
   where
     F: FnOnce() -> S,
   {
-    let target_id = match self
-      .graph
-      .state_ids
-      .get_or_insert(child_label)
-      .map(|s| *s.id())
-    {
+    let target_id = match self.graph.intern(child_label) {
       Insertion::Present(id) => id,
       Insertion::New(id) => {
-        self.graph.add_raw_vertex(f());
+        self.graph.add_interned_vertex(id, |_| f());
         id
       }
     };
@@ -270,6 +582,70 @@ impl<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> MutChildList<'a, T, S, A> {
   }
 }
 
+/// Mutable handle to a child edge whose target has not been determined yet.
+/// See
+/// [MutChildList::add_unexpanded_child](struct.MutChildList.html#method.add_unexpanded_child).
+pub struct UnexpandedEdge<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> {
+  graph: &'a mut Graph<T, S, A>,
+  source: VertexId,
+  index: usize,
+}
+
+impl<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> UnexpandedEdge<'a, T, S, A> {
+  fn edges(&mut self) -> &mut Vec<A> {
+    self.graph.unexpanded.get_mut(&self.source).unwrap()
+  }
+
+  /// Removes this edge from the unexpanded edges of its source vertex and
+  /// returns its data. Later unexpanded edges shift down by one index.
+  fn take(mut self) -> (&'a mut Graph<T, S, A>, A) {
+    let index = self.index;
+    let edges = self.edges();
+    let data = edges.remove(index);
+    if edges.is_empty() {
+      self.graph.unexpanded.remove(&self.source);
+    }
+    (self.graph, data)
+  }
+
+  /// Returns the data at this edge.
+  pub fn get_data(&self) -> &A {
+    &self.graph.get_unexpanded(self.source)[self.index]
+  }
+
+  /// Returns the data at this edge, mutably.
+  pub fn get_data_mut(&mut self) -> &mut A {
+    let index = self.index;
+    &mut self.edges()[index]
+  }
+
+  /// Returns a node handle for this edge's source vertex.
+  pub fn get_source(&self) -> Node<'_, T, S, A> {
+    Node::new(self.graph, self.source)
+  }
+
+  /// Resolves this edge to the vertex labeled by `child_label`, looking it up
+  /// in the transposition table. If no such vertex exists, it is created and
+  /// associated with the data returned by `f`. The edge is appended to the
+  /// (expanded) child edges of its source vertex, and later unexpanded edges
+  /// shift down by one index. Returns a mutable edge handle for the resolved
+  /// edge.
+  pub fn expand<F>(self, child_label: T, f: F) -> MutEdge<'a, T, S, A>
+  where
+    F: FnOnce() -> S,
+  {
+    let source = self.source;
+    let (graph, edge_data) = self.take();
+    MutChildList { graph, id: source }.to_add_child(child_label, f, edge_data)
+  }
+
+  /// Removes this edge without resolving it, returning its data. Later
+  /// unexpanded edges shift down by one index.
+  pub fn discard(self) -> A {
+    self.take().1
+  }
+}
+
 /// A traversible list of a vertex's incoming edges.
 pub struct MutParentList<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> {
   graph: &'a mut Graph<T, S, A>,
@@ -277,7 +653,7 @@ pub struct MutParentList<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> {
 }
 
 impl<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> MutParentList<'a, T, S, A> {
-  fn vertex<'s>(&'s self) -> &'s RawVertex<S> {
+  fn vertex(&self) -> &RawVertex<S> {
     self.graph.get_vertex(self.id)
   }
 
@@ -328,7 +704,7 @@ impl<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> MutParentList<'a, T, S, A> {
     let id = self.vertex().parents[i];
     MutEdge {
       graph: self.graph,
-      id: id,
+      id,
     }
   }
 
@@ -338,7 +714,7 @@ impl<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> MutParentList<'a, T, S, A> {
     let id = self.vertex().parents[i];
     MutEdge {
       graph: self.graph,
-      id: id,
+      id,
     }
   }
 
@@ -347,6 +723,11 @@ impl<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> MutParentList<'a, T, S, A> {
     self.get_target_node().get_parent_list().iter()
   }
 
+  /// Returns an iterator over parent edges paired with their indices.
+  pub fn iter_with_index<'s>(&'s self) -> Enumerate<ParentListIter<'s, T, S, A>> {
+    self.iter().enumerate()
+  }
+
   /// Adds a parent edge to the vertex labeled by `parent_label`. If no such
   /// vertex exists, it is created and associated with the data returned by
   /// `f`. Returns a mutable edge handle for the new edge, with a lifetime
@@ -360,15 +741,10 @@ impl<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> MutParentList<'a, T, S, A> {
   where
     F: FnOnce() -> S,
   {
-    let source_id = match self
-      .graph
-      .state_ids
-      .get_or_insert(parent_label)
-      .map(|s| *s.id())
-    {
+    let source_id = match self.graph.intern(parent_label) {
       Insertion::Present(id) => id,
       Insertion::New(id) => {
-        self.graph.add_raw_vertex(f());
+        self.graph.add_interned_vertex(id, |_| f());
         id
       }
     };
@@ -386,15 +762,10 @@ impl<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> MutParentList<'a, T, S, A> {
   where
     F: FnOnce() -> S,
   {
-    let source_id = match self
-      .graph
-      .state_ids
-      .get_or_insert(parent_label)
-      .map(|s| *s.id())
-    {
+    let source_id = match self.graph.intern(parent_label) {
       Insertion::Present(id) => id,
       Insertion::New(id) => {
-        self.graph.add_raw_vertex(f());
+        self.graph.add_interned_vertex(id, |_| f());
         id
       }
     };
@@ -446,9 +817,34 @@ impl<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> MutEdge<'a, T, S, A> {
 
   /// Returns the data at this edge, mutably.
   pub fn get_data_mut(&mut self) -> &mut A {
+    self.graph.touch_edge_data(self.id);
     &mut self.arc_mut().data
   }
 
+  /// Returns the value of this edge in the column `id` (see
+  /// [column](../column/index.html)). Panics if the graph has no such column.
+  pub fn column<V: 'static>(&self, id: EdgeColumnId<V>) -> &V {
+    &self.graph.edge_column(id)[self.id.as_usize()]
+  }
+
+  /// Returns the value of this edge in the column `id`, mutably. Panics if the
+  /// graph has no such column.
+  pub fn column_mut<V: 'static>(&mut self, id: EdgeColumnId<V>) -> &mut V {
+    &mut self.graph.edge_column_mut(id)[self.id.as_usize()]
+  }
+
+  /// Recomputes the key of this edge from its current data and restores the
+  /// order of the heap of its siblings, as described in
+  /// [Graph::order_children_by](../struct.Graph.html#method.order_children_by).
+  /// Does nothing if no heaps have been built.
+  pub fn touch_key(&mut self) {
+    let graph = &mut *self.graph;
+    if let Some(ref mut heaps) = graph.child_heaps {
+      let arc = &graph.arcs[self.id.as_usize()];
+      heaps.update(arc.source, self.id, &arc.data);
+    }
+  }
+
   /// Returns the target of this edge. Returns a node handle, whose lifetime is
   /// limited to a local borrow of `self`.
   pub fn get_target<'s>(&'s self) -> Node<'s, T, S, A> {
@@ -461,7 +857,7 @@ impl<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> MutEdge<'a, T, S, A> {
     let id = self.arc().target;
     MutNode {
       graph: self.graph,
-      id: id,
+      id,
     }
   }
 
@@ -471,7 +867,7 @@ impl<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> MutEdge<'a, T, S, A> {
     let id = self.arc().target;
     MutNode {
       graph: self.graph,
-      id: id,
+      id,
     }
   }
 
@@ -487,7 +883,7 @@ impl<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> MutEdge<'a, T, S, A> {
     let id = self.arc().source;
     MutNode {
       graph: self.graph,
-      id: id,
+      id,
     }
   }
 
@@ -498,7 +894,7 @@ impl<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> MutEdge<'a, T, S, A> {
     let id = self.arc().source;
     MutNode {
       graph: self.graph,
-      id: id,
+      id,
     }
   }
 
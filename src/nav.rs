@@ -6,19 +6,57 @@
 //! structures pointing into it. Graph data that may be updated safely through
 //! read-only references (such as atomic types and `std::cell::RefCell`) may be
 //! modified through these structures.
+//!
+//! A sequence of edges that can be walked end to end (e.g., a principal
+//! variation) is represented by [Path](struct.Path.html), which checks that
+//! its edges are contiguous when it is constructed.
 
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
 use std::hash::Hash;
-use std::iter::Iterator;
+use std::iter::{Enumerate, FusedIterator, Iterator};
+use std::ops::Range;
+use std::slice;
 
+use crate::audit::Provenance;
 use crate::base::{EdgeId, RawEdge, RawVertex, VertexId};
+use crate::column::{EdgeColumnId, VertexColumnId};
+use crate::config::ParentsUntracked;
+use crate::mark_compact;
+use crate::moves::{self, MoveKey};
+use crate::stats::Recency;
 use crate::Graph;
 use symbol_map::SymbolId;
 
+/// How much of a vertex's set of children has been generated.
+///
+/// Vertices are added as `Unexpanded`, and become `PartiallyExpanded` when
+/// their first child edge is added. Since the graph cannot tell when a vertex
+/// has all of its children, a search driver that generates them must say so
+/// with [MutNode::mark_expanded](../mutators/struct.MutNode.html#method.mark_expanded).
+/// This distinguishes vertices that have no children because they are
+/// terminal from those that have not been visited yet.
+///
+/// If garbage collection or partitioning drops some of the children of a
+/// `FullyExpanded` vertex, it reverts to `PartiallyExpanded`. Variants are
+/// ordered from least to most expanded.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Expansion {
+  /// No children have been generated.
+  #[default]
+  Unexpanded,
+  /// Some children have been generated.
+  PartiallyExpanded,
+  /// All children have been generated.
+  FullyExpanded,
+}
+
 /// Immutable handle to a graph vertex ("node handle").
 ///
 /// This zipper-like type enables traversal of a graph along the vertex's
 /// incoming and outgoing edges.
-#[derive(Clone, Copy)]
 pub struct Node<'a, T, S, A>
 where
   T: Hash + Eq + Clone + 'a,
@@ -29,6 +67,14 @@ where
   pub(crate) id: VertexId,
 }
 
+impl<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> Clone for Node<'a, T, S, A> {
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+
+impl<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> Copy for Node<'a, T, S, A> {}
+
 impl<'a, T, S, A> Node<'a, T, S, A>
 where
   T: Hash + Eq + Clone + 'a,
@@ -51,7 +97,15 @@ where
   /// consistently return a single value, regardless of which value was used
   /// to obtain this node handle.
   pub fn get_label(&self) -> &T {
-    &self.graph.get_state(self.id).unwrap()
+    self.graph.get_state(self.id).unwrap()
+  }
+
+  /// Returns an iterator over the game states that have been registered as
+  /// aliases of this vertex with
+  /// [Graph::alias_state](../struct.Graph.html#method.alias_state), in no
+  /// particular order.
+  pub fn get_aliases(&self) -> impl Iterator<Item = &'a T> {
+    self.graph.aliases_of(self.id)
   }
 
   /// Returns an immutable ID that is guaranteed to identify this vertex
@@ -61,7 +115,24 @@ where
   }
 
   fn parents(&self) -> &'a [EdgeId] {
-    &self.graph.get_vertex(self.id).parents
+    self.graph.parents(self.id)
+  }
+
+  /// Returns the IDs of the child edges of this vertex, in the same order as
+  /// [get_child_list](#method.get_child_list). Unlike the child list, this
+  /// does not construct a handle for each edge, so it suits inner loops that
+  /// only need IDs (e.g., to index into
+  /// [Graph::edge_endpoints](../struct.Graph.html#method.edge_endpoints) or
+  /// an [edge column](../column/index.html)).
+  pub fn child_edge_ids(&self) -> &'a [usize] {
+    EdgeId::as_usize_slice(self.children())
+  }
+
+  /// Returns the IDs of the parent edges of this vertex, in the same order as
+  /// [get_parent_list](#method.get_parent_list). Panics if the graph does not
+  /// track incoming edges.
+  pub fn parent_edge_ids(&self) -> &'a [usize] {
+    EdgeId::as_usize_slice(self.parents())
   }
 
   /// Returns the data at this vertex.
@@ -69,16 +140,118 @@ where
     &self.graph.get_vertex(self.id).data
   }
 
+  /// Returns how much of this vertex's set of children has been generated.
+  pub fn get_expansion(&self) -> Expansion {
+    self.graph.get_vertex(self.id).expansion
+  }
+
+  /// Returns when this vertex was created and when its data was last
+  /// modified, or `None` if the graph does not track this (see
+  /// [GraphConfig](../config/struct.GraphConfig.html)).
+  pub fn get_recency(&self) -> Option<Recency> {
+    self
+      .graph
+      .recency
+      .as_ref()
+      .map(|r| r.vertices[self.id.as_usize()])
+  }
+
+  /// Returns the audit tags of the mutations that created this vertex and
+  /// last modified its data, or `None` if the graph is not audited (see
+  /// [audit](../audit/index.html)).
+  pub fn get_provenance(&self) -> Option<Provenance> {
+    self
+      .graph
+      .audit
+      .as_ref()
+      .map(|a| a.vertices[self.id.as_usize()])
+  }
+
+  /// Returns the value of this vertex in the column `id` (see
+  /// [column](../column/index.html)). Panics if the graph has no such column.
+  pub fn column<V: 'static>(&self, id: VertexColumnId<V>) -> &'a V {
+    &self.graph.vertex_column(id)[self.id.as_usize()]
+  }
+
+  /// Returns the stable ID of this vertex, or `None` if the graph does not
+  /// assign them (see [stable](../stable/index.html)).
+  pub fn get_stable_id(&self) -> Option<u64> {
+    self
+      .graph
+      .stable
+      .as_ref()
+      .map(|s| s.ids[self.id.as_usize()])
+  }
+
   /// Returns true iff this vertex has no outgoing edges.
   pub fn is_leaf(&self) -> bool {
     self.children().is_empty()
   }
 
   /// Returns true iff this vertex has no incoming edges.
+  ///
+  /// Panics if the graph does not track incoming edges (see
+  /// [GraphConfig](../config/struct.GraphConfig.html)).
   pub fn is_root(&self) -> bool {
     self.parents().is_empty()
   }
 
+  /// As `is_root`, but returns an error if the graph does not track incoming
+  /// edges.
+  pub fn try_is_root(&self) -> Result<bool, ParentsUntracked> {
+    Ok(self.graph.try_parents(self.id)?.is_empty())
+  }
+
+  /// Returns true iff there is an edge from `parent` to this vertex. This is
+  /// as fast as [has_child](#method.has_child), and is a binary search if the
+  /// graph keeps parent lists sorted (see
+  /// [GraphConfig](../config/struct.GraphConfig.html)).
+  ///
+  /// Panics if the graph does not track incoming edges. In debug builds, also
+  /// panics if `parent` belongs to a different graph.
+  pub fn has_parent(&self, parent: &Node<'_, T, S, A>) -> bool {
+    assert!(self.graph.config.track_parents, "{}", ParentsUntracked);
+    debug_assert!(parent.belongs_to(self.graph), "node from another graph");
+    self.graph.has_edge(parent.id, self.id)
+  }
+
+  /// Returns true iff there is an edge from this vertex to `child`. This is as
+  /// fast as [Graph::contains_edge](../struct.Graph.html#method.contains_edge),
+  /// but does not look up game states.
+  ///
+  /// In debug builds, panics if `child` belongs to a different graph.
+  pub fn has_child(&self, child: &Node<'_, T, S, A>) -> bool {
+    debug_assert!(child.belongs_to(self.graph), "node from another graph");
+    self.graph.has_edge(self.id, child.id)
+  }
+
+  /// Returns an edge handle for the child edge that represents the move
+  /// `key`, or `None` if there is no such edge. See the
+  /// [moves](../moves/index.html) module.
+  pub fn child_by_move(&self, key: &A::Key) -> Option<Edge<'a, T, S, A>>
+  where
+    A: MoveKey,
+  {
+    self.get_child_list().find_by_move(key)
+  }
+
+  /// Returns true iff this is a handle for a vertex of `graph`, rather than of
+  /// some other graph. Graphs are compared by identity, not by contents.
+  pub fn belongs_to(&self, graph: &Graph<T, S, A>) -> bool {
+    self.graph.address() == graph.address()
+  }
+
+  /// Returns a token that identifies this vertex without borrowing its
+  /// graph. The token may later be converted into a mutable node handle with
+  /// [Graph::upgrade](../struct.Graph.html#method.upgrade).
+  pub fn token(&self) -> NodeToken {
+    NodeToken {
+      graph: self.graph.address(),
+      version: self.graph.version,
+      id: self.id,
+    }
+  }
+
   /// Returns a traversible list of outgoing edges.
   pub fn get_child_list(&self) -> ChildList<'a, T, S, A> {
     ChildList {
@@ -88,16 +261,104 @@ where
   }
 
   /// Returns a traversible list of incoming edges.
+  ///
+  /// Panics if the graph does not track incoming edges (see
+  /// [GraphConfig](../config/struct.GraphConfig.html)).
   pub fn get_parent_list(&self) -> ParentList<'a, T, S, A> {
-    ParentList {
-      graph: self.graph,
-      id: self.id,
+    self
+      .try_get_parent_list()
+      .unwrap_or_else(|e| panic!("{}", e))
+  }
+
+  /// As `get_parent_list`, but returns an error if the graph does not track
+  /// incoming edges.
+  pub fn try_get_parent_list(&self) -> Result<ParentList<'a, T, S, A>, ParentsUntracked> {
+    self.graph.try_parents(self.id)?;
+    Ok(ParentList::new(self.graph, self.id))
+  }
+
+  /// Returns the approximate memory used by the part of the graph that is
+  /// reachable from this vertex, as the sum of `sizer` over the game state and
+  /// data of each reachable vertex and of `edge_sizer` over the data of each
+  /// of their child edges. Each vertex and edge is counted once, however many
+  /// paths reach it, and the sizers are not called for anything else.
+  ///
+  /// Comparing the footprints of the children of a vertex shows which
+  /// subtrees are worth pruning. Since subtrees may share vertices, the
+  /// footprints of siblings may sum to more than the footprint of their
+  /// parent.
+  pub fn footprint<F, G>(&self, sizer: F, edge_sizer: G) -> usize
+  where
+    F: Fn(&T, &S) -> usize,
+    G: Fn(&A) -> usize,
+  {
+    let marked = mark_compact::mark_reachable(self.graph, &[self.id]);
+    let mut total = 0;
+    for (i, _) in marked.iter().enumerate().filter(|(_, m)| **m) {
+      let node = Node::new(self.graph, VertexId(i));
+      total += sizer(node.get_label(), node.get_data());
+      for edge in node.get_child_list().iter() {
+        total += edge_sizer(edge.get_data());
+      }
+    }
+    total
+  }
+
+  /// Returns true iff `other` can be reached from this vertex by following
+  /// one or more child edges. A vertex is only its own ancestor if it lies on
+  /// a cycle. The search is breadth-first and stops as soon as `other` is
+  /// found; for many queries on a graph that is not changing, build a
+  /// [ReachabilityIndex](../reach/struct.ReachabilityIndex.html) instead.
+  ///
+  /// In debug builds, panics if `other` belongs to a different graph.
+  pub fn is_ancestor_of(&self, other: &Node<'a, T, S, A>) -> bool {
+    debug_assert!(other.belongs_to(self.graph), "node from another graph");
+    let mut seen = vec![false; self.graph.vertices.len()];
+    let mut frontier = VecDeque::new();
+    frontier.push_back(self.id);
+    while let Some(id) = frontier.pop_front() {
+      for edge_id in self.graph.get_vertex(id).children.iter() {
+        let target = self.graph.get_arc(*edge_id).target;
+        if target == other.id {
+          return true;
+        }
+        if !seen[target.as_usize()] {
+          seen[target.as_usize()] = true;
+          frontier.push_back(target);
+        }
+      }
     }
+    false
+  }
+
+  /// Returns true iff this vertex can be reached from `other` by following
+  /// one or more child edges. See
+  /// [is_ancestor_of](#method.is_ancestor_of).
+  pub fn is_descendant_of(&self, other: &Node<'a, T, S, A>) -> bool {
+    other.is_ancestor_of(self)
   }
 }
 
+/// Identifies a vertex without holding a borrow of its graph.
+///
+/// A `NodeToken` is obtained from a [Node](struct.Node.html) during a
+/// read-only phase of computation, and it may later be converted into a
+/// mutable handle for the same vertex with
+/// [Graph::upgrade](../struct.Graph.html#method.upgrade). Tokens are
+/// invalidated when their graph is garbage-collected.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct NodeToken {
+  pub(crate) graph: usize,
+  pub(crate) version: u64,
+  pub(crate) id: VertexId,
+}
+
 /// A traversible list of a vertex's outgoing edges.
-#[derive(Clone, Copy)]
+///
+/// Child edges are listed in the order in which they were added. Garbage
+/// collection preserves this order, unless children are explicitly sorted
+/// with
+/// [View::retain_reachable_from_sorted_by_key](../view/struct.View.html#method.retain_reachable_from_sorted_by_key).
 pub struct ChildList<'a, T, S, A>
 where
   T: Hash + Eq + Clone + 'a,
@@ -108,6 +369,14 @@ where
   id: VertexId,
 }
 
+impl<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> Clone for ChildList<'a, T, S, A> {
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+
+impl<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> Copy for ChildList<'a, T, S, A> {}
+
 impl<'a, T, S, A> ChildList<'a, T, S, A>
 where
   T: Hash + Eq + Clone + 'a,
@@ -150,37 +419,133 @@ where
     }
   }
 
+  /// Returns the number of unexpanded child edges, whose targets have not
+  /// been determined yet. These are not included in `len`. See
+  /// [MutChildList::add_unexpanded_child](../mutators/struct.MutChildList.html#method.add_unexpanded_child).
+  pub fn unexpanded_len(&self) -> usize {
+    self.graph.get_unexpanded(self.id).len()
+  }
+
+  /// Returns the data of the `i`th unexpanded child edge.
+  pub fn get_unexpanded_data(&self, i: usize) -> &'a A {
+    &self.graph.get_unexpanded(self.id)[i]
+  }
+
+  /// Returns an iterator over the data of unexpanded child edges, in the
+  /// order in which they were added.
+  pub fn iter_unexpanded(&self) -> slice::Iter<'a, A> {
+    self.graph.get_unexpanded(self.id).iter()
+  }
+
+  /// Returns an edge handle for the first edge whose data satisfies `f`, or
+  /// `None` if there is no such edge.
+  pub fn find_by<F>(&self, mut f: F) -> Option<Edge<'a, T, S, A>>
+  where
+    F: FnMut(&A) -> bool,
+  {
+    self.iter().find(|e| f(e.get_data()))
+  }
+
+  /// Returns an edge handle for the first edge whose key is `key`, or `None`
+  /// if there is no such edge. Keys are extracted from edge data by the
+  /// function passed to
+  /// [Graph::index_children_by](../struct.Graph.html#method.index_children_by).
+  ///
+  /// If no index has been built, returns `None`. Edges whose keys have changed
+  /// since they were indexed may not be found by their new keys.
+  pub fn find_by_key(&self, key: u64) -> Option<Edge<'a, T, S, A>> {
+    let index = self.graph.child_index.as_ref()?;
+    match index.get(self.id, key) {
+      Some(id) if (index.key)(&self.graph.get_arc(id).data) == key => {
+        Some(Edge::new(self.graph, id))
+      }
+      Some(_) => self.find_by(|data| (index.key)(data) == key),
+      None => None,
+    }
+  }
+
+  /// Returns an edge handle for the edge with the greatest key, or `None` if
+  /// there are no edges. Keys are extracted from edge data by the function
+  /// passed to
+  /// [Graph::order_children_by](../struct.Graph.html#method.order_children_by),
+  /// as of when each edge was added or its key was last touched.
+  ///
+  /// If no heaps have been built, returns `None`.
+  pub fn best_by_key(&self) -> Option<Edge<'a, T, S, A>> {
+    let heaps = self.graph.child_heaps.as_ref()?;
+    heaps.best(self.id).map(|id| Edge::new(self.graph, id))
+  }
+
+  /// Returns an edge handle for the first edge that represents the move
+  /// `key`, or `None` if there is no such edge.
+  ///
+  /// This takes constant time if an index has been built with
+  /// [Graph::index_moves](../struct.Graph.html#method.index_moves), and it
+  /// scans the list of edges otherwise. With an index, edges whose move keys
+  /// have changed since they were indexed may not be found by their new keys.
+  pub fn find_by_move(&self, key: &A::Key) -> Option<Edge<'a, T, S, A>>
+  where
+    A: MoveKey,
+  {
+    match self.graph.child_index {
+      Some(ref index) if index.by_move => match index.get(self.id, moves::hash_key(key)) {
+        Some(id) if self.graph.get_arc(id).data.move_key() == *key => {
+          Some(Edge::new(self.graph, id))
+        }
+        Some(_) => self.find_by(|data| data.move_key() == *key),
+        None => None,
+      },
+      _ => self.find_by(|data| data.move_key() == *key),
+    }
+  }
+
   /// Returns an iterator over child edges.
   pub fn iter(&self) -> ChildListIter<'a, T, S, A> {
     ChildListIter {
       graph: self.graph,
-      id: self.id,
-      i: 0,
+      edges: self.vertex().children.iter(),
     }
   }
+
+  /// Returns an iterator over child edges paired with their indices, which
+  /// may be passed to `Traversal::Child` or `get_edge`.
+  pub fn iter_with_index(&self) -> Enumerate<ChildListIter<'a, T, S, A>> {
+    self.iter().enumerate()
+  }
+
+  /// Returns the index of `edge` in this list, or `None` if it is not a child
+  /// edge of this list's vertex.
+  ///
+  /// In debug builds, panics if `edge` belongs to a different graph.
+  pub fn position_of(&self, edge: &Edge<'a, T, S, A>) -> Option<usize> {
+    debug_assert!(edge.belongs_to(self.graph), "edge from another graph");
+    self.vertex().children.iter().position(|id| *id == edge.id)
+  }
 }
 
-/// Iterator over a vertex's child edges.
-pub struct ChildListIter<'a, T, S, A>
+impl<'a, T, S, A> IntoIterator for ChildList<'a, T, S, A>
 where
   T: Hash + Eq + Clone + 'a,
   S: 'a,
   A: 'a,
 {
-  graph: &'a Graph<T, S, A>,
-  id: VertexId,
-  i: usize,
+  type Item = Edge<'a, T, S, A>;
+  type IntoIter = ChildListIter<'a, T, S, A>;
+
+  fn into_iter(self) -> ChildListIter<'a, T, S, A> {
+    self.iter()
+  }
 }
 
-impl<'a, T, S, A> ChildListIter<'a, T, S, A>
+/// Iterator over a vertex's child edges.
+pub struct ChildListIter<'a, T, S, A>
 where
   T: Hash + Eq + Clone + 'a,
   S: 'a,
   A: 'a,
 {
-  fn children(&self) -> &'a [EdgeId] {
-    &self.graph.get_vertex(self.id).children
-  }
+  graph: &'a Graph<T, S, A>,
+  edges: slice::Iter<'a, EdgeId>,
 }
 
 impl<'a, T, S, A> Iterator for ChildListIter<'a, T, S, A>
@@ -192,28 +557,44 @@ where
   type Item = Edge<'a, T, S, A>;
 
   fn next(&mut self) -> Option<Edge<'a, T, S, A>> {
-    let cs = self.children();
-    if self.i >= cs.len() {
-      None
-    } else {
-      let e = Edge::new(self.graph, cs[self.i]);
-      self.i += 1;
-      Some(e)
-    }
+    let graph = self.graph;
+    self.edges.next().map(|id| Edge::new(graph, *id))
   }
 
   fn size_hint(&self) -> (usize, Option<usize>) {
-    let l = self.children().len();
-    if l <= self.i {
-      (0, Some(0))
-    } else {
-      (l - self.i, Some(l - self.i))
-    }
+    self.edges.size_hint()
+  }
+}
+
+impl<'a, T, S, A> DoubleEndedIterator for ChildListIter<'a, T, S, A>
+where
+  T: Hash + Eq + Clone + 'a,
+  S: 'a,
+  A: 'a,
+{
+  fn next_back(&mut self) -> Option<Edge<'a, T, S, A>> {
+    let graph = self.graph;
+    self.edges.next_back().map(|id| Edge::new(graph, *id))
   }
 }
 
+impl<'a, T, S, A> ExactSizeIterator for ChildListIter<'a, T, S, A>
+where
+  T: Hash + Eq + Clone + 'a,
+  S: 'a,
+  A: 'a,
+{
+}
+
+impl<'a, T, S, A> FusedIterator for ChildListIter<'a, T, S, A>
+where
+  T: Hash + Eq + Clone + 'a,
+  S: 'a,
+  A: 'a,
+{
+}
+
 /// A traversible list of a vertex's incoming edges.
-#[derive(Clone, Copy)]
 pub struct ParentList<'a, T, S, A>
 where
   T: Hash + Eq + Clone + 'a,
@@ -224,6 +605,14 @@ where
   id: VertexId,
 }
 
+impl<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> Clone for ParentList<'a, T, S, A> {
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+
+impl<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> Copy for ParentList<'a, T, S, A> {}
+
 impl<'a, T, S, A> ParentList<'a, T, S, A>
 where
   T: Hash + Eq + Clone + 'a,
@@ -271,33 +660,49 @@ where
   pub fn iter(&self) -> ParentListIter<'a, T, S, A> {
     ParentListIter {
       graph: self.graph,
-      id: self.id,
-      i: 0,
+      edges: self.vertex().parents.iter(),
     }
   }
+
+  /// Returns an iterator over parent edges paired with their indices, which
+  /// may be passed to `Traversal::Parent` or `get_edge`.
+  pub fn iter_with_index(&self) -> Enumerate<ParentListIter<'a, T, S, A>> {
+    self.iter().enumerate()
+  }
+
+  /// Returns the index of `edge` in this list, or `None` if it is not a
+  /// parent edge of this list's vertex.
+  ///
+  /// In debug builds, panics if `edge` belongs to a different graph.
+  pub fn position_of(&self, edge: &Edge<'a, T, S, A>) -> Option<usize> {
+    debug_assert!(edge.belongs_to(self.graph), "edge from another graph");
+    self.vertex().parents.iter().position(|id| *id == edge.id)
+  }
 }
 
-/// Iterator over a vertex's parent edges.
-pub struct ParentListIter<'a, T, S, A>
+impl<'a, T, S, A> IntoIterator for ParentList<'a, T, S, A>
 where
   T: Hash + Eq + Clone + 'a,
   S: 'a,
   A: 'a,
 {
-  graph: &'a Graph<T, S, A>,
-  id: VertexId,
-  i: usize,
+  type Item = Edge<'a, T, S, A>;
+  type IntoIter = ParentListIter<'a, T, S, A>;
+
+  fn into_iter(self) -> ParentListIter<'a, T, S, A> {
+    self.iter()
+  }
 }
 
-impl<'a, T, S, A> ParentListIter<'a, T, S, A>
+/// Iterator over a vertex's parent edges.
+pub struct ParentListIter<'a, T, S, A>
 where
   T: Hash + Eq + Clone + 'a,
   S: 'a,
   A: 'a,
 {
-  fn parents(&self) -> &'a [EdgeId] {
-    &self.graph.get_vertex(self.id).parents
-  }
+  graph: &'a Graph<T, S, A>,
+  edges: slice::Iter<'a, EdgeId>,
 }
 
 impl<'a, T, S, A> Iterator for ParentListIter<'a, T, S, A>
@@ -309,31 +714,47 @@ where
   type Item = Edge<'a, T, S, A>;
 
   fn next(&mut self) -> Option<Edge<'a, T, S, A>> {
-    let ps = self.parents();
-    if self.i >= ps.len() {
-      None
-    } else {
-      let e = Edge::new(self.graph, ps[self.i]);
-      self.i += 1;
-      Some(e)
-    }
+    let graph = self.graph;
+    self.edges.next().map(|id| Edge::new(graph, *id))
   }
 
   fn size_hint(&self) -> (usize, Option<usize>) {
-    let l = self.parents().len();
-    if l <= self.i {
-      (0, Some(0))
-    } else {
-      (l - self.i, Some(l - self.i))
-    }
+    self.edges.size_hint()
+  }
+}
+
+impl<'a, T, S, A> DoubleEndedIterator for ParentListIter<'a, T, S, A>
+where
+  T: Hash + Eq + Clone + 'a,
+  S: 'a,
+  A: 'a,
+{
+  fn next_back(&mut self) -> Option<Edge<'a, T, S, A>> {
+    let graph = self.graph;
+    self.edges.next_back().map(|id| Edge::new(graph, *id))
   }
 }
 
+impl<'a, T, S, A> ExactSizeIterator for ParentListIter<'a, T, S, A>
+where
+  T: Hash + Eq + Clone + 'a,
+  S: 'a,
+  A: 'a,
+{
+}
+
+impl<'a, T, S, A> FusedIterator for ParentListIter<'a, T, S, A>
+where
+  T: Hash + Eq + Clone + 'a,
+  S: 'a,
+  A: 'a,
+{
+}
+
 /// Immutable handle to a graph edge ("edge handle").
 ///
 /// This zipper-like type enables traversal of a graph along the edge's source
 /// and target vertices.
-#[derive(Clone, Copy)]
 pub struct Edge<'a, T, S, A>
 where
   T: Hash + Eq + Clone + 'a,
@@ -344,6 +765,14 @@ where
   pub(crate) id: EdgeId,
 }
 
+impl<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> Clone for Edge<'a, T, S, A> {
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+
+impl<'a, T: Hash + Eq + Clone + 'a, S: 'a, A: 'a> Copy for Edge<'a, T, S, A> {}
+
 impl<'a, T, S, A> Edge<'a, T, S, A>
 where
   T: Hash + Eq + Clone + 'a,
@@ -386,4 +815,739 @@ where
       id: self.arc().target,
     }
   }
+
+  /// Returns the audit tags of the mutations that created this edge and last
+  /// modified its data, or `None` if the graph is not audited (see
+  /// [audit](../audit/index.html)).
+  pub fn get_provenance(&self) -> Option<Provenance> {
+    self
+      .graph
+      .audit
+      .as_ref()
+      .map(|a| a.edges[self.id.as_usize()])
+  }
+
+  /// Returns the value of this edge in the column `id` (see
+  /// [column](../column/index.html)). Panics if the graph has no such column.
+  pub fn column<V: 'static>(&self, id: EdgeColumnId<V>) -> &'a V {
+    &self.graph.edge_column(id)[self.id.as_usize()]
+  }
+
+  /// Returns true iff this is a handle for an edge of `graph`, rather than of
+  /// some other graph. Graphs are compared by identity, not by contents.
+  pub fn belongs_to(&self, graph: &Graph<T, S, A>) -> bool {
+    self.graph.address() == graph.address()
+  }
+}
+
+/// Errors that may arise when constructing a [Path](struct.Path.html).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum PathError {
+  /// A path was requested from an empty sequence of edges, which does not
+  /// determine the vertex that the path starts at.
+  Empty,
+  /// Paths in different graphs were combined.
+  ForeignGraph,
+  /// An edge ID did not identify an edge of the graph.
+  EdgeBounds {
+    /// The edge ID that was requested.
+    edge_id: usize,
+    /// The actual number of edges (which `edge_id` exceeds).
+    edge_count: usize,
+  },
+  /// The edge at position `index` does not start where the preceding part of
+  /// the path ends (or, for a path taken from a search stack, the path does
+  /// not end at the stack's head).
+  Discontiguous {
+    /// The position of the offending edge in the path.
+    index: usize,
+  },
+}
+
+impl fmt::Display for PathError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      PathError::Empty => write!(f, "Path has no edges"),
+      PathError::ForeignGraph => write!(f, "Paths belong to different graphs"),
+      PathError::EdgeBounds {
+        edge_id,
+        edge_count,
+      } => write!(f, "Path refers to edge {}/{}", edge_id, edge_count),
+      PathError::Discontiguous { index } => write!(f, "Path is broken at edge {}", index),
+    }
+  }
+}
+
+impl Error for PathError {}
+
+/// A sequence of edges in which each edge starts at the target of the
+/// preceding one.
+///
+/// A path always has a first vertex, and may have no edges, in which case its
+/// first and last vertices are the same. Like other handles in this module,
+/// it holds a read-only borrow of its graph, so it cannot be invalidated by
+/// changes to graph topology.
+#[derive(Clone)]
+pub struct Path<'a, T, S, A>
+where
+  T: Hash + Eq + Clone + 'a,
+  S: 'a,
+  A: 'a,
+{
+  graph: &'a Graph<T, S, A>,
+  start: VertexId,
+  edges: Vec<EdgeId>,
+}
+
+impl<'a, T, S, A> Path<'a, T, S, A>
+where
+  T: Hash + Eq + Clone + 'a,
+  S: 'a,
+  A: 'a,
+{
+  /// Creates a path with no edges that starts (and ends) at `node`.
+  pub fn new(node: Node<'a, T, S, A>) -> Self {
+    Path {
+      graph: node.graph,
+      start: node.id,
+      edges: Vec::new(),
+    }
+  }
+
+  /// Creates a path with no edges that starts at the vertex for `state`
+  /// (which may be an alias), or returns `None` if there is no such vertex.
+  pub fn at(graph: &'a Graph<T, S, A>, state: &T) -> Option<Self> {
+    graph.find_node(state).map(Path::new)
+  }
+
+  /// Creates a path through the edges of `graph` with the given IDs (as
+  /// returned by `get_id` on edge handles), in order.
+  ///
+  /// Returns an error if `ids` is empty, if any ID does not identify an edge,
+  /// or if the edges are not contiguous.
+  pub fn from_edge_ids(graph: &'a Graph<T, S, A>, ids: &[usize]) -> Result<Self, PathError> {
+    let first = ids.first().ok_or(PathError::Empty)?;
+    let mut path = Path {
+      graph,
+      start: VertexId(0),
+      edges: Vec::with_capacity(ids.len()),
+    };
+    path.start = graph.get_arc(path.check_edge(*first)?).source;
+    for (index, id) in ids.iter().enumerate() {
+      let id = path.check_edge(*id)?;
+      if graph.get_arc(id).source != path.last_node().id {
+        return Err(PathError::Discontiguous { index });
+      }
+      path.edges.push(id);
+    }
+    Ok(path)
+  }
+
+  fn check_edge(&self, id: usize) -> Result<EdgeId, PathError> {
+    if id < self.graph.arcs.len() {
+      Ok(EdgeId(id))
+    } else {
+      Err(PathError::EdgeBounds {
+        edge_id: id,
+        edge_count: self.graph.arcs.len(),
+      })
+    }
+  }
+
+  /// Returns the number of edges in the path.
+  pub fn len(&self) -> usize {
+    self.edges.len()
+  }
+
+  /// Returns true iff the path has no edges.
+  pub fn is_empty(&self) -> bool {
+    self.edges.is_empty()
+  }
+
+  /// Returns the vertex that the path starts at.
+  pub fn first_node(&self) -> Node<'a, T, S, A> {
+    Node::new(self.graph, self.start)
+  }
+
+  /// Returns the vertex that the path ends at.
+  pub fn last_node(&self) -> Node<'a, T, S, A> {
+    match self.edges.last() {
+      Some(id) => Node::new(self.graph, self.graph.get_arc(*id).target),
+      None => self.first_node(),
+    }
+  }
+
+  /// Returns a handle to the `i`th edge of the path.
+  pub fn get_edge(&self, i: usize) -> Edge<'a, T, S, A> {
+    Edge::new(self.graph, self.edges[i])
+  }
+
+  /// Returns an iterator over the edges of the path, in order.
+  pub fn iter<'s>(
+    &'s self,
+  ) -> impl DoubleEndedIterator<Item = Edge<'a, T, S, A>> + ExactSizeIterator + 's {
+    let graph = self.graph;
+    self.edges.iter().map(move |id| Edge::new(graph, *id))
+  }
+
+  /// Returns the sum of `cost` over the data of the edges of the path.
+  pub fn total_cost<C, F>(&self, mut cost: F) -> C
+  where
+    C: std::iter::Sum<C>,
+    F: FnMut(&A) -> C,
+  {
+    self.iter().map(|e| cost(e.get_data())).sum()
+  }
+
+  /// Returns the ID of the `i`th vertex of the path, where the vertex at
+  /// position 0 is the first vertex.
+  fn vertex_at(&self, i: usize) -> VertexId {
+    match i {
+      0 => self.start,
+      _ => self.graph.get_arc(self.edges[i - 1]).target,
+    }
+  }
+
+  /// Returns the `i`th vertex of the path. Vertex 0 is the first vertex, and
+  /// vertex `len()` is the last one.
+  ///
+  /// Panics if `i` exceeds `len()`.
+  pub fn get_node(&self, i: usize) -> Node<'a, T, S, A> {
+    Node::new(self.graph, self.vertex_at(i))
+  }
+
+  /// Appends `other` to this path.
+  ///
+  /// Returns an error if `other` is in a different graph or does not start
+  /// where this path ends.
+  pub fn concat(mut self, other: Path<'a, T, S, A>) -> Result<Self, PathError> {
+    if self.graph.address() != other.graph.address() {
+      return Err(PathError::ForeignGraph);
+    }
+    if other.start != self.vertex_at(self.len()) {
+      return Err(PathError::Discontiguous { index: self.len() });
+    }
+    self.edges.extend(other.edges);
+    Ok(self)
+  }
+
+  /// Divides this path into the path up to vertex `mid` and the path from
+  /// vertex `mid` on, so that the first has `mid` edges.
+  ///
+  /// Panics if `mid` exceeds `len()`.
+  pub fn split_at(&self, mid: usize) -> (Self, Self) {
+    let (head, tail) = self.edges.split_at(mid);
+    (
+      Path {
+        graph: self.graph,
+        start: self.start,
+        edges: head.to_vec(),
+      },
+      Path {
+        graph: self.graph,
+        start: self.vertex_at(mid),
+        edges: tail.to_vec(),
+      },
+    )
+  }
+
+  /// Replaces the edges in `range` with the edges of `replacement`, in the
+  /// manner of `Vec::splice`.
+  ///
+  /// `replacement` must start at vertex `range.start` of this path, unless
+  /// `range` starts at 0, and must end at vertex `range.end`, unless `range`
+  /// extends to the end of this path. Returns an error if this is not the case
+  /// or if `replacement` is in a different graph, in which case this path is
+  /// left unchanged.
+  ///
+  /// Panics if `range` is decreasing or extends past the end of this path.
+  pub fn splice(
+    &mut self,
+    range: Range<usize>,
+    replacement: Path<'a, T, S, A>,
+  ) -> Result<(), PathError> {
+    assert!(range.start <= range.end, "decreasing path range");
+    assert!(range.end <= self.len(), "path range out of bounds");
+    if self.graph.address() != replacement.graph.address() {
+      return Err(PathError::ForeignGraph);
+    }
+    if range.start > 0 && replacement.start != self.vertex_at(range.start) {
+      return Err(PathError::Discontiguous { index: range.start });
+    }
+    if range.end < self.len()
+      && replacement.vertex_at(replacement.len()) != self.vertex_at(range.end)
+    {
+      return Err(PathError::Discontiguous {
+        index: range.start + replacement.len(),
+      });
+    }
+    if range.start == 0 {
+      self.start = replacement.start;
+    }
+    self.edges.splice(range, replacement.edges);
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  type Graph = crate::Graph<&'static str, &'static str, &'static str>;
+
+  fn make_graph() -> Graph {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| "root_data", "0", |_| "0_data", "root_0_data");
+    g.add_edge("root", |_| "root_data", "1", |_| "1_data", "root_1_data");
+    g.add_edge("root", |_| "root_data", "2", |_| "2_data", "root_2_data");
+    g.add_edge("0", |_| "0_data", "2", |_| "2_data", "0_2_data");
+    g
+  }
+
+  #[test]
+  fn child_list_into_iter_ok() {
+    let g = make_graph();
+    let root = g.find_node(&"root").unwrap();
+    let mut data = Vec::new();
+    for e in root.get_child_list() {
+      data.push(*e.get_data());
+    }
+    assert_eq!(vec!["root_0_data", "root_1_data", "root_2_data"], data);
+  }
+
+  #[test]
+  fn child_list_iter_double_ended_ok() {
+    let g = make_graph();
+    let children = g.find_node(&"root").unwrap().get_child_list();
+    let mut iter = children.iter();
+    assert_eq!(3, iter.len());
+    assert_eq!("root_2_data", *iter.next_back().unwrap().get_data());
+    assert_eq!(2, iter.len());
+    assert_eq!("root_0_data", *iter.next().unwrap().get_data());
+    assert_eq!("root_1_data", *iter.next_back().unwrap().get_data());
+    assert_eq!(0, iter.len());
+    assert!(iter.next().is_none());
+    assert!(iter.next_back().is_none());
+
+    let reversed: Vec<&str> = children.iter().rev().map(|e| *e.get_data()).collect();
+    assert_eq!(vec!["root_2_data", "root_1_data", "root_0_data"], reversed);
+  }
+
+  #[test]
+  fn parent_list_into_iter_ok() {
+    let g = make_graph();
+    let parents = g.find_node(&"2").unwrap().get_parent_list();
+    assert_eq!(2, parents.iter().len());
+    let sources: Vec<&str> = parents
+      .into_iter()
+      .map(|e| *e.get_source().get_label())
+      .collect();
+    assert_eq!(vec!["root", "0"], sources);
+    let sources: Vec<&str> = parents
+      .iter()
+      .rev()
+      .map(|e| *e.get_source().get_label())
+      .collect();
+    assert_eq!(vec!["0", "root"], sources);
+  }
+
+  #[test]
+  fn edge_ids_ok() {
+    let g = make_graph();
+    let root = g.find_node(&"root").unwrap();
+    let ids: Vec<usize> = root.get_child_list().iter().map(|e| e.get_id()).collect();
+    assert_eq!(&ids[..], root.child_edge_ids());
+    let target = g.find_node(&"2").unwrap();
+    let ids: Vec<usize> = target
+      .get_parent_list()
+      .iter()
+      .map(|e| e.get_id())
+      .collect();
+    assert_eq!(&ids[..], target.parent_edge_ids());
+    let endpoints: Vec<(usize, usize)> = root
+      .child_edge_ids()
+      .iter()
+      .map(|id| g.edge_endpoints(*id))
+      .collect();
+    let expected: Vec<(usize, usize)> = ["0", "1", "2"]
+      .iter()
+      .map(|s| (root.get_id(), g.find_node(s).unwrap().get_id()))
+      .collect();
+    assert_eq!(expected, endpoints);
+    assert!(g.find_node(&"1").unwrap().child_edge_ids().is_empty());
+  }
+
+  #[test]
+  fn find_by_ok() {
+    let g = make_graph();
+    let children = g.find_node(&"root").unwrap().get_child_list();
+    let e = children.find_by(|data| data.ends_with("1_data")).unwrap();
+    assert_eq!("1", *e.get_target().get_label());
+    assert!(children.find_by(|data| data.is_empty()).is_none());
+  }
+
+  #[test]
+  fn best_by_key_ok() {
+    fn key(data: &&'static str) -> u64 {
+      data.len() as u64
+    }
+
+    let mut g = make_graph();
+    assert!(g
+      .find_node(&"root")
+      .unwrap()
+      .get_child_list()
+      .best_by_key()
+      .is_none());
+    g.order_children_by(key);
+    g.add_edge("root", |_| "root_data", "3", |_| "3_data", "root_3_longest");
+    let children = g.find_node(&"root").unwrap().get_child_list();
+    assert_eq!(
+      "root_3_longest",
+      *children.best_by_key().unwrap().get_data()
+    );
+    assert!(g
+      .find_node(&"2")
+      .unwrap()
+      .get_child_list()
+      .best_by_key()
+      .is_none());
+    {
+      let mut root = g.find_node_mut(&"root").unwrap();
+      let mut children = root.get_child_list_mut();
+      let mut best = children.best_by_key_mut().unwrap();
+      *best.get_data_mut() = "r";
+      // The key is stale until it is touched.
+      assert_eq!("r", *children.best_by_key().unwrap().get_data());
+      let mut best = children.best_by_key_mut().unwrap();
+      best.touch_key();
+      // Ties go to the edge with the lowest ID.
+      assert_eq!("root_0_data", *children.best_by_key().unwrap().get_data());
+    }
+    g.compact_with(&["0"], &mut []);
+    let children = g.find_node(&"0").unwrap().get_child_list();
+    assert_eq!("0_2_data", *children.best_by_key().unwrap().get_data());
+  }
+
+  #[test]
+  fn best_by_key_order_ok() {
+    let mut g = crate::Graph::<u32, (), u64>::new();
+    g.order_children_by(|data| *data);
+    let mut x: u64 = 1;
+    let mut next = || {
+      x = x
+        .wrapping_mul(6364136223846793005)
+        .wrapping_add(1442695040888963407);
+      x >> 33
+    };
+    for i in 0..100 {
+      g.add_edge(0, |_| (), i + 1, |_| (), next() % 50);
+    }
+    for _ in 0..1000 {
+      let mut root = g.find_node_mut(&0).unwrap();
+      let mut children = root.get_child_list_mut();
+      let i = (next() % 100) as usize;
+      let mut edge = children.get_edge_mut(i);
+      *edge.get_data_mut() = next() % 50;
+      edge.touch_key();
+      let expected = children
+        .iter()
+        .map(|e| (*e.get_data(), std::cmp::Reverse(e.get_id())))
+        .max()
+        .unwrap();
+      let best = children.best_by_key().unwrap();
+      assert_eq!(
+        expected,
+        (*best.get_data(), std::cmp::Reverse(best.get_id()))
+      );
+    }
+  }
+
+  #[test]
+  fn find_by_key_ok() {
+    fn key(data: &&'static str) -> u64 {
+      data.len() as u64
+    }
+
+    let mut g = make_graph();
+    assert!(g
+      .find_node(&"root")
+      .unwrap()
+      .get_child_list()
+      .find_by_key(11)
+      .is_none());
+    g.index_children_by(key);
+    g.add_edge("root", |_| "root_data", "3", |_| "3_data", "root_3");
+    let children = g.find_node(&"root").unwrap().get_child_list();
+    // All edges added before the index was built have the same key.
+    assert_eq!("root_0_data", *children.find_by_key(11).unwrap().get_data());
+    assert_eq!("root_3", *children.find_by_key(6).unwrap().get_data());
+    assert!(children.find_by_key(7).is_none());
+    let children = g.find_node(&"0").unwrap().get_child_list();
+    assert_eq!("0_2_data", *children.find_by_key(8).unwrap().get_data());
+    assert!(children.find_by_key(6).is_none());
+
+    // Changing a key leaves the index entry for the old key.
+    *g.find_node_mut(&"0")
+      .unwrap()
+      .to_child_list()
+      .get_edge_mut(0)
+      .get_data_mut() = "0_2";
+    let children = g.find_node(&"0").unwrap().get_child_list();
+    assert!(children.find_by_key(8).is_none());
+    assert!(children.find_by_key(3).is_none());
+  }
+
+  #[test]
+  fn path_from_edge_ids_ok() {
+    let g = make_graph();
+    let path = super::Path::from_edge_ids(&g, &[0, 3]).unwrap();
+    assert_eq!(2, path.len());
+    assert!(!path.is_empty());
+    assert_eq!("root", *path.first_node().get_label());
+    assert_eq!("2", *path.last_node().get_label());
+    assert_eq!("0_2_data", *path.get_edge(1).get_data());
+    assert_eq!(
+      vec!["root_0_data", "0_2_data"],
+      path.iter().map(|e| *e.get_data()).collect::<Vec<_>>()
+    );
+    assert_eq!(19, path.total_cost(|a| a.len()));
+    let empty = super::Path::new(g.find_node(&"1").unwrap());
+    assert!(empty.is_empty());
+    assert_eq!("1", *empty.last_node().get_label());
+    assert_eq!(0, empty.total_cost(|a| a.len()));
+    let at = super::Path::at(&g, &"1").unwrap();
+    assert!(at.is_empty());
+    assert_eq!("1", *at.first_node().get_label());
+    assert!(super::Path::at(&g, &"missing").is_none());
+  }
+
+  #[test]
+  fn path_from_edge_ids_err() {
+    use super::{Path, PathError};
+    let g = make_graph();
+    assert_eq!(Some(PathError::Empty), Path::from_edge_ids(&g, &[]).err());
+    assert_eq!(
+      Some(PathError::EdgeBounds {
+        edge_id: 4,
+        edge_count: 4
+      }),
+      Path::from_edge_ids(&g, &[0, 4]).err()
+    );
+    assert_eq!(
+      Some(PathError::Discontiguous { index: 1 }),
+      Path::from_edge_ids(&g, &[0, 1]).err()
+    );
+  }
+
+  #[test]
+  fn path_concat_split_ok() {
+    use super::Path;
+    let g = make_graph();
+    let a = Path::from_edge_ids(&g, &[0]).unwrap();
+    let b = Path::from_edge_ids(&g, &[3]).unwrap();
+    let path = a.concat(b).unwrap();
+    assert_eq!(2, path.len());
+    assert_eq!("0", *path.get_node(1).get_label());
+    let (head, tail) = path.split_at(1);
+    assert_eq!("root", *head.first_node().get_label());
+    assert_eq!("0", *head.last_node().get_label());
+    assert_eq!("0", *tail.first_node().get_label());
+    assert_eq!("2", *tail.last_node().get_label());
+    let (empty, all) = path.split_at(0);
+    assert!(empty.is_empty());
+    assert_eq!("root", *empty.last_node().get_label());
+    assert_eq!(2, all.len());
+    let joined = head.concat(Path::new(tail.first_node())).unwrap();
+    assert_eq!(1, joined.len());
+  }
+
+  #[test]
+  fn path_concat_err() {
+    use super::{Path, PathError};
+    let g = make_graph();
+    let other = make_graph();
+    let a = Path::from_edge_ids(&g, &[0]).unwrap();
+    assert_eq!(
+      Some(PathError::Discontiguous { index: 1 }),
+      a.clone()
+        .concat(Path::from_edge_ids(&g, &[1]).unwrap())
+        .err()
+    );
+    assert_eq!(
+      Some(PathError::ForeignGraph),
+      a.concat(Path::from_edge_ids(&other, &[3]).unwrap()).err()
+    );
+  }
+
+  #[test]
+  fn path_splice_ok() {
+    use super::Path;
+    let g = make_graph();
+    // root -> 0 -> 2, spliced to root -> 2.
+    let mut path = Path::from_edge_ids(&g, &[0, 3]).unwrap();
+    path
+      .splice(0..2, Path::from_edge_ids(&g, &[2]).unwrap())
+      .unwrap();
+    assert_eq!(vec![2], path.iter().map(|e| e.get_id()).collect::<Vec<_>>());
+    // root -> 2, spliced back to root -> 0 -> 2.
+    path
+      .splice(0..1, Path::from_edge_ids(&g, &[0, 3]).unwrap())
+      .unwrap();
+    assert_eq!(
+      vec![0, 3],
+      path.iter().map(|e| e.get_id()).collect::<Vec<_>>()
+    );
+    // Inserting an empty path at a vertex leaves the path unchanged.
+    path.splice(1..1, Path::new(path.get_node(1))).unwrap();
+    assert_eq!(2, path.len());
+    // Replacing the tail of the path may change where it ends.
+    path
+      .splice(0..2, Path::from_edge_ids(&g, &[1]).unwrap())
+      .unwrap();
+    assert_eq!("1", *path.last_node().get_label());
+  }
+
+  #[test]
+  fn path_splice_err() {
+    use super::{Path, PathError};
+    let g = make_graph();
+    let mut path = Path::from_edge_ids(&g, &[0, 3]).unwrap();
+    assert_eq!(
+      Err(PathError::Discontiguous { index: 1 }),
+      path.splice(1..2, Path::from_edge_ids(&g, &[1]).unwrap())
+    );
+    assert_eq!(
+      Err(PathError::Discontiguous { index: 1 }),
+      path.splice(0..1, Path::from_edge_ids(&g, &[1]).unwrap())
+    );
+    assert_eq!(
+      vec![0, 3],
+      path.iter().map(|e| e.get_id()).collect::<Vec<_>>()
+    );
+  }
+
+  #[test]
+  fn child_list_iter_with_index_ok() {
+    let g = make_graph();
+    let children = g.find_node(&"root").unwrap().get_child_list();
+    let indexed: Vec<(usize, &str)> = children
+      .iter_with_index()
+      .map(|(i, e)| (i, *e.get_data()))
+      .collect();
+    assert_eq!(
+      vec![(0, "root_0_data"), (1, "root_1_data"), (2, "root_2_data")],
+      indexed
+    );
+    let (i, last) = children.iter_with_index().next_back().unwrap();
+    assert_eq!(2, i);
+    assert_eq!(Some(2), children.position_of(&last));
+    let other = g.find_node(&"0").unwrap().get_child_list().get_edge(0);
+    assert_eq!(None, children.position_of(&other));
+  }
+
+  #[test]
+  fn parent_list_position_of_ok() {
+    let g = make_graph();
+    let parents = g.find_node(&"2").unwrap().get_parent_list();
+    for (i, e) in parents.iter_with_index() {
+      assert_eq!(Some(i), parents.position_of(&e));
+    }
+    assert_eq!(
+      Some(1),
+      parents.position_of(&g.find_node(&"0").unwrap().get_child_list().get_edge(0))
+    );
+  }
+
+  #[test]
+  fn belongs_to_ok() {
+    let g = make_graph();
+    let h = make_graph();
+    let root = g.find_node(&"root").unwrap();
+    assert!(root.belongs_to(&g));
+    assert!(!root.belongs_to(&h));
+    let edge = root.get_child_list().get_edge(0);
+    assert!(edge.belongs_to(&g));
+    assert!(!edge.belongs_to(&h));
+    assert!(edge.get_target().belongs_to(&g));
+  }
+
+  #[test]
+  #[cfg(debug_assertions)]
+  #[should_panic(expected = "node from another graph")]
+  fn has_parent_foreign_node_err() {
+    let g = make_graph();
+    let h = make_graph();
+    g.find_node(&"root")
+      .unwrap()
+      .has_parent(&h.find_node(&"root").unwrap());
+  }
+
+  #[test]
+  #[cfg(debug_assertions)]
+  #[should_panic(expected = "node from another graph")]
+  fn has_child_foreign_node_err() {
+    let g = make_graph();
+    let h = make_graph();
+    g.find_node(&"root")
+      .unwrap()
+      .has_child(&h.find_node(&"root").unwrap());
+  }
+
+  #[test]
+  #[cfg(debug_assertions)]
+  #[should_panic(expected = "node from another graph")]
+  fn is_ancestor_of_foreign_node_err() {
+    let g = make_graph();
+    let h = make_graph();
+    g.find_node(&"root")
+      .unwrap()
+      .is_ancestor_of(&h.find_node(&"root").unwrap());
+  }
+
+  #[test]
+  #[cfg(debug_assertions)]
+  #[should_panic(expected = "edge from another graph")]
+  fn position_of_foreign_edge_err() {
+    let g = make_graph();
+    let h = make_graph();
+    let edge = h.find_node(&"root").unwrap().get_child_list().get_edge(0);
+    g.find_node(&"root")
+      .unwrap()
+      .get_child_list()
+      .position_of(&edge);
+  }
+
+  #[test]
+  fn footprint_ok() {
+    let mut g = make_graph();
+    let sizer = |state: &&str, data: &&str| state.len() + data.len();
+    let edge_sizer = |data: &&str| data.len();
+    let footprint = |g: &Graph, state| g.find_node(&state).unwrap().footprint(sizer, edge_sizer);
+    assert_eq!(7, footprint(&g, "1"));
+    assert_eq!(7 + 7 + 8, footprint(&g, "0"));
+    assert_eq!(13 + 3 * 7 + 3 * 11 + 8, footprint(&g, "root"));
+    // Cycles are counted once.
+    g.add_edge("2", |_| "2_data", "root", |_| "root_data", "c");
+    assert_eq!(13 + 3 * 7 + 3 * 11 + 8 + 1, footprint(&g, "0"));
+  }
+
+  #[test]
+  fn is_ancestor_of_ok() {
+    let mut g = make_graph();
+    g.add_node("other", "other_data");
+    {
+      let node = |state| g.find_node(&state).unwrap();
+      assert!(node("root").is_ancestor_of(&node("2")));
+      assert!(node("0").is_ancestor_of(&node("2")));
+      assert!(node("2").is_descendant_of(&node("0")));
+      assert!(!node("1").is_ancestor_of(&node("2")));
+      assert!(!node("2").is_ancestor_of(&node("root")));
+      assert!(!node("root").is_ancestor_of(&node("root")));
+      assert!(!node("root").is_ancestor_of(&node("other")));
+    }
+    g.add_edge("2", |_| "2_data", "root", |_| "root_data", "c");
+    let node = |state| g.find_node(&state).unwrap();
+    assert!(node("2").is_ancestor_of(&node("1")));
+    assert!(node("root").is_ancestor_of(&node("root")));
+    assert!(!node("1").is_ancestor_of(&node("1")));
+  }
 }
@@ -0,0 +1,401 @@
+//! Traits for read-only navigation that abstract over graph storage.
+//!
+//! The handles in [nav](../nav/index.html) navigate a
+//! [Graph](../struct.Graph.html), and those in
+//! [snapshot](../snapshot/index.html) navigate a frozen copy of one. The
+//! traits in this module describe what they have in common, so that an
+//! algorithm written against them can run over either, or over any other
+//! storage that implements them:
+//!
+//! * [INavTypes](trait.INavTypes.html) is implemented by (references to) the
+//!   graph-like structure itself, and looks up vertices.
+//! * [IVertex](trait.IVertex.html) is implemented by vertex handles.
+//! * [IEdge](trait.IEdge.html) is implemented by edge handles.
+//! * [IChildren](trait.IChildren.html) is implemented by lists of child edges.
+//!
+//! Incoming edges are not part of these traits, since not all graphs track
+//! them (see [GraphConfig](../config/struct.GraphConfig.html)).
+//!
+//! Handles are expected to be cheap to copy, as those in `nav` are.
+//!
+//! ```rust
+//! # use search_graph::Graph;
+//! # use search_graph::nav_types::{IChildren, INavTypes, IVertex};
+//! /// Counts the leaves that are reachable from `root` along at most `depth`
+//! /// edges, once for each path to them.
+//! fn leaves<V: IVertex>(root: V, depth: usize) -> usize {
+//!   if depth == 0 || root.is_leaf() {
+//!     return root.is_leaf() as usize;
+//!   }
+//!   root
+//!     .get_child_list()
+//!     .iter()
+//!     .map(|e| leaves(e.get_target(), depth - 1))
+//!     .sum()
+//! }
+//! # use search_graph::nav_types::IEdge;
+//!
+//! # fn main() {
+//! let mut g: Graph<u32, (), ()> = Graph::new();
+//! g.add_edge(0, |_| (), 1, |_| (), ());
+//! g.add_edge(0, |_| (), 2, |_| (), ());
+//! g.add_edge(1, |_| (), 2, |_| (), ());
+//! let snapshot = g.snapshot();
+//! assert_eq!(leaves((&g).find_vertex(&0).unwrap(), 3), 2);
+//! assert_eq!(leaves((&snapshot).find_vertex(&0).unwrap(), 3), 2);
+//! # }
+//! ```
+
+use std::hash::Hash;
+use std::iter::FusedIterator;
+
+use crate::base::VertexId;
+use crate::nav::{ChildList, Edge, Node};
+use crate::snapshot::{Snapshot, SnapshotChildList, SnapshotEdge, SnapshotNode};
+use crate::Graph;
+
+/// A graph-like structure whose vertices may be looked up.
+pub trait INavTypes {
+  /// The type of vertex handles.
+  type Vertex: IVertex;
+
+  /// Returns the number of vertices.
+  fn vertex_count(&self) -> usize;
+
+  /// Returns a handle for the vertex with the given label, if there is one.
+  fn find_vertex(&self, label: &<Self::Vertex as IVertex>::Label) -> Option<Self::Vertex>;
+
+  /// Returns a handle for the vertex with the given ID, if there is one. IDs
+  /// are those returned by
+  /// [IVertex::get_id](trait.IVertex.html#tymethod.get_id), and range from 0
+  /// up to the number of vertices.
+  fn get_vertex(&self, id: usize) -> Option<Self::Vertex>;
+}
+
+/// A handle to a vertex.
+pub trait IVertex: Copy {
+  /// The type of vertex labels (game states).
+  type Label;
+  /// The type of vertex data.
+  type Data;
+  /// The type of edge handles.
+  type Edge: IEdge<Vertex = Self>;
+  /// The type of lists of child edges.
+  type Children: IChildren<Edge = Self::Edge>;
+
+  /// Returns an ID that identifies this vertex uniquely within its graph.
+  fn get_id(&self) -> usize;
+
+  /// Returns the canonical label of this vertex.
+  fn get_label(&self) -> &Self::Label;
+
+  /// Returns the data at this vertex.
+  fn get_data(&self) -> &Self::Data;
+
+  /// Returns the list of outgoing edges.
+  fn get_child_list(&self) -> Self::Children;
+
+  /// Returns true iff this vertex has no outgoing edges.
+  fn is_leaf(&self) -> bool {
+    self.get_child_list().is_empty()
+  }
+}
+
+/// A handle to an edge.
+pub trait IEdge: Copy {
+  /// The type of edge data.
+  type Data;
+  /// The type of vertex handles.
+  type Vertex: IVertex<Edge = Self>;
+
+  /// Returns an ID that identifies this edge uniquely within its graph.
+  fn get_id(&self) -> usize;
+
+  /// Returns the data at this edge.
+  fn get_data(&self) -> &Self::Data;
+
+  /// Returns a handle for this edge's source vertex.
+  fn get_source(&self) -> Self::Vertex;
+
+  /// Returns a handle for this edge's target vertex.
+  fn get_target(&self) -> Self::Vertex;
+}
+
+/// The outgoing edges of a vertex, in order.
+pub trait IChildren: Copy {
+  /// The type of edge handles.
+  type Edge: IEdge;
+
+  /// Returns the number of edges.
+  fn len(&self) -> usize;
+
+  /// Returns a handle for the `i`th edge. Panics if there are `i` or fewer
+  /// edges.
+  fn get_edge(&self, i: usize) -> Self::Edge;
+
+  /// Returns true iff there are no edges.
+  fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /// Returns an iterator over the edges.
+  fn iter(&self) -> ChildrenIter<Self> {
+    ChildrenIter {
+      children: *self,
+      next: 0,
+      end: self.len(),
+    }
+  }
+}
+
+/// Iterator over the edges of an [IChildren](trait.IChildren.html).
+pub struct ChildrenIter<C> {
+  children: C,
+  next: usize,
+  end: usize,
+}
+
+impl<C: IChildren> Iterator for ChildrenIter<C> {
+  type Item = C::Edge;
+
+  fn next(&mut self) -> Option<C::Edge> {
+    if self.next < self.end {
+      self.next += 1;
+      Some(self.children.get_edge(self.next - 1))
+    } else {
+      None
+    }
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (self.end - self.next, Some(self.end - self.next))
+  }
+}
+
+impl<C: IChildren> DoubleEndedIterator for ChildrenIter<C> {
+  fn next_back(&mut self) -> Option<C::Edge> {
+    if self.next < self.end {
+      self.end -= 1;
+      Some(self.children.get_edge(self.end))
+    } else {
+      None
+    }
+  }
+}
+
+impl<C: IChildren> ExactSizeIterator for ChildrenIter<C> {}
+
+impl<C: IChildren> FusedIterator for ChildrenIter<C> {}
+
+impl<'a, T: Hash + Eq + Clone, S, A> INavTypes for &'a Graph<T, S, A> {
+  type Vertex = Node<'a, T, S, A>;
+
+  fn vertex_count(&self) -> usize {
+    Graph::vertex_count(self)
+  }
+
+  fn find_vertex(&self, label: &T) -> Option<Node<'a, T, S, A>> {
+    self.find_node(label)
+  }
+
+  fn get_vertex(&self, id: usize) -> Option<Node<'a, T, S, A>> {
+    if id < Graph::vertex_count(self) {
+      Some(Node::new(self, VertexId(id)))
+    } else {
+      None
+    }
+  }
+}
+
+impl<'a, T: Hash + Eq + Clone, S, A> IVertex for Node<'a, T, S, A> {
+  type Label = T;
+  type Data = S;
+  type Edge = Edge<'a, T, S, A>;
+  type Children = ChildList<'a, T, S, A>;
+
+  fn get_id(&self) -> usize {
+    Node::get_id(self)
+  }
+
+  fn get_label(&self) -> &T {
+    Node::get_label(self)
+  }
+
+  fn get_data(&self) -> &S {
+    Node::get_data(self)
+  }
+
+  fn get_child_list(&self) -> ChildList<'a, T, S, A> {
+    Node::get_child_list(self)
+  }
+}
+
+impl<'a, T: Hash + Eq + Clone, S, A> IEdge for Edge<'a, T, S, A> {
+  type Data = A;
+  type Vertex = Node<'a, T, S, A>;
+
+  fn get_id(&self) -> usize {
+    Edge::get_id(self)
+  }
+
+  fn get_data(&self) -> &A {
+    Edge::get_data(self)
+  }
+
+  fn get_source(&self) -> Node<'a, T, S, A> {
+    Edge::get_source(self)
+  }
+
+  fn get_target(&self) -> Node<'a, T, S, A> {
+    Edge::get_target(self)
+  }
+}
+
+impl<'a, T: Hash + Eq + Clone, S, A> IChildren for ChildList<'a, T, S, A> {
+  type Edge = Edge<'a, T, S, A>;
+
+  fn len(&self) -> usize {
+    ChildList::len(self)
+  }
+
+  fn get_edge(&self, i: usize) -> Edge<'a, T, S, A> {
+    ChildList::get_edge(self, i)
+  }
+}
+
+impl<'s, T: Hash + Eq, S, A> INavTypes for &'s Snapshot<T, S, A> {
+  type Vertex = SnapshotNode<'s, T, S, A>;
+
+  fn vertex_count(&self) -> usize {
+    Snapshot::vertex_count(self)
+  }
+
+  fn find_vertex(&self, label: &T) -> Option<SnapshotNode<'s, T, S, A>> {
+    self.find_node(label)
+  }
+
+  fn get_vertex(&self, id: usize) -> Option<SnapshotNode<'s, T, S, A>> {
+    self.node(id)
+  }
+}
+
+impl<'s, T: Hash + Eq, S, A> IVertex for SnapshotNode<'s, T, S, A> {
+  type Label = T;
+  type Data = S;
+  type Edge = SnapshotEdge<'s, T, S, A>;
+  type Children = SnapshotChildList<'s, T, S, A>;
+
+  fn get_id(&self) -> usize {
+    SnapshotNode::get_id(self)
+  }
+
+  fn get_label(&self) -> &T {
+    SnapshotNode::get_label(self)
+  }
+
+  fn get_data(&self) -> &S {
+    SnapshotNode::get_data(self)
+  }
+
+  fn get_child_list(&self) -> SnapshotChildList<'s, T, S, A> {
+    SnapshotNode::get_child_list(self)
+  }
+}
+
+impl<'s, T: Hash + Eq, S, A> IEdge for SnapshotEdge<'s, T, S, A> {
+  type Data = A;
+  type Vertex = SnapshotNode<'s, T, S, A>;
+
+  fn get_id(&self) -> usize {
+    SnapshotEdge::get_id(self)
+  }
+
+  fn get_data(&self) -> &A {
+    SnapshotEdge::get_data(self)
+  }
+
+  fn get_source(&self) -> SnapshotNode<'s, T, S, A> {
+    SnapshotEdge::get_source(self)
+  }
+
+  fn get_target(&self) -> SnapshotNode<'s, T, S, A> {
+    SnapshotEdge::get_target(self)
+  }
+}
+
+impl<'s, T: Hash + Eq, S, A> IChildren for SnapshotChildList<'s, T, S, A> {
+  type Edge = SnapshotEdge<'s, T, S, A>;
+
+  fn len(&self) -> usize {
+    SnapshotChildList::len(self)
+  }
+
+  fn get_edge(&self, i: usize) -> SnapshotEdge<'s, T, S, A> {
+    SnapshotChildList::get_edge(self, i)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::{IChildren, IEdge, INavTypes, IVertex};
+
+  type Graph = crate::Graph<&'static str, &'static str, &'static str>;
+
+  /// Returns the labels of the vertices reachable from `root`, in
+  /// depth-first order, and the data of the edges traversed to reach them.
+  fn walk<N: INavTypes>(g: N, root: &<N::Vertex as IVertex>::Label) -> Vec<String>
+  where
+    <N::Vertex as IVertex>::Label: std::fmt::Debug,
+    <<N::Vertex as IVertex>::Edge as IEdge>::Data: std::fmt::Debug,
+  {
+    let mut seen = vec![false; g.vertex_count()];
+    let mut stack = vec![g.find_vertex(root).unwrap()];
+    let mut visited = Vec::new();
+    while let Some(v) = stack.pop() {
+      if seen[v.get_id()] {
+        continue;
+      }
+      seen[v.get_id()] = true;
+      visited.push(format!("{:?}", v.get_label()));
+      for e in v.get_child_list().iter().rev() {
+        assert_eq!(v.get_id(), e.get_source().get_id());
+        visited.push(format!("{:?}", e.get_data()));
+        stack.push(e.get_target());
+      }
+    }
+    visited
+  }
+
+  #[test]
+  fn graph_and_snapshot_ok() {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| "", "a", |_| "", "root_a");
+    g.add_edge("root", |_| "", "b", |_| "", "root_b");
+    g.add_edge("a", |_| "", "b", |_| "", "a_b");
+    let snapshot = g.snapshot();
+    let expected = walk(&g, &"root");
+    assert_eq!(
+      vec![
+        "\"root\"",
+        "\"root_b\"",
+        "\"root_a\"",
+        "\"a\"",
+        "\"a_b\"",
+        "\"b\""
+      ],
+      expected
+    );
+    assert_eq!(expected, walk(&snapshot, &"root"));
+
+    let root = (&g).find_vertex(&"root").unwrap();
+    let children = IVertex::get_child_list(&root);
+    assert_eq!(2, IChildren::len(&children));
+    assert_eq!(2, IChildren::iter(&children).rev().len());
+    assert!(INavTypes::get_vertex(&&g, 3).is_none());
+    assert_eq!(
+      "b",
+      *IVertex::get_label(&(&snapshot).get_vertex(2).unwrap())
+    );
+    assert!((&snapshot).find_vertex(&"missing").is_none());
+  }
+}
@@ -0,0 +1,352 @@
+//! Support for splitting a graph into two graphs.
+//!
+//! [Graph::partition](../struct.Graph.html#method.partition) divides the
+//! vertices of a graph according to a predicate over game states and vertex
+//! data. This is useful for separating, e.g., solved positions that should be
+//! archived from the active working set of a search.
+//!
+//! [Graph::extract_reachable_from](../struct.Graph.html#method.extract_reachable_from)
+//! instead copies the part of a graph that is reachable from a game state into
+//! a new graph, leaving the original intact.
+
+use std::hash::Hash;
+use std::sync::atomic::Ordering;
+
+use crate::base::{RawEdge, VertexId};
+use crate::nav::Expansion;
+use crate::Graph;
+use symbol_map::indexing::Indexing;
+use symbol_map::SymbolId;
+
+/// An edge whose source and target were assigned to different graphs by
+/// [Graph::partition_with_cut](../struct.Graph.html#method.partition_with_cut).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CutEdge<T, A> {
+  /// The game state of the edge's source vertex.
+  pub source: T,
+  /// The game state of the edge's target vertex.
+  pub target: T,
+  /// The edge data.
+  pub data: A,
+}
+
+impl<T: Hash + Eq + Clone, S, A> Graph<T, S, A> {
+  /// Splits this graph into two graphs. The first contains the vertices whose
+  /// game state and data satisfy `pred`, and the second contains the rest.
+  /// Edges between vertices that are assigned to the same graph are retained,
+  /// and edges that cross between the two graphs are dropped.
+  ///
+  /// ```rust
+  /// # use search_graph::Graph;
+  /// # fn main() {
+  /// let mut g: Graph<u32, bool, ()> = Graph::new();
+  /// g.add_edge(0, |_| false, 1, |_| true, ());
+  /// g.add_edge(1, |_| true, 2, |_| true, ());
+  /// let (solved, active) = g.partition(|_, solved| *solved);
+  /// assert_eq!(solved.vertex_count(), 2);
+  /// assert_eq!(solved.edge_count(), 1);
+  /// assert_eq!(active.vertex_count(), 1);
+  /// assert_eq!(active.edge_count(), 0);
+  /// # }
+  /// ```
+  pub fn partition<P>(self, pred: P) -> (Self, Self)
+  where
+    P: Fn(&T, &S) -> bool,
+  {
+    let (matching, rest, _) = self.partition_with_cut(pred);
+    (matching, rest)
+  }
+
+  /// As [partition](#method.partition), but also returns the edges that cross
+  /// between the two graphs, in the order in which they were added.
+  pub fn partition_with_cut<P>(mut self, pred: P) -> (Self, Self, Vec<CutEdge<T, A>>)
+  where
+    P: Fn(&T, &S) -> bool,
+  {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::debug_span!("partition").entered();
+    let mut labels: Vec<Option<T>> = vec![None; self.vertices.len()];
+    for symbol in self.state_ids.table() {
+      labels[symbol.id().as_usize()] = Some(symbol.data().clone());
+    }
+    let labels: Vec<T> = labels.into_iter().map(Option::unwrap).collect();
+
+    let mut graphs = [self.new_like(), self.new_like()];
+    // For each vertex, the graph it is assigned to and its ID in that graph.
+    let mut assignments: Vec<(usize, VertexId)> = Vec::with_capacity(labels.len());
+    for (i, (label, vertex)) in labels.iter().zip(self.vertices.drain(..)).enumerate() {
+      let side = if pred(label, &vertex.data) { 0 } else { 1 };
+      let graph = &mut graphs[side];
+      let id = *graph.state_ids.get_or_insert(label.clone()).unwrap().id();
+      graph.add_raw_vertex(vertex.data).expansion = vertex.expansion;
+      if let (Some(from), Some(to)) = (&self.recency, &mut graph.recency) {
+        to.vertices[id.as_usize()] = from.vertices[i];
+      }
+      if let (Some(from), Some(to)) = (&self.stable, &mut graph.stable) {
+        to.replace(id.as_usize(), from.ids[i]);
+      }
+      if let (Some(from), Some(to)) = (&self.audit, &mut graph.audit) {
+        to.vertices[id.as_usize()] = from.vertices[i];
+      }
+      for (to, from) in graph.hot.get(id.as_usize()).iter().zip(self.hot.get(i)) {
+        to.store(from.load(Ordering::Relaxed), Ordering::Relaxed);
+      }
+      self
+        .vertex_columns
+        .move_to(i, &mut graph.vertex_columns, id.as_usize());
+      assignments.push((side, id));
+    }
+
+    for (id, edges) in self.unexpanded.drain() {
+      let (side, new_id) = assignments[id.as_usize()];
+      graphs[side].unexpanded.insert(new_id, edges);
+    }
+
+    for (pin, id) in self.pins.drain() {
+      let (side, new_id) = assignments[id];
+      graphs[side].pins.insert(pin, new_id);
+    }
+
+    for (alias, id) in self.aliases.drain() {
+      let (side, new_id) = assignments[id.as_usize()];
+      graphs[side].aliases.insert(alias, new_id);
+    }
+
+    let mut cut = Vec::new();
+    for (
+      i,
+      RawEdge {
+        data,
+        source,
+        target,
+      },
+    ) in self.arcs.drain(..).enumerate()
+    {
+      let (source_side, new_source) = assignments[source.as_usize()];
+      let (target_side, new_target) = assignments[target.as_usize()];
+      if source_side == target_side {
+        let graph = &mut graphs[source_side];
+        let id = graph.add_raw_edge(data, new_source, new_target);
+        if let (Some(from), Some(to)) = (&self.audit, &mut graph.audit) {
+          to.edges[id.as_usize()] = from.edges[i];
+        }
+        self
+          .edge_columns
+          .move_to(i, &mut graph.edge_columns, id.as_usize());
+      } else {
+        graphs[source_side].get_vertex_mut(new_source).expansion = Expansion::PartiallyExpanded;
+        cut.push(CutEdge {
+          source: labels[source.as_usize()].clone(),
+          target: labels[target.as_usize()].clone(),
+          data,
+        });
+      }
+    }
+
+    for graph in graphs.iter_mut() {
+      if let Some(ref mut audit) = graph.audit {
+        audit.events.clear();
+      }
+    }
+    let [matching, rest] = graphs;
+    (matching, rest, cut)
+  }
+}
+
+impl<T: Hash + Eq + Clone, S: Clone, A: Clone> Graph<T, S, A> {
+  /// Returns a new graph that contains copies of the vertices that are
+  /// reachable from `state` and the edges between them, leaving this graph
+  /// unchanged. The copy is configured like this graph, and its vertex and
+  /// edge IDs are compacted in breadth-first order from `state`, as by garbage
+  /// collection. Vertices keep their expansion states, aliases, and stable
+  /// IDs, but the copy has no pins, and its [columns](column/index.html) hold
+  /// default values. If there is no vertex for `state`, the copy is empty.
+  ///
+  /// This is useful for handing a subtree to another thread for analysis
+  /// without pruning this graph in place.
+  ///
+  /// ```rust
+  /// # use search_graph::Graph;
+  /// # fn main() {
+  /// let mut g: Graph<&str, u32, ()> = Graph::new();
+  /// g.add_edge("root", |_| 0, "a", |_| 1, ());
+  /// g.add_edge("a", |_| 1, "b", |_| 2, ());
+  /// let sub = g.extract_reachable_from(&"a");
+  /// assert_eq!(sub.vertex_count(), 2);
+  /// assert_eq!(sub.find_node(&"a").unwrap().get_id(), 0);
+  /// assert!(sub.find_node(&"root").is_none());
+  /// assert_eq!(g.vertex_count(), 3);
+  /// # }
+  /// ```
+  pub fn extract_reachable_from(&self, state: &T) -> Self {
+    let mut graph = self.new_like();
+    graph.pins = Default::default();
+    let root = match self.lookup(state) {
+      Some(id) => id,
+      None => return graph,
+    };
+    let mut labels: Vec<Option<&T>> = vec![None; self.vertices.len()];
+    for symbol in self.state_ids.table() {
+      labels[symbol.id().as_usize()] = Some(symbol.data());
+    }
+
+    // Visit reachable vertices in breadth-first order, which is the order of
+    // their new IDs.
+    let mut new_ids: Vec<Option<VertexId>> = vec![None; self.vertices.len()];
+    new_ids[root.as_usize()] = Some(VertexId(0));
+    let mut order = vec![root];
+    let mut next = 0;
+    while next < order.len() {
+      let id = order[next];
+      next += 1;
+      for child in self.get_vertex(id).children.iter() {
+        let target = self.get_arc(*child).target;
+        if new_ids[target.as_usize()].is_none() {
+          new_ids[target.as_usize()] = Some(VertexId(order.len()));
+          order.push(target);
+        }
+      }
+    }
+
+    for (new_id, id) in order.iter().enumerate() {
+      let i = id.as_usize();
+      graph.state_ids.get_or_insert(labels[i].unwrap().clone());
+      let vertex = self.get_vertex(*id);
+      graph.add_raw_vertex(vertex.data.clone()).expansion = vertex.expansion;
+      if let (Some(from), Some(to)) = (&self.recency, &mut graph.recency) {
+        to.vertices[new_id] = from.vertices[i];
+      }
+      if let (Some(from), Some(to)) = (&self.stable, &mut graph.stable) {
+        to.replace(new_id, from.ids[i]);
+      }
+      if let (Some(from), Some(to)) = (&self.audit, &mut graph.audit) {
+        to.vertices[new_id] = from.vertices[i];
+      }
+      for (to, from) in graph.hot.get(new_id).iter().zip(self.hot.get(i)) {
+        to.store(from.load(Ordering::Relaxed), Ordering::Relaxed);
+      }
+    }
+
+    for id in order.iter() {
+      let source = new_ids[id.as_usize()].unwrap();
+      for child in self.get_vertex(*id).children.iter() {
+        let arc = self.get_arc(*child);
+        let target = new_ids[arc.target.as_usize()].unwrap();
+        let new_id = graph.add_raw_edge(arc.data.clone(), source, target);
+        if let (Some(from), Some(to)) = (&self.audit, &mut graph.audit) {
+          to.edges[new_id.as_usize()] = from.edges[child.as_usize()];
+        }
+      }
+    }
+
+    for (id, edges) in self.unexpanded.iter() {
+      if let Some(new_id) = new_ids[id.as_usize()] {
+        graph.unexpanded.insert(new_id, edges.clone());
+      }
+    }
+    for (alias, id) in self.aliases.iter() {
+      if let Some(new_id) = new_ids[id.as_usize()] {
+        graph.aliases.insert(alias.clone(), new_id);
+      }
+    }
+    if let Some(ref mut audit) = graph.audit {
+      audit.events.clear();
+    }
+    graph
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::CutEdge;
+
+  type Graph = crate::Graph<&'static str, &'static str, &'static str>;
+
+  #[test]
+  fn partition_with_cut_ok() {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| "active", "a", |_| "solved", "root_a");
+    g.add_edge("root", |_| "active", "b", |_| "active", "root_b");
+    g.add_edge("a", |_| "solved", "aa", |_| "solved", "a_aa");
+    g.add_edge("aa", |_| "solved", "b", |_| "active", "aa_b");
+    g.add_edge("b", |_| "active", "root", |_| "active", "b_root");
+    g.add_edge("a", |_| "solved", "ab", |_| "solved", "a_ab");
+
+    let (solved, active, cut) = g.partition_with_cut(|_, data| *data == "solved");
+
+    assert_eq!(3, solved.vertex_count());
+    assert_eq!(2, solved.edge_count());
+    let a = solved.find_node(&"a").unwrap();
+    assert!(a.is_root());
+    let children: Vec<&str> = a.get_child_list().iter().map(|e| *e.get_data()).collect();
+    assert_eq!(vec!["a_aa", "a_ab"], children);
+    assert!(solved.find_node(&"aa").unwrap().is_leaf());
+    assert!(solved.find_node(&"root").is_none());
+
+    assert_eq!(2, active.vertex_count());
+    assert_eq!(2, active.edge_count());
+    let root = active.find_node(&"root").unwrap();
+    assert_eq!("active", *root.get_data());
+    assert_eq!("root_b", *root.get_child_list().get_edge(0).get_data());
+    assert_eq!("b_root", *root.get_parent_list().get_edge(0).get_data());
+    assert!(active.find_node(&"a").is_none());
+
+    assert_eq!(
+      vec![
+        CutEdge {
+          source: "root",
+          target: "a",
+          data: "root_a"
+        },
+        CutEdge {
+          source: "aa",
+          target: "b",
+          data: "aa_b"
+        },
+      ],
+      cut
+    );
+  }
+
+  #[test]
+  fn partition_empty_ok() {
+    let (matching, rest) = Graph::new().partition(|_, _| true);
+    assert_eq!(0, matching.vertex_count());
+    assert_eq!(0, rest.vertex_count());
+  }
+
+  #[test]
+  fn extract_reachable_from_ok() {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| "r", "a", |_| "a", "root_a");
+    g.add_edge("root", |_| "r", "b", |_| "b", "root_b");
+    g.add_edge("a", |_| "a", "c", |_| "c", "a_c");
+    g.add_edge("a", |_| "a", "d", |_| "d", "a_d");
+    g.add_edge("d", |_| "d", "c", |_| "c", "d_c");
+    g.add_edge("c", |_| "c", "a", |_| "a", "c_a");
+    g.add_edge("b", |_| "b", "c", |_| "c", "b_c");
+
+    let sub = g.extract_reachable_from(&"a");
+    crate::assert_graph!(sub,
+      vertices: ["a" => "a", "c" => "c", "d" => "d"],
+      edges: ["a" => "c" => "a_c", "a" => "d" => "a_d", "d" => "c" => "d_c", "c" => "a" => "c_a"]);
+    assert!(crate::testing::invariant_violations(&sub).is_empty());
+    let ids: Vec<usize> = ["a", "c", "d"]
+      .iter()
+      .map(|s| sub.find_node(s).unwrap().get_id())
+      .collect();
+    assert_eq!(vec![0, 1, 2], ids);
+    let parents: Vec<&str> = sub
+      .find_node(&"c")
+      .unwrap()
+      .get_parent_list()
+      .iter()
+      .map(|e| *e.get_data())
+      .collect();
+    assert_eq!(vec!["a_c", "d_c"], parents);
+    assert_eq!(5, g.vertex_count());
+    assert_eq!(7, g.edge_count());
+
+    assert_eq!(0, g.extract_reachable_from(&"missing").vertex_count());
+  }
+}
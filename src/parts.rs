@@ -0,0 +1,176 @@
+//! Construction of graphs from complete lists of vertices and edges.
+//!
+//! Importers that read a graph from another format, and tests that need a
+//! particular graph, know all of its vertices and edges up front.
+//! [Graph::from_parts](../struct.Graph.html#method.from_parts) builds such a
+//! graph in one step, from vertices given by position and edges that refer to
+//! them by index, instead of through incremental insertion by game state. The
+//! input is validated before anything is built, and problems are reported as a
+//! [GraphError](enum.GraphError.html).
+//!
+//! ```rust
+//! # use search_graph::Graph;
+//! # fn main() {
+//! let g: Graph<&str, u32, char> = Graph::from_parts(
+//!   vec![("root", 0), ("a", 1), ("b", 2)],
+//!   vec![(0, 1, 'x'), (0, 2, 'y'), (1, 2, 'z')],
+//! )
+//! .unwrap();
+//! assert_eq!(g.find_node(&"b").unwrap().get_id(), 2);
+//! assert_eq!(g.find_node(&"root").unwrap().get_child_list().len(), 2);
+//! # }
+//! ```
+
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+
+use crate::base::VertexId;
+use crate::Graph;
+use symbol_map::indexing::{Indexing, Insertion};
+
+/// Errors that may arise when building a graph from its parts.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum GraphError {
+  /// Two vertices have the same game state.
+  DuplicateState {
+    /// The index of the first vertex with the game state.
+    first: usize,
+    /// The index of the later vertex with the game state.
+    duplicate: usize,
+  },
+  /// An edge refers to a vertex index that is out of bounds.
+  VertexIndex {
+    /// The index of the edge.
+    edge: usize,
+    /// The vertex index that the edge refers to.
+    index: usize,
+    /// The number of vertices (which `index` exceeds).
+    vertex_count: usize,
+  },
+}
+
+impl fmt::Display for GraphError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      GraphError::DuplicateState { first, duplicate } => write!(
+        f,
+        "Vertices {} and {} have the same game state",
+        first, duplicate
+      ),
+      GraphError::VertexIndex {
+        edge,
+        index,
+        vertex_count,
+      } => write!(
+        f,
+        "Edge {} refers to vertex {}/{}",
+        edge, index, vertex_count
+      ),
+    }
+  }
+}
+
+impl Error for GraphError {}
+
+impl<T: Hash + Eq + Clone, S, A> Graph<T, S, A> {
+  /// Builds a graph from a list of vertices, as game states and data, and a
+  /// list of edges, as the indices of their source and target vertices in
+  /// `vertices` and their data.
+  ///
+  /// Each vertex is assigned its index in `vertices` as its ID, and each edge
+  /// its index in `edges`. Edges are added as with
+  /// [add_edge](#method.add_edge), so the child and parent edges of each
+  /// vertex are in the order in which they occur in `edges`, and vertices
+  /// with children are partially expanded.
+  ///
+  /// Returns an error if two vertices have the same game state or an edge
+  /// refers to a vertex that does not exist.
+  pub fn from_parts(
+    vertices: Vec<(T, S)>,
+    edges: Vec<(usize, usize, A)>,
+  ) -> Result<Self, GraphError> {
+    Self::build_from_parts(Graph::new(), vertices, edges)
+  }
+
+  /// As `from_parts`, but adds the vertices and edges to `graph`, which must
+  /// be empty.
+  pub(crate) fn build_from_parts(
+    mut graph: Self,
+    vertices: Vec<(T, S)>,
+    edges: Vec<(usize, usize, A)>,
+  ) -> Result<Self, GraphError> {
+    let vertex_count = vertices.len();
+    for (i, (source, target, _)) in edges.iter().enumerate() {
+      if let Some(index) = [*source, *target].iter().find(|v| **v >= vertex_count) {
+        return Err(GraphError::VertexIndex {
+          edge: i,
+          index: *index,
+          vertex_count,
+        });
+      }
+    }
+    for (i, (state, data)) in vertices.into_iter().enumerate() {
+      if let Insertion::Present(first) = graph.state_ids.get_or_insert(state) {
+        return Err(GraphError::DuplicateState {
+          first: first.id().0,
+          duplicate: i,
+        });
+      }
+      graph.add_raw_vertex(data);
+    }
+    for (source, target, data) in edges {
+      graph.add_raw_edge(data, VertexId(source), VertexId(target));
+    }
+    Ok(graph)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::GraphError;
+  use crate::nav::Expansion;
+
+  type Graph = crate::Graph<&'static str, u32, char>;
+
+  #[test]
+  fn from_parts_ok() {
+    let g = Graph::from_parts(
+      vec![("root", 0), ("a", 1), ("b", 2), ("c", 3)],
+      vec![(0, 1, 'x'), (0, 2, 'y'), (1, 2, 'z'), (2, 0, 'w')],
+    )
+    .unwrap();
+    crate::assert_graph!(g,
+      vertices: ["root" => 0, "a" => 1, "b" => 2, "c" => 3],
+      edges: ["root" => "a" => 'x', "root" => "b" => 'y', "a" => "b" => 'z', "b" => "root" => 'w']);
+    let b = g.find_node(&"b").unwrap();
+    assert_eq!(2, b.get_id());
+    assert_eq!(3, b.get_child_list().get_edge(0).get_id());
+    assert_eq!(2, b.get_parent_list().len());
+    assert_eq!(Expansion::PartiallyExpanded, b.get_expansion());
+    assert_eq!(
+      Expansion::Unexpanded,
+      g.find_node(&"c").unwrap().get_expansion()
+    );
+    assert_eq!(0, Graph::from_parts(vec![], vec![]).unwrap().vertex_count());
+  }
+
+  #[test]
+  fn from_parts_err() {
+    assert_eq!(
+      Some(GraphError::DuplicateState {
+        first: 0,
+        duplicate: 2
+      }),
+      Graph::from_parts(vec![("a", 0), ("b", 1), ("a", 2)], vec![]).err()
+    );
+    assert_eq!(
+      Some(GraphError::VertexIndex {
+        edge: 1,
+        index: 2,
+        vertex_count: 2
+      }),
+      Graph::from_parts(vec![("a", 0), ("b", 1)], vec![(0, 1, 'x'), (1, 2, 'y')]).err()
+    );
+  }
+}
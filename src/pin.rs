@@ -0,0 +1,207 @@
+//! Handles to vertices that remain valid through garbage collection.
+//!
+//! Vertex IDs (as returned by `get_id` on node handles) are reassigned by
+//! garbage collection, and [NodeToken](../nav/struct.NodeToken.html)s are
+//! rejected after it, so bookkeeping outside the graph that refers to vertices
+//! by either must be rebuilt after every collection. A vertex may instead be
+//! pinned with [Graph::pin](../struct.Graph.html#method.pin) or
+//! [MutNode::pin](../mutators/struct.MutNode.html#method.pin), which returns a
+//! [PinnedNodeId](struct.PinnedNodeId.html). The graph keeps a relocation
+//! table of pinned vertices that it rewrites whenever it compacts itself, so a
+//! pinned ID may be resolved with
+//! [Graph::resolve](../struct.Graph.html#method.resolve) at any later time.
+//!
+//! Pinning a vertex does not keep it alive: if garbage collection drops a
+//! pinned vertex, its pin is released and no longer resolves. Pins are carried
+//! through [partitioning](../struct.Graph.html#method.partition) to the graph
+//! that receives their vertex. Each pin costs a table entry until it is
+//! released with [Graph::unpin](../struct.Graph.html#method.unpin).
+//!
+//! ```rust
+//! # use search_graph::Graph;
+//! # fn main() {
+//! let mut g: Graph<&str, u32, ()> = Graph::new();
+//! g.add_edge("root", |_| 0, "a", |_| 1, ());
+//! let pinned = g.pin(&"a").unwrap();
+//! assert_eq!(g.find_node(&"a").unwrap().get_id(), 1);
+//! g.compact_with(&["a"], &mut []);
+//! let node = g.resolve(pinned).unwrap();
+//! assert_eq!(node.get_label(), &"a");
+//! assert_eq!(node.get_id(), 0);
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::base::VertexId;
+use crate::mutators::MutNode;
+use crate::nav::Node;
+use crate::Graph;
+use symbol_map::SymbolId;
+
+/// Identifies a pinned vertex for as long as it is in its graph.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct PinnedNodeId(u64);
+
+/// The vertex that each pin currently refers to.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Pins {
+  /// The next pin to issue. Pins are never reused.
+  next: u64,
+  vertices: HashMap<u64, VertexId>,
+}
+
+impl Pins {
+  /// Returns a new pin for `id`.
+  pub fn create(&mut self, id: VertexId) -> PinnedNodeId {
+    let pin = self.next;
+    self.next += 1;
+    self.vertices.insert(pin, id);
+    PinnedNodeId(pin)
+  }
+
+  /// Returns the vertex that `pin` refers to.
+  pub fn get(&self, pin: PinnedNodeId) -> Option<VertexId> {
+    self.vertices.get(&pin.0).cloned()
+  }
+
+  /// Releases `pin`, returning true iff it was held.
+  pub fn remove(&mut self, pin: PinnedNodeId) -> bool {
+    self.vertices.remove(&pin.0).is_some()
+  }
+
+  /// Returns the number of pins that are held.
+  pub fn len(&self) -> usize {
+    self.vertices.len()
+  }
+
+  /// Returns the vertex that each held pin refers to.
+  pub fn vertices(&self) -> impl Iterator<Item = VertexId> + '_ {
+    self.vertices.values().cloned()
+  }
+
+  /// Rewrites pins after vertices were renumbered by `f`, which maps old
+  /// vertex indices to new ones, releasing the pins of dropped vertices.
+  pub fn remap<F: Fn(usize) -> Option<usize>>(&mut self, f: F) {
+    self.vertices.retain(|_, id| match f(id.as_usize()) {
+      Some(new_id) => {
+        *id = VertexId(new_id);
+        true
+      }
+      None => false,
+    });
+  }
+
+  /// Returns empty pin tables for the two graphs that a graph is partitioned
+  /// into. They issue pins after those of this table, so that pins are not
+  /// reused by either.
+  pub fn empty_like(&self) -> Self {
+    Pins {
+      next: self.next,
+      vertices: HashMap::new(),
+    }
+  }
+
+  /// Removes all pins, returning each with the index of its vertex.
+  pub fn drain(&mut self) -> impl Iterator<Item = (PinnedNodeId, usize)> + '_ {
+    self
+      .vertices
+      .drain()
+      .map(|(pin, id)| (PinnedNodeId(pin), id.as_usize()))
+  }
+
+  /// Adds `pin`, which refers to `id`.
+  pub fn insert(&mut self, pin: PinnedNodeId, id: VertexId) {
+    self.vertices.insert(pin.0, id);
+  }
+}
+
+impl<T: Hash + Eq + Clone, S, A> Graph<T, S, A> {
+  /// Pins the vertex for `state`, returning an ID that may be resolved after
+  /// the graph is garbage-collected (see [pin](pin/index.html)), or `None` if
+  /// there is no such vertex. Pinning a vertex again returns a distinct ID.
+  pub fn pin(&mut self, state: &T) -> Option<PinnedNodeId> {
+    let id = self.lookup(state)?;
+    Some(self.pins.create(id))
+  }
+
+  /// Releases `pin`, returning true iff it was held. Releasing a pin does not
+  /// affect other pins of the same vertex.
+  pub fn unpin(&mut self, pin: PinnedNodeId) -> bool {
+    self.pins.remove(pin)
+  }
+
+  /// Returns the number of pins that are held.
+  pub fn pin_count(&self) -> usize {
+    self.pins.len()
+  }
+
+  /// Gets a node handle for the vertex that `pin` refers to, or `None` if it
+  /// has been released or its vertex was dropped.
+  pub fn resolve<'s>(&'s self, pin: PinnedNodeId) -> Option<Node<'s, T, S, A>> {
+    self.pins.get(pin).map(|id| Node::new(self, id))
+  }
+
+  /// As [resolve](#method.resolve), but returns a mutable node handle.
+  pub fn resolve_mut<'s>(&'s mut self, pin: PinnedNodeId) -> Option<MutNode<'s, T, S, A>> {
+    self.pins.get(pin).map(move |id| MutNode::new(self, id))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  type Graph = crate::Graph<u32, u32, ()>;
+
+  fn make_graph() -> Graph {
+    let mut g = Graph::new();
+    for i in 0..5 {
+      g.add_edge(i, |_| i, i + 1, |_| i + 1, ());
+    }
+    g
+  }
+
+  #[test]
+  fn pin_ok() {
+    let mut g = make_graph();
+    assert!(g.pin(&10).is_none());
+    let three = g.pin(&3).unwrap();
+    let one = g.find_node_mut(&1).unwrap().pin();
+    let five = g.pin(&5).unwrap();
+    let again = g.pin(&5).unwrap();
+    assert_ne!(five, again);
+    assert_eq!(4, g.pin_count());
+    assert_eq!(3, *g.resolve(three).unwrap().get_label());
+
+    g.compact_with(&[3], &mut []);
+    assert_eq!(0, g.find_node(&3).unwrap().get_id());
+    assert_eq!(3, *g.resolve(three).unwrap().get_label());
+    assert_eq!(0, g.resolve(three).unwrap().get_id());
+    assert_eq!(5, *g.resolve(five).unwrap().get_label());
+    assert!(g.resolve(one).is_none());
+    assert_eq!(3, g.pin_count());
+    *g.resolve_mut(three).unwrap().get_data_mut() = 30;
+    assert_eq!(30, *g.find_node(&3).unwrap().get_data());
+
+    assert!(g.unpin(five));
+    assert!(!g.unpin(five));
+    assert!(!g.unpin(one));
+    assert!(g.resolve(five).is_none());
+    assert_eq!(5, *g.resolve(again).unwrap().get_label());
+  }
+
+  #[test]
+  fn pin_partition_ok() {
+    let mut g = make_graph();
+    let two = g.pin(&2).unwrap();
+    let four = g.pin(&4).unwrap();
+    let (mut left, mut right) = g.partition(|state, _| *state < 3);
+    assert_eq!(2, *left.resolve(two).unwrap().get_label());
+    assert!(left.resolve(four).is_none());
+    assert_eq!(4, *right.resolve(four).unwrap().get_label());
+    assert!(right.resolve(two).is_none());
+    let zero = left.pin(&0).unwrap();
+    let three = right.pin(&3).unwrap();
+    assert!(zero != two && zero != four && three != two && three != four);
+  }
+}
@@ -0,0 +1,581 @@
+//! Policies for shrinking a graph beyond what is reachable from its roots.
+//!
+//! Garbage collection with
+//! [View::retain_reachable_from](../view/struct.View.html#method.retain_reachable_from)
+//! drops only the vertices that can no longer be reached. When a search must
+//! also shed reachable vertices to bound its memory use,
+//! [Graph::prune](../struct.Graph.html#method.prune) retains a connected set
+//! of at most a given number of vertices, chosen by a
+//! [PrunePolicy](trait.PrunePolicy.html).
+//!
+//! Vertices are selected best-first: starting from the roots, the vertex with
+//! the highest priority among those adjacent to the selection is added to it
+//! until the policy's capacity is reached. The selection therefore always
+//! consists of whole paths from the roots, and a vertex is only retained if
+//! its parent on such a path is.
+//!
+//! [Graph::keep_best_lines](../struct.Graph.html#method.keep_best_lines)
+//! instead selects edges, retaining a fixed number of the best-ranked children
+//! of each vertex near the roots.
+//!
+//! [by_horizon](fn.by_horizon.html) instead sheds history: it drops the
+//! positions that are more than a given number of plies behind the current
+//! root, which suits engines that play long matches and never look back far.
+//!
+//! A graph may also be configured to prune itself automatically when it grows
+//! past a threshold, with
+//! [Graph::set_auto_prune](../struct.Graph.html#method.set_auto_prune).
+//!
+//! ```rust
+//! # use search_graph::Graph;
+//! # use search_graph::prune::KeepMostVisited;
+//! # fn main() {
+//! let mut g: Graph<&str, u32, ()> = Graph::new();
+//! g.add_edge("root", |_| 10, "a", |_| 7, ());
+//! g.add_edge("root", |_| 10, "b", |_| 2, ());
+//! g.add_edge("a", |_| 7, "c", |_| 5, ());
+//! g.prune(&["root"], &KeepMostVisited(3, |visits: &u32| *visits));
+//! assert_eq!(g.vertex_count(), 3);
+//! assert!(g.find_node(&"b").is_none());
+//! assert!(g.find_node(&"c").is_some());
+//! # }
+//! ```
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+use std::hash::Hash;
+
+use crate::base::VertexId;
+use crate::mark_compact::Collector;
+use crate::nav::Node;
+use crate::Graph;
+use symbol_map::SymbolId;
+
+/// Determines which vertices are retained by
+/// [Graph::prune](../struct.Graph.html#method.prune).
+pub trait PrunePolicy<T: Hash + Eq + Clone, S, A> {
+  /// The type of vertex priorities. Vertices with greater priorities are
+  /// retained first.
+  type Priority: Ord;
+
+  /// Returns the maximum number of vertices to retain.
+  fn capacity(&self) -> usize;
+
+  /// Returns the priority of `node`, which is `depth` edges away from the
+  /// nearest root along the path by which it was reached.
+  fn priority(&self, node: &Node<T, S, A>, depth: usize) -> Self::Priority;
+}
+
+/// Retains at most `n` vertices, preferring those nearest to the roots.
+///
+/// Vertices near the roots have the deepest searches beneath them, so this
+/// corresponds to depth-preferred replacement in a transposition table.
+#[derive(Clone, Copy, Debug)]
+pub struct KeepDeepest(pub usize);
+
+impl<T: Hash + Eq + Clone, S, A> PrunePolicy<T, S, A> for KeepDeepest {
+  type Priority = Reverse<usize>;
+
+  fn capacity(&self) -> usize {
+    self.0
+  }
+
+  fn priority(&self, _: &Node<T, S, A>, depth: usize) -> Reverse<usize> {
+    Reverse(depth)
+  }
+}
+
+/// Retains at most `n` vertices, preferring those with the most visits, as
+/// given by a function of vertex data.
+#[derive(Clone, Copy, Debug)]
+pub struct KeepMostVisited<F>(pub usize, pub F);
+
+impl<T, S, A, F, V> PrunePolicy<T, S, A> for KeepMostVisited<F>
+where
+  T: Hash + Eq + Clone,
+  F: Fn(&S) -> V,
+  V: Ord,
+{
+  type Priority = V;
+
+  fn capacity(&self) -> usize {
+    self.0
+  }
+
+  fn priority(&self, node: &Node<T, S, A>, _: usize) -> V {
+    (self.1)(node.get_data())
+  }
+}
+
+/// A summary of an automatic pruning pass.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PruneReport {
+  /// The number of vertices before pruning.
+  pub vertices_before: usize,
+  /// The number of edges before pruning.
+  pub edges_before: usize,
+  /// The number of vertices after pruning.
+  pub vertices_after: usize,
+  /// The number of edges after pruning.
+  pub edges_after: usize,
+}
+
+type PruneFn<T, S, A> = Box<dyn Fn(&mut Graph<T, S, A>, &[T]) + Send + Sync>;
+type ReportFn = Box<dyn FnMut(&PruneReport) + Send + Sync>;
+
+/// Configuration for automatically pruning a graph when it grows too large.
+///
+/// See [Graph::set_auto_prune](../struct.Graph.html#method.set_auto_prune).
+pub struct AutoPrune<T: Hash + Eq + Clone, S, A> {
+  threshold: usize,
+  roots: Vec<T>,
+  prune: PruneFn<T, S, A>,
+  report: Option<ReportFn>,
+}
+
+impl<T: Hash + Eq + Clone, S, A> AutoPrune<T, S, A> {
+  /// Creates a configuration that prunes a graph with `policy`, retaining
+  /// components reachable from the pinned game states in `roots`, whenever the
+  /// graph has more than `threshold` vertices.
+  ///
+  /// The capacity of `policy` should be well below `threshold`, or pruning
+  /// will happen on nearly every insertion.
+  pub fn new<P>(threshold: usize, roots: Vec<T>, policy: P) -> Self
+  where
+    P: PrunePolicy<T, S, A> + Send + Sync + 'static,
+  {
+    AutoPrune {
+      threshold,
+      roots,
+      prune: Box::new(move |graph, roots| graph.prune(roots, &policy)),
+      report: None,
+    }
+  }
+
+  /// Sets a callback that is given a report after each automatic pruning pass.
+  pub fn on_report<F: FnMut(&PruneReport) + Send + Sync + 'static>(mut self, report: F) -> Self {
+    self.report = Some(Box::new(report));
+    self
+  }
+
+  /// Returns the pinned game states that pruning retains components of.
+  pub fn roots(&self) -> &[T] {
+    &self.roots
+  }
+
+  /// Replaces the pinned game states that pruning retains components of.
+  pub fn set_roots(&mut self, roots: Vec<T>) {
+    self.roots = roots;
+  }
+}
+
+impl<T: Hash + Eq + Clone, S, A> Graph<T, S, A> {
+  /// Configures this graph to prune itself when it grows past a threshold, or
+  /// disables automatic pruning if `auto_prune` is `None`.
+  ///
+  /// The threshold is checked by [maybe_prune](struct.Graph.html#method.maybe_prune),
+  /// which is called at the start of `add_node` and `add_edge`. Insertions
+  /// through the `mutators` and `view` modules do not check the threshold,
+  /// since pruning would invalidate the handles that they operate on, so code
+  /// that uses them should call `maybe_prune` at convenient points.
+  ///
+  /// Pruning reassigns vertex and edge IDs, as all garbage collection does.
+  pub fn set_auto_prune(&mut self, auto_prune: Option<AutoPrune<T, S, A>>) {
+    self.auto_prune = auto_prune.map(Box::new);
+  }
+
+  /// Returns the automatic pruning configuration of this graph, if any.
+  pub fn auto_prune_mut(&mut self) -> Option<&mut AutoPrune<T, S, A>> {
+    self.auto_prune.as_deref_mut()
+  }
+
+  /// Prunes this graph if automatic pruning is configured and the graph has
+  /// more vertices than its threshold. Returns a report if pruning happened.
+  pub fn maybe_prune(&mut self) -> Option<PruneReport> {
+    match self.auto_prune {
+      Some(ref config) if self.vertices.len() > config.threshold => (),
+      _ => return None,
+    }
+    let mut config = self.auto_prune.take().unwrap();
+    let mut report = PruneReport {
+      vertices_before: self.vertices.len(),
+      edges_before: self.arcs.len(),
+      ..Default::default()
+    };
+    (config.prune)(self, &config.roots);
+    report.vertices_after = self.vertices.len();
+    report.edges_after = self.arcs.len();
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+      vertices_before = report.vertices_before,
+      vertices_after = report.vertices_after,
+      "automatic pruning finished"
+    );
+    if let Some(ref mut f) = config.report {
+      f(&report);
+    }
+    self.auto_prune = Some(config);
+    Some(report)
+  }
+
+  /// Garbage-collects this graph, retaining at most `policy.capacity()`
+  /// vertices that are reachable from the vertices for `roots`, as selected by
+  /// `policy`. Game states in `roots` that are not in the graph are ignored.
+  pub fn prune<P: PrunePolicy<T, S, A>>(&mut self, roots: &[T], policy: &P) {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::debug_span!("prune", capacity = policy.capacity()).entered();
+    let root_ids: Vec<VertexId> = roots
+      .iter()
+      .filter_map(|state| self.lookup(state))
+      .collect();
+    let keep = self.select(&root_ids, policy);
+    Collector::retain_selected(self, &root_ids, keep);
+  }
+
+  /// Garbage-collects this graph, retaining only the `n` best lines of play
+  /// from the vertices for `roots` (e.g., to produce a compact opening book
+  /// from a large search graph). Game states in `roots` that are not in the
+  /// graph are ignored.
+  ///
+  /// At each vertex that is fewer than `depth` edges from the nearest root
+  /// (or at every vertex, if `depth` is `None`), only the `n` child edges
+  /// with the greatest `edge_key` are retained, with ties broken in favor of
+  /// earlier children. Deeper vertices retain all of their child edges.
+  /// Vertices that are no longer reachable from the roots are then dropped.
+  pub fn keep_best_lines<K, F>(
+    &mut self,
+    roots: &[T],
+    n: usize,
+    depth: Option<usize>,
+    mut edge_key: F,
+  ) where
+    K: Ord,
+    F: FnMut(&A) -> K,
+  {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::debug_span!("keep_best_lines", n).entered();
+    let root_ids: Vec<VertexId> = roots
+      .iter()
+      .filter_map(|state| self.lookup(state))
+      .collect();
+    let mut keep_arcs = vec![true; self.arcs.len()];
+    let mut seen = vec![false; self.vertices.len()];
+    let mut frontier = VecDeque::new();
+    for id in root_ids.iter() {
+      if !seen[id.as_usize()] {
+        seen[id.as_usize()] = true;
+        frontier.push_back((*id, 0));
+      }
+    }
+    while let Some((id, d)) = frontier.pop_front() {
+      let children = &self.get_vertex(id).children;
+      if depth.is_none_or(|depth| d < depth) {
+        let mut ranked: Vec<(K, usize)> = children
+          .iter()
+          .enumerate()
+          .map(|(i, edge_id)| (edge_key(&self.get_arc(*edge_id).data), i))
+          .collect();
+        ranked.sort_by(|(a, i), (b, j)| b.cmp(a).then(i.cmp(j)));
+        for (_, i) in ranked.into_iter().skip(n) {
+          keep_arcs[children[i].as_usize()] = false;
+        }
+      }
+      for edge_id in children.iter() {
+        let target = self.get_arc(*edge_id).target;
+        if keep_arcs[edge_id.as_usize()] && !seen[target.as_usize()] {
+          seen[target.as_usize()] = true;
+          frontier.push_back((target, d + 1));
+        }
+      }
+    }
+    Collector::retain_selected_edges(self, &root_ids, keep_arcs);
+  }
+
+  /// Returns which vertices `policy` selects for retention, indexed by
+  /// `VertexId`.
+  fn select<P: PrunePolicy<T, S, A>>(&self, roots: &[VertexId], policy: &P) -> Vec<bool> {
+    let mut keep = vec![false; self.vertices.len()];
+    let mut queued = vec![false; self.vertices.len()];
+    // Ties are broken in favor of the vertex that was queued first.
+    let mut heap = BinaryHeap::new();
+    let mut sequence = 0;
+    let mut enqueue = |heap: &mut BinaryHeap<_>, id: VertexId, depth: usize| {
+      if !queued[id.as_usize()] {
+        queued[id.as_usize()] = true;
+        let priority = policy.priority(&Node::new(self, id), depth);
+        heap.push((priority, Reverse(sequence), id.as_usize(), depth));
+        sequence += 1;
+      }
+    };
+    for id in roots.iter() {
+      enqueue(&mut heap, *id, 0);
+    }
+    let mut kept = 0;
+    while kept < policy.capacity() {
+      let (_, _, id, depth) = match heap.pop() {
+        Some(entry) => entry,
+        None => break,
+      };
+      keep[id] = true;
+      kept += 1;
+      for edge_id in self.get_vertex(VertexId(id)).children.iter() {
+        enqueue(&mut heap, self.get_arc(*edge_id).target, depth + 1);
+      }
+    }
+    keep
+  }
+}
+
+/// Garbage-collects `graph`, dropping the positions that are more than
+/// `max_plies_behind` plies behind the vertex for `root`. The graph is
+/// unchanged if `root` is not in it.
+///
+/// A vertex is within the horizon if a path of at most `max_plies_behind`
+/// edges leads from it to the vertex for `root`. Every vertex that is
+/// reachable from a vertex within the horizon is retained, including the
+/// descendants of `root` and the lines that branch off from recent history,
+/// and the rest are dropped. A vertex that is behind `root` along one path is
+/// therefore retained if it is within the horizon along another. With a
+/// `max_plies_behind` of 0, this is the same as
+/// [View::retain_reachable_from](../view/struct.View.html#method.retain_reachable_from)
+/// with `root` alone.
+///
+/// Incoming edges are followed if the graph tracks them, and found by
+/// scanning every edge otherwise.
+pub fn by_horizon<T, S, A>(graph: &mut Graph<T, S, A>, root: &T, max_plies_behind: usize)
+where
+  T: Hash + Eq + Clone,
+{
+  #[cfg(feature = "tracing")]
+  let _span = tracing::debug_span!("by_horizon", max_plies_behind).entered();
+  let root_id = match graph.lookup(root) {
+    Some(id) => id,
+    None => return,
+  };
+  let untracked_sources: Option<Vec<Vec<VertexId>>> = match graph.try_parents(root_id) {
+    Ok(_) => None,
+    Err(_) => {
+      let mut sources = vec![Vec::new(); graph.vertices.len()];
+      for arc in graph.arcs.iter() {
+        sources[arc.target.as_usize()].push(arc.source);
+      }
+      Some(sources)
+    }
+  };
+  let mut seen = vec![false; graph.vertices.len()];
+  seen[root_id.as_usize()] = true;
+  let mut horizon = vec![root_id];
+  let mut frontier = VecDeque::new();
+  frontier.push_back((root_id, 0));
+  while let Some((id, d)) = frontier.pop_front() {
+    if d == max_plies_behind {
+      continue;
+    }
+    let sources: Vec<VertexId> = match untracked_sources {
+      Some(ref sources) => sources[id.as_usize()].clone(),
+      None => graph
+        .parents(id)
+        .iter()
+        .map(|edge_id| graph.get_arc(*edge_id).source)
+        .collect(),
+    };
+    for source in sources {
+      if !seen[source.as_usize()] {
+        seen[source.as_usize()] = true;
+        horizon.push(source);
+        frontier.push_back((source, d + 1));
+      }
+    }
+  }
+  Collector::retain_reachable(graph, &horizon);
+}
+
+#[cfg(test)]
+mod test {
+  use super::{AutoPrune, KeepDeepest, KeepMostVisited, PruneReport};
+  use crate::config::GraphConfig;
+  use crate::nav::Expansion;
+  use std::sync::{Arc, Mutex};
+
+  type Graph = crate::Graph<&'static str, u32, &'static str>;
+
+  fn make_graph() -> Graph {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| 10, "a", |_| 2, "root_a");
+    g.add_edge("root", |_| 10, "b", |_| 8, "root_b");
+    g.add_edge("a", |_| 2, "c", |_| 9, "a_c");
+    g.add_edge("b", |_| 8, "d", |_| 3, "b_d");
+    g.add_edge("b", |_| 8, "a", |_| 2, "b_a");
+    g
+  }
+
+  fn labels(g: &Graph) -> Vec<&'static str> {
+    let mut labels: Vec<&'static str> = g.states().cloned().collect();
+    labels.sort();
+    labels
+  }
+
+  #[test]
+  fn by_horizon_ok() {
+    for track_parents in [true, false] {
+      let mut g = Graph::with_config(GraphConfig {
+        track_parents,
+        ..Default::default()
+      });
+      // A game history 0 -> 1 -> 2 -> 3 -> 4, with side lines and a
+      // transposition into 3.
+      for (source, target) in [
+        ("0", "1"),
+        ("1", "2"),
+        ("2", "3"),
+        ("3", "4"),
+        ("0", "x"),
+        ("1", "y"),
+        ("2", "z"),
+        ("w", "v"),
+        ("v", "3"),
+      ]
+      .iter()
+      {
+        g.add_edge(*source, |_| 0, *target, |_| 0, "");
+      }
+      let mut h = g.topology_clone();
+      super::by_horizon(&mut g, &"3", 1);
+      assert_eq!(vec!["2", "3", "4", "v", "z"], labels(&g));
+      assert_eq!(4, g.edge_count());
+      super::by_horizon(&mut g, &"missing", 0);
+      assert_eq!(5, g.vertex_count());
+      super::by_horizon(&mut g, &"3", 0);
+      assert_eq!(vec!["3", "4"], labels(&g));
+
+      super::by_horizon(&mut h, &"3", 2);
+      let mut h_labels: Vec<&str> = h.states().cloned().collect();
+      h_labels.sort();
+      assert_eq!(vec!["1", "2", "3", "4", "v", "w", "y", "z"], h_labels);
+    }
+  }
+
+  #[test]
+  fn keep_deepest_ok() {
+    let mut g = make_graph();
+    g.find_node_mut(&"a").unwrap().mark_expanded();
+    g.find_node_mut(&"root").unwrap().mark_expanded();
+    g.prune(&["root"], &KeepDeepest(3));
+    assert_eq!(vec!["a", "b", "root"], labels(&g));
+    // Edges to dropped vertices are dropped, but edges among retained vertices
+    // are kept.
+    assert_eq!(3, g.edge_count());
+    assert!(g.find_node(&"a").unwrap().is_leaf());
+    assert_eq!(2, g.find_node(&"a").unwrap().get_parent_list().len());
+    // "a" lost its child, but "root" did not.
+    assert_eq!(
+      Expansion::PartiallyExpanded,
+      g.find_node(&"a").unwrap().get_expansion()
+    );
+    assert_eq!(
+      Expansion::FullyExpanded,
+      g.find_node(&"root").unwrap().get_expansion()
+    );
+  }
+
+  #[test]
+  fn keep_most_visited_ok() {
+    let mut g = make_graph();
+    g.prune(&["root"], &KeepMostVisited(3, |visits: &u32| *visits));
+    // "c" has the most visits of any vertex other than "root", but it is
+    // dropped because it is only reachable through "a", which has fewer visits
+    // than "d".
+    assert_eq!(vec!["b", "d", "root"], labels(&g));
+    assert_eq!(2, g.edge_count());
+  }
+
+  #[test]
+  fn keep_best_lines_ok() {
+    let key = |data: &&str| match *data {
+      "root_a" => 1,
+      "root_b" => 2,
+      "b_d" => 1,
+      "b_a" => 1,
+      _ => 0,
+    };
+    let mut g = make_graph();
+    g.add_edge("c", |_| 0, "e", |_| 0, "c_e");
+    g.add_edge("c", |_| 0, "f", |_| 0, "c_f");
+    g.find_node_mut(&"b").unwrap().mark_expanded();
+    g.keep_best_lines(&["root"], 1, None, key);
+    // Ties between "b_d" and "b_a" go to the earlier child.
+    assert_eq!(vec!["b", "d", "root"], labels(&g));
+    assert_eq!(2, g.edge_count());
+    assert_eq!(
+      Expansion::PartiallyExpanded,
+      g.find_node(&"b").unwrap().get_expansion()
+    );
+
+    let mut g = make_graph();
+    g.add_edge("c", |_| 0, "e", |_| 0, "c_e");
+    g.add_edge("c", |_| 0, "f", |_| 0, "c_f");
+    g.keep_best_lines(&["root"], 1, Some(1), key);
+    // Only "root" is filtered, so "a" is retained through "b" even though the
+    // edge from "root" to "a" is dropped.
+    assert_eq!(vec!["a", "b", "c", "d", "e", "f", "root"], labels(&g));
+    assert_eq!(1, g.find_node(&"a").unwrap().get_parent_list().len());
+
+    let mut g = make_graph();
+    g.add_edge("c", |_| 0, "e", |_| 0, "c_e");
+    g.add_edge("c", |_| 0, "f", |_| 0, "c_f");
+    g.keep_best_lines(&["a"], 1, Some(1), key);
+    // Vertices at depth 1 and below keep all of their children.
+    assert_eq!(vec!["a", "c", "e", "f"], labels(&g));
+    let prefer_a = |data: &&str| (*data == "root_a") as u8;
+    let mut g = make_graph();
+    g.keep_best_lines(&["root"], 2, None, prefer_a);
+    assert_eq!(5, g.vertex_count());
+    assert_eq!(5, g.edge_count());
+  }
+
+  #[test]
+  fn prune_capacity_ok() {
+    let mut g = make_graph();
+    g.prune(&["root", "missing"], &KeepDeepest(100));
+    assert_eq!(5, g.vertex_count());
+    g.prune(&["root"], &KeepDeepest(0));
+    assert_eq!(0, g.vertex_count());
+    assert_eq!(0, g.edge_count());
+  }
+
+  #[test]
+  fn auto_prune_ok() {
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let mut g = Graph::new();
+    let sink = reports.clone();
+    g.set_auto_prune(Some(
+      AutoPrune::new(4, vec!["root"], KeepDeepest(2))
+        .on_report(move |r| sink.lock().unwrap().push(*r)),
+    ));
+    assert!(g.maybe_prune().is_none());
+    g.add_edge("root", |_| 10, "a", |_| 2, "root_a");
+    g.add_edge("root", |_| 10, "b", |_| 8, "root_b");
+    g.add_edge("a", |_| 2, "c", |_| 9, "a_c");
+    g.add_edge("b", |_| 8, "d", |_| 1, "b_d");
+    assert_eq!(5, g.vertex_count());
+    assert!(reports.lock().unwrap().is_empty());
+    // The threshold is exceeded, so this insertion is preceded by pruning.
+    g.add_node("e", 0);
+    assert_eq!(3, g.vertex_count());
+    assert!(g.find_node(&"a").is_some());
+    assert!(g.find_node(&"b").is_none());
+    assert_eq!(
+      vec![PruneReport {
+        vertices_before: 5,
+        edges_before: 4,
+        vertices_after: 2,
+        edges_after: 1,
+      }],
+      *reports.lock().unwrap()
+    );
+    g.auto_prune_mut().unwrap().set_roots(vec!["e"]);
+    assert_eq!(&["e"], g.auto_prune_mut().unwrap().roots());
+    g.set_auto_prune(None);
+    assert!(g.maybe_prune().is_none());
+  }
+}
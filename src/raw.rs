@@ -0,0 +1,347 @@
+//! Bulk access to the data of every vertex and edge in a graph.
+//!
+//! Passes that update every payload in a graph (e.g., decaying statistics, or
+//! vectorized updates of evaluations) need not go through a handle per vertex.
+//! [Graph::with_raw_parts_mut](../struct.Graph.html#method.with_raw_parts_mut)
+//! runs a closure with a [RawParts](struct.RawParts.html), which indexes
+//! vertices and edges by ID and gives mutable access to their data. The
+//! structure of the graph is read-only: the endpoints of edges and the child
+//! and parent edges of vertices can be read but not changed, so the graph's
+//! invariants hold however the closure uses it.
+//!
+//! [RawParts](struct.RawParts.html) has separate
+//! [RawVertices](struct.RawVertices.html) and [RawEdges](struct.RawEdges.html)
+//! fields, so vertex and edge data may be borrowed at the same time.
+//!
+//! Data that is modified through these types is recorded as modified once the
+//! closure returns or panics, as if it had been modified through a handle:
+//! recency ticks, the [audit trail](../audit/index.html), the next
+//! [snapshot](../snapshot/index.html), and
+//! [child heaps](../struct.Graph.html#method.order_children_by) are updated
+//! for each vertex and edge whose data was borrowed mutably. Iterating over all
+//! data mutably counts as modifying all of it.
+//!
+//! ```rust
+//! # use search_graph::Graph;
+//! # fn main() {
+//! let mut g: Graph<&str, f32, f32> = Graph::new();
+//! g.add_edge("root", |_| 1.0, "a", |_| 2.0, 0.5);
+//! g.add_edge("root", |_| 1.0, "b", |_| 4.0, 0.25);
+//! let total = g.with_raw_parts_mut(|parts| {
+//!   for value in parts.vertices.iter_mut() {
+//!     *value *= 0.5;
+//!   }
+//!   // Propagate vertex values along edges.
+//!   for (_, target, weight) in parts.edges.iter_mut() {
+//!     *weight *= parts.vertices.data(target);
+//!   }
+//!   parts.edges.iter().map(|(_, _, w)| *w).sum::<f32>()
+//! });
+//! assert_eq!(total, 1.0);
+//! assert_eq!(*g.find_node(&"b").unwrap().get_data(), 2.0);
+//! # }
+//! ```
+
+use std::hash::Hash;
+use std::panic;
+
+use crate::base::{EdgeId, RawEdge, RawVertex, VertexId};
+use crate::Graph;
+use symbol_map::SymbolId;
+
+/// Which items of a graph have been borrowed mutably.
+#[derive(Default)]
+struct Touched {
+  all: bool,
+  ids: Vec<usize>,
+}
+
+impl Touched {
+  fn ids(self, len: usize) -> Vec<usize> {
+    if self.all {
+      (0..len).collect()
+    } else {
+      let mut ids = self.ids;
+      ids.sort_unstable();
+      ids.dedup();
+      ids
+    }
+  }
+}
+
+/// Access to the data of every vertex and edge in a graph, as passed to the
+/// closure given to
+/// [Graph::with_raw_parts_mut](../struct.Graph.html#method.with_raw_parts_mut).
+pub struct RawParts<'a, S, A> {
+  /// The vertices of the graph, indexed by vertex ID.
+  pub vertices: RawVertices<'a, S>,
+  /// The edges of the graph, indexed by edge ID.
+  pub edges: RawEdges<'a, A>,
+}
+
+/// The vertices of a graph, indexed by vertex ID, with mutable data. See
+/// [RawParts](struct.RawParts.html).
+pub struct RawVertices<'a, S> {
+  vertices: &'a mut [RawVertex<S>],
+  touched: Touched,
+}
+
+impl<'a, S> RawVertices<'a, S> {
+  /// Returns the number of vertices.
+  pub fn len(&self) -> usize {
+    self.vertices.len()
+  }
+
+  /// Returns true iff there are no vertices.
+  pub fn is_empty(&self) -> bool {
+    self.vertices.is_empty()
+  }
+
+  /// Returns the data of vertex `id`. Panics if there is no such vertex.
+  pub fn data(&self, id: usize) -> &S {
+    &self.vertices[id].data
+  }
+
+  /// Returns the data of vertex `id`, mutably. Panics if there is no such
+  /// vertex.
+  pub fn data_mut(&mut self, id: usize) -> &mut S {
+    let data = &mut self.vertices[id].data;
+    self.touched.ids.push(id);
+    data
+  }
+
+  /// Returns the IDs of the child edges of vertex `id`, in order. Panics if
+  /// there is no such vertex.
+  pub fn children(&self, id: usize) -> impl ExactSizeIterator<Item = usize> + '_ {
+    self.vertices[id].children.iter().map(|e| e.as_usize())
+  }
+
+  /// Returns the IDs of the parent edges of vertex `id`, in order, or nothing
+  /// if the graph does not track parent edges. Panics if there is no such
+  /// vertex.
+  pub fn parents(&self, id: usize) -> impl ExactSizeIterator<Item = usize> + '_ {
+    self.vertices[id].parents.iter().map(|e| e.as_usize())
+  }
+
+  /// Returns an iterator over the data of each vertex, in order of vertex ID.
+  pub fn iter(&self) -> impl ExactSizeIterator<Item = &S> + DoubleEndedIterator {
+    self.vertices.iter().map(|v| &v.data)
+  }
+
+  /// Returns an iterator over the data of each vertex, mutably, in order of
+  /// vertex ID.
+  pub fn iter_mut(&mut self) -> impl ExactSizeIterator<Item = &mut S> + DoubleEndedIterator {
+    self.touched.all = true;
+    self.vertices.iter_mut().map(|v| &mut v.data)
+  }
+}
+
+/// The edges of a graph, indexed by edge ID, with read-only endpoints and
+/// mutable data. See [RawParts](struct.RawParts.html).
+pub struct RawEdges<'a, A> {
+  arcs: &'a mut [RawEdge<A>],
+  touched: Touched,
+}
+
+impl<'a, A> RawEdges<'a, A> {
+  /// Returns the number of edges.
+  pub fn len(&self) -> usize {
+    self.arcs.len()
+  }
+
+  /// Returns true iff there are no edges.
+  pub fn is_empty(&self) -> bool {
+    self.arcs.is_empty()
+  }
+
+  /// Returns the ID of the source vertex of edge `id`. Panics if there is no
+  /// such edge.
+  pub fn source(&self, id: usize) -> usize {
+    self.arcs[id].source.as_usize()
+  }
+
+  /// Returns the ID of the target vertex of edge `id`. Panics if there is no
+  /// such edge.
+  pub fn target(&self, id: usize) -> usize {
+    self.arcs[id].target.as_usize()
+  }
+
+  /// Returns the data of edge `id`. Panics if there is no such edge.
+  pub fn data(&self, id: usize) -> &A {
+    &self.arcs[id].data
+  }
+
+  /// Returns the data of edge `id`, mutably. Panics if there is no such edge.
+  pub fn data_mut(&mut self, id: usize) -> &mut A {
+    let data = &mut self.arcs[id].data;
+    self.touched.ids.push(id);
+    data
+  }
+
+  /// Returns an iterator over the source vertex ID, target vertex ID, and data
+  /// of each edge, in order of edge ID.
+  pub fn iter(&self) -> impl ExactSizeIterator<Item = (usize, usize, &A)> + DoubleEndedIterator {
+    self
+      .arcs
+      .iter()
+      .map(|arc| (arc.source.as_usize(), arc.target.as_usize(), &arc.data))
+  }
+
+  /// Returns an iterator over the source vertex ID, target vertex ID, and
+  /// mutable data of each edge, in order of edge ID.
+  pub fn iter_mut(
+    &mut self,
+  ) -> impl ExactSizeIterator<Item = (usize, usize, &mut A)> + DoubleEndedIterator {
+    self.touched.all = true;
+    self
+      .arcs
+      .iter_mut()
+      .map(|arc| (arc.source.as_usize(), arc.target.as_usize(), &mut arc.data))
+  }
+}
+
+impl<T: Hash + Eq + Clone, S, A> Graph<T, S, A> {
+  /// Calls `f` with bulk access to the data of every vertex and edge in this
+  /// graph, as described in the [raw](raw/index.html) module, and returns its
+  /// result. Vertex and edge IDs are as returned by `get_id` on node and edge
+  /// handles.
+  ///
+  /// If `f` panics, the data that it borrowed mutably is recorded as modified
+  /// before the panic resumes.
+  pub fn with_raw_parts_mut<R, F>(&mut self, f: F) -> R
+  where
+    F: FnOnce(&mut RawParts<S, A>) -> R,
+  {
+    let mut parts = RawParts {
+      vertices: RawVertices {
+        vertices: &mut self.vertices,
+        touched: Default::default(),
+      },
+      edges: RawEdges {
+        arcs: &mut self.arcs,
+        touched: Default::default(),
+      },
+    };
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(|| f(&mut parts)));
+    let RawParts { vertices, edges } = parts;
+    let (vertices, arcs) = (vertices.touched, edges.touched);
+    self.apply_touched(vertices, arcs);
+    match result {
+      Ok(result) => result,
+      Err(payload) => panic::resume_unwind(payload),
+    }
+  }
+
+  /// Records the vertices and edges whose data was borrowed mutably through a
+  /// [RawParts](raw/struct.RawParts.html) as modified.
+  fn apply_touched(&mut self, vertices: Touched, arcs: Touched) {
+    let vertices = vertices.ids(self.vertices.len());
+    let arcs = arcs.ids(self.arcs.len());
+    for id in vertices {
+      self.touch_vertex(VertexId(id));
+      self.touch_data(VertexId(id));
+    }
+    let rebuild_heaps = arcs.len() == self.arcs.len();
+    for id in arcs {
+      self.touch_arc(EdgeId(id));
+      self.touch_edge_data(EdgeId(id));
+      if !rebuild_heaps {
+        if let Some(ref mut heaps) = self.child_heaps {
+          let arc = &self.arcs[id];
+          heaps.update(arc.source, EdgeId(id), &arc.data);
+        }
+      }
+    }
+    if rebuild_heaps {
+      if let Some(ref mut heaps) = self.child_heaps {
+        heaps.rebuild(&self.vertices, &self.arcs);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::RawParts;
+  use crate::config::GraphConfig;
+
+  type Graph = crate::Graph<&'static str, u32, u32>;
+
+  #[test]
+  fn with_raw_parts_mut_ok() {
+    let mut g = Graph::with_config(GraphConfig {
+      track_recency: true,
+      ..Default::default()
+    });
+    g.add_edge("root", |_| 1, "a", |_| 2, 10);
+    g.add_edge("root", |_| 1, "b", |_| 3, 20);
+    g.add_edge("a", |_| 2, "b", |_| 3, 30);
+    g.order_children_by(|data| *data as u64);
+    let children = g.with_raw_parts_mut(|parts| {
+      assert_eq!(3, parts.vertices.len());
+      assert_eq!(3, parts.edges.len());
+      *parts.vertices.data_mut(1) += 100;
+      *parts.edges.data_mut(0) = 50;
+      let RawParts { vertices, edges } = parts;
+      for (source, _, data) in edges.iter() {
+        assert!(*data >= *vertices.data(source) / 100);
+      }
+      vertices.children(0).collect::<Vec<_>>()
+    });
+    assert_eq!(vec![0, 1], children);
+    assert_eq!(102, *g.find_node(&"a").unwrap().get_data());
+    let root = g.find_node(&"root").unwrap();
+    assert_eq!(50, *root.get_child_list().best_by_key().unwrap().get_data());
+    let (a, b) = (
+      g.find_node(&"a").unwrap().get_recency().unwrap(),
+      g.find_node(&"b").unwrap().get_recency().unwrap(),
+    );
+    assert!(a.touched > b.touched);
+    assert_eq!(b.created, b.touched);
+  }
+
+  #[test]
+  fn with_raw_parts_mut_iter_ok() {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| 1, "a", |_| 2, 10);
+    g.add_edge("root", |_| 1, "b", |_| 3, 20);
+    g.order_children_by(|data| *data as u64);
+    let snapshot = g.snapshot();
+    g.with_raw_parts_mut(|parts| {
+      for data in parts.vertices.iter_mut() {
+        *data *= 2;
+      }
+      for (_, target, data) in parts.edges.iter_mut().rev() {
+        *data = 100 - target as u32;
+      }
+      assert_eq!(vec![&2, &4, &6], parts.vertices.iter().collect::<Vec<_>>());
+    });
+    let root = g.find_node(&"root").unwrap();
+    assert_eq!(99, *root.get_child_list().best_by_key().unwrap().get_data());
+    assert_eq!(1, *snapshot.find_node(&"root").unwrap().get_data());
+    assert_eq!(2, *g.snapshot().find_node(&"root").unwrap().get_data());
+  }
+
+  #[test]
+  fn with_raw_parts_mut_panic_ok() {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| 1, "a", |_| 2, 10);
+    g.add_edge("root", |_| 1, "b", |_| 3, 20);
+    g.order_children_by(|data| *data as u64);
+    let snapshot = g.snapshot();
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+      g.with_raw_parts_mut(|parts| {
+        *parts.vertices.data_mut(0) = 5;
+        *parts.edges.data_mut(0) = 30;
+        panic!("raw");
+      })
+    }));
+    assert!(result.is_err());
+    let root = g.find_node(&"root").unwrap();
+    assert_eq!(5, *root.get_data());
+    assert_eq!(30, *root.get_child_list().best_by_key().unwrap().get_data());
+    assert_eq!(1, *snapshot.find_node(&"root").unwrap().get_data());
+    assert_eq!(5, *g.snapshot().find_node(&"root").unwrap().get_data());
+    assert!(crate::testing::invariant_violations(&g).is_empty());
+  }
+}
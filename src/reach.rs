@@ -0,0 +1,267 @@
+//! A precomputed index that answers reachability queries in constant time.
+//!
+//! [Node::is_ancestor_of](../nav/struct.Node.html#method.is_ancestor_of)
+//! searches the graph on every call. When many such queries are made on a
+//! graph that is not changing (e.g., while analyzing a finished search), a
+//! [ReachabilityIndex](struct.ReachabilityIndex.html) built with
+//! [Graph::build_reachability_index](../struct.Graph.html#method.build_reachability_index)
+//! answers each of them with a single bit lookup.
+//!
+//! The index stores a bitset over all vertices for each strongly connected
+//! component of the graph, so it needs up to `n * n / 8` bytes for a graph
+//! with `n` vertices, and takes time proportional to that to build. It
+//! describes the graph as it was when it was built: queries panic if vertices
+//! or edges have since been added or the graph has been garbage-collected.
+//!
+//! ```rust
+//! # use search_graph::Graph;
+//! # fn main() {
+//! let mut g: Graph<&str, (), ()> = Graph::new();
+//! g.add_edge("root", |_| (), "a", |_| (), ());
+//! g.add_edge("a", |_| (), "b", |_| (), ());
+//! g.add_node("c", ());
+//! let index = g.build_reachability_index();
+//! let node = |state| g.find_node(&state).unwrap();
+//! assert!(index.is_ancestor(&node("root"), &node("b")));
+//! assert!(!index.is_ancestor(&node("b"), &node("root")));
+//! assert!(!index.is_ancestor(&node("root"), &node("c")));
+//! # }
+//! ```
+
+use std::hash::Hash;
+
+use crate::nav::Node;
+use crate::Graph;
+use symbol_map::SymbolId;
+
+/// Which vertices of a graph are reachable from which others, as of when the
+/// index was built.
+#[derive(Clone, Debug)]
+pub struct ReachabilityIndex {
+  graph: usize,
+  version: u64,
+  edge_count: usize,
+  /// The strongly connected component of each vertex.
+  components: Vec<usize>,
+  /// The number of `u64` words in each row.
+  words: usize,
+  /// For each component, the set of vertices reachable from its vertices.
+  rows: Vec<u64>,
+}
+
+impl<T: Hash + Eq + Clone, S, A> Graph<T, S, A> {
+  /// Returns an index of which vertices are reachable from which others in
+  /// this graph, as described in the [reach](reach/index.html) module.
+  pub fn build_reachability_index(&self) -> ReachabilityIndex {
+    let (components, count) = self.strong_components();
+    let n = self.vertices.len();
+    let words = n.div_ceil(64);
+    let mut members: Vec<Vec<usize>> = vec![Vec::new(); count];
+    for (v, c) in components.iter().enumerate() {
+      members[*c].push(v);
+    }
+    let mut rows = vec![0u64; count * words];
+    // Components are numbered so that edges between them lead to
+    // lower-numbered components, whose rows are already complete.
+    for (c, vertices) in members.iter().enumerate() {
+      let (done, rest) = rows.split_at_mut(c * words);
+      let row = &mut rest[..words];
+      let mut cyclic = false;
+      for v in vertices {
+        for edge_id in self.vertices[*v].children.iter() {
+          let w = self.arcs[edge_id.as_usize()].target.as_usize();
+          row[w / 64] |= 1 << (w % 64);
+          if components[w] == c {
+            cyclic = true;
+          } else {
+            let other = &done[components[w] * words..(components[w] + 1) * words];
+            for (a, b) in row.iter_mut().zip(other) {
+              *a |= *b;
+            }
+          }
+        }
+      }
+      if cyclic {
+        for v in vertices {
+          row[v / 64] |= 1 << (v % 64);
+        }
+      }
+    }
+    ReachabilityIndex {
+      graph: self.address(),
+      version: self.version,
+      edge_count: self.arcs.len(),
+      components,
+      words,
+      rows,
+    }
+  }
+
+  /// Returns the strongly connected component of each vertex and the number
+  /// of components, using Tarjan's algorithm. Components are numbered in the
+  /// order in which they are completed, so every edge between two components
+  /// leads from a higher-numbered one to a lower-numbered one.
+  fn strong_components(&self) -> (Vec<usize>, usize) {
+    const UNVISITED: usize = usize::MAX;
+    let n = self.vertices.len();
+    let mut index = vec![UNVISITED; n];
+    let mut lowlink = vec![0; n];
+    let mut on_stack = vec![false; n];
+    let mut components = vec![0; n];
+    let mut stack = Vec::new();
+    // Vertices whose children are being visited, with the position of the
+    // next child to visit.
+    let mut calls: Vec<(usize, usize)> = Vec::new();
+    let mut next_index = 0;
+    let mut count = 0;
+    for root in 0..n {
+      if index[root] != UNVISITED {
+        continue;
+      }
+      index[root] = next_index;
+      lowlink[root] = next_index;
+      next_index += 1;
+      stack.push(root);
+      on_stack[root] = true;
+      calls.push((root, 0));
+      while let Some((v, position)) = calls.last().cloned() {
+        let children = &self.vertices[v].children;
+        if position < children.len() {
+          calls.last_mut().unwrap().1 += 1;
+          let w = self.arcs[children[position].as_usize()].target.as_usize();
+          if index[w] == UNVISITED {
+            index[w] = next_index;
+            lowlink[w] = next_index;
+            next_index += 1;
+            stack.push(w);
+            on_stack[w] = true;
+            calls.push((w, 0));
+          } else if on_stack[w] {
+            lowlink[v] = lowlink[v].min(index[w]);
+          }
+          continue;
+        }
+        calls.pop();
+        if let Some((u, _)) = calls.last() {
+          lowlink[*u] = lowlink[*u].min(lowlink[v]);
+        }
+        if lowlink[v] == index[v] {
+          loop {
+            let w = stack.pop().unwrap();
+            on_stack[w] = false;
+            components[w] = count;
+            if w == v {
+              break;
+            }
+          }
+          count += 1;
+        }
+      }
+    }
+    (components, count)
+  }
+}
+
+impl ReachabilityIndex {
+  /// Returns true iff this index still describes `graph`: it was built from
+  /// `graph`, which has not been garbage-collected and has had no vertices or
+  /// edges added since.
+  pub fn is_current<T: Hash + Eq + Clone, S, A>(&self, graph: &Graph<T, S, A>) -> bool {
+    self.graph == graph.address()
+      && self.version == graph.version
+      && self.components.len() == graph.vertices.len()
+      && self.edge_count == graph.arcs.len()
+  }
+
+  /// Returns true iff `descendant` can be reached from `ancestor` by following
+  /// one or more child edges, as with
+  /// [Node::is_ancestor_of](../nav/struct.Node.html#method.is_ancestor_of).
+  ///
+  /// Panics if the nodes do not belong to the graph that this index was built
+  /// from, or the index is not [current](#method.is_current).
+  pub fn is_ancestor<T, S, A>(&self, ancestor: &Node<T, S, A>, descendant: &Node<T, S, A>) -> bool
+  where
+    T: Hash + Eq + Clone,
+  {
+    assert!(
+      self.is_current(ancestor.graph) && self.is_current(descendant.graph),
+      "reachability index is not current"
+    );
+    let (from, to) = (ancestor.id.as_usize(), descendant.id.as_usize());
+    let row = self.components[from] * self.words;
+    self.rows[row + to / 64] & (1 << (to % 64)) != 0
+  }
+
+  /// Returns the number of strongly connected components in the graph that
+  /// this index was built from. Vertices in the same component can all reach
+  /// one another.
+  pub fn component_count(&self) -> usize {
+    self.rows.len().checked_div(self.words).unwrap_or(0)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  type Graph = crate::Graph<u32, (), ()>;
+
+  fn add_edges(g: &mut Graph, edges: &[(u32, u32)]) {
+    for (source, target) in edges {
+      g.add_edge(*source, |_| (), *target, |_| (), ());
+    }
+  }
+
+  #[test]
+  fn build_reachability_index_ok() {
+    let mut g = Graph::new();
+    // 1, 2, and 3 form a cycle; 5 has a self loop.
+    add_edges(
+      &mut g,
+      &[
+        (0, 1),
+        (1, 2),
+        (2, 3),
+        (3, 1),
+        (3, 4),
+        (0, 5),
+        (5, 5),
+        (6, 0),
+      ],
+    );
+    for i in 7..200 {
+      add_edges(&mut g, &[(i - 1, i)]);
+    }
+    let index = g.build_reachability_index();
+    assert_eq!(198, index.component_count());
+    let node = |state: u32| g.find_node(&state).unwrap();
+    for a in 0..200 {
+      for b in 0..200 {
+        assert_eq!(
+          node(a).is_ancestor_of(&node(b)),
+          index.is_ancestor(&node(a), &node(b)),
+          "{} -> {}",
+          a,
+          b
+        );
+      }
+    }
+    assert!(index.is_ancestor(&node(2), &node(2)));
+    assert!(index.is_ancestor(&node(5), &node(5)));
+    assert!(!index.is_ancestor(&node(4), &node(4)));
+    assert!(index.is_ancestor(&node(6), &node(4)));
+    assert!(!index.is_ancestor(&node(199), &node(6)));
+    assert!(index.is_current(&g));
+    assert_eq!(0, Graph::new().build_reachability_index().component_count());
+  }
+
+  #[test]
+  #[should_panic(expected = "reachability index is not current")]
+  fn build_reachability_index_err() {
+    let mut g = Graph::new();
+    add_edges(&mut g, &[(0, 1)]);
+    let index = g.build_reachability_index();
+    add_edges(&mut g, &[(1, 0)]);
+    assert!(!index.is_current(&g));
+    let node = |state: u32| g.find_node(&state).unwrap();
+    index.is_ancestor(&node(1), &node(0));
+  }
+}
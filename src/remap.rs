@@ -0,0 +1,235 @@
+//! Tables that describe how garbage collection reassigned vertex and edge IDs.
+//!
+//! Garbage collection (e.g., with
+//! [View::retain_reachable_from](../view/struct.View.html#method.retain_reachable_from))
+//! compacts a graph, so the IDs of the vertices and edges that survive it
+//! change. A graph retains a [Remap](struct.Remap.html) for its most recent
+//! collection, which may be used to translate IDs that were recorded before
+//! the collection (e.g., those returned by `get_id` on node and edge handles).
+//!
+//! Collection preserves the relative order of the child edges of each vertex
+//! that survives (unless they are explicitly reordered), but it may drop some
+//! of them, which shifts the positions of those that follow.
+//! [Remap::child](struct.Remap.html#method.child) translates the position of
+//! a child edge among its siblings, so caches of child positions (e.g., the
+//! index of the best child found so far) can be carried across a collection.
+//!
+//! Side structures that are keyed by vertex or edge IDs may instead implement
+//! [Remappable](trait.Remappable.html) and be passed to
+//! [Graph::compact_with](../struct.Graph.html#method.compact_with), which
+//! rewrites them as part of garbage collection.
+//!
+//! ```rust
+//! # use std::collections::HashMap;
+//! # use search_graph::Graph;
+//! # use search_graph::remap::{Remap, Remappable};
+//! /// Notes about vertices, keyed by vertex ID.
+//! struct Notes(HashMap<usize, &'static str>);
+//!
+//! impl Remappable for Notes {
+//!   fn remap(&mut self, remap: &Remap) {
+//!     self.0 = self
+//!       .0
+//!       .drain()
+//!       .filter_map(|(id, note)| remap.vertex(id).map(|id| (id, note)))
+//!       .collect();
+//!   }
+//! }
+//!
+//! # fn main() {
+//! let mut g: Graph<&str, (), ()> = Graph::new();
+//! g.add_edge("root", |_| (), "a", |_| (), ());
+//! let mut notes = Notes(HashMap::new());
+//! notes.0.insert(g.find_node(&"root").unwrap().get_id(), "start");
+//! notes.0.insert(g.find_node(&"a").unwrap().get_id(), "interesting");
+//! g.compact_with(&["a"], &mut [&mut notes]);
+//! assert_eq!(notes.0.len(), 1);
+//! assert_eq!(notes.0[&g.find_node(&"a").unwrap().get_id()], "interesting");
+//! # }
+//! ```
+
+use std::hash::Hash;
+
+use crate::base::{EdgeId, VertexId};
+use crate::mark_compact::Collector;
+use crate::Graph;
+use symbol_map::SymbolId;
+
+/// The mapping from old to new IDs produced by one garbage collection.
+#[derive(Clone, Debug)]
+pub struct Remap {
+  /// The graph version before the collection.
+  pub(crate) from_version: u64,
+  /// Indexed by old `VertexId`.
+  pub(crate) vertices: Vec<Option<VertexId>>,
+  /// Indexed by old `EdgeId`.
+  pub(crate) edges: Vec<Option<EdgeId>>,
+  /// Indexed by old `VertexId`. The start and length of the range of
+  /// `child_positions` that describes the vertex's old child edges.
+  pub(crate) child_ranges: Vec<(usize, usize)>,
+  /// The new position of each old child edge among its siblings.
+  pub(crate) child_positions: Vec<Option<usize>>,
+}
+
+impl Remap {
+  /// Returns the graph version before the collection.
+  pub fn from_version(&self) -> u64 {
+    self.from_version
+  }
+
+  /// Returns the graph version after the collection.
+  pub fn to_version(&self) -> u64 {
+    self.from_version + 1
+  }
+
+  /// Returns the new ID of the vertex that had ID `old`, or `None` if it was
+  /// dropped (or `old` is out of bounds).
+  pub fn vertex(&self, old: usize) -> Option<usize> {
+    self
+      .vertices
+      .get(old)
+      .cloned()
+      .flatten()
+      .map(|id| id.as_usize())
+  }
+
+  /// Returns the new ID of the edge that had ID `old`, or `None` if it was
+  /// dropped (or `old` is out of bounds).
+  pub fn edge(&self, old: usize) -> Option<usize> {
+    self
+      .edges
+      .get(old)
+      .cloned()
+      .flatten()
+      .map(|id| id.as_usize())
+  }
+
+  /// Returns the new position among the children of its source of the edge
+  /// that was child `old_index` of the vertex that had ID `old_vertex`, or
+  /// `None` if the edge or the vertex was dropped (or either is out of
+  /// bounds). The new ID of the vertex is given by [vertex](#method.vertex).
+  pub fn child(&self, old_vertex: usize, old_index: usize) -> Option<usize> {
+    let (start, len) = *self.child_ranges.get(old_vertex)?;
+    if old_index < len {
+      self.child_positions[start + old_index]
+    } else {
+      None
+    }
+  }
+
+  pub(crate) fn vertex_id(&self, old: VertexId) -> Option<VertexId> {
+    self.vertices.get(old.as_usize()).cloned().flatten()
+  }
+
+  pub(crate) fn edge_id(&self, old: EdgeId) -> Option<EdgeId> {
+    self.edges.get(old.as_usize()).cloned().flatten()
+  }
+}
+
+/// Structures outside of a graph that hold its vertex or edge IDs, and can
+/// rewrite them when garbage collection reassigns those IDs.
+pub trait Remappable {
+  /// Rewrites the IDs held by this structure according to `remap`. IDs for
+  /// which `remap` returns `None` refer to dropped graph elements.
+  fn remap(&mut self, remap: &Remap);
+}
+
+impl<T: Hash + Eq + Clone, S, A> Graph<T, S, A> {
+  /// Garbage-collects this graph, retaining only the components that are
+  /// reachable from the vertices for `roots`, and rewrites the IDs held by each
+  /// element of `maps` before returning. Game states in `roots` that are not
+  /// in the graph are ignored.
+  pub fn compact_with(&mut self, roots: &[T], maps: &mut [&mut dyn Remappable]) {
+    let root_ids: Vec<VertexId> = roots
+      .iter()
+      .filter_map(|state| self.lookup(state))
+      .collect();
+    Collector::retain_reachable_with(self, &root_ids, maps);
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::{Remap, Remappable};
+
+  type Graph = crate::Graph<&'static str, &'static str, &'static str>;
+
+  /// Per-edge annotations, indexed by edge ID.
+  struct EdgeNotes(Vec<Option<&'static str>>);
+
+  impl Remappable for EdgeNotes {
+    fn remap(&mut self, remap: &Remap) {
+      let mut notes = Vec::new();
+      for (id, note) in self.0.drain(..).enumerate() {
+        if let Some(new_id) = remap.edge(id) {
+          if notes.len() <= new_id {
+            notes.resize(new_id + 1, None);
+          }
+          notes[new_id] = note;
+        }
+      }
+      self.0 = notes;
+    }
+  }
+
+  #[test]
+  fn compact_with_ok() {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| "root_data", "a", |_| "a_data", "root_a");
+    g.add_edge("root", |_| "root_data", "b", |_| "b_data", "root_b");
+    g.add_edge("b", |_| "b_data", "c", |_| "c_data", "b_c");
+    let mut notes = EdgeNotes(vec![Some("first"), None, Some("third")]);
+    assert!(g.last_remap().is_none());
+    g.compact_with(&["b", "missing"], &mut [&mut notes]);
+    assert_eq!(2, g.vertex_count());
+    assert_eq!(1, g.edge_count());
+    assert_eq!(vec![Some("third")], notes.0);
+    let remap = g.last_remap().unwrap();
+    assert_eq!(1, remap.to_version());
+    assert_eq!(None, remap.vertex(0));
+    assert_eq!(Some(0), remap.vertex(2));
+    assert!(crate::testing::invariant_violations(&g).is_empty());
+  }
+
+  fn make_fan() -> Graph {
+    let mut g = Graph::new();
+    for (child, data) in [("a", "3"), ("b", "1"), ("c", "2"), ("d", "0")].iter() {
+      g.add_edge("root", |_| "root_data", *child, |_| "", *data);
+    }
+    g
+  }
+
+  #[test]
+  fn child_ok() {
+    let mut g = make_fan();
+    g.retain_edges(|e| *e.get_data() != "1");
+    let remap = g.last_remap().unwrap();
+    assert_eq!(Some(0), remap.child(0, 0));
+    assert_eq!(None, remap.child(0, 1));
+    assert_eq!(Some(1), remap.child(0, 2));
+    assert_eq!(Some(2), remap.child(0, 3));
+    assert_eq!(None, remap.child(0, 4));
+    assert_eq!(None, remap.child(5, 0));
+
+    let mut g = make_fan();
+    g.retain_nodes(|n| *n.get_label() != "c");
+    let remap = g.last_remap().unwrap();
+    assert_eq!(Some(1), remap.child(0, 1));
+    assert_eq!(None, remap.child(0, 2));
+    assert_eq!(Some(2), remap.child(0, 3));
+
+    let mut g = make_fan();
+    g.compact_with(&["a"], &mut []);
+    let remap = g.last_remap().unwrap();
+    assert_eq!(None, remap.child(0, 0));
+
+    let mut g = make_fan();
+    crate::view::of_graph(&mut g, |v| {
+      let root = v.find_node(&"root").unwrap();
+      v.retain_reachable_from_sorted_by_key(Some(root), |data| *data);
+    });
+    let remap = g.last_remap().unwrap();
+    let positions: Vec<Option<usize>> = (0..4).map(|i| remap.child(0, i)).collect();
+    assert_eq!(vec![Some(3), Some(1), Some(2), Some(0)], positions);
+  }
+}
@@ -0,0 +1,261 @@
+//! Common formulas for choosing which edge to follow during search.
+//!
+//! The formulas are provided as plain functions of `f64` statistics
+//! ([ucb1](fn.ucb1.html), [puct](fn.puct.html), and
+//! [softmax](fn.softmax.html)), and as functions that apply them to the child
+//! edges of a vertex ([select_ucb1](fn.select_ucb1.html),
+//! [select_puct](fn.select_puct.html), and
+//! [sample_softmax](fn.sample_softmax.html)). The latter extract an
+//! [EdgeStats](struct.EdgeStats.html) from each child edge with a closure, so
+//! statistics may be kept in edge data, in the data of the edge's target, or
+//! both. They are generic over [IVertex](../nav_types/trait.IVertex.html), so
+//! they accept handles into a `Graph` or a snapshot of one.
+//!
+//! Values are from the perspective of the player choosing an edge, so higher
+//! values are better.
+//!
+//! This module is only available when the `score` feature is enabled.
+//!
+//! ```rust
+//! # use search_graph::Graph;
+//! # use search_graph::score::{select_ucb1, EdgeStats};
+//! # fn main() {
+//! // Edge data is (visits, total value).
+//! let mut g: Graph<&str, (), (u64, f64)> = Graph::new();
+//! g.add_edge("root", |_| (), "a", |_| (), (10, 6.0));
+//! g.add_edge("root", |_| (), "b", |_| (), (2, 1.0));
+//! g.add_edge("root", |_| (), "c", |_| (), (0, 0.0));
+//! let root = g.find_node(&"root").unwrap();
+//! let stats = |e: &search_graph::nav::Edge<_, _, _>| {
+//!   let (visits, total) = *e.get_data();
+//!   EdgeStats::new(visits, total / visits.max(1) as f64, 0.0)
+//! };
+//! // Unvisited edges are chosen first.
+//! assert_eq!(select_ucb1(&root, 1.4, stats), Some(2));
+//! # }
+//! ```
+
+use crate::nav_types::{IChildren, IVertex};
+
+/// Search statistics about an edge, as used by the functions in this module.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct EdgeStats {
+  /// The number of times the edge has been traversed.
+  pub visits: u64,
+  /// The mean value of the edge's traversals.
+  pub value: f64,
+  /// The prior probability of choosing the edge, as from a policy.
+  pub prior: f64,
+}
+
+impl EdgeStats {
+  /// Creates statistics with the given visit count, mean value, and prior.
+  pub fn new(visits: u64, value: f64, prior: f64) -> Self {
+    EdgeStats {
+      visits,
+      value,
+      prior,
+    }
+  }
+}
+
+/// Returns the UCB1 score of an edge that has been traversed `visits` times
+/// with mean value `value`, when its siblings have been traversed
+/// `total_visits` times in all: `value + c * sqrt(ln(total_visits) /
+/// visits)`. Edges that have not been traversed score positive infinity.
+pub fn ucb1(total_visits: u64, visits: u64, value: f64, c: f64) -> f64 {
+  if visits == 0 {
+    return f64::INFINITY;
+  }
+  value + c * ((total_visits.max(1) as f64).ln() / visits as f64).sqrt()
+}
+
+/// Returns the PUCT score of an edge with prior probability `prior` that has
+/// been traversed `visits` times with mean value `value`, when its siblings
+/// have been traversed `total_visits` times in all: `value + c * prior *
+/// sqrt(total_visits) / (1 + visits)`.
+pub fn puct(total_visits: u64, visits: u64, value: f64, prior: f64, c: f64) -> f64 {
+  value + c * prior * (total_visits as f64).sqrt() / (1 + visits) as f64
+}
+
+/// Returns the probabilities of choosing each of `scores` under a softmax
+/// (Boltzmann) distribution with the given temperature, which must be
+/// positive. Lower temperatures concentrate probability on higher scores.
+/// Returns no probabilities if there are no scores.
+pub fn softmax(scores: &[f64], temperature: f64) -> Vec<f64> {
+  let max = scores.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+  let weights: Vec<f64> = scores
+    .iter()
+    .map(|s| ((s - max) / temperature).exp())
+    .collect();
+  let sum: f64 = weights.iter().sum();
+  weights.into_iter().map(|w| w / sum).collect()
+}
+
+/// Returns the index of the child edge of `vertex` with the highest
+/// [ucb1](fn.ucb1.html) score, or `None` if it has no children. Ties go to
+/// the edge with the lowest index.
+pub fn select_ucb1<V, F>(vertex: &V, c: f64, stats: F) -> Option<usize>
+where
+  V: IVertex,
+  F: FnMut(&V::Edge) -> EdgeStats,
+{
+  let stats = child_stats(vertex, stats);
+  let total: u64 = stats.iter().map(|s| s.visits).sum();
+  argmax(stats.iter().map(|s| ucb1(total, s.visits, s.value, c)))
+}
+
+/// Returns the index of the child edge of `vertex` with the highest
+/// [puct](fn.puct.html) score, or `None` if it has no children. Ties go to
+/// the edge with the lowest index.
+pub fn select_puct<V, F>(vertex: &V, c: f64, stats: F) -> Option<usize>
+where
+  V: IVertex,
+  F: FnMut(&V::Edge) -> EdgeStats,
+{
+  let stats = child_stats(vertex, stats);
+  let total: u64 = stats.iter().map(|s| s.visits).sum();
+  argmax(
+    stats
+      .iter()
+      .map(|s| puct(total, s.visits, s.value, s.prior, c)),
+  )
+}
+
+/// Returns the index of a child edge of `vertex` sampled from the
+/// [softmax](fn.softmax.html) distribution over the scores returned by
+/// `score`, or `None` if it has no children. Random numbers are drawn from
+/// `rng`, which should return uniformly distributed values.
+pub fn sample_softmax<V, F, R>(
+  vertex: &V,
+  temperature: f64,
+  mut score: F,
+  rng: &mut R,
+) -> Option<usize>
+where
+  V: IVertex,
+  F: FnMut(&V::Edge) -> f64,
+  R: FnMut() -> u64,
+{
+  let children = vertex.get_child_list();
+  let scores: Vec<f64> = children.iter().map(|e| score(&e)).collect();
+  let probabilities = softmax(&scores, temperature);
+  if probabilities.is_empty() {
+    return None;
+  }
+  // The top 53 bits give a uniform sample from [0, 1).
+  let mut x = (rng() >> 11) as f64 / (1u64 << 53) as f64;
+  for (i, p) in probabilities.iter().enumerate() {
+    if x < *p {
+      return Some(i);
+    }
+    x -= p;
+  }
+  Some(probabilities.len() - 1)
+}
+
+/// Returns the statistics of each child edge of `vertex`, in order.
+fn child_stats<V, F>(vertex: &V, mut stats: F) -> Vec<EdgeStats>
+where
+  V: IVertex,
+  F: FnMut(&V::Edge) -> EdgeStats,
+{
+  vertex.get_child_list().iter().map(|e| stats(&e)).collect()
+}
+
+/// Returns the index of the first greatest score, ignoring NaNs.
+fn argmax<I: Iterator<Item = f64>>(scores: I) -> Option<usize> {
+  let mut best: Option<(usize, f64)> = None;
+  for (i, score) in scores.enumerate() {
+    if !score.is_nan() && best.is_none_or(|(_, b)| score > b) {
+      best = Some((i, score));
+    }
+  }
+  best.map(|(i, _)| i)
+}
+
+#[cfg(test)]
+mod test {
+  use super::{puct, sample_softmax, select_puct, select_ucb1, softmax, ucb1, EdgeStats};
+
+  /// Edge data is (visits, mean value, prior).
+  type Graph = crate::Graph<&'static str, (), (u64, f64, f64)>;
+  type Edge<'a> = crate::nav::Edge<'a, &'static str, (), (u64, f64, f64)>;
+
+  fn make_graph() -> Graph {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| (), "a", |_| (), (8, 0.5, 0.1));
+    g.add_edge("root", |_| (), "b", |_| (), (1, 0.4, 0.7));
+    g.add_edge("root", |_| (), "c", |_| (), (1, 0.6, 0.2));
+    g.add_node("leaf", ());
+    g
+  }
+
+  fn stats(e: &Edge) -> EdgeStats {
+    let (visits, value, prior) = *e.get_data();
+    EdgeStats::new(visits, value, prior)
+  }
+
+  #[test]
+  fn formulas_ok() {
+    assert_eq!(f64::INFINITY, ucb1(10, 0, 0.0, 1.0));
+    assert!((ucb1(10, 2, 0.5, 2.0) - (0.5 + 2.0 * (10f64.ln() / 2.0).sqrt())).abs() < 1e-12);
+    assert_eq!(0.5 + 1.5 * 0.2 * 3.0 / 3.0, puct(9, 2, 0.5, 0.2, 1.5));
+    assert_eq!(0.25, puct(0, 0, 0.25, 1.0, 1.0));
+    let p = softmax(&[1.0, 1.0, 1.0 + 2f64.ln()], 1.0);
+    assert!((p[0] - 0.25).abs() < 1e-12);
+    assert!((p[2] - 0.5).abs() < 1e-12);
+    let p = softmax(&[1.0, 2.0], 0.01);
+    assert!(p[1] > 0.999);
+    assert!(softmax(&[], 1.0).is_empty());
+  }
+
+  #[test]
+  fn select_ok() {
+    let mut g = make_graph();
+    let root = g.find_node(&"root").unwrap();
+    // The least visited edge with the highest value wins under UCB1.
+    assert_eq!(Some(2), select_ucb1(&root, 1.0, stats));
+    // With no exploration, the highest value wins.
+    assert_eq!(Some(2), select_ucb1(&root, 0.0, stats));
+    // The high-prior edge wins under PUCT.
+    assert_eq!(Some(1), select_puct(&root, 1.0, stats));
+    assert_eq!(Some(2), select_puct(&root, 0.0, stats));
+    let snapshot = g.snapshot();
+    let root = crate::nav_types::INavTypes::find_vertex(&&snapshot, &"root").unwrap();
+    assert_eq!(
+      Some(1),
+      select_puct(&root, 1.0, |e| {
+        let (visits, value, prior) = *crate::nav_types::IEdge::get_data(e);
+        EdgeStats::new(visits, value, prior)
+      })
+    );
+    let leaf = g.find_node(&"leaf").unwrap();
+    assert_eq!(None, select_ucb1(&leaf, 1.0, stats));
+    assert_eq!(None, select_puct(&leaf, 1.0, stats));
+  }
+
+  #[test]
+  fn sample_softmax_ok() {
+    let g = make_graph();
+    let root = g.find_node(&"root").unwrap();
+    let mut state = 0x2545f4914f6cdd1du64;
+    let mut rng = || {
+      state ^= state << 13;
+      state ^= state >> 7;
+      state ^= state << 17;
+      state
+    };
+    let mut counts = [0; 3];
+    for _ in 0..1000 {
+      let score = |e: &Edge| e.get_data().2.ln();
+      counts[sample_softmax(&root, 1.0, score, &mut rng).unwrap()] += 1;
+    }
+    // Probabilities are the priors: 0.1, 0.7, and 0.2.
+    assert!(counts[0] > 50 && counts[0] < 150);
+    assert!(counts[1] > 630 && counts[1] < 770);
+    assert!(counts[2] > 150 && counts[2] < 250);
+    let leaf = g.find_node(&"leaf").unwrap();
+    assert_eq!(None, sample_softmax(&leaf, 1.0, |_| 0.0, &mut rng));
+  }
+}
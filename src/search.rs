@@ -3,9 +3,29 @@
 //! The main data structure in this module is `Stack`, which provides
 //! memory-safe construction of the path that is traversed when performing local
 //! search on a graph.
+//!
+//! A [Recorder](struct.Recorder.html) may be attached to a `Stack` to log the
+//! decisions that a search makes, so that they can be replayed over the graph
+//! afterwards (e.g., for post-mortem analysis or to animate the search in a
+//! user interface).
+//!
+//! Searches that select several leaves before evaluating any of them may seal
+//! the paths to those leaves, run rollouts from them on many threads at once,
+//! and back up the results along each path with
+//! [parallel_rollouts](fn.parallel_rollouts.html).
+//!
+//! Searches that look for a goal by always expanding the most promising
+//! vertex on their frontier (e.g., A* or greedy best-first search) may be
+//! driven by [best_first](fn.best_first.html), which yields the path that it
+//! finds as a `Stack`. [best_first_path](fn.best_first_path.html) searches a
+//! graph that has already been built in the same way, and is written against
+//! the traits in [nav_types](../nav_types/index.html), so that it may also
+//! search a snapshot.
 
 use std::clone::Clone;
 use std::cmp::Eq;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::error::Error;
 use std::fmt;
 use std::hash::Hash;
@@ -13,8 +33,10 @@ use std::iter::Iterator;
 
 use crate::base::{EdgeId, VertexId};
 use crate::mutators::MutNode;
-use crate::nav::{Edge, Node};
+use crate::nav::{Edge, Node, Path, PathError};
+use crate::nav_types::{IChildren, IEdge, INavTypes, IVertex};
 use crate::Graph;
+use symbol_map::SymbolId;
 
 /// Errors that may arise during search.
 #[derive(Debug)]
@@ -33,8 +55,110 @@ pub enum SearchError<E: Error> {
     /// The actual number of parents (which `requested_index` exceeds).
     parent_count: usize,
   },
+  /// A search operation selected a parent in a graph that does not track
+  /// incoming edges.
+  ParentsUntracked,
+  /// A search operation selected an edge to a vertex that already occurs on
+  /// the search path as many times as the path's repetition limit allows (see
+  /// [Stack::set_repetition_limit](struct.Stack.html#method.set_repetition_limit)).
+  RepetitionLimit {
+    /// The number of times the vertex already occurs on the path.
+    count: usize,
+  },
   /// A search operation encountered an error.
   SelectionError(E),
+  /// The region spilled from below the path head could not be reloaded (see
+  /// the [tier](../tier/index.html) module).
+  ReloadError(std::io::Error),
+}
+
+/// Errors that may arise when revalidating a
+/// [PathToken](struct.PathToken.html).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PathTokenError {
+  /// The token was sealed from a different graph.
+  ForeignGraph,
+  /// The graph has been garbage-collected more than once since the token was
+  /// sealed (or since it was last revalidated), so its path cannot be
+  /// translated.
+  Expired,
+  /// A vertex or edge on the token's path was dropped by garbage collection.
+  Collected,
+}
+
+impl fmt::Display for PathTokenError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      PathTokenError::ForeignGraph => write!(f, "Path token belongs to a different graph"),
+      PathTokenError::Expired => write!(f, "Path token predates the last garbage collection"),
+      PathTokenError::Collected => write!(f, "Path token refers to a collected graph element"),
+    }
+  }
+}
+
+impl Error for PathTokenError {}
+
+/// A search path that has been detached from its graph.
+///
+/// A `PathToken` is obtained with [Stack::seal](struct.Stack.html#method.seal)
+/// and records the graph version that it was sealed at. Garbage collection
+/// reassigns vertex and edge IDs, so a token must be passed through
+/// [Graph::revalidate](../struct.Graph.html#method.revalidate) (or
+/// [Stack::resume](struct.Stack.html#method.resume), which does so) before it
+/// is used again. Revalidation translates the path through the graph's most
+/// recent [Remap](../remap/struct.Remap.html), or reports that the path is no
+/// longer valid, instead of silently referring to the wrong graph elements.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct PathToken {
+  graph: usize,
+  version: u64,
+  path: Vec<EdgeId>,
+  head: VertexId,
+}
+
+impl PathToken {
+  /// Returns the number of elements in the path, including its head.
+  #[allow(clippy::len_without_is_empty)]
+  pub fn len(&self) -> usize {
+    self.path.len() + 1
+  }
+}
+
+impl<T: Hash + Eq + Clone, S, A> Graph<T, S, A> {
+  /// Checks that `token` is valid for the current state of this graph. If it
+  /// was sealed before the most recent garbage collection, it is translated
+  /// to use the IDs assigned by that collection.
+  ///
+  /// Returns an error if `token` was sealed from a different graph, if its
+  /// path cannot be translated because more than one garbage collection has
+  /// happened since it was sealed, or if any element of its path was dropped.
+  pub fn revalidate(&self, token: PathToken) -> Result<PathToken, PathTokenError> {
+    if token.graph != self.address() {
+      return Err(PathTokenError::ForeignGraph);
+    }
+    if token.version == self.version {
+      return Ok(token);
+    }
+    let remap = match self.last_remap {
+      Some(ref remap) if remap.from_version == token.version => remap,
+      _ => return Err(PathTokenError::Expired),
+    };
+    let head = remap
+      .vertex_id(token.head)
+      .ok_or(PathTokenError::Collected)?;
+    let path = token
+      .path
+      .iter()
+      .map(|id| remap.edge_id(*id))
+      .collect::<Option<Vec<EdgeId>>>()
+      .ok_or(PathTokenError::Collected)?;
+    Ok(PathToken {
+      graph: token.graph,
+      version: self.version,
+      path,
+      head,
+    })
+  }
 }
 
 /// Tracks the path through a graph that is followed when performing local search.
@@ -59,11 +183,19 @@ pub struct Stack<'a, T: 'a + Hash + Eq + Clone, S: 'a, A: 'a> {
   path: Vec<EdgeId>,
   /// The path head.
   head: VertexId,
+  /// The maximum number of times a vertex may occur on the path, if limited.
+  repetition_limit: Option<usize>,
+  /// The number of times each vertex occurs on the path, kept only while
+  /// repetitions are limited.
+  repetitions: HashMap<VertexId, usize>,
+  /// The log of traversals, if one is attached.
+  recorder: Option<Recorder>,
 }
 
 /// Indicates which edge of a vertex to traverse. Edges are denoted by a 0-based
 /// index. This type is used by functions provided during graph search to
 /// indicate which child or parent edges to traverse.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum Traversal {
   /// Traverse the given child.
   Child(usize),
@@ -71,6 +203,176 @@ pub enum Traversal {
   Parent(usize),
 }
 
+/// A decision logged by a [Recorder](struct.Recorder.html).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Decision {
+  /// An edge was pushed onto the path.
+  Push {
+    /// The ID of the vertex that was the head before the edge was pushed.
+    node: usize,
+    /// The edge of `node` that was traversed.
+    traversal: Traversal,
+    /// The reason tag given by the search (see
+    /// [Stack::push_tagged](struct.Stack.html#method.push_tagged)), or 0 if
+    /// none was given.
+    reason: u32,
+  },
+  /// The edge with the given ID was popped from the path.
+  Pop(usize),
+}
+
+/// A log of the traversal decisions made by a search.
+///
+/// A `Recorder` is attached to a `Stack` with
+/// [Stack::attach_recorder](struct.Stack.html#method.attach_recorder). While
+/// it is attached, every edge that is pushed onto or popped from the path is
+/// logged, in order. Decisions not to traverse any edge, and pushes that fail,
+/// are not logged.
+///
+/// Decisions refer to vertices and edges by ID, so a log may be replayed with
+/// [replay](struct.Recorder.html#method.replay) over the graph it was
+/// recorded on for as long as those IDs are valid (i.e., until the graph is
+/// garbage-collected or its elements are otherwise removed).
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Recorder {
+  decisions: Vec<Decision>,
+}
+
+/// A logged decision, resolved against a graph by
+/// [Recorder::replay](struct.Recorder.html#method.replay).
+pub enum Replayed<'a, T: 'a + Hash + Eq + Clone, S: 'a, A: 'a> {
+  /// An edge was pushed onto the path.
+  Push {
+    /// The edge that was traversed.
+    edge: Edge<'a, T, S, A>,
+    /// Whether the edge was traversed to a child or to a parent.
+    traversal: Traversal,
+    /// The reason tag given by the search.
+    reason: u32,
+  },
+  /// An edge was popped from the path.
+  Pop(Edge<'a, T, S, A>),
+}
+
+/// Errors that may arise when replaying a [Recorder](struct.Recorder.html).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ReplayError {
+  /// The index of the first decision that does not resolve in the graph.
+  pub decision: usize,
+}
+
+impl fmt::Display for ReplayError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(
+      f,
+      "Recorded decision {} does not resolve in graph",
+      self.decision
+    )
+  }
+}
+
+impl Error for ReplayError {}
+
+impl Recorder {
+  /// Creates an empty log.
+  pub fn new() -> Self {
+    Recorder::default()
+  }
+
+  /// Returns the logged decisions, oldest first.
+  pub fn decisions(&self) -> &[Decision] {
+    &self.decisions
+  }
+
+  /// Returns the number of logged decisions.
+  pub fn len(&self) -> usize {
+    self.decisions.len()
+  }
+
+  /// Returns true iff no decisions have been logged.
+  pub fn is_empty(&self) -> bool {
+    self.decisions.is_empty()
+  }
+
+  /// Discards all logged decisions.
+  pub fn clear(&mut self) {
+    self.decisions.clear()
+  }
+
+  /// Resolves the logged decisions against `graph`, in order. Returns an
+  /// error that identifies the first decision which refers to a vertex, edge,
+  /// or edge index that does not exist in `graph`.
+  pub fn replay<'a, T: Hash + Eq + Clone, S, A>(
+    &self,
+    graph: &'a Graph<T, S, A>,
+  ) -> Result<Vec<Replayed<'a, T, S, A>>, ReplayError> {
+    let mut replayed = Vec::with_capacity(self.decisions.len());
+    for (i, decision) in self.decisions.iter().enumerate() {
+      let error = ReplayError { decision: i };
+      match *decision {
+        Decision::Push {
+          node,
+          traversal,
+          reason,
+        } => {
+          if node >= graph.vertices.len() {
+            return Err(error);
+          }
+          let node = Node::new(graph, VertexId(node));
+          let edge = match traversal {
+            Traversal::Child(i) => {
+              let children = node.get_child_list();
+              if i >= children.len() {
+                return Err(error);
+              }
+              children.get_edge(i)
+            }
+            Traversal::Parent(i) => match node.try_get_parent_list() {
+              Ok(ref parents) if i < parents.len() => parents.get_edge(i),
+              _ => return Err(error),
+            },
+          };
+          replayed.push(Replayed::Push {
+            edge,
+            traversal,
+            reason,
+          });
+        }
+        Decision::Pop(edge) => {
+          if edge >= graph.arcs.len() {
+            return Err(error);
+          }
+          replayed.push(Replayed::Pop(Edge::new(graph, EdgeId(edge))));
+        }
+      }
+    }
+    Ok(replayed)
+  }
+}
+
+/// Moves the head of a path along `edge` to `target`, if `limit` allows
+/// another occurrence of `target` on it. Takes the fields of a `Stack`
+/// separately so that its graph may remain borrowed.
+fn advance<E: Error>(
+  path: &mut Vec<EdgeId>,
+  head: &mut VertexId,
+  repetitions: &mut HashMap<VertexId, usize>,
+  limit: Option<usize>,
+  edge: EdgeId,
+  target: VertexId,
+) -> Result<(), SearchError<E>> {
+  if let Some(limit) = limit {
+    let count = repetitions.entry(target).or_insert(0);
+    if *count >= limit {
+      return Err(SearchError::RepetitionLimit { count: *count });
+    }
+    *count += 1;
+  }
+  path.push(edge);
+  *head = target;
+  Ok(())
+}
+
 /// Iterates over elements of a search path, in the order in which they were
 /// traversed, ending with the head.
 pub struct StackIter<'a, 's, T: 'a + Hash + Eq + Clone, S: 'a, A: 'a>
@@ -83,6 +385,28 @@ where
   position: usize,
 }
 
+/// Unwinds a search path, yielding owned records of the elements that are
+/// removed. See [Stack::drain_to_root](struct.Stack.html#method.drain_to_root).
+pub struct StackDrain<'a, 's, T: 'a + Hash + Eq + Clone, S: 'a, A: 'a>
+where
+  'a: 's,
+{
+  /// The path being unwound.
+  path: &'s mut Stack<'a, T, S, A>,
+}
+
+/// An element that was removed from a search path by
+/// [Stack::drain_to_root](struct.Stack.html#method.drain_to_root). IDs are as
+/// returned by `get_id` on edge and node handles.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct PoppedEdge {
+  /// The ID of the edge that was removed.
+  pub edge: usize,
+  /// The ID of the vertex that was the head before the edge was removed (i.e.,
+  /// the target of `edge`).
+  pub node: usize,
+}
+
 /// Sum type for path elements. All elements except the head are represented
 /// with the `StackItem::Item` variant.
 pub enum StackItem<'a, T: 'a + Hash + Eq + Clone, S: 'a, A: 'a> {
@@ -107,29 +431,25 @@ impl<E: Error> fmt::Display for SearchError<E> {
         "Search chose parent {}/{}",
         requested_index, parent_count
       ),
+      SearchError::ParentsUntracked => write!(f, "Search chose a parent of a forward-only graph"),
+      SearchError::RepetitionLimit { count } => {
+        write!(
+          f,
+          "Search chose a vertex already on the path {} times",
+          count
+        )
+      }
       SearchError::SelectionError(ref e) => write!(f, "Error in search operation: {}", e),
+      SearchError::ReloadError(ref e) => write!(f, "Error reloading spilled region: {}", e),
     }
   }
 }
 
-impl<E: Error> Error for SearchError<E> {
-  fn description(&self) -> &str {
-    match *self {
-      SearchError::ChildBounds {
-        requested_index: _,
-        child_count: _,
-      } => "child out of bounds",
-      SearchError::ParentBounds {
-        requested_index: _,
-        parent_count: _,
-      } => "parent out of bounds",
-      SearchError::SelectionError(ref e) => e.description(),
-    }
-  }
-
-  fn cause(&self) -> Option<&Error> {
+impl<E: Error + 'static> Error for SearchError<E> {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
     match *self {
       SearchError::SelectionError(ref e) => Some(e),
+      SearchError::ReloadError(ref e) => Some(e),
       _ => None,
     }
   }
@@ -142,11 +462,51 @@ impl<'a, T: 'a + Hash + Eq + Clone, S: 'a, A: 'a> Stack<'a, T, S, A> {
       graph: node.graph,
       path: Vec::new(),
       head: node.id,
+      repetition_limit: None,
+      repetitions: HashMap::new(),
+      recorder: None,
+    }
+  }
+
+  /// Creates a new `Stack` whose head is the vertex for `state` (which may be
+  /// an alias), or returns `None` if there is no such vertex.
+  pub fn at(graph: &'a mut Graph<T, S, A>, state: &T) -> Option<Self> {
+    graph.find_node_mut(state).map(Stack::new)
+  }
+
+  /// Recreates a `Stack` from a token that was sealed from `graph`, after
+  /// revalidating it with
+  /// [Graph::revalidate](../struct.Graph.html#method.revalidate). Tokens do
+  /// not record repetition limits or recorders, so the new `Stack` has
+  /// neither.
+  pub fn resume(graph: &'a mut Graph<T, S, A>, token: PathToken) -> Result<Self, PathTokenError> {
+    let token = graph.revalidate(token)?;
+    Ok(Stack {
+      graph,
+      path: token.path,
+      head: token.head,
+      repetition_limit: None,
+      repetitions: HashMap::new(),
+      recorder: None,
+    })
+  }
+
+  /// Consumes the path and returns a token that records it, so that the
+  /// graph may be borrowed elsewhere (e.g., for garbage collection) before
+  /// the search is resumed with [resume](struct.Stack.html#method.resume).
+  /// Any attached recorder is dropped, so it should be detached first.
+  pub fn seal(self) -> PathToken {
+    PathToken {
+      graph: self.graph.address(),
+      version: self.graph.version,
+      path: self.path,
+      head: self.head,
     }
   }
 
   /// Returns the number of elements in the path. Since a path always has a
   /// head, there is always at least 1 element.
+  #[allow(clippy::len_without_is_empty)]
   pub fn len(&self) -> usize {
     self.path.len() + 1
   }
@@ -154,20 +514,171 @@ impl<'a, T: 'a + Hash + Eq + Clone, S: 'a, A: 'a> Stack<'a, T, S, A> {
   /// Removes the most recently traversed element from the path, if
   /// any. Returns a handle for any edge that was removed.
   pub fn pop<'s>(&'s mut self) -> Option<Edge<'s, T, S, A>> {
+    match self.pop_ids() {
+      Some((edge_id, _)) => Some(Edge::new(self.graph, edge_id)),
+      None => None,
+    }
+  }
+
+  /// Removes the most recently traversed element from the path, if any, and
+  /// returns the IDs of the edge that was removed and of the former head.
+  fn pop_ids(&mut self) -> Option<(EdgeId, VertexId)> {
     match self.path.pop() {
       Some(edge_id) => {
+        let old_head = self.head;
         self.head = self.graph.get_arc(edge_id).source;
-        Some(Edge::new(self.graph, edge_id))
+        if let Some(ref mut recorder) = self.recorder {
+          recorder.decisions.push(Decision::Pop(edge_id.as_usize()));
+        }
+        if self.repetition_limit.is_some() {
+          let count = self.repetitions.get_mut(&old_head).unwrap();
+          *count -= 1;
+          if *count == 0 {
+            self.repetitions.remove(&old_head);
+          }
+        }
+        Some((edge_id, old_head))
       }
       None => None,
     }
   }
 
+  /// Limits the number of times that any vertex may occur on the path to
+  /// `limit`, or removes the limit if `limit` is `None`. Once the limit is
+  /// reached for a vertex, [push](struct.Stack.html#method.push) refuses to
+  /// traverse another edge to it and returns a `SearchError::RepetitionLimit`
+  /// error. For example, a search of a game that is drawn by threefold
+  /// repetition may set a limit of 2 and score that error as a draw.
+  ///
+  /// Vertices that already occur on the path more than `limit` times are
+  /// left on it. Panics if `limit` is `Some(0)`.
+  pub fn set_repetition_limit(&mut self, limit: Option<usize>) {
+    assert!(limit != Some(0), "repetition limit must be positive");
+    self.repetition_limit = limit;
+    self.repetitions.clear();
+    if limit.is_some() {
+      *self.repetitions.entry(self.head).or_insert(0) += 1;
+      for edge_id in self.path.iter() {
+        *self
+          .repetitions
+          .entry(self.graph.get_arc(*edge_id).source)
+          .or_insert(0) += 1;
+      }
+    }
+  }
+
+  /// Attaches `recorder` to the path, replacing any recorder that was already
+  /// attached. Subsequent pushes and pops are logged to it.
+  pub fn attach_recorder(&mut self, recorder: Recorder) {
+    self.recorder = Some(recorder);
+  }
+
+  /// Detaches and returns the recorder, if one is attached.
+  pub fn detach_recorder(&mut self) -> Option<Recorder> {
+    self.recorder.take()
+  }
+
+  /// Returns the attached recorder, if any.
+  pub fn recorder(&self) -> Option<&Recorder> {
+    self.recorder.as_ref()
+  }
+
+  /// Returns the repetition limit, if any (see
+  /// [set_repetition_limit](struct.Stack.html#method.set_repetition_limit)).
+  pub fn repetition_limit(&self) -> Option<usize> {
+    self.repetition_limit
+  }
+
+  /// Returns the number of times the head occurs on the path, including as
+  /// the head. Repetitions are only counted while a repetition limit is set,
+  /// so this is 1 if there is no limit.
+  pub fn head_repetitions(&self) -> usize {
+    self.repetitions.get(&self.head).cloned().unwrap_or(1)
+  }
+
+  /// Removes elements from the path until at most `depth` edges remain on
+  /// it (i.e., until `len()` is at most `depth + 1`). Returns the number of
+  /// edges that were removed.
+  pub fn pop_to(&mut self, depth: usize) -> usize {
+    let mut popped = 0;
+    while self.path.len() > depth {
+      self.pop_ids();
+      popped += 1;
+    }
+    popped
+  }
+
+  /// Returns an iterator that unwinds the path towards its first vertex,
+  /// removing one element for each item that it yields. Each item is a
+  /// [PoppedEdge](struct.PoppedEdge.html) that identifies the edge that was
+  /// removed and the vertex that was the head before it was removed, most
+  /// recent first.
+  ///
+  /// Elements that have not been yielded when the iterator is dropped remain
+  /// on the path, so a backtracking search may stop unwinding as soon as it
+  /// reaches a vertex with alternatives left to explore.
+  pub fn drain_to_root<'s>(&'s mut self) -> StackDrain<'a, 's, T, S, A> {
+    StackDrain { path: self }
+  }
+
   /// Returns a read-only view of the head element.
   pub fn head<'s>(&'s self) -> Node<'s, T, S, A> {
     Node::new(self.graph, self.head)
   }
 
+  /// Returns the data of the head element, mutably. Unlike changes to the
+  /// graph's topology, changes to vertex data do not invalidate the path.
+  pub fn head_data_mut(&mut self) -> &mut S {
+    self.graph.get_data_mut(self.head)
+  }
+
+  /// Returns a read-only view of the vertex that `push` would move to for
+  /// `Traversal::Child(i)`, without changing the path, or `None` if the head
+  /// has no `i`th child. This allows selection functions to look more than
+  /// one move ahead.
+  ///
+  /// The push itself may still fail (e.g., because of a repetition limit).
+  pub fn peek_child<'s>(&'s self, i: usize) -> Option<Node<'s, T, S, A>> {
+    let children = self.head().get_child_list();
+    if i < children.len() {
+      Some(children.get_edge(i).get_target())
+    } else {
+      None
+    }
+  }
+
+  /// As [peek_child](#method.peek_child), but for `Traversal::Parent(i)`.
+  /// Returns `None` if the graph does not track incoming edges, since the
+  /// push would then fail.
+  pub fn peek_parent<'s>(&'s self, i: usize) -> Option<Node<'s, T, S, A>> {
+    let parents = self.head().try_get_parent_list().ok()?;
+    if i < parents.len() {
+      Some(parents.get_edge(i).get_source())
+    } else {
+      None
+    }
+  }
+
+  /// Returns the traversed edges as a [Path](../nav/struct.Path.html) that
+  /// ends at the head. A path with no edges is returned if nothing has been
+  /// traversed.
+  ///
+  /// Returns an error if any traversal was to a parent, since the edges are
+  /// then not contiguous.
+  pub fn to_path<'s>(&'s self) -> Result<Path<'s, T, S, A>, PathError> {
+    if self.path.is_empty() {
+      return Ok(Path::new(self.head()));
+    }
+    let ids: Vec<usize> = self.path.iter().map(|id| id.as_usize()).collect();
+    let path = Path::from_edge_ids(self.graph, &ids)?;
+    if path.last_node().get_id() != self.head().get_id() {
+      return Err(PathError::Discontiguous {
+        index: ids.len() - 1,
+      });
+    }
+    Ok(path)
+  }
+
   /// Consumes the path and returns a mutable view of its head.
   pub fn to_head(self) -> MutNode<'a, T, S, A> {
     MutNode {
@@ -186,45 +697,90 @@ impl<'a, T: 'a + Hash + Eq + Clone, S: 'a, A: 'a> Stack<'a, T, S, A> {
   /// `Ok(None)`, while `Err(E)` should be returned for any errors.
   ///
   /// Returns an `Ok(Option(e))` for any edge `e` that is traversed, or
-  /// `Err(e)` if an error was encountered.
+  /// `Err(e)` if an error was encountered, in which case the path is
+  /// unchanged.
   pub fn push<'s, F, E>(&'s mut self, mut f: F) -> Result<Option<Edge<'s, T, S, A>>, SearchError<E>>
   where
     F: FnMut(&Node<'s, T, S, A>) -> Result<Option<Traversal>, E>,
     E: Error,
   {
+    self.push_tagged(|node| f(node).map(|t| t.map(|t| (t, 0))))
+  }
+
+  /// Like [push](struct.Stack.html#method.push), except that `f` also returns
+  /// a reason tag for the traversal that it selects. The tag has no effect on
+  /// the search, but is logged with the traversal by any attached
+  /// [Recorder](struct.Recorder.html).
+  pub fn push_tagged<'s, F, E>(
+    &'s mut self,
+    mut f: F,
+  ) -> Result<Option<Edge<'s, T, S, A>>, SearchError<E>>
+  where
+    F: FnMut(&Node<'s, T, S, A>) -> Result<Option<(Traversal, u32)>, E>,
+    E: Error,
+  {
+    if let Err(e) = self.graph.reload_vertex(self.head) {
+      return Err(SearchError::ReloadError(e));
+    }
     let node = Node::new(self.graph, self.head);
-    match f(&node) {
-      Ok(Some(Traversal::Child(i))) => {
+    let (traversal, reason) = match f(&node) {
+      Ok(Some(selected)) => selected,
+      Ok(None) => return Ok(None),
+      Err(e) => return Err(SearchError::SelectionError(e)),
+    };
+    let edge = match traversal {
+      Traversal::Child(i) => {
         let children = node.get_child_list();
         if i >= children.len() {
-          Err(SearchError::ChildBounds {
+          return Err(SearchError::ChildBounds {
             requested_index: i,
             child_count: children.len(),
-          })
-        } else {
-          let child = children.get_edge(i);
-          self.path.push(EdgeId(child.get_id()));
-          self.head = VertexId(child.get_target().get_id());
-          Ok(Some(child))
+          });
         }
+        let child = children.get_edge(i);
+        let target = VertexId(child.get_target().get_id());
+        advance(
+          &mut self.path,
+          &mut self.head,
+          &mut self.repetitions,
+          self.repetition_limit,
+          EdgeId(child.get_id()),
+          target,
+        )?;
+        child
       }
-      Ok(Some(Traversal::Parent(i))) => {
-        let parents = node.get_parent_list();
+      Traversal::Parent(i) => {
+        let parents = match node.try_get_parent_list() {
+          Ok(parents) => parents,
+          Err(_) => return Err(SearchError::ParentsUntracked),
+        };
         if i >= parents.len() {
-          Err(SearchError::ParentBounds {
+          return Err(SearchError::ParentBounds {
             requested_index: i,
             parent_count: parents.len(),
-          })
-        } else {
-          let parent = parents.get_edge(i);
-          self.path.push(EdgeId(parent.get_id()));
-          self.head = VertexId(parent.get_source().get_id());
-          Ok(Some(parent))
+          });
         }
+        let parent = parents.get_edge(i);
+        let source = VertexId(parent.get_source().get_id());
+        advance(
+          &mut self.path,
+          &mut self.head,
+          &mut self.repetitions,
+          self.repetition_limit,
+          EdgeId(parent.get_id()),
+          source,
+        )?;
+        parent
       }
-      Ok(None) => Ok(None),
-      Err(e) => Err(SearchError::SelectionError(e)),
+    };
+    if let Some(ref mut recorder) = self.recorder {
+      recorder.decisions.push(Decision::Push {
+        node: node.get_id(),
+        traversal,
+        reason,
+      });
     }
+    Ok(Some(edge))
   }
 
   /// Returns an iterator over path elements. Iteration is in order of
@@ -253,10 +809,7 @@ where
 {
   /// Creates a new path iterator from a borrow of a path.
   fn new(path: &'s Stack<'a, T, S, A>) -> Self {
-    StackIter {
-      path: path,
-      position: 0,
-    }
+    StackIter { path, position: 0 }
   }
 }
 
@@ -281,9 +834,369 @@ where
   }
 }
 
+impl<'a, 's, T, S, A> Iterator for StackDrain<'a, 's, T, S, A>
+where
+  T: 'a + Hash + Eq + Clone,
+  S: 'a,
+  A: 'a,
+  'a: 's,
+{
+  type Item = PoppedEdge;
+
+  fn next(&mut self) -> Option<PoppedEdge> {
+    self.path.pop_ids().map(|(edge, node)| PoppedEdge {
+      edge: edge.as_usize(),
+      node: node.as_usize(),
+    })
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    let len = self.path.path.len();
+    (len, Some(len))
+  }
+}
+
+impl<'a, 's, T, S, A> ExactSizeIterator for StackDrain<'a, 's, T, S, A>
+where
+  T: 'a + Hash + Eq + Clone,
+  S: 'a,
+  A: 'a,
+  'a: 's,
+{
+}
+
+/// Runs `rollout` from the heads of the search paths in `frontier` (e.g., the
+/// paths of several descents of a search, each sealed with
+/// [Stack::seal](struct.Stack.html#method.seal)) on up to `threads` threads at
+/// once, then calls `backprop` with each path, resumed as a `Stack`, and the
+/// result of the rollout from its head, one path at a time in the order of
+/// `frontier`. Returns the number of rollouts that were run.
+///
+/// Rollouts only read the graph, which is shared between threads while they
+/// run. Backpropagation may modify the data of the vertices on the path it is
+/// given, by updating the data of the head with
+/// [head_data_mut](struct.Stack.html#method.head_data_mut) and popping the
+/// path, or may consume the path with
+/// [to_head](struct.Stack.html#method.to_head). Tokens that
+/// [Graph::revalidate](../struct.Graph.html#method.revalidate) rejects are
+/// skipped, as are paths that an earlier call to `backprop` invalidated (e.g.,
+/// by removing edges).
+///
+/// Panics if `threads` is 0.
+///
+/// ```rust
+/// # use std::convert::Infallible;
+/// # use search_graph::Graph;
+/// # use search_graph::search::{parallel_rollouts, Stack, Traversal};
+/// # fn main() {
+/// let mut g: Graph<u32, u32, ()> = Graph::new();
+/// for leaf in 1..=4 {
+///   g.add_edge(0, |_| 0, leaf, |_| 0, ());
+/// }
+/// let frontier = (0..4)
+///   .map(|i| {
+///     let mut stack = Stack::at(&mut g, &0).unwrap();
+///     stack.push(|_| Ok::<_, Infallible>(Some(Traversal::Child(i)))).unwrap();
+///     stack.seal()
+///   })
+///   .collect();
+/// parallel_rollouts(&mut g, frontier, 2, |leaf| leaf.get_label() * 10, |mut stack, value| {
+///   loop {
+///     *stack.head_data_mut() += value;
+///     if stack.pop().is_none() {
+///       break;
+///     }
+///   }
+/// });
+/// assert_eq!(*g.find_node(&0).unwrap().get_data(), 100);
+/// assert_eq!(*g.find_node(&3).unwrap().get_data(), 30);
+/// # }
+/// ```
+pub fn parallel_rollouts<T, S, A, R, F, B>(
+  graph: &mut Graph<T, S, A>,
+  frontier: Vec<PathToken>,
+  threads: usize,
+  rollout: F,
+  mut backprop: B,
+) -> usize
+where
+  T: Hash + Eq + Clone + Send + Sync,
+  S: Send + Sync,
+  A: Send + Sync,
+  R: Send,
+  F: Fn(Node<T, S, A>) -> R + Sync,
+  B: FnMut(Stack<T, S, A>, R),
+{
+  assert!(threads > 0, "thread count must be positive");
+  let tokens: Vec<PathToken> = frontier
+    .into_iter()
+    .filter_map(|token| graph.revalidate(token).ok())
+    .collect();
+  let results: Vec<R> = {
+    let graph = &*graph;
+    let rollout = &rollout;
+    let chunk_size = tokens.len().div_ceil(threads).max(1);
+    std::thread::scope(|s| {
+      let workers: Vec<_> = tokens
+        .chunks(chunk_size)
+        .map(|chunk| {
+          s.spawn(move || {
+            chunk
+              .iter()
+              .map(|token| rollout(Node::new(graph, token.head)))
+              .collect::<Vec<_>>()
+          })
+        })
+        .collect();
+      workers
+        .into_iter()
+        .flat_map(|w| w.join().unwrap())
+        .collect()
+    })
+  };
+  let count = results.len();
+  for (token, result) in tokens.into_iter().zip(results) {
+    if let Ok(stack) = Stack::resume(graph, token) {
+      backprop(stack, result);
+    }
+  }
+  count
+}
+
+/// Searches for a goal vertex in best-first order, starting from `root`, and
+/// returns a `Stack` whose path leads from `root` to the first goal vertex
+/// that is found, or `None` if no goal is reachable.
+///
+/// Vertices are expanded in increasing order of `priority`, with ties broken
+/// in the order in which vertices were reached; wrap priorities in
+/// `std::cmp::Reverse` to expand the greatest first. When a vertex is taken
+/// from the frontier, it is tested with `is_goal`, and if it is not a goal,
+/// `expand` is called on it so that it may add its children to the graph
+/// (e.g., by generating moves) or do nothing if they are already present.
+/// Each child that has not been expanded is then prioritized, and is reached
+/// through the parent that gave it the least priority so far. Each vertex is
+/// expanded at most once, so with priorities of the form `g + h` that `expand`
+/// maintains in vertex data, this is A* search with a consistent heuristic
+/// `h`, and with priorities of the form `h`, it is greedy best-first search.
+///
+/// Returns an error if `expand` does, or if a spilled region could not be
+/// reloaded (see the [tier](../tier/index.html) module). Panics if `expand`
+/// reassigns vertex or edge IDs (e.g., by removing edges).
+///
+/// ```rust
+/// # use search_graph::Graph;
+/// # use search_graph::search::best_first;
+/// # fn main() {
+/// // Finds a shortest sequence of +1 and *2 operations from 1 to 10.
+/// let mut g: Graph<u32, u32, ()> = Graph::new();
+/// g.add_node(1, 0);
+/// let stack = best_first(
+///   g.find_node_mut(&1).unwrap(),
+///   |node| *node.get_data(),
+///   |node| *node.get_label() == 10,
+///   |node| {
+///     let (n, depth) = (*node.get_label(), *node.get_data());
+///     let mut children = node.to_child_list();
+///     for m in [n + 1, n * 2] {
+///       if m <= 10 {
+///         children.add_child(m, || depth + 1, ());
+///       }
+///     }
+///     Ok::<_, std::fmt::Error>(())
+///   },
+/// )
+/// .unwrap()
+/// .unwrap();
+/// let path = stack.to_path().unwrap();
+/// let states: Vec<u32> = path.iter().map(|e| *e.get_target().get_label()).collect();
+/// assert_eq!(states, vec![2, 4, 5, 10]);
+/// # }
+/// ```
+pub fn best_first<'a, T, S, A, K, P, G, X, E>(
+  root: MutNode<'a, T, S, A>,
+  mut priority: P,
+  mut is_goal: G,
+  mut expand: X,
+) -> Result<Option<Stack<'a, T, S, A>>, SearchError<E>>
+where
+  T: Hash + Eq + Clone + 'a,
+  S: 'a,
+  A: 'a,
+  K: Ord + Clone,
+  P: FnMut(&Node<T, S, A>) -> K,
+  G: FnMut(&Node<T, S, A>) -> bool,
+  X: FnMut(MutNode<T, S, A>) -> Result<(), E>,
+  E: Error,
+{
+  let graph = root.graph;
+  let root = root.id;
+  let version = graph.version;
+  let mut frontier = BestFirstFrontier::new(root.as_usize(), priority(&Node::new(graph, root)));
+  while let Some(id) = frontier.pop() {
+    let id = VertexId(id);
+    if let Err(e) = graph.reload_vertex(id) {
+      return Err(SearchError::ReloadError(e));
+    }
+    if is_goal(&Node::new(graph, id)) {
+      let path = frontier
+        .path_to(id.as_usize())
+        .into_iter()
+        .map(|(_, edge)| EdgeId(edge))
+        .collect();
+      return Ok(Some(Stack {
+        graph,
+        path,
+        head: id,
+        repetition_limit: None,
+        repetitions: HashMap::new(),
+        recorder: None,
+      }));
+    }
+    expand(MutNode::new(graph, id)).map_err(SearchError::SelectionError)?;
+    assert_eq!(
+      version, graph.version,
+      "best-first expansion reassigned vertex or edge IDs"
+    );
+    frontier.relax(Node::new(graph, id), &mut priority);
+  }
+  Ok(None)
+}
+
+/// Searches the part of a graph that has already been built for a goal
+/// vertex in best-first order, starting from the vertex for `root`, as
+/// [best_first](fn.best_first.html) does, but without expanding vertices or
+/// reloading spilled regions. Returns the edges of the path from `root` to the
+/// first goal vertex that is found, in order, or `None` if no goal is
+/// reachable or there is no vertex for `root`.
+///
+/// Since it only reads the graph, this is written against the traits in
+/// [nav_types](../nav_types/index.html), so it may search a
+/// [Snapshot](../snapshot/struct.Snapshot.html) as well as a `Graph`.
+///
+/// ```rust
+/// # use search_graph::Graph;
+/// # use search_graph::search::best_first_path;
+/// # use search_graph::nav_types::{IEdge, IVertex};
+/// # fn main() {
+/// let mut g: Graph<&str, u32, ()> = Graph::new();
+/// g.add_edge("root", |_| 0, "a", |_| 5, ());
+/// g.add_edge("root", |_| 0, "b", |_| 1, ());
+/// g.add_edge("a", |_| 5, "goal", |_| 0, ());
+/// g.add_edge("b", |_| 1, "goal", |_| 0, ());
+/// let snapshot = g.snapshot();
+/// let path = best_first_path(&snapshot, &"root", |v| *v.get_data(), |v| *v.get_label() == "goal")
+///   .unwrap();
+/// let states: Vec<&str> = path.iter().map(|e| *e.get_target().get_label()).collect();
+/// assert_eq!(states, vec!["b", "goal"]);
+/// # }
+/// ```
+pub fn best_first_path<N, K, P, G>(
+  graph: N,
+  root: &<N::Vertex as IVertex>::Label,
+  mut priority: P,
+  mut is_goal: G,
+) -> Option<Vec<<N::Vertex as IVertex>::Edge>>
+where
+  N: INavTypes,
+  K: Ord + Clone,
+  P: FnMut(&N::Vertex) -> K,
+  G: FnMut(&N::Vertex) -> bool,
+{
+  let root = graph.find_vertex(root)?;
+  let mut frontier = BestFirstFrontier::new(root.get_id(), priority(&root));
+  while let Some(id) = frontier.pop() {
+    let vertex = graph.get_vertex(id).unwrap();
+    if is_goal(&vertex) {
+      let path = frontier.path_to(id).into_iter().map(|(source, edge)| {
+        let children = graph.get_vertex(source).unwrap().get_child_list();
+        children.iter().find(|e| e.get_id() == edge).unwrap()
+      });
+      return Some(path.collect());
+    }
+    frontier.relax(vertex, &mut priority);
+  }
+  None
+}
+
+/// The frontier of a best-first search, which refers to vertices and edges by
+/// their IDs, so that the graph may be modified between steps.
+struct BestFirstFrontier<K> {
+  /// Vertices to be expanded, by priority and then by the order in which they
+  /// were reached.
+  heap: BinaryHeap<Reverse<(K, usize, usize)>>,
+  /// The least priority that each vertex has been given.
+  best: HashMap<usize, K>,
+  /// The source and ID of the edge that each vertex other than the root was
+  /// last reached by.
+  reached_by: HashMap<usize, (usize, usize)>,
+  expanded: HashSet<usize>,
+  order: usize,
+}
+
+impl<K: Ord + Clone> BestFirstFrontier<K> {
+  /// Returns a frontier that holds `root`, with priority `priority`.
+  fn new(root: usize, priority: K) -> Self {
+    let mut frontier = BestFirstFrontier {
+      heap: BinaryHeap::new(),
+      best: HashMap::new(),
+      reached_by: HashMap::new(),
+      expanded: HashSet::new(),
+      order: 0,
+    };
+    frontier.best.insert(root, priority.clone());
+    frontier.heap.push(Reverse((priority, 0, root)));
+    frontier
+  }
+
+  /// Removes the vertex of least priority that has not been expanded from the
+  /// frontier, marks it as expanded, and returns it.
+  fn pop(&mut self) -> Option<usize> {
+    while let Some(Reverse((_, _, id))) = self.heap.pop() {
+      if self.expanded.insert(id) {
+        return Some(id);
+      }
+    }
+    None
+  }
+
+  /// Adds the targets of the child edges of `vertex` that have not been
+  /// expanded to the frontier, unless they are already on it with a lesser
+  /// priority.
+  fn relax<V: IVertex, P: FnMut(&V) -> K>(&mut self, vertex: V, priority: &mut P) {
+    for e in vertex.get_child_list().iter() {
+      let target = e.get_target();
+      let id = target.get_id();
+      if self.expanded.contains(&id) {
+        continue;
+      }
+      let p = priority(&target);
+      if self.best.get(&id).is_none_or(|old| p < *old) {
+        self.best.insert(id, p.clone());
+        self.reached_by.insert(id, (vertex.get_id(), e.get_id()));
+        self.order += 1;
+        self.heap.push(Reverse((p, self.order, id)));
+      }
+    }
+  }
+
+  /// Returns the source and ID of each edge on the path by which `id` was
+  /// reached from the root, in order.
+  fn path_to(&self, mut id: usize) -> Vec<(usize, usize)> {
+    let mut path = Vec::new();
+    while let Some(&(source, edge)) = self.reached_by.get(&id) {
+      path.push((source, edge));
+      id = source;
+    }
+    path.reverse();
+    path
+  }
+}
 #[cfg(test)]
 mod test {
-  use super::{SearchError, StackItem, Traversal};
+  use super::{
+    Decision, PathTokenError, PoppedEdge, Recorder, ReplayError, Replayed, SearchError, StackItem,
+    Traversal,
+  };
   use std::error::Error;
   use std::fmt;
 
@@ -320,6 +1233,16 @@ mod test {
     assert_eq!("root", *path.head().get_data());
   }
 
+  #[test]
+  fn at_ok() {
+    let mut g = Graph::new();
+    add_edge(&mut g, "root", "A");
+    let path = Stack::at(&mut g, &"A").unwrap();
+    assert_eq!(1, path.len());
+    assert_eq!("A", *path.head().get_data());
+    assert!(Stack::at(&mut g, &"B").is_none());
+  }
+
   #[test]
   fn push_no_children_ok() {
     let mut g = Graph::new();
@@ -598,6 +1521,45 @@ mod test {
     assert_eq!("A", *path.head().get_data());
   }
 
+  #[test]
+  fn peek_ok() {
+    let mut g = Graph::new();
+    add_edge(&mut g, "A", "B1");
+    add_edge(&mut g, "A", "B2");
+    add_edge(&mut g, "B2", "C");
+    add_edge(&mut g, "C", "B2");
+
+    let mut path = Stack::new(g.find_node_mut(&"A").unwrap());
+    assert_eq!("B2", *path.peek_child(1).unwrap().get_data());
+    assert!(path.peek_child(2).is_none());
+    assert!(path.peek_parent(0).is_none());
+    assert_eq!(1, path.len());
+
+    path
+      .push(|n| {
+        assert_eq!("A", *n.get_data());
+        Ok::<_, MockError>(Some(Traversal::Child(1)))
+      })
+      .unwrap();
+    // Look two moves ahead: B2 -> C -> B2.
+    assert_eq!("C", *path.peek_child(0).unwrap().get_data());
+    assert_eq!(
+      "B2",
+      *path
+        .peek_child(0)
+        .unwrap()
+        .get_child_list()
+        .get_edge(0)
+        .get_target()
+        .get_data()
+    );
+    assert_eq!("A", *path.peek_parent(0).unwrap().get_data());
+    assert_eq!("C", *path.peek_parent(1).unwrap().get_data());
+    assert!(path.peek_parent(2).is_none());
+    assert_eq!(2, path.len());
+    assert_eq!("B2", *path.head().get_data());
+  }
+
   #[test]
   fn search_path_iter_empty_ok() {
     let mut g = Graph::new();
@@ -741,4 +1703,546 @@ mod test {
 
     assert_eq!("A", *path.to_head().get_data());
   }
+
+  fn first_child(_: &Node) -> Result<Option<Traversal>, MockError> {
+    Ok(Some(Traversal::Child(0)))
+  }
+
+  fn retain_reachable(g: &mut Graph, root: &'static str) {
+    crate::view::of_graph(g, |v| {
+      let root = v.find_node(&root).unwrap();
+      v.retain_reachable_from(vec![root]);
+    });
+  }
+
+  #[test]
+  fn seal_resume_ok() {
+    let mut g = Graph::new();
+    add_edge(&mut g, "root", "A");
+    add_edge(&mut g, "A", "B");
+    let mut path = Stack::new(g.find_node_mut(&"root").unwrap());
+    path.push(first_child).unwrap();
+    let token = path.seal();
+    assert_eq!(2, token.len());
+    add_edge(&mut g, "B", "C");
+    let mut path = Stack::resume(&mut g, token).unwrap();
+    assert_eq!("A", *path.head().get_data());
+    path.push(first_child).unwrap();
+    assert_eq!("B", *path.head().get_data());
+  }
+
+  #[test]
+  fn seal_after_gc_ok() {
+    let mut g = Graph::new();
+    add_edge(&mut g, "root", "X");
+    add_edge(&mut g, "root", "A");
+    add_edge(&mut g, "A", "B");
+    add_edge(&mut g, "X", "Y");
+    let mut path = Stack::new(g.find_node_mut(&"A").unwrap());
+    path.push(first_child).unwrap();
+    let token = path.seal();
+    // Dropping "root" and "X" reassigns the IDs of "A" and its edge to "B".
+    retain_reachable(&mut g, "A");
+    assert_eq!(Some(0), g.last_remap().unwrap().vertex(2));
+    let path = Stack::resume(&mut g, token).unwrap();
+    let labels: Vec<&str> = path
+      .iter()
+      .map(|item| match item {
+        StackItem::Item(e) => *e.get_source().get_label(),
+        StackItem::Head(n) => *n.get_label(),
+      })
+      .collect();
+    assert_eq!(vec!["A", "B"], labels);
+  }
+
+  #[test]
+  fn seal_after_gc_err() {
+    let mut g = Graph::new();
+    add_edge(&mut g, "root", "A");
+    add_edge(&mut g, "A", "B");
+    let mut path = Stack::new(g.find_node_mut(&"root").unwrap());
+    path.push(first_child).unwrap();
+    let token = path.seal();
+    retain_reachable(&mut g, "A");
+    assert_eq!(Err(PathTokenError::Collected), g.revalidate(token.clone()));
+    retain_reachable(&mut g, "A");
+    assert_eq!(Err(PathTokenError::Expired), g.revalidate(token.clone()));
+    let other = Graph::new();
+    assert_eq!(Err(PathTokenError::ForeignGraph), other.revalidate(token));
+  }
+
+  #[test]
+  fn to_path_ok() {
+    let mut g = Graph::new();
+    add_edge(&mut g, "root", "A");
+    add_edge(&mut g, "A", "B");
+    let mut path = Stack::new(g.find_node_mut(&"root").unwrap());
+    assert!(path.to_path().unwrap().is_empty());
+    path.push(first_child).unwrap();
+    path.push(first_child).unwrap();
+    let p = path.to_path().unwrap();
+    assert_eq!(2, p.len());
+    assert_eq!("root", *p.first_node().get_label());
+    assert_eq!("B", *p.last_node().get_label());
+  }
+
+  #[test]
+  fn to_path_err() {
+    let mut g = Graph::new();
+    add_edge(&mut g, "root", "A");
+    let mut path = Stack::new(g.find_node_mut(&"A").unwrap());
+    path
+      .push(|_: &Node| Ok::<_, MockError>(Some(Traversal::Parent(0))))
+      .unwrap();
+    assert_eq!(
+      Some(crate::nav::PathError::Discontiguous { index: 0 }),
+      path.to_path().err()
+    );
+  }
+
+  #[test]
+  fn pop_to_ok() {
+    let mut g = Graph::new();
+    add_edge(&mut g, "root", "A");
+    add_edge(&mut g, "A", "B");
+    add_edge(&mut g, "B", "C");
+    let mut path = Stack::new(g.find_node_mut(&"root").unwrap());
+    for _ in 0..3 {
+      path.push(first_child).unwrap();
+    }
+    assert_eq!(0, path.pop_to(5));
+    assert_eq!(2, path.pop_to(1));
+    assert_eq!(2, path.len());
+    assert_eq!("A", *path.head().get_data());
+    assert_eq!(1, path.pop_to(0));
+    assert_eq!("root", *path.head().get_data());
+  }
+
+  #[test]
+  fn drain_to_root_ok() {
+    let mut g = Graph::new();
+    add_edge(&mut g, "root", "A");
+    add_edge(&mut g, "A", "B");
+    add_edge(&mut g, "B", "C");
+    let ids: Vec<usize> = ["root", "A", "B", "C"]
+      .iter()
+      .map(|s| g.find_node(s).unwrap().get_id())
+      .collect();
+    let mut path = Stack::new(g.find_node_mut(&"root").unwrap());
+    for _ in 0..3 {
+      path.push(first_child).unwrap();
+    }
+    {
+      let mut drain = path.drain_to_root();
+      assert_eq!(3, drain.len());
+      assert_eq!(
+        Some(PoppedEdge {
+          edge: 2,
+          node: ids[3]
+        }),
+        drain.next()
+      );
+    }
+    assert_eq!(3, path.len());
+    assert_eq!("B", *path.head().get_data());
+    assert_eq!(
+      vec![
+        PoppedEdge {
+          edge: 1,
+          node: ids[2]
+        },
+        PoppedEdge {
+          edge: 0,
+          node: ids[1]
+        },
+      ],
+      path.drain_to_root().collect::<Vec<_>>()
+    );
+    assert_eq!(1, path.len());
+    assert_eq!("root", *path.head().get_data());
+    assert_eq!(0, path.drain_to_root().count());
+  }
+
+  #[test]
+  fn repetition_limit_ok() {
+    let mut g = Graph::new();
+    add_edge(&mut g, "root", "A");
+    add_edge(&mut g, "A", "root");
+    let mut path = Stack::new(g.find_node_mut(&"root").unwrap());
+    path.push(first_child).unwrap();
+    path.push(first_child).unwrap();
+    assert_eq!(1, path.head_repetitions());
+    path.set_repetition_limit(Some(3));
+    assert_eq!(Some(3), path.repetition_limit());
+    assert_eq!(2, path.head_repetitions());
+    path.push(first_child).unwrap();
+    path.push(first_child).unwrap();
+    assert_eq!(3, path.head_repetitions());
+    path.pop();
+    path.pop();
+    assert_eq!(2, path.head_repetitions());
+    path.set_repetition_limit(None);
+    assert_eq!(None, path.repetition_limit());
+    for _ in 0..4 {
+      path.push(first_child).unwrap();
+    }
+    assert_eq!(7, path.len());
+  }
+
+  #[test]
+  fn repetition_limit_err() {
+    let mut g = Graph::new();
+    add_edge(&mut g, "root", "A");
+    add_edge(&mut g, "A", "root");
+    let mut path = Stack::new(g.find_node_mut(&"root").unwrap());
+    path.set_repetition_limit(Some(2));
+    path.push(first_child).unwrap();
+    path.push(first_child).unwrap();
+    path.push(first_child).unwrap();
+    match path.push(first_child) {
+      Err(SearchError::RepetitionLimit { count }) => assert_eq!(2, count),
+      _ => panic!(),
+    }
+    assert_eq!(4, path.len());
+    assert_eq!("A", *path.head().get_data());
+    assert_eq!(2, path.pop_to(1));
+    path.push(first_child).unwrap();
+    assert_eq!("root", *path.head().get_data());
+    assert_eq!(2, path.head_repetitions());
+  }
+
+  #[test]
+  fn recorder_replay_ok() {
+    let mut g = Graph::new();
+    add_edge(&mut g, "root", "A");
+    add_edge(&mut g, "A", "B");
+    add_edge(&mut g, "C", "B");
+    let ids: Vec<usize> = ["root", "A", "B", "C"]
+      .iter()
+      .map(|s| g.find_node(s).unwrap().get_id())
+      .collect();
+    let mut path = Stack::new(g.find_node_mut(&"root").unwrap());
+    path.push(first_child).unwrap();
+    path.attach_recorder(Recorder::new());
+    path
+      .push_tagged(|_| -> Result<_, MockError> { Ok(Some((Traversal::Child(0), 7))) })
+      .unwrap();
+    path
+      .push(|_| -> Result<_, MockError> { Ok(Some(Traversal::Parent(1))) })
+      .unwrap();
+    assert_eq!("C", *path.head().get_data());
+    path.push(|_| -> Result<_, MockError> { Ok(None) }).unwrap();
+    assert!(path
+      .push(|_| -> Result<_, MockError> { Ok(Some(Traversal::Child(5))) })
+      .is_err());
+    path.pop();
+    let recorder = path.detach_recorder().unwrap();
+    assert!(path.recorder().is_none());
+    path.pop();
+    assert_eq!(
+      &[
+        Decision::Push {
+          node: ids[1],
+          traversal: Traversal::Child(0),
+          reason: 7,
+        },
+        Decision::Push {
+          node: ids[2],
+          traversal: Traversal::Parent(1),
+          reason: 0,
+        },
+        Decision::Pop(2),
+      ],
+      recorder.decisions()
+    );
+
+    let replayed = recorder.replay(&g).unwrap();
+    assert_eq!(3, replayed.len());
+    match replayed[0] {
+      Replayed::Push {
+        ref edge,
+        traversal: Traversal::Child(0),
+        reason: 7,
+      } => assert_eq!("B", *edge.get_target().get_data()),
+      _ => panic!(),
+    }
+    match replayed[1] {
+      Replayed::Push {
+        ref edge,
+        traversal: Traversal::Parent(1),
+        reason: 0,
+      } => assert_eq!("C", *edge.get_source().get_data()),
+      _ => panic!(),
+    }
+    match replayed[2] {
+      Replayed::Pop(ref edge) => assert_eq!(2, edge.get_id()),
+      _ => panic!(),
+    }
+  }
+
+  #[test]
+  fn recorder_replay_err() {
+    let mut g = Graph::new();
+    add_edge(&mut g, "root", "A");
+    add_edge(&mut g, "A", "B");
+    let mut path = Stack::new(g.find_node_mut(&"root").unwrap());
+    path.attach_recorder(Recorder::new());
+    path.push(first_child).unwrap();
+    path.push(first_child).unwrap();
+    let recorder = path.detach_recorder().unwrap();
+    assert_eq!(2, recorder.len());
+
+    let mut other = Graph::new();
+    add_edge(&mut other, "root", "A");
+    other.add_node("B", "B");
+    assert_eq!(
+      Err(ReplayError { decision: 1 }),
+      recorder.replay(&other).map(|r| r.len())
+    );
+    assert_eq!(
+      Err(ReplayError { decision: 0 }),
+      recorder.replay(&Graph::new()).map(|r| r.len())
+    );
+  }
+
+  #[test]
+  fn parallel_rollouts_ok() {
+    type Graph = crate::Graph<u32, u32, ()>;
+    let make_graph = || {
+      let mut g = Graph::new();
+      for i in 1..20 {
+        g.add_edge(i / 2, |_| 0, i, |_| 0, ());
+      }
+      g
+    };
+    let seal = |g: &mut Graph, leaf: u32| {
+      let mut ancestors = vec![leaf];
+      while *ancestors.last().unwrap() > 1 {
+        ancestors.push(ancestors.last().unwrap() / 2);
+      }
+      let mut path = super::Stack::at(g, &0).unwrap();
+      for child in ancestors.into_iter().rev() {
+        let i = if child == 1 { 0 } else { child as usize % 2 };
+        path
+          .push(|_| Ok::<_, std::convert::Infallible>(Some(Traversal::Child(i))))
+          .unwrap();
+      }
+      path.seal()
+    };
+    for threads in 1..5 {
+      let mut g = make_graph();
+      let mut frontier: Vec<_> = (10..20).map(|i| seal(&mut g, i)).collect();
+      frontier.push(seal(&mut make_graph(), 10));
+      let mut order = Vec::new();
+      let count = super::parallel_rollouts(
+        &mut g,
+        frontier,
+        threads,
+        |leaf| leaf.get_label() + leaf.get_parent_list().target_node().get_data(),
+        |mut path, value| {
+          order.push((*path.head().get_label(), path.len()));
+          loop {
+            *path.head_data_mut() += value;
+            if path.pop().is_none() {
+              break;
+            }
+          }
+        },
+      );
+      assert_eq!(10, count);
+      assert_eq!(
+        (10..20)
+          .map(|i| (i, if i < 16 { 5 } else { 6 }))
+          .collect::<Vec<_>>(),
+        order
+      );
+      assert_eq!(145, *g.find_node(&0).unwrap().get_data());
+      assert_eq!(
+        10 + 11 + 16 + 17 + 18 + 19,
+        *g.find_node(&2).unwrap().get_data()
+      );
+      assert_eq!(19, *g.find_node(&19).unwrap().get_data());
+    }
+  }
+
+  #[test]
+  #[should_panic(expected = "thread count must be positive")]
+  fn parallel_rollouts_err() {
+    let mut g = Graph::new();
+    super::parallel_rollouts(&mut g, Vec::new(), 0, |_| (), |_, _| ());
+  }
+
+  #[test]
+  fn best_first_ok() {
+    // Dijkstra's algorithm, with path costs in vertex data and step costs in
+    // edge data.
+    let mut g: crate::Graph<&str, u32, u32> = crate::Graph::new();
+    for (source, dest, cost) in [
+      ("root", "a", 1),
+      ("root", "b", 4),
+      ("a", "b", 1),
+      ("a", "goal", 5),
+      ("b", "goal", 1),
+      ("goal", "root", 1),
+    ] {
+      g.add_edge(source, |_| u32::MAX, dest, |_| u32::MAX, cost);
+    }
+    *g.find_node_mut(&"root").unwrap().get_data_mut() = 0;
+    let mut expanded = Vec::new();
+    let stack = super::best_first(
+      g.find_node_mut(&"root").unwrap(),
+      |node| *node.get_data(),
+      |node| *node.get_label() == "goal",
+      |node| {
+        expanded.push(*node.get_label());
+        let cost = *node.get_data();
+        let mut children = node.to_child_list();
+        for i in 0..children.len() {
+          let mut edge = children.get_edge_mut(i);
+          let step = *edge.get_data();
+          let mut target = edge.get_target_mut();
+          let data = target.get_data_mut();
+          *data = (*data).min(cost + step);
+        }
+        Ok::<_, MockError>(())
+      },
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!("goal", *stack.head().get_label());
+    assert_eq!(3, *stack.head().get_data());
+    let path: Vec<&str> = stack
+      .to_path()
+      .unwrap()
+      .iter()
+      .map(|e| *e.get_source().get_label())
+      .collect();
+    assert_eq!(vec!["root", "a", "b"], path);
+    assert_eq!(vec!["root", "a", "b"], expanded);
+
+    // The root may be a goal.
+    let stack = super::best_first(
+      g.find_node_mut(&"a").unwrap(),
+      |_| 0,
+      |_| true,
+      |_| Ok::<_, MockError>(()),
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!("a", *stack.head().get_label());
+    assert_eq!(1, stack.len());
+  }
+
+  #[test]
+  fn best_first_path_ok() {
+    // Greedy best-first search, with estimates in vertex data.
+    let mut g: crate::Graph<&str, u32, ()> = crate::Graph::new();
+    for (source, dest) in [
+      ("root", "a"),
+      ("root", "b"),
+      ("a", "goal"),
+      ("b", "c"),
+      ("c", "goal"),
+      ("c", "root"),
+    ] {
+      g.add_edge(source, |_| 0, dest, |_| 0, ());
+    }
+    for (state, estimate) in [("a", 3), ("b", 1), ("c", 2)] {
+      *g.find_node_mut(&state).unwrap().get_data_mut() = estimate;
+    }
+    let snapshot = g.snapshot();
+    let targets = |path: Vec<_>| -> Vec<&str> {
+      path
+        .iter()
+        .map(|e: &crate::nav::Edge<_, _, _>| *e.get_target().get_label())
+        .collect()
+    };
+    let path = super::best_first_path(&g, &"root", |v| *v.get_data(), |v| *v.get_label() == "goal");
+    assert_eq!(vec!["b", "c", "goal"], targets(path.unwrap()));
+    let path = super::best_first_path(
+      &snapshot,
+      &"root",
+      |v| *v.get_data(),
+      |v| *v.get_label() == "goal",
+    )
+    .unwrap();
+    let ids: Vec<usize> = path.iter().map(|e| e.get_id()).collect();
+    assert_eq!(vec![1, 3, 4], ids);
+
+    assert!(super::best_first_path(&g, &"a", |_| 0, |v| *v.get_label() == "b").is_none());
+    assert!(super::best_first_path(&g, &"missing", |_| 0, |_| true).is_none());
+    assert_eq!(
+      Some(0),
+      super::best_first_path(&g, &"a", |_| 0, |_| true).map(|p| p.len())
+    );
+  }
+
+  #[test]
+  fn best_first_expand_ok() {
+    // Greedy search that generates children on demand.
+    let mut g = Graph::new();
+    g.add_node("root", "root");
+    let children = |state: &str| -> &'static [&'static str] {
+      match state {
+        "root" => &["far", "near"],
+        "near" => &["nearer", "far"],
+        "nearer" => &["goal"],
+        _ => &[],
+      }
+    };
+    let distance = |state: &str| match state {
+      "root" => 3,
+      "far" => 4,
+      "near" => 2,
+      "nearer" => 1,
+      _ => 0,
+    };
+    let stack = super::best_first(
+      g.find_node_mut(&"root").unwrap(),
+      |node| distance(node.get_label()),
+      |node| *node.get_label() == "goal",
+      |node| {
+        let state = *node.get_label();
+        let mut list = node.to_child_list();
+        for child in children(state) {
+          list.add_child(child, || child, ());
+        }
+        Ok::<_, MockError>(())
+      },
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(4, stack.len());
+    assert_eq!("goal", *stack.head().get_label());
+    assert!(g.find_node(&"far").unwrap().get_child_list().is_empty());
+    assert_eq!(5, g.vertex_count());
+  }
+
+  #[test]
+  fn best_first_err() {
+    let mut g = Graph::new();
+    add_edge(&mut g, "root", "a");
+    add_edge(&mut g, "a", "b");
+    let stack = super::best_first(
+      g.find_node_mut(&"root").unwrap(),
+      |_| 0,
+      |_| false,
+      |_| Ok::<_, MockError>(()),
+    )
+    .unwrap();
+    assert!(stack.is_none());
+    let result = super::best_first(
+      g.find_node_mut(&"root").unwrap(),
+      |_| 0,
+      |_| false,
+      |node| {
+        if *node.get_label() == "a" {
+          Err(MockError(()))
+        } else {
+          Ok(())
+        }
+      },
+    );
+    assert!(matches!(result, Err(SearchError::SelectionError(_))));
+  }
 }
@@ -0,0 +1,358 @@
+//! Serialization of graphs with [serde](https://serde.rs).
+//!
+//! With the `serde` feature enabled, a [Graph](../struct.Graph.html) can be
+//! serialized whenever its game states and data can, and deserialized whenever
+//! they can be deserialized, with any serde data format (e.g., JSON or
+//! bincode). This allows the transposition table built by a long search to be
+//! saved and reloaded after a restart.
+//!
+//! A graph is serialized as a struct with the following fields, which are a
+//! stable representation of its contents:
+//!
+//! * `config`: the [GraphConfig](../config/struct.GraphConfig.html) that the
+//!   graph was created with.
+//! * `vertices`: a sequence with the game state, data, and expansion state of
+//!   each vertex, in order of vertex ID, along with the IDs of its child and
+//!   parent edges in order.
+//! * `edges`: a sequence of tuples of the source vertex ID, target vertex ID,
+//!   and data of each edge, in order of edge ID.
+//! * `aliases`: a sequence of tuples of each
+//!   [alias](../alias/index.html) and the ID of the vertex that it addresses,
+//!   in order of vertex ID.
+//! * `unexpanded`: a sequence of tuples of a vertex ID and the data of the
+//!   unexpanded child edges of that vertex, in order of vertex ID.
+//!
+//! A deserialized graph therefore has the same vertex and edge IDs, mapping
+//! from game states to vertices, and order of child and parent edges as the
+//! graph that was serialized. Everything else that a graph maintains is
+//! rebuilt or reset, as when a graph is created with its configuration:
+//! statistics, [hot data](../hot/index.html), recency ticks, and the
+//! [audit trail](../audit/index.html) start over, and child indices, child
+//! heaps, automatic pruning, duplicate policies, and spill stores must be set
+//! up again. Deserialization fails if the input is inconsistent (e.g., if an
+//! edge refers to a vertex that does not exist, or a child list does not
+//! contain exactly the edges from its vertex).
+//!
+//! ```rust
+//! # use search_graph::Graph;
+//! # fn main() {
+//! let mut g: Graph<String, u32, char> = Graph::new();
+//! g.add_edge("root".to_string(), |_| 1, "a".to_string(), |_| 2, 'x');
+//! g.add_edge("a".to_string(), |_| 2, "root".to_string(), |_| 1, 'y');
+//! let json = serde_json::to_string(&g).unwrap();
+//! let h: Graph<String, u32, char> = serde_json::from_str(&json).unwrap();
+//! let a = h.find_node(&"a".to_string()).unwrap();
+//! assert_eq!(a.get_id(), 1);
+//! assert_eq!(*a.get_child_list().get_edge(0).get_data(), 'y');
+//! # }
+//! ```
+
+use std::hash::Hash;
+
+use serde::de::{Deserialize, Deserializer, Error};
+use serde::ser::{Serialize, Serializer};
+
+use crate::base::{EdgeId, VertexId};
+use crate::config::GraphConfig;
+use crate::nav::Expansion;
+use crate::Graph;
+use symbol_map::indexing::Indexing;
+use symbol_map::SymbolId;
+
+/// A vertex, as borrowed for serialization.
+#[derive(serde::Serialize)]
+struct VertexRef<'a, T, S> {
+  state: &'a T,
+  data: &'a S,
+  expansion: Expansion,
+  children: Vec<usize>,
+  parents: Vec<usize>,
+}
+
+/// A graph, as borrowed for serialization.
+#[derive(serde::Serialize)]
+struct GraphRef<'a, T, S, A> {
+  config: &'a GraphConfig,
+  vertices: Vec<VertexRef<'a, T, S>>,
+  edges: Vec<(usize, usize, &'a A)>,
+  aliases: Vec<(&'a T, usize)>,
+  unexpanded: Vec<(usize, &'a [A])>,
+}
+
+/// A vertex, as deserialized.
+#[derive(serde::Deserialize)]
+struct VertexParts<T, S> {
+  state: T,
+  data: S,
+  expansion: Expansion,
+  children: Vec<usize>,
+  parents: Vec<usize>,
+}
+
+/// A graph, as deserialized.
+#[derive(serde::Deserialize)]
+struct GraphParts<T, S, A> {
+  config: GraphConfig,
+  vertices: Vec<VertexParts<T, S>>,
+  edges: Vec<(usize, usize, A)>,
+  aliases: Vec<(T, usize)>,
+  unexpanded: Vec<(usize, Vec<A>)>,
+}
+
+fn ids(edges: &[EdgeId]) -> Vec<usize> {
+  edges.iter().map(|id| id.as_usize()).collect()
+}
+
+impl<T, S, A> Serialize for Graph<T, S, A>
+where
+  T: Hash + Eq + Clone + Serialize,
+  S: Serialize,
+  A: Serialize,
+{
+  fn serialize<R: Serializer>(&self, serializer: R) -> Result<R::Ok, R::Error> {
+    let mut labels: Vec<Option<&T>> = vec![None; self.vertices.len()];
+    for symbol in self.state_ids.table() {
+      labels[symbol.id().as_usize()] = Some(symbol.data());
+    }
+    let vertices = self
+      .vertices
+      .iter()
+      .zip(labels)
+      .map(|(vertex, state)| VertexRef {
+        state: state.unwrap(),
+        data: &vertex.data,
+        expansion: vertex.expansion,
+        children: ids(&vertex.children),
+        parents: ids(&vertex.parents),
+      })
+      .collect();
+    let edges = self
+      .arcs
+      .iter()
+      .map(|arc| (arc.source.as_usize(), arc.target.as_usize(), &arc.data))
+      .collect();
+    let mut aliases: Vec<(&T, usize)> = self
+      .aliases
+      .iter()
+      .map(|(alias, id)| (alias, id.as_usize()))
+      .collect();
+    aliases.sort_by_key(|(_, id)| *id);
+    let mut unexpanded: Vec<(usize, &[A])> = self
+      .unexpanded
+      .iter()
+      .filter(|(_, edges)| !edges.is_empty())
+      .map(|(id, edges)| (id.as_usize(), &edges[..]))
+      .collect();
+    unexpanded.sort_by_key(|(id, _)| *id);
+    GraphRef {
+      config: &self.config,
+      vertices,
+      edges,
+      aliases,
+      unexpanded,
+    }
+    .serialize(serializer)
+  }
+}
+
+impl<'de, T, S, A> Deserialize<'de> for Graph<T, S, A>
+where
+  T: Hash + Eq + Clone + Deserialize<'de>,
+  S: Deserialize<'de>,
+  A: Deserialize<'de>,
+{
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    GraphParts::deserialize(deserializer)?
+      .build()
+      .map_err(D::Error::custom)
+  }
+}
+
+impl<T: Hash + Eq + Clone, S, A> GraphParts<T, S, A> {
+  /// Builds the graph that these parts describe, or describes how they are
+  /// inconsistent.
+  fn build(self) -> Result<Graph<T, S, A>, String> {
+    #[cfg(feature = "hashkeys")]
+    {
+      if self.config.key_bits == 0 || self.config.key_bits > 64 {
+        return Err(format!("Invalid key width {}", self.config.key_bits));
+      }
+    }
+    let edge_count = self.edges.len();
+    let mut vertices = Vec::with_capacity(self.vertices.len());
+    let mut lists = Vec::with_capacity(self.vertices.len());
+    for v in self.vertices {
+      vertices.push((v.state, v.data));
+      lists.push((v.expansion, v.children, v.parents));
+    }
+    let graph = Graph::with_config(self.config);
+    let mut graph =
+      Graph::build_from_parts(graph, vertices, self.edges).map_err(|e| e.to_string())?;
+    if graph.arcs.len() != edge_count {
+      return Err("Parallel edges in a graph that merges them".to_string());
+    }
+    for (i, (expansion, children, parents)) in lists.into_iter().enumerate() {
+      let vertex = graph.get_vertex_mut(VertexId(i));
+      vertex.expansion = expansion;
+      vertex.children = reorder(&vertex.children, children)
+        .ok_or_else(|| format!("Child list of vertex {} does not match its edges", i))?;
+      vertex.parents = reorder(&vertex.parents, parents)
+        .ok_or_else(|| format!("Parent list of vertex {} does not match its edges", i))?;
+    }
+    graph.sort_parents();
+    let vertex_count = graph.vertices.len();
+    for (alias, id) in self.aliases {
+      if id >= vertex_count {
+        return Err(format!("Alias refers to vertex {}/{}", id, vertex_count));
+      }
+      if graph.lookup(&alias).is_some() {
+        return Err(format!("Alias of vertex {} is already in use", id));
+      }
+      graph.aliases.insert(alias, VertexId(id));
+    }
+    for (id, edges) in self.unexpanded {
+      if id >= vertex_count {
+        return Err(format!(
+          "Unexpanded edges refer to vertex {}/{}",
+          id, vertex_count
+        ));
+      }
+      graph
+        .unexpanded
+        .entry(VertexId(id))
+        .or_default()
+        .extend(edges);
+    }
+    Ok(graph)
+  }
+}
+
+/// Returns the edges in `edges`, in the order given by `order`, or `None` if
+/// `order` is not a permutation of them.
+fn reorder(edges: &[EdgeId], order: Vec<usize>) -> Option<Vec<EdgeId>> {
+  let mut expected = ids(edges);
+  let mut actual = order.clone();
+  expected.sort_unstable();
+  actual.sort_unstable();
+  if expected == actual {
+    Some(order.into_iter().map(EdgeId).collect())
+  } else {
+    None
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use crate::config::{GraphConfig, ParallelEdges};
+  use crate::nav::Expansion;
+
+  type Graph = crate::Graph<String, u32, String>;
+
+  fn s(x: &str) -> String {
+    x.to_string()
+  }
+
+  fn make_graph() -> Graph {
+    let mut g = Graph::new();
+    g.add_edge(s("root"), |_| 1, s("a"), |_| 2, s("root_a"));
+    g.add_edge(s("root"), |_| 1, s("b"), |_| 3, s("root_b"));
+    g.add_edge(s("a"), |_| 2, s("b"), |_| 3, s("a_b"));
+    // A parallel edge and a cycle.
+    g.add_edge(s("a"), |_| 2, s("b"), |_| 3, s("a_b2"));
+    g.add_edge(s("b"), |_| 3, s("root"), |_| 1, s("b_root"));
+    g.alias_state(s("a'"), &s("a")).unwrap();
+    g.find_node_mut(&s("root")).unwrap().mark_expanded();
+    g.find_node_mut(&s("b"))
+      .unwrap()
+      .get_child_list_mut()
+      .add_unexpanded_child(s("b_c"));
+    // Reassign IDs, so that child order no longer follows edge IDs.
+    g.compact_with(&[s("b")], &mut []);
+    g
+  }
+
+  fn assert_same(g: &Graph, h: &Graph) {
+    assert_eq!(g.iter().collect::<Vec<_>>(), h.iter().collect::<Vec<_>>());
+    for state in g.states() {
+      let (u, v) = (g.find_node(state).unwrap(), h.find_node(state).unwrap());
+      assert_eq!(u.get_id(), v.get_id());
+      assert_eq!(u.get_expansion(), v.get_expansion());
+      let edges = |n: &crate::nav::Node<String, u32, String>| {
+        let children: Vec<(usize, String)> = n
+          .get_child_list()
+          .iter()
+          .map(|e| (e.get_id(), e.get_data().clone()))
+          .collect();
+        let parents: Vec<usize> = n.get_parent_list().iter().map(|e| e.get_id()).collect();
+        (children, parents)
+      };
+      assert_eq!(edges(&u), edges(&v));
+    }
+    assert_eq!(g.config(), h.config());
+    assert_eq!(
+      g.find_node(&s("a'")).unwrap().get_id(),
+      h.find_node(&s("a'")).unwrap().get_id()
+    );
+  }
+
+  #[test]
+  fn serde_round_trip_ok() {
+    let g = make_graph();
+    let json = serde_json::to_string(&g).unwrap();
+    let mut h: Graph = serde_json::from_str(&json).unwrap();
+    assert_same(&g, &h);
+    assert_eq!(json, serde_json::to_string(&h).unwrap());
+    let mut b = h.find_node_mut(&s("b")).unwrap();
+    let children = b.get_child_list_mut();
+    assert_eq!(1, children.unexpanded_len());
+    let edge = children.to_unexpanded(0).unwrap().expand(s("c"), || 4);
+    assert_eq!("b_c", edge.get_data());
+
+    let bytes = bincode::serialize(&g).unwrap();
+    let h: Graph = bincode::deserialize(&bytes).unwrap();
+    assert_same(&g, &h);
+
+    let mut g = Graph::with_config(GraphConfig {
+      track_parents: false,
+      parallel_edges: ParallelEdges::Merge,
+      ..Default::default()
+    });
+    g.add_edge(s("root"), |_| 1, s("a"), |_| 2, s("root_a"));
+    g.add_edge(s("a"), |_| 2, s("root"), |_| 1, s("a_root"));
+    let h: Graph = serde_json::from_str(&serde_json::to_string(&g).unwrap()).unwrap();
+    assert_eq!(g.config(), h.config());
+    assert_eq!(2, h.edge_count());
+    assert_eq!(
+      Expansion::PartiallyExpanded,
+      h.find_node(&s("a")).unwrap().get_expansion()
+    );
+  }
+
+  #[test]
+  fn serde_round_trip_err() {
+    let json = serde_json::to_string(&make_graph()).unwrap();
+    let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    value["edges"][0][1] = 7.into();
+    assert!(serde_json::from_value::<Graph>(value)
+      .err()
+      .unwrap()
+      .to_string()
+      .contains("refers to vertex 7/3"));
+
+    let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    value["vertices"][0]["children"] = serde_json::json!([]);
+    assert!(serde_json::from_value::<Graph>(value)
+      .err()
+      .unwrap()
+      .to_string()
+      .contains("Child list of vertex 0"));
+
+    let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    value["aliases"] = serde_json::json!([["a", 0]]);
+    assert!(serde_json::from_value::<Graph>(value)
+      .err()
+      .unwrap()
+      .to_string()
+      .contains("already in use"));
+  }
+}
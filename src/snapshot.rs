@@ -0,0 +1,541 @@
+//! Immutable snapshots of a graph that may be read while the graph changes.
+//!
+//! A [Snapshot](struct.Snapshot.html) is created with
+//! [Graph::snapshot](../struct.Graph.html#method.snapshot). It owns a frozen
+//! copy of the graph's topology and data, so it may be sent to other threads
+//! and traversed there while the thread that owns the graph keeps mutating
+//! it.
+//!
+//! Snapshots are stored in fixed-size pages that are shared between
+//! successive snapshots of the same graph with copy-on-write semantics: a
+//! graph records which pages have been modified since its last snapshot, and
+//! only those pages (and pages for newly added vertices and edges) are copied
+//! when the next snapshot is taken. Garbage collection reassigns vertex and
+//! edge IDs, so the first snapshot after a collection copies the whole graph.
+//!
+//! ```rust
+//! # use search_graph::Graph;
+//! # fn main() {
+//! let mut g: Graph<&str, u32, ()> = Graph::new();
+//! g.add_edge("root", |_| 0, "a", |_| 0, ());
+//! let snapshot = g.snapshot();
+//! let reader = std::thread::spawn(move || {
+//!   let root = snapshot.find_node(&"root").unwrap();
+//!   root.children().map(|e| *e.get_target().get_label()).collect::<Vec<_>>()
+//! });
+//! g.add_edge("root", |_| 0, "b", |_| 0, ());
+//! *g.find_node_mut(&"a").unwrap().get_data_mut() = 10;
+//! assert_eq!(reader.join().unwrap(), vec!["a"]);
+//! assert_eq!(*g.snapshot().find_node(&"a").unwrap().get_data(), 10);
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Arc;
+
+use crate::base::{EdgeId, RawEdge, RawVertex, VertexId};
+use crate::config::ParentsUntracked;
+use crate::nav::Expansion;
+use crate::Graph;
+use symbol_map::SymbolId;
+
+/// Number of vertices or edges in each page of a snapshot.
+const PAGE_SIZE: usize = 256;
+
+type Pages<X> = Vec<Arc<Vec<X>>>;
+
+/// The contents of a snapshot.
+struct Frozen<T, S, A> {
+  version: u64,
+  vertex_count: usize,
+  edge_count: usize,
+  labels: Pages<T>,
+  vertices: Pages<RawVertex<S>>,
+  arcs: Pages<RawEdge<A>>,
+  /// Maps game states to vertex IDs. Each segment holds the game states that
+  /// were added between two snapshots, with older segments first.
+  index: Vec<Arc<HashMap<T, VertexId>>>,
+  aliases: Arc<HashMap<T, VertexId>>,
+  /// Whether the graph tracked incoming edges.
+  track_parents: bool,
+}
+
+/// Tracks the pages of the most recent snapshot of a graph that the graph has
+/// modified since.
+pub(crate) struct SnapshotCache<T, S, A> {
+  last: Arc<Frozen<T, S, A>>,
+  dirty_vertices: Vec<bool>,
+  dirty_arcs: Vec<bool>,
+}
+
+impl<T, S, A> SnapshotCache<T, S, A> {
+  fn touch(dirty: &mut [bool], index: usize) {
+    if let Some(page) = dirty.get_mut(index / PAGE_SIZE) {
+      *page = true;
+    }
+  }
+}
+
+/// Returns pages holding `len` elements, where the element at index `i` is
+/// `get(i)`. Pages are reused from `old` unless they are marked in `dirty` or
+/// have changed length.
+fn build_pages<X, F: Fn(usize) -> X>(
+  old: Option<(&Pages<X>, &[bool])>,
+  len: usize,
+  get: F,
+) -> Pages<X> {
+  (0..len)
+    .step_by(PAGE_SIZE)
+    .enumerate()
+    .map(|(page, start)| {
+      let end = (start + PAGE_SIZE).min(len);
+      if let Some((pages, dirty)) = old {
+        if let Some(old_page) = pages.get(page) {
+          if old_page.len() == end - start && !dirty.get(page).cloned().unwrap_or(false) {
+            return old_page.clone();
+          }
+        }
+      }
+      Arc::new((start..end).map(&get).collect())
+    })
+    .collect()
+}
+
+impl<T: Hash + Eq + Clone, S, A> Graph<T, S, A> {
+  /// Records that the vertex `id` is being modified.
+  pub(crate) fn touch_vertex(&mut self, id: VertexId) {
+    self.modifications = self.modifications.wrapping_add(1);
+    if let Some(ref mut cache) = self.snapshot_cache {
+      SnapshotCache::<T, S, A>::touch(&mut cache.dirty_vertices, id.as_usize());
+    }
+  }
+
+  /// Records that the edge `id` is being modified.
+  pub(crate) fn touch_arc(&mut self, id: EdgeId) {
+    self.modifications = self.modifications.wrapping_add(1);
+    if let Some(ref mut cache) = self.snapshot_cache {
+      SnapshotCache::<T, S, A>::touch(&mut cache.dirty_arcs, id.as_usize());
+    }
+  }
+
+  /// Returns an immutable snapshot of the current state of this graph. The
+  /// snapshot is not affected by later changes to the graph.
+  ///
+  /// Taking a snapshot copies only the parts of the graph that have changed
+  /// since the previous snapshot (if any), and sharing a snapshot with
+  /// another reader is as cheap as cloning an `Arc`.
+  pub fn snapshot(&mut self) -> Snapshot<T, S, A>
+  where
+    S: Clone,
+    A: Clone,
+  {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::debug_span!(
+      "snapshot",
+      vertices = self.vertices.len(),
+      edges = self.arcs.len()
+    )
+    .entered();
+    let cache = match self.snapshot_cache.take() {
+      Some(cache) if cache.last.version == self.version => Some(cache),
+      _ => None,
+    };
+    let old = cache.as_ref().map(|c| &*c.last);
+    let labels = build_pages(
+      old.map(|o| (&o.labels, &[][..])),
+      self.vertices.len(),
+      |i| self.get_state(VertexId(i)).unwrap().clone(),
+    );
+    let vertices = build_pages(
+      cache
+        .as_ref()
+        .map(|c| (&c.last.vertices, &c.dirty_vertices[..])),
+      self.vertices.len(),
+      |i| self.vertices[i].clone(),
+    );
+    let arcs = build_pages(
+      cache.as_ref().map(|c| (&c.last.arcs, &c.dirty_arcs[..])),
+      self.arcs.len(),
+      |i| self.arcs[i].clone(),
+    );
+    let (mut index, indexed) = match old {
+      Some(old) => (old.index.clone(), old.vertex_count),
+      None => (Vec::new(), 0),
+    };
+    if indexed < self.vertices.len() {
+      let segment = (indexed..self.vertices.len())
+        .map(|i| (self.get_state(VertexId(i)).unwrap().clone(), VertexId(i)))
+        .collect();
+      index.push(Arc::new(segment));
+    }
+    // Merge segments so that each is larger than the next, which keeps their
+    // number logarithmic in the number of game states.
+    while index.len() >= 2 && index[index.len() - 1].len() >= index[index.len() - 2].len() {
+      let newer = index.pop().unwrap();
+      let older = index.pop().unwrap();
+      let mut merged = (*older).clone();
+      merged.extend(newer.iter().map(|(state, id)| (state.clone(), *id)));
+      index.push(Arc::new(merged));
+    }
+    let frozen = Arc::new(Frozen {
+      version: self.version,
+      vertex_count: self.vertices.len(),
+      edge_count: self.arcs.len(),
+      labels,
+      vertices,
+      arcs,
+      index,
+      aliases: Arc::new(self.aliases.clone()),
+      track_parents: self.config.track_parents,
+    });
+    self.snapshot_cache = Some(Box::new(SnapshotCache {
+      last: frozen.clone(),
+      dirty_vertices: vec![false; frozen.vertices.len()],
+      dirty_arcs: vec![false; frozen.arcs.len()],
+    }));
+    Snapshot { frozen }
+  }
+}
+
+/// An immutable copy of a graph at the time it was taken with
+/// [Graph::snapshot](../struct.Graph.html#method.snapshot).
+///
+/// Cloning a `Snapshot` is cheap, and snapshots may be sent to other threads
+/// when their game states and data may be.
+pub struct Snapshot<T, S, A> {
+  frozen: Arc<Frozen<T, S, A>>,
+}
+
+impl<T, S, A> Clone for Snapshot<T, S, A> {
+  fn clone(&self) -> Self {
+    Snapshot {
+      frozen: self.frozen.clone(),
+    }
+  }
+}
+
+impl<T: Hash + Eq, S, A> Snapshot<T, S, A> {
+  fn vertex(&self, id: VertexId) -> &RawVertex<S> {
+    let i = id.as_usize();
+    &self.frozen.vertices[i / PAGE_SIZE][i % PAGE_SIZE]
+  }
+
+  fn arc(&self, id: EdgeId) -> &RawEdge<A> {
+    let i = id.as_usize();
+    &self.frozen.arcs[i / PAGE_SIZE][i % PAGE_SIZE]
+  }
+
+  fn label(&self, id: VertexId) -> &T {
+    let i = id.as_usize();
+    &self.frozen.labels[i / PAGE_SIZE][i % PAGE_SIZE]
+  }
+
+  /// Returns the graph version at which this snapshot was taken. This changes
+  /// when the graph is garbage-collected.
+  pub fn version(&self) -> u64 {
+    self.frozen.version
+  }
+
+  /// Returns the number of vertices in the snapshot.
+  pub fn vertex_count(&self) -> usize {
+    self.frozen.vertex_count
+  }
+
+  /// Returns the number of edges in the snapshot.
+  pub fn edge_count(&self) -> usize {
+    self.frozen.edge_count
+  }
+
+  /// Returns a handle for the vertex for `state` (which may be an alias), or
+  /// `None` if there was no such vertex when the snapshot was taken.
+  pub fn find_node<'s>(&'s self, state: &T) -> Option<SnapshotNode<'s, T, S, A>> {
+    let id = self
+      .frozen
+      .index
+      .iter()
+      .rev()
+      .find_map(|segment| segment.get(state))
+      .or_else(|| self.frozen.aliases.get(state))?;
+    Some(SnapshotNode {
+      snapshot: self,
+      id: *id,
+    })
+  }
+
+  /// Returns a handle for the vertex with the given ID (as returned by
+  /// `get_id` on node handles when the snapshot was taken), or `None` if
+  /// there is no such vertex.
+  pub fn node<'s>(&'s self, id: usize) -> Option<SnapshotNode<'s, T, S, A>> {
+    if id < self.frozen.vertex_count {
+      Some(SnapshotNode {
+        snapshot: self,
+        id: VertexId(id),
+      })
+    } else {
+      None
+    }
+  }
+}
+
+/// Handle to a vertex in a [Snapshot](struct.Snapshot.html).
+pub struct SnapshotNode<'s, T, S, A> {
+  snapshot: &'s Snapshot<T, S, A>,
+  id: VertexId,
+}
+
+impl<'s, T, S, A> Clone for SnapshotNode<'s, T, S, A> {
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+
+impl<'s, T, S, A> Copy for SnapshotNode<'s, T, S, A> {}
+
+impl<'s, T: Hash + Eq, S, A> SnapshotNode<'s, T, S, A> {
+  /// Returns the canonical label of this vertex.
+  pub fn get_label(&self) -> &'s T {
+    self.snapshot.label(self.id)
+  }
+
+  /// Returns the ID of this vertex when the snapshot was taken.
+  pub fn get_id(&self) -> usize {
+    self.id.as_usize()
+  }
+
+  /// Returns the data at this vertex.
+  pub fn get_data(&self) -> &'s S {
+    &self.snapshot.vertex(self.id).data
+  }
+
+  /// Returns how much of this vertex's set of children had been generated.
+  pub fn get_expansion(&self) -> Expansion {
+    self.snapshot.vertex(self.id).expansion
+  }
+
+  /// Returns true iff this vertex has no outgoing edges.
+  pub fn is_leaf(&self) -> bool {
+    self.snapshot.vertex(self.id).children.is_empty()
+  }
+
+  /// Returns true iff this vertex has no incoming edges.
+  ///
+  /// Panics if the graph did not track incoming edges (see
+  /// [GraphConfig](../config/struct.GraphConfig.html)). The same is true of
+  /// `parents`.
+  pub fn is_root(&self) -> bool {
+    self.parents().next().is_none()
+  }
+
+  /// Returns a list of the outgoing edges of this vertex, which may be
+  /// indexed.
+  pub fn get_child_list(&self) -> SnapshotChildList<'s, T, S, A> {
+    SnapshotChildList {
+      snapshot: self.snapshot,
+      id: self.id,
+    }
+  }
+
+  /// Returns an iterator over the outgoing edges of this vertex.
+  pub fn children(&self) -> impl ExactSizeIterator<Item = SnapshotEdge<'s, T, S, A>> + 's {
+    let snapshot = self.snapshot;
+    snapshot
+      .vertex(self.id)
+      .children
+      .iter()
+      .map(move |id| SnapshotEdge { snapshot, id: *id })
+  }
+
+  /// Returns an iterator over the incoming edges of this vertex.
+  pub fn parents(&self) -> impl ExactSizeIterator<Item = SnapshotEdge<'s, T, S, A>> + 's {
+    let snapshot = self.snapshot;
+    if !snapshot.frozen.track_parents {
+      panic!("{}", ParentsUntracked);
+    }
+    snapshot
+      .vertex(self.id)
+      .parents
+      .iter()
+      .map(move |id| SnapshotEdge { snapshot, id: *id })
+  }
+}
+
+/// The outgoing edges of a vertex in a [Snapshot](struct.Snapshot.html).
+pub struct SnapshotChildList<'s, T, S, A> {
+  snapshot: &'s Snapshot<T, S, A>,
+  id: VertexId,
+}
+
+impl<'s, T, S, A> Clone for SnapshotChildList<'s, T, S, A> {
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+
+impl<'s, T, S, A> Copy for SnapshotChildList<'s, T, S, A> {}
+
+impl<'s, T: Hash + Eq, S, A> SnapshotChildList<'s, T, S, A> {
+  /// Returns the number of outgoing edges.
+  pub fn len(&self) -> usize {
+    self.snapshot.vertex(self.id).children.len()
+  }
+
+  /// Returns true iff there are no outgoing edges.
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /// Returns a handle for the `i`th outgoing edge. Panics if there are `i` or
+  /// fewer outgoing edges.
+  pub fn get_edge(&self, i: usize) -> SnapshotEdge<'s, T, S, A> {
+    SnapshotEdge {
+      snapshot: self.snapshot,
+      id: self.snapshot.vertex(self.id).children[i],
+    }
+  }
+}
+
+/// Handle to an edge in a [Snapshot](struct.Snapshot.html).
+pub struct SnapshotEdge<'s, T, S, A> {
+  snapshot: &'s Snapshot<T, S, A>,
+  id: EdgeId,
+}
+
+impl<'s, T, S, A> Clone for SnapshotEdge<'s, T, S, A> {
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+
+impl<'s, T, S, A> Copy for SnapshotEdge<'s, T, S, A> {}
+
+impl<'s, T: Hash + Eq, S, A> SnapshotEdge<'s, T, S, A> {
+  /// Returns the ID of this edge when the snapshot was taken.
+  pub fn get_id(&self) -> usize {
+    self.id.as_usize()
+  }
+
+  /// Returns the data at this edge.
+  pub fn get_data(&self) -> &'s A {
+    &self.snapshot.arc(self.id).data
+  }
+
+  /// Returns a handle for this edge's source vertex.
+  pub fn get_source(&self) -> SnapshotNode<'s, T, S, A> {
+    SnapshotNode {
+      snapshot: self.snapshot,
+      id: self.snapshot.arc(self.id).source,
+    }
+  }
+
+  /// Returns a handle for this edge's target vertex.
+  pub fn get_target(&self) -> SnapshotNode<'s, T, S, A> {
+    SnapshotNode {
+      snapshot: self.snapshot,
+      id: self.snapshot.arc(self.id).target,
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::PAGE_SIZE;
+  use std::sync::Arc;
+
+  type Graph = crate::Graph<u32, u32, u32>;
+
+  /// A chain of `n` vertices, where each vertex's data is its game state.
+  fn make_graph(n: u32) -> Graph {
+    let mut g = Graph::new();
+    for i in 1..n {
+      g.add_edge(i - 1, |s| *s.get_label(), i, |s| *s.get_label(), i);
+    }
+    g
+  }
+
+  #[test]
+  fn snapshot_isolation_ok() {
+    let mut g = make_graph(3);
+    let snapshot = g.snapshot();
+    g.add_edge(2, |_| 2, 3, |_| 3, 3);
+    *g.find_node_mut(&1).unwrap().get_data_mut() = 100;
+    g.find_node_mut(&2).unwrap().mark_expanded();
+    assert_eq!(3, snapshot.vertex_count());
+    assert_eq!(2, snapshot.edge_count());
+    assert!(snapshot.find_node(&3).is_none());
+    let two = snapshot.find_node(&2).unwrap();
+    assert!(two.is_leaf());
+    assert_eq!(crate::nav::Expansion::Unexpanded, two.get_expansion());
+    assert_eq!(1, *snapshot.find_node(&1).unwrap().get_data());
+
+    let later = g.snapshot();
+    assert_eq!(4, later.vertex_count());
+    assert_eq!(100, *later.find_node(&1).unwrap().get_data());
+    let path: Vec<u32> = later
+      .find_node(&2)
+      .unwrap()
+      .children()
+      .map(|e| *e.get_target().get_label())
+      .collect();
+    assert_eq!(vec![3], path);
+    let edge = later.find_node(&3).unwrap().parents().next().unwrap();
+    assert_eq!(2, *edge.get_source().get_label());
+    assert_eq!(3, *edge.get_data());
+    assert!(later.node(4).is_none());
+  }
+
+  #[test]
+  fn snapshot_shares_pages_ok() {
+    let n = 3 * PAGE_SIZE as u32;
+    let mut g = make_graph(n);
+    let first = g.snapshot();
+    // Modify a vertex in the second page and add a vertex in a new page.
+    *g.find_node_mut(&(PAGE_SIZE as u32 + 1))
+      .unwrap()
+      .get_data_mut() = 0;
+    g.add_edge(n - 1, |_| 0, n, |_| 0, 0);
+    let second = g.snapshot();
+    let (a, b) = (&first.frozen, &second.frozen);
+    assert!(Arc::ptr_eq(&a.vertices[0], &b.vertices[0]));
+    assert!(!Arc::ptr_eq(&a.vertices[1], &b.vertices[1]));
+    assert!(!Arc::ptr_eq(&a.vertices[2], &b.vertices[2]));
+    assert_eq!(4, b.vertices.len());
+    assert!(Arc::ptr_eq(&a.labels[1], &b.labels[1]));
+    assert!(Arc::ptr_eq(&a.arcs[0], &b.arcs[0]));
+    assert_eq!(
+      PAGE_SIZE as u32 + 1,
+      *first.node(PAGE_SIZE + 1).unwrap().get_data()
+    );
+    assert_eq!(0, *second.node(PAGE_SIZE + 1).unwrap().get_data());
+    for i in 0..=n {
+      assert_eq!(i as usize, second.find_node(&i).unwrap().get_id());
+    }
+  }
+
+  #[test]
+  fn snapshot_after_gc_ok() {
+    let mut g = make_graph(4);
+    let before = g.snapshot();
+    g.compact_with(&[2], &mut []);
+    g.alias_state(20, &2).unwrap();
+    let after = g.snapshot();
+    assert_eq!(4, before.vertex_count());
+    assert_eq!(2, after.vertex_count());
+    assert_eq!(0, after.find_node(&2).unwrap().get_id());
+    assert_eq!(2, *after.find_node(&20).unwrap().get_label());
+    assert!(after.find_node(&0).is_none());
+    assert_eq!(2, before.find_node(&2).unwrap().get_id());
+  }
+
+  #[test]
+  fn snapshot_send_ok() {
+    let mut g = make_graph(3);
+    let snapshot = g.snapshot();
+    let reader = {
+      let snapshot = snapshot.clone();
+      std::thread::spawn(move || snapshot.find_node(&2).map(|n| *n.get_data()))
+    };
+    *g.find_node_mut(&2).unwrap().get_data_mut() = 7;
+    assert_eq!(Some(2), reader.join().unwrap());
+  }
+}
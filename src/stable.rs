@@ -0,0 +1,198 @@
+//! Vertex IDs that are derived from game states, for exchanging messages
+//! about vertices between processes.
+//!
+//! Vertex IDs (as returned by `get_id` on node handles) depend on the order in
+//! which vertices were added and are reassigned by garbage collection, so two
+//! processes that build the same graph generally disagree about them. A graph
+//! created with
+//! [GraphConfig::stable_ids](../config/struct.GraphConfig.html#structfield.stable_ids)
+//! also assigns each vertex a 64-bit stable ID that is the hash of its
+//! canonical game state. Stable IDs are returned by
+//! [Graph::stable_id](../struct.Graph.html#method.stable_id) and
+//! [Node::get_stable_id](../nav/struct.Node.html#method.get_stable_id), and
+//! resolved with
+//! [Graph::find_node_by_stable_id](../struct.Graph.html#method.find_node_by_stable_id).
+//!
+//! If the hash of a new game state is already the stable ID of another vertex,
+//! the new vertex takes the next unused value after it, wrapping around. Two
+//! processes therefore assign identical stable IDs to the same game states
+//! unless their hashes collide, and even then if the colliding game states
+//! were added in the same relative order. A stable ID is kept through garbage
+//! collection and partitioning, and becomes free again when its vertex is
+//! dropped. Hashes are computed with the standard library's default hasher
+//! with fixed keys, so processes must be built with the same Rust release and
+//! hash game states identically.
+//!
+//! ```rust
+//! # use search_graph::Graph;
+//! # use search_graph::config::GraphConfig;
+//! # fn main() {
+//! let config = GraphConfig {
+//!   stable_ids: true,
+//!   ..Default::default()
+//! };
+//! let mut a: Graph<&str, (), ()> = Graph::with_config(config);
+//! a.add_edge("root", |_| (), "x", |_| (), ());
+//! let mut b: Graph<&str, (), ()> = Graph::with_config(config);
+//! b.add_node("x", ());
+//! let id = a.stable_id(&"x").unwrap();
+//! assert_eq!(b.stable_id(&"x"), Some(id));
+//! assert_eq!(b.find_node_by_stable_id(id).unwrap().get_label(), &"x");
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::base::VertexId;
+use crate::moves::hash_key;
+use crate::nav::Node;
+use crate::Graph;
+use symbol_map::SymbolId;
+
+/// The stable ID of each vertex in a graph, and the vertex for each stable ID.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct StableIds {
+  /// Stable IDs in order of `VertexId`.
+  pub ids: Vec<u64>,
+  index: HashMap<u64, VertexId>,
+}
+
+impl StableIds {
+  /// Assigns a stable ID derived from `state` to a vertex, which must be the
+  /// next in ID order.
+  pub fn create<T: Hash>(&mut self, state: &T) {
+    let id = VertexId(self.ids.len());
+    let mut stable = hash_key(state);
+    while self.index.contains_key(&stable) {
+      stable = stable.wrapping_add(1);
+    }
+    self.index.insert(stable, id);
+    self.ids.push(stable);
+  }
+
+  /// Replaces the stable ID of the vertex with the given index by `stable`,
+  /// which must not be the stable ID of another vertex.
+  pub fn replace(&mut self, index: usize, stable: u64) {
+    let old = std::mem::replace(&mut self.ids[index], stable);
+    self.index.remove(&old);
+    self.index.insert(stable, VertexId(index));
+  }
+
+  /// Rebuilds the index from stable IDs after vertices have been dropped or
+  /// reordered.
+  pub fn reindex(&mut self) {
+    self.index = self
+      .ids
+      .iter()
+      .enumerate()
+      .map(|(i, stable)| (*stable, VertexId(i)))
+      .collect();
+  }
+
+  /// Returns true iff there is a stable ID for each of `vertex_count`
+  /// vertices, and the index maps each stable ID back to its vertex.
+  pub fn is_consistent(&self, vertex_count: usize) -> bool {
+    self.ids.len() == vertex_count
+      && self.index.len() == vertex_count
+      && self
+        .ids
+        .iter()
+        .enumerate()
+        .all(|(i, stable)| self.get(*stable) == Some(VertexId(i)))
+  }
+
+  /// Returns the vertex with the given stable ID.
+  pub fn get(&self, stable: u64) -> Option<VertexId> {
+    self.index.get(&stable).cloned()
+  }
+}
+
+impl<T: Hash + Eq + Clone, S, A> Graph<T, S, A> {
+  /// Returns the stable ID of the vertex for `state`, or `None` if there is no
+  /// such vertex or the graph does not assign stable IDs (see
+  /// [stable](stable/index.html)).
+  pub fn stable_id(&self, state: &T) -> Option<u64> {
+    let stable = self.stable.as_ref()?;
+    self.lookup(state).map(|id| stable.ids[id.as_usize()])
+  }
+
+  /// Gets a node handle for the vertex with the given stable ID, or `None` if
+  /// there is no such vertex or the graph does not assign stable IDs.
+  pub fn find_node_by_stable_id<'s>(&'s self, stable: u64) -> Option<Node<'s, T, S, A>> {
+    self
+      .stable
+      .as_ref()?
+      .get(stable)
+      .map(|id| Node::new(self, id))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::StableIds;
+  use crate::config::GraphConfig;
+  use crate::moves::hash_key;
+
+  type Graph = crate::Graph<u32, u32, ()>;
+
+  fn make_graph() -> Graph {
+    Graph::with_config(GraphConfig {
+      stable_ids: true,
+      ..Default::default()
+    })
+  }
+
+  #[test]
+  fn stable_id_ok() {
+    let mut a = make_graph();
+    let mut b = make_graph();
+    for i in 0..10 {
+      a.add_edge(i, |_| i, i + 1, |_| i + 1, ());
+      b.add_edge(10 - i, |_| 10 - i, 9 - i, |_| 9 - i, ());
+    }
+    for i in 0..=10 {
+      let stable = a.stable_id(&i).unwrap();
+      assert_eq!(hash_key(&i), stable);
+      assert_eq!(Some(stable), b.stable_id(&i));
+      assert_eq!(Some(stable), b.find_node(&i).unwrap().get_stable_id());
+      assert_eq!(i, *b.find_node_by_stable_id(stable).unwrap().get_label());
+    }
+    assert_eq!(None, a.stable_id(&11));
+    assert_eq!(None, Graph::new().stable_id(&0));
+    let mut g = Graph::new();
+    g.add_node(0, 0);
+    assert_eq!(None, g.find_node(&0).unwrap().get_stable_id());
+    assert!(g.find_node_by_stable_id(hash_key(&0)).is_none());
+  }
+
+  #[test]
+  fn stable_id_collision_ok() {
+    let mut ids = StableIds::default();
+    ids.create(&"a");
+    ids.create(&"a");
+    ids.create(&"a");
+    let h = hash_key(&"a");
+    assert_eq!(vec![h, h.wrapping_add(1), h.wrapping_add(2)], ids.ids);
+    assert_eq!(Some(crate::base::VertexId(1)), ids.get(h.wrapping_add(1)));
+  }
+
+  #[test]
+  fn stable_id_gc_ok() {
+    let mut g = make_graph();
+    for i in 0..5 {
+      g.add_edge(i, |_| i, i + 1, |_| i + 1, ());
+    }
+    let three = g.stable_id(&3).unwrap();
+    g.compact_with(&[3], &mut []);
+    assert_eq!(0, g.find_node(&3).unwrap().get_id());
+    assert_eq!(Some(three), g.stable_id(&3));
+    assert_eq!(3, *g.find_node_by_stable_id(three).unwrap().get_label());
+    assert!(g.find_node_by_stable_id(hash_key(&0)).is_none());
+
+    let (left, right) = g.partition(|state, _| *state == 5);
+    assert_eq!(Some(hash_key(&5)), left.stable_id(&5));
+    assert_eq!(Some(three), right.stable_id(&3));
+    assert_eq!(Some(three), right.topology_clone().stable_id(&3));
+  }
+}
@@ -0,0 +1,287 @@
+//! Buffers for recording graph insertions on other threads.
+//!
+//! A [Stage](struct.Stage.html) records prospective vertices and edges without
+//! touching the graph, so any number of stages may be filled in parallel (one
+//! per thread) while the graph is only borrowed immutably, or not at all.
+//! [Graph::commit](../struct.Graph.html#method.commit) then applies the
+//! contents of a sequence of stages to the graph. Stages are applied in the
+//! order given, and the contents of each stage in the order they were
+//! recorded, so the result does not depend on how threads were scheduled.
+//!
+//! When committed, game states that are already in the graph (or that were
+//! added by an earlier insertion in the same commit) resolve to the existing
+//! vertex, just as with [Graph::add_edge](../struct.Graph.html#method.add_edge),
+//! and the data staged for them is dropped, except that data staged with
+//! [Stage::add_node](struct.Stage.html#method.add_node) is handled according
+//! to the graph's [duplicate policy](../duplicate/index.html), as with
+//! [Graph::add_node](../struct.Graph.html#method.add_node). An edge is dropped
+//! as a duplicate if the graph already has an edge with the same source,
+//! target, and data, or with the same source and target if the graph merges
+//! parallel edges (see [ParallelEdges](../config/enum.ParallelEdges.html)).
+//!
+//! ```rust
+//! # use search_graph::Graph;
+//! # fn main() {
+//! let mut g: Graph<u32, (), u32> = Graph::new();
+//! g.add_node(1, ());
+//! let stages: Vec<_> = std::thread::scope(|s| {
+//!   let workers: Vec<_> = (0..4u32)
+//!     .map(|t| {
+//!       let mut stage = g.stage();
+//!       s.spawn(move || {
+//!         for m in 0..3 {
+//!           stage.add_edge(1, (), 10 + (t + m) % 4, (), m);
+//!         }
+//!         stage
+//!       })
+//!     })
+//!     .collect();
+//!   workers.into_iter().map(|w| w.join().unwrap()).collect()
+//! });
+//! let stats = g.commit(stages);
+//! assert_eq!(stats.vertices_added, 4);
+//! assert_eq!(stats.edges_added + stats.duplicate_edges, 12);
+//! assert_eq!(g.vertex_count(), 5);
+//! # }
+//! ```
+
+use std::hash::Hash;
+
+use crate::base::VertexId;
+use crate::Graph;
+use symbol_map::indexing::Insertion;
+
+/// An insertion recorded in a [Stage](struct.Stage.html).
+enum Staged<T, S, A> {
+  Node(T, S),
+  Edge {
+    source: T,
+    source_data: S,
+    dest: T,
+    dest_data: S,
+    data: A,
+  },
+}
+
+/// A buffer of prospective vertices and edges, to be applied to a graph with
+/// [Graph::commit](../struct.Graph.html#method.commit).
+pub struct Stage<T, S, A> {
+  insertions: Vec<Staged<T, S, A>>,
+}
+
+impl<T, S, A> Default for Stage<T, S, A> {
+  fn default() -> Self {
+    Stage::new()
+  }
+}
+
+impl<T, S, A> Stage<T, S, A> {
+  /// Creates an empty stage.
+  pub fn new() -> Self {
+    Stage {
+      insertions: Vec::new(),
+    }
+  }
+
+  /// Records a vertex for the game state `state` with data `data`, as with
+  /// [Graph::add_node](../struct.Graph.html#method.add_node).
+  pub fn add_node(&mut self, state: T, data: S) {
+    self.insertions.push(Staged::Node(state, data));
+  }
+
+  /// Records an edge from `source` to `dest` with data `data`, as with
+  /// [Graph::add_edge](../struct.Graph.html#method.add_edge). `source_data` and
+  /// `dest_data` are used only if the corresponding game states are novel when
+  /// the stage is committed.
+  pub fn add_edge(&mut self, source: T, source_data: S, dest: T, dest_data: S, data: A) {
+    self.insertions.push(Staged::Edge {
+      source,
+      source_data,
+      dest,
+      dest_data,
+      data,
+    });
+  }
+
+  /// Returns the number of insertions recorded.
+  pub fn len(&self) -> usize {
+    self.insertions.len()
+  }
+
+  /// Returns true iff no insertions have been recorded.
+  pub fn is_empty(&self) -> bool {
+    self.insertions.is_empty()
+  }
+}
+
+/// Counts of changes made by [Graph::commit](../struct.Graph.html#method.commit).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CommitStats {
+  /// The number of vertices added.
+  pub vertices_added: usize,
+  /// The number of edges added.
+  pub edges_added: usize,
+  /// The number of staged edges dropped because an identical edge was
+  /// already present (or, in a graph that merges parallel edges, any edge
+  /// between the same vertices).
+  pub duplicate_edges: usize,
+}
+
+impl<T: Hash + Eq + Clone, S, A: PartialEq> Graph<T, S, A> {
+  /// Returns an empty [Stage](stage/struct.Stage.html) for recording
+  /// insertions into this graph.
+  pub fn stage(&self) -> Stage<T, S, A> {
+    Stage::new()
+  }
+
+  /// Applies the insertions recorded in `stages` to this graph, in order. See
+  /// the [stage](stage/index.html) module for details.
+  ///
+  /// If automatic pruning is configured, the graph may be pruned once before
+  /// any insertions are made.
+  ///
+  /// Panics if a vertex recorded with `Stage::add_node` conflicts with an
+  /// existing one and the duplicate policy is `DuplicatePolicy::Error`.
+  pub fn commit<I>(&mut self, stages: I) -> CommitStats
+  where
+    I: IntoIterator<Item = Stage<T, S, A>>,
+  {
+    self.maybe_prune();
+    let mut stats = CommitStats::default();
+    for stage in stages {
+      for insertion in stage.insertions {
+        match insertion {
+          Staged::Node(state, data) => match self.intern(state) {
+            Insertion::Present(id) => {
+              if let Err(e) = self.add_duplicate(id, data) {
+                panic!("{}", e);
+              }
+            }
+            Insertion::New(_) => {
+              self.add_raw_vertex(data);
+              stats.vertices_added += 1;
+            }
+          },
+          Staged::Edge {
+            source,
+            source_data,
+            dest,
+            dest_data,
+            data,
+          } => {
+            let source_id = self.commit_vertex(source, source_data, &mut stats);
+            let dest_id = self.commit_vertex(dest, dest_data, &mut stats);
+            let duplicate = self.get_vertex(source_id).children.iter().any(|id| {
+              let arc = self.get_arc(*id);
+              arc.target == dest_id && arc.data == data
+            });
+            let edge_count = self.arcs.len();
+            if !duplicate {
+              self.add_raw_edge(data, source_id, dest_id);
+            }
+            if self.arcs.len() > edge_count {
+              stats.edges_added += 1;
+            } else {
+              stats.duplicate_edges += 1;
+            }
+          }
+        }
+      }
+    }
+    stats
+  }
+
+  /// Finds or adds the vertex for `state` on behalf of `commit`.
+  fn commit_vertex(&mut self, state: T, data: S, stats: &mut CommitStats) -> VertexId {
+    match self.intern(state) {
+      Insertion::Present(id) => id,
+      Insertion::New(id) => {
+        self.add_raw_vertex(data);
+        stats.vertices_added += 1;
+        id
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::CommitStats;
+
+  type Graph = crate::Graph<&'static str, &'static str, &'static str>;
+
+  #[test]
+  fn commit_ok() {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| "root_data", "a", |_| "a_data", "root_a");
+    let mut s1 = g.stage();
+    s1.add_edge("a", "ignored", "b", "b_data", "a_b");
+    s1.add_node("c", "c_data");
+    let mut s2 = g.stage();
+    s2.add_edge("root", "ignored", "a", "ignored", "root_a");
+    s2.add_edge("a", "ignored", "b", "ignored", "a_b");
+    s2.add_edge("a", "ignored", "b", "ignored", "a_b2");
+    s2.add_node("c", "ignored");
+    assert_eq!(2, s1.len());
+    assert!(!s2.is_empty());
+
+    let stats = g.commit(vec![s1, s2]);
+    assert_eq!(
+      CommitStats {
+        vertices_added: 2,
+        edges_added: 2,
+        duplicate_edges: 2,
+      },
+      stats
+    );
+    assert_eq!(4, g.vertex_count());
+    assert_eq!(3, g.edge_count());
+    assert_eq!("a_data", *g.find_node(&"a").unwrap().get_data());
+    assert_eq!("b_data", *g.find_node(&"b").unwrap().get_data());
+    assert_eq!("c_data", *g.find_node(&"c").unwrap().get_data());
+    let a = g.find_node(&"a").unwrap();
+    let children: Vec<_> = a.get_child_list().iter().map(|e| *e.get_data()).collect();
+    assert_eq!(vec!["a_b", "a_b2"], children);
+  }
+
+  #[test]
+  fn commit_deterministic_ok() {
+    let build = |threads: usize| {
+      let mut g: crate::Graph<u32, u32, u32> = crate::Graph::new();
+      let stages: Vec<_> = std::thread::scope(|s| {
+        let workers: Vec<_> = (0..threads as u32)
+          .map(|t| {
+            let mut stage = g.stage();
+            s.spawn(move || {
+              for i in 0..20 {
+                stage.add_edge(i % 5, t, (i * 7 + t) % 11, t, i % 3);
+              }
+              stage
+            })
+          })
+          .collect();
+        workers.into_iter().map(|w| w.join().unwrap()).collect()
+      });
+      g.commit(stages);
+      let mut edges = Vec::new();
+      for state in 0..11 {
+        if let Some(n) = g.find_node(&state) {
+          for e in n.get_child_list().iter() {
+            edges.push((
+              n.get_id(),
+              state,
+              e.get_id(),
+              *e.get_target().get_label(),
+              *e.get_data(),
+            ));
+          }
+        }
+      }
+      edges
+    };
+    let first = build(4);
+    for _ in 0..8 {
+      assert_eq!(first, build(4));
+    }
+  }
+}
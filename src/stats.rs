@@ -0,0 +1,117 @@
+//! Statistics that may be collected about a graph as it is built.
+
+/// Counts of transposition table lookups made when inserting game states.
+///
+/// A lookup is a hit when the game state being inserted was already present in
+/// the graph (i.e., a transposition was found) and a miss when a new vertex had
+/// to be created for it. These counts are collected by a
+/// [Graph](../struct.Graph.html) only after tracking has been enabled with
+/// [set_track_interning](../struct.Graph.html#method.set_track_interning).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct InterningStats {
+  /// The number of insertions that resolved to an existing vertex.
+  pub hits: usize,
+  /// The number of insertions that created a new vertex.
+  pub misses: usize,
+}
+
+impl InterningStats {
+  /// Returns the total number of lookups recorded.
+  pub fn lookups(&self) -> usize {
+    self.hits + self.misses
+  }
+
+  /// Returns the fraction of lookups that were hits, or `None` if no lookups
+  /// have been recorded.
+  pub fn hit_rate(&self) -> Option<f64> {
+    match self.lookups() {
+      0 => None,
+      n => Some(self.hits as f64 / n as f64),
+    }
+  }
+
+  /// Records the outcome of a single lookup.
+  pub(crate) fn record(&mut self, hit: bool) {
+    if hit {
+      self.hits += 1;
+    } else {
+      self.misses += 1;
+    }
+  }
+}
+
+/// When a vertex was created and when its data was last modified, in ticks of
+/// a logical clock that advances by one with each such event in a graph.
+///
+/// These times are recorded by a [Graph](../struct.Graph.html) only if it was
+/// created with [track_recency](../config/struct.GraphConfig.html#structfield.track_recency)
+/// set. Taking a mutable reference to a vertex's data counts as modifying it,
+/// whether or not the data is changed.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Recency {
+  /// The tick at which the vertex was created.
+  pub created: u64,
+  /// The tick at which the vertex's data was last modified, or at which the
+  /// vertex was created if its data has not been modified.
+  pub touched: u64,
+}
+
+/// What garbage collection from a set of roots would drop from a graph, as
+/// computed by
+/// [Graph::reachability_report](../struct.Graph.html#method.reachability_report).
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ReachabilityReport {
+  /// The number of vertices in the graph.
+  pub vertices_before: usize,
+  /// The number of edges in the graph.
+  pub edges_before: usize,
+  /// The IDs of the vertices that would be dropped, in increasing order.
+  pub dropped_vertices: Vec<usize>,
+  /// The IDs of the edges that would be dropped, in increasing order.
+  pub dropped_edges: Vec<usize>,
+}
+
+impl ReachabilityReport {
+  /// Returns the number of vertices that would be retained.
+  pub fn vertices_after(&self) -> usize {
+    self.vertices_before - self.dropped_vertices.len()
+  }
+
+  /// Returns the number of edges that would be retained.
+  pub fn edges_after(&self) -> usize {
+    self.edges_before - self.dropped_edges.len()
+  }
+}
+
+/// The recency of each vertex in a graph, indexed by `VertexId`.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RecencyTable {
+  /// The next tick of the clock.
+  pub tick: u64,
+  pub vertices: Vec<Recency>,
+}
+
+impl RecencyTable {
+  /// Returns an empty table whose clock continues from this one.
+  pub fn empty_like(&self) -> Self {
+    RecencyTable {
+      tick: self.tick,
+      vertices: Vec::new(),
+    }
+  }
+
+  /// Records the creation of a vertex, which must be the next in ID order.
+  pub fn create(&mut self) {
+    self.vertices.push(Recency {
+      created: self.tick,
+      touched: self.tick,
+    });
+    self.tick += 1;
+  }
+
+  /// Records a modification of the data of the vertex with the given index.
+  pub fn touch(&mut self, index: usize) {
+    self.vertices[index].touched = self.tick;
+    self.tick += 1;
+  }
+}
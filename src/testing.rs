@@ -0,0 +1,585 @@
+//! Assertions about the contents of a graph, for tests.
+//!
+//! An [ExpectedGraph](struct.ExpectedGraph.html) lists the vertices that a
+//! graph should contain, by game state, with their data, and the child edges
+//! of each vertex, in order, with their data. It is compared against a
+//! [Graph](../struct.Graph.html) without regard to vertex or edge IDs, so
+//! tests do not depend on the order in which vertices were added or on how
+//! garbage collection renumbered them. Each difference is reported on its own
+//! line, naming the game states involved.
+//!
+//! The [assert_graph](../macro.assert_graph.html) macro builds an
+//! `ExpectedGraph` from a compact description and panics if the graph does
+//! not match it.
+//!
+//! Code that may panic while modifying a graph (e.g., in a callback that
+//! supplies vertex data) may be checked with
+//! [assert_consistent_after_panic](fn.assert_consistent_after_panic.html),
+//! which verifies that the graph's internal structures still agree with each
+//! other afterwards.
+//!
+//! Synthetic graphs of a given size and shape may be generated with the
+//! functions in [generate](generate/index.html).
+//!
+//! ```rust
+//! # use search_graph::{assert_graph, Graph};
+//! # fn main() {
+//! let mut g: Graph<&str, u32, char> = Graph::new();
+//! g.add_edge("root", |_| 0, "a", |_| 1, 'x');
+//! g.add_edge("root", |_| 0, "b", |_| 2, 'y');
+//! assert_graph!(g,
+//!   vertices: ["root" => 0, "a" => 1, "b" => 2],
+//!   edges: ["root" => "a" => 'x', "root" => "b" => 'y']);
+//! # }
+//! ```
+
+pub mod generate;
+
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+
+use crate::base::VertexId;
+use crate::Graph;
+use symbol_map::indexing::Indexing;
+
+/// The expected contents of a graph. See the [testing](index.html) module.
+#[derive(Clone, Debug)]
+pub struct ExpectedGraph<T, S, A> {
+  vertices: Vec<(T, S)>,
+  edges: Vec<(T, T, A)>,
+}
+
+impl<T, S, A> Default for ExpectedGraph<T, S, A> {
+  fn default() -> Self {
+    ExpectedGraph {
+      vertices: Vec::new(),
+      edges: Vec::new(),
+    }
+  }
+}
+
+impl<T, S, A> ExpectedGraph<T, S, A>
+where
+  T: Hash + Eq + Clone + fmt::Debug,
+  S: PartialEq + fmt::Debug,
+  A: PartialEq + fmt::Debug,
+{
+  /// Creates an expectation of an empty graph.
+  pub fn new() -> Self {
+    Default::default()
+  }
+
+  /// Expects a vertex for `state` with the given data.
+  pub fn vertex(mut self, state: T, data: S) -> Self {
+    self.vertices.push((state, data));
+    self
+  }
+
+  /// Expects an edge from the vertex for `source` to the vertex for `target`
+  /// with the given data. Edges are expected among the children of their
+  /// source in the order in which they are added to this expectation.
+  pub fn edge(mut self, source: T, target: T, data: A) -> Self {
+    self.edges.push((source, target, data));
+    self
+  }
+
+  /// Returns a description of each way in which `graph` differs from this
+  /// expectation, or nothing if it matches. Game states are matched with the
+  /// canonical game states of vertices, not with aliases.
+  pub fn diff(&self, graph: &Graph<T, S, A>) -> Vec<String> {
+    let mut differences = Vec::new();
+    let mut expected_ids: Vec<Option<usize>> = vec![None; graph.vertices.len()];
+    for (i, (state, data)) in self.vertices.iter().enumerate() {
+      let id = match graph.state_ids.get(state) {
+        Some(symbol) => *symbol.id(),
+        None => {
+          differences.push(format!("missing vertex {:?}", state));
+          continue;
+        }
+      };
+      if expected_ids[id.0].replace(i).is_some() {
+        differences.push(format!("vertex {:?} is expected more than once", state));
+        continue;
+      }
+      let actual = &graph.get_vertex(id).data;
+      if actual != data {
+        differences.push(format!(
+          "vertex {:?}: expected data {:?}, found {:?}",
+          state, data, actual
+        ));
+      }
+    }
+    for (i, expected) in expected_ids.iter().enumerate() {
+      if expected.is_none() {
+        differences.push(format!(
+          "unexpected vertex {:?} with data {:?}",
+          graph.get_state(VertexId(i)).unwrap(),
+          graph.get_vertex(VertexId(i)).data
+        ));
+      }
+    }
+    for (i, expected) in expected_ids.iter().enumerate() {
+      let state = match expected {
+        Some(index) => &self.vertices[*index].0,
+        None => continue,
+      };
+      let expected_children: Vec<(&T, &A)> = self
+        .edges
+        .iter()
+        .filter(|(source, _, _)| source == state)
+        .map(|(_, target, data)| (target, data))
+        .collect();
+      let actual_children: Vec<(&T, &A)> = graph
+        .get_vertex(VertexId(i))
+        .children
+        .iter()
+        .map(|edge_id| {
+          let arc = graph.get_arc(*edge_id);
+          (graph.get_state(arc.target).unwrap(), &arc.data)
+        })
+        .collect();
+      if expected_children != actual_children {
+        differences.push(format!(
+          "vertex {:?}: expected children {}, found {}",
+          state,
+          format_children(&expected_children),
+          format_children(&actual_children)
+        ));
+      }
+    }
+    for (source, target, _) in self.edges.iter() {
+      if !self.vertices.iter().any(|(state, _)| state == source) {
+        differences.push(format!(
+          "edge {:?} -> {:?} has an unexpected source",
+          source, target
+        ));
+      }
+    }
+    differences
+  }
+
+  /// Returns an error listing the differences between `graph` and this
+  /// expectation, if there are any.
+  pub fn check(&self, graph: &Graph<T, S, A>) -> Result<(), GraphMismatch> {
+    let differences = self.diff(graph);
+    if differences.is_empty() {
+      Ok(())
+    } else {
+      Err(GraphMismatch { differences })
+    }
+  }
+
+  /// Panics with a list of the differences between `graph` and this
+  /// expectation, if there are any.
+  pub fn assert_matches(&self, graph: &Graph<T, S, A>) {
+    if let Err(e) = self.check(graph) {
+      panic!("{}", e);
+    }
+  }
+}
+
+/// Formats a list of child edges as `[target (data), ...]`.
+fn format_children<T: fmt::Debug, A: fmt::Debug>(children: &[(&T, &A)]) -> String {
+  let children: Vec<String> = children
+    .iter()
+    .map(|(target, data)| format!("{:?} ({:?})", target, data))
+    .collect();
+  format!("[{}]", children.join(", "))
+}
+
+/// The error returned when a graph does not match an
+/// [ExpectedGraph](struct.ExpectedGraph.html).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GraphMismatch {
+  /// A description of each difference.
+  pub differences: Vec<String>,
+}
+
+impl fmt::Display for GraphMismatch {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "Graph does not match expectation:")?;
+    for difference in self.differences.iter() {
+      write!(f, "\n  {}", difference)?;
+    }
+    Ok(())
+  }
+}
+
+impl Error for GraphMismatch {}
+
+/// Returns a description of each way in which the internal structure of
+/// `graph` is inconsistent (e.g., a game state without a vertex, or an edge
+/// that is missing from the child list of its source), or an empty list if
+/// it is consistent. A graph that is built and modified through this crate's
+/// API is always consistent, so this is meant for tests of code that may
+/// panic while modifying a graph.
+pub fn invariant_violations<T, S, A>(graph: &Graph<T, S, A>) -> Vec<String>
+where
+  T: Hash + Eq + Clone + fmt::Debug,
+{
+  let mut violations = Vec::new();
+  let vertex_count = graph.vertices.len();
+  let mut states = vec![0usize; vertex_count];
+  for symbol in graph.state_ids.table() {
+    match states.get_mut(symbol.id().0) {
+      Some(count) => *count += 1,
+      None => violations.push(format!(
+        "Game state {:?} has ID {} but there are {} vertices",
+        symbol.data(),
+        symbol.id().0,
+        vertex_count
+      )),
+    }
+  }
+  for (id, count) in states.iter().enumerate() {
+    if *count != 1 {
+      violations.push(format!("Vertex {} has {} game states", id, count));
+    }
+  }
+  for (alias, id) in graph.aliases.iter() {
+    if id.0 >= vertex_count {
+      violations.push(format!("Alias {:?} refers to vertex {}", alias, id.0));
+    }
+  }
+  let mut in_children = vec![0usize; graph.arcs.len()];
+  let mut in_parents = vec![0usize; graph.arcs.len()];
+  for (id, vertex) in graph.vertices.iter().enumerate() {
+    for edge in vertex.children.iter() {
+      match graph.arcs.get(edge.0) {
+        Some(arc) if arc.source.0 == id => in_children[edge.0] += 1,
+        _ => violations.push(format!("Vertex {} has foreign child edge {}", id, edge.0)),
+      }
+    }
+    for edge in vertex.parents.iter() {
+      match graph.arcs.get(edge.0) {
+        Some(arc) if arc.target.0 == id => in_parents[edge.0] += 1,
+        _ => violations.push(format!("Vertex {} has foreign parent edge {}", id, edge.0)),
+      }
+    }
+  }
+  for (id, arc) in graph.arcs.iter().enumerate() {
+    if arc.source.0 >= vertex_count || arc.target.0 >= vertex_count {
+      violations.push(format!(
+        "Edge {} joins vertices {} and {} but there are {} vertices",
+        id, arc.source.0, arc.target.0, vertex_count
+      ));
+    }
+    if in_children[id] != 1 {
+      violations.push(format!("Edge {} is in {} child lists", id, in_children[id]));
+    }
+    let expected = if graph.config.track_parents { 1 } else { 0 };
+    if in_parents[id] != expected {
+      violations.push(format!("Edge {} is in {} parent lists", id, in_parents[id]));
+    }
+  }
+  side_table_violations(graph, &mut violations);
+  violations
+}
+
+/// Adds to `violations` a description of each table kept alongside the
+/// vertices and edges of `graph` that does not match them.
+fn side_table_violations<T, S, A>(graph: &Graph<T, S, A>, violations: &mut Vec<String>)
+where
+  T: Hash + Eq + Clone + fmt::Debug,
+{
+  let vertex_count = graph.vertices.len();
+  let edge_count = graph.arcs.len();
+  for len in graph.vertex_columns.lens() {
+    if len != vertex_count {
+      violations.push(format!("Vertex column has {} values", len));
+    }
+  }
+  for len in graph.edge_columns.lens() {
+    if len != edge_count {
+      violations.push(format!("Edge column has {} values", len));
+    }
+  }
+  if graph.hot.values.len() != graph.hot.words * vertex_count {
+    violations.push(format!("Hot table has {} words", graph.hot.values.len()));
+  }
+  if let Some(ref recency) = graph.recency {
+    if recency.vertices.len() != vertex_count {
+      violations.push(format!(
+        "Recency table has {} vertices",
+        recency.vertices.len()
+      ));
+    }
+  }
+  if let Some(ref index) = graph.child_index {
+    if !index.is_consistent(&graph.vertices, &graph.arcs) {
+      violations.push("Child index does not match child edges".to_string());
+    }
+  }
+  if let Some(ref heaps) = graph.child_heaps {
+    if !heaps.is_consistent(&graph.vertices, &graph.arcs) {
+      violations.push("Child heaps do not match child edges".to_string());
+    }
+  }
+  if let Some(ref index) = graph.edge_index {
+    if !index.is_consistent(&graph.vertices, &graph.arcs) {
+      violations.push("Edge index does not match edges".to_string());
+    }
+  }
+  for id in graph.pins.vertices() {
+    if id.0 >= vertex_count {
+      violations.push(format!("Pin refers to vertex {}", id.0));
+    }
+  }
+  if let Some(ref stable) = graph.stable {
+    if !stable.is_consistent(vertex_count) {
+      violations.push("Stable IDs do not match vertices".to_string());
+    }
+  }
+  if let Some(ref audit) = graph.audit {
+    if audit.vertices.len() != vertex_count || audit.edges.len() != edge_count {
+      violations.push(format!(
+        "Audit trail has provenance for {} vertices and {} edges",
+        audit.vertices.len(),
+        audit.edges.len()
+      ));
+    }
+    for event in audit.events.iter() {
+      let count = if event.op.is_edge() {
+        edge_count
+      } else {
+        vertex_count
+      };
+      if event.id.is_some_and(|id| id >= count) {
+        violations.push(format!("Audit event {:?} refers to a missing item", event));
+      }
+    }
+  }
+  if let Some(ref remap) = graph.last_remap {
+    if remap.to_version() != graph.version {
+      violations.push(format!(
+        "Last remap is to version {} but the graph is at version {}",
+        remap.to_version(),
+        graph.version
+      ));
+    }
+    let vertices: Vec<usize> = remap.vertices.iter().flatten().map(|id| id.0).collect();
+    if !is_injection(&vertices, vertex_count) {
+      violations.push("Last remap does not map vertices onto distinct vertices".to_string());
+    }
+    let edges: Vec<usize> = remap.edges.iter().flatten().map(|id| id.0).collect();
+    if !is_injection(&edges, edge_count) {
+      violations.push("Last remap does not map edges onto distinct edges".to_string());
+    }
+  }
+}
+
+/// Returns true iff `ids` are distinct and all less than `count`.
+fn is_injection(ids: &[usize], count: usize) -> bool {
+  let mut seen = vec![false; count];
+  ids
+    .iter()
+    .all(|id| *id < count && !std::mem::replace(&mut seen[*id], true))
+}
+
+/// Calls `f` on `graph`, which must panic, and then panics if `graph` was
+/// left inconsistent, as reported by
+/// [invariant_violations](fn.invariant_violations.html).
+///
+/// ```rust
+/// # use search_graph::Graph;
+/// # use search_graph::testing::assert_consistent_after_panic;
+/// # fn main() {
+/// let mut g: Graph<u32, u32, ()> = Graph::new();
+/// assert_consistent_after_panic(&mut g, |g| {
+///   g.add_edge(0, |_| 0, 1, |_| panic!("no data"), ());
+/// });
+/// assert!(g.find_node(&1).is_none());
+/// # }
+/// ```
+pub fn assert_consistent_after_panic<T, S, A, F>(graph: &mut Graph<T, S, A>, f: F)
+where
+  T: Hash + Eq + Clone + fmt::Debug,
+  F: FnOnce(&mut Graph<T, S, A>),
+{
+  let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f(&mut *graph)));
+  assert!(result.is_err(), "Expected a panic");
+  let violations = invariant_violations(graph);
+  if !violations.is_empty() {
+    panic!(
+      "Graph is inconsistent after a panic:\n  {}",
+      violations.join("\n  ")
+    );
+  }
+}
+
+/// Asserts that a graph contains exactly the given vertices and child edges,
+/// as with
+/// [ExpectedGraph::assert_matches](testing/struct.ExpectedGraph.html#method.assert_matches).
+///
+/// Vertices are listed as `state => data` and edges as
+/// `source => target => data`, with the edges of each vertex in child order.
+/// Either list may be omitted if it is empty.
+///
+/// ```rust
+/// # use search_graph::{assert_graph, Graph};
+/// # fn main() {
+/// let mut g: Graph<u32, (), ()> = Graph::new();
+/// g.add_edge(0, |_| (), 1, |_| (), ());
+/// g.add_node(2, ());
+/// assert_graph!(g, vertices: [0 => (), 1 => (), 2 => ()], edges: [0 => 1 => ()]);
+/// assert_graph!(Graph::<u32, (), ()>::new());
+/// # }
+/// ```
+#[macro_export]
+macro_rules! assert_graph {
+  ($graph:expr $(, vertices: [$($state:expr => $data:expr),* $(,)?])?
+   $(, edges: [$($source:expr => $target:expr => $edge:expr),* $(,)?])? $(,)?) => {{
+    let expected = $crate::testing::ExpectedGraph::new()
+      $($(.vertex($state, $data))*)?
+      $($(.edge($source, $target, $edge))*)?;
+    expected.assert_matches(&$graph);
+  }};
+}
+
+#[cfg(test)]
+mod test {
+  use super::{assert_consistent_after_panic, invariant_violations, ExpectedGraph, VertexId};
+
+  type Graph = crate::Graph<&'static str, u32, char>;
+
+  fn make_graph() -> Graph {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| 0, "a", |_| 1, 'x');
+    g.add_edge("root", |_| 0, "b", |_| 2, 'y');
+    g.add_edge("a", |_| 1, "b", |_| 2, 'z');
+    g
+  }
+
+  #[test]
+  fn assert_graph_ok() {
+    let mut g = make_graph();
+    assert_graph!(g,
+      vertices: ["b" => 2, "a" => 1, "root" => 0],
+      edges: ["root" => "a" => 'x', "a" => "b" => 'z', "root" => "b" => 'y'],
+    );
+    g.compact_with(&["a"], &mut []);
+    assert_graph!(g, vertices: ["a" => 1, "b" => 2], edges: ["a" => "b" => 'z']);
+    assert_graph!(Graph::new());
+  }
+
+  #[test]
+  fn diff_ok() {
+    let g = make_graph();
+    let expected = ExpectedGraph::new()
+      .vertex("root", 0)
+      .vertex("a", 5)
+      .vertex("c", 3)
+      .edge("root", "b", 'y')
+      .edge("root", "a", 'x')
+      .edge("c", "a", 'w');
+    assert_eq!(
+      vec![
+        "vertex \"a\": expected data 5, found 1",
+        "missing vertex \"c\"",
+        "unexpected vertex \"b\" with data 2",
+        "vertex \"root\": expected children [\"b\" ('y'), \"a\" ('x')], \
+         found [\"a\" ('x'), \"b\" ('y')]",
+        "vertex \"a\": expected children [], found [\"b\" ('z')]",
+      ],
+      expected.diff(&g)
+    );
+    let error = expected.check(&g).unwrap_err();
+    assert_eq!(5, error.differences.len());
+    assert!(error
+      .to_string()
+      .starts_with("Graph does not match expectation:\n  vertex \"a\""));
+  }
+
+  #[test]
+  #[should_panic(expected = "unexpected vertex \"b\" with data 2")]
+  fn assert_graph_err() {
+    assert_graph!(make_graph(),
+      vertices: ["root" => 0, "a" => 1],
+      edges: ["root" => "a" => 'x']);
+  }
+
+  #[test]
+  fn panicking_insertion_ok() {
+    let mut g = make_graph();
+    assert!(invariant_violations(&g).is_empty());
+    assert_consistent_after_panic(&mut g, |g| {
+      g.add_edge("a", |_| 1, "c", |_| panic!("c"), 'w');
+    });
+    assert_consistent_after_panic(&mut g, |g| {
+      g.add_edge("d", |_| panic!("d"), "a", |_| 1, 'w');
+    });
+    assert_consistent_after_panic(&mut g, |g| {
+      g.find_node_mut(&"b")
+        .unwrap()
+        .to_child_list()
+        .add_child("c", || panic!("c"), 'w');
+    });
+    assert!(g.find_node(&"c").is_none());
+    assert!(g.find_node(&"d").is_none());
+    assert_graph!(g,
+      vertices: ["root" => 0, "a" => 1, "b" => 2],
+      edges: ["root" => "a" => 'x', "root" => "b" => 'y', "a" => "b" => 'z']);
+
+    g.add_edge("a", |_| 1, "c", |_| 3, 'w');
+    assert_eq!(Some(&3), g.find_node(&"c").map(|n| n.get_data()));
+    assert!(invariant_violations(&g).is_empty());
+  }
+
+  #[test]
+  fn side_tables_ok() {
+    let mut g = Graph::with_config(crate::config::GraphConfig {
+      track_recency: true,
+      hot_words: 2,
+      stable_ids: true,
+      ..Default::default()
+    });
+    g.set_audit_capacity(Some(16));
+    let column = g.attach_edge_column::<u32>();
+    g.attach_vertex_column::<u32>();
+    g.index_children_by(|c| *c as u64);
+    g.order_children_by(|c| *c as u64);
+    g.index_edges();
+    for (i, (source, target)) in [
+      ("root", "a"),
+      ("root", "b"),
+      ("a", "b"),
+      ("b", "c"),
+      ("a", "d"),
+    ]
+    .iter()
+    .enumerate()
+    {
+      g.add_edge(*source, |_| 0, *target, |_| 1, (b'v' + i as u8) as char);
+    }
+    g.pin(&"b").unwrap();
+    g.pin(&"d").unwrap();
+    g.edge_column_mut(column)[1] = 7;
+    assert!(invariant_violations(&g).is_empty());
+
+    assert_eq!(1, g.retain_edges(|e| *e.get_data() != 'w'));
+    assert!(invariant_violations(&g).is_empty());
+    g.reattach(&"b", &"d", 'q').unwrap();
+    assert!(invariant_violations(&g).is_empty());
+    g.compact_with(&["a"], &mut []);
+    assert!(invariant_violations(&g).is_empty());
+    assert_eq!(4, g.vertex_count());
+    assert_eq!(2, g.pin_count());
+
+    g.hot.values.pop();
+    g.pins.create(VertexId(9));
+    assert_eq!(
+      vec![
+        "Hot table has 7 words".to_string(),
+        "Pin refers to vertex 9".to_string(),
+      ],
+      invariant_violations(&g)
+    );
+  }
+
+  #[test]
+  #[should_panic(expected = "Expected a panic")]
+  fn assert_consistent_after_panic_err() {
+    assert_consistent_after_panic(&mut make_graph(), |_| ());
+  }
+}
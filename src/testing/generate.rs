@@ -0,0 +1,234 @@
+//! Generators of synthetic graphs, for benchmarks and performance tests.
+//!
+//! Each generator returns a `Graph<u64, (), ()>` whose game states are the
+//! serial numbers of its vertices, in the order in which they were created,
+//! starting with the root at 0. Generators that make random choices draw
+//! random numbers from a caller-provided closure that returns uniformly
+//! distributed `u64` values, so the same sequence of random numbers always
+//! produces the same graph.
+//!
+//! ```rust
+//! # use search_graph::testing::generate;
+//! # fn main() {
+//! let g = generate::tree(3, 4);
+//! assert_eq!(g.vertex_count(), 1 + 3 + 9 + 27 + 81);
+//!
+//! let mut state = 1u64;
+//! let mut rng = || {
+//!   state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+//!   state
+//! };
+//! let g = generate::dag_with_transpositions(1000, 4, 0.25, &mut rng);
+//! assert_eq!(g.vertex_count(), 1000);
+//! assert!(g.edge_count() > 999);
+//! # }
+//! ```
+
+use crate::Graph;
+
+/// Returns a uniform sample from `[0, 1)`.
+fn uniform<R: FnMut() -> u64>(rng: &mut R) -> f64 {
+  // The top 53 bits give a uniform sample from [0, 1).
+  (rng() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Returns a sample from `0..n`, which must not be empty.
+fn below<R: FnMut() -> u64>(rng: &mut R, n: u64) -> u64 {
+  (uniform(rng) * n as f64) as u64
+}
+
+/// Returns a complete tree in which every vertex above depth `depth` has
+/// `branching` children. The tree has `(branching^(depth + 1) - 1) /
+/// (branching - 1)` vertices, and its vertices are numbered in breadth-first
+/// order.
+pub fn tree(branching: usize, depth: usize) -> Graph<u64, (), ()> {
+  let mut graph = Graph::new();
+  graph.add_node(0, ());
+  let mut level = 0..1u64;
+  let mut next = 1;
+  for _ in 0..depth {
+    let start = next;
+    for parent in level {
+      for _ in 0..branching {
+        graph.add_edge(parent, |_| (), next, |_| (), ());
+        next += 1;
+      }
+    }
+    level = start..next;
+  }
+  graph
+}
+
+/// Returns a directed acyclic graph with `vertex_count` vertices (or 1, if
+/// `vertex_count` is 0), in which vertices are expanded in breadth-first order
+/// and each expanded vertex has up to `branching` children.
+///
+/// With probability `transposition_rate`, a child is an existing vertex that
+/// was created after its parent (a transposition) instead of a new vertex.
+/// Once `vertex_count` vertices exist, all further children are
+/// transpositions. Edges lead only from older to newer vertices, which keeps
+/// the graph acyclic, so a vertex that is newer than all others has no
+/// children.
+pub fn dag_with_transpositions<R>(
+  vertex_count: usize,
+  branching: usize,
+  transposition_rate: f64,
+  rng: &mut R,
+) -> Graph<u64, (), ()>
+where
+  R: FnMut() -> u64,
+{
+  let vertex_count = vertex_count.max(1) as u64;
+  let mut graph = Graph::new();
+  graph.add_node(0, ());
+  let mut created = 1;
+  for parent in 0..vertex_count {
+    for _ in 0..branching {
+      let newer = created - parent - 1;
+      let transpose = created == vertex_count || uniform(rng) < transposition_rate;
+      let child = if transpose && newer > 0 {
+        parent + 1 + below(rng, newer)
+      } else if created < vertex_count {
+        created += 1;
+        created - 1
+      } else {
+        continue;
+      };
+      graph.add_edge(parent, |_| (), child, |_| (), ());
+    }
+  }
+  graph
+}
+
+/// Returns a graph with `vertex_count` vertices (or 1, if `vertex_count` is 0)
+/// whose out-degrees follow a power law, grown by preferential attachment.
+///
+/// Vertices are added one at a time, and each new vertex becomes a child of
+/// up to `parents` distinct existing vertices, which are chosen with
+/// probability proportional to one more than their number of children. A few
+/// early vertices thus become hubs with very many children, as in search
+/// graphs with a handful of heavily explored positions. Edges lead only from
+/// older to newer vertices, so the graph is acyclic and every vertex is
+/// reachable from the root.
+pub fn scale_free<R>(vertex_count: usize, parents: usize, rng: &mut R) -> Graph<u64, (), ()>
+where
+  R: FnMut() -> u64,
+{
+  let vertex_count = vertex_count.max(1) as u64;
+  let mut graph = Graph::new();
+  graph.add_node(0, ());
+  // Each vertex occurs once, plus once for each of its children, so a uniform
+  // sample from this list is a preferential choice.
+  let mut weighted: Vec<u64> = vec![0];
+  let mut chosen: Vec<u64> = Vec::with_capacity(parents);
+  for child in 1..vertex_count {
+    chosen.clear();
+    for _ in 0..parents.min(child as usize) {
+      let parent = loop {
+        let candidate = weighted[below(rng, weighted.len() as u64) as usize];
+        if !chosen.contains(&candidate) {
+          break candidate;
+        }
+      };
+      chosen.push(parent);
+    }
+    for parent in chosen.iter() {
+      graph.add_edge(*parent, |_| (), child, |_| (), ());
+      weighted.push(*parent);
+    }
+    weighted.push(child);
+  }
+  graph
+}
+
+#[cfg(test)]
+mod test {
+  use super::{dag_with_transpositions, scale_free, tree};
+
+  fn xorshift(mut state: u64) -> impl FnMut() -> u64 {
+    move || {
+      state ^= state << 13;
+      state ^= state >> 7;
+      state ^= state << 17;
+      state
+    }
+  }
+
+  /// Returns true iff every edge of `g` leads from an older to a newer vertex.
+  fn is_forward(g: &crate::Graph<u64, (), ()>) -> bool {
+    g.states().all(|s| {
+      g.find_node(s)
+        .unwrap()
+        .get_child_list()
+        .iter()
+        .all(|e| e.get_target().get_label() > s)
+    })
+  }
+
+  #[test]
+  fn tree_ok() {
+    let g = tree(3, 2);
+    assert_eq!(13, g.vertex_count());
+    assert_eq!(12, g.edge_count());
+    assert_eq!(3, g.find_node(&0).unwrap().get_child_list().len());
+    assert_eq!(
+      4,
+      *g.find_node(&1)
+        .unwrap()
+        .get_child_list()
+        .get_edge(0)
+        .get_target()
+        .get_label()
+    );
+    assert!(g.find_node(&12).unwrap().is_leaf());
+    assert_eq!(1, tree(5, 0).vertex_count());
+  }
+
+  #[test]
+  fn dag_with_transpositions_ok() {
+    let g = dag_with_transpositions(500, 3, 0.0, &mut xorshift(7));
+    assert_eq!(500, g.vertex_count());
+    assert!(is_forward(&g));
+    // Without transpositions, vertices are numbered breadth-first until the
+    // limit is reached.
+    let children = |g: &crate::Graph<u64, (), ()>, s| -> Vec<u64> {
+      g.find_node(&s)
+        .unwrap()
+        .get_child_list()
+        .iter()
+        .map(|e| *e.get_target().get_label())
+        .collect()
+    };
+    assert_eq!(vec![4, 5, 6], children(&g, 1));
+    assert_eq!(vec![496, 497, 498], children(&g, 165));
+    assert_eq!(499, children(&g, 166)[0]);
+
+    let g = dag_with_transpositions(500, 3, 0.5, &mut xorshift(7));
+    assert_eq!(500, g.vertex_count());
+    assert!(is_forward(&g));
+    assert!(g
+      .states()
+      .any(|s| g.find_node(s).unwrap().get_parent_list().len() > 1));
+    let again = dag_with_transpositions(500, 3, 0.5, &mut xorshift(7));
+    assert_eq!(g.edge_count(), again.edge_count());
+    assert_eq!(
+      1,
+      dag_with_transpositions(0, 3, 0.5, &mut xorshift(7)).vertex_count()
+    );
+  }
+
+  #[test]
+  fn scale_free_ok() {
+    let g = scale_free(2000, 2, &mut xorshift(11));
+    assert_eq!(2000, g.vertex_count());
+    assert_eq!(1 + 2 * 1998, g.edge_count());
+    assert!(is_forward(&g));
+    let max = g
+      .states()
+      .map(|s| g.find_node(s).unwrap().get_child_list().len())
+      .max()
+      .unwrap();
+    // Hubs are far above the mean out-degree of 2.
+    assert!(max > 40, "{}", max);
+  }
+}
@@ -0,0 +1,775 @@
+//! Tiered storage that moves rarely used parts of a graph out of memory.
+//!
+//! A long-running analysis may accumulate far more of a graph than it is
+//! actively searching. [Graph::spill](../struct.Graph.html#method.spill)
+//! removes the subgraphs below a set of stub vertices and hands each of them to
+//! a [SpillStore](trait.SpillStore.html) as a self-contained
+//! [Region](struct.Region.html), and
+//! [Graph::reload](../struct.Graph.html#method.reload) restores a region below
+//! its stub. The stubs themselves remain in the graph, with their data, but
+//! without child edges. [Graph::spill_cold](../struct.Graph.html#method.spill_cold)
+//! chooses stubs by how recently their data was modified (see
+//! [Recency](../stats/struct.Recency.html)).
+//!
+//! A region consists of the vertices that are only reachable from the roots
+//! of a spill through its stub, and of the child edges of those vertices and
+//! of the stub. Edges into a region from vertices that are not reachable from
+//! the roots are dropped, and an edge from a region to a vertex that is not in
+//! the graph when the region is reloaded (e.g., because it is in another
+//! spilled region) is not restored.
+//!
+//! Reloading is only automatic in [search](../search/index.html): a
+//! [Stack](../search/struct.Stack.html) reloads the region below its head
+//! before choosing an edge from it, and
+//! [best_first](../search/fn.best_first.html) reloads each vertex before
+//! considering it, so search code that uses them sees spilled regions
+//! reappear as it reaches them. Everything else, including the handles in
+//! [nav](../nav/index.html) and [mutators](../mutators/index.html), views,
+//! and the algorithms in [algo](../algo/index.html), sees a stub as a vertex
+//! without children. Such code should check
+//! [Graph::is_spilled](../struct.Graph.html#method.is_spilled), which tells
+//! stubs apart from leaves, and call `reload` itself.
+//!
+//! Regions are written to files by [DirectoryStore](struct.DirectoryStore.html),
+//! which encodes them with user-provided functions. Reloaded vertices are new
+//! vertices: they receive new IDs, and their [hot data](../hot/index.html) and
+//! recency start afresh.
+//!
+//! ```rust
+//! # use search_graph::Graph;
+//! # use search_graph::tier::{Region, SpillStore};
+//! # use std::collections::HashMap;
+//! # use std::io;
+//! # fn main() {
+//! #[derive(Default)]
+//! struct MemoryStore(HashMap<u64, Region<u32, u32, ()>>);
+//!
+//! impl SpillStore<u32, u32, ()> for MemoryStore {
+//!   fn store(&mut self, key: u64, region: &Region<u32, u32, ()>) -> io::Result<()> {
+//!     self.0.insert(key, region.clone());
+//!     Ok(())
+//!   }
+//!
+//!   fn load(&mut self, key: u64) -> io::Result<Region<u32, u32, ()>> {
+//!     self.0.remove(&key).ok_or_else(|| io::ErrorKind::NotFound.into())
+//!   }
+//! }
+//!
+//! let mut g: Graph<u32, u32, ()> = Graph::new();
+//! g.add_edge(0, |_| 0, 1, |_| 1, ());
+//! g.add_edge(1, |_| 1, 2, |_| 2, ());
+//! g.add_edge(2, |_| 2, 3, |_| 3, ());
+//! g.set_spill_store(Some(Box::new(MemoryStore::default())));
+//! assert_eq!(g.spill(&[0], &[1]).unwrap(), 2);
+//! assert_eq!(g.vertex_count(), 2);
+//! assert!(g.is_spilled(&1));
+//! assert!(g.reload(&1).unwrap());
+//! assert_eq!(g.vertex_count(), 4);
+//! assert_eq!(*g.find_node(&3).unwrap().get_data(), 3);
+//! # }
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::hash::Hash;
+use std::io;
+use std::path::PathBuf;
+
+use crate::base::{EdgeId, VertexId};
+use crate::mark_compact::Collector;
+use crate::nav::Expansion;
+use crate::Graph;
+use symbol_map::SymbolId;
+
+/// A vertex of a spilled [Region](struct.Region.html).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SpilledVertex<T, S, A> {
+  /// The canonical game state of the vertex.
+  pub state: T,
+  /// Additional game states that addressed the vertex.
+  pub aliases: Vec<T>,
+  /// The vertex data.
+  pub data: S,
+  /// How much of the vertex's set of children had been generated.
+  pub expansion: Expansion,
+  /// The data of the vertex's unexpanded child edges.
+  pub unexpanded: Vec<A>,
+}
+
+/// An edge of a spilled [Region](struct.Region.html).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SpilledEdge<T, A> {
+  /// The game state of the edge's source vertex.
+  pub source: T,
+  /// The game state of the edge's target vertex.
+  pub target: T,
+  /// The edge data.
+  pub data: A,
+}
+
+/// A part of a graph that has been removed from below a stub vertex by
+/// [Graph::spill](../struct.Graph.html#method.spill).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Region<T, S, A> {
+  /// How much of the stub's set of children had been generated.
+  pub stub_expansion: Expansion,
+  /// The vertices that were removed, in order of vertex ID.
+  pub vertices: Vec<SpilledVertex<T, S, A>>,
+  /// The child edges of the stub and of the removed vertices, in order of
+  /// source vertex ID and then in child order.
+  pub edges: Vec<SpilledEdge<T, A>>,
+}
+
+/// Storage for spilled regions, which are identified by keys that a graph
+/// assigns when it spills them.
+pub trait SpillStore<T, S, A> {
+  /// Stores `region` under `key`. If this returns an error, the graph keeps
+  /// the region in memory and tries to store it again later.
+  fn store(&mut self, key: u64, region: &Region<T, S, A>) -> io::Result<()>;
+
+  /// Removes and returns the region stored under `key`.
+  fn load(&mut self, key: u64) -> io::Result<Region<T, S, A>>;
+}
+
+/// A [SpillStore](trait.SpillStore.html) that writes each region to its own
+/// file in a directory, using an `encode` function to convert regions to bytes
+/// and a `decode` function to convert them back.
+pub struct DirectoryStore<E, D> {
+  dir: PathBuf,
+  encode: E,
+  decode: D,
+}
+
+impl<E, D> DirectoryStore<E, D> {
+  /// Creates a store that writes regions to files in `dir`, which must exist.
+  pub fn new<P: Into<PathBuf>>(dir: P, encode: E, decode: D) -> Self {
+    DirectoryStore {
+      dir: dir.into(),
+      encode,
+      decode,
+    }
+  }
+
+  /// Returns the path of the file that holds the region with the given key.
+  fn path(&self, key: u64) -> PathBuf {
+    self.dir.join(format!("region-{}.bin", key))
+  }
+}
+
+impl<T, S, A, E, D> SpillStore<T, S, A> for DirectoryStore<E, D>
+where
+  E: Fn(&Region<T, S, A>) -> Vec<u8>,
+  D: Fn(&[u8]) -> io::Result<Region<T, S, A>>,
+{
+  fn store(&mut self, key: u64, region: &Region<T, S, A>) -> io::Result<()> {
+    fs::write(self.path(key), (self.encode)(region))
+  }
+
+  fn load(&mut self, key: u64) -> io::Result<Region<T, S, A>> {
+    let bytes = fs::read(self.path(key))?;
+    let region = (self.decode)(&bytes)?;
+    fs::remove_file(self.path(key))?;
+    Ok(region)
+  }
+}
+
+type BoxedStore<T, S, A> = Box<dyn SpillStore<T, S, A> + Send + Sync>;
+
+/// The spill store of a graph and the regions that have been spilled to it.
+pub(crate) struct Tiering<T, S, A> {
+  store: BoxedStore<T, S, A>,
+  /// The key of the region below each stub, by the game state of the stub.
+  spilled: HashMap<T, u64>,
+  /// Spilled regions that have not been stored yet, with their keys, in the
+  /// order in which they are to be stored.
+  pending: VecDeque<(u64, Region<T, S, A>)>,
+  next_key: u64,
+}
+
+impl<T: Hash + Eq + Clone, S, A> Graph<T, S, A> {
+  /// Sets the store that regions are spilled to, or removes it if `store` is
+  /// `None`. Regions that were spilled to a previous store are forgotten,
+  /// including those that it has not stored yet, so they should be reloaded
+  /// first.
+  pub fn set_spill_store(&mut self, store: Option<BoxedStore<T, S, A>>) {
+    self.tiering = store.map(|store| {
+      Box::new(Tiering {
+        store,
+        spilled: HashMap::new(),
+        pending: VecDeque::new(),
+        next_key: 0,
+      })
+    });
+  }
+
+  /// Returns true iff a region has been spilled from below the vertex for
+  /// `state` and not yet reloaded.
+  pub fn is_spilled(&self, state: &T) -> bool {
+    match (self.tiering.as_ref(), self.get_state_of(state)) {
+      (Some(tiering), Some(canonical)) => tiering.spilled.contains_key(canonical),
+      _ => false,
+    }
+  }
+
+  /// Returns the number of regions that have been spilled and not yet
+  /// reloaded.
+  pub fn spilled_count(&self) -> usize {
+    self.tiering.as_ref().map_or(0, |t| t.spilled.len())
+  }
+
+  /// Spills the region below each vertex for a game state in `stubs` to the
+  /// spill store, as described in the [tier](tier/index.html) module, and
+  /// returns the number of vertices that were removed from the graph. Stubs
+  /// whose regions are empty, that are already spilled, or that are not in the
+  /// graph are ignored, as are game states in `roots` that are not in the
+  /// graph.
+  ///
+  /// Vertex and edge IDs are reassigned as by garbage collection if anything
+  /// is spilled. Panics if no spill store has been set.
+  ///
+  /// Regions are removed from the graph before they are stored. If the store
+  /// returns an error, the regions that it has not stored are kept in memory
+  /// and still count as spilled: they are stored by the next call to
+  /// [store_pending](struct.Graph.html#method.store_pending) or to this
+  /// method, or restored by [reload](struct.Graph.html#method.reload).
+  pub fn spill(&mut self, roots: &[T], stubs: &[T]) -> io::Result<usize> {
+    assert!(self.tiering.is_some(), "no spill store has been set");
+    let root_ids: Vec<VertexId> = roots.iter().filter_map(|s| self.lookup(s)).collect();
+    let mut stub_ids: Vec<VertexId> = Vec::new();
+    let mut is_stub = vec![false; self.vertices.len()];
+    for state in stubs {
+      if let Some(id) = self.lookup(state) {
+        if !is_stub[id.as_usize()] && !self.is_spilled(state) {
+          is_stub[id.as_usize()] = true;
+          stub_ids.push(id);
+        }
+      }
+    }
+
+    // Vertices that remain: those reachable from the roots without passing
+    // through a stub, and the stubs.
+    let mut region_of: Vec<Option<usize>> = vec![None; self.vertices.len()];
+    let mut hot = vec![false; self.vertices.len()];
+    let mut frontier: VecDeque<VertexId> = VecDeque::new();
+    for id in root_ids.iter().chain(stub_ids.iter()) {
+      if !hot[id.as_usize()] {
+        hot[id.as_usize()] = true;
+        frontier.push_back(*id);
+      }
+    }
+    while let Some(id) = frontier.pop_front() {
+      if is_stub[id.as_usize()] {
+        continue;
+      }
+      for edge_id in self.get_vertex(id).children.iter() {
+        let target = self.get_arc(*edge_id).target;
+        if !hot[target.as_usize()] {
+          hot[target.as_usize()] = true;
+          frontier.push_back(target);
+        }
+      }
+    }
+
+    // Each region claims the vertices below its stub that are not claimed
+    // already.
+    let mut regions: Vec<(VertexId, Vec<VertexId>)> = Vec::new();
+    for stub in stub_ids {
+      let index = regions.len();
+      let mut members = Vec::new();
+      frontier.push_back(stub);
+      while let Some(id) = frontier.pop_front() {
+        for edge_id in self.get_vertex(id).children.iter() {
+          let target = self.get_arc(*edge_id).target;
+          if !hot[target.as_usize()] && region_of[target.as_usize()].is_none() {
+            region_of[target.as_usize()] = Some(index);
+            members.push(target);
+            frontier.push_back(target);
+          }
+        }
+      }
+      if !members.is_empty() {
+        members.sort();
+        regions.push((stub, members));
+      } else {
+        is_stub[stub.as_usize()] = false;
+      }
+    }
+    if regions.is_empty() {
+      return Ok(0);
+    }
+
+    // Record everything that collection will not hand back: game states,
+    // aliases, unexpanded edges, and the order of child edges.
+    let states: Vec<Option<T>> = (0..self.vertices.len())
+      .map(|i| self.get_state(VertexId(i)).cloned())
+      .collect();
+    let mut aliases: HashMap<VertexId, Vec<T>> = HashMap::new();
+    for (alias, id) in self.aliases.iter() {
+      if region_of[id.as_usize()].is_some() {
+        aliases.entry(*id).or_default().push(alias.clone());
+      }
+    }
+    let mut unexpanded: HashMap<VertexId, Vec<A>> = HashMap::new();
+    for (_, members) in regions.iter() {
+      for id in members {
+        if let Some(edges) = self.unexpanded.remove(id) {
+          unexpanded.insert(*id, edges);
+        }
+      }
+    }
+    let mut keep_arcs = vec![true; self.arcs.len()];
+    let mut edge_order: Vec<Vec<EdgeId>> = Vec::with_capacity(regions.len());
+    let mut stub_expansions = Vec::with_capacity(regions.len());
+    for (stub, members) in regions.iter() {
+      stub_expansions.push(self.get_vertex(*stub).expansion);
+      let mut order = Vec::new();
+      for id in Some(stub).into_iter().chain(members.iter()) {
+        for edge_id in self.get_vertex(*id).children.iter() {
+          keep_arcs[edge_id.as_usize()] = false;
+          order.push(*edge_id);
+        }
+      }
+      edge_order.push(order);
+    }
+
+    let keep: Vec<bool> = region_of.iter().map(Option::is_none).collect();
+    let kept: Vec<VertexId> = (0..keep.len()).filter(|i| keep[*i]).map(VertexId).collect();
+    let (vertices, arcs) = Collector::retain_selected_capturing(self, &kept, keep, keep_arcs);
+
+    let mut spilled_vertices: Vec<Vec<_>> = regions.iter().map(|_| Vec::new()).collect();
+    let mut count = 0;
+    for (id, vertex) in vertices {
+      if let Some(index) = region_of[id.as_usize()] {
+        spilled_vertices[index].push(SpilledVertex {
+          state: states[id.as_usize()].clone().unwrap(),
+          aliases: aliases.remove(&id).unwrap_or_default(),
+          data: vertex.data,
+          expansion: vertex.expansion,
+          unexpanded: unexpanded.remove(&id).unwrap_or_default(),
+        });
+        count += 1;
+      }
+    }
+    let mut arc_data: HashMap<EdgeId, (VertexId, VertexId, A)> = arcs
+      .into_iter()
+      .map(|(id, arc)| (id, (arc.source, arc.target, arc.data)))
+      .collect();
+    let tiering = self.tiering.as_mut().unwrap();
+    let regions = regions
+      .into_iter()
+      .zip(spilled_vertices)
+      .zip(edge_order)
+      .zip(stub_expansions);
+    for ((((stub, _), vertices), order), stub_expansion) in regions {
+      let edges = order
+        .into_iter()
+        .map(|id| {
+          let (source, target, data) = arc_data.remove(&id).unwrap();
+          SpilledEdge {
+            source: states[source.as_usize()].clone().unwrap(),
+            target: states[target.as_usize()].clone().unwrap(),
+            data,
+          }
+        })
+        .collect();
+      let region = Region {
+        stub_expansion,
+        vertices,
+        edges,
+      };
+      let key = tiering.next_key;
+      tiering.next_key += 1;
+      let stub_state = states[stub.as_usize()].clone().unwrap();
+      tiering.spilled.insert(stub_state, key);
+      tiering.pending.push_back((key, region));
+    }
+    self.store_pending()?;
+    Ok(count)
+  }
+
+  /// Stores the regions that were spilled but could not be stored because the
+  /// spill store returned an error, in the order in which they were spilled.
+  /// Stops at the first error, keeping the regions that have not been stored.
+  pub fn store_pending(&mut self) -> io::Result<()> {
+    let tiering = match self.tiering {
+      Some(ref mut tiering) => &mut **tiering,
+      None => return Ok(()),
+    };
+    while let Some((key, region)) = tiering.pending.front() {
+      tiering.store.store(*key, region)?;
+      tiering.pending.pop_front();
+    }
+    Ok(())
+  }
+
+  /// Spills the regions below the vertices reachable from `roots` whose data
+  /// was last modified before the tick `touched_before`, as by
+  /// [spill](struct.Graph.html#method.spill). Vertices are considered
+  /// breadth-first from the roots, and the regions below the first cold
+  /// vertices that are found on each path are spilled. The roots are never
+  /// spilled from.
+  ///
+  /// Panics if the graph does not track
+  /// [recency](config/struct.GraphConfig.html#structfield.track_recency) or
+  /// has no spill store.
+  pub fn spill_cold(&mut self, roots: &[T], touched_before: u64) -> io::Result<usize> {
+    let recency = self.recency.as_ref().expect("recency is not tracked");
+    let mut seen = vec![false; self.vertices.len()];
+    let mut frontier: VecDeque<VertexId> = VecDeque::new();
+    for id in roots.iter().filter_map(|s| self.lookup(s)) {
+      if !seen[id.as_usize()] {
+        seen[id.as_usize()] = true;
+        frontier.push_back(id);
+      }
+    }
+    let mut stubs = Vec::new();
+    while let Some(id) = frontier.pop_front() {
+      for edge_id in self.get_vertex(id).children.iter() {
+        let target = self.get_arc(*edge_id).target;
+        if seen[target.as_usize()] {
+          continue;
+        }
+        seen[target.as_usize()] = true;
+        let vertex = self.get_vertex(target);
+        if recency.vertices[target.as_usize()].touched < touched_before
+          && !vertex.children.is_empty()
+        {
+          stubs.push(self.get_state(target).unwrap().clone());
+        } else {
+          frontier.push_back(target);
+        }
+      }
+    }
+    self.spill(roots, &stubs)
+  }
+
+  /// Reloads the region that was spilled from below the vertex for `stub`.
+  /// Returns true if a region was reloaded, or false if none was spilled from
+  /// there or the stub is no longer in the graph.
+  ///
+  /// Vertices of the region that have been added to the graph again since it
+  /// was spilled keep their current data. Vertex and edge IDs are not
+  /// reassigned. If the store returns an error, the region remains spilled.
+  pub fn reload(&mut self, stub: &T) -> io::Result<bool> {
+    let stub_id = match self.lookup(stub) {
+      Some(id) => id,
+      None => return Ok(false),
+    };
+    let stub_state = self.get_state(stub_id).unwrap().clone();
+    let key = match self.tiering.as_mut() {
+      Some(tiering) => match tiering.spilled.remove(&stub_state) {
+        Some(key) => key,
+        None => return Ok(false),
+      },
+      None => return Ok(false),
+    };
+    let tiering = self.tiering.as_mut().unwrap();
+    let region = match tiering.pending.iter().position(|(k, _)| *k == key) {
+      Some(index) => tiering.pending.remove(index).unwrap().1,
+      None => match tiering.store.load(key) {
+        Ok(region) => region,
+        Err(e) => {
+          tiering.spilled.insert(stub_state, key);
+          return Err(e);
+        }
+      },
+    };
+    for vertex in region.vertices {
+      let id = match self.intern(vertex.state) {
+        crate::Insertion::Present(_) => continue,
+        crate::Insertion::New(id) => {
+          self.add_raw_vertex(vertex.data).expansion = vertex.expansion;
+          id
+        }
+      };
+      for alias in vertex.aliases {
+        if self.lookup(&alias).is_none() {
+          self.aliases.insert(alias, id);
+        }
+      }
+      if !vertex.unexpanded.is_empty() {
+        self.unexpanded.insert(id, vertex.unexpanded);
+      }
+    }
+    let mut complete = true;
+    for edge in region.edges {
+      match (self.lookup(&edge.source), self.lookup(&edge.target)) {
+        (Some(source), Some(target)) => {
+          self.add_raw_edge(edge.data, source, target);
+        }
+        (Some(source), None) => {
+          complete = complete && source != stub_id;
+          let vertex = self.get_vertex_mut(source);
+          if vertex.expansion == Expansion::FullyExpanded {
+            vertex.expansion = Expansion::PartiallyExpanded;
+          }
+        }
+        _ => {}
+      }
+    }
+    if complete {
+      self.get_vertex_mut(stub_id).expansion = region.stub_expansion;
+    }
+    Ok(true)
+  }
+
+  /// Reloads the region below the vertex with the given ID, if one was
+  /// spilled from there.
+  pub(crate) fn reload_vertex(&mut self, id: VertexId) -> io::Result<bool> {
+    match self.tiering {
+      Some(ref tiering) if !tiering.spilled.is_empty() => {
+        let state = self.get_state(id).unwrap().clone();
+        self.reload(&state)
+      }
+      _ => Ok(false),
+    }
+  }
+
+  /// Returns the canonical game state of the vertex for `state`, if any.
+  fn get_state_of(&self, state: &T) -> Option<&T> {
+    self.lookup(state).and_then(|id| self.get_state(id))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::{DirectoryStore, Region, SpillStore, SpilledEdge, SpilledVertex};
+  use crate::config::GraphConfig;
+  use crate::nav::Expansion;
+  use std::collections::HashMap;
+  use std::io;
+
+  type Graph = crate::Graph<&'static str, &'static str, &'static str>;
+  type Regions = HashMap<u64, Region<&'static str, &'static str, &'static str>>;
+
+  #[derive(Default)]
+  struct MemoryStore(Regions);
+
+  impl SpillStore<&'static str, &'static str, &'static str> for MemoryStore {
+    fn store(
+      &mut self,
+      key: u64,
+      region: &Region<&'static str, &'static str, &'static str>,
+    ) -> io::Result<()> {
+      self.0.insert(key, region.clone());
+      Ok(())
+    }
+
+    fn load(&mut self, key: u64) -> io::Result<Region<&'static str, &'static str, &'static str>> {
+      self
+        .0
+        .remove(&key)
+        .ok_or_else(|| io::ErrorKind::NotFound.into())
+    }
+  }
+
+  /// A store that fails to store and load regions the given numbers of times
+  /// before it succeeds.
+  struct FlakyStore(MemoryStore, usize, usize);
+
+  impl SpillStore<&'static str, &'static str, &'static str> for FlakyStore {
+    fn store(
+      &mut self,
+      key: u64,
+      region: &Region<&'static str, &'static str, &'static str>,
+    ) -> io::Result<()> {
+      if self.1 > 0 {
+        self.1 -= 1;
+        return Err(io::ErrorKind::Other.into());
+      }
+      self.0.store(key, region)
+    }
+
+    fn load(&mut self, key: u64) -> io::Result<Region<&'static str, &'static str, &'static str>> {
+      if self.2 > 0 {
+        self.2 -= 1;
+        return Err(io::ErrorKind::Other.into());
+      }
+      self.0.load(key)
+    }
+  }
+
+  fn make_graph() -> Graph {
+    let mut g = Graph::new();
+    g.add_edge("root", |_| "root", "a", |_| "a", "root_a");
+    g.add_edge("root", |_| "root", "b", |_| "b", "root_b");
+    g.add_edge("a", |_| "a", "c", |_| "c", "a_c");
+    g.add_edge("a", |_| "a", "b", |_| "b", "a_b");
+    g.add_edge("c", |_| "c", "d", |_| "d", "c_d");
+    g.add_edge("d", |_| "d", "b", |_| "b", "d_b");
+    g.find_node_mut(&"a").unwrap().mark_expanded();
+    g.set_spill_store(Some(Box::new(MemoryStore::default())));
+    g
+  }
+
+  fn children(g: &Graph, state: &'static str) -> Vec<&'static str> {
+    g.find_node(&state)
+      .unwrap()
+      .get_child_list()
+      .iter()
+      .map(|e| *e.get_target().get_label())
+      .collect()
+  }
+
+  #[test]
+  fn spill_reload_ok() {
+    let mut g = make_graph();
+    g.alias_state("d2", &"d").unwrap();
+    assert_eq!(2, g.spill(&["root"], &["a", "missing"]).unwrap());
+    assert_eq!(3, g.vertex_count());
+    assert_eq!(2, g.edge_count());
+    assert!(g.find_node(&"c").is_none());
+    assert!(g.find_node(&"d2").is_none());
+    assert!(children(&g, "a").is_empty());
+    assert!(g.is_spilled(&"a"));
+    assert!(!g.is_spilled(&"b"));
+    assert_eq!(1, g.spilled_count());
+    assert_eq!(0, g.spill(&["root"], &["a"]).unwrap());
+
+    assert!(g.reload(&"a").unwrap());
+    assert!(!g.reload(&"a").unwrap());
+    assert!(!g.is_spilled(&"a"));
+    assert_eq!(5, g.vertex_count());
+    assert_eq!(6, g.edge_count());
+    assert_eq!(vec!["c", "b"], children(&g, "a"));
+    assert_eq!(vec!["b"], children(&g, "d"));
+    assert_eq!("d", *g.find_node(&"d2").unwrap().get_data());
+    assert_eq!(
+      Expansion::FullyExpanded,
+      g.find_node(&"a").unwrap().get_expansion()
+    );
+  }
+
+  #[test]
+  fn spill_store_err() {
+    let mut g = make_graph();
+    g.set_spill_store(Some(Box::new(FlakyStore(MemoryStore::default(), 1, 0))));
+    assert!(g.spill(&["root"], &["a"]).is_err());
+    assert_eq!(3, g.vertex_count());
+    assert!(g.is_spilled(&"a"));
+    // The region was kept, and is restored from memory.
+    assert!(g.reload(&"a").unwrap());
+    assert_eq!(vec!["c", "b"], children(&g, "a"));
+    assert_eq!(vec!["b"], children(&g, "d"));
+
+    g.set_spill_store(Some(Box::new(FlakyStore(MemoryStore::default(), 2, 1))));
+    assert!(g.spill(&["root"], &["a"]).is_err());
+    assert!(g.store_pending().is_err());
+    g.store_pending().unwrap();
+    // The region is now in the store, which fails to load it once.
+    assert!(g.reload(&"a").is_err());
+    assert!(g.is_spilled(&"a"));
+    assert!(g.reload(&"a").unwrap());
+    assert_eq!(vec!["c", "b"], children(&g, "a"));
+    assert!(crate::testing::invariant_violations(&g).is_empty());
+  }
+
+  #[test]
+  fn spill_shared_ok() {
+    let mut g = make_graph();
+    // "b" is reachable from the root without passing through "a", so it stays.
+    g.spill(&["root"], &["a"]).unwrap();
+    assert!(g.find_node(&"b").is_some());
+    // With no roots, everything below a stub goes.
+    g.reload(&"a").unwrap();
+    assert_eq!(3, g.spill(&[], &["a"]).unwrap());
+    assert_eq!(2, g.vertex_count());
+    // An edge into a missing vertex is not restored.
+    g.add_node("x", "x");
+    g.spill(&[], &["x"]).unwrap();
+    assert!(g.reload(&"a").unwrap());
+    assert_eq!(vec!["c", "b"], children(&g, "a"));
+  }
+
+  #[test]
+  fn spill_cold_ok() {
+    let mut g = Graph::with_config(GraphConfig {
+      track_recency: true,
+      ..Default::default()
+    });
+    g.add_edge("root", |_| "root", "a", |_| "a", "root_a");
+    g.add_edge("a", |_| "a", "c", |_| "c", "a_c");
+    g.add_edge("root", |_| "root", "b", |_| "b", "root_b");
+    g.add_edge("b", |_| "b", "d", |_| "d", "b_d");
+    let tick = g.find_node(&"d").unwrap().get_recency().unwrap().created + 1;
+    *g.find_node_mut(&"b").unwrap().get_data_mut() = "b2";
+    g.set_spill_store(Some(Box::new(MemoryStore::default())));
+    assert_eq!(1, g.spill_cold(&["root"], tick).unwrap());
+    assert!(g.is_spilled(&"a"));
+    assert!(!g.is_spilled(&"b"));
+    assert!(g.find_node(&"c").is_none());
+    assert!(g.find_node(&"d").is_some());
+  }
+
+  #[test]
+  fn stack_reload_ok() {
+    use crate::search::{Stack, Traversal};
+    let mut g = make_graph();
+    g.spill(&["root"], &["a"]).unwrap();
+    let mut stack = Stack::new(g.find_node_mut(&"root").unwrap());
+    let first =
+      |_: &crate::nav::Node<_, _, _>| -> Result<_, io::Error> { Ok(Some(Traversal::Child(0))) };
+    stack.push(first).unwrap();
+    assert_eq!("a", *stack.head().get_label());
+    stack.push(first).unwrap();
+    assert_eq!("c", *stack.head().get_label());
+    drop(stack);
+    assert!(!g.is_spilled(&"a"));
+  }
+
+  #[test]
+  fn directory_store_ok() {
+    let dir = std::env::temp_dir().join(format!("search-graph-tier-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    const STATES: [&str; 9] = ["a", "b", "c", "d", "root", "a_b", "a_c", "c_d", "d_b"];
+    let find = |s: &str| *STATES.iter().find(|t| **t == s).unwrap();
+    let encode = |r: &Region<&'static str, &'static str, &'static str>| {
+      let mut text = String::new();
+      for v in r.vertices.iter() {
+        text.push_str(&format!("v {}\n", v.state));
+      }
+      for e in r.edges.iter() {
+        text.push_str(&format!("e {} {} {}\n", e.source, e.target, e.data));
+      }
+      text.into_bytes()
+    };
+    let decode = move |bytes: &[u8]| {
+      let mut region = Region {
+        stub_expansion: Expansion::PartiallyExpanded,
+        vertices: Vec::new(),
+        edges: Vec::new(),
+      };
+      for line in std::str::from_utf8(bytes).unwrap().lines() {
+        let fields: Vec<&str> = line.split(' ').collect();
+        match fields[0] {
+          "v" => region.vertices.push(SpilledVertex {
+            state: find(fields[1]),
+            aliases: Vec::new(),
+            data: find(fields[1]),
+            expansion: Expansion::PartiallyExpanded,
+            unexpanded: Vec::new(),
+          }),
+          _ => region.edges.push(SpilledEdge {
+            source: find(fields[1]),
+            target: find(fields[2]),
+            data: find(fields[3]),
+          }),
+        }
+      }
+      Ok(region)
+    };
+    let mut g = make_graph();
+    g.set_spill_store(Some(Box::new(DirectoryStore::new(
+      dir.clone(),
+      encode,
+      decode,
+    ))));
+    g.spill(&["root"], &["a"]).unwrap();
+    assert!(dir.join("region-0.bin").exists());
+    g.reload(&"a").unwrap();
+    assert!(!dir.join("region-0.bin").exists());
+    assert_eq!(vec!["c", "b"], children(&g, "a"));
+    assert_eq!(vec!["d"], children(&g, "c"));
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+}
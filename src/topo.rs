@@ -0,0 +1,237 @@
+//! Topological ordering of graphs and detection of cycles.
+//!
+//! The graphs of many games are acyclic even with transpositions, and
+//! algorithms that back up values from leaves (e.g.,
+//! [algo::propagate](../algo/fn.propagate.html)) rely on that. A bug in how
+//! game states are hashed or compared can silently introduce cycles, so
+//! [Graph::topological_sort](../struct.Graph.html#method.topological_sort)
+//! reports a [CycleError](struct.CycleError.html) that names the edges of a
+//! cycle when there is no topological order, and
+//! [Graph::find_cycle](../struct.Graph.html#method.find_cycle) returns a cycle
+//! as a [Path](../nav/struct.Path.html) that can be inspected directly.
+//!
+//! ```rust
+//! # use search_graph::Graph;
+//! # fn main() {
+//! let mut g: Graph<&str, (), ()> = Graph::new();
+//! g.add_edge("root", |_| (), "a", |_| (), ());
+//! g.add_edge("root", |_| (), "b", |_| (), ());
+//! g.add_edge("a", |_| (), "b", |_| (), ());
+//! let order: Vec<&str> = g
+//!   .topological_sort()
+//!   .unwrap()
+//!   .iter()
+//!   .map(|n| *n.get_label())
+//!   .collect();
+//! assert_eq!(order, vec!["root", "a", "b"]);
+//! assert!(!g.has_cycle());
+//!
+//! g.add_edge("b", |_| (), "root", |_| (), ());
+//! let cycle = g.find_cycle().unwrap();
+//! assert_eq!(cycle.len(), 3);
+//! assert_eq!(
+//!   cycle.first_node().get_label(),
+//!   cycle.last_node().get_label()
+//! );
+//! assert_eq!(g.topological_sort().err().unwrap().edges.len(), 3);
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+
+use crate::base::{EdgeId, VertexId};
+use crate::nav::{Node, Path};
+use crate::Graph;
+use symbol_map::SymbolId;
+
+/// The error returned when a graph with a cycle is sorted topologically.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CycleError {
+  /// The IDs of the edges of a cycle, in order, as returned by
+  /// [Edge::get_id](../nav/struct.Edge.html#method.get_id). They may be
+  /// turned into a path with
+  /// [Path::from_edge_ids](../nav/struct.Path.html#method.from_edge_ids).
+  pub edges: Vec<usize>,
+}
+
+impl fmt::Display for CycleError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "Graph has a cycle of {} edges", self.edges.len())
+  }
+}
+
+impl Error for CycleError {}
+
+/// The state of a vertex during a depth-first search for cycles.
+#[derive(Clone, Copy, PartialEq)]
+enum Visit {
+  Unvisited,
+  /// The vertex is on the search path, at the given depth.
+  OnPath(usize),
+  Finished,
+}
+
+impl<T: Hash + Eq + Clone, S, A> Graph<T, S, A> {
+  /// Returns the vertices of this graph in an order in which each vertex
+  /// precedes its children, or an error that names the edges of a cycle if
+  /// there is no such order. Among vertices whose parents have all been
+  /// ordered, those with lower IDs come first, so the order is deterministic.
+  /// This does not require the graph to track incoming edges.
+  pub fn topological_sort<'s>(&'s self) -> Result<Vec<Node<'s, T, S, A>>, CycleError> {
+    let mut in_degree = vec![0usize; self.vertices.len()];
+    for arc in self.arcs.iter() {
+      in_degree[arc.target.as_usize()] += 1;
+    }
+    let mut ready: VecDeque<usize> = (0..self.vertices.len())
+      .filter(|i| in_degree[*i] == 0)
+      .collect();
+    let mut order = Vec::with_capacity(self.vertices.len());
+    while let Some(i) = ready.pop_front() {
+      for child in self.vertices[i].children.iter() {
+        let target = self.get_arc(*child).target.as_usize();
+        in_degree[target] -= 1;
+        if in_degree[target] == 0 {
+          ready.push_back(target);
+        }
+      }
+      order.push(Node::new(self, VertexId(i)));
+    }
+    if order.len() < self.vertices.len() {
+      let edges = self
+        .cycle_edges()
+        .expect("unordered vertices without a cycle");
+      return Err(CycleError {
+        edges: edges.iter().map(|e| e.as_usize()).collect(),
+      });
+    }
+    Ok(order)
+  }
+
+  /// Returns true iff this graph has a cycle (including a self loop).
+  pub fn has_cycle(&self) -> bool {
+    self.cycle_edges().is_some()
+  }
+
+  /// Returns a cycle of this graph as a path that starts and ends at the same
+  /// vertex, or `None` if the graph is acyclic. The cycle is the first one
+  /// that a depth-first search from vertices in order of ID finds.
+  pub fn find_cycle<'s>(&'s self) -> Option<Path<'s, T, S, A>> {
+    let edges: Vec<usize> = self.cycle_edges()?.iter().map(|e| e.as_usize()).collect();
+    Some(Path::from_edge_ids(self, &edges).unwrap())
+  }
+
+  /// Returns the edges of the first cycle that a depth-first search finds.
+  fn cycle_edges(&self) -> Option<Vec<EdgeId>> {
+    let mut visits = vec![Visit::Unvisited; self.vertices.len()];
+    // The edges of the search path, and for each vertex on it, the number of
+    // its children that have been examined.
+    let mut path: Vec<EdgeId> = Vec::new();
+    let mut stack: Vec<(VertexId, usize)> = Vec::new();
+    for root in 0..self.vertices.len() {
+      if visits[root] != Visit::Unvisited {
+        continue;
+      }
+      visits[root] = Visit::OnPath(0);
+      stack.push((VertexId(root), 0));
+      while let Some(&(id, next)) = stack.last() {
+        let children = &self.get_vertex(id).children;
+        if next == children.len() {
+          visits[id.as_usize()] = Visit::Finished;
+          stack.pop();
+          path.pop();
+          continue;
+        }
+        let edge = children[next];
+        stack.last_mut().unwrap().1 += 1;
+        let target = self.get_arc(edge).target;
+        match visits[target.as_usize()] {
+          Visit::Unvisited => {
+            path.push(edge);
+            visits[target.as_usize()] = Visit::OnPath(path.len());
+            stack.push((target, 0));
+          }
+          Visit::OnPath(depth) => {
+            let mut cycle = path.split_off(depth);
+            cycle.push(edge);
+            return Some(cycle);
+          }
+          Visit::Finished => (),
+        }
+      }
+    }
+    None
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::CycleError;
+  use crate::nav::Path;
+
+  type Graph = crate::Graph<&'static str, &'static str, &'static str>;
+
+  fn add_edge(g: &mut Graph, source: &'static str, dest: &'static str) {
+    g.add_edge(source, |_| "", dest, |_| "", "");
+  }
+
+  fn labels(g: &Graph) -> Vec<&'static str> {
+    g.topological_sort()
+      .unwrap()
+      .iter()
+      .map(|n| *n.get_label())
+      .collect()
+  }
+
+  #[test]
+  fn topological_sort_ok() {
+    let mut g = Graph::new();
+    assert!(g.topological_sort().unwrap().is_empty());
+    add_edge(&mut g, "c", "d");
+    add_edge(&mut g, "a", "c");
+    add_edge(&mut g, "b", "c");
+    add_edge(&mut g, "a", "b");
+    add_edge(&mut g, "a", "b");
+    add_edge(&mut g, "e", "e2");
+    assert_eq!(vec!["a", "e", "b", "e2", "c", "d"], labels(&g));
+    assert!(!g.has_cycle());
+    assert!(g.find_cycle().is_none());
+  }
+
+  #[test]
+  fn topological_sort_err() {
+    let mut g = Graph::new();
+    add_edge(&mut g, "root", "a");
+    add_edge(&mut g, "a", "b");
+    add_edge(&mut g, "b", "c");
+    add_edge(&mut g, "c", "a");
+    add_edge(&mut g, "root", "c");
+    assert!(g.has_cycle());
+    let CycleError { edges } = g.topological_sort().err().unwrap();
+    let cycle = Path::from_edge_ids(&g, &edges).unwrap();
+    let states: Vec<&str> = cycle.iter().map(|e| *e.get_source().get_label()).collect();
+    assert_eq!(vec!["a", "b", "c"], states);
+    assert_eq!("a", *cycle.last_node().get_label());
+    assert_eq!(
+      edges,
+      g.find_cycle()
+        .unwrap()
+        .iter()
+        .map(|e| e.get_id())
+        .collect::<Vec<_>>()
+    );
+
+    let mut g = Graph::new();
+    add_edge(&mut g, "root", "a");
+    add_edge(&mut g, "a", "a");
+    let cycle = g.find_cycle().unwrap();
+    assert_eq!(1, cycle.len());
+    assert_eq!("a", *cycle.first_node().get_label());
+    assert_eq!(
+      "Graph has a cycle of 1 edges",
+      g.topological_sort().err().unwrap().to_string()
+    );
+  }
+}
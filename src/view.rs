@@ -86,16 +86,21 @@
 //! ```
 
 use symbol_map::indexing::Indexing;
+use symbol_map::SymbolId;
 
 use crate::base::{EdgeId, RawEdge, RawVertex, VertexId};
+use crate::moves::MoveKey;
 use crate::mutators;
+use crate::nav;
 use crate::Graph;
 
 use std::cmp;
+use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::fmt;
 use std::hash::Hash;
+use std::iter::FusedIterator;
 use std::marker::PhantomData;
-use std::ops::{Deref, DerefMut, Index, IndexMut};
+use std::ops::{Add, Deref, DerefMut, Index, IndexMut};
 use std::slice;
 
 #[derive(Clone, Copy)]
@@ -248,6 +253,7 @@ where
   }
 
   fn raw_vertex_mut(&mut self, node: NodeRef<'id>) -> &mut RawVertex<S> {
+    self.graph.touch_vertex(node.id);
     unsafe { self.graph.vertices.get_unchecked_mut(node.id.0) }
   }
 
@@ -256,6 +262,7 @@ where
   }
 
   fn raw_edge_mut(&mut self, edge: EdgeRef<'id>) -> &mut RawEdge<A> {
+    self.graph.touch_arc(edge.id);
     unsafe { self.graph.arcs.get_unchecked_mut(edge.id.0) }
   }
 
@@ -269,14 +276,17 @@ where
   }
 
   /// Returns a reference to an edge between the given nodes that is already in
-  /// the graph, or `None` if there is no such edge.
+  /// the graph, or `None` if there is no such edge. This scans the child list
+  /// of `source` unless an index has been built with
+  /// [Graph::index_edges](../struct.Graph.html#method.index_edges).
   pub fn find_edge(&self, source: NodeRef<'id>, target: NodeRef<'id>) -> Option<EdgeRef<'id>> {
-    for child in self.children(source) {
-      if self.raw_edge(child).target == target.id {
-        return Some(child);
-      }
-    }
-    None
+    self
+      .graph
+      .find_parallel(source.id, target.id)
+      .map(|id| EdgeRef {
+        id,
+        _lifetime: self.lifetime,
+      })
   }
 
   /// Adds a node for the given game state with the given data, returning a
@@ -348,7 +358,7 @@ where
 
   /// Returns a reference to the game state that `node` is associated with.
   pub fn node_state(&self, node: NodeRef<'id>) -> &T {
-    &self
+    self
       .graph
       .state_ids
       .get_symbol(&node.id)
@@ -366,6 +376,7 @@ where
   /// Returns a mutable reference to the data (usually statistics or payout
   /// information) for `node`.
   pub fn node_data_mut(&mut self, node: NodeRef<'id>) -> &mut S {
+    self.graph.touch_data(node.id);
     &mut self.raw_vertex_mut(node).data
   }
 
@@ -378,6 +389,7 @@ where
   /// Returns a mutable reference to the data (usually statistics or payout
   /// information) for `edge`.
   pub fn edge_data_mut(&mut self, edge: EdgeRef<'id>) -> &mut A {
+    self.graph.touch_edge_data(edge.id);
     &mut self.raw_edge_mut(edge).data
   }
 
@@ -397,6 +409,17 @@ where
     }
   }
 
+  /// Returns how much of the set of children of `node` has been generated.
+  /// See [Expansion](../nav/enum.Expansion.html).
+  pub fn node_expansion(&self, node: NodeRef<'id>) -> nav::Expansion {
+    self.raw_vertex(node).expansion
+  }
+
+  /// Records how much of the set of children of `node` has been generated.
+  pub fn set_node_expansion(&mut self, node: NodeRef<'id>, expansion: nav::Expansion) {
+    self.raw_vertex_mut(node).expansion = expansion;
+  }
+
   /// Returns the number of children (outgoing edges) that `node` has.
   pub fn child_count(&self, node: NodeRef<'id>) -> usize {
     self.raw_vertex(node).children.len()
@@ -432,9 +455,56 @@ where
     }
   }
 
+  /// Returns a reference to the `i`th child (outgoing edge) of `node`, or
+  /// `None` if `node` has `i` or fewer children.
+  ///
+  /// ```rust
+  /// # use search_graph::Graph;
+  /// # use search_graph::view;
+  /// # fn main() {
+  /// let mut g: Graph<String, String, String> = Graph::new();
+  /// view::of_graph(&mut g, |mut v| {
+  ///   let root = v.append_node("root_state".into(), "root_data".into());
+  ///   let child1 = v.append_node("child1_state".into(), "child1_data".into());
+  ///   let child2 = v.append_node("child2_state".into(), "child2_data".into());
+  ///   v.append_edge(root, child1, "edge1_data".into());
+  ///   v.append_edge(root, child2, "edge2_data".into());
+  ///   assert_eq!(v[v.child(root, 1).unwrap()], "edge2_data");
+  ///   assert!(v.child(root, 2).is_none());
+  ///   let last = v.children(root).next_back().unwrap();
+  ///   assert_eq!(v.child(root, v.child_count(root) - 1), Some(last));
+  /// });
+  /// # }
+  /// ```
+  pub fn child(&self, node: NodeRef<'id>, i: usize) -> Option<EdgeRef<'id>> {
+    self.raw_vertex(node).children.get(i).map(|&id| EdgeRef {
+      id,
+      _lifetime: self.lifetime,
+    })
+  }
+
+  /// Returns a reference to the child (outgoing edge) of `node` that
+  /// represents the move `key`, or `None` if there is no such edge. See the
+  /// [moves](../moves/index.html) module.
+  pub fn child_by_move(&self, node: NodeRef<'id>, key: &A::Key) -> Option<EdgeRef<'id>>
+  where
+    A: MoveKey,
+  {
+    nav::Node::new(self.graph, node.id)
+      .child_by_move(key)
+      .map(|e| EdgeRef {
+        id: e.id,
+        _lifetime: self.lifetime,
+      })
+  }
+
   /// Returns the number of parents (incoming edges) that `node` has.
+  ///
+  /// Panics if the graph does not track incoming edges (see
+  /// [GraphConfig](../config/struct.GraphConfig.html)). The same is true of
+  /// `parents` and `parent`.
   pub fn parent_count(&self, node: NodeRef<'id>) -> usize {
-    self.raw_vertex(node).parents.len()
+    self.graph.parents(node.id).len()
   }
 
   /// Returns an iterator over the parents (incoming edges) that `node` has.
@@ -457,16 +527,28 @@ where
   ///   let parent_data: Vec<&String> =
   ///     v.parents(child).map(|e| v.node_data(v.edge_source(e))).collect();
   ///   assert_eq!(parent_data, vec!["parent1_data", "parent2_data", "parent3_data"]);
+  ///   let edge_data: Vec<&String> = v.parents(child).rev().map(|e| v.edge_data(e)).collect();
+  ///   assert_eq!(edge_data, vec!["edge3_data", "edge2_data", "edge1_data"]);
+  ///   assert_eq!(v.parents(child).len(), 3);
   /// });
   /// # }
   /// ```
   pub fn parents<'s>(&'s self, node: NodeRef<'id>) -> EdgeIter<'a, 's, 'id, T, S, A> {
     EdgeIter {
       view: self,
-      edges: self.raw_vertex(node).parents.iter(),
+      edges: self.graph.parents(node.id).iter(),
     }
   }
 
+  /// Returns a reference to the `i`th parent (incoming edge) of `node`, or
+  /// `None` if `node` has `i` or fewer parents.
+  pub fn parent(&self, node: NodeRef<'id>, i: usize) -> Option<EdgeRef<'id>> {
+    self.graph.parents(node.id).get(i).map(|&id| EdgeRef {
+      id,
+      _lifetime: self.lifetime,
+    })
+  }
+
   /// Deletes all graph components that are not reachable by a traversal
   /// starting from each of `roots`.
   pub fn retain_reachable_from<I: IntoIterator<Item = NodeRef<'id>>>(self, roots: I) {
@@ -474,9 +556,327 @@ where
     self.retain_reachable_from_ids(&root_ids);
   }
 
+  /// As `retain_reachable_from`, but also sorts the child edges of each
+  /// retained vertex by the key that `key` extracts from their data. The sort
+  /// is stable, so children with equal keys keep their relative order.
+  ///
+  /// ```rust
+  /// # use search_graph::Graph;
+  /// # use search_graph::view;
+  /// # fn main() {
+  /// let mut g: Graph<&str, (), u32> = Graph::new();
+  /// view::of_graph(&mut g, |mut v| {
+  ///   let root = v.append_node("root", ());
+  ///   for (state, score) in vec![("a", 3), ("b", 1), ("c", 2)] {
+  ///     let child = v.append_node(state, ());
+  ///     v.append_edge(root, child, score);
+  ///   }
+  ///   v.retain_reachable_from_sorted_by_key(Some(root), |score| *score);
+  /// });
+  /// let scores: Vec<u32> = g
+  ///   .find_node(&"root")
+  ///   .unwrap()
+  ///   .get_child_list()
+  ///   .iter()
+  ///   .map(|e| *e.get_data())
+  ///   .collect();
+  /// assert_eq!(scores, vec![1, 2, 3]);
+  /// # }
+  /// ```
+  pub fn retain_reachable_from_sorted_by_key<I, K, F>(self, roots: I, mut key: F)
+  where
+    I: IntoIterator<Item = NodeRef<'id>>,
+    K: Ord,
+    F: FnMut(&A) -> K,
+  {
+    let root_ids: Vec<VertexId> = roots.into_iter().map(|n| n.id).collect();
+    crate::mark_compact::Collector::retain_reachable_ordered(
+      self.graph,
+      &root_ids,
+      &mut |a: &A, b: &A| key(a).cmp(&key(b)),
+    );
+  }
+
+  /// Deletes `edge`, detaching it from the child list of its source and the
+  /// parent list of its target, and returns its source. If its source was
+  /// fully expanded, it becomes partially expanded. Vertices are kept, even
+  /// if they become unreachable.
+  ///
+  /// Edge IDs are reassigned as by
+  /// [Graph::retain_edges](../struct.Graph.html#method.retain_edges), which
+  /// invalidates references into the graph, so this consumes the view and
+  /// returns a `MutNode`.
+  ///
+  /// ```rust
+  /// # use search_graph::Graph;
+  /// # use search_graph::view;
+  /// # fn main() {
+  /// let mut g: Graph<&str, (), &str> = Graph::new();
+  /// g.add_edge("root", |_| (), "a", |_| (), "root_a");
+  /// g.add_edge("root", |_| (), "b", |_| (), "root_b");
+  /// g.add_edge("a", |_| (), "b", |_| (), "a_b");
+  /// view::of_graph(&mut g, |v| {
+  ///   let root = v.find_node(&"root").unwrap();
+  ///   let edge = v.child(root, 0).unwrap();
+  ///   let root = v.remove_edge(edge);
+  ///   assert_eq!(root.get_child_list().len(), 1);
+  /// });
+  /// assert_eq!(g.edge_count(), 2);
+  /// let b = g.find_node(&"b").unwrap();
+  /// assert_eq!(*b.get_parent_list().get_edge(0).get_data(), "root_b");
+  /// assert!(g.find_node(&"a").unwrap().is_root());
+  /// # }
+  /// ```
+  pub fn remove_edge(self, edge: EdgeRef<'id>) -> mutators::MutNode<'a, T, S, A> {
+    let source = self.graph.get_arc(edge.id).source;
+    self.graph.remove_raw_edges(vec![edge.id]);
+    mutators::MutNode {
+      graph: self.graph,
+      id: source,
+    }
+  }
+
+  /// Deletes `node`, along with its incident edges, its unexpanded child
+  /// edges, and its game state and aliases, so that looking up its game state
+  /// no longer finds a vertex. Sources of removed edges that were fully
+  /// expanded become partially expanded. Other vertices are kept, even if they
+  /// become unreachable; to also drop the subtree below `node`, follow this
+  /// with garbage collection.
+  ///
+  /// The graph is compacted as by
+  /// [Graph::retain_nodes](../struct.Graph.html#method.retain_nodes), which
+  /// reassigns vertex and edge IDs and invalidates references into the graph,
+  /// so this consumes the view and returns the graph.
+  ///
+  /// ```rust
+  /// # use search_graph::Graph;
+  /// # use search_graph::view;
+  /// # fn main() {
+  /// let mut g: Graph<&str, (), ()> = Graph::new();
+  /// g.add_edge("root", |_| (), "bad", |_| (), ());
+  /// g.add_edge("bad", |_| (), "c", |_| (), ());
+  /// g.add_edge("root", |_| (), "d", |_| (), ());
+  /// view::of_graph(&mut g, |v| {
+  ///   let bad = v.find_node(&"bad").unwrap();
+  ///   let g = v.remove_node(bad);
+  ///   assert!(g.find_node(&"bad").is_none());
+  ///   assert_eq!(g.find_node(&"root").unwrap().get_child_list().len(), 1);
+  ///   assert!(g.find_node(&"c").unwrap().is_root());
+  /// });
+  /// assert_eq!(g.vertex_count(), 3);
+  /// assert_eq!(g.edge_count(), 1);
+  /// # }
+  /// ```
+  pub fn remove_node(self, node: NodeRef<'id>) -> &'a mut Graph<T, S, A> {
+    let id = node.id.as_usize();
+    self.graph.retain_nodes(|n| n.get_id() != id);
+    self.graph
+  }
+
   /// As `retain_reachable_from`, but working over raw `VertexId`s.
-  fn retain_reachable_from_ids(mut self, root_ids: &[VertexId]) {
-    crate::mark_compact::Collector::retain_reachable(&mut self.graph, root_ids);
+  fn retain_reachable_from_ids(self, root_ids: &[VertexId]) {
+    crate::mark_compact::Collector::retain_reachable(self.graph, root_ids);
+  }
+}
+
+/// An entry in the frontier of a best-first search, ordered so that the entry
+/// with the lowest estimated total cost is at the top of a `BinaryHeap`.
+struct Frontier<K> {
+  estimate: K,
+  cost: K,
+  id: VertexId,
+}
+
+impl<K: PartialOrd> PartialEq for Frontier<K> {
+  fn eq(&self, other: &Self) -> bool {
+    self.cmp(other) == cmp::Ordering::Equal
+  }
+}
+
+impl<K: PartialOrd> Eq for Frontier<K> {}
+
+impl<K: PartialOrd> PartialOrd for Frontier<K> {
+  fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl<K: PartialOrd> Ord for Frontier<K> {
+  fn cmp(&self, other: &Self) -> cmp::Ordering {
+    // Incomparable costs (e.g., NaN) are treated as equal.
+    other
+      .estimate
+      .partial_cmp(&self.estimate)
+      .unwrap_or(cmp::Ordering::Equal)
+  }
+}
+
+impl<'a, 'id, T: Hash + Eq + Clone, S, A> View<'a, 'id, T, S, A>
+where
+  'a: 'id,
+{
+  /// Finds a lowest-cost path from `start` to a node that satisfies `goal`
+  /// with A* search, growing the graph as the search proceeds.
+  ///
+  /// The cost of traversing an edge is given by `cost`, and `heuristic`
+  /// estimates the cost of reaching a goal from a node. For the path found to
+  /// be of lowest cost, `heuristic` must never overestimate this cost and must
+  /// be consistent (i.e., for every edge, the estimate at its source may not
+  /// exceed the cost of the edge plus the estimate at its target).
+  ///
+  /// Each node that is not marked as
+  /// [FullyExpanded](../nav/enum.Expansion.html#variant.FullyExpanded) is
+  /// passed to `expand` once, when it is first removed from the search
+  /// frontier, and before its children are examined. `expand` may add nodes
+  /// and edges through the view (e.g., by generating the successors of a game
+  /// state that has not been visited before), so that state spaces may be
+  /// materialized only as far as the search requires. It should mark the
+  /// nodes that it finishes expanding with
+  /// [set_node_expansion](struct.View.html#method.set_node_expansion), so
+  /// that later searches do not expand them again.
+  ///
+  /// Returns the edges of the path that was found, in order, along with its
+  /// total cost, or `None` if no goal is reachable from `start`.
+  ///
+  /// ```rust
+  /// # use search_graph::Graph;
+  /// # use search_graph::view;
+  /// # use search_graph::nav::Expansion;
+  /// # fn main() {
+  /// // States are integers, with moves that add 1 or double, each costing 1.
+  /// let mut graph: Graph<u32, (), u32> = Graph::new();
+  /// view::of_graph(&mut graph, |mut v| {
+  ///   let start = v.append_node(1, ());
+  ///   let (path, cost) = v
+  ///     .astar(
+  ///       start,
+  ///       |v, n| *v.node_state(n) == 10,
+  ///       |_| 1,
+  ///       |v, n| if *v.node_state(n) < 10 { 1 } else { 0 },
+  ///       |v, n| {
+  ///         let state = *v.node_state(n);
+  ///         if state < 10 {
+  ///           for next in [state + 1, state * 2].iter() {
+  ///             let child = v.append_node(*next, ());
+  ///             v.append_edge(n, child, *next);
+  ///           }
+  ///         }
+  ///         v.set_node_expansion(n, Expansion::FullyExpanded);
+  ///       },
+  ///     )
+  ///     .unwrap();
+  ///   assert_eq!(cost, 4);
+  ///   let states: Vec<u32> = path.iter().map(|e| v[*e]).collect();
+  ///   assert_eq!(states, vec![2, 4, 5, 10]);
+  /// });
+  /// # }
+  /// ```
+  pub fn astar<K, G, C, H, E>(
+    &mut self,
+    start: NodeRef<'id>,
+    mut goal: G,
+    mut cost: C,
+    mut heuristic: H,
+    mut expand: E,
+  ) -> Option<(Vec<EdgeRef<'id>>, K)>
+  where
+    K: Copy + PartialOrd + Add<Output = K> + Default,
+    G: FnMut(&Self, NodeRef<'id>) -> bool,
+    C: FnMut(&A) -> K,
+    H: FnMut(&Self, NodeRef<'id>) -> K,
+    E: FnMut(&mut Self, NodeRef<'id>),
+  {
+    // Lowest known cost of reaching each vertex, and the edge it is reached by.
+    let mut best: HashMap<VertexId, (K, Option<EdgeId>)> = HashMap::new();
+    let mut closed: HashSet<VertexId> = HashSet::new();
+    let mut frontier = BinaryHeap::new();
+    best.insert(start.id, (K::default(), None));
+    frontier.push(Frontier {
+      estimate: heuristic(self, start),
+      cost: K::default(),
+      id: start.id,
+    });
+    while let Some(Frontier { cost: g, id, .. }) = frontier.pop() {
+      if !closed.insert(id) {
+        continue;
+      }
+      let node = NodeRef {
+        id,
+        _lifetime: self.lifetime,
+      };
+      if goal(self, node) {
+        let mut path = Vec::new();
+        let mut current = id;
+        while let Some((_, Some(edge))) = best.get(&current) {
+          path.push(EdgeRef {
+            id: *edge,
+            _lifetime: self.lifetime,
+          });
+          current = self.graph.get_arc(*edge).source;
+        }
+        path.reverse();
+        return Some((path, g));
+      }
+      if self.node_expansion(node) != nav::Expansion::FullyExpanded {
+        expand(self, node);
+      }
+      let children: Vec<EdgeRef<'id>> = self.children(node).collect();
+      for edge in children {
+        let target = self.edge_target(edge);
+        if closed.contains(&target.id) {
+          continue;
+        }
+        let next = g + cost(self.edge_data(edge));
+        if let Some((known, _)) = best.get(&target.id) {
+          if next.partial_cmp(known) != Some(cmp::Ordering::Less) {
+            continue;
+          }
+        }
+        best.insert(target.id, (next, Some(edge.id)));
+        frontier.push(Frontier {
+          estimate: next + heuristic(self, target),
+          cost: next,
+          id: target.id,
+        });
+      }
+    }
+    None
+  }
+
+  /// As [astar](struct.View.html#method.astar), but without a heuristic
+  /// (i.e., Dijkstra's algorithm). Edge costs must not be negative.
+  ///
+  /// ```rust
+  /// # use search_graph::Graph;
+  /// # use search_graph::view;
+  /// # fn main() {
+  /// let mut graph: Graph<&str, (), f64> = Graph::new();
+  /// graph.add_edge("a", |_| (), "b", |_| (), 1.0);
+  /// graph.add_edge("b", |_| (), "c", |_| (), 1.0);
+  /// graph.add_edge("a", |_| (), "c", |_| (), 2.5);
+  /// view::of_graph(&mut graph, |mut v| {
+  ///   let a = v.find_node(&"a").unwrap();
+  ///   let (path, cost) = v.dijkstra(a, |v, n| *v.node_state(n) == "c", |w| *w, |_, _| ()).unwrap();
+  ///   assert_eq!(path.len(), 2);
+  ///   assert_eq!(cost, 2.0);
+  ///   assert!(v.dijkstra(a, |v, n| *v.node_state(n) == "d", |w| *w, |_, _| ()).is_none());
+  /// });
+  /// # }
+  /// ```
+  pub fn dijkstra<K, G, C, E>(
+    &mut self,
+    start: NodeRef<'id>,
+    goal: G,
+    cost: C,
+    expand: E,
+  ) -> Option<(Vec<EdgeRef<'id>>, K)>
+  where
+    K: Copy + PartialOrd + Add<Output = K> + Default,
+    G: FnMut(&Self, NodeRef<'id>) -> bool,
+    C: FnMut(&A) -> K,
+    E: FnMut(&mut Self, NodeRef<'id>),
+  {
+    self.astar(start, goal, cost, |_, _| K::default(), expand)
   }
 }
 
@@ -706,8 +1106,8 @@ where 'a: 'id,
 
   fn next(&mut self) -> Option<Self::Item> {
     match self.edges.next() {
-      None => return None,
-      Some(&id) => return Some(EdgeRef { id, _lifetime: self.view.lifetime, }),
+      None => None,
+      Some(&id) => Some(EdgeRef { id, _lifetime: self.view.lifetime, }),
     }
   }
 
@@ -715,3 +1115,172 @@ where 'a: 'id,
     self.edges.size_hint()
   }
 }
+
+impl<'a, 'b, 'id, T: Hash + Eq + Clone, S, A> DoubleEndedIterator for EdgeIter<'a, 'b, 'id, T, S, A>
+where 'a: 'id,
+{
+  fn next_back(&mut self) -> Option<Self::Item> {
+    match self.edges.next_back() {
+      None => None,
+      Some(&id) => Some(EdgeRef { id, _lifetime: self.view.lifetime, }),
+    }
+  }
+}
+
+impl<'a, 'b, 'id, T: Hash + Eq + Clone, S, A> ExactSizeIterator for EdgeIter<'a, 'b, 'id, T, S, A>
+where 'a: 'id,
+{}
+
+impl<'a, 'b, 'id, T: Hash + Eq + Clone, S, A> FusedIterator for EdgeIter<'a, 'b, 'id, T, S, A>
+where 'a: 'id,
+{}
+
+#[cfg(test)]
+mod test {
+  use crate::nav::Expansion;
+
+  type Graph = crate::Graph<&'static str, (), u32>;
+
+  fn make_graph(edges: &[(&'static str, &'static str, u32)]) -> Graph {
+    let mut g = Graph::new();
+    for (source, target, cost) in edges.iter() {
+      g.add_edge(*source, |_| (), *target, |_| (), *cost);
+    }
+    g
+  }
+
+  #[test]
+  fn astar_unreachable_ok() {
+    let mut g = make_graph(&[("s", "a", 1), ("a", "b", 2), ("c", "g", 1)]);
+    let mut expanded = Vec::new();
+    super::of_graph(&mut g, |mut v| {
+      let s = v.find_node(&"s").unwrap();
+      let found = v.astar(
+        s,
+        |v, n| *v.node_state(n) == "g",
+        |c| *c,
+        |_, _| 0,
+        |v, n| expanded.push(*v.node_state(n)),
+      );
+      assert!(found.is_none());
+    });
+    assert_eq!(vec!["s", "a", "b"], expanded);
+  }
+
+  #[test]
+  fn astar_expand_ok() {
+    // States are integers, and each is expanded into its successor and its
+    // double, until 10 is reached.
+    let mut g: crate::Graph<u32, (), u32> = crate::Graph::new();
+    g.add_edge(1, |_| (), 2, |_| (), 1);
+    g.find_node_mut(&1)
+      .unwrap()
+      .set_expansion(Expansion::FullyExpanded);
+    let mut expanded = Vec::new();
+    super::of_graph(&mut g, |mut v| {
+      let start = v.find_node(&1).unwrap();
+      let (path, cost) = v
+        .astar(
+          start,
+          |v, n| *v.node_state(n) == 10,
+          |c| *c,
+          |v, n| (*v.node_state(n) < 10) as u32,
+          |v, n| {
+            let state = *v.node_state(n);
+            expanded.push(state);
+            if state < 10 {
+              for next in [state + 1, state * 2].iter() {
+                let child = v.append_node(*next, ());
+                v.append_edge(n, child, 1);
+              }
+            }
+            v.set_node_expansion(n, Expansion::FullyExpanded);
+          },
+        )
+        .unwrap();
+      assert_eq!(4, cost);
+      let states: Vec<u32> = path
+        .iter()
+        .map(|e| *v.node_state(v.edge_target(*e)))
+        .collect();
+      assert_eq!(vec![2, 4, 5, 10], states);
+    });
+    // The start was already expanded, and no vertex is expanded twice.
+    assert!(!expanded.contains(&1));
+    let mut sorted = expanded.clone();
+    sorted.sort_unstable();
+    sorted.dedup();
+    assert_eq!(expanded.len(), sorted.len());
+    // Repeating the search does not expand vertices again.
+    super::of_graph(&mut g, |mut v| {
+      let start = v.find_node(&1).unwrap();
+      let found = v.astar(
+        start,
+        |v, n| *v.node_state(n) == 10,
+        |c| *c,
+        |v, n| (*v.node_state(n) < 10) as u32,
+        |_, _| panic!("expanded again"),
+      );
+      assert_eq!(4, found.unwrap().1);
+    });
+    assert!(crate::testing::invariant_violations(&g).is_empty());
+  }
+
+  #[test]
+  fn astar_zero_cost_cycle_ok() {
+    let mut g = make_graph(&[
+      ("s", "a", 0),
+      ("a", "b", 0),
+      ("b", "a", 0),
+      ("b", "b", 0),
+      ("b", "g", 2),
+      ("s", "g", 3),
+    ]);
+    super::of_graph(&mut g, |mut v| {
+      let s = v.find_node(&"s").unwrap();
+      let (path, cost) = v
+        .dijkstra(s, |v, n| *v.node_state(n) == "g", |c| *c, |_, _| ())
+        .unwrap();
+      assert_eq!(2, cost);
+      let states: Vec<_> = path
+        .iter()
+        .map(|e| *v.node_state(v.edge_target(*e)))
+        .collect();
+      assert_eq!(vec!["a", "b", "g"], states);
+      assert!(v
+        .dijkstra(s, |v, n| *v.node_state(n) == "x", |c| *c, |_, _| ())
+        .is_none());
+    });
+  }
+
+  #[test]
+  fn astar_inconsistent_heuristic_ok() {
+    let mut g = make_graph(&[
+      ("s", "a", 1),
+      ("s", "b", 1),
+      ("a", "c", 1),
+      ("b", "c", 2),
+      ("c", "g", 3),
+    ]);
+    super::of_graph(&mut g, |mut v| {
+      let s = v.find_node(&"s").unwrap();
+      // The heuristic never overestimates, but it is inconsistent at "a", so
+      // "c" is reached first, and closed, on its costlier path through "b".
+      let (path, cost) = v
+        .astar(
+          s,
+          |v, n| *v.node_state(n) == "g",
+          |c| *c,
+          |v, n| if *v.node_state(n) == "a" { 4 } else { 0 },
+          |_, _| (),
+        )
+        .unwrap();
+      assert_eq!(6, cost);
+      assert_eq!(3, path.len());
+      let (_, cost) = v
+        .dijkstra(s, |v, n| *v.node_state(n) == "g", |c| *c, |_, _| ())
+        .unwrap();
+      assert_eq!(5, cost);
+    });
+  }
+}